@@ -0,0 +1,77 @@
+use crate::actions::{Action, ActionContext};
+use crate::error::Result;
+use crate::shared::packages::PackageSetGroup;
+use crate::shared::{package_set_scripts, FileSystemResource, Name, ScriptApprovals};
+
+// ------------------------------------------------------------------------------------------------
+// Public Types
+// ------------------------------------------------------------------------------------------------
+
+///
+/// This action shows the scripts of each package set whose scripts have not been approved, or
+/// have changed since they were approved, and asks the user to approve them; with the `prompt`
+/// script trust policy only approved scripts are run.
+///
+#[derive(Debug)]
+pub struct ApproveAction {
+    group: Option<Name>,
+    yes: bool,
+}
+
+// ------------------------------------------------------------------------------------------------
+// Implementations
+// ------------------------------------------------------------------------------------------------
+
+impl Action for ApproveAction {
+    fn run(&self, context: &ActionContext) -> Result<()> {
+        info!("ApproveAction::run {:?}", self);
+        let repository = context.repository()?;
+        let mut approvals = ScriptApprovals::open(context.scope())?;
+        let groups: Vec<&PackageSetGroup> = match &self.group {
+            None => repository.groups().collect(),
+            Some(group) => vec![repository.group_or_suggest(group)?],
+        };
+
+        let mut unapproved = 0;
+        let mut approved = 0;
+        for group in groups {
+            for package_set in group.package_sets() {
+                if approvals.is_approved(&group.name(), package_set) {
+                    continue;
+                }
+                unapproved += 1;
+                if self.yes {
+                    println!(
+                        "Approving scripts in package set {}/{}:",
+                        group.name(),
+                        package_set.name()
+                    );
+                    for (script_name, script) in package_set_scripts(package_set) {
+                        println!("  {}: {}", script_name, script);
+                    }
+                    approvals.approve(&group.name(), package_set);
+                    approved += 1;
+                } else if approvals.ask(&group.name(), package_set)? {
+                    approved += 1;
+                }
+            }
+        }
+
+        if unapproved == 0 {
+            println!("All package set scripts are approved");
+        } else {
+            approvals.save()?;
+            println!(
+                "Approved the scripts in {} of {} package set(s)",
+                approved, unapproved
+            );
+        }
+        Ok(())
+    }
+}
+
+impl ApproveAction {
+    pub fn new_action(group: Option<Name>, yes: bool) -> Result<Box<dyn Action>> {
+        Ok(Box::from(ApproveAction { group, yes }))
+    }
+}
@@ -0,0 +1,92 @@
+use crate::actions::{Action, ActionContext};
+use crate::error::Result;
+use crate::shared::{AuditedCommand, InstallActionKind, PackageSetSelection};
+use serde::Serialize;
+use std::path::PathBuf;
+
+// ------------------------------------------------------------------------------------------------
+// Public Types
+// ------------------------------------------------------------------------------------------------
+
+///
+/// This action lists every command, and script, that performing an action on the selected
+/// package sets would run, with variables replaced and grouped by package set file; so that they
+/// can be reviewed before they are run. Unlike the action itself nothing is checked against the
+/// local machine, so commands for packages already installed are included.
+///
+#[derive(Debug)]
+pub struct AuditAction {
+    action: InstallActionKind,
+    selection: PackageSetSelection,
+    json: bool,
+}
+
+// ------------------------------------------------------------------------------------------------
+// Private Types
+// ------------------------------------------------------------------------------------------------
+
+#[derive(Serialize, Debug)]
+struct AuditedPackageSet {
+    name: String,
+    path: PathBuf,
+    commands: Vec<AuditedCommand>,
+}
+
+// ------------------------------------------------------------------------------------------------
+// Implementations
+// ------------------------------------------------------------------------------------------------
+
+impl Action for AuditAction {
+    fn run(&self, context: &ActionContext) -> Result<()> {
+        info!("AuditAction::run {:?}", self);
+        let repository = context.repository()?;
+        let registry = context.registry()?;
+        self.selection.check_matched(repository)?;
+
+        let mut audited: Vec<AuditedPackageSet> = Default::default();
+        for group in repository.groups() {
+            for package_set in group
+                .package_sets()
+                .filter(|package_set| package_set.is_platform_match())
+                .filter(|package_set| self.selection.is_selected(&group.name(), package_set))
+            {
+                let commands = registry.audit_package_set(&self.action, package_set);
+                if !commands.is_empty() {
+                    audited.push(AuditedPackageSet {
+                        name: format!("{}/{}", group.name(), package_set.name()),
+                        path: package_set.path().clone(),
+                        commands,
+                    });
+                }
+            }
+        }
+
+        if self.json {
+            println!("{}", serde_json::to_string_pretty(&audited)?);
+        } else if audited.is_empty() {
+            println!("No commands would be run by {}", self.action);
+        } else {
+            for package_set in &audited {
+                println!("{} ({:?})", package_set.name, package_set.path);
+                for command in &package_set.commands {
+                    println!("  {}: {}", command.source(), command.command());
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl AuditAction {
+    pub fn new_action(
+        action: InstallActionKind,
+        selection: PackageSetSelection,
+        json: bool,
+    ) -> Result<Box<dyn Action>> {
+        Ok(Box::from(AuditAction {
+            action,
+            selection,
+            json,
+        }))
+    }
+}
@@ -0,0 +1,212 @@
+use crate::actions::{Action, ActionContext};
+use crate::error::{Error, Result};
+use crate::shared::config::CONFIG_FILE;
+use crate::shared::dirs::temp_dir_for;
+use crate::shared::install_log::{InstallLogFormat, JSON_LINES_LOG_FILE, LOG_FILE};
+use crate::shared::installer::REGISTRY_FILE;
+use crate::shared::packages::REPOSITORY_DIR;
+use crate::shared::{execute_program, Steps};
+use crate::APP_NAME;
+use std::fs;
+use std::os::unix::fs as unix_fs;
+use std::path::{Path, PathBuf};
+
+// ------------------------------------------------------------------------------------------------
+// Public Types
+// ------------------------------------------------------------------------------------------------
+
+///
+/// This action will either backup the package repository, installer registry, configuration, and
+/// install log to an archive; or restore them from an archive onto a new machine.
+///
+#[derive(Debug)]
+pub struct BackupAction {
+    restore: bool,
+    archive: PathBuf,
+    local_dir: Option<String>,
+}
+
+// ------------------------------------------------------------------------------------------------
+// Implementations
+// ------------------------------------------------------------------------------------------------
+
+impl Action for BackupAction {
+    fn run(&self, context: &ActionContext) -> Result<()> {
+        info!("BackupAction::run {:?}", self);
+        let steps = context.steps(if self.restore { "restore" } else { "backup" });
+        // The archive is staged in a new, private, directory; which is removed whether or not the
+        // action succeeds, a failure to remove it is not an error.
+        let work_dir = temp_dir_for(APP_NAME, "backup")?;
+        let result = if self.restore {
+            self.restore(context, &steps, work_dir.path())
+        } else {
+            self.backup(context, &steps, work_dir.path())
+        };
+        if let Err(e) = work_dir.close() {
+            warn!(
+                "BackupAction::run could not remove work directory, error: {}",
+                e
+            );
+        }
+        let _ = steps.finish();
+        result
+    }
+}
+
+impl BackupAction {
+    pub fn backup_action(archive: PathBuf) -> Result<Box<dyn Action>> {
+        Ok(Box::from(BackupAction {
+            restore: false,
+            archive,
+            local_dir: None,
+        }))
+    }
+
+    pub fn restore_action(archive: PathBuf, local_dir: Option<String>) -> Result<Box<dyn Action>> {
+        Ok(Box::from(BackupAction {
+            restore: true,
+            archive,
+            local_dir,
+        }))
+    }
+
+    fn backup(&self, context: &ActionContext, steps: &Steps, work_dir: &Path) -> Result<()> {
+        // Resolve any symlink, created by `init --local-dir`, to back up the repository content.
+        let repository_path = fs::canonicalize(context.repository_path())?;
+        steps.run(&format!("Copying repository {:?}", repository_path), || {
+            copy_dir(&repository_path, &work_dir.join(REPOSITORY_DIR))
+        })?;
+
+        for (path, message) in &[
+            (context.registry_path().clone(), "installer registry"),
+            (context.config_path().clone(), "configuration file"),
+            (context.install_log_path(), "install log"),
+        ] {
+            let name = format!("Copying {} {:?}", message, path);
+            if path.is_file() {
+                steps.run(&name, || {
+                    let _ = fs::copy(path, work_dir.join(path.file_name().unwrap()))?;
+                    Ok(())
+                })?;
+            } else {
+                warn!("BackupAction::backup no {} found at {:?}", message, path);
+                steps.skip(&name, "it does not exist");
+            }
+        }
+
+        steps.run(&format!("Writing archive {:?}", &self.archive), || {
+            let _ = execute_program(
+                "tar",
+                &[
+                    "-czf",
+                    &self.archive.to_string_lossy(),
+                    "-C",
+                    &work_dir.to_string_lossy(),
+                    ".",
+                ],
+            )?;
+            Ok(())
+        })
+    }
+
+    fn restore(&self, context: &ActionContext, steps: &Steps, work_dir: &Path) -> Result<()> {
+        let repository_path = context.repository_path().clone();
+        if repository_path.exists() {
+            return Err(Error::RestoreTargetExists(
+                repository_path.to_string_lossy().to_string(),
+            ));
+        }
+
+        steps.run(&format!("Reading archive {:?}", &self.archive), || {
+            let _ = execute_program(
+                "tar",
+                &[
+                    "-xzf",
+                    &self.archive.to_string_lossy(),
+                    "-C",
+                    &work_dir.to_string_lossy(),
+                ],
+            )?;
+            Ok(())
+        })?;
+
+        let local_dir = match &self.local_dir {
+            None => repository_path.clone(),
+            Some(local_dir) => PathBuf::from(local_dir),
+        };
+        steps.run(&format!("Restoring repository to {:?}", local_dir), || {
+            copy_dir(&work_dir.join(REPOSITORY_DIR), &local_dir)
+        })?;
+        if self.local_dir.is_some() {
+            steps.run(
+                &format!(
+                    "Creating repository link {:?} -> {:?}",
+                    local_dir, &repository_path
+                ),
+                || {
+                    fs::create_dir_all(repository_path.parent().unwrap())?;
+                    unix_fs::symlink(&local_dir, &repository_path)?;
+                    Ok(())
+                },
+            )?;
+        }
+
+        for (file_name, path, message) in &[
+            (
+                REGISTRY_FILE,
+                context.registry_path().clone(),
+                "installer registry",
+            ),
+            (
+                CONFIG_FILE,
+                context.config_path().clone(),
+                "configuration file",
+            ),
+            (
+                LOG_FILE,
+                InstallLogFormat::Sqlite.default_path(context.scope()),
+                "install log",
+            ),
+            (
+                JSON_LINES_LOG_FILE,
+                InstallLogFormat::JsonLines.default_path(context.scope()),
+                "install log",
+            ),
+        ] {
+            let from_path = work_dir.join(file_name);
+            let name = format!("Restoring {} to {:?}", message, path);
+            if from_path.is_file() {
+                steps.run(&name, || {
+                    fs::create_dir_all(path.parent().unwrap())?;
+                    let _ = fs::copy(&from_path, path)?;
+                    Ok(())
+                })?;
+            } else {
+                warn!("BackupAction::restore no {} in archive", message);
+                steps.skip(&name, "it is not in the archive");
+            }
+        }
+        Ok(())
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+// Private Functions
+// ------------------------------------------------------------------------------------------------
+
+pub(crate) fn copy_dir(from_dir: &Path, to_dir: &Path) -> Result<()> {
+    fs::create_dir_all(to_dir)?;
+    for entry in fs::read_dir(from_dir)? {
+        let from_path = entry?.path();
+        let to_path = to_dir.join(from_path.file_name().unwrap());
+        let file_type = fs::symlink_metadata(&from_path)?.file_type();
+        if file_type.is_symlink() {
+            unix_fs::symlink(fs::read_link(&from_path)?, &to_path)?;
+        } else if file_type.is_dir() {
+            copy_dir(&from_path, &to_path)?;
+        } else {
+            let _ = fs::copy(&from_path, &to_path)?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,131 @@
+use crate::actions::{Action, ActionContext};
+use crate::error::{Error, Result};
+use crate::shared::Name;
+use serde::Serialize;
+use std::fmt::{Display, Formatter};
+use std::fs::read_to_string;
+use std::str::FromStr;
+
+// ------------------------------------------------------------------------------------------------
+// Public Types
+// ------------------------------------------------------------------------------------------------
+
+///
+/// This action prints the definition of a package set, or an installer, to stdout. Package sets
+/// may be printed as the raw file content, or normalized by reading and re-writing it.
+///
+#[derive(Debug)]
+pub struct CatAction {
+    target: CatTarget,
+    normalized: bool,
+    format: CatFormat,
+}
+
+///
+/// The format used to print definitions; JSON output is always normalized.
+///
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CatFormat {
+    #[allow(missing_docs)]
+    Yaml,
+    #[allow(missing_docs)]
+    Json,
+}
+
+// ------------------------------------------------------------------------------------------------
+// Private Types
+// ------------------------------------------------------------------------------------------------
+
+#[derive(Debug)]
+enum CatTarget {
+    PackageSet { group: Name, package_set: Name },
+    Installer(Name),
+}
+
+// ------------------------------------------------------------------------------------------------
+// Implementations
+// ------------------------------------------------------------------------------------------------
+
+impl Action for CatAction {
+    fn run(&self, context: &ActionContext) -> Result<()> {
+        info!("CatAction::run {:?}", self);
+        match &self.target {
+            CatTarget::PackageSet { group, package_set } => {
+                let package_set = context.package_set(group, package_set)?;
+                if self.normalized || self.format == CatFormat::Json {
+                    self.print(&package_set)?;
+                } else {
+                    print!("{}", read_to_string(package_set.path())?);
+                }
+            }
+            CatTarget::Installer(name) => {
+                // Installers are always normalized, they are not stored in their own file.
+                let registry = context.registry()?;
+                let installer = registry
+                    .installers()
+                    .find(|installer| installer.name() == name)
+                    .ok_or_else(|| Error::NoInstaller(name.to_string()))?;
+                self.print(installer)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl CatAction {
+    pub fn package_set_action(
+        group: Name,
+        package_set: Name,
+        normalized: bool,
+        format: CatFormat,
+    ) -> Result<Box<dyn Action>> {
+        Ok(Box::from(CatAction {
+            target: CatTarget::PackageSet { group, package_set },
+            normalized,
+            format,
+        }))
+    }
+
+    pub fn installer_action(installer: Name, format: CatFormat) -> Result<Box<dyn Action>> {
+        Ok(Box::from(CatAction {
+            target: CatTarget::Installer(installer),
+            normalized: true,
+            format,
+        }))
+    }
+
+    fn print<T: Serialize>(&self, value: &T) -> Result<()> {
+        match self.format {
+            CatFormat::Yaml => print!("{}", serde_yaml::to_string(value)?),
+            CatFormat::Json => println!("{}", serde_json::to_string_pretty(value)?),
+        }
+        Ok(())
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+
+impl Display for CatFormat {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Self::Yaml => "yaml",
+                Self::Json => "json",
+            }
+        )
+    }
+}
+
+impl FromStr for CatFormat {
+    type Err = Error;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "yaml" => Ok(Self::Yaml),
+            "json" => Ok(Self::Json),
+            _ => Err(Error::InvalidFormatString(s.to_string())),
+        }
+    }
+}
@@ -0,0 +1,319 @@
+use crate::error::Result;
+use crate::reporter::Reporter;
+use crate::shared::packages::LazyPackageRepository;
+use crate::shared::{
+    default_machine_id, default_vars, Config, FileSystemResource, InstallLog, InstallLogFormat,
+    InstallerRegistry, Name, PackageRepository, PackageSet, PackageSetSelection, RepoVcs,
+    RepoVcsKind, Scope, Steps,
+};
+use std::borrow::Cow;
+use std::cell::OnceCell;
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+// ------------------------------------------------------------------------------------------------
+// Public Types
+// ------------------------------------------------------------------------------------------------
+
+///
+/// The resources shared by all actions; the tool configuration, the environment variables used in
+/// commands, the reporter, the installer registry, and the package repository. The context is
+/// created once, by the tool, and passed to the action it runs. The context is for a single
+/// `Scope`, which determines the default paths of its resources.
+///
+/// The configuration, installer registry, and package repository are only read when an action
+/// first asks for them, so that actions that do not need them, or create them, can run without
+/// them. Any of them may be provided up front instead, for example by a test, in which case the
+/// files are never read.
+///
+#[derive(Debug)]
+pub struct ActionContext {
+    scope: Scope,
+    config_path: PathBuf,
+    repository_path: PathBuf,
+    registry_path: PathBuf,
+    install_log_path: Option<PathBuf>,
+    environment: HashMap<String, String>,
+    reporter: Reporter,
+    config: OnceCell<Config>,
+    registry: OnceCell<InstallerRegistry>,
+    repository: OnceCell<PackageRepository>,
+}
+
+// ------------------------------------------------------------------------------------------------
+// Implementations
+// ------------------------------------------------------------------------------------------------
+
+impl Default for ActionContext {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ActionContext {
+    /// Create a context for the configuration, repository, and registry at their default paths,
+    /// in the user scope, with the default environment variables and the current reporter.
+    pub fn new() -> Self {
+        Self::for_scope(Scope::default())
+    }
+
+    /// Create a context for the configuration, repository, and registry at their default paths in
+    /// `scope`, with the default environment variables for `scope` and the current reporter.
+    pub fn for_scope(scope: Scope) -> Self {
+        Self {
+            scope,
+            config_path: Config::default_path(scope),
+            repository_path: PackageRepository::default_path(scope),
+            registry_path: InstallerRegistry::default_path(scope),
+            install_log_path: None,
+            environment: default_vars(scope),
+            reporter: Reporter::current(),
+            config: Default::default(),
+            registry: Default::default(),
+            repository: Default::default(),
+        }
+    }
+
+    /// Use the tool configuration file at `config_path`, rather than the default.
+    pub fn with_config_path(self, config_path: PathBuf) -> Self {
+        Self {
+            config_path,
+            config: Default::default(),
+            ..self
+        }
+    }
+
+    /// Use the package repository at `repository_path`, rather than the default.
+    pub fn with_repository_path(self, repository_path: PathBuf) -> Self {
+        Self {
+            repository_path,
+            repository: Default::default(),
+            ..self
+        }
+    }
+
+    /// Use the installer registry file at `registry_path`, rather than the default.
+    pub fn with_registry_path(self, registry_path: PathBuf) -> Self {
+        Self {
+            registry_path,
+            registry: Default::default(),
+            ..self
+        }
+    }
+
+    /// Use the install log at `install_log_path`, rather than the default path for the configured
+    /// install log format.
+    pub fn with_install_log_path(self, install_log_path: PathBuf) -> Self {
+        Self {
+            install_log_path: Some(install_log_path),
+            ..self
+        }
+    }
+
+    /// Use `environment` as the environment variables for commands, rather than the default.
+    pub fn with_environment(self, environment: HashMap<String, String>) -> Self {
+        Self {
+            registry: match self.registry.into_inner() {
+                Some(registry) => OnceCell::from(registry.with_environment(environment.clone())),
+                None => Default::default(),
+            },
+            environment,
+            ..self
+        }
+    }
+
+    /// Use `reporter` to report to the user, rather than the current reporter.
+    pub fn with_reporter(self, reporter: Reporter) -> Self {
+        Self { reporter, ..self }
+    }
+
+    /// Use `config` as the tool configuration, rather than reading it from its file.
+    pub fn with_config(self, config: Config) -> Self {
+        Self {
+            config: OnceCell::from(config),
+            ..self
+        }
+    }
+
+    /// Use `registry` as the installer registry, rather than reading it from its file; its
+    /// commands use this context's environment variables.
+    pub fn with_registry(self, registry: InstallerRegistry) -> Self {
+        Self {
+            registry: OnceCell::from(registry.with_environment(self.environment.clone())),
+            ..self
+        }
+    }
+
+    /// Use `repository` as the package repository, rather than reading it from its directory.
+    pub fn with_repository(self, repository: PackageRepository) -> Self {
+        Self {
+            repository_path: repository.path().clone(),
+            repository: OnceCell::from(repository),
+            ..self
+        }
+    }
+
+    /// Return the scope of this context.
+    pub fn scope(&self) -> Scope {
+        self.scope
+    }
+
+    /// Return the path of the tool configuration file.
+    pub fn config_path(&self) -> &PathBuf {
+        &self.config_path
+    }
+
+    /// Return the path of the package repository.
+    pub fn repository_path(&self) -> &PathBuf {
+        &self.repository_path
+    }
+
+    /// Return the path of the installer registry file.
+    pub fn registry_path(&self) -> &PathBuf {
+        &self.registry_path
+    }
+
+    /// Return the environment variables for commands.
+    pub fn environment(&self) -> &HashMap<String, String> {
+        &self.environment
+    }
+
+    /// Return the reporter.
+    pub fn reporter(&self) -> &Reporter {
+        &self.reporter
+    }
+
+    /// Return a new set of steps, named `title`, reported with this context's reporter.
+    pub fn steps(&self, title: &str) -> Steps {
+        Steps::new(title).with_reporter(self.reporter)
+    }
+
+    /// Return the tool configuration, reading it on first use.
+    pub fn config(&self) -> Result<&Config> {
+        if self.config.get().is_none() {
+            let _ = self
+                .config
+                .set(Config::open_from(self.config_path.clone())?);
+        }
+        Ok(self.config.get().unwrap())
+    }
+
+    /// Return the install log format selected by the tool configuration, or the default format if
+    /// the configuration does not select one, or cannot be read.
+    pub fn install_log_format(&self) -> InstallLogFormat {
+        self.config()
+            .ok()
+            .and_then(|config| *config.install_log())
+            .unwrap_or_default()
+    }
+
+    /// Return the path of the install log; by default the default path for the configured install
+    /// log format.
+    pub fn install_log_path(&self) -> PathBuf {
+        match &self.install_log_path {
+            Some(install_log_path) => install_log_path.clone(),
+            None => self.install_log_format().default_path(self.scope),
+        }
+    }
+
+    /// Return the identifier recorded for this machine in the install log; either the
+    /// `machine-id` setting in the tool configuration or, by default, see `default_machine_id`.
+    pub fn machine_id(&self) -> String {
+        self.config()
+            .ok()
+            .and_then(|config| config.machine_id().clone())
+            .unwrap_or_else(default_machine_id)
+    }
+
+    /// Open the install log in the format selected by the tool configuration, recording this
+    /// machine's identifier.
+    pub fn open_install_log(&self) -> Result<Box<dyn InstallLog>> {
+        let mut install_log = self
+            .install_log_format()
+            .open_from(self.install_log_path())?;
+        install_log.set_machine(&self.machine_id());
+        Ok(install_log)
+    }
+
+    /// Return the repository VCS selected by the tool configuration, or the default VCS if the
+    /// configuration does not select one, or cannot be read.
+    pub fn repo_vcs_kind(&self) -> RepoVcsKind {
+        self.config()
+            .ok()
+            .and_then(|config| *config.vcs())
+            .unwrap_or_default()
+    }
+
+    /// Open the repository VCS selected by the tool configuration, with its settings.
+    pub fn open_repo_vcs(&self) -> Result<Box<dyn RepoVcs>> {
+        self.repo_vcs_kind().open_with(self.config()?)
+    }
+
+    /// Return the installer registry, reading it on first use.
+    pub fn registry(&self) -> Result<&InstallerRegistry> {
+        if self.registry.get().is_none() {
+            let _ = self.registry.set(
+                InstallerRegistry::open_from(self.registry_path.clone())?
+                    .with_environment(self.environment.clone()),
+            );
+        }
+        Ok(self.registry.get().unwrap())
+    }
+
+    /// Return the package repository, with all package sets loaded, reading it on first use.
+    pub fn repository(&self) -> Result<&PackageRepository> {
+        if self.repository.get().is_none() {
+            let _ = self.repository.set(self.lazy_repository()?.load()?);
+        }
+        Ok(self.repository.get().unwrap())
+    }
+
+    /// Return the package repository with, at least, the package sets that may be included in
+    /// `selection` loaded. If the repository has already been read, or was provided, it is
+    /// returned as is; otherwise only the package sets that may be selected are read, and this is
+    /// read each time it is called.
+    pub fn selected_repository(
+        &self,
+        selection: &PackageSetSelection,
+    ) -> Result<Cow<'_, PackageRepository>> {
+        match self.repository.get() {
+            Some(repository) => Ok(Cow::Borrowed(repository)),
+            None => Ok(Cow::Owned(
+                self.lazy_repository()?.load_selected(selection)?,
+            )),
+        }
+    }
+
+    /// Return the single package set, `group/package_set`. If the repository has already been
+    /// read, or was provided, the package set is taken from it; otherwise only the package set's
+    /// own file is read.
+    pub fn package_set(&self, group: &Name, package_set: &Name) -> Result<PackageSet> {
+        match self.repository.get() {
+            Some(repository) => repository.package_set(group, package_set).cloned(),
+            None => self.lazy_repository()?.package_set(group, package_set),
+        }
+    }
+
+    /// Return the names of the groups in the package repository, without reading their package
+    /// sets unless the repository has already been read, or was provided.
+    pub fn group_names(&self) -> Result<Vec<Name>> {
+        match self.repository.get() {
+            Some(repository) => Ok(repository.groups().map(|group| group.name()).collect()),
+            None => Ok(self
+                .lazy_repository()?
+                .groups()
+                .map(|group| group.name())
+                .collect()),
+        }
+    }
+
+    // Return the package repository without loading its package sets, so that only those needed
+    // are read. The package set cache is only used for the repository at its default path.
+    fn lazy_repository(&self) -> Result<LazyPackageRepository> {
+        if self.repository_path == PackageRepository::default_path(self.scope) {
+            LazyPackageRepository::open(self.scope)
+        } else {
+            LazyPackageRepository::open_from(self.repository_path.clone())
+        }
+    }
+}
@@ -0,0 +1,93 @@
+use crate::actions::{Action, ActionContext};
+use crate::error::Result;
+use crate::shared::Name;
+
+// ------------------------------------------------------------------------------------------------
+// Public Types
+// ------------------------------------------------------------------------------------------------
+
+///
+/// This action reports how the local machine has drifted from the package repository; package
+/// sets changed since they were last applied, package sets never applied or since uninstalled, and
+/// package sets in the install log that no longer exist in the repository.
+///
+#[derive(Debug)]
+pub struct DriftAction {}
+
+// ------------------------------------------------------------------------------------------------
+// Implementations
+// ------------------------------------------------------------------------------------------------
+
+impl Action for DriftAction {
+    fn run(&self, context: &ActionContext) -> Result<()> {
+        info!("DriftAction::run {:?}", self);
+        let repository = context.repository()?;
+        let mut log_db = context.open_install_log()?;
+
+        let mut changed: Vec<String> = Default::default();
+        let mut not_applied: Vec<String> = Default::default();
+        for group in repository.groups() {
+            for package_set in group
+                .package_sets()
+                .filter(|package_set| package_set.is_platform_match())
+            {
+                let name = qualified_name(&group.name(), package_set.name());
+                match log_db.last_applied_hash(&group.name(), package_set.name())? {
+                    None => not_applied.push(name),
+                    Some(hash) => {
+                        if hash != package_set.content_hash()? {
+                            changed.push(name)
+                        }
+                    }
+                }
+            }
+        }
+
+        let removed: Vec<String> = log_db
+            .logged_package_sets()?
+            .iter()
+            .filter(|(group, package_set)| {
+                repository
+                    .group(group)
+                    .map(|group| !group.has_package_set(package_set))
+                    .unwrap_or(true)
+            })
+            .map(|(group, package_set)| qualified_name(group, package_set))
+            .collect();
+
+        if changed.is_empty() && not_applied.is_empty() && removed.is_empty() {
+            println!("This machine is up to date with the repository");
+        } else {
+            print_drift("Package sets changed since last applied:", &changed);
+            print_drift("Package sets not applied:", &not_applied);
+            print_drift(
+                "Package sets in the install log, but not the repository:",
+                &removed,
+            );
+        }
+        Ok(())
+    }
+}
+
+impl DriftAction {
+    pub fn new_action() -> Result<Box<dyn Action>> {
+        Ok(Box::from(DriftAction {}))
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+// Private Functions
+// ------------------------------------------------------------------------------------------------
+
+fn qualified_name(group: &Name, package_set: &Name) -> String {
+    format!("{}/{}", group, package_set)
+}
+
+fn print_drift(heading: &str, package_sets: &[String]) {
+    if !package_sets.is_empty() {
+        println!("{}", heading);
+        for package_set in package_sets {
+            println!("  * {}", package_set);
+        }
+    }
+}
@@ -0,0 +1,75 @@
+use crate::actions::{Action, ActionContext};
+use crate::error::Result;
+use crate::shared::builders::{Builder, ShellCommandBuilder};
+use crate::shared::env::vars_to_env_vars;
+use crate::shared::{
+    command_environment, execution_vars, shell_escape, user_shell, CaptureMode, InstallActionKind,
+    Name,
+};
+use crate::APP_NAME;
+
+// ------------------------------------------------------------------------------------------------
+// Public Types
+// ------------------------------------------------------------------------------------------------
+
+///
+/// This action displays the environment that installer commands and scripts have when an action
+/// is performed on a package set, or runs the user's shell with that environment.
+///
+#[derive(Debug)]
+pub struct EnvAction {
+    action: InstallActionKind,
+    group: Name,
+    package_set: Name,
+    json: bool,
+    shell: bool,
+}
+
+// ------------------------------------------------------------------------------------------------
+// Implementations
+// ------------------------------------------------------------------------------------------------
+
+impl Action for EnvAction {
+    fn run(&self, context: &ActionContext) -> Result<()> {
+        info!("EnvAction::run {:?}", self);
+        let package_set = context.package_set(&self.group, &self.package_set)?;
+        let vars = execution_vars(context.environment(), &self.action, &package_set, None);
+
+        if self.shell {
+            // The same environment policy, and variables, as a package set script.
+            let _ = ShellCommandBuilder::command_line(&user_shell())?
+                .envs(&vars_to_env_vars(&vars, &APP_NAME.to_uppercase()))
+                .capture(CaptureMode::Inherit)
+                .build()
+                .execute()?;
+        } else {
+            let environment = command_environment(&vars);
+            if self.json {
+                println!("{}", serde_json::to_string_pretty(&environment)?);
+            } else {
+                for (name, value) in environment {
+                    println!("{}={}", name, shell_escape(&value));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl EnvAction {
+    pub fn new_action(
+        action: InstallActionKind,
+        group: Name,
+        package_set: Name,
+        json: bool,
+        shell: bool,
+    ) -> Result<Box<dyn Action>> {
+        Ok(Box::from(EnvAction {
+            action,
+            group,
+            package_set,
+            json,
+            shell,
+        }))
+    }
+}
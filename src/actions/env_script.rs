@@ -0,0 +1,63 @@
+use crate::actions::{Action, ActionContext};
+use crate::error::Result;
+use crate::shared::{
+    regenerate_env_scripts, write_env_scripts, ENV_SCRIPT_FILE, ENV_SCRIPT_FISH_FILE,
+};
+
+// ------------------------------------------------------------------------------------------------
+// Public Types
+// ------------------------------------------------------------------------------------------------
+
+///
+/// This action writes the shell environment scripts, for all installed package sets, to the
+/// repository's local directory. The scripts are also written after each install, update,
+/// uninstall, or sync; this action is only needed when the repository has changed without one.
+///
+#[derive(Debug)]
+pub struct EnvScriptAction {}
+
+// ------------------------------------------------------------------------------------------------
+// Implementations
+// ------------------------------------------------------------------------------------------------
+
+impl Action for EnvScriptAction {
+    fn run(&self, context: &ActionContext) -> Result<()> {
+        info!("EnvScriptAction::run {:?}", self);
+        let repository = context.repository()?;
+        let script = write_env_scripts(repository, context.open_install_log()?.as_mut())?;
+        let local_path = repository.local_path();
+        println!(
+            "Wrote shell environment scripts, including {} env-files",
+            script.env_files().len()
+        );
+        println!("To use them add the following to your shell startup file");
+        println!("  sh, bash, zsh: . {:?}", local_path.join(ENV_SCRIPT_FILE));
+        println!("  fish: source {:?}", local_path.join(ENV_SCRIPT_FISH_FILE));
+        Ok(())
+    }
+}
+
+impl EnvScriptAction {
+    pub fn new_action() -> Result<Box<dyn Action>> {
+        Ok(Box::from(EnvScriptAction {}))
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+// Private Functions
+// ------------------------------------------------------------------------------------------------
+
+// Regenerate the shell environment scripts, for the context's repository and install log, after
+// an action changes the package sets installed; any failure is logged, not returned.
+pub(crate) fn regenerate_context_env_scripts(context: &ActionContext) {
+    match context
+        .repository()
+        .and_then(|repository| Ok((repository, context.open_install_log()?)))
+    {
+        Ok((repository, mut log)) => regenerate_env_scripts(repository, log.as_mut()),
+        Err(e) => warn!(
+            "regenerate_context_env_scripts: could not read repository, or install log, error: {}",
+            e
+        ),
+    }
+}
@@ -0,0 +1,94 @@
+use crate::actions::{Action, ActionContext};
+use crate::error::{Error, Result};
+use crate::shared::installer::format_registry;
+use std::fs::{read_to_string, write};
+use std::path::Path;
+
+// ------------------------------------------------------------------------------------------------
+// Public Types
+// ------------------------------------------------------------------------------------------------
+
+///
+/// This action rewrites the package sets in the repository, and the installer registry, in a
+/// canonical key order and style. By default the differences are reported and the action fails if
+/// any file is not in canonical form; if `write` is set the files are rewritten in place.
+///
+#[derive(Debug)]
+pub struct FormatAction {
+    write: bool,
+}
+
+// ------------------------------------------------------------------------------------------------
+// Implementations
+// ------------------------------------------------------------------------------------------------
+
+impl Action for FormatAction {
+    fn run(&self, context: &ActionContext) -> Result<()> {
+        info!("FormatAction::run {:?}", self);
+        let mut changed = 0;
+
+        let repository = context.repository()?;
+        for package_set in repository.groups().flat_map(|group| group.package_sets()) {
+            let path = package_set.path();
+            if self.format_file(
+                path,
+                &read_to_string(path)?,
+                &serde_yaml::to_string(package_set)?,
+            )? {
+                changed += 1;
+            }
+        }
+
+        let registry_path = context.registry_path().clone();
+        if registry_path.is_file() {
+            let registry_data = read_to_string(&registry_path)?;
+            if self.format_file(
+                &registry_path,
+                &registry_data,
+                &format_registry(&registry_data)?,
+            )? {
+                changed += 1;
+            }
+        }
+
+        if changed == 0 {
+            println!("All files are formatted");
+            Ok(())
+        } else if self.write {
+            println!("Formatted {} file(s)", changed);
+            Ok(())
+        } else {
+            Err(Error::UnformattedFiles(changed))
+        }
+    }
+}
+
+impl FormatAction {
+    pub fn new_action(write: bool) -> Result<Box<dyn Action>> {
+        Ok(Box::from(FormatAction { write }))
+    }
+
+    // Returns `true` if the file is not in canonical form, either reporting the differences or
+    // rewriting the file.
+    fn format_file(&self, path: &Path, current: &str, formatted: &str) -> Result<bool> {
+        if current == formatted {
+            debug!("FormatAction::format_file {:?} is formatted", path);
+            return Ok(false);
+        }
+        if self.write {
+            println!("Formatting {:?}", path);
+            write(path, formatted)?;
+        } else {
+            println!("--- {}", path.display());
+            println!("+++ {}", path.display());
+            for line in diff::lines(current, formatted) {
+                match line {
+                    diff::Result::Left(line) => println!("-{}", line),
+                    diff::Result::Right(line) => println!("+{}", line),
+                    diff::Result::Both(line, _) => println!(" {}", line),
+                }
+            }
+        }
+        Ok(true)
+    }
+}
@@ -1,7 +1,6 @@
-use crate::actions::Action;
+use crate::actions::{Action, ActionContext};
 use crate::error::Result;
-use crate::shared::install_log::PackageLog;
-use crate::shared::FileSystemResource;
+use crate::shared::install_log::{duration_to_string, PackageLogQuery};
 use prettytable::Table;
 
 // ------------------------------------------------------------------------------------------------
@@ -14,6 +13,8 @@ use prettytable::Table;
 #[derive(Debug)]
 pub struct HistoryAction {
     limit: u32,
+    commands: bool,
+    machine: Option<String>,
 }
 
 // ------------------------------------------------------------------------------------------------
@@ -21,22 +22,40 @@ pub struct HistoryAction {
 // ------------------------------------------------------------------------------------------------
 
 impl Action for HistoryAction {
-    fn run(&self) -> Result<()> {
+    fn run(&self, context: &ActionContext) -> Result<()> {
         info!("HistoryAction::run {:?}", self);
 
-        let mut log_db = PackageLog::open()?;
-        let history = log_db.installed_package_history(self.limit)?;
+        if self.commands {
+            return self.run_commands(context);
+        }
+
+        let mut log_db = context.open_install_log()?;
+        let mut query = PackageLogQuery::default().with_limit(self.limit);
+        if let Some(machine) = &self.machine {
+            query = query.with_machine(machine);
+        }
+        let history = log_db.installed_packages(&query)?;
 
         if !history.is_empty() {
             let mut table = Table::new();
-            table.set_titles(row!["Date", "Group", "Set", "Package", "Installer"]);
+            table.set_titles(row![
+                "Date",
+                "Group",
+                "Set",
+                "Package",
+                "Installer",
+                "Duration",
+                "Machine"
+            ]);
             for db_row in history {
                 let _ = table.add_row(row![
                     db_row.date_time_str(),
                     db_row.package_set_group_name(),
                     db_row.package_set_name(),
                     db_row.package_name(),
-                    db_row.installer_name()
+                    db_row.installer_name(),
+                    r->db_row.duration_str(),
+                    db_row.machine().cloned().unwrap_or_default()
                 ]);
             }
             let _ = table.printstd();
@@ -49,9 +68,66 @@ impl Action for HistoryAction {
 }
 
 impl HistoryAction {
-    pub fn new_action(limit: Option<u32>) -> Result<Box<dyn Action>> {
+    pub fn new_action(
+        limit: Option<u32>,
+        commands: bool,
+        machine: Option<String>,
+    ) -> Result<Box<dyn Action>> {
         Ok(Box::from(HistoryAction {
             limit: limit.unwrap_or_default(),
+            commands,
+            machine,
         }))
     }
+
+    fn run_commands(&self, context: &ActionContext) -> Result<()> {
+        let mut log_db = context.open_install_log()?;
+        let history = log_db.command_history(self.limit)?;
+
+        if !history.is_empty() {
+            let mut table = Table::new();
+            table.set_titles(row![
+                "Started", "Context", "Command", "Exit", "Duration", "Error"
+            ]);
+            for record in history {
+                let _ = table.add_row(row![
+                    record.started().to_string(),
+                    record.context().clone().unwrap_or_default(),
+                    truncate(record.command(), MAX_COMMAND_WIDTH),
+                    r->record
+                        .exit_code()
+                        .map(|code| code.to_string())
+                        .unwrap_or_else(|| "-".to_string()),
+                    r->duration_to_string(*record.duration()),
+                    if record.is_success() {
+                        String::new()
+                    } else {
+                        truncate(
+                            record.stderr().lines().rev().find(|l| !l.trim().is_empty()).unwrap_or_default(),
+                            MAX_COMMAND_WIDTH,
+                        )
+                    }
+                ]);
+            }
+            let _ = table.printstd();
+        } else {
+            println!("No recorded commands.");
+        }
+
+        Ok(())
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+// Private Functions
+// ------------------------------------------------------------------------------------------------
+
+const MAX_COMMAND_WIDTH: usize = 48;
+
+fn truncate(s: &str, width: usize) -> String {
+    if s.chars().count() > width {
+        format!("{}...", s.chars().take(width - 3).collect::<String>())
+    } else {
+        s.to_string()
+    }
 }
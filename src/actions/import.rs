@@ -0,0 +1,337 @@
+use crate::actions::{Action, ActionContext};
+use crate::error::{Error, Result};
+use crate::shared::builders::{Builder, PackageBuilder, PackageSetBuilder};
+use crate::shared::packages::{Writeable, PACKAGE_SET_FILE};
+use crate::shared::{Name, PackageKind, Platform, Steps};
+use std::collections::HashSet;
+use std::fs::{copy, create_dir_all, read_dir, File};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+// ------------------------------------------------------------------------------------------------
+// Public Types
+// ------------------------------------------------------------------------------------------------
+
+///
+/// This action imports the existing state of a machine into the package repository, as draft
+/// package sets that the user should review before they are installed elsewhere. Either the
+/// packages installed by each installer, or the files in an existing stow, or chezmoi, style
+/// dotfiles directory.
+///
+#[derive(Debug)]
+pub struct ImportAction {
+    kind: ImportActionKind,
+    group: Name,
+}
+
+// ------------------------------------------------------------------------------------------------
+// Private Types
+// ------------------------------------------------------------------------------------------------
+
+#[derive(Debug)]
+enum ImportActionKind {
+    System,
+    Dotfiles { dotfiles_dir: PathBuf },
+}
+
+// ------------------------------------------------------------------------------------------------
+// Implementations
+// ------------------------------------------------------------------------------------------------
+
+impl Action for ImportAction {
+    fn run(&self, context: &ActionContext) -> Result<()> {
+        info!("ImportAction::run {:?}", self);
+        let steps = context.steps("import");
+        let result = match &self.kind {
+            ImportActionKind::System => self.import_system(context, &steps),
+            ImportActionKind::Dotfiles { dotfiles_dir } => {
+                self.import_dotfiles(context, &steps, dotfiles_dir)
+            }
+        };
+        let _ = steps.finish();
+        result
+    }
+}
+
+impl ImportAction {
+    pub fn system_action(group: Name) -> Result<Box<dyn Action>> {
+        Ok(Box::from(ImportAction {
+            kind: ImportActionKind::System,
+            group,
+        }))
+    }
+
+    pub fn dotfiles_action(dotfiles_dir: PathBuf, group: Name) -> Result<Box<dyn Action>> {
+        Ok(Box::from(ImportAction {
+            kind: ImportActionKind::Dotfiles { dotfiles_dir },
+            group,
+        }))
+    }
+
+    fn import_system(&self, context: &ActionContext, steps: &Steps) -> Result<()> {
+        let registry = context.registry()?;
+        let repository = context.repository()?;
+        let group_path = repository.path().join(self.group.as_path());
+
+        // Packages already in the repository are not imported again.
+        let known: HashSet<&Name> = repository
+            .groups()
+            .flat_map(|group| group.package_sets())
+            .filter_map(|package_set| package_set.packages())
+            .flatten()
+            .map(|package| package.name())
+            .collect();
+
+        for installer in registry
+            .installers()
+            .filter(|installer| installer.list_installed().is_some())
+        {
+            let name = format!("Importing packages installed with {}", installer.name());
+            let packages: Vec<Name> =
+                match installer.list_installed_packages(registry.environment()) {
+                    Ok(packages) => packages
+                        .into_iter()
+                        .filter(|package| !known.contains(package))
+                        .collect(),
+                    Err(e) => {
+                        warn!(
+                            "ImportAction::import_system could not list packages for {}: {}",
+                            installer.name(),
+                            e
+                        );
+                        steps.skip(&name, "could not list packages");
+                        continue;
+                    }
+                };
+            if packages.is_empty() {
+                steps.skip(&name, "no new packages found");
+                continue;
+            }
+
+            let set_path = group_path.join(format!("{}.yml", installer.name()));
+            if set_path.exists() {
+                steps.skip(&name, &format!("package set {:?} already exists", set_path));
+                continue;
+            }
+            let mut builder = PackageSetBuilder::named(installer.name().clone());
+            let _ = builder
+                .description(&format!(
+                    "Packages imported from the {} installer, review before use.",
+                    installer.name()
+                ))
+                .with_package_actions();
+            // Language packages are usually portable, system packages are not.
+            if !matches!(installer.kind(), PackageKind::Language(_)) {
+                let _ = builder.for_platform(Platform::CURRENT);
+            }
+            for package in &packages {
+                let _ = builder.add_package_action(
+                    PackageBuilder::named(package.clone())
+                        .of_kind(installer.kind())
+                        .build(),
+                )?;
+            }
+            steps.run(
+                &format!(
+                    "Importing {} package(s) installed with {} to draft package set {:?}",
+                    packages.len(),
+                    installer.name(),
+                    set_path
+                ),
+                || {
+                    create_dir_all(&group_path)?;
+                    builder.build().write(&mut File::create(&set_path)?)
+                },
+            )?;
+        }
+
+        if steps.summary().done() > 0 {
+            println!(
+                "Review the draft package sets in group '{}' before installing them elsewhere.",
+                self.group
+            );
+        }
+        Ok(())
+    }
+
+    fn import_dotfiles(
+        &self,
+        context: &ActionContext,
+        steps: &Steps,
+        dotfiles_dir: &Path,
+    ) -> Result<()> {
+        let group_path = context.repository_path().join(self.group.as_path());
+        let package_sets: Vec<(Name, Vec<(PathBuf, PathBuf)>)> = if is_chezmoi_dir(dotfiles_dir)? {
+            // A chezmoi source directory mirrors the home directory, with encoded file names.
+            let name = dotfiles_dir
+                .file_name()
+                .and_then(|name| Name::from_str(&name.to_string_lossy()).ok())
+                .unwrap_or_else(|| Name::from_str("dotfiles").unwrap());
+            let files = dir_files(dotfiles_dir, dotfiles_dir)?
+                .into_iter()
+                .filter_map(|file| {
+                    let target = chezmoi_target(&file);
+                    if target.is_none() {
+                        println!("   Skipping chezmoi file {:?}, it cannot be imported", file);
+                    }
+                    target.map(|target| (dotfiles_dir.join(&file), target))
+                })
+                .collect();
+            vec![(name, files)]
+        } else {
+            // A stow directory has a package directory for each set, mirroring the home directory.
+            let mut package_sets = Vec::default();
+            for package_dir in sorted_entries(dotfiles_dir)?
+                .into_iter()
+                .filter(|path| path.is_dir() && !is_hidden(path))
+            {
+                let dir_name = package_dir
+                    .file_name()
+                    .unwrap()
+                    .to_string_lossy()
+                    .to_string();
+                match Name::from_str(&dir_name) {
+                    Ok(name) => {
+                        let files = dir_files(&package_dir, &package_dir)?
+                            .into_iter()
+                            .map(|file| (package_dir.join(&file), file))
+                            .collect();
+                        package_sets.push((name, files));
+                    }
+                    Err(_) => steps.skip(
+                        &format!("Importing directory {:?}", package_dir),
+                        "it is not a valid package set name",
+                    ),
+                }
+            }
+            package_sets
+        };
+
+        // Check all targets first, so that nothing is imported if any package set exists.
+        let package_sets: Vec<(Name, Vec<(PathBuf, PathBuf)>)> = package_sets
+            .into_iter()
+            .filter(|(_, files)| !files.is_empty())
+            .collect();
+        for (name, _) in &package_sets {
+            let set_path = group_path.join(name.as_path());
+            if set_path.exists() {
+                return Err(Error::TargetExists(set_path.to_string_lossy().to_string()));
+            }
+        }
+
+        for (name, files) in package_sets {
+            let set_path = group_path.join(name.as_path());
+            steps.run(
+                &format!(
+                    "Importing {} file(s) to draft package set {:?}",
+                    files.len(),
+                    set_path
+                ),
+                || {
+                    let mut builder = PackageSetBuilder::named(name);
+                    let _ = builder.description(&format!(
+                        "Dotfiles imported from {}, review before use.",
+                        dotfiles_dir.display()
+                    ));
+                    for (from_path, target) in &files {
+                        let to_path = set_path.join(target);
+                        create_dir_all(to_path.parent().unwrap())?;
+                        let _ = copy(from_path, &to_path)?;
+                        let target = target.to_string_lossy();
+                        let _ = builder.add_link_file(&target, &format!("{{{{home}}}}/{}", target));
+                    }
+                    builder
+                        .build()
+                        .write(&mut File::create(set_path.join(PACKAGE_SET_FILE))?)
+                },
+            )?;
+        }
+        Ok(())
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+// Private Functions
+// ------------------------------------------------------------------------------------------------
+
+const CHEZMOI_PREFIXES: &[&str] = &[
+    "private_",
+    "readonly_",
+    "empty_",
+    "executable_",
+    "exact_",
+    "create_",
+];
+
+const CHEZMOI_UNSUPPORTED_PREFIXES: &[&str] = &["encrypted_", "modify_", "run_", "symlink_"];
+
+fn is_hidden(path: &Path) -> bool {
+    path.file_name()
+        .map(|name| name.to_string_lossy().starts_with('.'))
+        .unwrap_or_default()
+}
+
+fn is_chezmoi_dir(dir: &Path) -> Result<bool> {
+    Ok(sorted_entries(dir)?.iter().any(|path| {
+        let name = path.file_name().unwrap().to_string_lossy();
+        name.starts_with(".chezmoi") || name.starts_with("dot_")
+    }))
+}
+
+fn sorted_entries(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut entries = read_dir(dir)?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .collect::<Vec<PathBuf>>();
+    entries.sort();
+    Ok(entries)
+}
+
+// All files below `dir`, relative to `base`, ignoring any VCS, or tool, metadata.
+fn dir_files(dir: &Path, base: &Path) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::default();
+    for path in sorted_entries(dir)? {
+        let name = path.file_name().unwrap().to_string_lossy();
+        if name == ".git" || name.starts_with(".chezmoi") || name.starts_with(".stow") {
+            continue;
+        }
+        if path.is_dir() {
+            files.extend(dir_files(&path, base)?);
+        } else if path.is_file() {
+            files.push(path.strip_prefix(base).unwrap().to_path_buf());
+        }
+    }
+    Ok(files)
+}
+
+// The target path for a file in a chezmoi source directory, or `None` if it cannot be imported.
+fn chezmoi_target(file: &Path) -> Option<PathBuf> {
+    if file
+        .extension()
+        .map(|ext| ext == "tmpl")
+        .unwrap_or_default()
+    {
+        return None;
+    }
+    let mut target = PathBuf::new();
+    for component in file.iter() {
+        let mut name = component.to_string_lossy().to_string();
+        if CHEZMOI_UNSUPPORTED_PREFIXES
+            .iter()
+            .any(|prefix| name.starts_with(prefix))
+        {
+            return None;
+        }
+        while let Some(prefix) = CHEZMOI_PREFIXES
+            .iter()
+            .find(|prefix| name.starts_with(*prefix))
+        {
+            name = name[prefix.len()..].to_string();
+        }
+        if let Some(rest) = name.strip_prefix("dot_") {
+            name = format!(".{}", rest);
+        }
+        target.push(name);
+    }
+    Some(target)
+}
@@ -1,29 +1,64 @@
-use crate::actions::Action;
+use crate::actions::{Action, ActionContext};
+use crate::error::Error;
 use crate::error::Result;
-use crate::shared::install_log::PackageLog;
-use crate::shared::{FileSystemResource, InstallerRegistry, PackageRepository, StepCounter};
-use git2::Repository;
+use crate::shared::dirs::temp_dir_for;
+use crate::shared::migrate::{
+    check_format_version, repository_version, MigrationTarget, REPOSITORY_FORMAT_VERSION,
+    REPOSITORY_VERSION_FILE,
+};
+use crate::shared::{FileSystemResource, PackageRepository, RepoVcs, Steps};
+use crate::APP_NAME;
 use std::fs;
 use std::os::unix::fs as unix_fs;
-use std::path::PathBuf;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
 
 // ------------------------------------------------------------------------------------------------
 // Public Types
 // ------------------------------------------------------------------------------------------------
 
 ///
-/// This action initializes the package repository and installer registry on a new machine.
+/// This action initializes the package repository and installer registry on a new machine. In
+/// adopt mode an existing directory of package sets, fetched by other means, is checked and used
+/// as the repository; only the repository link, installer registry, configuration, and log file
+/// are created.
 ///
 #[derive(Debug)]
 pub struct InitAction {
     local_dir: Option<String>,
     repository_url: Option<String>,
+    template: Option<String>,
+    scaffold: Vec<InitScaffold>,
+    initial_commit: bool,
+    origin_url: Option<String>,
+    adopt: bool,
+    dry_run: bool,
+}
+
+///
+/// Additional boilerplate files that may be generated in the repository during initialization.
+///
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum InitScaffold {
+    /// A `.gitignore` file that ignores machine-local state.
+    GitIgnore,
+    /// A `README.md` file describing the repository layout.
+    Readme,
+    /// A Git pre-commit hook that runs `mcfg lint`.
+    PreCommitHook,
 }
 
 // ------------------------------------------------------------------------------------------------
 // Private Types
 // ------------------------------------------------------------------------------------------------
 
+#[derive(Debug)]
+struct TemplateFile {
+    path: &'static str,
+    message: &'static str,
+    content: &'static str,
+}
+
 // ------------------------------------------------------------------------------------------------
 // Public Functions
 // ------------------------------------------------------------------------------------------------
@@ -34,119 +69,200 @@ pub struct InitAction {
 
 const DEFAULT_INSTALLER_REGISTRY: &str = include_str!("default-installers.yml");
 
-const HOMEBREW_PACKAGE_SET: &str = include_str!("macos-homebrew.yml");
-const HOMEBREW_SERVICES_PACKAGE_SET: &str = include_str!("macos-homebrew-services.yml");
+const DEFAULT_CONFIG: &str = include_str!("default-config.yml");
+
+const HOMEBREW: TemplateFile = TemplateFile {
+    path: "00-installers/macos-homebrew.yml",
+    message: "'00-installers/homebrew' package set",
+    content: include_str!("templates/macos-homebrew.yml"),
+};
+
+const HOMEBREW_SERVICES: TemplateFile = TemplateFile {
+    path: "00-installers/macos-homebrew-services.yml",
+    message: "'00-installers/homebrew-services' package set",
+    content: include_str!("templates/macos-homebrew-services.yml"),
+};
+
+const HELLO_WORLD: TemplateFile = TemplateFile {
+    path: "example/hello-world.yml",
+    message: "'example/hello world' package set",
+    content: include_str!("templates/hello-world.yml"),
+};
+
+const GIT_IGNORE: TemplateFile = TemplateFile {
+    path: ".gitignore",
+    message: "repository '.gitignore' file",
+    content: include_str!("templates/gitignore"),
+};
+
+const README: TemplateFile = TemplateFile {
+    path: "README.md",
+    message: "repository 'README.md' file",
+    content: include_str!("templates/README.md"),
+};
+
+const PRE_COMMIT_HOOK: TemplateFile = TemplateFile {
+    path: ".git/hooks/pre-commit",
+    message: "Git pre-commit hook",
+    content: include_str!("templates/pre-commit"),
+};
+
+const DEFAULT_TEMPLATE: &str = "default";
+
+const DEFAULT_TEMPLATE_FILES: &[TemplateFile] = &[HOMEBREW, HOMEBREW_SERVICES, HELLO_WORLD];
+
+const MINIMAL_TEMPLATE_FILES: &[TemplateFile] = &[HELLO_WORLD];
+
+const PLATFORMS_TEMPLATE_FILES: &[TemplateFile] = &[
+    HOMEBREW,
+    HOMEBREW_SERVICES,
+    TemplateFile {
+        path: "10-shared/cli-tools.yml",
+        message: "'10-shared/cli-tools' package set",
+        content: include_str!("templates/cli-tools.yml"),
+    },
+    TemplateFile {
+        path: "20-macos/defaults.yml",
+        message: "'20-macos/defaults' package set",
+        content: include_str!("templates/macos-defaults.yml"),
+    },
+    TemplateFile {
+        path: "20-macos/applications.yml",
+        message: "'20-macos/applications' package set",
+        content: include_str!("templates/macos-applications.yml"),
+    },
+    TemplateFile {
+        path: "20-linux/essentials.yml",
+        message: "'20-linux/essentials' package set",
+        content: include_str!("templates/linux-essentials.yml"),
+    },
+];
 
 impl Action for InitAction {
-    fn run(&self) -> Result<()> {
-        let steps = StepCounter::from_one();
+    fn run(&self, context: &ActionContext) -> Result<()> {
+        let steps = context.steps("init").with_dry_run(self.dry_run);
         info!("InitAction::run {:?}", self);
+        if self.dry_run {
+            println!("Planned initialization steps, nothing will be changed:");
+        }
+        let result = self.run_steps(&steps, context);
+        let _ = steps.finish();
+        result
+    }
+}
 
+impl InitAction {
+    fn run_steps(&self, steps: &Steps, context: &ActionContext) -> Result<()> {
         let (link_required, local_dir) = match &self.local_dir {
-            None => (false, PackageRepository::default_path()),
+            None => (false, context.repository_path().clone()),
             Some(path) => (true, PathBuf::from(path)),
         };
 
-        init_create_dir(&steps, &local_dir, "local directory for repository")?;
+        if self.adopt {
+            init_adopt(steps, &local_dir)?;
+            if link_required {
+                init_link(steps, &local_dir, context.repository_path())?;
+            }
+            return init_machine_files(steps, context);
+        }
+
+        init_create_dir(steps, &local_dir, "local directory for repository")?;
 
-        let git_repo = local_dir.join(".git");
-        if !git_repo.is_dir() {
+        let vcs = context.open_repo_vcs()?;
+        if !vcs.is_repository(&local_dir) {
             match &self.repository_url {
                 None => {
-                    println!("{}. Initializing Git repository", steps.step());
-                    let _ = Repository::init(local_dir.clone())?;
+                    steps.run("Initializing repository", || vcs.init(&local_dir))?;
                 }
                 Some(repo_url) => {
-                    println!("{}. Cloning <{}> into repository", steps.step(), &repo_url);
-                    debug!("InitAction::run repo_url={:?}", repo_url);
-                    let _ = Repository::clone(repo_url, local_dir.clone())?;
+                    steps.run(&format!("Cloning <{}> into repository", &repo_url), || {
+                        debug!("InitAction::run repo_url={:?}", repo_url);
+                        vcs.clone_from(repo_url, &local_dir)
+                    })?;
                 }
             }
+        } else if let Some(repo_url) = &self.repository_url {
+            steps.skip(
+                &format!("Cloning <{}> into repository", &repo_url),
+                "it is already a repository",
+            );
         } else {
-            warn!("InitAction::run git repo exists, ignoring init/clone");
+            steps.skip("Initializing repository", "it is already a repository");
         }
 
-        let repository_path = PackageRepository::default_path();
+        let repository_path = context.repository_path().clone();
         if link_required {
-            println!(
-                "{}. Creating repository link {:?} -> {:?}",
-                steps.step(),
-                local_dir,
-                &repository_path
-            );
-            debug!("InitAction::run repository_path={:?}", repository_path);
-            fs::create_dir_all(repository_path.parent().unwrap())?;
-            unix_fs::symlink(local_dir, &repository_path)?;
+            init_link(steps, &local_dir, &repository_path)?;
         }
 
-        if matches!(&self.repository_url, None) {
-            init_create_dir(
-                &steps,
-                &PackageRepository::default_config_path(),
-                "repository '.config' directory",
-            )?;
-
-            init_create_dir(
-                &steps,
-                &PackageRepository::default_local_path(),
-                "repository '.local' directory",
-            )?;
+        if self.repository_url.is_none() {
+            let config_path = local_dir.join(".config");
+            init_create_dir(steps, &config_path, "repository '.config' directory")?;
 
             init_create_file(
-                &steps,
-                &repository_path.join("00-installers/macos-homebrew.yml"),
-                "'00-installers/homebrew' package set",
-                HOMEBREW_PACKAGE_SET,
+                steps,
+                &config_path.join(REPOSITORY_VERSION_FILE),
+                "repository format version file",
+                &format!("---\nversion: {}\n", REPOSITORY_FORMAT_VERSION),
             )?;
 
-            init_create_file(
-                &steps,
-                &repository_path.join("00-installers/macos-homebrew-services.yml"),
-                "'00-installers/homebrew-services' package set",
-                HOMEBREW_SERVICES_PACKAGE_SET,
+            init_create_dir(
+                steps,
+                &local_dir.join(".local"),
+                "repository '.local' directory",
             )?;
 
-            init_create_file(
-                &steps,
-                &repository_path.join("example/hello-world.yml"),
-                "'example/hello world' package set",
-                r##"---
-        name: hello-world
-        description: just a test to make sure things work
-        run-before: cargo --version"##,
+            init_template(
+                steps,
+                vcs.as_ref(),
+                &local_dir,
+                self.template.as_deref().unwrap_or(DEFAULT_TEMPLATE),
             )?;
         } else {
             warn!("InitAction::run no examples added to cloned repository");
         }
 
-        init_create_file(
-            &steps,
-            &InstallerRegistry::default_path(),
-            "standard installer registry file",
-            DEFAULT_INSTALLER_REGISTRY,
-        )?;
-
-        let log_file = PackageLog::default_path();
-        if !log_file.is_file() {
-            println!("{}. Creating package install log file", steps.step(),);
-            let _ = PackageLog::open();
-        } else {
-            warn!("InitAction::run log file {:?} exists", log_file)
+        for scaffold in &self.scaffold {
+            init_scaffold(steps, &local_dir, scaffold)?;
         }
 
-        println!("Done.");
-        Ok(())
+        if self.repository_url.is_none() {
+            if self.initial_commit {
+                init_commit(steps, vcs.as_ref(), &local_dir)?;
+            }
+            if let Some(origin_url) = &self.origin_url {
+                init_origin(steps, vcs.as_ref(), &local_dir, origin_url)?;
+            }
+        }
+
+        init_machine_files(steps, context)
     }
-}
 
-impl InitAction {
+    #[allow(clippy::too_many_arguments)]
     pub fn new_action(
         local_dir: Option<String>,
         repository_url: Option<String>,
+        template: Option<String>,
+        scaffold: Vec<InitScaffold>,
+        initial_commit: bool,
+        origin_url: Option<String>,
+        adopt: bool,
+        dry_run: bool,
     ) -> Result<Box<dyn Action>> {
+        if let Some(template) = &template {
+            if built_in_template(template).is_none() && !is_template_url(template) {
+                return Err(Error::UnknownTemplate(template.clone()));
+            }
+        }
         Ok(Box::from(InitAction {
             local_dir,
             repository_url,
+            template,
+            scaffold,
+            initial_commit,
+            origin_url,
+            adopt,
+            dry_run,
         }))
     }
 }
@@ -155,30 +271,253 @@ impl InitAction {
 // Private Functions
 // ------------------------------------------------------------------------------------------------
 
-fn init_create_dir(steps: &StepCounter, dir_path: &PathBuf, message: &str) -> Result<()> {
-    if !dir_path.is_dir() {
-        println!("{}. Creating {}", steps.step(), message);
-        fs::create_dir_all(dir_path)?;
+// Check that the existing directory is a package repository that can be read by this version
+// of the tool, with at least one group. A repository in an older format cannot be read until it
+// is migrated, which requires it to be adopted first, so only its group directories are checked.
+fn init_adopt(steps: &Steps, local_dir: &Path) -> Result<()> {
+    let cannot_adopt = |reason: &str| {
+        Error::CannotAdoptRepository(local_dir.to_string_lossy().to_string(), reason.to_string())
+    };
+    if !local_dir.is_dir() {
+        return Err(cannot_adopt("it is not a directory"));
+    }
+    steps.step(&format!("Checking existing repository {:?}", local_dir));
+    let version = repository_version(local_dir)?;
+    match check_format_version(MigrationTarget::Repository, version) {
+        Ok(()) => {
+            let repository = PackageRepository::open_from(local_dir.to_path_buf())?;
+            if repository.is_empty() {
+                return Err(cannot_adopt("it contains no package set groups"));
+            }
+            let package_sets: usize = repository
+                .groups()
+                .map(|group| group.package_sets().count())
+                .sum();
+            steps.step(&format!(
+                "Adopting repository with {} group(s) and {} package set(s)",
+                repository.groups().count(),
+                package_sets
+            ));
+        }
+        Err(Error::MigrationRequired(_, _, current)) => {
+            let groups = fs::read_dir(local_dir)?
+                .filter_map(|entry| entry.ok())
+                .filter(|entry| entry.path().is_dir())
+                .filter(|entry| !entry.file_name().to_string_lossy().starts_with('.'))
+                .count();
+            if groups == 0 {
+                return Err(cannot_adopt("it contains no package set groups"));
+            }
+            steps.step(&format!(
+                "Adopting repository with {} group(s), in format version {}; run the 'migrate' command to upgrade it to version {}",
+                groups,
+                version,
+                current
+            ));
+        }
+        Err(e) => return Err(e),
+    }
+    Ok(())
+}
+
+fn init_link(steps: &Steps, local_dir: &Path, repository_path: &Path) -> Result<()> {
+    let name = format!(
+        "Creating repository link {:?} -> {:?}",
+        local_dir, repository_path
+    );
+    if fs::read_link(repository_path).ok().as_deref() == Some(local_dir) {
+        steps.skip(&name, "it already exists");
+        return Ok(());
+    }
+    steps.run(&name, || {
+        debug!("InitAction::run repository_path={:?}", repository_path);
+        fs::create_dir_all(repository_path.parent().unwrap())?;
+        unix_fs::symlink(local_dir, repository_path)?;
+        Ok(())
+    })
+}
+
+// Create the installer registry, configuration, and log files; these belong to the machine,
+// rather than the repository.
+fn init_machine_files(steps: &Steps, context: &ActionContext) -> Result<()> {
+    init_create_file(
+        steps,
+        context.registry_path(),
+        "standard installer registry file",
+        DEFAULT_INSTALLER_REGISTRY,
+    )?;
+
+    init_create_file(
+        steps,
+        context.config_path(),
+        "default configuration file",
+        DEFAULT_CONFIG,
+    )?;
+
+    let log_format = context.install_log_format();
+    let log_file = context.install_log_path();
+    if !log_file.is_file() {
+        steps.run("Creating package install log file", || {
+            let _ = log_format.open_from(log_file.clone());
+            Ok(())
+        })
     } else {
-        warn!("Directory {} ({:?}) exists", message, dir_path);
+        steps.skip("Creating package install log file", "it already exists");
+        Ok(())
+    }
+}
+
+fn init_template(
+    steps: &Steps,
+    vcs: &dyn RepoVcs,
+    repository_path: &Path,
+    template: &str,
+) -> Result<()> {
+    debug!("init_template template={:?}", template);
+    match built_in_template(template) {
+        Some(template_files) => {
+            for file in template_files {
+                init_create_file(
+                    steps,
+                    &repository_path.join(file.path),
+                    file.message,
+                    file.content,
+                )?;
+            }
+            Ok(())
+        }
+        None if is_template_url(template) => {
+            let name = format!("Fetching template <{}>", template);
+            if steps.is_dry_run() {
+                steps.skip(&name, "dry run");
+                return Ok(());
+            }
+            // The template is cloned into a new, private, directory; which is removed whether
+            // or not the copy succeeds, a failure to remove it is not an error.
+            let work_dir = temp_dir_for(APP_NAME, "template")?;
+            let template_dir = work_dir.path().join("template");
+            let result = steps
+                .run(&name, || vcs.clone_from(template, &template_dir))
+                .and_then(|_| init_copy_template(steps, &template_dir, repository_path));
+            if let Err(e) = work_dir.close() {
+                warn!(
+                    "init_template: could not remove template clone, error: {}",
+                    e
+                );
+            }
+            result
+        }
+        None => Err(Error::UnknownTemplate(template.to_string())),
+    }
+}
+
+fn built_in_template(template: &str) -> Option<&'static [TemplateFile]> {
+    match template {
+        DEFAULT_TEMPLATE => Some(DEFAULT_TEMPLATE_FILES),
+        "minimal" => Some(MINIMAL_TEMPLATE_FILES),
+        "platforms" => Some(PLATFORMS_TEMPLATE_FILES),
+        _ => None,
+    }
+}
+
+fn is_template_url(template: &str) -> bool {
+    template.contains("://") || template.starts_with("git@") || Path::new(template).is_dir()
+}
+
+fn init_copy_template(steps: &Steps, from_dir: &Path, to_dir: &Path) -> Result<()> {
+    for entry in fs::read_dir(from_dir)? {
+        let entry = entry?;
+        let file_name = entry.file_name();
+        if file_name == ".git" {
+            continue;
+        }
+        let from_path = entry.path();
+        let to_path = to_dir.join(&file_name);
+        if from_path.is_dir() {
+            fs::create_dir_all(&to_path)?;
+            init_copy_template(steps, &from_path, &to_path)?;
+        } else {
+            let name = format!("Copying template file {:?}", file_name);
+            if to_path.exists() {
+                steps.skip(&name, "it already exists");
+            } else {
+                steps.run(&name, || {
+                    let _ = fs::copy(&from_path, &to_path)?;
+                    Ok(())
+                })?;
+            }
+        }
     }
     Ok(())
 }
 
+fn init_scaffold(steps: &Steps, local_dir: &Path, scaffold: &InitScaffold) -> Result<()> {
+    debug!("init_scaffold scaffold={:?}", scaffold);
+    let file = match scaffold {
+        InitScaffold::GitIgnore => GIT_IGNORE,
+        InitScaffold::Readme => README,
+        InitScaffold::PreCommitHook => PRE_COMMIT_HOOK,
+    };
+    let file_path = local_dir.join(file.path);
+    init_create_file(steps, &file_path, file.message, file.content)?;
+    if *scaffold == InitScaffold::PreCommitHook && !steps.is_dry_run() {
+        fs::set_permissions(&file_path, fs::Permissions::from_mode(0o755))?;
+    }
+    Ok(())
+}
+
+fn init_commit(steps: &Steps, vcs: &dyn RepoVcs, local_dir: &Path) -> Result<()> {
+    if vcs.is_repository(local_dir) && vcs.has_commits(local_dir)? {
+        steps.skip(
+            "Committing repository files",
+            "the repository already has commits",
+        );
+        return Ok(());
+    }
+    steps.run("Committing repository files", || {
+        vcs.commit_all(
+            local_dir,
+            &format!("Initial repository created by {}", APP_NAME),
+        )
+    })
+}
+
+fn init_origin(steps: &Steps, vcs: &dyn RepoVcs, local_dir: &Path, origin_url: &str) -> Result<()> {
+    let name = format!("Setting repository origin <{}>", origin_url);
+    if vcs.is_repository(local_dir) && vcs.has_origin(local_dir)? {
+        steps.skip(&name, "the repository already has an origin");
+        Ok(())
+    } else {
+        steps.run(&name, || vcs.set_origin(local_dir, origin_url))
+    }
+}
+
+fn init_create_dir(steps: &Steps, dir_path: &PathBuf, message: &str) -> Result<()> {
+    let name = format!("Creating {}", message);
+    if dir_path.is_dir() {
+        steps.skip(&name, "it already exists");
+        Ok(())
+    } else {
+        steps.run(&name, || Ok(fs::create_dir_all(dir_path)?))
+    }
+}
+
 fn init_create_file(
-    steps: &StepCounter,
+    steps: &Steps,
     file_path: &PathBuf,
     message: &str,
     content: &str,
 ) -> Result<()> {
-    if !file_path.is_file() {
-        println!("{}. Creating {}", steps.step(), message,);
-        fs::create_dir_all(file_path.parent().unwrap())?;
-        fs::write(file_path, content)?;
+    let name = format!("Creating {}", message);
+    if file_path.is_file() {
+        steps.skip(&name, "it already exists");
+        Ok(())
     } else {
-        warn!("File {} ({:?}) exists", message, file_path);
+        steps.run(&name, || {
+            fs::create_dir_all(file_path.parent().unwrap())?;
+            Ok(fs::write(file_path, content)?)
+        })
     }
-    Ok(())
 }
 
 // ------------------------------------------------------------------------------------------------
@@ -1,8 +1,8 @@
-use crate::actions::Action;
+use crate::actions::{after_run, Action, ActionContext, ActionReport};
 use crate::error::Result;
-use crate::shared::installer::{InstallActionKind, InstallerRegistry};
-use crate::shared::packages::PackageRepository;
-use crate::shared::{FileSystemResource, Name};
+use crate::shared::installer::{ExecuteOptions, InstallActionKind};
+use crate::shared::PackageSetSelection;
+use std::time::Instant;
 
 // ------------------------------------------------------------------------------------------------
 // Public Types
@@ -14,8 +14,8 @@ use crate::shared::{FileSystemResource, Name};
 #[derive(Debug)]
 pub struct InstallAction {
     kind: InstallActionKind,
-    group: Option<Name>,
-    package_set: Option<Name>,
+    selection: PackageSetSelection,
+    options: ExecuteOptions,
 }
 
 // ------------------------------------------------------------------------------------------------
@@ -23,59 +23,81 @@ pub struct InstallAction {
 // ------------------------------------------------------------------------------------------------
 
 impl Action for InstallAction {
-    fn run(&self) -> Result<()> {
+    fn run(&self, context: &ActionContext) -> Result<()> {
+        self.run_with_report(context).map(|_| ())
+    }
+
+    fn run_with_report(&self, context: &ActionContext) -> Result<ActionReport> {
         info!("InstallAction::run {:?}", self);
 
-        let repository = PackageRepository::open()?;
-        if repository.is_empty() {
+        let started = Instant::now();
+        // The configuration is read before the run, so that it fails early if invalid.
+        let _ = context.config()?;
+        if context.group_names()?.is_empty() {
             println!("No package sets found in repository");
+            Ok(ActionReport::new(started.elapsed()))
         } else {
-            let installer_registry = InstallerRegistry::open()?;
-            installer_registry.execute(&self.kind, &repository, &self.group, &self.package_set)?;
+            let repository = context.selected_repository(&self.selection)?;
+            let installer_registry = context.registry()?;
+            let mut log_db = context.open_install_log()?;
+            let result = installer_registry.execute_logged(
+                &self.kind,
+                &repository,
+                &self.selection,
+                &self.options,
+                log_db.as_mut(),
+            );
+            installer_registry
+                .last_summary()
+                .steps()
+                .report_with(context.reporter());
+            after_run(
+                context,
+                &self.kind.to_string(),
+                started,
+                &installer_registry.last_summary(),
+                &result,
+                self.kind != InstallActionKind::LinkFiles,
+            );
+            let summary = result?;
+            Ok(ActionReport::from(&summary).with_duration(started.elapsed()))
         }
-        Ok(())
     }
 }
 
 impl InstallAction {
     pub fn install_action(
-        group: Option<Name>,
-        package_set: Option<Name>,
+        selection: PackageSetSelection,
+        options: ExecuteOptions,
     ) -> Result<Box<dyn Action>> {
         Ok(Box::from(InstallAction {
             kind: InstallActionKind::Install,
-            group,
-            package_set,
+            selection,
+            options,
         }))
     }
     pub fn update_action(
-        group: Option<Name>,
-        package_set: Option<Name>,
+        selection: PackageSetSelection,
+        options: ExecuteOptions,
     ) -> Result<Box<dyn Action>> {
         Ok(Box::from(InstallAction {
             kind: InstallActionKind::Update,
-            group,
-            package_set,
+            selection,
+            options,
         }))
     }
-    pub fn uninstall_action(
-        group: Option<Name>,
-        package_set: Option<Name>,
-    ) -> Result<Box<dyn Action>> {
+    pub fn uninstall_action(selection: PackageSetSelection) -> Result<Box<dyn Action>> {
         Ok(Box::from(InstallAction {
             kind: InstallActionKind::Uninstall,
-            group,
-            package_set,
+            selection,
+            options: Default::default(),
         }))
     }
-    pub fn link_files_action(
-        group: Option<Name>,
-        package_set: Option<Name>,
-    ) -> Result<Box<dyn Action>> {
+    pub fn link_files_action(selection: PackageSetSelection) -> Result<Box<dyn Action>> {
         Ok(Box::from(InstallAction {
             kind: InstallActionKind::LinkFiles,
-            group,
-            package_set,
+            selection,
+            options: Default::default(),
         }))
     }
 }
@@ -1,19 +1,57 @@
-use crate::actions::Action;
-use crate::error::Result;
-use crate::shared::command::edit_file;
-use crate::shared::installer::InstallerRegistry;
-use crate::shared::FileSystemResource;
+use crate::actions::{Action, ActionContext};
+use crate::error::{Error, Result};
+use crate::shared::command::edit_file_checked;
+use crate::shared::env::{add_action_vars, add_global_vars, var_references};
+use crate::shared::installer::{
+    read_registry_installers, set_installer_enabled, InstallerRegistry,
+};
+use crate::shared::{
+    find_executable, FileSystemResource, InstallActionKind, Installer, InstallerCommand, Name,
+    PackageKind,
+};
+use std::collections::HashMap;
 
 // ------------------------------------------------------------------------------------------------
 // Public Types
 // ------------------------------------------------------------------------------------------------
 
 ///
-/// This action will invoke the system text editor to edit the installer registry file.
+/// This action will invoke the system text editor to edit the installer registry file, the file
+/// is then checked and the editor reopened if the user wants to fix any problems.
 ///
 #[derive(Debug)]
 pub struct EditInstallersAction {}
 
+///
+/// This action shows the definition of a single installer, along with the checks that decide
+/// whether it is used on this machine; its platform, its `if_exists` check, the user it must be run
+/// as, and whether the program run by each of its commands is on the `PATH`.
+///
+#[derive(Debug)]
+pub struct ShowInstallerAction {
+    name: Name,
+}
+
+///
+/// This action smoke-tests an installer without installing anything, running its `if_exists`,
+/// `run_as`, and `latest_version` checks and a dry-run of its install command, and failing if any
+/// check fails.
+///
+#[derive(Debug)]
+pub struct TestInstallerAction {
+    name: Name,
+}
+
+///
+/// This action enables, or disables, an installer in the registry file without removing it; a
+/// disabled installer is simply not used until it is enabled again.
+///
+#[derive(Debug)]
+pub struct EnableInstallerAction {
+    name: Name,
+    enabled: bool,
+}
+
 // ------------------------------------------------------------------------------------------------
 // Private Types
 // ------------------------------------------------------------------------------------------------
@@ -27,11 +65,15 @@ pub struct EditInstallersAction {}
 // ------------------------------------------------------------------------------------------------
 
 impl Action for EditInstallersAction {
-    fn run(&self) -> Result<()> {
-        let registry_path = InstallerRegistry::default_path();
+    fn run(&self, context: &ActionContext) -> Result<()> {
+        let registry_path = context.registry_path().clone();
         debug!("EditInstallersAction::run editing file {:?}", registry_path);
-        let _ = edit_file(&registry_path)?;
-        Ok(())
+        edit_file_checked(&registry_path, |path| {
+            match InstallerRegistry::open_from(path.to_path_buf()) {
+                Ok(_) => Vec::default(),
+                Err(e) => vec![e.to_string()],
+            }
+        })
     }
 }
 
@@ -40,3 +82,236 @@ impl EditInstallersAction {
         Ok(Box::from(EditInstallersAction {}))
     }
 }
+
+// ------------------------------------------------------------------------------------------------
+
+impl Action for ShowInstallerAction {
+    fn run(&self, context: &ActionContext) -> Result<()> {
+        info!("ShowInstallerAction::run {:?}", self);
+        let installers = named_installers(context, &self.name)?;
+        for (index, installer) in installers.iter().enumerate() {
+            if index > 0 {
+                println!();
+            }
+            show_installer(context, installer);
+        }
+        Ok(())
+    }
+}
+
+impl ShowInstallerAction {
+    pub fn new_action(name: Name) -> Result<Box<dyn Action>> {
+        Ok(Box::from(ShowInstallerAction { name }))
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+
+impl Action for TestInstallerAction {
+    fn run(&self, context: &ActionContext) -> Result<()> {
+        info!("TestInstallerAction::run {:?}", self);
+        let installers = named_installers(context, &self.name)?;
+        let mut failures = 0;
+        for (index, installer) in installers.iter().enumerate() {
+            if index > 0 {
+                println!();
+            }
+            println!("Installer: {} ({})", installer.name(), installer.platform());
+            if !installer.is_platform_match() || !installer.is_enabled() {
+                println!("  note: this installer is not used on this machine");
+            }
+            for check in installer.smoke_test(context.environment()) {
+                println!("  {}", check);
+                if !check.is_passed() {
+                    failures += 1;
+                }
+            }
+        }
+        if failures == 0 {
+            Ok(())
+        } else {
+            Err(Error::InstallerTestFailed(self.name.to_string(), failures))
+        }
+    }
+}
+
+impl TestInstallerAction {
+    pub fn new_action(name: Name) -> Result<Box<dyn Action>> {
+        Ok(Box::from(TestInstallerAction { name }))
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+
+impl Action for EnableInstallerAction {
+    fn run(&self, context: &ActionContext) -> Result<()> {
+        info!("EnableInstallerAction::run {:?}", self);
+        let state = if self.enabled { "enabled" } else { "disabled" };
+        let changed = set_installer_enabled(context.registry_path(), &self.name, self.enabled)?;
+        if changed == 0 {
+            println!("Installer {} is already {}", self.name, state);
+        } else {
+            println!("Installer {} is now {}", self.name, state);
+        }
+        Ok(())
+    }
+}
+
+impl EnableInstallerAction {
+    pub fn enable_action(name: Name) -> Result<Box<dyn Action>> {
+        Ok(Box::from(EnableInstallerAction {
+            name,
+            enabled: true,
+        }))
+    }
+
+    pub fn disable_action(name: Name) -> Result<Box<dyn Action>> {
+        Ok(Box::from(EnableInstallerAction {
+            name,
+            enabled: false,
+        }))
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+// Private Functions
+// ------------------------------------------------------------------------------------------------
+
+// The registry file is read directly, as opening the registry discards the installers that are
+// not used on this machine.
+fn named_installers(context: &ActionContext, name: &Name) -> Result<Vec<Installer>> {
+    let installers: Vec<Installer> = read_registry_installers(context.registry_path())?
+        .into_iter()
+        .filter(|installer| installer.name() == name)
+        .collect();
+    if installers.is_empty() {
+        Err(Error::NoInstaller(name.to_string()))
+    } else {
+        Ok(installers)
+    }
+}
+
+fn show_installer(context: &ActionContext, installer: &Installer) {
+    println!("Installer: {}", installer.name());
+    println!(
+        "  platform:  {} ({})",
+        installer.platform(),
+        if installer.is_platform_match() {
+            "matches this machine"
+        } else {
+            "does not match this machine"
+        }
+    );
+    println!("  kind:      {}", kind_to_string(&installer.kind()));
+    println!(
+        "  enabled:   {}",
+        if installer.is_enabled() { "yes" } else { "no" }
+    );
+    // An if-exists check may run a command, so it is only evaluated once.
+    let if_exists_match = installer.if_exists_match(context.environment());
+    match installer.if_exists() {
+        None => println!("  if-exists: none"),
+        Some(if_exists) => println!(
+            "  if-exists: {} ({})",
+            if_exists,
+            if if_exists_match { "passed" } else { "failed" }
+        ),
+    }
+    match installer.check_run_as() {
+        Ok(_) => println!("  run-as:    {:?}", installer.run_as()),
+        Err(e) => println!("  run-as:    {:?} ({})", installer.run_as(), e),
+    }
+    let mut discarded: Vec<&str> = Default::default();
+    if !installer.is_enabled() {
+        discarded.push("disabled");
+    }
+    if !installer.is_platform_match() {
+        discarded.push("not a platform match");
+    }
+    if !if_exists_match {
+        discarded.push("'if-exists' check failed");
+    }
+    if discarded.is_empty() {
+        println!("  status:    used on this machine");
+    } else {
+        println!("  status:    discarded, {}", discarded.join(", "));
+    }
+
+    println!("Commands, with variables replaced as far as possible:");
+    let mut commands: Vec<(String, &InstallerCommand, HashMap<String, String>)> =
+        Default::default();
+    for (label, action_commands) in &[
+        ("", installer.commands()),
+        ("bundle ", installer.bundle_commands()),
+    ] {
+        for action in &ACTIONS {
+            if let Some(command) = action_commands.get(action) {
+                commands.push((
+                    format!("{}{}", label, action),
+                    command,
+                    add_global_vars(&add_action_vars(action, context.environment())),
+                ));
+            }
+        }
+    }
+    for (label, command) in &[
+        ("installed-version", installer.installed_version()),
+        ("latest-version", installer.latest_version()),
+        ("available-versions", installer.available_versions()),
+        ("install-version", installer.install_version()),
+        ("update-self", installer.update_self()),
+        ("list-installed", installer.list_installed()),
+        ("add-prerequisite", installer.add_prerequisite()),
+    ] {
+        if let Some(command) = command {
+            commands.push((
+                label.to_string(),
+                command,
+                add_global_vars(context.environment()),
+            ));
+        }
+    }
+    if commands.is_empty() {
+        println!("  none");
+    }
+    for (label, command, vars) in commands {
+        let vars = with_placeholders(command, vars);
+        println!("  {}: {}", label, command.with_vars(&vars));
+        match command.program(&vars) {
+            None => println!("    program: unknown"),
+            Some(program) => match find_executable(&program) {
+                Some(path) => println!("    program: {} ({})", program, path.display()),
+                None => println!("    program: {} (not found on PATH)", program),
+            },
+        }
+    }
+}
+
+// Variables that are only set when a command is run for a package, such as `package_name`, are
+// shown as references rather than replaced with their name.
+fn with_placeholders(
+    command: &InstallerCommand,
+    mut vars: HashMap<String, String>,
+) -> HashMap<String, String> {
+    for name in var_references(&command.to_string()) {
+        let placeholder = format!("{{{{{}}}}}", name);
+        let _ = vars.entry(name).or_insert(placeholder);
+    }
+    vars
+}
+
+fn kind_to_string(kind: &PackageKind) -> String {
+    match kind {
+        PackageKind::Application(None) => "application".to_string(),
+        PackageKind::Application(Some(name)) => format!("application: {}", name),
+        PackageKind::Default => "default".to_string(),
+        PackageKind::Language(name) => format!("language: {}", name),
+    }
+}
+
+const ACTIONS: [InstallActionKind; 4] = [
+    InstallActionKind::Install,
+    InstallActionKind::Update,
+    InstallActionKind::Uninstall,
+    InstallActionKind::LinkFiles,
+];
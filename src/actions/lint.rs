@@ -0,0 +1,45 @@
+use crate::actions::{Action, ActionContext};
+use crate::error::{Error, Result};
+use crate::shared::{lint_installer_registry, lint_repository};
+
+// ------------------------------------------------------------------------------------------------
+// Public Types
+// ------------------------------------------------------------------------------------------------
+
+///
+/// This action checks the package repository, and installer registry, for problems; reporting
+/// each and failing if any are found.
+///
+#[derive(Debug)]
+pub struct LintAction {}
+
+// ------------------------------------------------------------------------------------------------
+// Implementations
+// ------------------------------------------------------------------------------------------------
+
+impl Action for LintAction {
+    fn run(&self, context: &ActionContext) -> Result<()> {
+        info!("LintAction::run {:?}", self);
+        let problems = lint_repository(context.scope(), context.repository_path())
+            .iter()
+            .chain(lint_installer_registry(context.registry_path()).iter())
+            .map(|problem| problem.to_string())
+            .collect::<Vec<String>>();
+
+        if problems.is_empty() {
+            println!("No problems found in repository");
+            Ok(())
+        } else {
+            for problem in &problems {
+                println!("{}", problem);
+            }
+            Err(Error::LintFailed(problems.len()))
+        }
+    }
+}
+
+impl LintAction {
+    pub fn new_action() -> Result<Box<dyn Action>> {
+        Ok(Box::from(LintAction {}))
+    }
+}
@@ -1,18 +1,21 @@
-use crate::actions::Action;
+use crate::actions::show::print_readme;
+use crate::actions::{Action, ActionContext};
 use crate::error::Result;
-use crate::shared::packages::{PackageRepository, PackageSet, PackageSetGroup};
-use crate::shared::{FileSystemResource, Name};
+use crate::shared::packages::{PackageSet, PackageSetGroup};
+use crate::shared::Name;
 
 // ------------------------------------------------------------------------------------------------
 // Public Types
 // ------------------------------------------------------------------------------------------------
 
 ///
-/// This action will list, hierarchically, the package set groups and package sets.
+/// This action will list, hierarchically, the package set groups and package sets. When
+/// describing, the README file of each group and package set is also shown.
 ///
 #[derive(Debug)]
 pub struct ListAction {
     group: Option<Name>,
+    describe: bool,
 }
 
 // ------------------------------------------------------------------------------------------------
@@ -20,24 +23,20 @@ pub struct ListAction {
 // ------------------------------------------------------------------------------------------------
 
 impl Action for ListAction {
-    fn run(&self) -> Result<()> {
+    fn run(&self, context: &ActionContext) -> Result<()> {
         info!("ListAction::run {:?}", self);
-        let package_repository = PackageRepository::open()?;
+        let package_repository = context.repository()?;
         if package_repository.is_empty() {
             println!("No package sets found in repository");
         } else {
             match &self.group {
                 None => {
                     for group in package_repository.groups() {
-                        list_group(&group);
+                        list_group(group, self.describe)?;
                     }
                 }
                 Some(group) => {
-                    if let Some(found) = package_repository.group(group) {
-                        list_group(found);
-                    } else {
-                        println!("No group found in repository named '{}'", group);
-                    }
+                    list_group(package_repository.group_or_suggest(group)?, self.describe)?;
                 }
             }
         }
@@ -47,7 +46,17 @@ impl Action for ListAction {
 
 impl ListAction {
     pub fn new_action(group: Option<Name>) -> Result<Box<dyn Action>> {
-        Ok(Box::from(ListAction { group }))
+        Ok(Box::from(ListAction {
+            group,
+            describe: false,
+        }))
+    }
+
+    pub fn describe_action(group: Option<Name>) -> Result<Box<dyn Action>> {
+        Ok(Box::from(ListAction {
+            group,
+            describe: true,
+        }))
     }
 }
 
@@ -55,20 +64,39 @@ impl ListAction {
 // Private Functions
 // ------------------------------------------------------------------------------------------------
 
-fn list_group(group: &PackageSetGroup) {
-    println!("* {}", group.name());
+fn list_group(group: &PackageSetGroup, describe: bool) -> Result<()> {
+    match group.description() {
+        None => println!("* {}", group.name()),
+        Some(description) => println!("* {}: {}", group.name(), description),
+    }
+    if describe {
+        if let Some(readme_path) = group.readme_path() {
+            print_readme(&readme_path, false, "    ")?;
+        }
+    }
     for set in group.package_sets() {
-        list_set(set);
+        list_set(set, describe)?;
     }
+    Ok(())
 }
 
-fn list_set(set: &PackageSet) {
+fn list_set(set: &PackageSet, describe: bool) -> Result<()> {
+    let name = match set.display_name() {
+        None => set.name().to_string(),
+        Some(display_name) => format!("{} ({})", display_name, set.name()),
+    };
     match set.description() {
         None => {
-            println!("  * {}", set.name());
+            println!("  * {}", name);
         }
         Some(description) => {
-            println!("  * {}: {}", set.name(), description);
+            println!("  * {}: {}", name, description);
+        }
+    }
+    if describe {
+        if let Some(readme_path) = set.readme_path() {
+            print_readme(&readme_path, false, "      ")?;
         }
     }
+    Ok(())
 }
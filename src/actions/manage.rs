@@ -1,23 +1,36 @@
-use crate::actions::Action;
-use crate::error::Result;
-use crate::shared::command::edit_file;
-use crate::shared::{FileSystemResource, Name, PackageRepository};
-use std::fs::{create_dir_all, write};
-use std::path::PathBuf;
+use crate::actions::backup::copy_dir;
+use crate::actions::{Action, ActionContext};
+use crate::error::{Error, Result};
+use crate::shared::builders::{Builder, PackageBuilder, PackageSetBuilder};
+use crate::shared::command::{edit_file, edit_file_checked};
+use crate::shared::packages::{
+    LazyPackageSetGroup, Readable, Writeable, ARCHIVE_DIR, PACKAGE_SET_FILE,
+};
+use crate::shared::{
+    commit_all, lint_package_set_file, move_path, prompt, prompt_optional, suggest_names, Name,
+    Package, PackageKind, PackageSet, PackageSetGroup, Platform, Scope,
+};
+use git2::Repository;
+use regex::Regex;
+use std::fs::{copy, create_dir_all, read_to_string, remove_file, write, File};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
 
 // ------------------------------------------------------------------------------------------------
 // Public Types
 // ------------------------------------------------------------------------------------------------
 
 ///
-/// This action performs simple management actions on the package repository -- to add, edit, or
-/// remove package sets.
+/// This action performs simple management actions on the package repository -- to add, edit,
+/// remove, archive, retire, rename, move, or copy package sets; to rename package set groups; and
+/// to add or remove individual packages in a package set.
 ///
 #[derive(Debug)]
 pub struct ManageAction {
     kind: ManageActionKind,
     group: Name,
-    package_set: Name,
+    package_set: Option<Name>,
     package_set_is_file: bool,
 }
 
@@ -27,9 +40,43 @@ pub struct ManageAction {
 
 #[derive(Debug)]
 enum ManageActionKind {
-    Add,
+    Add {
+        source: AddSource,
+    },
     Edit,
-    Remove,
+    Remove {
+        uninstall: bool,
+    },
+    Archive {
+        uninstall: bool,
+        commit: bool,
+    },
+    Rename {
+        new_name: Name,
+        commit: bool,
+    },
+    Move {
+        to_group: Name,
+        commit: bool,
+    },
+    Copy {
+        to_group: Name,
+        new_name: Name,
+        commit: bool,
+    },
+    AddPackage {
+        package: Box<Package>,
+    },
+    RemovePackage {
+        package: Name,
+    },
+}
+
+#[derive(Debug)]
+enum AddSource {
+    Template,
+    Interactive,
+    Stdin,
 }
 
 // ------------------------------------------------------------------------------------------------
@@ -42,31 +89,62 @@ description: my new pset package set.
 packages:
   - name: pset"##;
 
+lazy_static! {
+    static ref NAME_FIELD: Regex = Regex::new(r"(?m)^name:.*$").unwrap();
+}
+
 impl Action for ManageAction {
-    fn run(&self) -> Result<()> {
-        let direct_path = self.make_package_set_path(true);
-        let indirect_path = self.make_package_set_path(false);
+    fn run(&self, context: &ActionContext) -> Result<()> {
+        let package_set = match (&self.kind, &self.package_set) {
+            (ManageActionKind::Rename { new_name, commit }, None) => {
+                return self.rename_group(context, new_name, *commit);
+            }
+            (_, Some(package_set)) => package_set,
+            (_, None) => unreachable!(),
+        };
+        let direct_path = self.make_package_set_path(context, package_set, true);
+        let indirect_path = self.make_package_set_path(context, package_set, false);
         debug!(
             "ManageAction::run: Taking action {:?} on path {:?} or {:?}",
             self.kind, direct_path, indirect_path
         );
-        match self.kind {
-            ManageActionKind::Add => {
+        match &self.kind {
+            ManageActionKind::Add { source } => {
                 if !direct_path.exists() && !indirect_path.exists() {
-                    if self.package_set_is_file {
-                        create_dir_all(direct_path.parent().unwrap())?;
-                        write(
-                            &direct_path,
-                            EMPTY_PACKAGE_SET.replace("pset", &self.package_set.to_string()),
-                        )?;
-                        edit_file(&direct_path)?;
+                    let path = if self.package_set_is_file {
+                        direct_path
                     } else {
-                        create_dir_all(indirect_path.parent().unwrap())?;
-                        write(
-                            &indirect_path,
-                            EMPTY_PACKAGE_SET.replace("pset", &self.package_set.to_string()),
-                        )?;
-                        edit_file(&indirect_path)?;
+                        indirect_path
+                    };
+                    match source {
+                        AddSource::Template => {
+                            create_dir_all(path.parent().unwrap())?;
+                            write(
+                                &path,
+                                EMPTY_PACKAGE_SET.replace("pset", &package_set.to_string()),
+                            )?;
+                            edit_file(&path)?;
+                        }
+                        AddSource::Interactive => {
+                            let package_set = build_package_set(package_set)?;
+                            create_dir_all(path.parent().unwrap())?;
+                            package_set.write(&mut File::create(&path)?)?;
+                            println!("Package set written to {:?}", path);
+                        }
+                        AddSource::Stdin => {
+                            let package_set = read_package_set(package_set)?;
+                            create_dir_all(path.parent().unwrap())?;
+                            package_set.write(&mut File::create(&path)?)?;
+                            let problems = lint_package_set_file(context.scope(), &path);
+                            if !problems.is_empty() {
+                                remove_file(&path)?;
+                                for problem in &problems {
+                                    eprintln!("{}", problem);
+                                }
+                                return Err(Error::LintFailed(problems.len()));
+                            }
+                            println!("Package set written to {:?}", path);
+                        }
                     }
                 } else {
                     eprintln!(
@@ -77,10 +155,14 @@ impl Action for ManageAction {
             }
             ManageActionKind::Edit => match (direct_path.exists(), indirect_path.exists()) {
                 (true, false) => {
-                    edit_file(&direct_path)?;
+                    edit_file_checked(&direct_path, |path| {
+                        check_package_set_file(context.scope(), path)
+                    })?;
                 }
                 (false, true) => {
-                    edit_file(&indirect_path)?;
+                    edit_file_checked(&indirect_path, |path| {
+                        check_package_set_file(context.scope(), path)
+                    })?;
                 }
                 (true, true) => {
                     eprintln!(
@@ -89,25 +171,68 @@ impl Action for ManageAction {
                         );
                 }
                 (false, false) => {
-                    eprintln!(
-                            "Error: neither the package set file {:?} or {:?} exist, making it hard to edit them",
-                            direct_path, indirect_path
-                        );
+                    return Err(self.no_package_set(context, package_set));
                 }
             },
-            ManageActionKind::Remove => {
+            ManageActionKind::Remove { uninstall } => {
+                if *uninstall && (direct_path.exists() || indirect_path.exists()) {
+                    self.retire_package_set(context, package_set)?;
+                }
                 if direct_path.exists() {
                     debug!("ManageAction::run: removing file {:?}", direct_path);
-                    std::fs::remove_file(direct_path)?;
+                    let readme_path = direct_path.with_extension("md");
+                    if readme_path.is_file() {
+                        remove_file(readme_path)?;
+                    }
+                    remove_file(direct_path)?;
                 } else if indirect_path.exists() {
                     debug!("ManageAction::run: removing file {:?}", indirect_path);
-                    std::fs::remove_file(indirect_path)?;
+                    remove_file(indirect_path)?;
                 } else {
-                    eprintln!(
-                        "Error: neither the package set file {:?}, or {:?} exist, making it hard to remove them",
-                        direct_path, indirect_path
-                    );
+                    return Err(self.no_package_set(context, package_set));
+                }
+            }
+            ManageActionKind::Archive { uninstall, commit } => {
+                if *uninstall && (direct_path.exists() || indirect_path.exists()) {
+                    self.retire_package_set(context, package_set)?;
                 }
+                self.archive_package_set(context, package_set, *commit)?;
+            }
+            ManageActionKind::Rename { new_name, commit } => {
+                self.rename_package_set(context, package_set, new_name, *commit)?;
+            }
+            ManageActionKind::Move { to_group, commit } => {
+                self.move_package_set(context, package_set, to_group, *commit)?;
+            }
+            ManageActionKind::Copy {
+                to_group,
+                new_name,
+                commit,
+            } => {
+                self.copy_package_set(context, package_set, to_group, new_name, *commit)?;
+            }
+            ManageActionKind::AddPackage { package } => {
+                self.update_packages(context, package_set, |builder, package_set| {
+                    if package_set
+                        .packages()
+                        .map(|mut packages| packages.any(|found| found.name() == package.name()))
+                        .unwrap_or_default()
+                    {
+                        Err(Error::PackageExists(
+                            package_set.name().to_string(),
+                            package.name().to_string(),
+                        ))
+                    } else {
+                        let _ = builder.add_package_action(package.as_ref().clone())?;
+                        Ok(())
+                    }
+                })?;
+            }
+            ManageActionKind::RemovePackage { package } => {
+                self.update_packages(context, package_set, |builder, _| {
+                    let _ = builder.remove_package_action(package)?;
+                    Ok(())
+                })?;
             }
         }
         Ok(())
@@ -119,11 +244,33 @@ impl ManageAction {
         group: Name,
         package_set: Name,
         package_set_is_file: bool,
+        interactive: bool,
     ) -> Result<Box<dyn Action>> {
         Ok(Box::new(ManageAction {
-            kind: ManageActionKind::Add,
+            kind: ManageActionKind::Add {
+                source: if interactive {
+                    AddSource::Interactive
+                } else {
+                    AddSource::Template
+                },
+            },
             group,
-            package_set,
+            package_set: Some(package_set),
+            package_set_is_file,
+        }))
+    }
+
+    pub fn add_from_stdin_action(
+        group: Name,
+        package_set: Name,
+        package_set_is_file: bool,
+    ) -> Result<Box<dyn Action>> {
+        Ok(Box::new(ManageAction {
+            kind: ManageActionKind::Add {
+                source: AddSource::Stdin,
+            },
+            group,
+            package_set: Some(package_set),
             package_set_is_file,
         }))
     }
@@ -131,27 +278,603 @@ impl ManageAction {
         Ok(Box::new(ManageAction {
             kind: ManageActionKind::Edit,
             group,
-            package_set,
+            package_set: Some(package_set),
             package_set_is_file: true,
         }))
     }
     pub fn remove_action(group: Name, package_set: Name) -> Result<Box<dyn Action>> {
         Ok(Box::new(ManageAction {
-            kind: ManageActionKind::Remove,
+            kind: ManageActionKind::Remove { uninstall: false },
+            group,
+            package_set: Some(package_set),
+            package_set_is_file: true,
+        }))
+    }
+    pub fn archive_action(group: Name, package_set: Name, commit: bool) -> Result<Box<dyn Action>> {
+        Ok(Box::new(ManageAction {
+            kind: ManageActionKind::Archive {
+                uninstall: false,
+                commit,
+            },
+            group,
+            package_set: Some(package_set),
+            package_set_is_file: true,
+        }))
+    }
+    pub fn retire_action(
+        group: Name,
+        package_set: Name,
+        archive: bool,
+        commit: bool,
+    ) -> Result<Box<dyn Action>> {
+        Ok(Box::new(ManageAction {
+            kind: if archive {
+                ManageActionKind::Archive {
+                    uninstall: true,
+                    commit,
+                }
+            } else {
+                ManageActionKind::Remove { uninstall: true }
+            },
+            group,
+            package_set: Some(package_set),
+            package_set_is_file: true,
+        }))
+    }
+    pub fn rename_action(
+        group: Name,
+        package_set: Option<Name>,
+        new_name: Name,
+        commit: bool,
+    ) -> Result<Box<dyn Action>> {
+        Ok(Box::new(ManageAction {
+            kind: ManageActionKind::Rename { new_name, commit },
             group,
             package_set,
             package_set_is_file: true,
         }))
     }
 
-    fn make_package_set_path(&self, package_set_is_file: bool) -> PathBuf {
-        let group_path = PackageRepository::default_path().join(&self.group.as_path());
+    pub fn move_action(
+        group: Name,
+        package_set: Name,
+        to_group: Name,
+        commit: bool,
+    ) -> Result<Box<dyn Action>> {
+        Ok(Box::new(ManageAction {
+            kind: ManageActionKind::Move { to_group, commit },
+            group,
+            package_set: Some(package_set),
+            package_set_is_file: true,
+        }))
+    }
+
+    pub fn copy_action(
+        group: Name,
+        package_set: Name,
+        to_group: Name,
+        new_name: Name,
+        commit: bool,
+    ) -> Result<Box<dyn Action>> {
+        Ok(Box::new(ManageAction {
+            kind: ManageActionKind::Copy {
+                to_group,
+                new_name,
+                commit,
+            },
+            group,
+            package_set: Some(package_set),
+            package_set_is_file: true,
+        }))
+    }
+
+    pub fn add_package_action(
+        group: Name,
+        package_set: Name,
+        package: Package,
+    ) -> Result<Box<dyn Action>> {
+        Ok(Box::new(ManageAction {
+            kind: ManageActionKind::AddPackage {
+                package: Box::new(package),
+            },
+            group,
+            package_set: Some(package_set),
+            package_set_is_file: true,
+        }))
+    }
+
+    pub fn remove_package_action(
+        group: Name,
+        package_set: Name,
+        package: Name,
+    ) -> Result<Box<dyn Action>> {
+        Ok(Box::new(ManageAction {
+            kind: ManageActionKind::RemovePackage { package },
+            group,
+            package_set: Some(package_set),
+            package_set_is_file: true,
+        }))
+    }
+
+    fn make_package_set_path(
+        &self,
+        context: &ActionContext,
+        package_set: &Name,
+        package_set_is_file: bool,
+    ) -> PathBuf {
+        let group_path = context.repository_path().join(self.group.as_path());
         if package_set_is_file {
-            group_path.join(&format!("{}.yml", self.package_set))
+            group_path.join(format!("{}.yml", package_set))
         } else {
             group_path
-                .join(&self.package_set.as_path())
-                .join("package-set.yml")
+                .join(package_set.as_path())
+                .join(PACKAGE_SET_FILE)
         }
     }
+
+    // The error for a package set not found in this action's group, suggesting similar package
+    // sets; or if the group itself is not found, suggesting similar groups.
+    fn no_package_set(&self, context: &ActionContext, package_set: &Name) -> Error {
+        match LazyPackageSetGroup::read(&context.repository_path().join(self.group.as_path())) {
+            Ok(group) => group.no_package_set(package_set),
+            Err(_) => self.no_group(context),
+        }
+    }
+
+    // The error for this action's group not found, suggesting similar groups.
+    fn no_group(&self, context: &ActionContext) -> Error {
+        let group_names: Vec<Name> = context.group_names().unwrap_or_default();
+        Error::NoPackageSetGroup {
+            name: self.group.to_string(),
+            suggestions: suggest_names(&self.group.to_string(), group_names),
+        }
+    }
+
+    // Read the package set, change it using the builder, and write it back to the same file.
+    fn update_packages<F>(
+        &self,
+        context: &ActionContext,
+        package_set: &Name,
+        update_fn: F,
+    ) -> Result<()>
+    where
+        F: Fn(&mut PackageSetBuilder, &PackageSet) -> Result<()>,
+    {
+        let direct_path = self.make_package_set_path(context, package_set, true);
+        let indirect_path = self.make_package_set_path(context, package_set, false);
+        let set_file = if direct_path.is_file() {
+            direct_path
+        } else if indirect_path.is_file() {
+            indirect_path
+        } else {
+            return Err(self.no_package_set(context, package_set));
+        };
+        let package_set = PackageSet::read(&set_file)?;
+        let mut builder = PackageSetBuilder::from(package_set.clone());
+        update_fn(&mut builder, &package_set)?;
+        builder.build().write(&mut File::create(&set_file)?)?;
+        println!("Package set {:?} updated", set_file);
+        Ok(())
+    }
+
+    fn rename_package_set(
+        &self,
+        context: &ActionContext,
+        package_set: &Name,
+        new_name: &Name,
+        commit: bool,
+    ) -> Result<()> {
+        let direct_path = self.make_package_set_path(context, package_set, true);
+        let indirect_path = self.make_package_set_path(context, package_set, false);
+        let (from_path, to_path, set_file) = if direct_path.is_file() {
+            let to_path = self.make_package_set_path(context, new_name, true);
+            (direct_path, to_path.clone(), to_path)
+        } else if indirect_path.is_file() {
+            let to_path = self.make_package_set_path(context, new_name, false);
+            (
+                indirect_path.parent().unwrap().to_path_buf(),
+                to_path.parent().unwrap().to_path_buf(),
+                to_path,
+            )
+        } else {
+            return Err(self.no_package_set(context, package_set));
+        };
+        if to_path.exists() {
+            return Err(Error::TargetExists(to_path.to_string_lossy().to_string()));
+        }
+
+        // A package set file's README is a file of the same name, it is renamed as well.
+        let readme_paths = (from_path.with_extension("md"), to_path.with_extension("md"));
+        let move_readme = from_path.is_file() && readme_paths.0.is_file();
+        if move_readme && readme_paths.1.exists() {
+            return Err(Error::TargetExists(
+                readme_paths.1.to_string_lossy().to_string(),
+            ));
+        }
+
+        let repository = Repository::open(context.repository_path().clone())?;
+        println!("Renaming {:?} to {:?}", from_path, to_path);
+        move_path(&repository, &from_path, &to_path)?;
+        if move_readme {
+            move_path(&repository, &readme_paths.0, &readme_paths.1)?;
+        }
+        rewrite_name(&set_file, new_name)?;
+
+        if commit {
+            let _ = commit_all(
+                &repository,
+                &format!(
+                    "Renamed package set '{}/{}' to '{}/{}'",
+                    self.group, package_set, self.group, new_name
+                ),
+            )?;
+        }
+        Ok(())
+    }
+
+    fn move_package_set(
+        &self,
+        context: &ActionContext,
+        package_set: &Name,
+        to_group: &Name,
+        commit: bool,
+    ) -> Result<()> {
+        let repository_path = context.repository_path().clone();
+        let to_group_path = repository_path.join(to_group.as_path());
+        let moves = self.package_set_moves(context, package_set, &to_group_path)?;
+
+        let repository = Repository::open(&repository_path)?;
+        for (from_path, to_path) in &moves {
+            println!("Moving {:?} to {:?}", from_path, to_path);
+            move_path(&repository, from_path, to_path)?;
+        }
+
+        if commit {
+            let _ = commit_all(
+                &repository,
+                &format!(
+                    "Moved package set '{}/{}' to group '{}'",
+                    self.group, package_set, to_group
+                ),
+            )?;
+        }
+        Ok(())
+    }
+
+    // Uninstall the packages of the package set installed on this machine, and remove the links to
+    // its files, before it is removed from the repository.
+    fn retire_package_set(&self, context: &ActionContext, package_set: &Name) -> Result<()> {
+        let repository = context.repository()?;
+        let group = repository.group_or_suggest(&self.group)?;
+        let retired = group
+            .package_set(package_set)
+            .ok_or_else(|| self.no_package_set(context, package_set))?;
+        let mut log_db = context.open_install_log()?;
+        let uninstalled =
+            context
+                .registry()?
+                .retire_package_set(group, retired, log_db.as_mut())?;
+        println!(
+            "Uninstalled {} package(s) from package set {}/{}",
+            uninstalled.len(),
+            self.group,
+            retired.name()
+        );
+        Ok(())
+    }
+
+    // Move the package set into the group's directory in the repository's archive, and record
+    // the removal in the install log.
+    fn archive_package_set(
+        &self,
+        context: &ActionContext,
+        package_set: &Name,
+        commit: bool,
+    ) -> Result<()> {
+        let repository_path = context.repository_path().clone();
+        let archive_group_path = repository_path.join(ARCHIVE_DIR).join(self.group.as_path());
+        let direct_path = self.make_package_set_path(context, package_set, true);
+        let indirect_path = self.make_package_set_path(context, package_set, false);
+        let archived = if direct_path.is_file() {
+            PackageSet::read(&direct_path)?
+        } else if indirect_path.is_file() {
+            PackageSet::read(&indirect_path)?
+        } else {
+            return Err(self.no_package_set(context, package_set));
+        };
+        let content_hash = archived.content_hash()?;
+        let moves = self.package_set_moves(context, package_set, &archive_group_path)?;
+
+        let repository = Repository::open(&repository_path)?;
+        for (from_path, to_path) in &moves {
+            println!("Archiving {:?} to {:?}", from_path, to_path);
+            move_path(&repository, from_path, to_path)?;
+        }
+        context.open_install_log()?.log_archived_package_set(
+            &self.group,
+            archived.name(),
+            &content_hash,
+        )?;
+
+        if commit {
+            let _ = commit_all(
+                &repository,
+                &format!("Archived package set '{}/{}'", self.group, package_set),
+            )?;
+        }
+        Ok(())
+    }
+
+    // The files to move, from and to, to move the package set, and any assets only it uses, to
+    // another group directory.
+    fn package_set_moves(
+        &self,
+        context: &ActionContext,
+        package_set: &Name,
+        to_group_path: &Path,
+    ) -> Result<Vec<(PathBuf, PathBuf)>> {
+        let repository_path = context.repository_path().clone();
+        let from_group_path = repository_path.join(self.group.as_path());
+        let direct_path = self.make_package_set_path(context, package_set, true);
+        let indirect_path = self.make_package_set_path(context, package_set, false);
+
+        // A package set directory includes all of its assets, a package set file may refer to
+        // assets in the group directory, these are moved as long as no other package set in the
+        // group also refers to them.
+        let mut moves: Vec<(PathBuf, PathBuf)> = Default::default();
+        if direct_path.is_file() {
+            let group = PackageSetGroup::read(&from_group_path)?;
+            let (moving, staying): (Vec<&PackageSet>, Vec<&PackageSet>) = group
+                .package_sets()
+                .partition(|found| found.path() == &direct_path);
+            let shared: Vec<PathBuf> = staying.into_iter().flat_map(package_set_assets).collect();
+            moves.push((
+                direct_path.clone(),
+                to_group_path.join(direct_path.file_name().unwrap()),
+            ));
+            let readme_path = direct_path.with_extension("md");
+            if readme_path.is_file() {
+                moves.push((
+                    readme_path.clone(),
+                    to_group_path.join(readme_path.file_name().unwrap()),
+                ));
+            }
+            for asset in moving.into_iter().flat_map(package_set_assets) {
+                if shared.contains(&asset) {
+                    warn!(
+                        "ManageAction::move_package_set asset {:?} is shared, not moving",
+                        asset
+                    );
+                } else if from_group_path.join(&asset).exists() {
+                    moves.push((from_group_path.join(&asset), to_group_path.join(&asset)));
+                }
+            }
+        } else if indirect_path.is_file() {
+            moves.push((
+                from_group_path.join(package_set.as_path()),
+                to_group_path.join(package_set.as_path()),
+            ));
+        } else {
+            return Err(self.no_package_set(context, package_set));
+        }
+        for (_, to_path) in &moves {
+            if to_path.exists() {
+                return Err(Error::TargetExists(to_path.to_string_lossy().to_string()));
+            }
+        }
+        Ok(moves)
+    }
+
+    fn copy_package_set(
+        &self,
+        context: &ActionContext,
+        package_set: &Name,
+        to_group: &Name,
+        new_name: &Name,
+        commit: bool,
+    ) -> Result<()> {
+        let repository_path = context.repository_path().clone();
+        let from_group_path = repository_path.join(self.group.as_path());
+        let to_group_path = repository_path.join(to_group.as_path());
+        let direct_path = self.make_package_set_path(context, package_set, true);
+        let indirect_path = self.make_package_set_path(context, package_set, false);
+
+        let set_file = if direct_path.is_file() {
+            let to_path = to_group_path.join(format!("{}.yml", new_name));
+            if to_path.exists() {
+                return Err(Error::TargetExists(to_path.to_string_lossy().to_string()));
+            }
+            println!("Copying {:?} to {:?}", direct_path, to_path);
+            create_dir_all(&to_group_path)?;
+            let _ = copy(&direct_path, &to_path)?;
+            let (from_readme, to_readme) = (
+                direct_path.with_extension("md"),
+                to_path.with_extension("md"),
+            );
+            if from_readme.is_file() && !to_readme.exists() {
+                let _ = copy(&from_readme, &to_readme)?;
+            }
+            // Assets in the group directory are shared in the same group, or copied to a new one.
+            if to_group_path != from_group_path {
+                for asset in package_set_assets(&PackageSet::read(&direct_path)?) {
+                    let (from_asset, to_asset) =
+                        (from_group_path.join(&asset), to_group_path.join(&asset));
+                    if from_asset.is_file() && !to_asset.exists() {
+                        println!("Copying {:?} to {:?}", from_asset, to_asset);
+                        create_dir_all(to_asset.parent().unwrap())?;
+                        let _ = copy(&from_asset, &to_asset)?;
+                    }
+                }
+            }
+            to_path
+        } else if indirect_path.is_file() {
+            let to_path = to_group_path.join(new_name.as_path());
+            if to_path.exists() {
+                return Err(Error::TargetExists(to_path.to_string_lossy().to_string()));
+            }
+            println!(
+                "Copying {:?} to {:?}",
+                indirect_path.parent().unwrap(),
+                to_path
+            );
+            copy_dir(indirect_path.parent().unwrap(), &to_path)?;
+            to_path.join(PACKAGE_SET_FILE)
+        } else {
+            return Err(self.no_package_set(context, package_set));
+        };
+        rewrite_name(&set_file, new_name)?;
+
+        if commit {
+            let repository = Repository::open(&repository_path)?;
+            let _ = commit_all(
+                &repository,
+                &format!(
+                    "Copied package set '{}/{}' to '{}/{}'",
+                    self.group, package_set, to_group, new_name
+                ),
+            )?;
+        }
+        Ok(())
+    }
+
+    fn rename_group(&self, context: &ActionContext, new_name: &Name, commit: bool) -> Result<()> {
+        let repository_path = context.repository_path().clone();
+        let from_path = repository_path.join(self.group.as_path());
+        let to_path = repository_path.join(new_name.as_path());
+        if !from_path.is_dir() {
+            return Err(self.no_group(context));
+        }
+        if to_path.exists() {
+            return Err(Error::TargetExists(to_path.to_string_lossy().to_string()));
+        }
+
+        let repository = Repository::open(&repository_path)?;
+        println!("Renaming {:?} to {:?}", from_path, to_path);
+        move_path(&repository, &from_path, &to_path)?;
+
+        if commit {
+            let _ = commit_all(
+                &repository,
+                &format!(
+                    "Renamed package set group '{}' to '{}'",
+                    self.group, new_name
+                ),
+            )?;
+        }
+        Ok(())
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+// Private Functions
+// ------------------------------------------------------------------------------------------------
+
+fn check_package_set_file(scope: Scope, set_file: &Path) -> Vec<String> {
+    lint_package_set_file(scope, set_file)
+        .iter()
+        .map(|problem| problem.message().clone())
+        .collect()
+}
+
+// Rewrite the `name` field in the package set file, leaving the rest of the file unchanged.
+fn rewrite_name(set_file: &Path, new_name: &Name) -> Result<()> {
+    let content = read_to_string(set_file)?;
+    write(
+        set_file,
+        NAME_FIELD
+            .replace(&content, format!("name: {}", new_name).as_str())
+            .as_ref(),
+    )?;
+    Ok(())
+}
+
+// The paths, relative to the group directory, of any files a package set file refers to.
+fn package_set_assets(package_set: &PackageSet) -> Vec<PathBuf> {
+    let mut assets: Vec<String> = package_set.link_files().keys().cloned().collect();
+    if let Some(env_file) = package_set.env_file() {
+        assets.push(env_file.clone());
+    }
+    for shell_files in package_set.shell_files().values() {
+        assets.extend(shell_files.link_files().keys().cloned());
+        if let Some(env_file) = shell_files.env_file() {
+            assets.push(env_file.clone());
+        }
+    }
+    if let Some(bundle) = package_set.bundle() {
+        assets.push(bundle.file().clone());
+    }
+    assets
+        .into_iter()
+        .filter(|asset| !asset.contains("{{"))
+        .map(PathBuf::from)
+        .filter(|asset| asset.is_relative())
+        .collect()
+}
+
+// Read a package set, as YAML or JSON, from stdin; its name must match the one being added.
+fn read_package_set(name: &Name) -> Result<PackageSet> {
+    let mut content = String::new();
+    let _ = std::io::stdin().read_to_string(&mut content)?;
+    let package_set: PackageSet = serde_yaml::from_str(&content)?;
+    if package_set.name() != name {
+        return Err(Error::PackageSetNameMismatch(
+            name.to_string(),
+            package_set.name().to_string(),
+        ));
+    }
+    Ok(package_set)
+}
+
+// Build a new package set by prompting the user for its details.
+fn build_package_set(name: &Name) -> Result<PackageSet> {
+    let mut builder = PackageSetBuilder::named(name.clone());
+    let description = prompt(
+        "Description",
+        Some(&format!("my new {} package set.", name)),
+    )?;
+    let _ = builder.description(&description);
+    loop {
+        match prompt("Platform (any, macos, linux)", Some("any"))?.as_str() {
+            "any" => break,
+            "macos" => {
+                let _ = builder.for_platform(Platform::Macos);
+                break;
+            }
+            "linux" => {
+                let _ = builder.for_platform(Platform::Linux);
+                break;
+            }
+            _ => println!("Please answer 'any', 'macos', or 'linux'"),
+        }
+    }
+    let kind = loop {
+        match PackageKind::from_str(&prompt(
+            "Package kind (default, application, application:<name>, or a language installer name)",
+            Some("default"),
+        )?) {
+            Ok(kind) => break kind,
+            Err(e) => println!("{}", e),
+        }
+    };
+
+    let _ = builder.with_package_actions();
+    while let Some(package) = prompt_optional("Package name (empty to finish)")? {
+        match Name::from_str(&package) {
+            Ok(package) => {
+                let _ = builder.add_package_action(
+                    PackageBuilder::named(package).of_kind(kind.clone()).build(),
+                )?;
+            }
+            Err(e) => println!("{}", e),
+        }
+    }
+
+    while let Some(repo_file) = prompt_optional("Link file, in the repository (empty to finish)")? {
+        let local_file = prompt(
+            "Link file, local target",
+            Some(&format!("{{{{home}}}}/{}", repo_file)),
+        )?;
+        let _ = builder.add_link_file(&repo_file, &local_file);
+    }
+    Ok(builder.build())
 }
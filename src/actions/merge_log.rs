@@ -0,0 +1,71 @@
+use crate::actions::{Action, ActionContext};
+use crate::error::{Error, Result};
+use crate::shared::InstallLogFormat;
+use std::path::PathBuf;
+
+// ------------------------------------------------------------------------------------------------
+// Public Types
+// ------------------------------------------------------------------------------------------------
+
+///
+/// This action merges the install log from another machine into the local install log, so that
+/// the history of several machines can be reported on together. Entries already in the local log
+/// are skipped, so the same log may be merged again as it grows.
+///
+#[derive(Debug)]
+pub struct MergeLogAction {
+    log_file: PathBuf,
+    machine: Option<String>,
+}
+
+// ------------------------------------------------------------------------------------------------
+// Implementations
+// ------------------------------------------------------------------------------------------------
+
+impl Action for MergeLogAction {
+    fn run(&self, context: &ActionContext) -> Result<()> {
+        info!("MergeLogAction::run {:?}", self);
+        if !self.log_file.is_file() {
+            return Err(Error::InvalidConfigValue(
+                "log-file".to_string(),
+                self.log_file.to_string_lossy().to_string(),
+            ));
+        }
+
+        let local_format = context.install_log_format();
+        let local_path = context.install_log_path();
+        if local_path.is_file() && local_path.canonicalize()? == self.log_file.canonicalize()? {
+            return Err(Error::MergeLogIntoItself(
+                self.log_file.to_string_lossy().to_string(),
+            ));
+        }
+
+        let machine = match &self.machine {
+            Some(machine) => machine.clone(),
+            None => self
+                .log_file
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().to_string())
+                .unwrap_or_default(),
+        };
+        if machine.trim().is_empty() {
+            return Err(Error::InvalidConfigValue("machine".to_string(), machine));
+        }
+
+        let mut other_log =
+            InstallLogFormat::for_path(&self.log_file).open_from(self.log_file.clone())?;
+        let mut log_db = local_format.open_from(local_path)?;
+        let merged = log_db.merge_from(other_log.as_mut(), &machine)?;
+        println!(
+            "Merged {} entries from {:?} into the local install log",
+            merged, self.log_file
+        );
+        Ok(())
+    }
+}
+
+impl MergeLogAction {
+    pub fn new_action(log_file: PathBuf, machine: Option<String>) -> Result<Box<dyn Action>> {
+        Ok(Box::from(MergeLogAction { log_file, machine }))
+    }
+}
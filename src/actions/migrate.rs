@@ -0,0 +1,69 @@
+use crate::actions::{Action, ActionContext};
+use crate::error::Result;
+use crate::shared::migrate::{format_version, pending_migrations};
+use crate::shared::MigrationTarget;
+use crate::shared::Steps;
+
+// ------------------------------------------------------------------------------------------------
+// Public Types
+// ------------------------------------------------------------------------------------------------
+
+///
+/// This action upgrades the package repository, and installer registry, in place from older
+/// format versions to the versions supported by this version of the tool.
+///
+#[derive(Debug)]
+pub struct MigrateAction {
+    dry_run: bool,
+}
+
+// ------------------------------------------------------------------------------------------------
+// Implementations
+// ------------------------------------------------------------------------------------------------
+
+impl Action for MigrateAction {
+    fn run(&self, context: &ActionContext) -> Result<()> {
+        info!("MigrateAction::run {:?}", self);
+        let steps = context.steps("migrate").with_dry_run(self.dry_run);
+        if self.dry_run {
+            println!("Planned migrations, nothing will be changed:");
+        }
+        let result = self.migrate(context, &steps);
+        let _ = steps.finish();
+        result
+    }
+}
+
+impl MigrateAction {
+    pub fn new_action(dry_run: bool) -> Result<Box<dyn Action>> {
+        Ok(Box::from(MigrateAction { dry_run }))
+    }
+
+    fn migrate(&self, context: &ActionContext, steps: &Steps) -> Result<()> {
+        for (target, path) in &[
+            (
+                MigrationTarget::Repository,
+                context.repository_path().clone(),
+            ),
+            (MigrationTarget::Registry, context.registry_path().clone()),
+        ] {
+            let version = format_version(*target, path)?;
+            let pending = pending_migrations(*target, version);
+            if pending.is_empty() {
+                steps.skip(
+                    &format!("Migrating {}", target),
+                    &format!(
+                        "it is up to date, format version {}",
+                        target.current_version()
+                    ),
+                );
+            }
+            for migration in pending {
+                steps.run(&format!("Migrating {}", migration), || {
+                    migration.apply(path)
+                })?;
+            }
+        }
+        Ok(())
+    }
+}
@@ -8,15 +8,16 @@ The following is an example [`Action`](trait.action.html) implementation that do
 use existing actions, or create new ones, see the [User Guide](https://simonkjohnston.life/rust-mcfg/).
 
 ```rust
-use mcfg::actions::Action;
+use mcfg::actions::{Action, ActionContext};
 use mcfg::error::Result;
 
 #[derive(Debug)]
 pub struct ExampleAction {}
 
 impl Action for ExampleAction {
-    fn run(&self) -> Result<()> {
-        println!("ListAction::run {:?}", self);
+    fn run(&self, context: &ActionContext) -> Result<()> {
+        println!("ExampleAction::run {:?}", self);
+        println!("  groups: {}", context.repository()?.groups().count());
         Ok(())
     }
 }
@@ -31,7 +32,13 @@ impl ExampleAction {
 
 */
 
+use crate::actions::env_script::regenerate_context_env_scripts;
 use crate::error::Result;
+use crate::shared::installer::ExecuteSummary;
+use crate::shared::{notify_completion, record_metrics, RunMetrics};
+#[cfg(feature = "webhooks")]
+use crate::shared::{notify_webhooks, RunSummary};
+use std::time::Instant;
 
 // ------------------------------------------------------------------------------------------------
 // Public Types
@@ -41,21 +48,129 @@ use crate::error::Result;
 /// Implemented by the actions exposed by the CLI.
 ///
 pub trait Action: Debug {
-    /// Run this action, this assumes all information was passed to the action during creation.
-    fn run(&self) -> Result<()>;
+    /// Run this action, this assumes all information was passed to the action during creation;
+    /// the shared resources it needs are taken from `context`.
+    fn run(&self, context: &ActionContext) -> Result<()>;
+
+    /// Run this action, as `run` does, returning a report of the work done. By default the
+    /// report only records the time taken, actions that process items override this to report
+    /// them.
+    fn run_with_report(&self, context: &ActionContext) -> Result<ActionReport> {
+        let started = Instant::now();
+        self.run(context)?;
+        Ok(ActionReport::new(started.elapsed()))
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+// Private Functions
+// ------------------------------------------------------------------------------------------------
+
+// Report the completion of a run of the installer action `action_name`, started at `started`;
+// with a desktop notification, to webhooks, and to metrics, as configured. If
+// `regenerate_env_scripts` is `true` the environment scripts are also regenerated, so that they
+// include any packages installed, or removed, by the run.
+pub(crate) fn after_run<T>(
+    context: &ActionContext,
+    action_name: &str,
+    started: Instant,
+    summary: &ExecuteSummary,
+    result: &Result<T>,
+    regenerate_env_scripts: bool,
+) {
+    match context.config() {
+        Ok(config) => {
+            if let Some(notifications) = config.notifications() {
+                notify_completion(
+                    notifications,
+                    context.environment(),
+                    action_name,
+                    started.elapsed(),
+                    result,
+                );
+            }
+            #[cfg(feature = "webhooks")]
+            if !config.webhooks().is_empty() {
+                notify_webhooks(
+                    config.webhooks(),
+                    &RunSummary::new(
+                        &context.machine_id(),
+                        action_name,
+                        started.elapsed(),
+                        summary,
+                        result,
+                    ),
+                );
+            }
+            if let Some(metrics) = config.metrics() {
+                record_metrics(
+                    metrics,
+                    &RunMetrics::new(action_name, started.elapsed(), summary, result),
+                );
+            }
+        }
+        Err(e) => warn!("after_run: could not read configuration, error: {}", e),
+    }
+    if regenerate_env_scripts {
+        regenerate_context_env_scripts(context);
+    }
 }
 
 // ------------------------------------------------------------------------------------------------
 // Modules
 // ------------------------------------------------------------------------------------------------
 
+mod context;
+pub use context::ActionContext;
+
+mod report;
+pub use report::{ActionReport, ReportFormat};
+
+#[doc(hidden)]
+mod approve;
+pub use approve::ApproveAction;
+
+#[doc(hidden)]
+mod audit;
+pub use audit::AuditAction;
+
+#[doc(hidden)]
+mod backup;
+pub use backup::BackupAction;
+
+#[doc(hidden)]
+mod cat;
+pub use cat::{CatAction, CatFormat};
+
+#[doc(hidden)]
+mod drift;
+pub use drift::DriftAction;
+
+#[doc(hidden)]
+mod env;
+pub use env::EnvAction;
+
+#[doc(hidden)]
+mod env_script;
+pub use env_script::EnvScriptAction;
+
+#[doc(hidden)]
+mod fmt;
+pub use fmt::FormatAction;
+
+#[doc(hidden)]
+mod import;
+pub use import::ImportAction;
+
 #[doc(hidden)]
 mod installers;
-pub use installers::EditInstallersAction;
+pub use installers::{
+    EditInstallersAction, EnableInstallerAction, ShowInstallerAction, TestInstallerAction,
+};
 
 #[doc(hidden)]
 mod init;
-pub use init::InitAction;
+pub use init::{InitAction, InitScaffold};
 
 #[doc(hidden)]
 mod history;
@@ -65,14 +180,28 @@ pub use history::HistoryAction;
 mod install;
 pub use install::InstallAction;
 
+#[doc(hidden)]
+mod lint;
+pub use lint::LintAction;
+
 #[doc(hidden)]
 mod list;
 pub use list::ListAction;
 
+#[cfg(feature = "git")]
 #[doc(hidden)]
 mod manage;
+#[cfg(feature = "git")]
 pub use manage::ManageAction;
 
+#[doc(hidden)]
+mod merge_log;
+pub use merge_log::MergeLogAction;
+
+#[doc(hidden)]
+mod migrate;
+pub use migrate::MigrateAction;
+
 #[doc(hidden)]
 mod paths;
 pub use paths::ShowPathsAction;
@@ -83,15 +212,51 @@ mod remove_self;
 #[cfg(feature = "remove-self")]
 pub use remove_self::RemoveSelfAction;
 
+#[cfg(feature = "git")]
+#[doc(hidden)]
+mod push;
+#[cfg(feature = "git")]
+pub use push::PushAction;
+
 #[doc(hidden)]
 mod refresh;
 pub use refresh::RefreshAction;
 
+#[doc(hidden)]
+mod schedule;
+pub use schedule::ScheduleAction;
+
 #[doc(hidden)]
 mod shell;
 pub use shell::ShellAction;
 
+#[doc(hidden)]
+mod show;
+pub use show::ShowAction;
+
+#[doc(hidden)]
+mod stats;
+pub use stats::StatsAction;
+
+#[doc(hidden)]
+mod status;
+pub use status::StatusAction;
+
+#[doc(hidden)]
+mod sync;
+pub use sync::SyncAction;
+
 #[doc(hidden)]
 mod upgrade;
 use std::fmt::Debug;
 pub use upgrade::UpdateSelfAction;
+
+#[doc(hidden)]
+mod vars;
+pub use vars::VarsAction;
+
+#[cfg(feature = "watch")]
+#[doc(hidden)]
+mod watch;
+#[cfg(feature = "watch")]
+pub use watch::WatchAction;
@@ -1,7 +1,6 @@
-use crate::actions::Action;
+use crate::actions::{Action, ActionContext};
 use crate::error::Result;
-use crate::shared::installer::InstallerRegistry;
-use crate::shared::{FileSystemResource, PackageLog, PackageRepository};
+use crate::shared::{FileSystemResource, PackageRepository, PackageSetCache};
 
 // ------------------------------------------------------------------------------------------------
 // Public Types
@@ -19,30 +18,36 @@ pub struct ShowPathsAction {}
 // ------------------------------------------------------------------------------------------------
 
 impl Action for ShowPathsAction {
-    fn run(&self) -> Result<()> {
-        let repository_location = PackageRepository::default_path();
+    fn run(&self, context: &ActionContext) -> Result<()> {
+        let repository_location = context.repository_path().clone();
         println!("Package Repository path:\n\t{:?}", &repository_location);
-        let metadata = std::fs::symlink_metadata(&repository_location)?;
-        let file_type = metadata.file_type();
-        if file_type.is_symlink() {
-            let local_location = std::fs::read_link(repository_location)?;
-            println!("Package Repository symlinked to:\n\t{:?}", &local_location);
+        // The paths are shown even if the repository is not yet initialized.
+        if let Ok(metadata) = std::fs::symlink_metadata(&repository_location) {
+            if metadata.file_type().is_symlink() {
+                let local_location = std::fs::read_link(repository_location)?;
+                println!("Package Repository symlinked to:\n\t{:?}", &local_location);
+            }
         }
         println!(
             "Package Repository config file path:\n\t{:?}",
-            &PackageRepository::default_config_path()
+            &PackageRepository::default_config_path(context.scope())
         );
         println!(
             "Package Repository local file path:\n\t{:?}",
-            &PackageRepository::default_local_path()
+            &PackageRepository::default_local_path(context.scope())
         );
         println!(
             "Installer Registry path:\n\t{:?}",
-            InstallerRegistry::default_path()
+            context.registry_path().clone()
         );
         println!(
             "Package Installer log file path:\n\t{:?}",
-            PackageLog::default_path()
+            context.install_log_path()
+        );
+        println!("Configuration file path:\n\t{:?}", context.config_path());
+        println!(
+            "Package set cache file path:\n\t{:?}",
+            PackageSetCache::default_path(context.scope())
         );
         Ok(())
     }
@@ -0,0 +1,113 @@
+use crate::actions::{Action, ActionContext};
+use crate::error::Result;
+use crate::shared::{commit_all, prompt, remote_callbacks};
+use git2::{DiffFormat, DiffOptions, PushOptions, Repository};
+
+// ------------------------------------------------------------------------------------------------
+// Public Types
+// ------------------------------------------------------------------------------------------------
+
+///
+/// This action will commit any local changes to the package repository and push them to the
+/// remote, basically a Git commit and push.
+///
+#[derive(Debug)]
+pub struct PushAction {
+    message: Option<String>,
+}
+
+// ------------------------------------------------------------------------------------------------
+// Implementations
+// ------------------------------------------------------------------------------------------------
+
+impl Action for PushAction {
+    fn run(&self, context: &ActionContext) -> Result<()> {
+        info!("PushAction::run {:?}", self);
+        let repository = Repository::open(context.repository_path().clone())?;
+
+        if show_changes(&repository)? {
+            let message = match &self.message {
+                Some(message) => message.clone(),
+                None => prompt("Commit message", None)?,
+            };
+            if message.trim().is_empty() {
+                println!("No commit message provided, changes not committed");
+                return Ok(());
+            }
+            commit_changes(&repository, message.trim())?;
+        } else {
+            println!("No changes to commit");
+        }
+
+        push_changes(context, &repository)
+    }
+}
+
+impl PushAction {
+    pub fn new_action(message: Option<String>) -> Result<Box<dyn Action>> {
+        Ok(Box::from(PushAction { message }))
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+// Private Functions
+// ------------------------------------------------------------------------------------------------
+
+fn show_changes(repository: &Repository) -> Result<bool> {
+    let head_tree = match repository.head() {
+        Ok(head) => Some(head.peel_to_tree()?),
+        Err(_) => None,
+    };
+    let mut options = DiffOptions::new();
+    let _ = options
+        .include_untracked(true)
+        .recurse_untracked_dirs(true)
+        .show_untracked_content(true);
+    let diff =
+        repository.diff_tree_to_workdir_with_index(head_tree.as_ref(), Some(&mut options))?;
+    if diff.deltas().len() == 0 {
+        return Ok(false);
+    }
+    diff.print(DiffFormat::Patch, |_, _, line| {
+        if matches!(line.origin(), '+' | '-' | ' ') {
+            print!("{}", line.origin());
+        }
+        print!("{}", String::from_utf8_lossy(line.content()));
+        true
+    })?;
+    Ok(true)
+}
+
+fn commit_changes(repository: &Repository, message: &str) -> Result<()> {
+    let commit = commit_all(repository, message)?;
+    println!("Committed changes as {}", commit);
+    Ok(())
+}
+
+fn push_changes(context: &ActionContext, repository: &Repository) -> Result<()> {
+    let head = repository.head()?;
+    let head_ref = head.name().unwrap();
+    let mut remote = repository.find_remote("origin")?;
+    debug!(
+        "push_changes: pushing {} to {:?}",
+        head_ref,
+        remote.url().unwrap_or_default()
+    );
+    let mut callbacks = remote_callbacks(&context.config()?.git().clone().unwrap_or_default());
+    let _ = callbacks.push_update_reference(|reference, status| match status {
+        None => Ok(()),
+        Some(status) => Err(git2::Error::from_str(&format!(
+            "remote rejected {}: {}",
+            reference, status
+        ))),
+    });
+    let mut options = PushOptions::new();
+    let _ = options.remote_callbacks(callbacks);
+    remote.push(&[format!("{}:{}", head_ref, head_ref)], Some(&mut options))?;
+    println!(
+        "Pushed {} to <{}>",
+        head_ref,
+        remote.url().unwrap_or_default()
+    );
+    Ok(())
+}
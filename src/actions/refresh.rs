@@ -1,14 +1,15 @@
-use crate::actions::Action;
+use crate::actions::{Action, ActionContext};
 use crate::error::Result;
-use crate::shared::{FileSystemResource, PackageRepository};
-use git2::{ErrorClass, ErrorCode, Repository};
+use crate::shared::migrate::{check_format_version, repository_version, MigrationTarget};
+use crate::shared::Steps;
 
 // ------------------------------------------------------------------------------------------------
 // Public Types
 // ------------------------------------------------------------------------------------------------
 
 ///
-/// This action will refresh the package repository, basically a Git pull.
+/// This action will refresh the package repository, basically a Git pull; the repository VCS
+/// is selected by the tool configuration file.
 ///
 #[derive(Debug)]
 pub struct RefreshAction {}
@@ -18,46 +19,12 @@ pub struct RefreshAction {}
 // ------------------------------------------------------------------------------------------------
 
 impl Action for RefreshAction {
-    fn run(&self) -> Result<()> {
-        info!("RefreshAction::run refreshing local git");
-        // TODO: check if it exists!
-        match Repository::open(PackageRepository::default_path()) {
-            Err(e) => {
-                if e.code() == ErrorCode::NotFound && e.class() == ErrorClass::Repository {
-                    debug!("Local dir does not contain a Git repo, ignoring refresh");
-                    Ok(())
-                } else {
-                    Err(e.into())
-                }
-            }
-            Ok(repo) => {
-                let head_ref = repo.head();
-                let head_ref = head_ref.unwrap();
-                let head_ref = head_ref.name().unwrap();
-                debug!("fetching remote reference {}", head_ref);
-
-                repo.find_remote("origin")?.fetch(&[head_ref], None, None)?;
-                // TODO: stop if it is not remote
-
-                let fetch_head = repo.find_reference("FETCH_HEAD")?;
-                let fetch_commit = repo.reference_to_annotated_commit(&fetch_head)?;
-                let analysis = repo.merge_analysis(&[&fetch_commit])?;
-                if analysis.0.is_up_to_date() {
-                    debug!("No remote changes, repository untouched");
-                    Ok(())
-                } else if analysis.0.is_fast_forward() {
-                    debug!("fast-forwarding changes from remote");
-                    let mut reference = repo.find_reference(head_ref)?;
-                    // returns another reference, we can ignore it.
-                    let _ = reference.set_target(fetch_commit.id(), "Fast-Forward")?;
-                    repo.set_head(head_ref)?;
-                    repo.checkout_head(Some(git2::build::CheckoutBuilder::default().force()))?;
-                    Ok(())
-                } else {
-                    panic!("Fast-Forward only");
-                }
-            }
-        }
+    fn run(&self, context: &ActionContext) -> Result<()> {
+        info!("RefreshAction::run refreshing local repository");
+        let steps = context.steps("refresh");
+        let result = refresh_repository(context, &steps);
+        let _ = steps.finish();
+        result.map(|_| ())
     }
 }
 
@@ -66,3 +33,45 @@ impl RefreshAction {
         Ok(Box::from(RefreshAction {}))
     }
 }
+
+// ------------------------------------------------------------------------------------------------
+// Private Functions
+// ------------------------------------------------------------------------------------------------
+
+///
+/// Update the repository from its remote, using the configured repository VCS, returning `true`
+/// if any changes were applied. Each step is reported to `steps`; once changes are applied the
+/// repository format version is checked, as the remote may have been migrated by a newer version
+/// of the tool.
+///
+pub(crate) fn refresh_repository(context: &ActionContext, steps: &Steps) -> Result<bool> {
+    let repository_path = context.repository_path();
+    let vcs = context.open_repo_vcs()?;
+    let name = format!(
+        "Refreshing repository {:?} ({})",
+        repository_path,
+        context.repo_vcs_kind()
+    );
+    if !vcs.is_repository(repository_path) {
+        steps.skip(&name, "it is not a repository");
+        return Ok(false);
+    }
+    let mut changed = false;
+    steps.run(&name, || {
+        changed = vcs.refresh(repository_path)?;
+        Ok(())
+    })?;
+
+    let name = "Checking repository format version";
+    if changed {
+        steps.run(name, || {
+            check_format_version(
+                MigrationTarget::Repository,
+                repository_version(repository_path)?,
+            )
+        })?;
+    } else {
+        steps.skip(name, "no remote changes");
+    }
+    Ok(changed)
+}
@@ -1,7 +1,5 @@
-use crate::actions::Action;
+use crate::actions::{Action, ActionContext};
 use crate::error::Result;
-use crate::shared::installer::InstallerRegistry;
-use crate::shared::{FileSystemResource, PackageLog, PackageRepository};
 use std::fs;
 
 // ------------------------------------------------------------------------------------------------
@@ -23,9 +21,9 @@ pub struct RemoveSelfAction {}
 // ------------------------------------------------------------------------------------------------
 
 impl Action for RemoveSelfAction {
-    fn run(&self) -> Result<()> {
-        let repository_location = PackageRepository::default_path();
-        let metadata = std::fs::symlink_metadata(&repository_location)?;
+    fn run(&self, context: &ActionContext) -> Result<()> {
+        let repository_location = context.repository_path().clone();
+        let metadata = fs::symlink_metadata(&repository_location)?;
         let file_type = metadata.file_type();
         if file_type.is_symlink() {
             println!(
@@ -39,15 +37,21 @@ impl Action for RemoveSelfAction {
 
         println!(
             "Removing Installer Registry file:\n\t{:?}",
-            InstallerRegistry::default_path()
+            context.registry_path().clone()
         );
-        fs::remove_file(InstallerRegistry::default_path())?;
+        fs::remove_file(context.registry_path())?;
 
-        println!(
-            "Removing Package Installer log file:\n\t{:?}",
-            PackageLog::default_path()
-        );
-        fs::remove_file(PackageLog::default_path())?;
+        let log_file = context.install_log_path();
+        println!("Removing Package Installer log file:\n\t{:?}", log_file);
+        fs::remove_file(log_file)?;
+
+        if context.config_path().is_file() {
+            println!(
+                "Removing Configuration file:\n\t{:?}",
+                context.config_path()
+            );
+            fs::remove_file(context.config_path())?;
+        }
         Ok(())
     }
 }
@@ -0,0 +1,175 @@
+use crate::error::Error;
+use crate::shared::installer::ExecuteSummary;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+use std::time::Duration;
+
+// ------------------------------------------------------------------------------------------------
+// Public Types
+// ------------------------------------------------------------------------------------------------
+
+///
+/// The outcome of running an action; the items it processed, any warnings and errors reported
+/// along the way, and how long it took. This allows a tool embedding the actions to use the
+/// results directly, rather than reading the action's output.
+///
+/// An action that fails returns an error rather than a report, errors in the report are those
+/// that did not stop the action; for example, a package set that failed while others continued.
+///
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ActionReport {
+    processed: Vec<String>,
+    warnings: Vec<String>,
+    errors: Vec<String>,
+    duration: Duration,
+}
+
+///
+/// The formats in which the command-line tool can print an `ActionReport`.
+///
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReportFormat {
+    /// A short, human readable, summary.
+    Text,
+    /// A single JSON object.
+    Json,
+}
+
+// ------------------------------------------------------------------------------------------------
+// Implementations
+// ------------------------------------------------------------------------------------------------
+
+impl From<&ExecuteSummary> for ActionReport {
+    fn from(summary: &ExecuteSummary) -> Self {
+        Self {
+            processed: summary.succeeded().clone(),
+            warnings: summary.warnings().clone(),
+            errors: summary
+                .failed()
+                .iter()
+                .map(|package_set| format!("package set {} failed", package_set))
+                .collect(),
+            duration: Default::default(),
+        }
+    }
+}
+
+impl Display for ActionReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Processed {} item(s) in {:.2}s, with {} warning(s) and {} error(s)",
+            self.processed.len(),
+            self.duration.as_secs_f64(),
+            self.warnings.len(),
+            self.errors.len()
+        )?;
+        for item in &self.processed {
+            write!(f, "\n  {}", item)?;
+        }
+        for warning in &self.warnings {
+            write!(f, "\nWarning: {}", warning)?;
+        }
+        for error in &self.errors {
+            write!(f, "\nError: {}", error)?;
+        }
+        Ok(())
+    }
+}
+
+impl ActionReport {
+    /// Create an empty report, for an action that took `duration`.
+    pub fn new(duration: Duration) -> Self {
+        Self {
+            duration,
+            ..Default::default()
+        }
+    }
+
+    /// Set the time taken by the action to `duration`.
+    pub fn with_duration(self, duration: Duration) -> Self {
+        Self { duration, ..self }
+    }
+
+    /// Add `item` to the items processed.
+    pub fn add_processed(&mut self, item: &str) {
+        self.processed.push(item.to_string());
+    }
+
+    /// Add the warning `warning`.
+    pub fn add_warning(&mut self, warning: &str) {
+        self.warnings.push(warning.to_string());
+    }
+
+    /// Add the error `error`.
+    pub fn add_error(&mut self, error: &str) {
+        self.errors.push(error.to_string());
+    }
+
+    /// Return the items processed by the action.
+    pub fn processed(&self) -> &Vec<String> {
+        &self.processed
+    }
+
+    /// Return the warnings reported by the action.
+    pub fn warnings(&self) -> &Vec<String> {
+        &self.warnings
+    }
+
+    /// Return the errors reported by the action, those that did not stop it.
+    pub fn errors(&self) -> &Vec<String> {
+        &self.errors
+    }
+
+    /// Return the time taken by the action.
+    pub fn duration(&self) -> Duration {
+        self.duration
+    }
+
+    /// Returns `true` if the action reported no errors, else `false`.
+    pub fn is_success(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    /// Return the report in `format`.
+    pub fn format(&self, format: ReportFormat) -> String {
+        match format {
+            ReportFormat::Text => self.to_string(),
+            ReportFormat::Json => serde_json::json!({
+                "processed": self.processed,
+                "warnings": self.warnings,
+                "errors": self.errors,
+                "duration_secs": self.duration.as_secs_f64(),
+                "succeeded": self.is_success(),
+            })
+            .to_string(),
+        }
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+
+impl Display for ReportFormat {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Self::Text => "text",
+                Self::Json => "json",
+            }
+        )
+    }
+}
+
+impl FromStr for ReportFormat {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(Self::Text),
+            "json" => Ok(Self::Json),
+            _ => Err(Error::InvalidFormatString(s.to_string())),
+        }
+    }
+}
@@ -0,0 +1,77 @@
+use crate::actions::{Action, ActionContext};
+use crate::error::Result;
+use crate::shared::schedule::{parse_duration, Schedule};
+use crate::shared::InstallActionKind;
+use std::fs;
+use std::time::Duration;
+
+// ------------------------------------------------------------------------------------------------
+// Public Types
+// ------------------------------------------------------------------------------------------------
+
+///
+/// This action creates, or removes, the service manager files that periodically run a refresh
+/// followed by an install or update.
+///
+#[derive(Debug)]
+pub struct ScheduleAction {
+    schedule: Schedule,
+    remove: bool,
+}
+
+// ------------------------------------------------------------------------------------------------
+// Implementations
+// ------------------------------------------------------------------------------------------------
+
+impl Action for ScheduleAction {
+    fn run(&self, context: &ActionContext) -> Result<()> {
+        info!("ScheduleAction::run {:?}", self);
+        let schedule = self.schedule.clone().with_scope(context.scope());
+        if self.remove {
+            println!(
+                "Disable the schedule before removing files with:\n\t{}",
+                schedule.disable_command()
+            );
+            for file in schedule.files() {
+                if file.path().is_file() {
+                    println!("Removing schedule file {:?}", file.path());
+                    fs::remove_file(file.path())?;
+                } else {
+                    warn!("ScheduleAction::run file {:?} does not exist", file.path());
+                }
+            }
+        } else {
+            for file in schedule.files() {
+                println!("Writing schedule file {:?}", file.path());
+                fs::create_dir_all(file.path().parent().unwrap())?;
+                fs::write(file.path(), file.content())?;
+            }
+            println!(
+                "Output from scheduled runs will be appended to {:?}",
+                schedule.output_path()
+            );
+            println!("Enable the schedule with:\n\t{}", schedule.enable_command());
+        }
+        Ok(())
+    }
+}
+
+impl ScheduleAction {
+    pub fn new_action(action: InstallActionKind, every: Option<String>) -> Result<Box<dyn Action>> {
+        let every = match every {
+            None => Duration::from_secs(24 * 60 * 60),
+            Some(every) => parse_duration(&every)?,
+        };
+        Ok(Box::from(ScheduleAction {
+            schedule: Schedule::new(action, every, std::env::current_exe()?)?,
+            remove: false,
+        }))
+    }
+
+    pub fn remove_action(action: InstallActionKind) -> Result<Box<dyn Action>> {
+        Ok(Box::from(ScheduleAction {
+            schedule: Schedule::new(action, Default::default(), std::env::current_exe()?)?,
+            remove: true,
+        }))
+    }
+}
@@ -1,13 +1,16 @@
-use crate::actions::Action;
+use crate::actions::{Action, ActionContext};
 use crate::error::Result;
-use crate::shared::{execute_interactive_shell, FileSystemResource, PackageRepository};
+use crate::shared::builders::{Builder, ShellCommandBuilder};
+use crate::shared::env::vars_to_env_vars;
+use crate::shared::CaptureMode;
+use crate::APP_NAME;
 
 // ------------------------------------------------------------------------------------------------
 // Public Types
 // ------------------------------------------------------------------------------------------------
 
 ///
-/// This action will refresh the package repository, basically a Git pull.
+/// This action will start an interactive shell in the package repository directory.
 ///
 #[derive(Debug)]
 pub struct ShellAction {
@@ -19,9 +22,18 @@ pub struct ShellAction {
 // ------------------------------------------------------------------------------------------------
 
 impl Action for ShellAction {
-    fn run(&self) -> Result<()> {
+    fn run(&self, context: &ActionContext) -> Result<()> {
         info!("ShellAction::run");
-        execute_interactive_shell(PackageRepository::default_path())?;
+        let _ = ShellCommandBuilder::command_line(&self.shell)?
+            .envs(&vars_to_env_vars(
+                context.environment(),
+                &APP_NAME.to_uppercase(),
+            ))
+            .current_dir(context.repository_path())
+            .capture(CaptureMode::Inherit)
+            .apply_environment_policy(false)
+            .build()
+            .execute()?;
         Ok(())
     }
 }
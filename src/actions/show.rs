@@ -0,0 +1,129 @@
+use crate::actions::{Action, ActionContext};
+use crate::error::Result;
+use crate::shared::packages::{PackageSet, PackageSetGroup};
+use crate::shared::{render_markdown, Name};
+use std::fs::read_to_string;
+use std::io::IsTerminal;
+use std::path::Path;
+
+// ------------------------------------------------------------------------------------------------
+// Public Types
+// ------------------------------------------------------------------------------------------------
+
+///
+/// This action shows a package set group, or a package set, with the content of its README file
+/// if it has one. The README is rendered for the terminal, or printed as written.
+///
+#[derive(Debug)]
+pub struct ShowAction {
+    group: Name,
+    package_set: Option<Name>,
+    plain: bool,
+}
+
+// ------------------------------------------------------------------------------------------------
+// Public Functions
+// ------------------------------------------------------------------------------------------------
+
+///
+/// Print the README file, rendered for the terminal unless `plain` is `true`, with each line
+/// indented by `indent`.
+///
+pub(crate) fn print_readme(readme_path: &Path, plain: bool, indent: &str) -> Result<()> {
+    let content = read_to_string(readme_path)?;
+    let lines: Vec<String> = if plain {
+        content.lines().map(str::to_string).collect()
+    } else {
+        render_markdown(&content, std::io::stdout().is_terminal())
+    };
+    for line in lines {
+        if line.is_empty() {
+            println!();
+        } else {
+            println!("{}{}", indent, line);
+        }
+    }
+    Ok(())
+}
+
+// ------------------------------------------------------------------------------------------------
+// Implementations
+// ------------------------------------------------------------------------------------------------
+
+impl Action for ShowAction {
+    fn run(&self, context: &ActionContext) -> Result<()> {
+        info!("ShowAction::run {:?}", self);
+        match &self.package_set {
+            None => {
+                let repository = context.repository()?;
+                self.show_group(repository.group_or_suggest(&self.group)?)
+            }
+            Some(package_set) => {
+                let package_set = context.package_set(&self.group, package_set)?;
+                self.show_package_set(&package_set)
+            }
+        }
+    }
+}
+
+impl ShowAction {
+    pub fn new_action(
+        group: Name,
+        package_set: Option<Name>,
+        plain: bool,
+    ) -> Result<Box<dyn Action>> {
+        Ok(Box::from(ShowAction {
+            group,
+            package_set,
+            plain,
+        }))
+    }
+
+    fn show_group(&self, group: &PackageSetGroup) -> Result<()> {
+        println!("Group:        {} ({})", group.display_name(), group.name());
+        if let Some(description) = group.description() {
+            println!("Description:  {}", description);
+        }
+        println!("Path:         {:?}", group.path());
+        println!(
+            "Package sets: {}",
+            group
+                .package_sets()
+                .map(|package_set| package_set.name().to_string())
+                .collect::<Vec<String>>()
+                .join(", ")
+        );
+        self.show_readme(group.readme_path().as_deref())
+    }
+
+    fn show_package_set(&self, package_set: &PackageSet) -> Result<()> {
+        match package_set.display_name() {
+            None => println!("Package set:  {}/{}", self.group, package_set.name()),
+            Some(display_name) => println!(
+                "Package set:  {} ({}/{})",
+                display_name,
+                self.group,
+                package_set.name()
+            ),
+        }
+        if let Some(description) = package_set.description() {
+            println!("Description:  {}", description);
+        }
+        println!("Path:         {:?}", package_set.path());
+        println!("Platform:     {:?}", package_set.platform());
+        println!("Optional:     {}", package_set.is_optional());
+        let tags: Vec<String> = package_set.tags().map(|tag| tag.to_string()).collect();
+        if !tags.is_empty() {
+            println!("Tags:         {}", tags.join(", "));
+        }
+        self.show_readme(package_set.readme_path().as_deref())
+    }
+
+    fn show_readme(&self, readme_path: Option<&Path>) -> Result<()> {
+        if let Some(readme_path) = readme_path {
+            println!();
+            print_readme(readme_path, self.plain, "")?;
+        }
+        Ok(())
+    }
+}
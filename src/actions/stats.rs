@@ -0,0 +1,137 @@
+use crate::actions::{Action, ActionContext};
+use crate::error::Result;
+use crate::shared::install_log::duration_to_string;
+use crate::shared::LogStatistics;
+use prettytable::Table;
+
+// ------------------------------------------------------------------------------------------------
+// Public Types
+// ------------------------------------------------------------------------------------------------
+
+///
+/// This action displays statistics aggregated from the log file; the packages acted upon by each
+/// installer, the package sets most recently updated, the packages never updated, the busiest
+/// days, and the packages that took the most time to act upon.
+///
+#[derive(Debug)]
+pub struct StatsAction {
+    limit: u32,
+}
+
+// ------------------------------------------------------------------------------------------------
+// Implementations
+// ------------------------------------------------------------------------------------------------
+
+impl Action for StatsAction {
+    fn run(&self, context: &ActionContext) -> Result<()> {
+        info!("StatsAction::run {:?}", self);
+
+        let mut log_db = context.open_install_log()?;
+        let statistics = LogStatistics::from_log(log_db.as_mut(), self.limit)?;
+
+        if statistics.is_empty() {
+            println!("No recorded install history.");
+            return Ok(());
+        }
+
+        if !statistics.installers().is_empty() {
+            println!("Installers:");
+            let mut table = Table::new();
+            table.set_titles(row!["Installer", "Count", "Packages", "Average"]);
+            for installer in statistics.installers() {
+                let _ = table.add_row(row![
+                    installer.installer_name(),
+                    r->installer.count(),
+                    r->installer.packages(),
+                    r->installer.average().map(duration_to_string).unwrap_or_default()
+                ]);
+            }
+            let _ = table.printstd();
+        }
+
+        if !statistics.recently_updated().is_empty() {
+            println!("Most recently updated package sets:");
+            let mut table = Table::new();
+            table.set_titles(row!["Date", "Group", "Set"]);
+            for applied in statistics.recently_updated() {
+                let _ = table.add_row(row![
+                    applied.date_time_str(),
+                    applied.package_set_group_name(),
+                    applied.package_set_name()
+                ]);
+            }
+            let _ = table.printstd();
+        }
+
+        if !statistics.never_updated().is_empty() {
+            println!("Packages never updated:");
+            let mut table = Table::new();
+            table.set_titles(row!["Installed", "Group", "Set", "Package", "Installer"]);
+            for installed in statistics.never_updated() {
+                let _ = table.add_row(row![
+                    installed.date_time_str(),
+                    installed.package_set_group_name(),
+                    installed.package_set_name(),
+                    installed.package_name(),
+                    installed.installer_name()
+                ]);
+            }
+            let _ = table.printstd();
+        }
+
+        if !statistics.busiest_days().is_empty() {
+            println!("Busiest days:");
+            let mut table = Table::new();
+            table.set_titles(row!["Date", "Packages"]);
+            for day in statistics.busiest_days() {
+                let _ = table.add_row(row![day.date().format("%Y-%m-%d"), r->day.count()]);
+            }
+            let _ = table.printstd();
+        }
+
+        if !statistics.slowest_packages().is_empty() {
+            println!("Slowest packages:");
+            let mut table = Table::new();
+            table.set_titles(row![
+                "Group",
+                "Set",
+                "Package",
+                "Installer",
+                "Count",
+                "Total",
+                "Average",
+                "Longest"
+            ]);
+            for db_row in statistics.slowest_packages() {
+                let _ = table.add_row(row![
+                    db_row.package_set_group_name(),
+                    db_row.package_set_name(),
+                    db_row.package_name(),
+                    db_row.installer_name(),
+                    r->db_row.count(),
+                    r->duration_to_string(*db_row.total()),
+                    r->duration_to_string(db_row.average()),
+                    r->duration_to_string(*db_row.longest())
+                ]);
+            }
+            let _ = table.printstd();
+        }
+
+        Ok(())
+    }
+}
+
+impl StatsAction {
+    pub fn new_action(limit: Option<u32>) -> Result<Box<dyn Action>> {
+        Ok(Box::from(StatsAction {
+            limit: limit.unwrap_or(DEFAULT_STATS_LIMIT),
+        }))
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+// Private Functions
+// ------------------------------------------------------------------------------------------------
+
+// The number of rows shown in each table, if not specified.
+const DEFAULT_STATS_LIMIT: u32 = 10;
@@ -0,0 +1,110 @@
+use crate::actions::{Action, ActionContext};
+use crate::error::Result;
+use crate::shared::packages::PackageSetGroup;
+use crate::shared::{InstallerRegistry, Name};
+use prettytable::Table;
+
+// ------------------------------------------------------------------------------------------------
+// Public Types
+// ------------------------------------------------------------------------------------------------
+
+///
+/// This action displays, in a table, the installed version of each package for the current
+/// platform, flagging any that violate the package's version constraint.
+///
+#[derive(Debug)]
+pub struct StatusAction {
+    group: Option<Name>,
+}
+
+// ------------------------------------------------------------------------------------------------
+// Implementations
+// ------------------------------------------------------------------------------------------------
+
+impl Action for StatusAction {
+    fn run(&self, context: &ActionContext) -> Result<()> {
+        info!("StatusAction::run {:?}", self);
+        let package_repository = context.repository()?;
+        let registry = context.registry()?;
+
+        let mut table = Table::new();
+        table.set_titles(row![
+            "Group",
+            "Set",
+            "Package",
+            "Installer",
+            "Installed",
+            "Constraint",
+            "Status"
+        ]);
+        let mut violations = 0;
+        match &self.group {
+            None => {
+                for group in package_repository.groups() {
+                    violations += status_group(registry, group, &mut table);
+                }
+            }
+            Some(group) => {
+                let found = package_repository.group_or_suggest(group)?;
+                violations += status_group(registry, found, &mut table);
+            }
+        }
+
+        if table.is_empty() {
+            println!("No packages found in repository");
+        } else {
+            let _ = table.printstd();
+            if violations > 0 {
+                println!(
+                    "{} package(s) violate their version constraint.",
+                    violations
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+impl StatusAction {
+    pub fn new_action(group: Option<Name>) -> Result<Box<dyn Action>> {
+        Ok(Box::from(StatusAction { group }))
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+// Private Functions
+// ------------------------------------------------------------------------------------------------
+
+fn status_group(registry: &InstallerRegistry, group: &PackageSetGroup, table: &mut Table) -> u32 {
+    let mut violations = 0;
+    for package_set in group.package_sets().filter(|set| set.is_platform_match()) {
+        if let Some(packages) = package_set.packages() {
+            for package in packages.filter(|package| package.is_platform_match()) {
+                let status = registry.package_status(&group.name(), package_set, package);
+                if status.is_violation() {
+                    violations += 1;
+                }
+                let _ = table.add_row(row![
+                    group.name(),
+                    package_set.name(),
+                    package.name(),
+                    registry
+                        .installer_name_for(package)
+                        .map(|name| name.to_string())
+                        .unwrap_or_default(),
+                    status
+                        .version()
+                        .map(|version| version.to_string())
+                        .unwrap_or_default(),
+                    package
+                        .version()
+                        .as_ref()
+                        .map(|constraint| constraint.to_string())
+                        .unwrap_or_default(),
+                    status
+                ]);
+            }
+        }
+    }
+    violations
+}
@@ -0,0 +1,112 @@
+use crate::actions::refresh::refresh_repository;
+use crate::actions::{after_run, Action, ActionContext, ActionReport};
+use crate::error::Result;
+use crate::shared::install_log::duration_to_string;
+use crate::shared::installer::{ExecuteSummary, InstallActionKind, InstallerRegistry};
+use crate::shared::PackageSetSelection;
+use std::time::Instant;
+
+// ------------------------------------------------------------------------------------------------
+// Public Types
+// ------------------------------------------------------------------------------------------------
+
+///
+/// This action performs, in one pass, a refresh of the package repository, an update of all
+/// installers that support update-self, and then an update of all package sets.
+///
+#[derive(Debug)]
+pub struct SyncAction {}
+
+// ------------------------------------------------------------------------------------------------
+// Private Types
+// ------------------------------------------------------------------------------------------------
+
+#[derive(Debug, Default)]
+struct SyncSummary {
+    refreshed: bool,
+    installers: u32,
+    execute: ExecuteSummary,
+}
+
+// ------------------------------------------------------------------------------------------------
+// Implementations
+// ------------------------------------------------------------------------------------------------
+
+impl Action for SyncAction {
+    fn run(&self, context: &ActionContext) -> Result<()> {
+        self.run_with_report(context).map(|_| ())
+    }
+
+    fn run_with_report(&self, context: &ActionContext) -> Result<ActionReport> {
+        info!("SyncAction::run");
+        // The configuration is read before the run, so that it fails early if invalid.
+        let _ = context.config()?;
+        let installer_registry = context.registry()?;
+        let started = Instant::now();
+        let result = self.sync(context, installer_registry);
+        after_run(
+            context,
+            "sync",
+            started,
+            &installer_registry.last_summary(),
+            &result,
+            true,
+        );
+        let summary = result?;
+
+        println!(
+            "Repository {}.",
+            if summary.refreshed {
+                "updated from remote"
+            } else {
+                "already up to date"
+            }
+        );
+        println!("Installers updated: {}", summary.installers);
+        println!("Package sets updated: {}", summary.execute.package_sets());
+        println!("Packages updated: {}", summary.execute.packages());
+        println!("Done, in {}.", duration_to_string(started.elapsed()));
+        Ok(ActionReport::from(&summary.execute).with_duration(started.elapsed()))
+    }
+}
+
+impl SyncAction {
+    pub fn new_action() -> Result<Box<dyn Action>> {
+        Ok(Box::from(SyncAction {}))
+    }
+
+    fn sync(
+        &self,
+        context: &ActionContext,
+        installer_registry: &InstallerRegistry,
+    ) -> Result<SyncSummary> {
+        let mut summary = SyncSummary::default();
+
+        context.reporter().message("Refreshing repository");
+        summary.refreshed = refresh_repository(context, &context.steps("refresh"))?;
+
+        // Note that the repository is only opened after the refresh, so any changes are included.
+        let repository = context.repository()?;
+        summary.installers = installer_registry.update_installers()?;
+
+        if repository.is_empty() {
+            context
+                .reporter()
+                .message("No package sets found in repository");
+        } else {
+            let mut log_db = context.open_install_log()?;
+            log_db.begin()?;
+            let result = installer_registry.execute_logged(
+                &InstallActionKind::Update,
+                repository,
+                &PackageSetSelection::all(),
+                &Default::default(),
+                log_db.as_mut(),
+            );
+            // Packages already updated are logged, even if a later one fails.
+            log_db.commit()?;
+            summary.execute = result?;
+        }
+        Ok(summary)
+    }
+}
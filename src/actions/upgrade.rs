@@ -1,7 +1,7 @@
-use crate::actions::Action;
+use crate::actions::{Action, ActionContext};
 use crate::error::Result;
-use crate::shared::installer::InstallerRegistry;
-use crate::shared::FileSystemResource;
+use crate::shared::notify_completion;
+use std::time::Instant;
 
 // ------------------------------------------------------------------------------------------------
 // Public Types
@@ -18,9 +18,21 @@ pub struct UpdateSelfAction {}
 // ------------------------------------------------------------------------------------------------
 
 impl Action for UpdateSelfAction {
-    fn run(&self) -> Result<()> {
-        let installer_registry = InstallerRegistry::open()?;
-        installer_registry.update_self()?;
+    fn run(&self, context: &ActionContext) -> Result<()> {
+        let config = context.config()?;
+        let installer_registry = context.registry()?;
+        let started = Instant::now();
+        let result = installer_registry.update_self();
+        if let Some(notifications) = config.notifications() {
+            notify_completion(
+                notifications,
+                context.environment(),
+                "update-self",
+                started.elapsed(),
+                &result,
+            );
+        }
+        result?;
         Ok(())
     }
 }
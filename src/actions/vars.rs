@@ -0,0 +1,79 @@
+use crate::actions::{Action, ActionContext};
+use crate::error::{Error, Result};
+use crate::shared::{execution_vars, InstallActionKind, Name};
+use prettytable::Table;
+use std::collections::BTreeMap;
+
+// ------------------------------------------------------------------------------------------------
+// Public Types
+// ------------------------------------------------------------------------------------------------
+
+///
+/// This action displays the variables, and their values, that installer commands and scripts see
+/// when an action is performed on a package set, or a single package within it.
+///
+#[derive(Debug)]
+pub struct VarsAction {
+    action: InstallActionKind,
+    group: Name,
+    package_set: Name,
+    package: Option<Name>,
+    json: bool,
+}
+
+// ------------------------------------------------------------------------------------------------
+// Implementations
+// ------------------------------------------------------------------------------------------------
+
+impl Action for VarsAction {
+    fn run(&self, context: &ActionContext) -> Result<()> {
+        info!("VarsAction::run {:?}", self);
+        let package_set = context.package_set(&self.group, &self.package_set)?;
+        let package = match &self.package {
+            None => None,
+            Some(name) => Some(
+                package_set
+                    .packages()
+                    .and_then(|mut packages| packages.find(|package| package.name() == name))
+                    .ok_or_else(|| {
+                        Error::NoPackage(self.package_set.to_string(), name.to_string())
+                    })?,
+            ),
+        };
+
+        let vars: BTreeMap<String, String> =
+            execution_vars(context.environment(), &self.action, &package_set, package)
+                .into_iter()
+                .collect();
+
+        if self.json {
+            println!("{}", serde_json::to_string_pretty(&vars)?);
+        } else {
+            let mut table = Table::new();
+            table.set_titles(row!["Variable", "Value"]);
+            for (name, value) in vars {
+                let _ = table.add_row(row![name, value]);
+            }
+            let _ = table.printstd();
+        }
+        Ok(())
+    }
+}
+
+impl VarsAction {
+    pub fn new_action(
+        action: InstallActionKind,
+        group: Name,
+        package_set: Name,
+        package: Option<Name>,
+        json: bool,
+    ) -> Result<Box<dyn Action>> {
+        Ok(Box::from(VarsAction {
+            action,
+            group,
+            package_set,
+            package,
+            json,
+        }))
+    }
+}
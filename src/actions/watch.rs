@@ -0,0 +1,169 @@
+use crate::actions::{Action, ActionContext, InstallAction};
+use crate::error::{Error, Result};
+use crate::shared::packages::{is_reserved_name, Readable, GROUP_FILE, PACKAGE_SET_FILE};
+use crate::shared::{
+    install_interrupt_handler, is_interrupted, lint_package_set_file, ExecuteOptions,
+    InstallActionKind, Name, NamePattern, PackageSet, PackageSetSelection,
+};
+use notify::{watcher, DebouncedEvent, RecursiveMode, Watcher};
+use std::ffi::OsStr;
+use std::path::{Component, Path, PathBuf};
+use std::str::FromStr;
+use std::sync::mpsc::{channel, RecvTimeoutError};
+use std::time::Duration;
+
+// ------------------------------------------------------------------------------------------------
+// Public Types
+// ------------------------------------------------------------------------------------------------
+
+///
+/// This action watches the local repository for changes to package set files, checking each
+/// changed package set and, optionally, applying it with the install or link-files action. It
+/// runs until interrupted.
+///
+#[derive(Debug)]
+pub struct WatchAction {
+    apply: Option<InstallActionKind>,
+}
+
+// ------------------------------------------------------------------------------------------------
+// Implementations
+// ------------------------------------------------------------------------------------------------
+
+impl Action for WatchAction {
+    fn run(&self, context: &ActionContext) -> Result<()> {
+        info!("WatchAction::run {:?}", self);
+        let repository_path = context.repository_path().clone();
+        install_interrupt_handler();
+
+        let (sender, receiver) = channel();
+        let mut watcher = watcher(sender, Duration::from_millis(WATCH_DELAY_MILLIS))?;
+        watcher.watch(&repository_path, RecursiveMode::Recursive)?;
+        println!(
+            "Watching {:?} for changes to package sets, press Ctrl-C to stop",
+            repository_path
+        );
+
+        loop {
+            match receiver.recv_timeout(Duration::from_millis(WATCH_DELAY_MILLIS)) {
+                Ok(event) => {
+                    if let Some(path) = changed_path(event) {
+                        if let Some(group) = package_set_group(&repository_path, &path) {
+                            self.changed(context, group, &path)?;
+                        }
+                    }
+                }
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => break,
+            }
+            if is_interrupted() {
+                break;
+            }
+        }
+        println!("Stopped watching");
+        Ok(())
+    }
+}
+
+impl WatchAction {
+    pub fn new_action(apply: Option<InstallActionKind>) -> Result<Box<dyn Action>> {
+        match apply {
+            None | Some(InstallActionKind::Install) | Some(InstallActionKind::LinkFiles) => {
+                Ok(Box::from(WatchAction { apply }))
+            }
+            Some(action) => Err(Error::InvalidConfigValue(
+                "apply".to_string(),
+                action.to_string(),
+            )),
+        }
+    }
+
+    // Lint the changed package set file, and if it has no problems apply it. Only an interrupt
+    // stops the watch, any other failure is reported and the watch continues.
+    fn changed(&self, context: &ActionContext, group: Name, path: &PathBuf) -> Result<()> {
+        println!("Changed {:?}", path);
+        let problems = lint_package_set_file(context.scope(), path);
+        if !problems.is_empty() {
+            for problem in &problems {
+                println!("{}", problem);
+            }
+            return Ok(());
+        }
+        println!("No problems found in package set");
+
+        if let Some(action) = &self.apply {
+            let result = PackageSet::read(path)
+                .and_then(|package_set| {
+                    NamePattern::from_str(&format!("{}/{}", group, package_set.name()))
+                })
+                .and_then(|pattern| {
+                    let selection = PackageSetSelection::new(Vec::default(), vec![pattern]);
+                    match action {
+                        InstallActionKind::LinkFiles => InstallAction::link_files_action(selection),
+                        _ => InstallAction::install_action(selection, ExecuteOptions::default()),
+                    }
+                })
+                .and_then(|action| action.run(context));
+            match result {
+                Err(e) if matches!(e, Error::Interrupted) => return Err(e),
+                Err(e) => println!("Failed to {} package set, error: {}", action, e),
+                Ok(_) => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+// Private Types
+// ------------------------------------------------------------------------------------------------
+
+// The time to wait for further changes before a change is reported, so that an editor saving a
+// file in several steps results in a single change.
+const WATCH_DELAY_MILLIS: u64 = 500;
+
+// ------------------------------------------------------------------------------------------------
+// Private Functions
+// ------------------------------------------------------------------------------------------------
+
+fn changed_path(event: DebouncedEvent) -> Option<PathBuf> {
+    debug!("changed_path: event {:?}", event);
+    match event {
+        DebouncedEvent::Create(path) | DebouncedEvent::Write(path) => Some(path),
+        DebouncedEvent::Rename(_, path) => Some(path),
+        DebouncedEvent::Error(e, path) => {
+            warn!("changed_path: watch error {}, path: {:?}", e, path);
+            None
+        }
+        _ => None,
+    }
+}
+
+// If `path` is a package set file in the repository, either `<group>/<name>.yml` or
+// `<group>/<name>/package-set.yml`, return the name of its group.
+fn package_set_group(repository_path: &Path, path: &Path) -> Option<Name> {
+    if !path.is_file() {
+        return None;
+    }
+    let relative = path.strip_prefix(repository_path).ok()?;
+    let components: Vec<&OsStr> = relative
+        .components()
+        .filter_map(|component| match component {
+            Component::Normal(name) => Some(name),
+            _ => None,
+        })
+        .collect();
+    let is_package_set = match components.as_slice() {
+        [_, file_name] => {
+            *file_name != OsStr::new(GROUP_FILE) && path.extension() == Some(OsStr::new("yml"))
+        }
+        [_, _, file_name] => *file_name == OsStr::new(PACKAGE_SET_FILE),
+        _ => false,
+    };
+    let group = components.first()?.to_str()?;
+    if is_package_set && !is_reserved_name(group) {
+        Name::from_str(group).ok()
+    } else {
+        None
+    }
+}
@@ -1,9 +1,16 @@
 use mcfg::actions::*;
-use mcfg::error::Result;
-use mcfg::shared::{user_shell, FileSystemResource, InstallerRegistry, Name, PackageRepository};
+use mcfg::error::{Error, Result};
+use mcfg::reporter::{CiFormat, Reporter};
+use mcfg::shared::builders::{Builder, PackageBuilder};
+use mcfg::shared::{
+    check_scope_privileges, is_root, set_environment_policy, set_ignore_case, user_shell, Config,
+    ExecuteOptions, FileSystemResource, InstallActionKind, InstallerRegistry, Name, NamePattern,
+    PackageKind, PackageRepository, PackageSetSelection, Platform, Scope, INTERRUPTED_EXIT_CODE,
+};
 use mcfg::APP_NAME;
 use std::convert::TryInto;
-use std::error::Error;
+use std::path::PathBuf;
+use std::str::FromStr;
 use structopt::StructOpt;
 
 // ------------------------------------------------------------------------------------------------
@@ -17,6 +24,30 @@ pub struct CommandLine {
     #[structopt(long, short = "v", parse(from_occurrences))]
     verbose: i8,
 
+    /// Configure the machine for all users, using /etc/mcfg and /var/lib/mcfg; requires root
+    #[structopt(long)]
+    system: bool,
+
+    /// Allow changes when running as root, without '--system' this configures root's home
+    #[structopt(long)]
+    allow_root: bool,
+
+    /// Show the output of installer commands and scripts, each line prefixed with its package
+    #[structopt(long)]
+    show_output: bool,
+
+    /// Annotate output for a CI system, grouping each package-set and surfacing failures
+    #[structopt(long, possible_values = &["github"])]
+    ci: Option<CiFormat>,
+
+    /// Match group and package-set names ignoring case
+    #[structopt(long)]
+    ignore_case: bool,
+
+    /// Print a report of the work done once the command completes, as 'text' or 'json'
+    #[structopt(long, possible_values = &["text", "json"])]
+    report: Option<ReportFormat>,
+
     #[structopt(subcommand)]
     sub_command: SubCommands,
 }
@@ -31,59 +62,268 @@ pub enum SubCommands {
         /// The URL to an existing repository to clone for this machine
         #[structopt(long, short)]
         repository_url: Option<String>,
+        /// A starter template for a new repository, either a built-in template name (default,
+        /// minimal, platforms) or the URL of a Git repository to copy
+        #[structopt(long, short, conflicts_with = "repository_url")]
+        template: Option<String>,
+        /// Add a '.gitignore' file that ignores machine-local state
+        #[structopt(long)]
+        git_ignore: bool,
+        /// Add a 'README.md' file describing the repository layout
+        #[structopt(long)]
+        readme: bool,
+        /// Add a Git pre-commit hook that runs the lint command
+        #[structopt(long)]
+        pre_commit_hook: bool,
+        /// Stage all files in the new repository and make an initial commit
+        #[structopt(long, short, conflicts_with = "repository_url")]
+        commit: bool,
+        /// The URL to set as the 'origin' remote of the new repository
+        #[structopt(long, short, conflicts_with = "repository_url")]
+        origin_url: Option<String>,
+        /// Adopt an existing directory of package sets, the local directory if set, as the
+        /// repository; no repository is created or cloned, and no examples are added
+        #[structopt(
+            long,
+            conflicts_with_all = &[
+                "repository_url",
+                "template",
+                "git_ignore",
+                "readme",
+                "pre_commit_hook",
+                "commit",
+                "origin_url"
+            ]
+        )]
+        adopt: bool,
+        /// Only show the steps initialization would take, do not perform them
+        #[structopt(long, short)]
+        dry_run: bool,
     },
     /// Refresh the current repository
     Refresh,
+    /// Backup the repository, installer registry, configuration, and install log to an archive
+    Backup {
+        /// The archive file to write, a gzipped tar file
+        archive: PathBuf,
+    },
+    /// Restore the repository, installer registry, configuration, and install log from an archive
+    Restore {
+        /// Override the local directory for the restored repository
+        #[structopt(long, short)]
+        local_dir: Option<String>,
+        /// The archive file, written by backup, to restore from
+        archive: PathBuf,
+    },
+    /// Commit any changes to the current repository, and push them to the remote
+    Push {
+        /// The commit message, if not specified you will be prompted for one
+        #[structopt(long, short)]
+        message: Option<String>,
+    },
+    /// Refresh the current repository, update all installers, then update all package-sets
+    Sync,
     // --------------------------------------------------------------------------------------------
     /// Install package-sets as described in the local repository
     Install {
-        /// If specified, only install package-sets from the named group
+        /// If specified, only install package-sets from the named groups
+        #[structopt(long = "group", short = "g", number_of_values = 1)]
+        groups: Vec<NamePattern>,
+        /// If specified, only act on the named package-sets; a name of the form 'group/set'
+        /// selects the package-set in that group
+        #[structopt(long = "package-set", short = "p", number_of_values = 1)]
+        package_sets: Vec<NamePattern>,
+        /// If specified, only act on package-sets with any of the named tags
+        #[structopt(long = "tag", short = "t", number_of_values = 1)]
+        tags: Vec<Name>,
+        /// Skip all package-sets in the named groups
+        #[structopt(long = "skip-group", number_of_values = 1)]
+        skip_groups: Vec<NamePattern>,
+        /// Skip the named package-sets; a name of the form 'group/set' skips the package-set in
+        /// that group only
+        #[structopt(long = "skip-set", number_of_values = 1)]
+        skip_sets: Vec<NamePattern>,
+        /// Only install package-sets that have changed since they were last installed, or updated
         #[structopt(long, short)]
-        group: Option<Name>,
-        #[structopt(long, short, requires_all = &["group"])]
-        package_set: Option<Name>,
+        changed: bool,
+        /// Warn about, rather than fail on, groups or package-sets that are not in the repository
+        #[structopt(long)]
+        ignore_missing: bool,
+        /// Skip the package-sets, and packages, completed by the previous install if it failed
+        #[structopt(long)]
+        resume: bool,
+        /// If a package-set fails, undo the changes already made to it, by its rollback script or
+        /// by uninstalling its packages
+        #[structopt(long)]
+        transactional: bool,
     },
     /// Update package-sets as described in the local repository
     Update {
-        /// If specified, only update package-sets from the named group
+        /// If specified, only update package-sets from the named groups
+        #[structopt(long = "group", short = "g", number_of_values = 1)]
+        groups: Vec<NamePattern>,
+        /// If specified, only act on the named package-sets; a name of the form 'group/set'
+        /// selects the package-set in that group
+        #[structopt(long = "package-set", short = "p", number_of_values = 1)]
+        package_sets: Vec<NamePattern>,
+        /// If specified, only act on package-sets with any of the named tags
+        #[structopt(long = "tag", short = "t", number_of_values = 1)]
+        tags: Vec<Name>,
+        /// Skip all package-sets in the named groups
+        #[structopt(long = "skip-group", number_of_values = 1)]
+        skip_groups: Vec<NamePattern>,
+        /// Skip the named package-sets; a name of the form 'group/set' skips the package-set in
+        /// that group only
+        #[structopt(long = "skip-set", number_of_values = 1)]
+        skip_sets: Vec<NamePattern>,
+        /// Only update package-sets that have changed since they were last installed, or updated
         #[structopt(long, short)]
-        group: Option<Name>,
-        #[structopt(long, short, requires_all = &["group"])]
-        package_set: Option<Name>,
+        changed: bool,
+        /// Warn about, rather than fail on, groups or package-sets that are not in the repository
+        #[structopt(long)]
+        ignore_missing: bool,
+        /// Skip the package-sets, and packages, completed by the previous update if it failed
+        #[structopt(long)]
+        resume: bool,
+        /// Remove the links for env-files and link-files, but do not re-create them
+        #[structopt(long = "unlink-only")]
+        unlink_only: bool,
     },
     /// Uninstall package-sets as described in the local repository
     Uninstall {
-        /// If specified, only uninstall package-sets from the named group
-        #[structopt(long, short)]
-        group: Option<Name>,
-        #[structopt(long, short, requires_all = &["group"])]
-        package_set: Option<Name>,
+        /// If specified, only uninstall package-sets from the named groups
+        #[structopt(long = "group", short = "g", number_of_values = 1)]
+        groups: Vec<NamePattern>,
+        /// If specified, only act on the named package-sets; a name of the form 'group/set'
+        /// selects the package-set in that group
+        #[structopt(long = "package-set", short = "p", number_of_values = 1)]
+        package_sets: Vec<NamePattern>,
+        /// If specified, only act on package-sets with any of the named tags
+        #[structopt(long = "tag", short = "t", number_of_values = 1)]
+        tags: Vec<Name>,
     },
     /// Link any files specified in package-sets as described in the local repository
     LinkFiles {
-        /// If specified, only link files in the package-sets from the named group
-        #[structopt(long, short)]
-        group: Option<Name>,
-        #[structopt(long, short, requires_all = &["group"])]
-        package_set: Option<Name>,
+        /// If specified, only link files in the package-sets from the named groups
+        #[structopt(long = "group", short = "g", number_of_values = 1)]
+        groups: Vec<NamePattern>,
+        /// If specified, only act on the named package-sets; a name of the form 'group/set'
+        /// selects the package-set in that group
+        #[structopt(long = "package-set", short = "p", number_of_values = 1)]
+        package_sets: Vec<NamePattern>,
     },
     /// Show the current configuration
     UpdateSelf,
+    /// Schedule a periodic refresh and install, or update, of package-sets in the background
+    Schedule {
+        /// Remove the existing schedule for the action, rather than create one
+        #[structopt(long, short)]
+        remove: bool,
+        /// The period between runs, for example '12h', '1d', or '1h30m'; the default is '1d'
+        #[structopt(long, short, conflicts_with = "remove")]
+        every: Option<String>,
+        /// The action to schedule
+        #[structopt(possible_values = &["install", "update"])]
+        action: InstallActionKind,
+    },
     // --------------------------------------------------------------------------------------------
+    /// Import existing packages, or dotfiles, into the local repository as draft package-sets
+    Import {
+        #[structopt(subcommand)]
+        source: ImportSource,
+    },
+    /// Check the local repository, and installer registry, for problems
+    Lint,
+    /// Watch the local repository, checking each package-set as it is changed
+    #[cfg(feature = "watch")]
+    Watch {
+        /// Also apply each changed package-set, if it has no problems, with the named action
+        #[structopt(long, short, possible_values = &["install", "link-files"])]
+        apply: Option<InstallActionKind>,
+    },
+    /// Rewrite package-sets, and the installer registry, in a canonical form
+    Fmt {
+        /// Rewrite files in place, rather than showing the differences
+        #[structopt(long, short)]
+        write: bool,
+    },
+    /// Upgrade the local repository, and installer registry, to the current format version
+    Migrate {
+        /// Only show the migrations required, do not apply them
+        #[structopt(long, short)]
+        dry_run: bool,
+    },
     /// Show current path locations
     Paths,
-    /// Edit the current installer registry file
-    Installers,
+    /// Write the shell scripts, to source from a shell startup file, that set up the environment
+    /// of all installed package-sets
+    EnvScript,
+    /// Edit the current installer registry file, or show a single installer
+    Installers {
+        #[structopt(subcommand)]
+        command: Option<InstallersCommand>,
+    },
     /// List package-sets in the local repository
     List {
         /// If specified, only list package-sets from the named group
         #[structopt(long, short)]
         group: Option<Name>,
+        /// Also show the README file of each group and package-set
+        #[structopt(long, short)]
+        describe: bool,
+    },
+    /// Show a group, or a package-set as 'group/set', with its README file
+    Show {
+        /// Print the README file as written, rather than rendered for the terminal
+        #[structopt(long, short)]
+        plain: bool,
+        /// The group, or package-set as 'group/set', to show
+        name: Name,
+    },
+    /// Show the installed version of packages, and any that violate their version constraint
+    Status {
+        /// If specified, only show packages from the named group
+        #[structopt(long, short)]
+        group: Option<Name>,
+    },
+    /// Show package-sets changed since last applied, not applied, or no longer in the repository
+    Drift,
+    /// Review, and approve, the scripts of package-sets not yet approved, or changed since
+    /// approved; see the script-trust setting
+    Approve {
+        /// If specified, only review package-sets from the named group
+        #[structopt(long, short)]
+        group: Option<Name>,
+        /// Approve all the scripts shown, without asking
+        #[structopt(long, short)]
+        yes: bool,
     },
     /// Show a history of install actions on the local machine
     History {
         #[structopt(long, short)]
         limit: Option<u32>,
+        /// Show every command executed, with its exit code and duration, rather than packages
+        #[structopt(long, short)]
+        commands: bool,
+        /// Only show packages acted upon on the named machine, see merge-log
+        #[structopt(long, short, conflicts_with = "commands")]
+        machine: Option<String>,
+    },
+    /// Show statistics for the install history on the local machine; installers, recent updates,
+    /// packages never updated, busiest days, and the slowest packages
+    Stats {
+        /// The number of rows shown in each table, the default is 10 and 0 shows all rows
+        #[structopt(long, short)]
+        limit: Option<u32>,
+    },
+    /// Merge the install log from another machine into the install log on the local machine
+    MergeLog {
+        /// The machine recorded for entries that do not record one, the default is the log file's
+        /// name without its extension
+        #[structopt(long, short)]
+        machine: Option<String>,
+        /// The install log file to merge, a JSON-lines file if its extension is 'jsonl'
+        log_file: PathBuf,
     },
     /// Run a shell in the repository directory, with a basic script environment
     Shell {
@@ -95,23 +335,216 @@ pub enum SubCommands {
     Add {
         #[structopt(long, short)]
         as_file: bool,
+        /// Build the package-set by answering prompts, rather than editing a template
+        #[structopt(long, short, conflicts_with = "from-stdin")]
+        interactive: bool,
+        /// Read the package-set, as YAML or JSON, from stdin rather than editing a template
+        #[structopt(long)]
+        from_stdin: bool,
         group: Name,
         package_set: Name,
     },
     /// Add an existing package-set in the local repository
     Edit { group: Name, package_set: Name },
     /// Remove an existing package-set from the local repository
-    Remove { group: Name, package_set: Name },
+    Remove {
+        /// Move the package-set to the repository's '.archive' directory, and record the removal
+        /// in the install log, rather than deleting it
+        #[structopt(long, short)]
+        archive: bool,
+        /// First uninstall the packages the install log records as installed on this machine,
+        /// and remove the links to the package-set's files
+        #[structopt(long, short)]
+        uninstall: bool,
+        /// Commit the archived package-set to the local repository
+        #[structopt(long, short, requires = "archive")]
+        commit: bool,
+        group: Name,
+        package_set: Name,
+    },
+    /// Uninstall the packages of a package-set installed on this machine, remove the links to its
+    /// files, then archive it; see 'remove --uninstall --archive'
+    Retire {
+        /// Commit the archived package-set to the local repository
+        #[structopt(long, short)]
+        commit: bool,
+        group: Name,
+        package_set: Name,
+    },
+    /// Add a package to an existing package-set in the local repository
+    AddPackage {
+        /// The kind of package; 'default', 'application', 'application:<name>', or a language
+        /// installer name
+        #[structopt(long, short)]
+        kind: Option<PackageKind>,
+        /// The platform the package is installed on, if not all
+        #[structopt(long, short)]
+        platform: Option<Platform>,
+        group: Name,
+        package_set: Name,
+        package: Name,
+    },
+    /// Remove a package from an existing package-set in the local repository
+    RemovePackage {
+        group: Name,
+        package_set: Name,
+        package: Name,
+    },
+    /// Print a package-set, as 'group/set', or an installer definition to stdout
+    Cat {
+        /// Print the named installer, rather than a package-set
+        #[structopt(long, short)]
+        installer: bool,
+        /// Print the package-set as re-written by the tool, rather than the file content
+        #[structopt(long, short)]
+        normalized: bool,
+        /// The output format, either 'yaml' or 'json'; JSON output is always normalized
+        #[structopt(long, short, default_value = "yaml")]
+        format: CatFormat,
+        /// The package-set to print, as 'group/set', or the installer name
+        name: Name,
+    },
+    /// List every command and script that an action would run on package-sets, grouped by
+    /// package-set file, for review before it is run
+    Audit {
+        /// The action to list the commands for
+        #[structopt(long, short, default_value = "install")]
+        action: InstallActionKind,
+        /// If specified, only list commands for package-sets from the named groups
+        #[structopt(long = "group", short = "g", number_of_values = 1)]
+        groups: Vec<NamePattern>,
+        /// If specified, only list commands for the named package-sets; a name of the form
+        /// 'group/set' selects the package-set in that group
+        #[structopt(long = "package-set", short = "p", number_of_values = 1)]
+        package_sets: Vec<NamePattern>,
+        /// If specified, only list commands for package-sets with any of the named tags
+        #[structopt(long = "tag", short = "t", number_of_values = 1)]
+        tags: Vec<Name>,
+        /// Print the commands as JSON, rather than text
+        #[structopt(long, short)]
+        json: bool,
+    },
+    /// Show the variables passed to installer commands and scripts for a package-set, as
+    /// 'group/set', or a package within it
+    Vars {
+        /// The action the variables are computed for
+        #[structopt(long, short, default_value = "install")]
+        action: InstallActionKind,
+        /// Print the variables as a JSON object, rather than a table
+        #[structopt(long, short)]
+        json: bool,
+        /// The package-set, as 'group/set'
+        name: Name,
+        /// If specified, include the variables for the named package
+        package: Option<Name>,
+    },
+    /// Show the environment of installer commands and scripts for a package-set, or run a shell
+    /// with that environment
+    Env {
+        /// The action the environment is computed for
+        #[structopt(long, short, default_value = "install")]
+        action: InstallActionKind,
+        /// Print the environment as a JSON object, rather than as 'NAME=value' lines
+        #[structopt(long, short, conflicts_with = "shell")]
+        json: bool,
+        /// Run the user's shell with the environment, rather than print it
+        #[structopt(long, short)]
+        shell: bool,
+        group: Name,
+        package_set: Name,
+    },
+    /// Copy a package-set, as 'group/set', to a new package-set in the local repository
+    Copy {
+        /// Commit the change to the local repository
+        #[structopt(long, short)]
+        commit: bool,
+        /// The package-set to copy, as 'group/set'
+        from: Name,
+        /// The new package-set, as 'group/set', or a name in the same group
+        to: Name,
+    },
+    /// Move a package-set, as 'group/set', to another group in the local repository
+    Move {
+        /// Commit the change to the local repository
+        #[structopt(long, short)]
+        commit: bool,
+        /// The package-set to move, as 'group/set'
+        from: Name,
+        /// The group to move the package-set to
+        to_group: Name,
+    },
+    /// Rename a package-set, as 'group/set', or a group in the local repository
+    Rename {
+        /// Commit the change to the local repository
+        #[structopt(long, short)]
+        commit: bool,
+        /// The package-set, as 'group/set', or group to rename
+        from: Name,
+        /// The new name for the package-set, or group
+        new_name: Name,
+    },
     // --------------------------------------------------------------------------------------------
     #[cfg(feature = "remove-self")]
     CompletelyAndPermanentlyRemoveSelf,
 }
 
+#[derive(Debug, StructOpt)]
+pub enum ImportSource {
+    /// Import the packages installed with each installer that has a 'list_installed' command
+    System {
+        /// The group to add the draft package-sets to
+        #[structopt(long, short, default_value = "imported")]
+        group: Name,
+    },
+    /// Import the files in a stow, or chezmoi, style dotfiles directory
+    Dotfiles {
+        /// The group to add the draft package-sets to
+        #[structopt(long, short, default_value = "dotfiles")]
+        group: Name,
+        /// The dotfiles directory to import
+        dotfiles_dir: PathBuf,
+    },
+}
+
+#[derive(Debug, StructOpt)]
+pub enum InstallersCommand {
+    /// Edit the current installer registry file
+    Edit,
+    /// Show an installer, and check whether it is used on this machine and its commands can be run
+    Show {
+        /// The name of the installer to show
+        name: Name,
+    },
+    /// Smoke-test an installer, checking it and dry-running its install command without installing
+    /// anything
+    Test {
+        /// The name of the installer to test
+        name: Name,
+    },
+    /// Enable an installer that was disabled
+    Enable {
+        /// The name of the installer to enable
+        name: Name,
+    },
+    /// Disable an installer, it is kept in the installer registry file but not used
+    Disable {
+        /// The name of the installer to disable
+        name: Name,
+    },
+}
+
+// ------------------------------------------------------------------------------------------------
+// Private Types
+// ------------------------------------------------------------------------------------------------
+
+// The exit code when the local repository is not initialized; `EX_CONFIG` from sysexits.h.
+const NOT_INITIALIZED_EXIT_CODE: i32 = 78;
+
 // ------------------------------------------------------------------------------------------------
 // Private Functions
 // ------------------------------------------------------------------------------------------------
 
-fn parse() -> Result<Box<dyn Action>> {
+fn parse() -> Result<(Box<dyn Action>, ActionContext, Option<ReportFormat>)> {
     let args = CommandLine::from_args();
 
     pretty_env_logger::formatted_builder()
@@ -125,18 +558,88 @@ fn parse() -> Result<Box<dyn Action>> {
         })
         .init();
 
-    if !args.sub_command.is_init() && !is_initialized() {
-        eprintln!(
-            "Error: your local repository is not initialized, try running the 'init' command"
-        );
-        panic!("Could not continue");
+    Reporter::current()
+        .with_show_command_output(args.show_output)
+        .with_ci_format(args.ci)
+        .install();
+    set_ignore_case(args.ignore_case);
+
+    let scope = if args.system {
+        Scope::System
+    } else {
+        Scope::User
+    };
+    if args.system {
+        if !args.sub_command.is_read_only() {
+            check_scope_privileges(scope)?;
+        }
+    } else if is_root() && !args.sub_command.is_read_only() {
+        if args.allow_root {
+            eprintln!("Warning: running as root, changes will be made to root's home directory");
+        } else {
+            return Err(Error::RunningAsRoot);
+        }
+    }
+
+    if args.sub_command.needs_initialized() {
+        let missing = missing_initialized_paths(scope);
+        if !missing.is_empty() {
+            return Err(Error::NotInitialized(
+                missing
+                    .iter()
+                    .map(|path| format!("{:?}", path))
+                    .collect::<Vec<String>>()
+                    .join(" and "),
+            ));
+        }
+    }
+
+    let context = ActionContext::for_scope(scope);
+    // The policy applies to all commands, the configuration is read directly so that it is not
+    // cached in the context before an action that writes it.
+    match Config::open_from(context.config_path().clone()) {
+        Ok(config) => set_environment_policy(config.environment().clone()),
+        Err(e) => log::warn!(
+            "could not read configuration, environment policy not applied: {}",
+            e
+        ),
     }
 
-    args.sub_command.try_into()
+    let report = args.report;
+    Ok((args.sub_command.try_into()?, context, report))
 }
 
-pub fn is_initialized() -> bool {
-    InstallerRegistry::default_path().is_file() && PackageRepository::default_path().is_dir()
+fn split_group_path(path: &Name) -> Result<(Name, Option<Name>)> {
+    let path = path.to_string();
+    match path.find('/') {
+        None => Ok((Name::from_str(&path)?, None)),
+        Some(index) => Ok((
+            Name::from_str(&path[..index])?,
+            Some(Name::from_str(&path[index + 1..])?),
+        )),
+    }
+}
+
+fn exit_code(e: &Error) -> i32 {
+    match e {
+        Error::NotInitialized(_) => NOT_INITIALIZED_EXIT_CODE,
+        Error::Interrupted => INTERRUPTED_EXIT_CODE,
+        _ => 1,
+    }
+}
+
+// Return the paths created by the 'init' command, in `scope`, that do not exist.
+fn missing_initialized_paths(scope: Scope) -> Vec<PathBuf> {
+    let registry_path = InstallerRegistry::default_path(scope);
+    let repository_path = PackageRepository::default_path(scope);
+    let mut missing: Vec<PathBuf> = Default::default();
+    if !repository_path.is_dir() {
+        missing.push(repository_path);
+    }
+    if !registry_path.is_file() {
+        missing.push(registry_path);
+    }
+    missing
 }
 
 // ------------------------------------------------------------------------------------------------
@@ -154,45 +657,294 @@ impl TryInto<Box<dyn Action>> for SubCommands {
             SubCommands::Init {
                 local_dir,
                 repository_url,
-            } => InitAction::new_action(local_dir, repository_url),
+                template,
+                git_ignore,
+                readme,
+                pre_commit_hook,
+                commit,
+                origin_url,
+                adopt,
+                dry_run,
+            } => InitAction::new_action(
+                local_dir,
+                repository_url,
+                template,
+                [
+                    (git_ignore, InitScaffold::GitIgnore),
+                    (readme, InitScaffold::Readme),
+                    (pre_commit_hook, InitScaffold::PreCommitHook),
+                ]
+                .iter()
+                .filter_map(|(enabled, scaffold)| if *enabled { Some(*scaffold) } else { None })
+                .collect(),
+                commit,
+                origin_url,
+                adopt,
+                dry_run,
+            ),
             SubCommands::Refresh => RefreshAction::new_action(),
+            SubCommands::Push { message } => PushAction::new_action(message),
+            SubCommands::Backup { archive } => BackupAction::backup_action(archive),
+            SubCommands::Restore { local_dir, archive } => {
+                BackupAction::restore_action(archive, local_dir)
+            }
+            SubCommands::Sync => SyncAction::new_action(),
             SubCommands::Add {
                 group,
                 package_set,
                 as_file,
-            } => ManageAction::add_action(group, package_set, as_file),
+                interactive,
+                from_stdin,
+            } => {
+                if from_stdin {
+                    ManageAction::add_from_stdin_action(group, package_set, as_file)
+                } else {
+                    ManageAction::add_action(group, package_set, as_file, interactive)
+                }
+            }
             SubCommands::Edit { group, package_set } => {
                 ManageAction::edit_action(group, package_set)
             }
-            SubCommands::Remove { group, package_set } => {
-                ManageAction::remove_action(group, package_set)
+            SubCommands::Remove {
+                archive,
+                uninstall,
+                commit,
+                group,
+                package_set,
+            } => {
+                if uninstall {
+                    ManageAction::retire_action(group, package_set, archive, commit)
+                } else if archive {
+                    ManageAction::archive_action(group, package_set, commit)
+                } else {
+                    ManageAction::remove_action(group, package_set)
+                }
             }
-            SubCommands::List { group } => ListAction::new_action(group),
-            // ----------------------------------------------------------------------------------------
-            // Package Commands
-            // ----------------------------------------------------------------------------------------
-            SubCommands::Install { group, package_set } => {
-                InstallAction::install_action(group, package_set)
+            SubCommands::Retire {
+                commit,
+                group,
+                package_set,
+            } => ManageAction::retire_action(group, package_set, true, commit),
+            SubCommands::AddPackage {
+                kind,
+                platform,
+                group,
+                package_set,
+                package,
+            } => {
+                let mut builder = PackageBuilder::named(package);
+                if let Some(kind) = kind {
+                    let _ = builder.of_kind(kind);
+                }
+                if let Some(platform) = platform {
+                    let _ = builder.for_platform(platform);
+                }
+                ManageAction::add_package_action(group, package_set, builder.build())
             }
-            SubCommands::Update { group, package_set } => {
-                InstallAction::update_action(group, package_set)
+            SubCommands::RemovePackage {
+                group,
+                package_set,
+                package,
+            } => ManageAction::remove_package_action(group, package_set, package),
+            SubCommands::Copy { commit, from, to } => match split_group_path(&from)? {
+                (group, Some(package_set)) => match split_group_path(&to)? {
+                    (to_group, Some(new_name)) => {
+                        ManageAction::copy_action(group, package_set, to_group, new_name, commit)
+                    }
+                    (new_name, None) => ManageAction::copy_action(
+                        group.clone(),
+                        package_set,
+                        group,
+                        new_name,
+                        commit,
+                    ),
+                },
+                (_, None) => Err(Error::NotAPackageSetPath(from.to_string())),
+            },
+            SubCommands::Cat {
+                installer,
+                normalized,
+                format,
+                name,
+            } => {
+                if installer {
+                    CatAction::installer_action(name, format)
+                } else {
+                    match split_group_path(&name)? {
+                        (group, Some(package_set)) => {
+                            CatAction::package_set_action(group, package_set, normalized, format)
+                        }
+                        (_, None) => Err(Error::NotAPackageSetPath(name.to_string())),
+                    }
+                }
             }
-            SubCommands::Uninstall { group, package_set } => {
-                InstallAction::uninstall_action(group, package_set)
+            SubCommands::Audit {
+                action,
+                groups,
+                package_sets,
+                tags,
+                json,
+            } => AuditAction::new_action(
+                action,
+                PackageSetSelection::new(groups, package_sets).with_tags(tags),
+                json,
+            ),
+            SubCommands::Vars {
+                action,
+                json,
+                name,
+                package,
+            } => match split_group_path(&name)? {
+                (group, Some(package_set)) => {
+                    VarsAction::new_action(action, group, package_set, package, json)
+                }
+                (_, None) => Err(Error::NotAPackageSetPath(name.to_string())),
+            },
+            SubCommands::Env {
+                action,
+                json,
+                shell,
+                group,
+                package_set,
+            } => EnvAction::new_action(action, group, package_set, json, shell),
+            SubCommands::Move {
+                commit,
+                from,
+                to_group,
+            } => match split_group_path(&from)? {
+                (group, Some(package_set)) => {
+                    ManageAction::move_action(group, package_set, to_group, commit)
+                }
+                (_, None) => Err(Error::NotAPackageSetPath(from.to_string())),
+            },
+            SubCommands::Rename {
+                commit,
+                from,
+                new_name,
+            } => {
+                let (group, package_set) = split_group_path(&from)?;
+                ManageAction::rename_action(group, package_set, new_name, commit)
             }
-            SubCommands::LinkFiles { group, package_set } => {
-                InstallAction::link_files_action(group, package_set)
+            SubCommands::List { group, describe } => {
+                if describe {
+                    ListAction::describe_action(group)
+                } else {
+                    ListAction::new_action(group)
+                }
             }
+            SubCommands::Show { plain, name } => {
+                let (group, package_set) = split_group_path(&name)?;
+                ShowAction::new_action(group, package_set, plain)
+            }
+            SubCommands::Import { source } => match source {
+                ImportSource::System { group } => ImportAction::system_action(group),
+                ImportSource::Dotfiles {
+                    group,
+                    dotfiles_dir,
+                } => ImportAction::dotfiles_action(dotfiles_dir, group),
+            },
+            SubCommands::Lint => LintAction::new_action(),
+            #[cfg(feature = "watch")]
+            SubCommands::Watch { apply } => WatchAction::new_action(apply),
+            SubCommands::Fmt { write } => FormatAction::new_action(write),
+            SubCommands::Migrate { dry_run } => MigrateAction::new_action(dry_run),
+            // ----------------------------------------------------------------------------------------
+            // Package Commands
+            // ----------------------------------------------------------------------------------------
+            SubCommands::Install {
+                groups,
+                package_sets,
+                tags,
+                skip_groups,
+                skip_sets,
+                changed,
+                ignore_missing,
+                resume,
+                transactional,
+            } => InstallAction::install_action(
+                PackageSetSelection::new(groups, package_sets)
+                    .with_tags(tags)
+                    .skipping(skip_groups, skip_sets),
+                ExecuteOptions::default()
+                    .with_changed_only(changed)
+                    .with_ignore_missing(ignore_missing)
+                    .with_resume(resume)
+                    .with_transactional(transactional),
+            ),
+            SubCommands::Update {
+                groups,
+                package_sets,
+                tags,
+                skip_groups,
+                skip_sets,
+                changed,
+                ignore_missing,
+                resume,
+                unlink_only,
+            } => InstallAction::update_action(
+                PackageSetSelection::new(groups, package_sets)
+                    .with_tags(tags)
+                    .skipping(skip_groups, skip_sets),
+                ExecuteOptions::default()
+                    .with_changed_only(changed)
+                    .with_ignore_missing(ignore_missing)
+                    .with_resume(resume)
+                    .with_unlink_only(unlink_only),
+            ),
+            SubCommands::Uninstall {
+                groups,
+                package_sets,
+                tags,
+            } => InstallAction::uninstall_action(
+                PackageSetSelection::new(groups, package_sets).with_tags(tags),
+            ),
+            SubCommands::LinkFiles {
+                groups,
+                package_sets,
+            } => InstallAction::link_files_action(PackageSetSelection::new(groups, package_sets)),
             // ----------------------------------------------------------------------------------------
             // Installer Commands
             // ----------------------------------------------------------------------------------------
-            SubCommands::Installers => EditInstallersAction::new_action(),
-            SubCommands::History { limit } => HistoryAction::new_action(limit),
+            SubCommands::Installers { command } => match command {
+                None | Some(InstallersCommand::Edit) => EditInstallersAction::new_action(),
+                Some(InstallersCommand::Show { name }) => ShowInstallerAction::new_action(name),
+                Some(InstallersCommand::Test { name }) => TestInstallerAction::new_action(name),
+                Some(InstallersCommand::Enable { name }) => {
+                    EnableInstallerAction::enable_action(name)
+                }
+                Some(InstallersCommand::Disable { name }) => {
+                    EnableInstallerAction::disable_action(name)
+                }
+            },
+            SubCommands::Drift => DriftAction::new_action(),
+            SubCommands::Approve { group, yes } => ApproveAction::new_action(group, yes),
+            SubCommands::History {
+                limit,
+                commands,
+                machine,
+            } => HistoryAction::new_action(limit, commands, machine),
+            SubCommands::Status { group } => StatusAction::new_action(group),
+            SubCommands::Stats { limit } => StatsAction::new_action(limit),
+            SubCommands::MergeLog { machine, log_file } => {
+                MergeLogAction::new_action(log_file, machine)
+            }
             SubCommands::UpdateSelf => UpdateSelfAction::new_action(),
+            SubCommands::Schedule {
+                remove,
+                every,
+                action,
+            } => {
+                if remove {
+                    ScheduleAction::remove_action(action)
+                } else {
+                    ScheduleAction::new_action(action, every)
+                }
+            }
             // ----------------------------------------------------------------------------------------
             // Help Commands
             // ----------------------------------------------------------------------------------------
             SubCommands::Paths => ShowPathsAction::new_action(),
+            SubCommands::EnvScript => EnvScriptAction::new_action(),
             #[cfg(feature = "remove-self")]
             SubCommands::CompletelyAndPermanentlyRemoveSelf => RemoveSelfAction::new_action(),
             SubCommands::Shell { shell } => {
@@ -203,8 +955,38 @@ impl TryInto<Box<dyn Action>> for SubCommands {
 }
 
 impl SubCommands {
-    fn is_init(&self) -> bool {
-        matches!(self, SubCommands::Init { .. })
+    // Commands that create the local repository, or only report on paths, can run before it is
+    // initialized.
+    fn needs_initialized(&self) -> bool {
+        !matches!(
+            self,
+            SubCommands::Init { .. } | SubCommands::Restore { .. } | SubCommands::Paths
+        )
+    }
+
+    fn is_read_only(&self) -> bool {
+        matches!(
+            self,
+            SubCommands::Lint
+                | SubCommands::Fmt { write: false }
+                | SubCommands::Paths
+                | SubCommands::Installers {
+                    command: None
+                        | Some(InstallersCommand::Edit)
+                        | Some(InstallersCommand::Show { .. })
+                        | Some(InstallersCommand::Test { .. })
+                }
+                | SubCommands::List { .. }
+                | SubCommands::Show { .. }
+                | SubCommands::Status { .. }
+                | SubCommands::Drift
+                | SubCommands::History { .. }
+                | SubCommands::Stats { .. }
+                | SubCommands::Cat { .. }
+                | SubCommands::Vars { .. }
+                | SubCommands::Audit { .. }
+                | SubCommands::Env { .. }
+        )
     }
 }
 
@@ -212,8 +994,18 @@ impl SubCommands {
 // Start Here!
 // ------------------------------------------------------------------------------------------------
 
-fn main() -> std::result::Result<(), Box<dyn Error>> {
+fn main() {
     mcfg::reporter::set_is_interactive(true);
-    parse()?.run()?;
-    Ok(())
+    let result = parse().and_then(|(action, context, report_format)| match report_format {
+        None => action.run(&context),
+        Some(report_format) => {
+            let report = action.run_with_report(&context)?;
+            println!("{}", report.format(report_format));
+            Ok(())
+        }
+    });
+    if let Err(e) = result {
+        eprintln!("Error: {}", e);
+        std::process::exit(exit_code(&e));
+    }
 }
@@ -1,104 +1,278 @@
 /*!
-The common `ErrorKind`, `Error`, and `Result` types used throughout.
-*/
+The common `Error`, and `Result`, types used throughout.
 
-#![allow(missing_docs)]
+`Error` is an enumeration of everything that can go wrong, each variant carrying the values that
+describe the failure, so that callers may match on it directly. Errors from the libraries this
+crate uses are wrapped in their own variants, and are returned by `source`.
+*/
 
-use crate::shared::{PackageKind, Platform};
+use crate::shared::{did_you_mean, PackageKind, Platform};
 use std::process::ExitStatus;
 
 // ------------------------------------------------------------------------------------------------
 // Public Types
 // ------------------------------------------------------------------------------------------------
 
-error_chain! {
-    errors {
-        #[doc("Invalid configuration value")]
-        InvalidConfigValue(field: String, value: String) {
-            description("Invalid configuration value")
-            display("Invalid value for configuration field '{}': '{}'", field, value)
-        }
-
-        #[doc("No package set found in group")]
-        NoPackageSet(group: String, package_set: String) {
-            description("No package set found in group")
-            display("No package set '{}' found in group '{}'", package_set, group)
-        }
-
-        #[doc("No package set found in group")]
-        PackagePlatformError(package: String) {
-            description("The package cannot be installed on this platform")
-            display("The package '{}' cannot be installed on platform {:?}", package, Platform::CURRENT)
-        }
-
-        #[doc("No installer found for package kind")]
-        NoInstallerForKind(kind: PackageKind) {
-            description("No installer found for package kind")
-            display("No installer found for platform '{:?}' and package kind '{:?}'", Platform::CURRENT, kind)
-        }
-
-        #[doc("Wrong installer used for package kind")]
-        WrongInstallerForKind(kind: PackageKind) {
-            description("Wrong installer used for package kind")
-            display("Wrong installer used for package kind '{:?}'", kind)
-        }
-
-        #[doc("Invalid command string for installer action")]
-        InvalidCommandString(cmd_str: String) {
-            description("Invalid command string for installer action")
-            display("Invalid command string for installer action: {:?}", cmd_str)
-        }
-
-        #[doc("Command string for install action failed to run")]
-        InstallerCommandFailed {
-            description("Command string for install action failed to run")
-            display("Command string for install action failed to run")
-        }
-
-        #[doc("std::process::Command failed to execute command")]
-        CommandExecutionFailed(cmd: String, exit_status: Option<ExitStatus>) {
-            description("std::process::Command failed to execute command")
-            display("std::process::Command failed to execute command '{}', status: {:?}", cmd, exit_status)
-        }
-
-        #[doc("Invalid builder state")]
-        InvalidBuilderState {
-            description("Invalid builder state")
-            display("Invalid builder state")
-        }
-
-        #[doc("Value provided is not a valid Name representation")]
-        InvalidNameString(name: String) {
-            description("Value provided is not a valid Name representation")
-            display("Value '{}' is not a valid Name representation", name)
-        }
-    }
-
-    foreign_links {
-        Fmt(::std::fmt::Error);
-        Git(::git2::Error);
-        Io(::std::io::Error);
-        Serialization(::serde_yaml::Error);
-        Sql(::rusqlite::Error);
-    }
-}
+///
+/// The error type for all operations in this crate. New variants may be added in any release, so
+/// a match on this type must include a wildcard arm.
+///
+#[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
+pub enum Error {
+    /// Invalid configuration value
+    #[error("Invalid value for configuration field '{0}': '{1}'")]
+    InvalidConfigValue(String, String),
 
-// ------------------------------------------------------------------------------------------------
-// Private Types
-// ------------------------------------------------------------------------------------------------
+    /// No package set found in group
+    #[error("No package set '{name}' found in group '{group}'{}", did_you_mean(.suggestions))]
+    NoPackageSet {
+        /// The name of the group searched
+        group: String,
+        /// The name of the package set not found
+        name: String,
+        /// The names of similar package sets in the group
+        suggestions: Vec<String>,
+    },
 
-// ------------------------------------------------------------------------------------------------
-// Public Functions
-// ------------------------------------------------------------------------------------------------
+    /// The package cannot be installed on this platform
+    #[error("The package '{0}' cannot be installed on platform {platform:?}", platform = Platform::CURRENT)]
+    PackagePlatformError(String),
 
-// ------------------------------------------------------------------------------------------------
-// Implementations
-// ------------------------------------------------------------------------------------------------
+    /// No installer found for package kind
+    #[error("No installer found for platform '{platform:?}' and package kind '{0:?}'", platform = Platform::CURRENT)]
+    NoInstallerForKind(PackageKind),
 
-// ------------------------------------------------------------------------------------------------
-// Private Functions
-// ------------------------------------------------------------------------------------------------
+    /// Wrong installer used for package kind
+    #[error("Wrong installer used for package kind '{0:?}'")]
+    WrongInstallerForKind(PackageKind),
 
-// ------------------------------------------------------------------------------------------------
-// Modules
-// ------------------------------------------------------------------------------------------------
+    /// Invalid command string for installer action
+    #[error("Invalid command string for installer action: {0:?}")]
+    InvalidCommandString(String),
+
+    /// Command string for install action failed to run
+    #[error("Command string for install action failed to run")]
+    InstallerCommandFailed,
+
+    /// The tool was interrupted, usually by Ctrl-C
+    #[error("Interrupted; the current command was terminated and partial changes to the package set removed")]
+    Interrupted,
+
+    /// A command did not complete in the time allowed
+    #[error("The command '{0}' did not complete in {1} seconds, and was killed")]
+    CommandTimedOut(String, u64),
+
+    /// std::process::Command failed to execute command
+    #[error("std::process::Command failed to execute command '{0}', status: {1:?}")]
+    CommandExecutionFailed(String, Option<ExitStatus>),
+
+    /// Downloaded file did not match the expected checksum
+    #[error("File downloaded from '{0}' has checksum '{2}', expected '{1}'")]
+    ChecksumMismatch(String, String, String),
+
+    /// No release found for the repository
+    #[error("No release '{1}' found for the repository '{0}'")]
+    ReleaseNotFound(String, String),
+
+    /// No release asset matched the current platform
+    #[error("No asset in release '{1}' of repository '{0}' matched the current platform")]
+    NoMatchingAsset(String, String),
+
+    /// A release's checksum asset did not include the selected asset
+    #[error("The checksum file '{0}' does not include a checksum for the asset '{1}'")]
+    MissingChecksum(String, String),
+
+    /// The template is neither a built-in template nor a repository URL
+    #[error("The template '{0}' is neither a built-in template nor a repository URL")]
+    UnknownTemplate(String),
+
+    /// Problems were found in the package repository
+    #[error("{0} problem(s) were found in the package repository")]
+    LintFailed(usize),
+
+    /// Files in the package repository, or installer registry, are not formatted
+    #[error("{0} file(s) are not formatted, run 'mcfg fmt --write' to format them")]
+    UnformattedFiles(usize),
+
+    /// The package repository already exists, and will not be overwritten
+    #[error("The package repository '{0}' already exists, and will not be overwritten")]
+    RestoreTargetExists(String),
+
+    /// An existing directory cannot be adopted as the package repository
+    #[error("The directory '{0}' cannot be adopted as a package repository, {1}")]
+    CannotAdoptRepository(String, String),
+
+    /// The format version is older than the version supported, a migration is required
+    #[error("The {0} format version {1} is older than the supported version {2}, run 'mcfg migrate' to upgrade it")]
+    MigrationRequired(String, u32, u32),
+
+    /// The format version is not supported by this version of the tool
+    #[error(
+        "The {0} format version {1} is not supported, this version of mcfg supports version {2}"
+    )]
+    UnsupportedFormatVersion(String, u32, u32),
+
+    /// Value provided is not a valid name pattern
+    #[error("Value '{0}' is not a valid name, glob, or regular expression pattern")]
+    InvalidNamePattern(String),
+
+    /// No package sets in the repository match the selection
+    #[error("Nothing in the repository matches the selection: {0}")]
+    NoMatchingPackageSets(String),
+
+    /// No package set group found in the repository
+    #[error("No package set group '{name}' found in the repository{}", did_you_mean(.suggestions))]
+    NoPackageSetGroup {
+        /// The name of the group not found
+        name: String,
+        /// The names of similar groups in the repository
+        suggestions: Vec<String>,
+    },
+
+    /// The value is not a package set path, of the form 'group/set'
+    #[error("The value '{0}' is not a package set path, of the form 'group/set'")]
+    NotAPackageSetPath(String),
+
+    /// The target of a repository change already exists, and will not be overwritten
+    #[error("The path '{0}' already exists, and will not be overwritten")]
+    TargetExists(String),
+
+    /// The name in a package set does not match the name it is added as
+    #[error("The package set is named '{1}', expecting '{0}'")]
+    PackageSetNameMismatch(String, String),
+
+    /// Value provided is not a valid output format
+    #[error("Value '{0}' is not a valid output format, expecting 'yaml' or 'json'")]
+    InvalidFormatString(String),
+
+    /// No installer found in the registry
+    #[error("No installer '{0}' found in the registry for this platform")]
+    NoInstaller(String),
+
+    /// An installer has no command to add a package set prerequisite
+    #[error("The installer '{0}' has no add_prerequisite command, required by a package set")]
+    NoPrerequisiteCommand(String),
+
+    /// A package set prerequisite is empty, or contains a quote or control character
+    #[error("The package set prerequisite {0:?} is not valid, it may not contain quotes or control characters")]
+    InvalidPrerequisite(String),
+
+    /// An installer failed one or more of its smoke test checks
+    #[error("The installer '{0}' failed {1} check(s)")]
+    InstallerTestFailed(String, usize),
+
+    /// No package found in package set
+    #[error("No package '{1}' found in package set '{0}'")]
+    NoPackage(String, String),
+
+    /// A package with this name is already in the package set
+    #[error("A package '{1}' is already in package set '{0}'")]
+    PackageExists(String, String),
+
+    /// Value provided is not a valid platform name
+    #[error("Value '{0}' is not a valid platform, expecting 'macos' or 'linux'")]
+    InvalidPlatformString(String),
+
+    /// System-wide mode requires root privileges
+    #[error("System-wide mode requires root privileges, try running the command with 'sudo'")]
+    SystemScopeRequiresRoot,
+
+    /// The local repository, or installer registry, has not been initialized
+    #[error(
+        "Your local repository is not initialized, {0} not found; try running the 'init' command"
+    )]
+    NotInitialized(String),
+
+    /// The tool is running as root in the user scope
+    #[error("Running as root will configure root's home directory; use '--allow-root' to continue, or '--system' for system-wide mode")]
+    RunningAsRoot,
+
+    /// The installer requires root privileges
+    #[error("The installer '{0}' must be run as root, try running the command with 'sudo'")]
+    InstallerRequiresRoot(String),
+
+    /// The installer must not be run as root
+    #[error("The installer '{0}' must not be run as root")]
+    InstallerRefusesRoot(String),
+
+    /// The secret reference does not match any secret provider
+    #[error("The secret '{0}' does not match any secret provider, expecting 'op://', 'pass:', or 'keychain:'")]
+    UnknownSecretProvider(String),
+
+    /// The secret could not be read from its provider
+    #[error("The secret '{0}' could not be read from its provider: {1}")]
+    SecretNotResolved(String, String),
+
+    /// A prompted variable has no answer, and the user cannot be asked for one
+    #[error("The prompted variable '{0}' in package set '{1}' has no answer, and the user cannot be asked for one")]
+    PromptNotAnswered(String, String),
+
+    /// The scripts in a package set have not been approved, and the user cannot be asked
+    #[error("The scripts in package set '{0}' have not been approved; run the approve command to review them")]
+    ScriptsNotApproved(String),
+
+    /// The install log format is not supported by this build of the tool
+    #[error("The install log format '{0}' is not supported by this build of mcfg")]
+    UnsupportedInstallLogFormat(String),
+
+    /// The repository VCS is not supported by this build of the tool
+    #[error("The repository VCS '{0}' is not supported by this build of mcfg")]
+    UnsupportedRepoVcs(String),
+
+    /// The repository VCS does not support an operation
+    #[error("The repository VCS '{0}' does not support {1}")]
+    UnsupportedRepoVcsOperation(String, String),
+
+    /// An install log cannot be merged into itself
+    #[error("The install log '{0}' cannot be merged into itself")]
+    MergeLogIntoItself(String),
+
+    /// A webhook could not be posted to
+    #[error("The webhook '{0}' could not be posted to: {1}")]
+    WebhookFailed(String, String),
+
+    /// Invalid builder state
+    #[error("Invalid builder state")]
+    InvalidBuilderState,
+
+    /// Value provided is not a valid Name representation
+    #[error("Value '{0}' is not a valid Name representation")]
+    InvalidNameString(String),
+
+    /// A value could not be formatted
+    #[error("{0}")]
+    Fmt(#[from] std::fmt::Error),
+
+    /// An operation on a git repository failed
+    #[cfg(feature = "git")]
+    #[error("{0}")]
+    Git(#[from] git2::Error),
+
+    /// An I/O operation failed
+    #[error("{0}")]
+    Io(#[from] std::io::Error),
+
+    /// A value could not be read, or written, as JSON
+    #[error("{0}")]
+    Json(#[from] serde_json::Error),
+
+    /// A value could not be read, or written, as YAML
+    #[error("{0}")]
+    Serialization(#[from] serde_yaml::Error),
+
+    /// An operation on the install log database failed
+    #[cfg(feature = "sqlite-log")]
+    #[error("{0}")]
+    Sql(#[from] rusqlite::Error),
+
+    /// Files could not be watched for changes
+    #[cfg(feature = "watch")]
+    #[error("{0}")]
+    Watch(#[from] notify::Error),
+}
+
+///
+/// The result type for all operations in this crate.
+///
+pub type Result<T> = std::result::Result<T, Error>;
@@ -28,10 +28,6 @@ More information is found in the [User Guide](https://simonkjohnston.life/rust-m
     unused_qualifications,
     unused_results,
 )]
-
-#[macro_use]
-extern crate error_chain;
-
 #[macro_use]
 extern crate lazy_static;
 
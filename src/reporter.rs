@@ -1,5 +1,22 @@
+use crate::error::Error;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
 use std::sync::RwLock;
 
+// ------------------------------------------------------------------------------------------------
+// Public Types
+// ------------------------------------------------------------------------------------------------
+
+///
+/// The continuous integration systems for which reported output can be annotated, so that each
+/// package set is a collapsible group in the CI log and failures are surfaced as annotations.
+///
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CiFormat {
+    /// GitHub Actions workflow commands, `::group::` and `::error::`.
+    GitHub,
+}
+
 // ------------------------------------------------------------------------------------------------
 // Public Macros
 // ------------------------------------------------------------------------------------------------
@@ -26,12 +43,25 @@ macro_rules! ereportln {
     })
 }
 
+///
+/// The reporting settings, those set by `set_is_interactive`, `set_show_command_output`, and
+/// `set_ci_format`, as a single value that can be passed to the code that reports to the user.
+///
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Reporter {
+    is_interactive: bool,
+    show_command_output: bool,
+    ci_format: Option<CiFormat>,
+}
+
 // ------------------------------------------------------------------------------------------------
 // Public Functions
 // ------------------------------------------------------------------------------------------------
 
 lazy_static! {
     static ref IS_INTERACTIVE: RwLock<bool> = RwLock::new(false);
+    static ref SHOW_COMMAND_OUTPUT: RwLock<bool> = RwLock::new(false);
+    static ref CI_FORMAT: RwLock<Option<CiFormat>> = RwLock::new(None);
 }
 
 ///
@@ -47,21 +77,250 @@ pub fn set_is_interactive(is_interactive: bool) {
 /// Returns whether the library is part of an interactive tool or not.
 ///
 pub fn is_interactive() -> bool {
-    reportln!("{}", "str");
     *IS_INTERACTIVE.read().unwrap()
 }
 
-#[doc(hidden)]
-pub fn report_message(msg: &str, error: bool) {
+///
+/// Set whether the output of installer commands and scripts is reported as it is written, or only
+/// logged at level `debug`.
+///
+pub fn set_show_command_output(show_command_output: bool) {
+    let mut inner = SHOW_COMMAND_OUTPUT.write().unwrap();
+    *inner = show_command_output;
+}
+
+///
+/// Returns whether the output of installer commands and scripts is reported as it is written.
+///
+pub fn show_command_output() -> bool {
+    *SHOW_COMMAND_OUTPUT.read().unwrap()
+}
+
+///
+/// Set the continuous integration system reported output is annotated for, if any.
+///
+pub fn set_ci_format(ci_format: Option<CiFormat>) {
+    let mut inner = CI_FORMAT.write().unwrap();
+    *inner = ci_format;
+}
+
+///
+/// Returns the continuous integration system reported output is annotated for, if any.
+///
+pub fn ci_format() -> Option<CiFormat> {
+    *CI_FORMAT.read().unwrap()
+}
+
+///
+/// Start a collapsible group, named `title`, in the CI log; all output reported until the
+/// matching `report_group_end` is part of the group. This does nothing unless a CI format has
+/// been set and the library is part of an interactive tool.
+///
+pub fn report_group_start(title: &str) {
     if is_interactive() {
-        if error {
-            eprintln!("{}", msg);
-        } else {
-            println!("{}", msg);
+        if let Some(CiFormat::GitHub) = ci_format() {
+            println!("::group::{}", escape_github_data(title));
+        }
+    }
+}
+
+///
+/// End the collapsible group started by `report_group_start`.
+///
+pub fn report_group_end() {
+    if is_interactive() {
+        if let Some(CiFormat::GitHub) = ci_format() {
+            println!("::endgroup::");
+        }
+    }
+}
+
+///
+/// Surface a failure, with a short `title` and the full `message`, as an annotation on the CI
+/// run. This does nothing unless a CI format has been set and the library is part of an
+/// interactive tool; the failure should be reported in the usual way as well.
+///
+pub fn report_failure(title: &str, message: &str) {
+    if is_interactive() {
+        if let Some(CiFormat::GitHub) = ci_format() {
+            println!("{}", github_error_command(title, message));
         }
+    }
+}
+
+///
+/// Report a single line of output from a command, prefixed with `[prefix]` so that the output of
+/// several commands remains attributable when interleaved. If command output is not shown this
+/// logs the line at level `debug`.
+///
+pub fn report_output_line(prefix: &str, line: &str, error: bool) {
+    if show_command_output() {
+        report_message(&format!("[{}] {}", prefix, line), error);
     } else if error {
-        error!("{}", msg);
+        debug!("[{}] stderr: {}", prefix, line);
     } else {
-        info!("{}", msg);
+        debug!("[{}] stdout: {}", prefix, line);
+    }
+}
+
+#[doc(hidden)]
+pub fn report_message(msg: &str, error: bool) {
+    Reporter::current().report(msg, error)
+}
+
+// ------------------------------------------------------------------------------------------------
+// Implementations
+// ------------------------------------------------------------------------------------------------
+
+impl Reporter {
+    /// Create a reporter for an interactive tool, or not, with command output hidden and no CI
+    /// annotations.
+    pub fn new(is_interactive: bool) -> Self {
+        Self {
+            is_interactive,
+            ..Default::default()
+        }
+    }
+
+    /// Return the reporter for the current settings.
+    pub fn current() -> Self {
+        Self {
+            is_interactive: is_interactive(),
+            show_command_output: show_command_output(),
+            ci_format: ci_format(),
+        }
+    }
+
+    /// Show the output of installer commands and scripts as it is written.
+    pub fn with_show_command_output(self, show_command_output: bool) -> Self {
+        Self {
+            show_command_output,
+            ..self
+        }
+    }
+
+    /// Annotate reported output for the continuous integration system `ci_format`.
+    pub fn with_ci_format(self, ci_format: Option<CiFormat>) -> Self {
+        Self { ci_format, ..self }
+    }
+
+    /// Make these the current settings, used by the `reportln` and `ereportln` macros and the
+    /// `report_*` functions.
+    pub fn install(&self) {
+        set_is_interactive(self.is_interactive);
+        set_show_command_output(self.show_command_output);
+        set_ci_format(self.ci_format);
+    }
+
+    /// Returns whether this reporter is part of an interactive tool or not.
+    pub fn is_interactive(&self) -> bool {
+        self.is_interactive
+    }
+
+    /// Returns whether the output of installer commands and scripts is reported as it is written.
+    pub fn show_command_output(&self) -> bool {
+        self.show_command_output
+    }
+
+    /// Returns the continuous integration system reported output is annotated for, if any.
+    pub fn ci_format(&self) -> Option<CiFormat> {
+        self.ci_format
+    }
+
+    /// Report `msg` to the user, as `reportln` does but using this reporter's settings.
+    pub fn message(&self, msg: &str) {
+        self.report(msg, false)
+    }
+
+    /// Report the error `msg` to the user, as `ereportln` does but using this reporter's settings.
+    pub fn error(&self, msg: &str) {
+        self.report(msg, true)
+    }
+
+    fn report(&self, msg: &str, error: bool) {
+        if self.is_interactive {
+            if error {
+                eprintln!("{}", msg);
+            } else {
+                println!("{}", msg);
+            }
+        } else if error {
+            error!("{}", msg);
+        } else {
+            info!("{}", msg);
+        }
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+
+impl Display for CiFormat {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Self::GitHub => "github",
+            }
+        )
+    }
+}
+
+impl FromStr for CiFormat {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "github" => Ok(Self::GitHub),
+            _ => Err(Error::InvalidFormatString(s.to_string())),
+        }
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+// Private Functions
+// ------------------------------------------------------------------------------------------------
+
+fn github_error_command(title: &str, message: &str) -> String {
+    format!(
+        "::error title={}::{}",
+        escape_github_property(title),
+        escape_github_data(message)
+    )
+}
+
+// See https://github.com/actions/toolkit/blob/main/packages/core/src/command.ts
+fn escape_github_data(s: &str) -> String {
+    s.replace('%', "%25")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+fn escape_github_property(s: &str) -> String {
+    escape_github_data(s)
+        .replace(':', "%3A")
+        .replace(',', "%2C")
+}
+
+// ------------------------------------------------------------------------------------------------
+// Unit Tests
+// ------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_github_error_command() {
+        assert_eq!(
+            github_error_command(
+                "install failed: tools/rust",
+                "exit status: 1, 100% broken\ntry again"
+            ),
+            "::error title=install failed%3A tools/rust::exit status: 1, 100%25 broken%0Atry again"
+        );
+        assert_eq!(CiFormat::from_str("github").unwrap(), CiFormat::GitHub);
+        assert!(CiFormat::from_str("gitlab").is_err());
     }
 }
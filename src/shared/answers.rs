@@ -0,0 +1,179 @@
+/*!
+The answers given by the user to the prompts for a package set's `prompt-vars`. Answers are
+specific to a machine, and so are kept in the tool's local data directory rather than in the
+package repository; once answered a variable is not asked again on this machine.
+*/
+
+use crate::error::{Error, Result};
+use crate::reporter::is_interactive;
+use crate::shared::{dirs, prompt_optional, FileSystemResource, PackageSet, Scope};
+use crate::APP_NAME;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs::read_to_string;
+use std::io::IsTerminal;
+use std::path::PathBuf;
+
+// ------------------------------------------------------------------------------------------------
+// Public Types
+// ------------------------------------------------------------------------------------------------
+
+///
+/// The answers to prompted variables, keyed by the variable name; a variable declared by more
+/// than one package set is only asked once.
+///
+#[derive(Deserialize, Serialize, Clone, Debug, Default, PartialEq)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct PromptAnswers {
+    #[serde(skip)]
+    path: PathBuf,
+    answers: BTreeMap<String, String>,
+}
+
+///
+/// The answers file name.
+///
+pub const ANSWERS_FILE: &str = "answers.yml";
+
+// ------------------------------------------------------------------------------------------------
+// Implementations
+// ------------------------------------------------------------------------------------------------
+
+impl FileSystemResource for PromptAnswers {
+    fn default_path(scope: Scope) -> PathBuf {
+        dirs::data_local_dir_for(scope, APP_NAME)
+            .unwrap()
+            .join(ANSWERS_FILE)
+    }
+
+    fn open_from(answers_file: PathBuf) -> Result<Self> {
+        if answers_file.is_file() {
+            info!("PromptAnswers::open_from loading from {:?}", answers_file);
+            let answers: Option<PromptAnswers> =
+                serde_yaml::from_str(&read_to_string(&answers_file)?)?;
+            Ok(Self {
+                path: answers_file,
+                ..answers.unwrap_or_default()
+            })
+        } else {
+            debug!(
+                "PromptAnswers::open_from no file {:?}, no answers",
+                answers_file
+            );
+            Ok(Self {
+                path: answers_file,
+                ..Default::default()
+            })
+        }
+    }
+}
+
+impl PromptAnswers {
+    /// Return the path these answers were read from, and will be saved to.
+    pub fn path(&self) -> &PathBuf {
+        &self.path
+    }
+
+    /// Return the answer for the variable `name`, if it has been answered.
+    pub fn answer(&self, name: &str) -> Option<&String> {
+        self.answers.get(name)
+    }
+
+    /// Record the answer for the variable `name`, replacing any previous answer.
+    pub fn set_answer(&mut self, name: &str, answer: &str) {
+        let _ = self.answers.insert(name.to_string(), answer.to_string());
+    }
+
+    /// Remove the answer for the variable `name`, so that it is asked again.
+    pub fn remove_answer(&mut self, name: &str) {
+        let _ = self.answers.remove(name);
+    }
+
+    /// Return the answers to the package set's prompted variables, only those already answered
+    /// are included.
+    pub fn answers_for(&self, package_set: &PackageSet) -> HashMap<String, String> {
+        package_set
+            .prompt_vars()
+            .keys()
+            .filter_map(|name| {
+                self.answer(name)
+                    .map(|answer| (name.clone(), answer.clone()))
+            })
+            .collect()
+    }
+
+    /// Ask the user for the value of any of the package set's prompted variables not already
+    /// answered, saving the answers. This fails if a variable is not answered, either because the
+    /// tool is not interactive or the user gave an empty answer.
+    pub fn ask_for(&mut self, package_set: &PackageSet) -> Result<()> {
+        let mut prompt_vars: Vec<(&String, &String)> = package_set
+            .prompt_vars()
+            .iter()
+            .filter(|(name, _)| self.answer(name).is_none())
+            .collect();
+        if prompt_vars.is_empty() {
+            return Ok(());
+        }
+        prompt_vars.sort();
+        let can_ask = is_interactive() && std::io::stdin().is_terminal();
+        for (name, question) in prompt_vars {
+            let answer = if can_ask {
+                prompt_optional(question)?
+            } else {
+                None
+            };
+            match answer {
+                Some(answer) => self.set_answer(name, &answer),
+                None => {
+                    return Err(Error::PromptNotAnswered(
+                        name.clone(),
+                        package_set.name().to_string(),
+                    ))
+                }
+            }
+        }
+        self.save()
+    }
+
+    /// Save the answers back to the path they were read from.
+    pub fn save(&self) -> Result<()> {
+        debug!("PromptAnswers::save to {:?}", &self.path);
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&self.path, serde_yaml::to_string(self)?)?;
+        Ok(())
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+// Unit Tests
+// ------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shared::builders::{Builder, PackageSetBuilder};
+    use crate::shared::Name;
+    use pretty_assertions::assert_eq;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_answers_for_package_set() {
+        let work_dir = tempfile::tempdir().unwrap();
+        let answers_path = work_dir.path().join("answers.yml");
+        let package_set = PackageSetBuilder::named(Name::from_str("git").unwrap())
+            .prompt_var("git_email", "Your git email")
+            .prompt_var("git_name", "Your full name")
+            .build();
+
+        let mut answers = PromptAnswers::open_from(answers_path.clone()).unwrap();
+        answers.set_answer("git_email", "me@example.com");
+        answers.save().unwrap();
+
+        let answers = PromptAnswers::open_from(answers_path).unwrap();
+        let found = answers.answers_for(&package_set);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found.get("git_email").unwrap(), "me@example.com");
+    }
+}
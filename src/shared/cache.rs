@@ -0,0 +1,251 @@
+/*!
+A cache of parsed package sets, so that repeated commands do not re-read and re-parse every package
+set file in the repository. Each entry is keyed by the path of the package set file, and is only
+used while the file's modification time and length are unchanged; entries for files that no longer
+exist are removed whenever the cache is saved with new entries.
+
+The cache is kept in the tool's local data directory, and is discarded entirely if it cannot be
+read or was written by a different version of the tool; a cache can never cause a command to fail.
+*/
+
+use crate::error::Result;
+use crate::shared::packages::Readable;
+use crate::shared::{dirs, FileSystemResource, PackageSet, Scope};
+use crate::APP_NAME;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::read_to_string;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+// ------------------------------------------------------------------------------------------------
+// Public Types
+// ------------------------------------------------------------------------------------------------
+
+///
+/// The cache of parsed package sets, keyed by the path of each package set file.
+///
+#[derive(Deserialize, Serialize, Clone, Debug, Default)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct PackageSetCache {
+    #[serde(skip)]
+    path: PathBuf,
+    #[serde(skip)]
+    dirty: bool,
+    tool_version: String,
+    entries: BTreeMap<PathBuf, CachedPackageSet>,
+}
+
+///
+/// The cache file name.
+///
+pub const PACKAGE_SET_CACHE_FILE: &str = "package-sets.cache.json";
+
+///
+/// The modification time and length of a package set file, a cached package set is only used
+/// while these are unchanged.
+///
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub struct FileStamp {
+    modified_secs: u64,
+    modified_nanos: u32,
+    length: u64,
+}
+
+// ------------------------------------------------------------------------------------------------
+// Private Types
+// ------------------------------------------------------------------------------------------------
+
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+struct CachedPackageSet {
+    #[serde(flatten)]
+    stamp: FileStamp,
+    package_set: PackageSet,
+}
+
+// ------------------------------------------------------------------------------------------------
+// Implementations
+// ------------------------------------------------------------------------------------------------
+
+impl FileSystemResource for PackageSetCache {
+    fn default_path(scope: Scope) -> PathBuf {
+        dirs::data_local_dir_for(scope, APP_NAME)
+            .unwrap()
+            .join(PACKAGE_SET_CACHE_FILE)
+    }
+
+    fn open_from(cache_file: PathBuf) -> Result<Self> {
+        let cache: Option<PackageSetCache> = if cache_file.is_file() {
+            info!("PackageSetCache::open_from loading from {:?}", cache_file);
+            match read_to_string(&cache_file)
+                .map_err(|e| e.to_string())
+                .and_then(|data| serde_json::from_str(&data).map_err(|e| e.to_string()))
+            {
+                Ok(cache) => Some(cache),
+                Err(e) => {
+                    warn!(
+                        "PackageSetCache::open_from discarding unreadable cache {:?}, error: {}",
+                        cache_file, e
+                    );
+                    None
+                }
+            }
+        } else {
+            None
+        };
+        let cache = match cache {
+            Some(cache) if cache.tool_version == env!("CARGO_PKG_VERSION") => cache,
+            Some(_) => {
+                debug!("PackageSetCache::open_from discarding cache from another version");
+                Default::default()
+            }
+            None => Default::default(),
+        };
+        Ok(Self {
+            path: cache_file,
+            tool_version: env!("CARGO_PKG_VERSION").to_string(),
+            ..cache
+        })
+    }
+}
+
+impl PackageSetCache {
+    /// Return the path this cache was read from, and will be saved to.
+    pub fn path(&self) -> &PathBuf {
+        &self.path
+    }
+
+    /// Return the number of package sets in the cache.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns `true` if there are no package sets in the cache, else `false`.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Read the package set file at `path`, from the cache if the file has not changed since it
+    /// was cached, otherwise by parsing the file and caching the result.
+    pub fn read(&mut self, path: &PathBuf) -> Result<PackageSet> {
+        let stamp = FileStamp::of(path)?;
+        match self.get(path, &stamp) {
+            Some(package_set) => Ok(package_set),
+            None => {
+                let package_set = PackageSet::read(path)?;
+                self.insert(path, stamp, &package_set);
+                Ok(package_set)
+            }
+        }
+    }
+
+    /// Return the cached package set for the file at `path`, if it was cached with the same
+    /// `stamp`.
+    pub fn get(&self, path: &Path, stamp: &FileStamp) -> Option<PackageSet> {
+        match self.entries.get(path) {
+            Some(cached) if &cached.stamp == stamp => {
+                trace!("PackageSetCache::get: using cached {:?}", path);
+                let mut package_set = cached.package_set.clone();
+                package_set.set_path(path);
+                Some(package_set)
+            }
+            _ => None,
+        }
+    }
+
+    /// Cache the package set parsed from the file at `path`, the `stamp` should be taken before
+    /// the file is read so that a change while it is read is not missed.
+    pub fn insert(&mut self, path: &Path, stamp: FileStamp, package_set: &PackageSet) {
+        let _ = self.entries.insert(
+            path.to_path_buf(),
+            CachedPackageSet {
+                stamp,
+                package_set: package_set.clone(),
+            },
+        );
+        self.dirty = true;
+    }
+
+    /// Save the cache back to the path it was read from, if it has changed. Entries for files
+    /// that no longer exist are removed first.
+    pub fn save(&mut self) -> Result<()> {
+        if !self.dirty {
+            return Ok(());
+        }
+        self.entries.retain(|path, _| path.is_file());
+        debug!("PackageSetCache::save to {:?}", &self.path);
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let temp_path = self.path.with_extension("json.tmp");
+        std::fs::write(&temp_path, serde_json::to_string(self)?)?;
+        std::fs::rename(&temp_path, &self.path)?;
+        self.dirty = false;
+        Ok(())
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+
+impl FileStamp {
+    /// Return the stamp for the file at `path`.
+    pub fn of(path: &Path) -> Result<Self> {
+        let metadata = std::fs::metadata(path)?;
+        let modified = metadata
+            .modified()?
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        Ok(Self {
+            modified_secs: modified.as_secs(),
+            modified_nanos: modified.subsec_nanos(),
+            length: metadata.len(),
+        })
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+// Unit Tests
+// ------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_cache_invalidated_on_change() {
+        let work_dir = tempfile::tempdir().unwrap();
+        let dir = work_dir.path();
+        let set_path = dir.join("rust.yml");
+        let cache_path = dir.join(PACKAGE_SET_CACHE_FILE);
+        std::fs::write(
+            &set_path,
+            "name: rust\ntags: [dev]\nenv-vars:\n  RUST_LOG: info\nactions:\n  packages:\n    - name: ripgrep\n      kind:\n        language: rust\n",
+        )
+        .unwrap();
+
+        let mut cache = PackageSetCache::open_from(cache_path.clone()).unwrap();
+        assert!(cache.is_empty());
+        let parsed = cache.read(&set_path).unwrap();
+        cache.save().unwrap();
+
+        let mut cache = PackageSetCache::open_from(cache_path.clone()).unwrap();
+        assert_eq!(cache.len(), 1);
+        let cached = cache.read(&set_path).unwrap();
+        assert_eq!(cached, parsed);
+        assert_eq!(cached.path(), &set_path);
+        assert!(!cache.dirty);
+
+        std::fs::write(&set_path, "name: rust-tools\n").unwrap();
+        let changed = cache.read(&set_path).unwrap();
+        assert_eq!(changed.name().to_string(), "rust-tools");
+        assert!(cache.dirty);
+
+        std::fs::remove_file(&set_path).unwrap();
+        cache.save().unwrap();
+        let cache = PackageSetCache::open_from(cache_path).unwrap();
+        assert!(cache.is_empty());
+    }
+}
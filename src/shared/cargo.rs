@@ -0,0 +1,141 @@
+/*!
+Native integration with the Rust `cargo` installer. As `cargo install` is slow, even when a crate
+is already installed, this module parses the output of `cargo install --list` so that the
+installer registry can skip crates that do not need to be installed, or uninstalled.
+*/
+
+use crate::error::Result;
+use crate::shared::builders::{Builder, ShellCommandBuilder};
+use crate::shared::{Installer, Name, PackageKind};
+use std::collections::HashMap;
+
+// ------------------------------------------------------------------------------------------------
+// Public Types
+// ------------------------------------------------------------------------------------------------
+
+///
+/// The set of crates installed by `cargo install`, mapping each crate name to its installed
+/// version string.
+///
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct InstalledCrates(HashMap<String, String>);
+
+///
+/// The language name used by installers, and packages, of Rust crates.
+///
+pub const RUST_LANGUAGE: &str = "rust";
+
+// ------------------------------------------------------------------------------------------------
+// Public Functions
+// ------------------------------------------------------------------------------------------------
+
+///
+/// Return `true` if the installer is the one used for Rust crates, this is determined by the
+/// installer's package kind, `{language: rust}`.
+///
+pub fn is_cargo_installer(installer: &Installer) -> bool {
+    matches!(installer.kind(), PackageKind::Language(language) if language == *RUST_LANGUAGE)
+}
+
+// ------------------------------------------------------------------------------------------------
+// Implementations
+// ------------------------------------------------------------------------------------------------
+
+impl InstalledCrates {
+    /// Run `cargo install --list` and parse the result.
+    pub fn fetch() -> Result<Self> {
+        debug!("InstalledCrates::fetch: running `cargo install --list`");
+        let output = ShellCommandBuilder::program("cargo")
+            .args(&["install", "--list"])
+            .apply_environment_policy(false)
+            .build()
+            .execute_output()?;
+        Ok(Self::parse(&output))
+    }
+
+    ///
+    /// Parse the output of `cargo install --list`, this has the form below; each crate is listed
+    /// with it's version (and optionally source) followed by an indented list of binaries.
+    ///
+    /// ```text
+    /// mdbook v0.4.5:
+    ///     mdbook
+    /// ripgrep v12.1.1 (/Users/me/ripgrep):
+    ///     rg
+    /// ```
+    ///
+    pub fn parse(list_output: &str) -> Self {
+        Self(
+            list_output
+                .lines()
+                .filter(|line| !line.starts_with(char::is_whitespace))
+                .filter_map(|line| {
+                    let mut parts = line.trim_end_matches(':').split_whitespace();
+                    match (parts.next(), parts.next()) {
+                        (Some(name), Some(version)) => Some((
+                            name.to_string(),
+                            version.trim_start_matches('v').to_string(),
+                        )),
+                        _ => None,
+                    }
+                })
+                .collect(),
+        )
+    }
+
+    /// Return `true` if there are no installed crates, else `false`.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Return `true` if the named crate is installed, else `false`.
+    pub fn is_installed(&self, name: &Name) -> bool {
+        self.0.contains_key(&name.to_string())
+    }
+
+    /// Return the installed version of the named crate, if it is installed.
+    pub fn version(&self, name: &Name) -> Option<&String> {
+        self.0.get(&name.to_string())
+    }
+
+    /// Return an iterator over all installed crates, and their versions.
+    pub fn crates(&self) -> impl Iterator<Item = (&String, &String)> {
+        self.0.iter()
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+// Unit Tests
+// ------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_parse_install_list() {
+        let installed = InstalledCrates::parse(
+            r##"cargo-husky v1.5.0:
+    cargo-husky
+mdbook v0.4.5:
+    mdbook
+ripgrep v12.1.1 (/Users/me/ripgrep):
+    rg
+"##,
+        );
+        assert_eq!(installed.crates().count(), 3);
+        assert!(installed.is_installed(&Name::from_str("mdbook").unwrap()));
+        assert!(!installed.is_installed(&Name::from_str("rg").unwrap()));
+        assert_eq!(
+            installed.version(&Name::from_str("ripgrep").unwrap()),
+            Some(&"12.1.1".to_string())
+        );
+    }
+
+    #[test]
+    fn test_parse_empty_list() {
+        assert!(InstalledCrates::parse("").is_empty());
+    }
+}
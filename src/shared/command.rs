@@ -1,18 +1,152 @@
-use crate::error::{ErrorKind, Result};
-use crate::shared::default_vars;
-use crate::shared::env::{var_string_replace, vars_to_env_vars};
+use crate::error::{Error, Result};
+use crate::reporter::report_output_line;
+use crate::shared::builders::{Builder, ShellCommandBuilder};
+use crate::shared::env::{
+    var_references, var_string_replace, vars_to_env_vars, ESCAPED_BRACES, VARIABLES,
+};
+use crate::shared::interrupt::{
+    is_interrupt_handler_installed, is_interrupted, kill_process_group, terminate_process_group,
+};
+use crate::shared::prompt_yes_no;
+use crate::shared::secrets::{is_secret_var, resolve_secret_var};
 use crate::APP_NAME;
 use log::LevelFilter;
-use regex::Regex;
-use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, VecDeque};
 use std::env;
-use std::path::PathBuf;
-use std::process::Command;
+use std::fmt::{Display, Formatter};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::os::unix::fs::PermissionsExt;
+use std::os::unix::process::CommandExt;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::sync::{Mutex, RwLock};
+use std::thread;
+use std::time::{Duration, Instant};
+
+// ------------------------------------------------------------------------------------------------
+// Public Types
+// ------------------------------------------------------------------------------------------------
+
+///
+/// Summary information captured from the execution of a command; when it started, how long it
+/// took, and how much output it produced.
+///
+#[derive(Clone, Debug, PartialEq)]
+pub struct CommandOutput {
+    started: time::OffsetDateTime,
+    duration: Duration,
+    output_bytes: usize,
+    output_lines: usize,
+}
+
+///
+/// A record of a single execution of a `ShellCommand`, whether or not it succeeded; its exit code,
+/// how long it took, and the end of its `stderr` output. The context is the command's output
+/// prefix, if any, such as `group/set/package`.
+///
+#[derive(Clone, Debug, PartialEq)]
+pub struct CommandRecord {
+    context: Option<String>,
+    command: String,
+    started: time::OffsetDateTime,
+    duration: Duration,
+    exit_code: Option<i32>,
+    stderr: String,
+}
+
+///
+/// A command to execute; a program, its arguments, and the environment it executes in. Instances
+/// are constructed with a `ShellCommandBuilder`, and may be executed any number of times.
+///
+#[derive(Clone, Debug, PartialEq)]
+pub struct ShellCommand {
+    program: String,
+    args: Vec<String>,
+    current_dir: Option<PathBuf>,
+    env_vars: HashMap<String, String>,
+    apply_environment_policy: bool,
+    timeout: Option<Duration>,
+    capture: CaptureMode,
+    output_prefix: Option<String>,
+    input: Option<String>,
+}
+
+///
+/// Determines how the standard input and output streams of a `ShellCommand` are handled.
+///
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CaptureMode {
+    /// The command has no input, and its output is captured; `stdout` is returned to the caller
+    /// and both `stdout` and `stderr` are logged. This is the default.
+    Capture,
+    /// The command is attached to the terminal, inheriting all three streams from the tool; this
+    /// is required for interactive commands such as an editor or shell.
+    Inherit,
+    /// The command inherits `stdin` and `stderr` from the tool, so that it may prompt the user,
+    /// and only its `stdout` is captured; this is returned to the caller, but is not logged, for
+    /// example the output of a secret store that may ask to be unlocked.
+    Stdout,
+}
+
+///
+/// The policy applied to the environment of commands spawned by the tool. By default commands
+/// inherit the tool's entire environment; when a policy is configured, and `sanitize` is `true`,
+/// the environment is cleared except for the variables in `DEFAULT_ALLOWED_VARS` and in the
+/// policy's own `allow` list. A name ending in `*` allows all variables with that prefix.
+///
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct EnvironmentPolicy {
+    sanitize: bool,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    allow: Vec<String>,
+}
+
+///
+/// The variables always passed to commands when the environment is sanitized.
+///
+pub const DEFAULT_ALLOWED_VARS: &[&str] = &[
+    "PATH",
+    "HOME",
+    "USER",
+    "LOGNAME",
+    "SHELL",
+    "TERM",
+    "LANG",
+    "LC_*",
+    "TMPDIR",
+    "http_proxy",
+    "https_proxy",
+    "ftp_proxy",
+    "all_proxy",
+    "no_proxy",
+    "HTTP_PROXY",
+    "HTTPS_PROXY",
+    "FTP_PROXY",
+    "ALL_PROXY",
+    "NO_PROXY",
+    "MCFG_*",
+];
+
+///
+/// The maximum number of bytes of `stderr` output kept in a `CommandRecord`, output beyond this is
+/// dropped from the start so that the final, usually most relevant, lines are kept.
+///
+pub const MAX_RECORDED_STDERR: usize = 2048;
 
 // ------------------------------------------------------------------------------------------------
 // Public Functions
 // ------------------------------------------------------------------------------------------------
 
+///
+/// Remove, and return, the records of all commands executed since this was last called. Only the
+/// most recent 1,000 records are kept between calls.
+///
+pub fn take_command_records() -> Vec<CommandRecord> {
+    COMMAND_RECORDS.lock().unwrap().drain(..).collect()
+}
+
 ///
 /// Return the currently selected shell for this terminal session.
 ///
@@ -22,32 +156,255 @@ pub fn user_shell() -> String {
 }
 
 ///
-/// Execute a shell interactively, the shell to run is taken from `user_shell`.
+/// Returns `true` if the shell `shell`, such as `bash` or `fish`, is present on the local machine;
+/// that is, found on the `PATH`.
+///
+pub fn is_shell_present(shell: &str) -> bool {
+    find_executable(shell).is_some()
+}
+
 ///
-pub fn execute_interactive_shell(in_dir: PathBuf) -> Result<()> {
+/// Set the environment policy applied to spawned commands, usually that selected by the tool
+/// configuration file; by default no policy is applied.
+///
+pub fn set_environment_policy(policy: Option<EnvironmentPolicy>) {
+    *ENVIRONMENT_POLICY.write().unwrap() = policy;
+}
+
+///
+/// Execute a shell interactively, with the variables `variable_replacements` set in its
+/// environment; the shell to run is taken from `user_shell`.
+///
+pub fn execute_interactive_shell(
+    in_dir: PathBuf,
+    variable_replacements: &HashMap<String, String>,
+) -> Result<()> {
     debug!("execute_interactive_shell ({:?}", in_dir);
-    let program = user_shell();
-    let mut command = Command::new(&program);
-    let _ = command
-        .envs(vars_to_env_vars(&default_vars(), &APP_NAME.to_uppercase()))
-        .current_dir(in_dir);
-    execute(&mut command, &program)
+    let _ = ShellCommandBuilder::program(&user_shell())
+        .envs(&vars_to_env_vars(
+            variable_replacements,
+            &APP_NAME.to_uppercase(),
+        ))
+        .current_dir(&in_dir)
+        .capture(CaptureMode::Inherit)
+        .apply_environment_policy(false)
+        .build()
+        .execute()?;
+    Ok(())
+}
+
+///
+/// Edit the provided file, as `edit_file`, then validate it using `check` which returns a list of
+/// problems. While problems are found they are reported and the user is offered the chance to
+/// reopen the editor to fix them.
+///
+pub fn edit_file_checked<F>(file_path: &PathBuf, check: F) -> Result<()>
+where
+    F: Fn(&Path) -> Vec<String>,
+{
+    loop {
+        edit_file(file_path)?;
+        let problems = check(file_path);
+        if problems.is_empty() {
+            return Ok(());
+        }
+        eprintln!("The file {:?} has problems:", file_path);
+        for problem in &problems {
+            eprintln!("  {}", problem);
+        }
+        if !prompt_yes_no("Reopen the editor to fix these problems?", true)? {
+            eprintln!("Warning: the file {:?} was left with problems", file_path);
+            return Ok(());
+        }
+    }
 }
 
 ///
-/// Execute a script string using a shell, the shell to run is taken from `user_shell`.
+/// Execute a script string using a shell, the shell to run is taken from `user_shell`. On success
+/// this returns a summary of the command's execution.
 pub fn execute_shell_command(
     script_string: &str,
     variable_replacements: &HashMap<String, String>,
-) -> Result<()> {
+) -> Result<CommandOutput> {
     debug!("execute_shell_command ({:?}, ...)", script_string);
-    let program = user_shell();
-    let mut command = prepare(script_string, variable_replacements);
-    execute(&mut command, &program)
+    ShellCommandBuilder::script(script_string, variable_replacements)
+        .build()
+        .execute()
+}
+
+///
+/// Execute a script string using a shell, as `execute_shell_command` does, but on success this
+/// returns the text written by the command to `stdout`.
+///
+pub fn execute_shell_command_output(
+    script_string: &str,
+    variable_replacements: &HashMap<String, String>,
+) -> Result<String> {
+    debug!("execute_shell_command_output ({:?}, ...)", script_string);
+    ShellCommandBuilder::script(script_string, variable_replacements)
+        .build()
+        .execute_output()
+}
+
+///
+/// Return the path of the executable file for `program`; a program name containing a `/` is
+/// checked as given, otherwise each directory in `PATH` is searched in turn.
+///
+pub fn find_executable(program: &str) -> Option<PathBuf> {
+    let is_executable = |path: &Path| {
+        path.metadata()
+            .map(|metadata| metadata.is_file() && metadata.permissions().mode() & 0o111 != 0)
+            .unwrap_or_default()
+    };
+    if program.contains('/') {
+        let path = PathBuf::from(program);
+        if is_executable(&path) {
+            Some(path)
+        } else {
+            None
+        }
+    } else {
+        env::split_paths(&env::var_os("PATH")?)
+            .map(|dir| dir.join(program))
+            .find(|path| is_executable(path))
+    }
+}
+
+///
+/// Return the environment of a command spawned with the variables `variable_replacements`; the
+/// tool's own environment, with the configured `EnvironmentPolicy` applied, and the variables
+/// added as `vars_to_env_vars` does.
+///
+pub fn command_environment(
+    variable_replacements: &HashMap<String, String>,
+) -> BTreeMap<String, String> {
+    let mut environment: BTreeMap<String, String> = env::vars_os()
+        .map(|(name, value)| {
+            (
+                name.to_string_lossy().to_string(),
+                value.to_string_lossy().to_string(),
+            )
+        })
+        .filter(|(name, _)| match &*ENVIRONMENT_POLICY.read().unwrap() {
+            Some(policy) => policy.is_allowed(name),
+            None => true,
+        })
+        .collect();
+    environment.extend(vars_to_env_vars(
+        variable_replacements,
+        &APP_NAME.to_uppercase(),
+    ));
+    environment
 }
 
 ///
-/// Return the currently selected editor for this terminal session.
+/// Execute an argument vector directly, without a shell; the first element is the program to
+/// execute. Variables are replaced in each element individually, so a value containing spaces, or
+/// shell meta-characters, is always passed as a single argument. On success this returns a summary
+/// of the command's execution.
+///
+pub fn execute_command_args(
+    args: &[String],
+    variable_replacements: &HashMap<String, String>,
+) -> Result<CommandOutput> {
+    debug!("execute_command_args ({:?}, ...)", args);
+    ShellCommandBuilder::command_args(args, variable_replacements)?
+        .build()
+        .execute()
+}
+
+///
+/// Execute an argument vector directly, as `execute_command_args` does, but on success this
+/// returns the text written by the command to `stdout`.
+///
+pub fn execute_command_args_output(
+    args: &[String],
+    variable_replacements: &HashMap<String, String>,
+) -> Result<String> {
+    debug!("execute_command_args_output ({:?}, ...)", args);
+    ShellCommandBuilder::command_args(args, variable_replacements)?
+        .build()
+        .execute_output()
+}
+
+///
+/// Execute a program directly, without a shell, with the provided arguments. On success this
+/// returns a summary of the command's execution.
+///
+pub fn execute_program(program: &str, args: &[&str]) -> Result<CommandOutput> {
+    debug!("execute_program ({:?}, {:?})", program, args);
+    ShellCommandBuilder::program(program)
+        .args(args)
+        .build()
+        .execute()
+}
+
+///
+/// Escape `value` so that a POSIX shell reads it as a single word with exactly this content.
+/// Values made up only of characters with no special meaning to the shell are returned unchanged,
+/// all others are enclosed in single quotes, within which backslashes, `$`, backticks, and newlines
+/// are all literal; any embedded single quote is written as `'\''`.
+///
+pub fn shell_escape(value: &str) -> String {
+    if !value.is_empty()
+        && value
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || SHELL_SAFE_CHARS.contains(c))
+    {
+        value.to_string()
+    } else {
+        format!("'{}'", escape_for(value, Quoting::Single))
+    }
+}
+
+///
+/// Replace variables in a script string, as `var_string_replace` does, escaping each value for
+/// the shell quoting context in which the variable appears. Within single quotes any single quote
+/// in the value is escaped, and within double quotes any backslash, `"`, `$`, or backtick is
+/// escaped; so a quoted variable always expands to exactly its value. Unquoted variables are
+/// replaced without escaping, so that a value such as `package_args` may expand to several words.
+///
+pub fn shell_var_replace(script_string: &str, vars: &HashMap<String, String>) -> String {
+    let mut out_string = String::with_capacity(script_string.len());
+    let mut scanner = QuotingScanner::new();
+
+    let mut from: usize = 0;
+    for capture in VARIABLES.captures_iter(script_string) {
+        let var = capture.get(0).unwrap();
+        let text = &script_string[from..var.start()];
+        out_string.push_str(text);
+        from = var.end();
+        let quoting = scanner.scan(text);
+        if capture.get(1).is_some() {
+            out_string.push_str(&ESCAPED_BRACES[1..]);
+            let _ = scanner.scan(&ESCAPED_BRACES[1..]);
+            continue;
+        }
+        scanner.skip();
+
+        let var_name = &var.as_str()[2..var.as_str().len() - 2];
+        match vars.get(var_name) {
+            // A secret is always a single word, whatever it contains.
+            Some(value) if is_secret_var(var_name) && quoting == Quoting::None => {
+                out_string.push_str(&shell_escape(value))
+            }
+            Some(value) => out_string.push_str(&escape_for(value, quoting)),
+            // Secrets are only resolved when the command is executed.
+            None if is_secret_var(var_name) => out_string.push_str(var.as_str()),
+            None => {
+                warn!("No variable named {:?} in replacements", var_name);
+                out_string.push_str(var_name)
+            }
+        }
+    }
+    out_string.push_str(&script_string[from..]);
+
+    out_string
+}
+
+///
+/// Return the currently selected editor for this terminal session, this may be a command line
+/// with arguments, such as `code --wait` or `emacsclient -t`.
 ///
 pub fn user_editor() -> String {
     match (env::var("VISUAL"), env::var("EDITOR")) {
@@ -58,100 +415,823 @@ pub fn user_editor() -> String {
 }
 
 ///
-/// Edit the provided file, the editor to run is taken from `user_editor`.
+/// Edit the provided file, the editor to run is taken from `user_editor`. The editor is attached
+/// to the terminal, and this function waits for it to exit.
 ///
 pub fn edit_file(file_path: &PathBuf) -> Result<()> {
     debug!("edit_file ({:?})", file_path);
-    let program = user_editor();
-    let mut command = Command::new(&program);
-    let _ = command.arg(file_path);
-    execute(&mut command, &program)
+    let _ = ShellCommandBuilder::command_line(&user_editor())?
+        .arg(&file_path.to_string_lossy())
+        .capture(CaptureMode::Inherit)
+        .apply_environment_policy(false)
+        .build()
+        .execute()?;
+    Ok(())
 }
 
 // ------------------------------------------------------------------------------------------------
-// Private Functions
+// Implementations
 // ------------------------------------------------------------------------------------------------
 
-lazy_static! {
-    static ref UNQUOTED: Regex = Regex::new(r#"((^|[^\\])")"#).unwrap();
+impl CommandOutput {
+    pub(crate) fn new(
+        started: time::OffsetDateTime,
+        duration: Duration,
+        output_bytes: usize,
+        output_lines: usize,
+    ) -> Self {
+        Self {
+            started,
+            duration,
+            output_bytes,
+            output_lines,
+        }
+    }
+
+    /// Return the date and time the command was started.
+    pub fn started(&self) -> &time::OffsetDateTime {
+        &self.started
+    }
+
+    /// Return the wall-clock time taken to execute the command.
+    pub fn duration(&self) -> &Duration {
+        &self.duration
+    }
+
+    /// Return the number of bytes written by the command to `stdout` and `stderr`.
+    pub fn output_bytes(&self) -> usize {
+        self.output_bytes
+    }
+
+    /// Return the number of non-empty lines written by the command to `stdout` and `stderr`.
+    pub fn output_lines(&self) -> usize {
+        self.output_lines
+    }
 }
 
-const SHELL_ARG: &str = "-c";
+// ------------------------------------------------------------------------------------------------
 
-fn prepare(script_string: &str, variables: &HashMap<String, String>) -> Command {
-    debug!("prepare({:?}, ...)", script_string);
-    let safe_script = make_safe(&var_string_replace(script_string, variables));
+impl CommandRecord {
+    // Only used by the install log formats, which may all be disabled.
+    #[cfg_attr(
+        not(any(feature = "sqlite-log", feature = "jsonl-log")),
+        allow(dead_code)
+    )]
+    pub(crate) fn new(
+        context: Option<String>,
+        command: String,
+        started: time::OffsetDateTime,
+        duration: Duration,
+        exit_code: Option<i32>,
+        stderr: String,
+    ) -> Self {
+        Self {
+            context,
+            command,
+            started,
+            duration,
+            exit_code,
+            stderr,
+        }
+    }
+
+    /// Return the context the command was executed in, such as `group/set/package`, if any.
+    pub fn context(&self) -> &Option<String> {
+        &self.context
+    }
+
+    /// Return the command line executed.
+    pub fn command(&self) -> &String {
+        &self.command
+    }
+
+    /// Return the date and time the command was started.
+    pub fn started(&self) -> &time::OffsetDateTime {
+        &self.started
+    }
+
+    /// Return the wall-clock time taken to execute the command.
+    pub fn duration(&self) -> &Duration {
+        &self.duration
+    }
+
+    /// Return the command's exit code; this is `None` if the command could not be started, was
+    /// killed by a signal, or timed out.
+    pub fn exit_code(&self) -> &Option<i32> {
+        &self.exit_code
+    }
 
-    let mut command = Command::new(user_shell());
-    let _ = command
-        .envs(vars_to_env_vars(variables, &APP_NAME.to_uppercase()))
-        .args(vec![SHELL_ARG, &safe_script]);
-    command
+    /// Return the end of the command's `stderr` output, at most `MAX_RECORDED_STDERR` bytes.
+    pub fn stderr(&self) -> &String {
+        &self.stderr
+    }
+
+    /// Return `true` if the command exited with the code `0`.
+    pub fn is_success(&self) -> bool {
+        self.exit_code == Some(0)
+    }
 }
 
-fn execute(command: &mut Command, program: &str) -> Result<()> {
-    debug!("execute({:?})", command);
-    let result = command.output();
+// ------------------------------------------------------------------------------------------------
+
+impl Display for ShellCommand {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            shell_words::join(std::iter::once(&self.program).chain(self.args.iter()))
+        )
+    }
+}
+
+impl ShellCommand {
+    /// Return the program to execute.
+    pub fn program(&self) -> &String {
+        &self.program
+    }
+
+    /// Return the arguments passed to the program.
+    pub fn args(&self) -> &Vec<String> {
+        &self.args
+    }
 
-    match result {
-        Ok(output) => {
-            if log::max_level() >= LevelFilter::Debug {
-                for line in String::from_utf8(output.stdout).unwrap().split('\n') {
+    /// Return the directory the program executes in, if not the current directory.
+    pub fn current_dir(&self) -> &Option<PathBuf> {
+        &self.current_dir
+    }
+
+    /// Return the variables added to the program's environment.
+    pub fn env_vars(&self) -> &HashMap<String, String> {
+        &self.env_vars
+    }
+
+    /// Return `true` if the configured `EnvironmentPolicy`, if any, is applied to the program's
+    /// environment.
+    pub fn is_environment_policy_applied(&self) -> bool {
+        self.apply_environment_policy
+    }
+
+    /// Return the time after which the program is killed, if any.
+    pub fn timeout(&self) -> &Option<Duration> {
+        &self.timeout
+    }
+
+    /// Return the way the program's input and output are handled.
+    pub fn capture_mode(&self) -> CaptureMode {
+        self.capture
+    }
+
+    /// Return the prefix with which each line of captured output is reported, if any.
+    pub fn output_prefix(&self) -> &Option<String> {
+        &self.output_prefix
+    }
+
+    /// Return the text written to the program's `stdin`, if any.
+    pub fn input(&self) -> &Option<String> {
+        &self.input
+    }
+
+    /// Execute the command, waiting for it to complete. On success this returns a summary of the
+    /// command's execution.
+    pub fn execute(&self) -> Result<CommandOutput> {
+        self.execute_capture()
+            .map(|(command_output, _, _)| command_output)
+    }
+
+    /// Execute the command, as `execute` does, but on success this returns the text written by
+    /// the command to `stdout`; this is always empty if the output is not captured.
+    pub fn execute_output(&self) -> Result<String> {
+        let (_, stdout, _) = self.execute_capture()?;
+        Ok(String::from_utf8_lossy(&stdout).into_owned())
+    }
+
+    /// Execute the command, as `execute` does, but on success this returns the text written by
+    /// the command to `stdout`, and to `stderr`; each is empty if it is not captured.
+    pub fn execute_all_output(&self) -> Result<(String, String)> {
+        let (_, stdout, stderr) = self.execute_capture()?;
+        Ok((
+            String::from_utf8_lossy(&stdout).into_owned(),
+            String::from_utf8_lossy(&stderr).into_owned(),
+        ))
+    }
+
+    fn execute_capture(&self) -> Result<(CommandOutput, Vec<u8>, Vec<u8>)> {
+        let mut command = Command::new(&self.program);
+        if self.apply_environment_policy {
+            apply_environment_policy(&mut command);
+        }
+        let _ = command.args(&self.resolve_secrets()?).envs(&self.env_vars);
+        if let Some(current_dir) = &self.current_dir {
+            let _ = command.current_dir(current_dir);
+        }
+        match self.capture {
+            CaptureMode::Capture => {
+                // The command leads its own process group, so that it, and any processes it
+                // starts, can be terminated together on a timeout or interrupt.
+                let _ = command
+                    .stdin(Stdio::null())
+                    .stdout(Stdio::piped())
+                    .stderr(Stdio::piped())
+                    .process_group(0);
+            }
+            CaptureMode::Inherit => {}
+            CaptureMode::Stdout => {
+                let _ = command.stdout(Stdio::piped());
+            }
+        }
+        if self.input.is_some() {
+            let _ = command.stdin(Stdio::piped());
+        }
+        // The command itself is not logged, its arguments may now include secrets.
+        debug!("ShellCommand::execute({})", self);
+
+        let started = time::OffsetDateTime::now_utc();
+        let timer = Instant::now();
+        let mut child = command.spawn().map_err(|err| {
+            error!("Error executing command {}, err: {:?}", self.program, err);
+            self.record(started, timer.elapsed(), None, err.to_string().as_bytes());
+            Error::CommandExecutionFailed(self.program.clone(), None)
+        })?;
+        // Write input, and read output, on separate threads, so that a full pipe cannot block the
+        // command.
+        if let (Some(input), Some(mut writer)) = (self.input.clone(), child.stdin.take()) {
+            let _ = thread::spawn(move || writer.write_all(input.as_bytes()));
+        }
+        let stdout = child
+            .stdout
+            .take()
+            .map(|reader| read_all(reader, self.output_prefix.clone(), false));
+        let stderr = child
+            .stderr
+            .take()
+            .map(|reader| read_all(reader, self.output_prefix.clone(), true));
+        let exit_status = self.wait(&mut child).inspect_err(|err| {
+            // the output threads are not joined, the pipes may be held open by a descendant.
+            self.record(started, timer.elapsed(), None, err.to_string().as_bytes());
+        })?;
+        let duration = timer.elapsed();
+        let stdout = stdout
+            .map(|reader| reader.join().unwrap_or_default())
+            .unwrap_or_default();
+        let stderr = stderr
+            .map(|reader| reader.join().unwrap_or_default())
+            .unwrap_or_default();
+
+        let command_output = CommandOutput {
+            started,
+            duration,
+            output_bytes: stdout.len() + stderr.len(),
+            output_lines: count_lines(&stdout) + count_lines(&stderr),
+        };
+        self.record(started, duration, exit_status.code(), &stderr);
+
+        if self.capture == CaptureMode::Capture
+            && self.output_prefix.is_none()
+            && log::max_level() >= LevelFilter::Debug
+        {
+            for line in String::from_utf8_lossy(&stdout).split('\n') {
+                if !line.is_empty() {
+                    debug!("stdout: {}", line);
+                }
+            }
+        }
+
+        if exit_status.success() {
+            if self.output_prefix.is_none() && log::max_level() >= LevelFilter::Debug {
+                for line in String::from_utf8_lossy(&stderr).split('\n') {
                     if !line.is_empty() {
-                        debug!("stdout: {}", line);
+                        warn!("stderr: {}", line);
                     }
                 }
             }
+            Ok((command_output, stdout, stderr))
+        } else {
+            error!(
+                "Error executing command {}, status: {:?}",
+                self.program, exit_status
+            );
+            for line in String::from_utf8_lossy(&stderr).split('\n') {
+                if !line.is_empty() {
+                    error!("stderr: {}", line);
+                }
+            }
+            Err(Error::CommandExecutionFailed(
+                self.program.clone(),
+                Some(exit_status),
+            ))
+        }
+    }
 
-            let exit_status = output.status;
-            if exit_status.success() {
-                if log::max_level() >= LevelFilter::Debug {
-                    for line in String::from_utf8(output.stderr).unwrap().split('\n') {
-                        if !line.is_empty() {
-                            warn!("stderr: {}", line);
+    // Replace any secret variables in the arguments with their values; this is only done as the
+    // command is spawned, so that secrets are never logged or recorded.
+    fn resolve_secrets(&self) -> Result<Vec<String>> {
+        let mut is_script = false;
+        self.args
+            .iter()
+            .map(|arg| {
+                let after_shell_arg = is_script;
+                is_script = arg == SHELL_ARG;
+                let references = var_references(arg);
+                if !references.iter().any(|var_name| is_secret_var(var_name)) {
+                    return Ok(arg.clone());
+                }
+                // Only secrets are replaced; any other reference, such as one produced by escaped
+                // braces in the original string, is replaced by itself.
+                let secrets = references
+                    .into_iter()
+                    .map(|var_name| {
+                        if is_secret_var(&var_name) {
+                            resolve_secret_var(&var_name).map(|value| (var_name, value))
+                        } else {
+                            Ok((var_name.clone(), format!("{{{{{}}}}}", var_name)))
                         }
+                    })
+                    .collect::<Result<HashMap<String, String>>>()?;
+                Ok(if after_shell_arg {
+                    shell_var_replace(arg, &secrets)
+                } else {
+                    var_string_replace(arg, &secrets)
+                })
+            })
+            .collect()
+    }
+
+    fn record(
+        &self,
+        started: time::OffsetDateTime,
+        duration: Duration,
+        exit_code: Option<i32>,
+        stderr: &[u8],
+    ) {
+        let stderr = &stderr[stderr.len().saturating_sub(MAX_RECORDED_STDERR)..];
+        let mut records = COMMAND_RECORDS.lock().unwrap();
+        if records.len() >= MAX_COMMAND_RECORDS {
+            let _ = records.pop_front();
+        }
+        records.push_back(CommandRecord {
+            context: self.output_prefix.clone(),
+            command: self.to_string(),
+            started,
+            duration,
+            exit_code,
+            stderr: String::from_utf8_lossy(stderr).into_owned(),
+        });
+    }
+
+    fn wait(&self, child: &mut Child) -> Result<ExitStatus> {
+        let interruptible =
+            self.capture == CaptureMode::Capture && is_interrupt_handler_installed();
+        if self.timeout.is_none() && !interruptible {
+            return Ok(child.wait()?);
+        }
+        let timer = Instant::now();
+        loop {
+            if let Some(exit_status) = child.try_wait()? {
+                return Ok(exit_status);
+            }
+            if interruptible && is_interrupted() {
+                warn!("Interrupted, terminating command {}", self.program);
+                self.terminate(child);
+                return Err(Error::Interrupted);
+            }
+            if let Some(timeout) = self.timeout {
+                if timer.elapsed() >= timeout {
+                    error!(
+                        "Command {} did not complete in {:?}, killing it",
+                        self.program, timeout
+                    );
+                    self.terminate(child);
+                    return Err(Error::CommandTimedOut(
+                        self.program.clone(),
+                        timeout.as_secs(),
+                    ));
+                }
+            }
+            thread::sleep(TIMEOUT_POLL_INTERVAL);
+        }
+    }
+
+    // Terminate the command's process group, allowing a short time for it to exit cleanly.
+    fn terminate(&self, child: &mut Child) {
+        if self.capture == CaptureMode::Capture {
+            terminate_process_group(child.id());
+            let timer = Instant::now();
+            while timer.elapsed() < TERMINATE_GRACE_PERIOD {
+                if let Ok(Some(_)) = child.try_wait() {
+                    return;
+                }
+                thread::sleep(TIMEOUT_POLL_INTERVAL);
+            }
+            kill_process_group(child.id());
+        } else {
+            let _ = child.kill();
+        }
+        let _ = child.wait();
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+
+impl Default for EnvironmentPolicy {
+    fn default() -> Self {
+        Self {
+            sanitize: true,
+            allow: Default::default(),
+        }
+    }
+}
+
+impl EnvironmentPolicy {
+    /// Return `true` if the environment of spawned commands is sanitized.
+    pub fn sanitize(&self) -> bool {
+        self.sanitize
+    }
+
+    /// Return the additional variables allowed, beyond `DEFAULT_ALLOWED_VARS`.
+    pub fn allow(&self) -> &Vec<String> {
+        &self.allow
+    }
+
+    /// Add an additional allowed variable name, or prefix pattern ending in `*`.
+    pub fn add_allowed(&mut self, name: &str) {
+        self.allow.push(name.to_string());
+    }
+
+    /// Return `true` if the variable `name` is passed to spawned commands under this policy.
+    pub fn is_allowed(&self, name: &str) -> bool {
+        !self.sanitize
+            || DEFAULT_ALLOWED_VARS
+                .iter()
+                .copied()
+                .chain(self.allow.iter().map(String::as_str))
+                .any(|pattern| match pattern.strip_suffix('*') {
+                    Some(prefix) => name.starts_with(prefix),
+                    None => name == pattern,
+                })
+    }
+
+    /// Apply this policy to the command; this must be done before any variables are added to the
+    /// command's environment, as they would also be cleared.
+    pub fn apply(&self, command: &mut Command) {
+        if self.sanitize {
+            let _ = command.env_clear();
+            for (name, value) in env::vars_os() {
+                if self.is_allowed(&name.to_string_lossy()) {
+                    let _ = command.env(name, value);
+                } else {
+                    trace!("EnvironmentPolicy::apply removing {:?}", name);
+                }
+            }
+        }
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+// Private Functions
+// ------------------------------------------------------------------------------------------------
+
+const SHELL_SAFE_CHARS: &str = "_-+=@%,.:/";
+
+const SHELL_WORD_BREAKS: &str = "|&;()";
+
+const SHELL_DOUBLE_QUOTE_SPECIALS: &str = "\\\"$`";
+
+const MAX_COMMAND_RECORDS: usize = 1_000;
+
+lazy_static! {
+    static ref COMMAND_RECORDS: Mutex<VecDeque<CommandRecord>> = Default::default();
+    static ref ENVIRONMENT_POLICY: RwLock<Option<EnvironmentPolicy>> = RwLock::new(None);
+}
+
+fn apply_environment_policy(command: &mut Command) {
+    if let Some(policy) = &*ENVIRONMENT_POLICY.read().unwrap() {
+        policy.apply(command);
+    }
+}
+
+const SHELL_ARG: &str = "-c";
+
+const TIMEOUT_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+const TERMINATE_GRACE_PERIOD: Duration = Duration::from_secs(2);
+
+// Read all of a command's output; if a prefix is provided each line is also reported as it is read.
+fn read_all<R: Read + Send + 'static>(
+    mut reader: R,
+    output_prefix: Option<String>,
+    is_stderr: bool,
+) -> thread::JoinHandle<Vec<u8>> {
+    thread::spawn(move || {
+        let mut buffer = Vec::default();
+        match output_prefix {
+            None => {
+                let _ = reader.read_to_end(&mut buffer);
+            }
+            Some(prefix) => {
+                let mut reader = BufReader::new(reader);
+                let mut line = Vec::default();
+                while let Ok(count) = reader.read_until(b'\n', &mut line) {
+                    if count == 0 {
+                        break;
+                    }
+                    let text = String::from_utf8_lossy(&line);
+                    let text = text.trim_end_matches(&['\r', '\n'][..]);
+                    if !text.is_empty() {
+                        report_output_line(&prefix, text, is_stderr);
                     }
+                    buffer.append(&mut line);
                 }
-                Ok(())
-            } else {
-                error!(
-                    "Error executing command {}, status: {:?}",
-                    program, exit_status
-                );
-                for line in String::from_utf8(output.stderr).unwrap().split('\n') {
-                    if !line.is_empty() {
-                        error!("stderr: {}", line);
+            }
+        }
+        buffer
+    })
+}
+
+// Split a command line, using shell quoting rules, into the program and its arguments.
+fn split_command_line(command_line: &str) -> Result<(String, Vec<String>)> {
+    match shell_words::split(command_line) {
+        Ok(mut words) if !words.is_empty() => {
+            let program = words.remove(0);
+            Ok((program, words))
+        }
+        _ => Err(Error::InvalidCommandString(command_line.to_string())),
+    }
+}
+
+fn count_lines(output: &[u8]) -> usize {
+    String::from_utf8_lossy(output)
+        .split('\n')
+        .filter(|line| !line.is_empty())
+        .count()
+}
+
+// The shell quoting state following the text scanned so far, this is only as complete as
+// required to determine how a variable's value is escaped.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Quoting {
+    None,
+    Single,
+    Double,
+    Comment,
+}
+
+// Tracks the quoting state across a script, including the nested contexts started by command
+// substitution, `$(...)`, and sub-shells, `(...)`.
+#[derive(Debug)]
+struct QuotingScanner {
+    quoting: Quoting,
+    outer: Vec<Quoting>,
+    escaped: bool,
+    dollar: bool,
+    word_start: bool,
+}
+
+impl QuotingScanner {
+    fn new() -> Self {
+        Self {
+            quoting: Quoting::None,
+            outer: Default::default(),
+            escaped: false,
+            dollar: false,
+            word_start: true,
+        }
+    }
+
+    fn scan(&mut self, text: &str) -> Quoting {
+        for c in text.chars() {
+            if self.escaped {
+                self.escaped = false;
+                self.dollar = false;
+                self.word_start = false;
+                continue;
+            }
+            match (self.quoting, c) {
+                (Quoting::None, '\\') | (Quoting::Double, '\\') => self.escaped = true,
+                (Quoting::None, '\'') => self.quoting = Quoting::Single,
+                (Quoting::None, '"') => self.quoting = Quoting::Double,
+                (Quoting::None, '#') if self.word_start => self.quoting = Quoting::Comment,
+                (Quoting::None, '(') => self.outer.push(Quoting::None),
+                (Quoting::Double, '(') if self.dollar => {
+                    self.outer.push(Quoting::Double);
+                    self.quoting = Quoting::None;
+                }
+                (Quoting::None, ')') => {
+                    if let Some(outer) = self.outer.pop() {
+                        self.quoting = outer;
                     }
                 }
-                Err(
-                    ErrorKind::CommandExecutionFailed(program.to_string(), Some(exit_status))
-                        .into(),
-                )
+                (Quoting::Single, '\'') | (Quoting::Double, '"') | (Quoting::Comment, '\n') => {
+                    self.quoting = Quoting::None
+                }
+                _ => {}
             }
+            self.dollar = c == '$' && !self.escaped;
+            self.word_start = !self.escaped && (c.is_whitespace() || SHELL_WORD_BREAKS.contains(c));
         }
-        Err(err) => {
-            error!("Error executing command {}, err: {:?}", program, err);
-            Err(ErrorKind::CommandExecutionFailed(program.to_string(), None).into())
+        self.quoting
+    }
+
+    // A variable's value is never at the start of a word, nor does it change the quoting.
+    fn skip(&mut self) {
+        self.escaped = false;
+        self.dollar = false;
+        self.word_start = false;
+    }
+}
+
+fn escape_for(value: &str, quoting: Quoting) -> String {
+    match quoting {
+        Quoting::Single => value.replace('\'', r#"'\''"#),
+        Quoting::Double => {
+            let mut escaped = String::with_capacity(value.len());
+            for c in value.chars() {
+                if SHELL_DOUBLE_QUOTE_SPECIALS.contains(c) {
+                    escaped.push('\\');
+                }
+                escaped.push(c);
+            }
+            escaped
         }
+        Quoting::None | Quoting::Comment => value.to_string(),
     }
 }
 
-fn make_safe(script_string: &str) -> String {
-    let mut out_string = String::new();
+// ------------------------------------------------------------------------------------------------
+// Modules
+// ------------------------------------------------------------------------------------------------
 
-    let mut from: usize = 0;
-    for capture in UNQUOTED.captures_iter(script_string) {
-        let var = capture.get(1).unwrap();
-        out_string.push_str(&script_string[from..var.start()]);
-        let unquoted = var.as_str();
-        let unquoted = &unquoted[..unquoted.len() - 1];
-        out_string.push_str(unquoted);
-        out_string.push_str("\\\"");
-        from = var.end();
+pub mod builders {
+    use super::{split_command_line, user_shell, CaptureMode, ShellCommand, SHELL_ARG};
+    use crate::error::{Error, Result};
+    use crate::shared::builders::Builder;
+    use crate::shared::env::{var_string_replace, vars_to_env_vars};
+    use crate::shared::shell_var_replace;
+    use crate::APP_NAME;
+    use std::collections::HashMap;
+    use std::path::Path;
+    use std::time::Duration;
+
+    // --------------------------------------------------------------------------------------------
+    // Public Types
+    // --------------------------------------------------------------------------------------------
+
+    ///
+    /// Provides a fluent interface for programmatic creation of [`ShellCommand`](../struct.ShellCommand.html)
+    /// instances.
+    ///
+    #[derive(Clone, Debug)]
+    pub struct ShellCommandBuilder(ShellCommand);
+
+    // --------------------------------------------------------------------------------------------
+    // Implementations
+    // --------------------------------------------------------------------------------------------
+
+    impl Builder for ShellCommandBuilder {
+        type Inner = ShellCommand;
+
+        fn build(&mut self) -> Self::Inner {
+            self.0.clone()
+        }
     }
-    out_string.push_str(&script_string[from..]);
 
-    out_string
+    impl ShellCommandBuilder {
+        /// Create a new instance to execute `program`, which is found using `PATH` if it is not a
+        /// path itself.
+        pub fn program(program: &str) -> Self {
+            Self(ShellCommand {
+                program: program.to_string(),
+                args: Default::default(),
+                current_dir: None,
+                env_vars: Default::default(),
+                apply_environment_policy: true,
+                timeout: None,
+                capture: CaptureMode::Capture,
+                output_prefix: None,
+                input: None,
+            })
+        }
+
+        /// Create a new instance to execute a script string using the shell from `user_shell`.
+        /// Variables are replaced in the script string, as `shell_var_replace` does, and are
+        /// also added to the environment, as `vars_to_env_vars` does.
+        pub fn script(
+            script_string: &str,
+            variable_replacements: &HashMap<String, String>,
+        ) -> Self {
+            let mut builder = Self::program(&user_shell());
+            let _ = builder
+                .arg(SHELL_ARG)
+                .arg(&shell_var_replace(script_string, variable_replacements))
+                .envs(&vars_to_env_vars(
+                    variable_replacements,
+                    &APP_NAME.to_uppercase(),
+                ));
+            builder
+        }
+
+        /// Create a new instance to execute an argument vector, where the first element is the
+        /// program. Variables are replaced in each element individually, and are also added to the
+        /// environment, as `vars_to_env_vars` does.
+        pub fn command_args(
+            args: &[String],
+            variable_replacements: &HashMap<String, String>,
+        ) -> Result<Self> {
+            let mut replaced = args
+                .iter()
+                .map(|arg| var_string_replace(arg, variable_replacements));
+            match replaced.next() {
+                Some(program) if !program.is_empty() => {
+                    let mut builder = Self::program(&program);
+                    for arg in replaced {
+                        let _ = builder.arg(&arg);
+                    }
+                    let _ = builder.envs(&vars_to_env_vars(
+                        variable_replacements,
+                        &APP_NAME.to_uppercase(),
+                    ));
+                    Ok(builder)
+                }
+                _ => Err(Error::InvalidCommandString(shell_words::join(args))),
+            }
+        }
+
+        /// Create a new instance from a command line, such as `code --wait`, split into the
+        /// program and arguments using shell quoting rules.
+        pub fn command_line(command_line: &str) -> Result<Self> {
+            let (program, args) = split_command_line(command_line)?;
+            let mut builder = Self::program(&program);
+            for arg in args {
+                let _ = builder.arg(&arg);
+            }
+            Ok(builder)
+        }
+
+        /// Add an argument to pass to the program.
+        pub fn arg(&mut self, arg: &str) -> &mut Self {
+            self.0.args.push(arg.to_string());
+            self
+        }
+
+        /// Add a list of arguments to pass to the program.
+        pub fn args(&mut self, args: &[&str]) -> &mut Self {
+            self.0.args.extend(args.iter().map(|arg| arg.to_string()));
+            self
+        }
+
+        /// Set the directory the program executes in.
+        pub fn current_dir(&mut self, current_dir: &Path) -> &mut Self {
+            self.0.current_dir = Some(current_dir.to_path_buf());
+            self
+        }
+
+        /// Add a variable to the program's environment.
+        pub fn env(&mut self, name: &str, value: &str) -> &mut Self {
+            let _ = self.0.env_vars.insert(name.to_string(), value.to_string());
+            self
+        }
+
+        /// Add all the variables to the program's environment.
+        pub fn envs(&mut self, env_vars: &HashMap<String, String>) -> &mut Self {
+            self.0
+                .env_vars
+                .extend(env_vars.iter().map(|(k, v)| (k.clone(), v.clone())));
+            self
+        }
+
+        /// Determine whether the configured `EnvironmentPolicy`, if any, is applied to the
+        /// program's environment; the default is `true`.
+        pub fn apply_environment_policy(&mut self, apply: bool) -> &mut Self {
+            self.0.apply_environment_policy = apply;
+            self
+        }
+
+        /// Set a time after which the program is killed, and the execution fails.
+        pub fn timeout(&mut self, timeout: Duration) -> &mut Self {
+            self.0.timeout = Some(timeout);
+            self
+        }
+
+        /// Set the way the program's input and output are handled.
+        pub fn capture(&mut self, capture: CaptureMode) -> &mut Self {
+            self.0.capture = capture;
+            self
+        }
+
+        /// Set a prefix, such as `group/set/package`, with which each line of captured output is
+        /// reported as it is written; see `reporter::report_output_line`.
+        pub fn output_prefix(&mut self, output_prefix: &str) -> &mut Self {
+            self.0.output_prefix = Some(output_prefix.to_string());
+            self
+        }
+
+        /// Set text to write to the program's `stdin`, rather than it having no input, or the
+        /// terminal's. The input is never logged, so it may be used to pass values, such as tokens,
+        /// that should not be visible in the program's arguments.
+        pub fn input(&mut self, input: &str) -> &mut Self {
+            self.0.input = Some(input.to_string());
+            self
+        }
+    }
 }
 
 // ------------------------------------------------------------------------------------------------
@@ -161,12 +1241,322 @@ fn make_safe(script_string: &str) -> String {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::error::Error;
     use pretty_assertions::assert_eq;
 
+    const AWKWARD_VALUES: &[&str] = &[
+        "",
+        "simon",
+        "hello simon",
+        "it's",
+        "'quoted'",
+        "\"double\"",
+        "back\\slash",
+        "trailing\\",
+        "$HOME",
+        "${HOME}",
+        "`id`",
+        "$(id)",
+        "line\nbreak",
+        "tab\there",
+        "semi; echo injected",
+        "glob*?[a]",
+        "~user",
+        "a'b\"c\\d$e`f",
+    ];
+
+    fn shell_output(script_string: &str) -> String {
+        let output = Command::new("sh")
+            .args(["-c", script_string])
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "failed: {}", script_string);
+        String::from_utf8(output.stdout).unwrap()
+    }
+
+    #[test]
+    fn test_find_executable() {
+        assert!(find_executable("sh").is_some());
+        assert_eq!(find_executable("/bin/sh"), Some(PathBuf::from("/bin/sh")));
+        assert_eq!(find_executable("/etc/passwd"), None);
+        assert_eq!(find_executable("mcfg-no-such-program"), None);
+    }
+
+    #[test]
+    fn test_shell_escape() {
+        assert_eq!(shell_escape("simon"), "simon");
+        assert_eq!(shell_escape("/usr/local/bin"), "/usr/local/bin");
+        assert_eq!(shell_escape("name@1.0,x=y"), "name@1.0,x=y");
+        assert_eq!(shell_escape(""), "''");
+        assert_eq!(shell_escape("hello simon"), "'hello simon'");
+        assert_eq!(shell_escape("it's"), r#"'it'\''s'"#);
+        assert_eq!(shell_escape("$HOME"), "'$HOME'");
+        assert_eq!(shell_escape("`id`"), "'`id`'");
+        assert_eq!(shell_escape("back\\slash"), r#"'back\slash'"#);
+        assert_eq!(shell_escape("line\nbreak"), "'line\nbreak'");
+    }
+
+    #[test]
+    fn test_shell_escape_round_trip() {
+        for value in AWKWARD_VALUES {
+            let script = format!("printf '%s' {}", shell_escape(value));
+            assert_eq!(&shell_output(&script), value);
+        }
+    }
+
+    #[test]
+    fn test_shell_var_replace_leaves_script_unchanged() {
+        let vars: HashMap<String, String> = Default::default();
+        for script in &[
+            "echo \"hello\" simon",
+            r#"echo "hello \"simon\"""#,
+            r#"echo 'it'\''s' \$HOME"#,
+            "echo `id -u` $(id -u) && true",
+        ] {
+            assert_eq!(&shell_var_replace(script, &vars), script);
+        }
+        assert_eq!(
+            shell_output(&shell_var_replace(r#"echo "hello \"simon\"""#, &vars)),
+            "hello \"simon\"\n"
+        );
+    }
+
+    #[test]
+    fn test_shell_var_replace_unquoted() {
+        let mut vars: HashMap<String, String> = Default::default();
+        let _ = vars.insert("args".to_string(), "--features pcre2".to_string());
+        assert_eq!(
+            shell_var_replace("cargo install {{args}} ripgrep", &vars),
+            "cargo install --features pcre2 ripgrep"
+        );
+        assert_eq!(
+            shell_var_replace("{{args}} \"{{args}}\" '{{args}}'", &vars),
+            "--features pcre2 \"--features pcre2\" '--features pcre2'"
+        );
+        assert_eq!(
+            shell_var_replace("echo \"{{missing}}\"", &vars),
+            "echo \"missing\""
+        );
+    }
+
+    #[test]
+    fn test_shell_var_replace_escaped_braces() {
+        let mut vars: HashMap<String, String> = Default::default();
+        let _ = vars.insert("name".to_string(), "it's".to_string());
+        assert_eq!(
+            shell_var_replace(r#"echo '\{{name}}' '{{name}}'"#, &vars),
+            r#"echo '{{name}}' 'it'\''s'"#
+        );
+    }
+
+    #[test]
+    fn test_shell_var_replace_quoted() {
+        let mut vars: HashMap<String, String> = Default::default();
+        for value in AWKWARD_VALUES {
+            let _ = vars.insert("value".to_string(), value.to_string());
+            for script in &[
+                "printf '%s' \"{{value}}\"",
+                "printf '%s' '{{value}}'",
+                "printf '%s' \"$(printf '%s' '{{value}}')\"",
+                "test \"it's\" && v=\"{{value}}\" && printf '%s' \"$v\"",
+                "# it's a comment\nprintf '%s' '{{value}}'",
+            ] {
+                let output = shell_output(&shell_var_replace(script, &vars));
+                assert_eq!(&output, value, "script: {:?}", script);
+            }
+        }
+    }
+
+    #[test]
+    fn test_shell_command_builder() {
+        let command = ShellCommandBuilder::command_line("code --wait")
+            .unwrap()
+            .arg("my file.yml")
+            .env("EDITOR_MODE", "wait")
+            .timeout(Duration::from_secs(10))
+            .capture(CaptureMode::Inherit)
+            .build();
+        assert_eq!(command.program(), "code");
+        assert_eq!(command.args(), &vec!["--wait", "my file.yml"]);
+        assert_eq!(command.env_vars().get("EDITOR_MODE").unwrap(), "wait");
+        assert_eq!(command.timeout(), &Some(Duration::from_secs(10)));
+        assert_eq!(command.capture_mode(), CaptureMode::Inherit);
+        assert!(command.is_environment_policy_applied());
+        assert_eq!(command.to_string(), "code --wait 'my file.yml'");
+    }
+
+    #[test]
+    fn test_shell_command_execute() {
+        let mut vars: HashMap<String, String> = Default::default();
+        let _ = vars.insert("package_name".to_string(), "ripgrep".to_string());
+        let output =
+            ShellCommandBuilder::script("printf '%s' \"$MCFG_PACKAGE_NAME\" && pwd", &vars)
+                .current_dir(Path::new("/"))
+                .build()
+                .execute_output()
+                .unwrap();
+        assert_eq!(output, "ripgrep/\n");
+
+        let command = ShellCommandBuilder::program("sh")
+            .args(&["-c", "echo out; echo err >&2"])
+            .build();
+        let command_output = command.execute().unwrap();
+        assert_eq!(command_output.output_lines(), 2);
+        assert_eq!(command_output.output_bytes(), 8);
+
+        assert!(ShellCommandBuilder::program("false")
+            .build()
+            .execute()
+            .is_err());
+        assert!(ShellCommandBuilder::command_line("").is_err());
+    }
+
+    #[test]
+    fn test_shell_command_input() {
+        let command = ShellCommandBuilder::program("cat")
+            .input("url = \"https://example.com/?token=x\"\n")
+            .build();
+        // the input is never part of the command as logged, or recorded.
+        assert_eq!(command.to_string(), "cat");
+        assert_eq!(
+            command.execute_output().unwrap(),
+            "url = \"https://example.com/?token=x\"\n"
+        );
+
+        let (stdout, stderr) = ShellCommandBuilder::program("sh")
+            .args(&["-c", "read line; echo \"$line\"; echo err >&2"])
+            .input("one\n")
+            .build()
+            .execute_all_output()
+            .unwrap();
+        assert_eq!(stdout, "one\n");
+        assert_eq!(stderr, "err\n");
+    }
+
+    #[test]
+    fn test_shell_command_output_prefix() {
+        let command = ShellCommandBuilder::program("sh")
+            .args(&["-c", "echo one; echo err >&2; printf two"])
+            .output_prefix("group/set/package")
+            .build();
+        assert_eq!(
+            command.output_prefix(),
+            &Some("group/set/package".to_string())
+        );
+        // lines are reported as they are read, but the output is still captured unchanged.
+        assert_eq!(command.execute_output().unwrap(), "one\ntwo");
+        let command_output = command.execute().unwrap();
+        assert_eq!(command_output.output_lines(), 3);
+        assert_eq!(command_output.output_bytes(), 11);
+    }
+
+    #[test]
+    fn test_secret_vars_are_resolved_on_execute() {
+        let vars: HashMap<String, String> = Default::default();
+        let command = ShellCommandBuilder::script("echo {{secret:vault:dev/token}}", &vars).build();
+        // the secret is not replaced when the command is built, so it is never logged.
+        assert!(command.to_string().contains("{{secret:vault:dev/token}}"));
+        assert!(matches!(
+            command.execute().unwrap_err(),
+            Error::UnknownSecretProvider(_)
+        ));
+
+        let secrets: HashMap<String, String> = vec![("secret:pass:dev/token", "a b")]
+            .into_iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        assert_eq!(
+            shell_var_replace("echo {{secret:pass:dev/token}}", &secrets),
+            "echo 'a b'"
+        );
+    }
+
+    #[test]
+    fn test_command_records() {
+        let command = ShellCommandBuilder::program("sh")
+            .args(&["-c", "echo out; echo failed >&2; exit 3"])
+            .output_prefix("test/records/failing")
+            .build();
+        assert!(command.execute().is_err());
+        let records: Vec<CommandRecord> = take_command_records()
+            .into_iter()
+            .filter(|record| record.context() == &Some("test/records/failing".to_string()))
+            .collect();
+        assert_eq!(records.len(), 1);
+        let record = records.first().unwrap();
+        assert_eq!(record.command(), &command.to_string());
+        assert_eq!(record.exit_code(), &Some(3));
+        assert!(!record.is_success());
+        assert_eq!(record.stderr(), "failed\n");
+    }
+
+    #[test]
+    fn test_shell_command_timeout() {
+        let result = ShellCommandBuilder::program("sleep")
+            .arg("10")
+            .timeout(Duration::from_millis(200))
+            .build()
+            .execute();
+        match result {
+            Err(Error::CommandTimedOut(program, _)) => assert_eq!(program, "sleep"),
+            _ => panic!("expected a timeout, not {:?}", result),
+        }
+    }
+
+    #[test]
+    fn test_environment_policy() {
+        let policy = EnvironmentPolicy::default();
+        assert!(policy.is_allowed("PATH"));
+        assert!(policy.is_allowed("LC_ALL"));
+        assert!(policy.is_allowed("MCFG_PACKAGE_NAME"));
+        assert!(policy.is_allowed("https_proxy"));
+        assert!(!policy.is_allowed("AWS_SECRET_ACCESS_KEY"));
+        assert!(!policy.is_allowed("PATHEXT"));
+
+        let mut policy = EnvironmentPolicy::default();
+        policy.add_allowed("HOMEBREW_*");
+        assert!(policy.is_allowed("HOMEBREW_NO_ANALYTICS"));
+
+        let policy: EnvironmentPolicy = serde_yaml::from_str("sanitize: false").unwrap();
+        assert!(policy.is_allowed("AWS_SECRET_ACCESS_KEY"));
+
+        env::set_var("MCFG_TEST_POLICY_HIDDEN", "secret");
+        let mut command = Command::new("env");
+        let policy: EnvironmentPolicy = serde_yaml::from_str("allow: [ CARGO_HOME ]").unwrap();
+        policy.apply(&mut command);
+        let names: Vec<String> = command
+            .get_envs()
+            .map(|(name, _)| name.to_string_lossy().to_string())
+            .collect();
+        assert!(names.contains(&"MCFG_TEST_POLICY_HIDDEN".to_string()));
+        assert!(names.iter().all(|name| policy.is_allowed(name)));
+    }
+
+    #[test]
+    fn test_split_command_line() {
+        assert_eq!(
+            split_command_line("vi").unwrap(),
+            ("vi".to_string(), Vec::<String>::new())
+        );
+        assert_eq!(
+            split_command_line("code --wait").unwrap(),
+            ("code".to_string(), vec!["--wait".to_string()])
+        );
+        assert_eq!(
+            split_command_line("'/Applications/My Editor' -w --new-window").unwrap(),
+            (
+                "/Applications/My Editor".to_string(),
+                vec!["-w".to_string(), "--new-window".to_string()]
+            )
+        );
+        assert!(split_command_line("").is_err());
+        assert!(split_command_line("emacsclient 'unbalanced").is_err());
+    }
+
     #[test]
-    fn test_make_safe() {
-        assert_eq!(make_safe("hello simon"), r#"hello simon"#);
-        assert_eq!(make_safe("hello \"simon\""), r#"hello \"simon\""#);
-        assert_eq!(make_safe("\"hello\" simon"), r#"\"hello\" simon"#);
+    fn test_count_lines() {
+        assert_eq!(count_lines(b""), 0);
+        assert_eq!(count_lines(b"one line"), 1);
+        assert_eq!(count_lines(b"one\ntwo\n\nthree\n"), 3);
     }
 }
@@ -0,0 +1,456 @@
+use crate::error::Result;
+use crate::shared::command::EnvironmentPolicy;
+use crate::shared::dirs;
+use crate::shared::install_log::InstallLogFormat;
+use crate::shared::trust::ScriptTrust;
+use crate::shared::vcs::RepoVcsKind;
+use crate::shared::{FileSystemResource, Scope};
+use crate::APP_NAME;
+use serde::{Deserialize, Serialize};
+use std::fs::read_to_string;
+use std::path::PathBuf;
+
+// ------------------------------------------------------------------------------------------------
+// Public Types
+// ------------------------------------------------------------------------------------------------
+
+///
+/// The tool configuration file; this holds settings that apply to the local machine rather than
+/// to the package repository. If the file does not exist all settings take their default values.
+///
+#[derive(Deserialize, Serialize, Clone, Debug, Default, PartialEq)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Config {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    notifications: Option<NotificationConfig>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    git: Option<GitConfig>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    vcs: Option<RepoVcsKind>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    archive: Option<ArchiveConfig>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    environment: Option<EnvironmentPolicy>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    script_trust: Option<ScriptTrust>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    install_log: Option<InstallLogFormat>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    machine_id: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    webhooks: Vec<WebhookConfig>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    metrics: Option<MetricsConfig>,
+}
+
+///
+/// Settings for desktop notifications, fired when a long-running action such as install or
+/// update completes.
+///
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct NotificationConfig {
+    on_success: bool,
+    on_failure: bool,
+    min_duration_secs: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    command: Option<String>,
+}
+
+///
+/// A webhook, an HTTP endpoint that is sent a summary of each install, update, uninstall, or
+/// link-files run as it completes.
+///
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct WebhookConfig {
+    url: String,
+    #[serde(default)]
+    kind: WebhookKind,
+    #[serde(default = "default_true")]
+    on_success: bool,
+    #[serde(default = "default_true")]
+    on_failure: bool,
+}
+
+///
+/// The form of the request body sent to a webhook.
+///
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum WebhookKind {
+    /// The run summary as a JSON object.
+    #[default]
+    Generic,
+    /// A Slack incoming webhook message, with the run summary as its text.
+    Slack,
+}
+
+///
+/// Settings for the metrics recorded for each install, update, uninstall, link-files, or sync
+/// run; either, or both, a Prometheus textfile and a statsd server.
+///
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct MetricsConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    textfile: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    statsd: Option<String>,
+    statsd_prefix: String,
+}
+
+///
+/// Settings for authenticating with the package repository's Git remote, used by the refresh and
+/// push actions. The credentials are tried in order; the SSH agent, the SSH key file, the Git
+/// credential helper, and finally a token from an environment variable.
+///
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct GitConfig {
+    ssh_agent: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    ssh_key: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    ssh_key_passphrase_var: Option<String>,
+    credential_helper: bool,
+    token_var: String,
+}
+
+///
+/// Settings for a package repository downloaded as an archive, rather than cloned, used when the
+/// `vcs` setting is `archive`. The archive's SHA-256 checksum is either given, or downloaded from
+/// a URL; one or the other is required.
+///
+#[derive(Deserialize, Serialize, Clone, Debug, Default, PartialEq)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct ArchiveConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sha256: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sha256_url: Option<String>,
+}
+
+///
+/// The configuration file name.
+///
+pub const CONFIG_FILE: &str = "config.yml";
+
+///
+/// The default environment variable checked for a token used to authenticate with HTTPS remotes.
+///
+pub const GIT_TOKEN_VAR: &str = "MCFG_GIT_TOKEN";
+
+// ------------------------------------------------------------------------------------------------
+// Implementations
+// ------------------------------------------------------------------------------------------------
+
+impl FileSystemResource for Config {
+    fn default_path(scope: Scope) -> PathBuf {
+        dirs::config_dir_for(scope, APP_NAME)
+            .unwrap()
+            .join(CONFIG_FILE)
+    }
+
+    fn open_from(config_file: PathBuf) -> Result<Self> {
+        if config_file.is_file() {
+            info!("Config::open_from loading from {:?}", config_file);
+            let config_data = read_to_string(config_file)?;
+            let config: Option<Config> = serde_yaml::from_str(&config_data)?;
+            Ok(config.unwrap_or_default())
+        } else {
+            debug!(
+                "Config::open_from no file {:?}, using defaults",
+                config_file
+            );
+            Ok(Default::default())
+        }
+    }
+}
+
+impl Config {
+    /// Return the notification settings, if notifications have been configured.
+    pub fn notifications(&self) -> &Option<NotificationConfig> {
+        &self.notifications
+    }
+
+    /// Set the notification settings.
+    pub fn set_notifications(&mut self, notifications: Option<NotificationConfig>) {
+        self.notifications = notifications;
+    }
+
+    /// Return the Git remote settings, if they have been configured.
+    pub fn git(&self) -> &Option<GitConfig> {
+        &self.git
+    }
+
+    /// Set the Git remote settings.
+    pub fn set_git(&mut self, git: Option<GitConfig>) {
+        self.git = git;
+    }
+
+    /// Return the version control system used for the package repository, if one has been
+    /// configured.
+    pub fn vcs(&self) -> &Option<RepoVcsKind> {
+        &self.vcs
+    }
+
+    /// Set the version control system used for the package repository.
+    pub fn set_vcs(&mut self, vcs: Option<RepoVcsKind>) {
+        self.vcs = vcs;
+    }
+
+    /// Return the repository archive settings, if they have been configured.
+    pub fn archive(&self) -> &Option<ArchiveConfig> {
+        &self.archive
+    }
+
+    /// Set the repository archive settings.
+    pub fn set_archive(&mut self, archive: Option<ArchiveConfig>) {
+        self.archive = archive;
+    }
+
+    /// Return the environment policy for spawned commands, if one has been configured.
+    pub fn environment(&self) -> &Option<EnvironmentPolicy> {
+        &self.environment
+    }
+
+    /// Set the environment policy for spawned commands.
+    pub fn set_environment(&mut self, environment: Option<EnvironmentPolicy>) {
+        self.environment = environment;
+    }
+
+    /// Return the trust policy for package set scripts, if one has been configured.
+    pub fn script_trust(&self) -> &Option<ScriptTrust> {
+        &self.script_trust
+    }
+
+    /// Set the trust policy for package set scripts.
+    pub fn set_script_trust(&mut self, script_trust: Option<ScriptTrust>) {
+        self.script_trust = script_trust;
+    }
+
+    /// Return the install log format, if one has been configured.
+    pub fn install_log(&self) -> &Option<InstallLogFormat> {
+        &self.install_log
+    }
+
+    /// Set the install log format.
+    pub fn set_install_log(&mut self, install_log: Option<InstallLogFormat>) {
+        self.install_log = install_log;
+    }
+
+    /// Return the identifier recorded for this machine in the install log, if one has been
+    /// configured; the default is the machine's host name.
+    pub fn machine_id(&self) -> &Option<String> {
+        &self.machine_id
+    }
+
+    /// Set the identifier recorded for this machine in the install log.
+    pub fn set_machine_id(&mut self, machine_id: Option<String>) {
+        self.machine_id = machine_id;
+    }
+
+    /// Return the webhooks sent a summary of each run.
+    pub fn webhooks(&self) -> &Vec<WebhookConfig> {
+        &self.webhooks
+    }
+
+    /// Set the webhooks sent a summary of each run.
+    pub fn set_webhooks(&mut self, webhooks: Vec<WebhookConfig>) {
+        self.webhooks = webhooks;
+    }
+
+    /// Return the metrics settings, if metrics have been configured.
+    pub fn metrics(&self) -> &Option<MetricsConfig> {
+        &self.metrics
+    }
+
+    /// Set the metrics settings.
+    pub fn set_metrics(&mut self, metrics: Option<MetricsConfig>) {
+        self.metrics = metrics;
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+
+impl Default for NotificationConfig {
+    fn default() -> Self {
+        Self {
+            on_success: true,
+            on_failure: true,
+            min_duration_secs: 0,
+            command: None,
+        }
+    }
+}
+
+impl NotificationConfig {
+    /// Return `true` if a notification should be sent when an action succeeds.
+    pub fn on_success(&self) -> bool {
+        self.on_success
+    }
+
+    /// Return `true` if a notification should be sent when an action fails.
+    pub fn on_failure(&self) -> bool {
+        self.on_failure
+    }
+
+    /// Return the minimum time, in seconds, an action must take before a notification is sent.
+    pub fn min_duration_secs(&self) -> u64 {
+        self.min_duration_secs
+    }
+
+    /// Return the script string used to send a notification, if one has been provided to replace
+    /// the platform default.
+    pub fn command(&self) -> &Option<String> {
+        &self.command
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+
+impl WebhookConfig {
+    /// Create a new webhook, sent a summary of every run.
+    pub fn new(url: &str, kind: WebhookKind) -> Self {
+        Self {
+            url: url.to_string(),
+            kind,
+            on_success: true,
+            on_failure: true,
+        }
+    }
+
+    /// Return the URL the run summary is posted to.
+    pub fn url(&self) -> &String {
+        &self.url
+    }
+
+    /// Return the form of the request body sent to the webhook.
+    pub fn kind(&self) -> WebhookKind {
+        self.kind
+    }
+
+    /// Return `true` if the webhook should be sent a summary when a run succeeds.
+    pub fn on_success(&self) -> bool {
+        self.on_success
+    }
+
+    /// Return `true` if the webhook should be sent a summary when a run fails.
+    pub fn on_failure(&self) -> bool {
+        self.on_failure
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+
+impl Default for MetricsConfig {
+    fn default() -> Self {
+        Self {
+            textfile: None,
+            statsd: None,
+            statsd_prefix: APP_NAME.to_string(),
+        }
+    }
+}
+
+impl MetricsConfig {
+    /// Return the path of the Prometheus textfile the metrics are written to, if one has been
+    /// provided.
+    pub fn textfile(&self) -> &Option<String> {
+        &self.textfile
+    }
+
+    /// Return the address, `host:port`, of the statsd server the metrics are sent to, if one has
+    /// been provided.
+    pub fn statsd(&self) -> &Option<String> {
+        &self.statsd
+    }
+
+    /// Return the prefix for the names of metrics sent to statsd.
+    pub fn statsd_prefix(&self) -> &String {
+        &self.statsd_prefix
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+
+impl Default for GitConfig {
+    fn default() -> Self {
+        Self {
+            ssh_agent: true,
+            ssh_key: None,
+            ssh_key_passphrase_var: None,
+            credential_helper: true,
+            token_var: GIT_TOKEN_VAR.to_string(),
+        }
+    }
+}
+
+impl GitConfig {
+    /// Return `true` if the SSH agent should be used to authenticate SSH remotes.
+    pub fn ssh_agent(&self) -> bool {
+        self.ssh_agent
+    }
+
+    /// Return the path to a private key file used to authenticate SSH remotes, if provided.
+    pub fn ssh_key(&self) -> &Option<String> {
+        &self.ssh_key
+    }
+
+    /// Return the name of an environment variable holding the passphrase for the SSH key file,
+    /// if provided.
+    pub fn ssh_key_passphrase_var(&self) -> &Option<String> {
+        &self.ssh_key_passphrase_var
+    }
+
+    /// Return `true` if the Git credential helper should be used to authenticate HTTPS remotes.
+    pub fn credential_helper(&self) -> bool {
+        self.credential_helper
+    }
+
+    /// Return the name of the environment variable holding a token used to authenticate HTTPS
+    /// remotes.
+    pub fn token_var(&self) -> &String {
+        &self.token_var
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+
+impl ArchiveConfig {
+    /// Create settings for an archive with the SHA-256 checksum `sha256`.
+    pub fn with_sha256(sha256: &str) -> Self {
+        Self {
+            sha256: Some(sha256.to_string()),
+            sha256_url: None,
+        }
+    }
+
+    /// Create settings for an archive whose SHA-256 checksum is downloaded from `sha256_url`.
+    pub fn with_sha256_url(sha256_url: &str) -> Self {
+        Self {
+            sha256: None,
+            sha256_url: Some(sha256_url.to_string()),
+        }
+    }
+
+    /// Return the SHA-256 checksum of the archive, if provided.
+    pub fn sha256(&self) -> &Option<String> {
+        &self.sha256
+    }
+
+    /// Return the URL of a file holding the SHA-256 checksum of the archive, if provided. The
+    /// checksum is the first word in the file, as written by `sha256sum`.
+    pub fn sha256_url(&self) -> &Option<String> {
+        &self.sha256_url
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+// Private Functions
+// ------------------------------------------------------------------------------------------------
+
+fn default_true() -> bool {
+    true
+}
@@ -1,4 +1,7 @@
+use crate::error::Result;
+use crate::reporter::Reporter;
 use std::cell::RefCell;
+use std::fmt::{Display, Formatter};
 use std::ops::RangeFrom;
 
 // ------------------------------------------------------------------------------------------------
@@ -11,6 +14,44 @@ use std::ops::RangeFrom;
 #[derive(Debug)]
 pub struct StepCounter(RefCell<RangeFrom<u32>>);
 
+///
+/// The state of a step reported by `Steps`.
+///
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StepState {
+    /// The step was performed, or is being performed.
+    Done,
+    /// The step was not performed, the reason is reported with the step.
+    Skipped,
+    /// The step failed, the error is reported with the step.
+    Failed,
+}
+
+///
+/// Reports the numbered steps of a multi-step action, such as `init` or `install`, to the user;
+/// each step is either done, skipped, or failed, and once all steps are reported a summary of
+/// the counts of each can be reported. In dry-run mode steps are reported but not performed.
+///
+#[derive(Debug)]
+pub struct Steps {
+    title: String,
+    counter: StepCounter,
+    reporter: Reporter,
+    dry_run: bool,
+    states: RefCell<Vec<StepState>>,
+}
+
+///
+/// The counts of steps, by state, reported by `Steps`.
+///
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct StepsSummary {
+    title: String,
+    done: u32,
+    skipped: u32,
+    failed: u32,
+}
+
 // ------------------------------------------------------------------------------------------------
 // Implementations
 // ------------------------------------------------------------------------------------------------
@@ -52,6 +93,157 @@ impl StepCounter {
     }
 }
 
+// ------------------------------------------------------------------------------------------------
+
+impl Steps {
+    /// Create a new set of steps, the `title` names the action in the summary; steps are
+    /// reported using the current reporter settings.
+    pub fn new(title: &str) -> Self {
+        Self {
+            title: title.to_string(),
+            counter: StepCounter::from_one(),
+            reporter: Reporter::current(),
+            dry_run: false,
+            states: Default::default(),
+        }
+    }
+
+    /// Report steps using `reporter`, rather than the current reporter settings.
+    pub fn with_reporter(self, reporter: Reporter) -> Self {
+        Self { reporter, ..self }
+    }
+
+    /// Report steps, but do not perform them; steps passed to `run` are reported as skipped.
+    pub fn with_dry_run(self, dry_run: bool) -> Self {
+        Self { dry_run, ..self }
+    }
+
+    /// Returns `true` if steps are reported but not performed.
+    pub fn is_dry_run(&self) -> bool {
+        self.dry_run
+    }
+
+    /// Report the next step, which the caller performs, as done.
+    pub fn step(&self, name: &str) {
+        self.reporter
+            .message(&format!("{}. {}", self.record(StepState::Done), name));
+    }
+
+    /// Report the next step as skipped, with the reason it was not performed.
+    pub fn skip(&self, name: &str, reason: &str) {
+        self.reporter.message(&format!(
+            "{}. {} (skipped, {})",
+            self.record(StepState::Skipped),
+            name,
+            reason
+        ));
+    }
+
+    /// Report the most recent step as failed, with the error that caused it to fail.
+    pub fn fail(&self, error: &dyn Display) {
+        if let Some(state) = self.states.borrow_mut().last_mut() {
+            *state = StepState::Failed;
+        }
+        self.reporter.error(&format!("   failed: {}", error));
+    }
+
+    /// Report the next step and perform it by calling `f`, unless in dry-run mode; if `f` fails
+    /// the step is reported as failed and the error returned.
+    pub fn run<F>(&self, name: &str, f: F) -> Result<()>
+    where
+        F: FnOnce() -> Result<()>,
+    {
+        if self.dry_run {
+            self.skip(name, "dry run");
+            Ok(())
+        } else {
+            self.step(name);
+            f().inspect_err(|e| self.fail(e))
+        }
+    }
+
+    /// Return the counts of the steps reported so far, by state.
+    pub fn summary(&self) -> StepsSummary {
+        let states = self.states.borrow();
+        let count = |state: StepState| states.iter().filter(|s| **s == state).count() as u32;
+        StepsSummary {
+            title: self.title.clone(),
+            done: count(StepState::Done),
+            skipped: count(StepState::Skipped),
+            failed: count(StepState::Failed),
+        }
+    }
+
+    /// Report the summary of all steps, returning it.
+    pub fn finish(&self) -> StepsSummary {
+        let summary = self.summary();
+        summary.report_with(&self.reporter);
+        summary
+    }
+
+    fn record(&self, state: StepState) -> u32 {
+        self.states.borrow_mut().push(state);
+        self.counter.step()
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+
+impl Display for StepsSummary {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}: {} step(s), {} done",
+            self.title,
+            self.total(),
+            self.done
+        )?;
+        if self.skipped > 0 {
+            write!(f, ", {} skipped", self.skipped)?;
+        }
+        if self.failed > 0 {
+            write!(f, ", {} failed", self.failed)?;
+        }
+        Ok(())
+    }
+}
+
+impl StepsSummary {
+    /// Return the number of steps reported.
+    pub fn total(&self) -> u32 {
+        self.done + self.skipped + self.failed
+    }
+
+    /// Return the number of steps done.
+    pub fn done(&self) -> u32 {
+        self.done
+    }
+
+    /// Return the number of steps skipped.
+    pub fn skipped(&self) -> u32 {
+        self.skipped
+    }
+
+    /// Return the number of steps that failed.
+    pub fn failed(&self) -> u32 {
+        self.failed
+    }
+
+    /// Report this summary using the current reporter settings; as an error if any step failed.
+    pub fn report(&self) {
+        self.report_with(&Reporter::current())
+    }
+
+    /// Report this summary using `reporter`; as an error if any step failed.
+    pub fn report_with(&self, reporter: &Reporter) {
+        if self.failed > 0 {
+            reporter.error(&self.to_string());
+        } else {
+            reporter.message(&self.to_string());
+        }
+    }
+}
+
 // ------------------------------------------------------------------------------------------------
 // Unit Tests
 // ------------------------------------------------------------------------------------------------
@@ -107,4 +299,36 @@ mod tests {
         assert_eq!(counter.step(), 20);
         assert_eq!(counter.step(), 21);
     }
+
+    #[test]
+    fn test_steps_summary() {
+        let steps = Steps::new("init");
+        steps.step("Creating directory");
+        steps.skip("Creating file", "it already exists");
+        assert!(steps.run("Committing", || Ok(())).is_ok());
+        assert!(steps
+            .run("Pushing", || Err(
+                crate::error::Error::InstallerCommandFailed
+            ))
+            .is_err());
+        let summary = steps.summary();
+        assert_eq!(summary.total(), 4);
+        assert_eq!(summary.done(), 2);
+        assert_eq!(summary.skipped(), 1);
+        assert_eq!(summary.failed(), 1);
+        assert_eq!(
+            summary.to_string(),
+            "init: 4 step(s), 2 done, 1 skipped, 1 failed"
+        );
+    }
+
+    #[test]
+    fn test_steps_dry_run() {
+        let steps = Steps::new("init").with_dry_run(true);
+        assert!(steps.run("Committing", || panic!("not performed")).is_ok());
+        assert_eq!(
+            steps.summary().to_string(),
+            "init: 1 step(s), 0 done, 1 skipped"
+        );
+    }
 }
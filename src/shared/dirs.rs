@@ -0,0 +1,140 @@
+/*!
+The standard directories used by the tool, and by installers, for configuration, data, and log
+files. On all platforms the XDG environment variables `XDG_CONFIG_HOME`, `XDG_DATA_HOME`, and
+`XDG_STATE_HOME` override the platform defaults, if they are set to an absolute path.
+
+Each function takes the [`Scope`](../env/enum.Scope.html) the directories are for. In the system
+scope these directories are shared by all users; configuration is kept under `/etc`, and data and
+logs under `/var/lib`. The XDG variables are ignored in this scope.
+*/
+
+use crate::shared::env::Scope;
+use std::env;
+use std::path::{Component, Path, PathBuf};
+
+// ------------------------------------------------------------------------------------------------
+// Public Types
+// ------------------------------------------------------------------------------------------------
+
+///
+/// The environment variable that overrides the configuration directory.
+///
+pub const XDG_CONFIG_HOME: &str = "XDG_CONFIG_HOME";
+
+///
+/// The environment variable that overrides the local data directory.
+///
+pub const XDG_DATA_HOME: &str = "XDG_DATA_HOME";
+
+///
+/// The environment variable that overrides the state, and so log, directory.
+///
+pub const XDG_STATE_HOME: &str = "XDG_STATE_HOME";
+
+///
+/// The root of all configuration directories in the system scope.
+///
+pub const SYSTEM_CONFIG_DIR: &str = "/etc";
+
+///
+/// The root of all data, and log, directories in the system scope.
+///
+pub const SYSTEM_STATE_DIR: &str = "/var/lib";
+
+// ------------------------------------------------------------------------------------------------
+// Public Functions
+// ------------------------------------------------------------------------------------------------
+
+///
+/// Return the configuration directory for the application `app`, in `scope`.
+///
+pub fn config_dir_for(scope: Scope, app: &str) -> Option<PathBuf> {
+    if scope == Scope::System {
+        return Some(PathBuf::from(SYSTEM_CONFIG_DIR).join(app));
+    }
+    xdg_dir(XDG_CONFIG_HOME)
+        .map(|dir| dir.join(app))
+        .or_else(|| xdirs::config_dir_for(app))
+}
+
+///
+/// Return the local data directory for the application `app`, in `scope`.
+///
+pub fn data_local_dir_for(scope: Scope, app: &str) -> Option<PathBuf> {
+    if scope == Scope::System {
+        return Some(PathBuf::from(SYSTEM_STATE_DIR).join(app));
+    }
+    xdg_dir(XDG_DATA_HOME)
+        .map(|dir| dir.join(app))
+        .or_else(|| xdirs::data_local_dir_for(app))
+}
+
+///
+/// Return the log directory for the application `app`, in `scope`.
+///
+pub fn log_dir_for(scope: Scope, app: &str) -> Option<PathBuf> {
+    if scope == Scope::System {
+        return Some(PathBuf::from(SYSTEM_STATE_DIR).join(app).join("log"));
+    }
+    xdg_dir(XDG_STATE_HOME)
+        .map(|dir| dir.join(app).join("log"))
+        .or_else(|| xdirs::log_dir_for(app))
+}
+
+///
+/// Create a new, uniquely named, scratch directory for the application `app`, accessible only by
+/// the current user. The directory, and its content, is removed when the returned value is
+/// dropped, or closed.
+///
+pub fn temp_dir_for(app: &str, purpose: &str) -> std::io::Result<tempfile::TempDir> {
+    tempfile::Builder::new()
+        .prefix(&format!("{}-{}-", app, purpose))
+        .tempdir()
+}
+
+///
+/// Create a new, uniquely named, scratch directory within `parent`, as for `temp_dir_for`; this
+/// is used where the content is to be moved, rather than copied, to another path in `parent`.
+///
+pub fn temp_dir_in(parent: &Path, app: &str, purpose: &str) -> std::io::Result<tempfile::TempDir> {
+    tempfile::Builder::new()
+        .prefix(&format!("{}-{}-", app, purpose))
+        .tempdir_in(parent)
+}
+
+///
+/// Return the path `to` expressed relative to the directory `from`, so that `from.join(result)`
+/// refers to the same file as `to`. If either path is not absolute `to` is returned unchanged.
+///
+pub fn relative_path(from: &Path, to: &Path) -> PathBuf {
+    if !from.is_absolute() || !to.is_absolute() {
+        return to.to_path_buf();
+    }
+    let from: Vec<Component<'_>> = from.components().collect();
+    let to: Vec<Component<'_>> = to.components().collect();
+    let common = from
+        .iter()
+        .zip(to.iter())
+        .take_while(|(lhs, rhs)| lhs == rhs)
+        .count();
+    let mut result: PathBuf = from[common..]
+        .iter()
+        .map(|_| Component::ParentDir)
+        .collect();
+    result.extend(to[common..].iter());
+    if result.as_os_str().is_empty() {
+        result.push(Component::CurDir);
+    }
+    result
+}
+
+// ------------------------------------------------------------------------------------------------
+// Private Functions
+// ------------------------------------------------------------------------------------------------
+
+// The XDG specification requires that relative paths are ignored.
+fn xdg_dir(var_name: &str) -> Option<PathBuf> {
+    env::var_os(var_name)
+        .map(PathBuf::from)
+        .filter(|path| path.is_absolute())
+}
@@ -0,0 +1,504 @@
+/*!
+A built-in installer for packages that are only distributed as a downloadable file, often a
+tarball attached to a project's release page. The file is downloaded, its SHA-256 checksum
+verified, optionally extracted, and the resulting binary placed in the repository's local `bin`
+directory.
+*/
+
+use crate::error::{Error, Result};
+use crate::shared::command::{execute_program, CommandOutput};
+use crate::shared::dirs::temp_dir_for;
+use crate::shared::env::{insert_built_in_var, var_string_replace, vars_scope};
+use crate::shared::{InstallActionKind, Package, PackageRepository, Scope};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::ffi::OsStr;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+// ------------------------------------------------------------------------------------------------
+// Public Types
+// ------------------------------------------------------------------------------------------------
+
+///
+/// Describes where to download a package from, and how to verify and install it. The `url` and
+/// `binary` values may include variables, including `download_version` which is set from the
+/// `version` value.
+///
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct PackageDownload {
+    url: String,
+    sha256: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    version: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    extract: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    binary: Option<String>,
+}
+
+///
+/// The name used for the built-in download installer, in reports and in the install log.
+///
+pub const DOWNLOAD_INSTALLER: &str = "download";
+
+// ------------------------------------------------------------------------------------------------
+// Public Functions
+// ------------------------------------------------------------------------------------------------
+
+///
+/// Return the SHA-256 checksum of the file at `path`, as a lower-case hex string.
+///
+pub fn sha256_file(path: &Path) -> Result<String> {
+    let mut file = File::open(path)?;
+    let mut hasher = Sha256::new();
+    let _ = std::io::copy(&mut file, &mut hasher)?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+///
+/// Return the path to the directory into which downloaded binaries are installed, in `scope`.
+///
+pub fn download_bin_path(scope: Scope) -> PathBuf {
+    PackageRepository::default_local_path(scope).join("bin")
+}
+
+///
+/// Download the file at `url`, verify its checksum if one is provided, extract it if required,
+/// and copy the binary at `binary_path` (relative to the extracted archive) to `installed_path`.
+/// If `binary_path` is a simple file name, and is not found at the root of the extracted archive,
+/// the archive is searched for a file with that name.
+///
+pub(crate) fn install_from_url(
+    url: &str,
+    sha256: Option<&str>,
+    extract: bool,
+    binary_path: &Path,
+    installed_path: &Path,
+) -> Result<CommandOutput> {
+    let started = time::OffsetDateTime::now_utc();
+    let timer = Instant::now();
+
+    let work_dir = temp_dir_for(crate::APP_NAME, "download")?;
+    let result = download_into(
+        url,
+        sha256,
+        extract,
+        work_dir.path(),
+        binary_path,
+        installed_path,
+    );
+    let _ = work_dir.close();
+    let output_bytes = result?;
+
+    Ok(CommandOutput::new(
+        started,
+        timer.elapsed(),
+        output_bytes as usize,
+        0,
+    ))
+}
+
+///
+/// Download the file at `url` into the directory `work_dir`, verifying its checksum if one is
+/// provided, and return the path of the downloaded file. The file is named for the last segment
+/// of the URL's path, which must not be empty.
+///
+pub(crate) fn download_file(url: &str, sha256: Option<&str>, work_dir: &Path) -> Result<PathBuf> {
+    let download_path = work_dir.join(url_file_name(url)?);
+    debug!("download_file: {} -> {:?}", url, download_path);
+    let _ = execute_program(
+        "curl",
+        &[
+            "--fail",
+            "--silent",
+            "--show-error",
+            "--location",
+            "--output",
+            &download_path.to_string_lossy(),
+            url,
+        ],
+    )?;
+
+    if let Some(expected) = sha256 {
+        let actual = sha256_file(&download_path)?;
+        if !actual.eq_ignore_ascii_case(expected) {
+            return Err(Error::ChecksumMismatch(
+                url.to_string(),
+                expected.to_string(),
+                actual,
+            ));
+        }
+    }
+    Ok(download_path)
+}
+
+///
+/// Quote `value` for a curl config file, as read with `--config`, escaping the characters curl
+/// treats as special within a quoted string.
+///
+#[cfg(any(feature = "github-releases", feature = "webhooks"))]
+pub(crate) fn curl_config_value(value: &str) -> String {
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+///
+/// Extract the archive at `archive_path`, downloaded from `url`, into the directory `to_dir`. A
+/// URL ending in `.zip` is extracted with `unzip`, anything else with `tar`.
+///
+pub(crate) fn extract_archive(url: &str, archive_path: &Path, to_dir: &Path) -> Result<()> {
+    let to_dir_str = to_dir.to_string_lossy();
+    let archive_str = archive_path.to_string_lossy();
+    match archive_kind(url) {
+        Some(ArchiveKind::Zip) => {
+            let _ = execute_program("unzip", &["-q", "-o", &archive_str, "-d", &to_dir_str])?;
+        }
+        _ => {
+            let _ = execute_program("tar", &["-xf", &archive_str, "-C", &to_dir_str])?;
+        }
+    }
+    Ok(())
+}
+
+///
+/// Return `true` if the file name, or URL, has an extension denoting a supported archive format.
+///
+pub fn is_archive(file_name: &str) -> bool {
+    archive_kind(file_name).is_some()
+}
+
+// ------------------------------------------------------------------------------------------------
+// Implementations
+// ------------------------------------------------------------------------------------------------
+
+impl PackageDownload {
+    /// Construct a new download, from the URL and the expected SHA-256 checksum of the file.
+    pub fn new(url: &str, sha256: &str) -> Self {
+        Self {
+            url: url.to_string(),
+            sha256: sha256.to_string(),
+            version: None,
+            extract: None,
+            binary: None,
+        }
+    }
+
+    /// Set the version to download, this is available as the `download_version` variable.
+    pub fn with_version(self, version: &str) -> Self {
+        Self {
+            version: Some(version.to_string()),
+            ..self
+        }
+    }
+
+    /// Set whether the downloaded file is an archive to extract.
+    pub fn with_extract(self, extract: bool) -> Self {
+        Self {
+            extract: Some(extract),
+            ..self
+        }
+    }
+
+    /// Set the path of the binary, within the archive if extracted, to install.
+    pub fn with_binary(self, binary: &str) -> Self {
+        Self {
+            binary: Some(binary.to_string()),
+            ..self
+        }
+    }
+
+    /// Return the (un-substituted) URL to download from.
+    pub fn url(&self) -> &String {
+        &self.url
+    }
+
+    /// Return the expected SHA-256 checksum of the downloaded file.
+    pub fn sha256(&self) -> &String {
+        &self.sha256
+    }
+
+    /// Return the version to download, if specified.
+    pub fn version(&self) -> &Option<String> {
+        &self.version
+    }
+
+    /// Return `true` if the downloaded file is an archive to extract. If not specified this is
+    /// determined from the file extension of the URL.
+    pub fn is_extract(&self) -> bool {
+        self.extract
+            .unwrap_or_else(|| archive_kind(&self.url).is_some())
+    }
+
+    /// Return the (un-substituted) path of the binary to install, if specified.
+    pub fn binary(&self) -> &Option<String> {
+        &self.binary
+    }
+
+    /// Add the `download_version` variable to the provided set.
+    pub fn add_vars(&self, package_vars: &HashMap<String, String>) -> HashMap<String, String> {
+        let mut replacements = package_vars.clone();
+        if let Some(version) = &self.version {
+            insert_built_in_var(&mut replacements, "download_version", version.clone());
+        }
+        replacements
+    }
+
+    /// Perform the installer action for the package using this download description.
+    pub fn execute(
+        &self,
+        action: &InstallActionKind,
+        package: &Package,
+        variable_replacements: &HashMap<String, String>,
+    ) -> Result<Option<CommandOutput>> {
+        let variable_replacements = self.add_vars(variable_replacements);
+        let binary_path = self.binary_path(package, &variable_replacements);
+        let installed_path = self.installed_path(package, &variable_replacements);
+        match action {
+            InstallActionKind::Install | InstallActionKind::Update => {
+                reportln!(
+                    "* performing {} on {} package {}",
+                    action,
+                    DOWNLOAD_INSTALLER,
+                    package.name()
+                );
+                Ok(Some(install_from_url(
+                    &var_string_replace(&self.url, &variable_replacements),
+                    Some(&self.sha256),
+                    self.is_extract(),
+                    &binary_path,
+                    &installed_path,
+                )?))
+            }
+            InstallActionKind::Uninstall => {
+                reportln!(
+                    "* performing {} on {} package {}",
+                    action,
+                    DOWNLOAD_INSTALLER,
+                    package.name()
+                );
+                if installed_path.is_file() {
+                    std::fs::remove_file(&installed_path)?;
+                }
+                Ok(None)
+            }
+            InstallActionKind::LinkFiles => Ok(None),
+        }
+    }
+
+    /// Return the path the package's binary is installed to.
+    pub fn installed_path(
+        &self,
+        package: &Package,
+        variable_replacements: &HashMap<String, String>,
+    ) -> PathBuf {
+        let variable_replacements = self.add_vars(variable_replacements);
+        let binary_path = self.binary_path(package, &variable_replacements);
+        download_bin_path(vars_scope(&variable_replacements))
+            .join(binary_path.file_name().unwrap_or_default())
+    }
+
+    fn binary_path(&self, package: &Package, variables: &HashMap<String, String>) -> PathBuf {
+        PathBuf::from(match &self.binary {
+            Some(binary) => var_string_replace(binary, variables),
+            None => package.name().to_string(),
+        })
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+// Private Types
+// ------------------------------------------------------------------------------------------------
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum ArchiveKind {
+    Tar,
+    Zip,
+}
+
+// ------------------------------------------------------------------------------------------------
+// Private Functions
+// ------------------------------------------------------------------------------------------------
+
+fn download_into(
+    url: &str,
+    sha256: Option<&str>,
+    extract: bool,
+    work_dir: &Path,
+    binary_path: &Path,
+    installed_path: &Path,
+) -> Result<u64> {
+    let download_path = download_file(url, sha256, work_dir)?;
+
+    let source_path = if extract {
+        extract_archive(url, &download_path, work_dir)?;
+        let source_path = work_dir.join(binary_path);
+        if !source_path.is_file() && binary_path.parent() == Some(Path::new("")) {
+            find_file(work_dir, binary_path.as_os_str()).unwrap_or(source_path)
+        } else {
+            source_path
+        }
+    } else {
+        download_path
+    };
+
+    if !source_path.is_file() {
+        return Err(Error::InvalidConfigValue(
+            "binary".to_string(),
+            binary_path.to_string_lossy().into_owned(),
+        ));
+    }
+
+    std::fs::create_dir_all(installed_path.parent().unwrap())?;
+    let bytes = std::fs::copy(&source_path, installed_path)?;
+    set_executable(installed_path)?;
+    Ok(bytes)
+}
+
+fn find_file(in_dir: &Path, file_name: &OsStr) -> Option<PathBuf> {
+    for entry in std::fs::read_dir(in_dir).ok()?.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            if let Some(found) = find_file(&path, file_name) {
+                return Some(found);
+            }
+        } else if path.file_name() == Some(file_name) {
+            return Some(path);
+        }
+    }
+    None
+}
+
+// Return the path of the URL, without the scheme and host, or any query or fragment.
+fn url_path(url: &str) -> &str {
+    let url = url.split(['?', '#']).next().unwrap_or_default();
+    match url.split_once("://") {
+        Some((_, rest)) => rest
+            .split_once('/')
+            .map(|(_, path)| path)
+            .unwrap_or_default(),
+        None => url,
+    }
+}
+
+// Return the last segment of the URL's path, the name of the file it refers to.
+fn url_file_name(url: &str) -> Result<String> {
+    match url_path(url).rsplit('/').next() {
+        Some(name) if !name.is_empty() && name != "." && name != ".." => Ok(name.to_string()),
+        _ => Err(Error::InvalidConfigValue(
+            "url".to_string(),
+            url.to_string(),
+        )),
+    }
+}
+
+fn archive_kind(url: &str) -> Option<ArchiveKind> {
+    const TAR_EXTENSIONS: &[&str] = &[".tar", ".tar.gz", ".tgz", ".tar.xz", ".txz", ".tar.bz2"];
+    let url = url_path(url).to_lowercase();
+    if url.ends_with(".zip") {
+        Some(ArchiveKind::Zip)
+    } else if TAR_EXTENSIONS.iter().any(|ext| url.ends_with(ext)) {
+        Some(ArchiveKind::Tar)
+    } else {
+        None
+    }
+}
+
+fn set_executable(path: &Path) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    let mut permissions = std::fs::metadata(path)?.permissions();
+    permissions.set_mode(0o755);
+    std::fs::set_permissions(path, permissions)?;
+    Ok(())
+}
+
+// ------------------------------------------------------------------------------------------------
+// Unit Tests
+// ------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use std::io::Write;
+
+    #[test]
+    fn test_archive_kind() {
+        assert_eq!(
+            archive_kind("https://x.org/rg.tar.gz"),
+            Some(ArchiveKind::Tar)
+        );
+        assert_eq!(archive_kind("https://x.org/rg.TGZ"), Some(ArchiveKind::Tar));
+        assert_eq!(archive_kind("https://x.org/rg.zip"), Some(ArchiveKind::Zip));
+        assert_eq!(archive_kind("https://x.org/rg"), None);
+        assert_eq!(
+            archive_kind("https://x.org/rg.zip?raw=true"),
+            Some(ArchiveKind::Zip)
+        );
+    }
+
+    #[test]
+    #[cfg(any(feature = "github-releases", feature = "webhooks"))]
+    fn test_curl_config_value() {
+        assert_eq!(
+            curl_config_value("https://hooks.example.com/T00/B00?token=x"),
+            r#""https://hooks.example.com/T00/B00?token=x""#
+        );
+        assert_eq!(
+            curl_config_value("{\"text\": \"a\\\\b\nc\"}"),
+            r#""{\"text\": \"a\\\\b\nc\"}""#
+        );
+    }
+
+    #[test]
+    fn test_url_file_name() {
+        assert_eq!(
+            url_file_name("https://x.org/releases/rg.tar.gz").unwrap(),
+            "rg.tar.gz"
+        );
+        assert_eq!(
+            url_file_name("https://x.org/download/rg?version=1.0#top").unwrap(),
+            "rg"
+        );
+        assert!(url_file_name("https://x.org/releases/").is_err());
+        assert!(url_file_name("https://x.org").is_err());
+        assert!(url_file_name("https://x.org/releases/..?name=rg").is_err());
+    }
+
+    #[test]
+    fn test_is_extract() {
+        assert!(PackageDownload::new("https://x.org/rg.tar.gz", "").is_extract());
+        assert!(!PackageDownload::new("https://x.org/rg", "").is_extract());
+        assert!(!PackageDownload::new("https://x.org/rg.tar.gz", "")
+            .with_extract(false)
+            .is_extract());
+    }
+
+    #[test]
+    fn test_sha256_file() {
+        let work_dir = tempfile::tempdir().unwrap();
+        let path = work_dir.path().join("hello.txt");
+        let mut file = File::create(&path).unwrap();
+        file.write_all(b"hello world").unwrap();
+        drop(file);
+        assert_eq!(
+            sha256_file(&path).unwrap(),
+            "b94d27b9934d3e08a52e52d7da7dabfac484efe37a5380ee9088f7ace2efcde9"
+        );
+    }
+}
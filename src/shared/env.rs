@@ -1,23 +1,92 @@
+use crate::error::{Error, Result};
+use crate::shared::dirs;
+use crate::shared::secrets::is_secret_var;
 use crate::shared::{
     user_shell, InstallActionKind, Package, PackageRepository, PackageSet, Platform,
 };
+use crate::APP_NAME;
 use dirs_next::home_dir;
 use regex::Regex;
+use semver::Version;
 use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::fs::read_to_string;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Mutex;
+
+// ------------------------------------------------------------------------------------------------
+// Public Types
+// ------------------------------------------------------------------------------------------------
+
+///
+/// The scope of the environment the tool configures; either the current user's home, or the
+/// whole machine, for all users. The scope determines where the tool's own configuration, data,
+/// and log files are kept, see the [`dirs`](../dirs/index.html) module. The scope is chosen when
+/// the tool starts, and is carried in the `install_scope` variable of the environment.
+///
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Scope {
+    /// Configure the current user; files are kept in the user's home directory.
+    #[default]
+    User,
+    /// Configure the machine for all users; configuration is kept in `/etc/mcfg`, and data and
+    /// logs in `/var/lib/mcfg`. Any changes in this scope require root privileges.
+    System,
+}
+
+///
+/// The name of the file containing user-defined global variables, both in the repository's
+/// `.config` directory and in the tool's local configuration directory.
+///
+pub const VARS_FILE: &str = "vars.yml";
+
+///
+/// The prefix that namespaces built-in variables; each built-in variable, such as `platform`, may
+/// also be referenced as `mcfg.platform`, and the namespaced name always refers to the built-in
+/// value, even if a user-defined variable of the same name overrides it.
+///
+pub const BUILT_IN_VAR_PREFIX: &str = "mcfg.";
+
+///
+/// The text written in a string to produce literal opening braces, `"{{"`, rather than a variable.
+///
+pub const ESCAPED_BRACES: &str = "\\{{";
 
 // ------------------------------------------------------------------------------------------------
 // Public Functions
 // ------------------------------------------------------------------------------------------------
 
 ///
-/// Return a default set of variables, these can be the basis for any script/command execution
-/// environment.
+/// Returns `true` if the current process is running with root privileges.
+///
+#[allow(unsafe_code)]
+pub fn is_root() -> bool {
+    unsafe { libc::geteuid() == 0 }
+}
+
+///
+/// Returns an error if `scope` is `Scope::System` and the current process is not running with root
+/// privileges.
+///
+pub fn check_scope_privileges(scope: Scope) -> Result<()> {
+    if scope == Scope::System && !is_root() {
+        Err(Error::SystemScopeRequiresRoot)
+    } else {
+        Ok(())
+    }
+}
+
+///
+/// Return a default set of variables for `scope`, these can be the basis for any script/command
+/// execution environment.
 ///
 /// ## Variables set
 ///
 /// The following variables are set by this function.
 ///
 /// * `home` - the current user's home directory, usually equivalent to `$HOME`.
+/// * `install_scope` - the `Scope`, either `user` or `system`.
 /// * `command_log_level` - the name of the current log level, if a command wishes to do any
 ///   logging of it's own.
 /// * `command_shell` - the name of the command shell used to execute script strings.
@@ -30,42 +99,54 @@ use std::collections::HashMap;
 /// * `repo_local_path` - the path within the package repository for local files, including the
 ///   `bin` directory.
 ///
-pub fn default_vars() -> HashMap<String, String> {
+pub fn default_vars(scope: Scope) -> HashMap<String, String> {
     let mut replacements: HashMap<String, String> = Default::default();
-    let _ = replacements.insert(
-        "home".to_string(),
+    insert_built_in_var(
+        &mut replacements,
+        "home",
         home_dir().unwrap().to_string_lossy().to_string(),
     );
-    let _ = replacements.insert(
-        "command_log_level".to_string(),
+    insert_built_in_var(&mut replacements, "install_scope", scope.to_string());
+    insert_built_in_var(
+        &mut replacements,
+        "command_log_level",
         log::max_level().to_string().to_lowercase(),
     );
-    let _ = replacements.insert("command_shell".to_string(), user_shell());
+    insert_built_in_var(&mut replacements, "command_shell", user_shell());
     if let Some(download_dir) = dirs_next::download_dir() {
-        let _ = replacements.insert(
-            "local_download_path".to_string(),
+        insert_built_in_var(
+            &mut replacements,
+            "local_download_path",
             download_dir.to_string_lossy().to_string(),
         );
     }
-    let _ = replacements.insert("platform".to_string(), Platform::CURRENT.to_string());
-    let _ = replacements.insert(
-        "platform_family".to_string(),
+    insert_built_in_var(&mut replacements, "platform", Platform::CURRENT.to_string());
+    insert_built_in_var(
+        &mut replacements,
+        "platform_family",
         std::env::consts::FAMILY.to_string(),
     );
-    let _ = replacements.insert("platform_os".to_string(), std::env::consts::OS.to_string());
-    let _ = replacements.insert(
-        "platform_arch".to_string(),
+    insert_built_in_var(
+        &mut replacements,
+        "platform_os",
+        std::env::consts::OS.to_string(),
+    );
+    insert_built_in_var(
+        &mut replacements,
+        "platform_arch",
         std::env::consts::ARCH.to_string(),
     );
-    let _ = replacements.insert(
-        "repo_config_path".to_string(),
-        PackageRepository::default_config_path()
+    insert_built_in_var(
+        &mut replacements,
+        "repo_config_path",
+        PackageRepository::default_config_path(scope)
             .to_string_lossy()
             .to_string(),
     );
-    let _ = replacements.insert(
-        "repo_local_path".to_string(),
-        PackageRepository::default_local_path()
+    insert_built_in_var(
+        &mut replacements,
+        "repo_local_path",
+        PackageRepository::default_local_path(scope)
             .to_string_lossy()
             .to_string(),
     );
@@ -91,11 +172,68 @@ pub fn add_action_vars(
     default_vars: &HashMap<String, String>,
 ) -> HashMap<String, String> {
     let mut replacements = default_vars.clone();
-    let _ = replacements.insert("command_action".to_string(), action.to_string());
+    insert_built_in_var(&mut replacements, "command_action", action.to_string());
     debug!("add_action_vars: {:?}", &replacements);
     replacements
 }
 
+///
+/// Add the user-defined global variables, these are read from the files returned by
+/// `global_vars_paths`, for the scope in `action_vars`, with later files overriding earlier ones.
+/// As with `add_other_vars` these variables support substitution using the values in
+/// `action_vars`.
+///
+/// It is expected that these variables are added to those returned from `add_action_vars`.
+///
+pub fn add_global_vars(action_vars: &HashMap<String, String>) -> HashMap<String, String> {
+    let mut replacements = action_vars.clone();
+    let mut global_vars = GLOBAL_VARS.lock().unwrap();
+    for vars in global_vars
+        .entry(vars_scope(action_vars))
+        .or_insert_with_key(|scope| read_global_vars(*scope))
+        .iter()
+    {
+        replacements = add_other_vars(&replacements, vars);
+    }
+    debug!("add_global_vars: {:?}", &replacements);
+    replacements
+}
+
+///
+/// Return the paths of the files containing user-defined global variables, in the order they are
+/// applied, in `scope`; the file in the repository, shared by all machines, and then the file for
+/// this machine only.
+///
+pub fn global_vars_paths(scope: Scope) -> Vec<PathBuf> {
+    let mut paths = vec![PackageRepository::default_config_path(scope).join(VARS_FILE)];
+    if let Some(config_dir) = dirs::config_dir_for(scope, APP_NAME) {
+        paths.push(config_dir.join(VARS_FILE));
+    }
+    paths
+}
+
+///
+/// Return the scope in the `install_scope` variable in `variables`; `Scope::User` if it is not
+/// set, or is not a scope.
+///
+pub fn vars_scope(variables: &HashMap<String, String>) -> Scope {
+    variables
+        .get("install_scope")
+        .and_then(|scope| Scope::from_str(scope).ok())
+        .unwrap_or_default()
+}
+
+///
+/// Read the user-defined variables in the file at `path`, a missing file has no variables.
+///
+pub fn read_vars_file(path: &Path) -> Result<HashMap<String, String>> {
+    if path.is_file() {
+        Ok(serde_yaml::from_str(&read_to_string(path)?)?)
+    } else {
+        Ok(Default::default())
+    }
+}
+
 ///
 /// Add additional variables based on the the selected PackageSet.
 ///
@@ -114,12 +252,14 @@ pub fn add_package_set_action_vars(
     action_vars: &HashMap<String, String>,
 ) -> HashMap<String, String> {
     let mut replacements = action_vars.clone();
-    let _ = replacements.insert(
-        "package_set_name".to_string(),
+    insert_built_in_var(
+        &mut replacements,
+        "package_set_name",
         package_set.name().to_string(),
     );
-    let _ = replacements.insert(
-        "package_set_file".to_string(),
+    insert_built_in_var(
+        &mut replacements,
+        "package_set_file",
         package_set
             .path()
             .file_name()
@@ -127,8 +267,9 @@ pub fn add_package_set_action_vars(
             .to_string_lossy()
             .into_owned(),
     );
-    let _ = replacements.insert(
-        "package_set_path".to_string(),
+    insert_built_in_var(
+        &mut replacements,
+        "package_set_path",
         package_set
             .path()
             .parent()
@@ -151,6 +292,10 @@ pub fn add_package_set_action_vars(
 /// The following variables are set by this function.
 ///
 /// * `package_name` - the name of the package being actioned.
+/// * `package_id` - the installer-specific identifier of the package being actioned, if the package
+///   does not specify an `id` this is the same as `package_name`.
+/// * `package_args` - any additional installer arguments for the package being actioned, this may
+///   be an empty string.
 /// * `package_config_path` - the current user's local configuration path for this package.
 /// * `package_data_local_path` - the current user's local data path for this package.
 /// * `package_log_path` - the full path to the installer log file.
@@ -160,24 +305,46 @@ pub fn add_package_action_vars(
     package_set_vars: &HashMap<String, String>,
 ) -> HashMap<String, String> {
     let mut replacements = package_set_vars.clone();
-    let _ = replacements.insert("package_name".to_string(), package.name().to_string());
-    let _ = replacements.insert(
-        "package_config_path".to_string(),
-        xdirs::config_dir_for(&package.name().to_string())
+    let scope = vars_scope(package_set_vars);
+    insert_built_in_var(
+        &mut replacements,
+        "package_name",
+        package.name().to_string(),
+    );
+    insert_built_in_var(
+        &mut replacements,
+        "package_id",
+        package
+            .id()
+            .as_ref()
+            .cloned()
+            .unwrap_or_else(|| package.name().to_string()),
+    );
+    insert_built_in_var(
+        &mut replacements,
+        "package_args",
+        package.args().as_ref().cloned().unwrap_or_default(),
+    );
+    insert_built_in_var(
+        &mut replacements,
+        "package_config_path",
+        dirs::config_dir_for(scope, &package.name().to_string())
             .unwrap()
             .to_string_lossy()
             .into_owned(),
     );
-    let _ = replacements.insert(
-        "package_data_local_path".to_string(),
-        xdirs::data_local_dir_for(&package.name().to_string())
+    insert_built_in_var(
+        &mut replacements,
+        "package_data_local_path",
+        dirs::data_local_dir_for(scope, &package.name().to_string())
             .unwrap()
             .to_string_lossy()
             .into_owned(),
     );
-    let _ = replacements.insert(
-        "package_log_path".to_string(),
-        xdirs::log_dir_for(&package.name().to_string())
+    insert_built_in_var(
+        &mut replacements,
+        "package_log_path",
+        dirs::log_dir_for(scope, &package.name().to_string())
             .unwrap()
             .to_string_lossy()
             .into_owned(),
@@ -187,6 +354,77 @@ pub fn add_package_action_vars(
     replacements
 }
 
+///
+/// Add additional variables based on the the selected package set's bundle file.
+///
+/// It is expected that these variables are added to those returned from `add_package_set_action_vars`.
+///
+/// ## Variables set
+///
+/// The following variables are set by this function.
+///
+/// * `bundle_file` - the full path to the bundle file being actioned.
+///
+pub fn add_bundle_action_vars(
+    bundle_file: &Path,
+    package_set_vars: &HashMap<String, String>,
+) -> HashMap<String, String> {
+    let mut replacements = package_set_vars.clone();
+    insert_built_in_var(
+        &mut replacements,
+        "bundle_file",
+        bundle_file.to_string_lossy().into_owned(),
+    );
+
+    debug!("add_bundle_action_vars: {:?}", &replacements);
+    replacements
+}
+
+///
+/// Add additional variables for a specific version of the selected Package, used when a package
+/// with a version constraint is updated to a version other than the latest.
+///
+/// It is expected that these variables are added to those returned from `add_package_action_vars`.
+///
+/// ## Variables set
+///
+/// The following variables are set by this function.
+///
+/// * `package_version` - the version of the package to install.
+///
+pub fn add_package_version_vars(
+    version: &Version,
+    package_vars: &HashMap<String, String>,
+) -> HashMap<String, String> {
+    let mut replacements = package_vars.clone();
+    insert_built_in_var(&mut replacements, "package_version", version.to_string());
+
+    debug!("add_package_version_vars: {:?}", &replacements);
+    replacements
+}
+
+///
+/// Add additional variables for a prerequisite of the selected package set.
+///
+/// It is expected that these variables are added to those returned from `add_package_set_action_vars`.
+///
+/// ## Variables set
+///
+/// The following variables are set by this function.
+///
+/// * `prerequisite` - the prerequisite being added, such as a Homebrew tap.
+///
+pub fn add_prerequisite_vars(
+    prerequisite: &str,
+    package_set_vars: &HashMap<String, String>,
+) -> HashMap<String, String> {
+    let mut replacements = package_set_vars.clone();
+    insert_built_in_var(&mut replacements, "prerequisite", prerequisite.to_string());
+
+    debug!("add_prerequisite_vars: {:?}", &replacements);
+    replacements
+}
+
 ///
 /// Add any additional variables outside the pre-defined set. These, and **only** these variable
 /// mappings support substitution using the values in `existing_vars`. Substitution will be applied
@@ -201,10 +439,12 @@ pub fn add_other_vars(
     let mut replacements = existing_vars.clone();
 
     for (key, value) in other_vars {
-        let _ = replacements.insert(
-            var_string_replace(key, &replacements),
-            var_string_replace(value, &replacements),
-        );
+        let key = var_string_replace(key, &replacements);
+        if key.starts_with(BUILT_IN_VAR_PREFIX) {
+            warn!("Variable {:?} is in the built-in namespace, ignored", key);
+            continue;
+        }
+        let _ = replacements.insert(key, var_string_replace(value, &replacements));
     }
 
     debug!("add_other_vars: {:?}", &replacements);
@@ -214,7 +454,8 @@ pub fn add_other_vars(
 ///
 /// Convert the set of provided variables into the preferred for for use as environment
 /// variables in sub-processes. This involves upper-casing the key value and adding the prefix
-/// `MCFG_`.
+/// `MCFG_`. Namespaced built-in variables are not included, they duplicate the un-namespaced ones.
+/// The repository's local `bin` directory, for the scope in `variables`, is added to `PATH`.
 ///
 pub fn vars_to_env_vars(
     variables: &HashMap<String, String>,
@@ -222,23 +463,98 @@ pub fn vars_to_env_vars(
 ) -> HashMap<String, String> {
     let mut env_vars: HashMap<String, String> = variables
         .iter()
+        .filter(|(k, _)| !k.starts_with(BUILT_IN_VAR_PREFIX))
         .map(|(k, v)| (format!("{}_{}", prefix, k.to_uppercase()), v.clone()))
         .collect();
     if let Ok(current_path) = std::env::var("PATH") {
         let _ = env_vars.insert(
             "PATH".to_string(),
             format!(
-                "{}:{:?}/bin",
+                "{}:{}",
                 current_path,
-                PackageRepository::default_local_path()
+                PackageRepository::default_local_path(vars_scope(variables))
+                    .join("bin")
+                    .display()
             ),
         );
     }
     env_vars
 }
 
+///
+/// The names of all variables set by the functions in this module; any other variable referenced
+/// must be defined by a package set's `env-vars`.
+///
+pub const BUILT_IN_VARS: &[&str] = &[
+    "home",
+    "install_scope",
+    "command_log_level",
+    "command_shell",
+    "local_download_path",
+    "platform",
+    "platform_family",
+    "platform_os",
+    "platform_arch",
+    "repo_config_path",
+    "repo_local_path",
+    "command_action",
+    "package_set_name",
+    "package_set_file",
+    "package_set_path",
+    "package_name",
+    "package_id",
+    "package_args",
+    "package_config_path",
+    "package_data_local_path",
+    "package_log_path",
+    "package_version",
+    "bundle_file",
+    "prerequisite",
+    "download_version",
+];
+
+///
+/// Return the names of all variables referenced, using the handlebars convention of `"{{name}}"`,
+/// in `string`; in the order they are referenced.
+///
+pub fn var_references(string: &str) -> Vec<String> {
+    VARIABLES
+        .captures_iter(string)
+        .filter_map(|capture| capture.get(2))
+        .map(|var| var.as_str()[2..var.as_str().len() - 2].to_string())
+        .collect()
+}
+
+///
+/// Returns `true` if `var_name` is a built-in variable, either with or without the built-in
+/// namespace prefix.
+///
+pub fn is_built_in_var(var_name: &str) -> bool {
+    BUILT_IN_VARS.contains(
+        &var_name
+            .strip_prefix(BUILT_IN_VAR_PREFIX)
+            .unwrap_or(var_name),
+    )
+}
+
+// Insert a built-in variable under both its plain, and its namespaced, name.
+pub(crate) fn insert_built_in_var(vars: &mut HashMap<String, String>, name: &str, value: String) {
+    let _ = vars.insert(format!("{}{}", BUILT_IN_VAR_PREFIX, name), value.clone());
+    let _ = vars.insert(name.to_string(), value);
+}
+
 lazy_static! {
-    static ref VARIABLES: Regex = Regex::new(r#"(\{\{[a-zA-Z0-9\-_:]+\}\})"#).unwrap();
+    // Group 1 is an escape, `\{{`, group 2 is a variable reference.
+    pub(crate) static ref VARIABLES: Regex = Regex::new(
+        r#"(\\\{\{)|(\{\{(?:secret:[^{}\s]+|[a-zA-Z0-9\-_:.]+)\}\})"#
+    )
+    .unwrap();
+}
+
+lazy_static! {
+    // Read once for each scope.
+    static ref GLOBAL_VARS: Mutex<HashMap<Scope, Vec<HashMap<String, String>>>> =
+        Default::default();
 }
 
 ///
@@ -248,30 +564,89 @@ lazy_static! {
 /// If no substitution is found in `vars` for a variable the name is simply used instead.
 ///
 /// Note that the name portion may only include letters, numbers and the characters `'-'`, `'_'`,
-/// and `':'`. Also, not whitespace is allowed between the braces and name.
+/// `':'`, and `'.'`. Also, not whitespace is allowed between the braces and name. Secret variables,
+/// `"{{secret:<reference>}}"`, are left in place unless they are present in `vars`, see the
+/// [`secrets`](../secrets/index.html) module. Literal braces are written as `"\{{"`, which is
+/// replaced by `"{{"`, and the text following it is not treated as a variable.
 ///
 pub fn var_string_replace(string: &str, vars: &HashMap<String, String>) -> String {
     let mut out_string = String::new();
 
     let mut from: usize = 0;
     for capture in VARIABLES.captures_iter(string) {
-        let var = capture.get(1).unwrap();
+        let var = capture.get(0).unwrap();
         out_string.push_str(&string[from..var.start()]);
+        from = var.end();
+        if capture.get(1).is_some() {
+            out_string.push_str(&ESCAPED_BRACES[1..]);
+            continue;
+        }
         let var_name = var.as_str();
         let var_name = &var_name[2..var_name.len() - 2];
         if let Some(replacement) = vars.get(var_name) {
             out_string.push_str(replacement)
+        } else if is_secret_var(var_name) {
+            // Secrets are only resolved when a command is executed.
+            out_string.push_str(var.as_str());
         } else {
             warn!("No variable named {:?} in replacements", var_name);
             out_string.push_str(var_name);
         }
-        from = var.end();
     }
     out_string.push_str(&string[from..]);
 
     out_string
 }
 
+// ------------------------------------------------------------------------------------------------
+// Implementations
+// ------------------------------------------------------------------------------------------------
+
+impl Display for Scope {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Scope::User => "user",
+                Scope::System => "system",
+            }
+        )
+    }
+}
+
+impl FromStr for Scope {
+    type Err = Error;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "user" => Ok(Scope::User),
+            "system" => Ok(Scope::System),
+            _ => Err(Error::InvalidConfigValue(
+                "install_scope".to_string(),
+                s.to_string(),
+            )),
+        }
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+// Private Functions
+// ------------------------------------------------------------------------------------------------
+
+// A file that can't be read is reported here and by `lint`, but otherwise ignored.
+fn read_global_vars(scope: Scope) -> Vec<HashMap<String, String>> {
+    global_vars_paths(scope)
+        .iter()
+        .map(|path| {
+            read_vars_file(path).unwrap_or_else(|e| {
+                ereportln!("Could not read variables file {:?}: {}", path, e);
+                Default::default()
+            })
+        })
+        .collect()
+}
+
 // ------------------------------------------------------------------------------------------------
 // Unit Tests
 // ------------------------------------------------------------------------------------------------
@@ -301,9 +676,60 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_replace_escaped_braces() {
+        let replacements: HashMap<String, String> = vec![("name", "wallace")]
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+
+        assert_eq!(
+            var_string_replace(r#"\{{name}} is {{name}}"#, &replacements),
+            "{{name}} is wallace"
+        );
+        assert_eq!(
+            var_string_replace(r#"{{#each items}}\{{this}}{{/each}}"#, &replacements),
+            "{{#each items}}{{this}}{{/each}}"
+        );
+        assert_eq!(
+            var_references(r#"\{{name}} {{other}}"#),
+            vec!["other".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_namespaced_built_in_variables() {
+        let replacements = default_vars(Scope::User);
+        let user_vars: HashMap<String, String> =
+            [("platform", "my-platform"), ("mcfg.platform_os", "my-os")]
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect();
+        let replacements = add_other_vars(&replacements, &user_vars);
+
+        assert_eq!(
+            var_string_replace("{{platform}} {{mcfg.platform}}", &replacements),
+            format!("my-platform {}", Platform::CURRENT)
+        );
+        assert_eq!(
+            var_string_replace("{{mcfg.platform_os}}", &replacements),
+            std::env::consts::OS
+        );
+        assert!(is_built_in_var("mcfg.home"));
+        assert!(!is_built_in_var("mcfg.lux_home"));
+        let env_vars = vars_to_env_vars(&replacements, "MCFG");
+        assert!(!env_vars.keys().any(|key| key.contains('.')));
+        assert!(env_vars.get("PATH").unwrap().ends_with(&format!(
+            ":{}",
+            PackageRepository::default_local_path(Scope::User)
+                .join("bin")
+                .display()
+        )));
+    }
+
     #[test]
     fn test_replace_variables_in_variables() {
-        let replacements = default_vars();
+        let replacements = default_vars(Scope::User);
 
         let test_vars: HashMap<String, String> = vec![
             (
@@ -336,4 +762,42 @@ mod tests {
             "this is my platform"
         );
     }
+
+    #[test]
+    fn test_package_id_variable() {
+        use crate::shared::builders::{Builder, PackageBuilder};
+        use crate::shared::Name;
+        use std::str::FromStr;
+
+        let package = PackageBuilder::named(Name::from_str("xcode").unwrap()).build();
+        let replacements = add_package_action_vars(&package, &Default::default());
+        assert_eq!(replacements.get("package_id").unwrap(), "xcode");
+
+        let package = PackageBuilder::named(Name::from_str("xcode").unwrap())
+            .with_id("497799835")
+            .build();
+        let replacements = add_package_action_vars(&package, &Default::default());
+        assert_eq!(replacements.get("package_name").unwrap(), "xcode");
+        assert_eq!(replacements.get("package_id").unwrap(), "497799835");
+    }
+
+    #[test]
+    fn test_package_args_variable() {
+        use crate::shared::builders::{Builder, PackageBuilder};
+        use crate::shared::Name;
+        use std::str::FromStr;
+
+        let package = PackageBuilder::named(Name::from_str("ripgrep").unwrap()).build();
+        let replacements = add_package_action_vars(&package, &Default::default());
+        assert_eq!(replacements.get("package_args").unwrap(), "");
+
+        let package = PackageBuilder::named(Name::from_str("ripgrep").unwrap())
+            .with_args("--features pcre2")
+            .build();
+        let replacements = add_package_action_vars(&package, &Default::default());
+        assert_eq!(
+            replacements.get("package_args").unwrap(),
+            "--features pcre2"
+        );
+    }
 }
@@ -0,0 +1,395 @@
+/*!
+Shell environment scripts, generated from the env-files of all installed package sets and the
+repository's local `bin` directory, so that a shell startup file need only source a single file
+rather than each package set's env-file.
+
+Two scripts are written to the repository's local directory; `mcfg-env.sh` for POSIX shells, which
+sources each env-file in turn, and `mcfg-env.fish` for fish, into which the variable assignments in
+each env-file are copied. Env-files are expected to contain simple assignments, `NAME=value` or
+`export NAME=value`; any other line cannot be copied into the fish script and is skipped.
+
+A package set may also have an env-file for a single shell, in its `shell-files`; these are
+sourced as they are, by the POSIX script for `sh`, `bash`, and `zsh` (only when run by that shell),
+and by the fish script for `fish`.
+*/
+
+use crate::error::Result;
+use crate::shared::install_log::InstallLog;
+use crate::shared::{
+    shell_escape, InstallActionKind, Name, PackageRepository, PackageSet, ShellFiles,
+};
+use std::collections::HashSet;
+use std::fs::read_to_string;
+use std::path::{Path, PathBuf};
+
+// ------------------------------------------------------------------------------------------------
+// Public Types
+// ------------------------------------------------------------------------------------------------
+
+///
+/// The content of the shell environment scripts; the directories added to `PATH`, and the
+/// env-file of each installed package set.
+///
+#[derive(Clone, Debug, PartialEq)]
+pub struct EnvScript {
+    path_entries: Vec<PathBuf>,
+    env_files: Vec<(String, PathBuf)>,
+    shell_env_files: Vec<(String, String, PathBuf)>,
+}
+
+///
+/// The file name of the script for POSIX shells.
+///
+pub const ENV_SCRIPT_FILE: &str = "mcfg-env.sh";
+
+///
+/// The file name of the script for the fish shell.
+///
+pub const ENV_SCRIPT_FISH_FILE: &str = "mcfg-env.fish";
+
+// ------------------------------------------------------------------------------------------------
+// Public Functions
+// ------------------------------------------------------------------------------------------------
+
+///
+/// Write the shell environment scripts to the repository's local directory, for the package sets
+/// in `repository` installed according to the install log `log`.
+///
+pub fn write_env_scripts(
+    repository: &PackageRepository,
+    log: &mut dyn InstallLog,
+) -> Result<EnvScript> {
+    let script = EnvScript::from_installed(repository, log)?;
+    script.write(&repository.local_path())?;
+    Ok(script)
+}
+
+///
+/// Regenerate the shell environment scripts, as `write_env_scripts` does, after an action changes
+/// the package sets installed.
+///
+/// Any failure to write the scripts is logged, it is never returned as an error as it should not
+/// affect the outcome of the action that prompted it.
+///
+pub fn regenerate_env_scripts(repository: &PackageRepository, log: &mut dyn InstallLog) {
+    if let Err(e) = write_env_scripts(repository, log) {
+        warn!(
+            "regenerate_env_scripts: could not write scripts, error: {}",
+            e
+        );
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+// Implementations
+// ------------------------------------------------------------------------------------------------
+
+impl EnvScript {
+    /// Create a script that adds `local_bin` to `PATH`, and sources no env-files.
+    pub fn new(local_bin: PathBuf) -> Self {
+        Self {
+            path_entries: vec![local_bin],
+            env_files: Default::default(),
+            shell_env_files: Default::default(),
+        }
+    }
+
+    /// Create the script for the package sets in `repository` that are installed on this
+    /// machine; those whose most recent install, update, or uninstall in `log` was not an
+    /// uninstall.
+    pub fn from_installed(
+        repository: &PackageRepository,
+        log: &mut dyn InstallLog,
+    ) -> Result<Self> {
+        let actions: Vec<String> = [
+            InstallActionKind::Install,
+            InstallActionKind::Update,
+            InstallActionKind::Uninstall,
+        ]
+        .iter()
+        .map(InstallActionKind::to_string)
+        .collect();
+        let uninstall = InstallActionKind::Uninstall.to_string();
+        let machine = log.machine().to_string();
+        // Applied package sets are most recent first, so only the first seen for each counts.
+        let mut seen: HashSet<(Name, Name)> = Default::default();
+        let mut installed: HashSet<(Name, Name)> = Default::default();
+        for applied in log.applied_package_sets()?.iter().filter(|applied| {
+            applied.machine().map(|m| m == &machine).unwrap_or(true)
+                && actions.iter().any(|action| action == applied.action())
+        }) {
+            let key = (
+                applied.package_set_group_name().clone(),
+                applied.package_set_name().clone(),
+            );
+            if seen.insert(key.clone()) && applied.action() != uninstall {
+                let _ = installed.insert(key);
+            }
+        }
+
+        let mut script = Self::new(repository.local_path().join("bin"));
+        for group in repository.groups() {
+            for package_set in group.package_sets() {
+                if installed.contains(&(group.name(), package_set.name().clone())) {
+                    let name = format!("{}/{}", group.name(), package_set.name());
+                    if let Some(env_file) = package_set.env_file_path() {
+                        script.add_env_file(&name, &env_file_source_path(package_set, env_file));
+                    }
+                    let mut shells: Vec<(&String, &ShellFiles)> =
+                        package_set.shell_files().iter().collect();
+                    shells.sort_by(|lhs, rhs| lhs.0.cmp(rhs.0));
+                    for (shell, shell_files) in shells {
+                        if let Some(env_file) = shell_files.env_file_path() {
+                            script.add_shell_env_file(
+                                &name,
+                                shell,
+                                &env_file_source_path(package_set, env_file),
+                            );
+                        }
+                    }
+                }
+            }
+        }
+        Ok(script)
+    }
+
+    /// Add the env-file at `path`, for the package set `package_set`, as `group/set`.
+    pub fn add_env_file(&mut self, package_set: &str, path: &Path) {
+        self.env_files
+            .push((package_set.to_string(), path.to_path_buf()));
+    }
+
+    /// Add the env-file at `path`, for the package set `package_set`, as `group/set`, that is only
+    /// sourced by the shell `shell`.
+    pub fn add_shell_env_file(&mut self, package_set: &str, shell: &str, path: &Path) {
+        self.shell_env_files.push((
+            package_set.to_string(),
+            shell.to_string(),
+            path.to_path_buf(),
+        ));
+    }
+
+    /// Return the directories added to `PATH`.
+    pub fn path_entries(&self) -> &Vec<PathBuf> {
+        &self.path_entries
+    }
+
+    /// Return the package set, as `group/set`, and path of each env-file.
+    pub fn env_files(&self) -> &Vec<(String, PathBuf)> {
+        &self.env_files
+    }
+
+    /// Return the package set, as `group/set`, shell, and path of each shell's env-file.
+    pub fn shell_env_files(&self) -> &Vec<(String, String, PathBuf)> {
+        &self.shell_env_files
+    }
+
+    /// Write both scripts to the directory `dir`.
+    pub fn write(&self, dir: &Path) -> Result<()> {
+        debug!("EnvScript::write ({:?})", dir);
+        std::fs::create_dir_all(dir)?;
+        std::fs::write(dir.join(ENV_SCRIPT_FILE), self.to_posix())?;
+        std::fs::write(dir.join(ENV_SCRIPT_FISH_FILE), self.to_fish())?;
+        Ok(())
+    }
+
+    /// Return the script for POSIX shells, sourcing each env-file that exists.
+    pub fn to_posix(&self) -> String {
+        let mut script = format!("{}\n", GENERATED_HEADER);
+        for path in &self.path_entries {
+            script.push_str(&format!(
+                "export PATH=\"$PATH\":{}\n",
+                shell_escape(&path.to_string_lossy())
+            ));
+        }
+        for (package_set, path) in &self.env_files {
+            let path = shell_escape(&path.to_string_lossy());
+            script.push_str(&format!(
+                "\n# {}\nif [ -f {} ]; then . {}; fi\n",
+                package_set, path, path
+            ));
+        }
+        for (package_set, shell, path) in &self.shell_env_files {
+            let condition = match shell.as_str() {
+                "sh" => String::new(),
+                "bash" => "[ -n \"$BASH_VERSION\" ] && ".to_string(),
+                "zsh" => "[ -n \"$ZSH_VERSION\" ] && ".to_string(),
+                _ => continue,
+            };
+            let path = shell_escape(&path.to_string_lossy());
+            script.push_str(&format!(
+                "\n# {} ({})\nif {}[ -f {} ]; then . {}; fi\n",
+                package_set, shell, condition, path, path
+            ));
+        }
+        script
+    }
+
+    /// Return the script for fish, with the assignments in each env-file copied into it; an
+    /// env-file that cannot be read is skipped.
+    pub fn to_fish(&self) -> String {
+        let mut script = format!("{}\n", GENERATED_HEADER);
+        for path in &self.path_entries {
+            script.push_str(&format!(
+                "set -gx PATH $PATH {}\n",
+                fish_escape(&path.to_string_lossy())
+            ));
+        }
+        for (package_set, path) in &self.env_files {
+            script.push_str(&format!("\n# {}\n", package_set));
+            match read_to_string(path) {
+                Ok(content) => script.push_str(&env_file_to_fish(&content)),
+                Err(e) => warn!(
+                    "EnvScript::to_fish: skipping env-file {:?}, error: {}",
+                    path, e
+                ),
+            }
+        }
+        for (package_set, _, path) in self
+            .shell_env_files
+            .iter()
+            .filter(|(_, shell, _)| shell == "fish")
+        {
+            let path = fish_escape(&path.to_string_lossy());
+            script.push_str(&format!(
+                "\n# {} (fish)\nif test -f {}; source {}; end\n",
+                package_set, path, path
+            ));
+        }
+        script
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+// Private Types
+// ------------------------------------------------------------------------------------------------
+
+const GENERATED_HEADER: &str =
+    "# Generated by mcfg from the env-files of installed package sets, do not edit.";
+
+// ------------------------------------------------------------------------------------------------
+// Private Functions
+// ------------------------------------------------------------------------------------------------
+
+// An env-file is named relative to the directory containing the package set file.
+fn env_file_source_path(package_set: &PackageSet, env_file: PathBuf) -> PathBuf {
+    match package_set.path().parent() {
+        Some(dir) => dir.join(env_file),
+        None => env_file,
+    }
+}
+
+// Convert the assignments in a POSIX env-file to fish; `$NAME` references are kept as fish
+// expands them in the same way, and the elements of a `PATH` value are set as a list.
+fn env_file_to_fish(content: &str) -> String {
+    let mut script = String::new();
+    for line in content.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let assignment = line.strip_prefix("export ").unwrap_or(line).trim();
+        match assignment.split_once('=') {
+            Some((name, value)) if is_var_name(name) => {
+                let value = unquote(value.trim());
+                let values: Vec<String> = if name.ends_with("PATH") {
+                    value.split(':').map(fish_value).collect()
+                } else {
+                    vec![fish_value(value)]
+                };
+                script.push_str(&format!("set -gx {} {}\n", name, values.join(" ")));
+            }
+            _ => {
+                warn!("env_file_to_fish: skipping line {:?}", line);
+                script.push_str(&format!("# skipped: {}\n", line));
+            }
+        }
+    }
+    script
+}
+
+fn is_var_name(name: &str) -> bool {
+    !name.is_empty()
+        && !name.starts_with(|c: char| c.is_ascii_digit())
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+fn unquote(value: &str) -> &str {
+    if value.len() >= 2
+        && ((value.starts_with('"') && value.ends_with('"'))
+            || (value.starts_with('\'') && value.ends_with('\'')))
+    {
+        &value[1..value.len() - 1]
+    } else {
+        value
+    }
+}
+
+// A value that is only a variable reference is left unquoted so that a list, such as `$PATH`,
+// keeps its elements; a value with a reference is double quoted, and any other single quoted.
+fn fish_value(value: &str) -> String {
+    if value.starts_with('$') && is_var_name(&value[1..]) {
+        value.to_string()
+    } else if value.contains('$') {
+        format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+    } else {
+        fish_escape(value)
+    }
+}
+
+fn fish_escape(value: &str) -> String {
+    format!("'{}'", value.replace('\\', "\\\\").replace('\'', "\\'"))
+}
+
+// ------------------------------------------------------------------------------------------------
+// Unit Tests
+// ------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_posix_script() {
+        let mut script =
+            EnvScript::new(PathBuf::from("/home/me/.config/mcfg/repository/.local/bin"));
+        script.add_env_file("tools/lux", Path::new("/repo/tools/lux/sample.env"));
+        assert_eq!(
+            script.to_posix(),
+            format!(
+                "{}\n{}\n\n{}\n{}\n",
+                GENERATED_HEADER,
+                "export PATH=\"$PATH\":/home/me/.config/mcfg/repository/.local/bin",
+                "# tools/lux",
+                "if [ -f /repo/tools/lux/sample.env ]; then . /repo/tools/lux/sample.env; fi",
+            )
+        );
+    }
+
+    #[test]
+    fn test_shell_env_files() {
+        let mut script = EnvScript::new(PathBuf::from("/local/bin"));
+        script.add_shell_env_file("tools/lux", "zsh", Path::new("/repo/tools/lux/lux.zsh"));
+        script.add_shell_env_file("tools/lux", "fish", Path::new("/repo/tools/lux/lux.fish"));
+        script.add_shell_env_file("tools/lux", "nu", Path::new("/repo/tools/lux/lux.nu"));
+        assert!(script.to_posix().ends_with(
+            "\n# tools/lux (zsh)\nif [ -n \"$ZSH_VERSION\" ] && [ -f /repo/tools/lux/lux.zsh ]; then . /repo/tools/lux/lux.zsh; fi\n"
+        ));
+        assert!(!script.to_posix().contains("lux.fish"));
+        assert!(script.to_fish().ends_with(
+            "\n# tools/lux (fish)\nif test -f '/repo/tools/lux/lux.fish'; source '/repo/tools/lux/lux.fish'; end\n"
+        ));
+        assert!(!script.to_fish().contains("lux.nu"));
+    }
+
+    #[test]
+    fn test_env_file_to_fish() {
+        let fish = env_file_to_fish(
+            "# comment\n\nexport LUX_HOME=\"$HOME/.lux\"\nLUX_MODE='fast'\nPATH=$PATH:/opt/lux/bin\nalias l=lux\nif true; then\n",
+        );
+        assert_eq!(
+            fish,
+            "set -gx LUX_HOME \"$HOME/.lux\"\nset -gx LUX_MODE 'fast'\nset -gx PATH $PATH '/opt/lux/bin'\n# skipped: alias l=lux\n# skipped: if true; then\n"
+        );
+    }
+}
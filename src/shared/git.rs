@@ -0,0 +1,222 @@
+/*!
+Support for Git operations on the package repository; the credentials used to authenticate with
+the remote, the update of submodules, moving files, and the commits themselves.
+*/
+
+use crate::error::Result;
+pub use crate::shared::config::GIT_TOKEN_VAR;
+use crate::shared::GitConfig;
+use crate::APP_NAME;
+use git2::{
+    Cred, CredentialType, FetchOptions, IndexAddOption, Oid, RemoteCallbacks, Repository,
+    Signature, SubmoduleUpdateOptions,
+};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// ------------------------------------------------------------------------------------------------
+// Public Types
+// ------------------------------------------------------------------------------------------------
+
+///
+/// The user name used with a token when the remote URL does not include one.
+///
+pub const GIT_TOKEN_USER: &str = "x-access-token";
+
+// ------------------------------------------------------------------------------------------------
+// Public Functions
+// ------------------------------------------------------------------------------------------------
+
+///
+/// Return the callbacks used for remote operations, authenticating with the remote as described
+/// by `config`. Each kind of credential is tried at most once, so that a remote rejecting all
+/// credentials results in an error rather than a loop.
+///
+pub fn remote_callbacks<'a>(config: &GitConfig) -> RemoteCallbacks<'a> {
+    let config = config.clone();
+    let mut callbacks = RemoteCallbacks::new();
+    let mut tried_agent = false;
+    let mut tried_key = false;
+    let mut tried_helper = false;
+    let mut tried_token = false;
+    let _ = callbacks.credentials(move |url, username_from_url, allowed_types| {
+        debug!(
+            "remote_callbacks: credentials for {:?}, user {:?}, allowed {:?}",
+            url, username_from_url, allowed_types
+        );
+        let username = username_from_url.unwrap_or("git");
+        if allowed_types.contains(CredentialType::USERNAME) {
+            return Cred::username(username);
+        }
+        if allowed_types.contains(CredentialType::SSH_KEY) {
+            if config.ssh_agent() && !tried_agent {
+                tried_agent = true;
+                return Cred::ssh_key_from_agent(username);
+            }
+            if let (Some(ssh_key), false) = (config.ssh_key(), tried_key) {
+                tried_key = true;
+                let passphrase = config
+                    .ssh_key_passphrase_var()
+                    .as_ref()
+                    .and_then(|var| std::env::var(var).ok());
+                return Cred::ssh_key(username, None, &expand_home(ssh_key), passphrase.as_deref());
+            }
+        }
+        if allowed_types.contains(CredentialType::USER_PASS_PLAINTEXT) {
+            if config.credential_helper() && !tried_helper {
+                tried_helper = true;
+                if let Ok(git_config) = git2::Config::open_default() {
+                    if let Ok(credentials) =
+                        Cred::credential_helper(&git_config, url, username_from_url)
+                    {
+                        return Ok(credentials);
+                    }
+                }
+            }
+            if !tried_token {
+                tried_token = true;
+                if let Ok(token) = std::env::var(config.token_var()) {
+                    return Cred::userpass_plaintext(
+                        username_from_url.unwrap_or(GIT_TOKEN_USER),
+                        &token,
+                    );
+                }
+            }
+        }
+        Err(git2::Error::from_str(&format!(
+            "no credentials available for remote '{}'",
+            url
+        )))
+    });
+    callbacks
+}
+
+///
+/// Initialize, and update, all the submodules of the repository, recursively; returning the
+/// number of submodules whose checked out commit changed. Submodules are used for groups shared
+/// between repositories, and without an update they appear as empty directories.
+///
+pub fn update_submodules(repository: &Repository, config: &GitConfig) -> Result<usize> {
+    let mut updated = 0;
+    for mut submodule in repository.submodules()? {
+        debug!(
+            "update_submodules: updating {:?} from {:?}",
+            submodule.path(),
+            submodule.url()
+        );
+        let mut fetch_options = FetchOptions::new();
+        let _ = fetch_options.remote_callbacks(remote_callbacks(config));
+        let mut update_options = SubmoduleUpdateOptions::new();
+        let _ = update_options.fetch(fetch_options);
+        let before = submodule.workdir_id();
+        submodule.update(true, Some(&mut update_options))?;
+        submodule.reload(true)?;
+        if submodule.workdir_id() != before {
+            updated += 1;
+        }
+        updated += update_submodules(&submodule.open()?, config)?;
+    }
+    Ok(updated)
+}
+
+///
+/// Return the signature used for commits made to the repository, this is the user configured in
+/// Git, or a default if no user has been configured.
+///
+pub fn commit_signature(repository: &Repository) -> Result<Signature<'static>> {
+    match repository.signature() {
+        Ok(signature) => Ok(signature),
+        Err(e) => {
+            warn!(
+                "commit_signature: no Git user configured ({}), using default",
+                e
+            );
+            Ok(Signature::now(
+                APP_NAME,
+                &format!("{}@localhost", APP_NAME),
+            )?)
+        }
+    }
+}
+
+///
+/// Stage all changes in the repository's working directory, including new and deleted files,
+/// and commit them with `message`; returning the new commit's identifier.
+///
+pub fn commit_all(repository: &Repository, message: &str) -> Result<Oid> {
+    debug!("commit_all: committing with message {:?}", message);
+    let mut index = repository.index()?;
+    index.add_all(["*"].iter(), IndexAddOption::DEFAULT, None)?;
+    index.update_all(["*"].iter(), None)?;
+    index.write()?;
+    let tree = repository.find_tree(index.write_tree()?)?;
+    let signature = commit_signature(repository)?;
+    let parent = match repository.head() {
+        Ok(head) => Some(head.peel_to_commit()?),
+        Err(_) => None,
+    };
+    let parents = parent.iter().collect::<Vec<_>>();
+    Ok(repository.commit(
+        Some("HEAD"),
+        &signature,
+        &signature,
+        message,
+        &tree,
+        &parents,
+    )?)
+}
+
+///
+/// Move the file, or directory, `from` to `to` in the working directory of `repository`; if the
+/// file, or files in the directory, are tracked the index is updated with the move, as `git mv`
+/// would, so that Git records a rename and the history is preserved.
+///
+pub fn move_path(repository: &Repository, from: &Path, to: &Path) -> Result<()> {
+    debug!("move_path: moving {:?} to {:?}", from, to);
+    let work_dir = repository.workdir().map(fs::canonicalize).transpose()?;
+    if let Some(parent) = to.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::rename(from, to)?;
+    if let Some(work_dir) = work_dir {
+        let from = relative_to(&work_dir, from)?;
+        let to = relative_to(&work_dir, to)?;
+        let mut index = repository.index()?;
+        let tracked: Vec<PathBuf> = index
+            .iter()
+            .map(|entry| PathBuf::from(String::from_utf8_lossy(&entry.path).to_string()))
+            .filter(|path| path.starts_with(&from))
+            .collect();
+        for path in tracked {
+            index.remove_path(&path)?;
+            let relative = path.strip_prefix(&from).unwrap();
+            if relative.as_os_str().is_empty() {
+                index.add_path(&to)?;
+            } else {
+                index.add_path(&to.join(relative))?;
+            }
+        }
+        index.write()?;
+    }
+    Ok(())
+}
+
+// ------------------------------------------------------------------------------------------------
+// Private Functions
+// ------------------------------------------------------------------------------------------------
+
+fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix("~/"), dirs_next::home_dir()) {
+        (Some(relative), Some(home)) => home.join(relative),
+        _ => PathBuf::from(path),
+    }
+}
+
+fn relative_to(work_dir: &Path, path: &Path) -> Result<PathBuf> {
+    // The file may no longer exist, so only the parent directory can be canonicalized.
+    let parent = fs::canonicalize(path.parent().unwrap())?;
+    Ok(parent
+        .strip_prefix(work_dir)
+        .map(|parent| parent.join(path.file_name().unwrap()))
+        .unwrap_or_else(|_| path.to_path_buf()))
+}
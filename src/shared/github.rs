@@ -0,0 +1,524 @@
+/*!
+A built-in installer for packages distributed as GitHub release assets. The release, either the
+latest or a pinned tag, is resolved using the GitHub API, an asset matching the current platform
+and architecture is selected, and installed as for the [`download`](../download/index.html)
+installer. The resolved release tag is recorded in the repository's lock file.
+
+This module is only included if the feature `github-releases` is enabled.
+*/
+
+use crate::error::{Error, Result};
+use crate::shared::builders::{Builder, ShellCommandBuilder};
+use crate::shared::command::CommandOutput;
+use crate::shared::dirs::temp_dir_for;
+use crate::shared::download::{
+    curl_config_value, download_bin_path, download_file, install_from_url, is_archive,
+};
+use crate::shared::env::{var_string_replace, vars_scope};
+use crate::shared::lock::{LockFile, LockedPackage};
+use crate::shared::{FileSystemResource, InstallActionKind, Name, Package, Platform};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+// ------------------------------------------------------------------------------------------------
+// Public Types
+// ------------------------------------------------------------------------------------------------
+
+///
+/// Describes a GitHub repository whose releases provide a package. The `asset` value is a regular
+/// expression, which may include variables, used to select the asset to download; if not
+/// specified an asset is selected by looking for common operating system and architecture names.
+///
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct PackageGitHubRelease {
+    repository: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    tag: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    asset: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    binary: Option<String>,
+}
+
+///
+/// A release, as returned by the GitHub API, only the values used by this installer are included.
+///
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+pub struct Release {
+    tag_name: String,
+    #[serde(default)]
+    assets: Vec<ReleaseAsset>,
+}
+
+///
+/// An asset attached to a release, as returned by the GitHub API.
+///
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+pub struct ReleaseAsset {
+    name: String,
+    browser_download_url: String,
+}
+
+///
+/// The name used for the built-in GitHub releases installer, in reports, the install log, and
+/// the lock file.
+///
+pub const GITHUB_INSTALLER: &str = "github";
+
+///
+/// The environment variable checked for a GitHub API token, used to avoid rate limiting.
+///
+pub const GITHUB_TOKEN_VAR: &str = "GITHUB_TOKEN";
+
+// ------------------------------------------------------------------------------------------------
+// Implementations
+// ------------------------------------------------------------------------------------------------
+
+impl PackageGitHubRelease {
+    /// Construct a new GitHub release source, `repository` is of the form `owner/repo`.
+    pub fn new(repository: &str) -> Self {
+        Self {
+            repository: repository.to_string(),
+            tag: None,
+            asset: None,
+            binary: None,
+        }
+    }
+
+    /// Pin the release to the given tag, rather than the latest release.
+    pub fn with_tag(self, tag: &str) -> Self {
+        Self {
+            tag: Some(tag.to_string()),
+            ..self
+        }
+    }
+
+    /// Set the regular expression used to select the release asset.
+    pub fn with_asset(self, asset: &str) -> Self {
+        Self {
+            asset: Some(asset.to_string()),
+            ..self
+        }
+    }
+
+    /// Set the path of the binary, within the asset if it is an archive, to install.
+    pub fn with_binary(self, binary: &str) -> Self {
+        Self {
+            binary: Some(binary.to_string()),
+            ..self
+        }
+    }
+
+    /// Return the repository, of the form `owner/repo`.
+    pub fn repository(&self) -> &String {
+        &self.repository
+    }
+
+    /// Return the pinned release tag, `None` implies the latest release.
+    pub fn tag(&self) -> &Option<String> {
+        &self.tag
+    }
+
+    /// Return the regular expression used to select the release asset, if specified.
+    pub fn asset(&self) -> &Option<String> {
+        &self.asset
+    }
+
+    /// Return the (un-substituted) path of the binary to install, if specified.
+    pub fn binary(&self) -> &Option<String> {
+        &self.binary
+    }
+
+    /// Resolve the release, either the pinned tag, or the latest, using the GitHub API.
+    pub fn resolve(&self, tag: &Option<String>) -> Result<Release> {
+        let url = match tag {
+            None => format!(
+                "https://api.github.com/repos/{}/releases/latest",
+                self.repository
+            ),
+            Some(tag) => format!(
+                "https://api.github.com/repos/{}/releases/tags/{}",
+                self.repository, tag
+            ),
+        };
+        debug!("PackageGitHubRelease::resolve: fetching {}", url);
+        let mut builder = ShellCommandBuilder::program("curl");
+        let _ = builder
+            .args(&[
+                "--fail",
+                "--silent",
+                "--show-error",
+                "--location",
+                "--header",
+                "Accept: application/vnd.github.v3+json",
+            ])
+            .apply_environment_policy(false);
+        if let Ok(token) = std::env::var(GITHUB_TOKEN_VAR) {
+            // The token is passed on stdin so that it is not visible in the process list, or
+            // logged.
+            let _ = builder.args(&["--config", "-"]).input(&format!(
+                "header = {}\n",
+                curl_config_value(&format!("Authorization: token {}", token))
+            ));
+        }
+        match builder.arg(&url).build().execute_output() {
+            Ok(output) => Ok(serde_json::from_str(&output)?),
+            Err(e) => {
+                error!("GitHub API request failed: {}", e);
+                Err(Error::ReleaseNotFound(
+                    self.repository.clone(),
+                    tag.clone().unwrap_or_else(|| "latest".to_string()),
+                ))
+            }
+        }
+    }
+
+    /// Perform the installer action for the package using this release source. The locked
+    /// release tag is used for `install`, if present, and `update` always resolves the release
+    /// again; in both cases the resolved tag is recorded in the lock file.
+    pub fn execute(
+        &self,
+        action: &InstallActionKind,
+        group: &Name,
+        package_set: &Name,
+        package: &Package,
+        variable_replacements: &HashMap<String, String>,
+    ) -> Result<Option<CommandOutput>> {
+        let binary_path = PathBuf::from(match &self.binary {
+            Some(binary) => var_string_replace(binary, variable_replacements),
+            None => package.name().to_string(),
+        });
+        let installed_path = match binary_path.file_name() {
+            Some(file_name) => download_bin_path(vars_scope(variable_replacements)).join(file_name),
+            None => {
+                return Err(Error::InvalidConfigValue(
+                    "binary".to_string(),
+                    binary_path.to_string_lossy().into_owned(),
+                ))
+            }
+        };
+        let mut lock_file = LockFile::open(vars_scope(variable_replacements))?;
+        match action {
+            InstallActionKind::Install | InstallActionKind::Update => {
+                let tag = match (action, &self.tag) {
+                    (_, Some(tag)) => Some(tag.clone()),
+                    (InstallActionKind::Install, None) => lock_file
+                        .package(group, package_set, package.name())
+                        .map(|locked| locked.version().clone()),
+                    _ => None,
+                };
+                let release = self.resolve(&tag)?;
+                reportln!(
+                    "* performing {} on {} package {} (release {})",
+                    action,
+                    GITHUB_INSTALLER,
+                    package.name(),
+                    release.tag_name()
+                );
+                let asset = self.select_asset(&release, variable_replacements)?;
+                let sha256 = release.checksum(asset)?;
+                let output = install_from_url(
+                    asset.browser_download_url(),
+                    sha256.as_deref(),
+                    is_archive(asset.name()),
+                    &binary_path,
+                    &installed_path,
+                )?;
+                lock_file.lock_package(
+                    group,
+                    package_set,
+                    package.name(),
+                    LockedPackage::new(Name::from_str(GITHUB_INSTALLER)?, release.tag_name()),
+                );
+                lock_file.save()?;
+                Ok(Some(output))
+            }
+            InstallActionKind::Uninstall => {
+                reportln!(
+                    "* performing {} on {} package {}",
+                    action,
+                    GITHUB_INSTALLER,
+                    package.name()
+                );
+                if installed_path.is_file() {
+                    std::fs::remove_file(&installed_path)?;
+                }
+                lock_file.unlock_package(group, package_set, package.name());
+                lock_file.save()?;
+                Ok(None)
+            }
+            InstallActionKind::LinkFiles => Ok(None),
+        }
+    }
+
+    /// Select the asset to install from the release, either using the `asset` regular
+    /// expression, or the default platform matching.
+    pub fn select_asset<'a>(
+        &self,
+        release: &'a Release,
+        variable_replacements: &HashMap<String, String>,
+    ) -> Result<&'a ReleaseAsset> {
+        let selected = match &self.asset {
+            Some(pattern) => {
+                let pattern = var_string_replace(pattern, variable_replacements);
+                let regex = Regex::new(&pattern)
+                    .map_err(|_| Error::InvalidConfigValue("asset".to_string(), pattern.clone()))?;
+                release.assets().find(|asset| regex.is_match(asset.name()))
+            }
+            None => release
+                .assets()
+                .find(|asset| is_platform_asset(asset.name(), std::env::consts::ARCH)),
+        };
+        selected.ok_or_else(|| {
+            Error::NoMatchingAsset(self.repository.clone(), release.tag_name().clone())
+        })
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+
+impl Release {
+    /// Return the release's tag.
+    pub fn tag_name(&self) -> &String {
+        &self.tag_name
+    }
+
+    /// Return an iterator over the release's assets.
+    pub fn assets(&self) -> impl Iterator<Item = &ReleaseAsset> {
+        self.assets.iter()
+    }
+
+    /// Return the asset holding checksums for `asset`, if the release has one. An asset named
+    /// for `asset` with a `.sha256` extension is preferred, otherwise a combined checksum file
+    /// such as `SHA256SUMS` or `checksums.txt`.
+    pub fn checksum_asset(&self, asset: &ReleaseAsset) -> Option<&ReleaseAsset> {
+        let single_name = format!("{}.sha256", asset.name()).to_lowercase();
+        self.assets()
+            .find(|candidate| candidate.name().to_lowercase() == single_name)
+            .or_else(|| {
+                self.assets().find(|candidate| {
+                    let name = candidate.name().to_lowercase();
+                    name.contains("sha256sum") || name.contains("checksums")
+                })
+            })
+    }
+
+    /// Download the checksum asset for `asset`, if the release has one, and return the SHA-256
+    /// checksum it records for `asset`. It is an error for the checksum asset to exist but not
+    /// include `asset`.
+    pub fn checksum(&self, asset: &ReleaseAsset) -> Result<Option<String>> {
+        match self.checksum_asset(asset) {
+            None => {
+                warn!(
+                    "Release::checksum: no checksum asset for {}, not verifying download",
+                    asset.name()
+                );
+                Ok(None)
+            }
+            Some(checksum_asset) => {
+                let work_dir = temp_dir_for(crate::APP_NAME, "checksum")?;
+                let content =
+                    download_file(checksum_asset.browser_download_url(), None, work_dir.path())
+                        .and_then(|path| Ok(std::fs::read_to_string(path)?));
+                let _ = work_dir.close();
+                match find_checksum(&content?, asset.name()) {
+                    Some(checksum) => Ok(Some(checksum)),
+                    None => Err(Error::MissingChecksum(
+                        checksum_asset.name().clone(),
+                        asset.name().clone(),
+                    )),
+                }
+            }
+        }
+    }
+}
+
+impl ReleaseAsset {
+    /// Return the asset's file name.
+    pub fn name(&self) -> &String {
+        &self.name
+    }
+
+    /// Return the URL used to download the asset.
+    pub fn browser_download_url(&self) -> &String {
+        &self.browser_download_url
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+// Private Functions
+// ------------------------------------------------------------------------------------------------
+
+///
+/// Find the checksum for `asset_name` in the content of a checksum file. Lines are of the form
+/// `<checksum> [*]<file name>`, as written by `sha256sum`; a line with only a checksum is used
+/// for a file that holds the checksum of a single asset.
+///
+fn find_checksum(content: &str, asset_name: &str) -> Option<String> {
+    content.lines().find_map(|line| {
+        let mut fields = line.split_whitespace();
+        let checksum = fields.next()?;
+        if checksum.len() != 64 || !checksum.chars().all(|c| c.is_ascii_hexdigit()) {
+            return None;
+        }
+        match fields.next() {
+            None => Some(checksum.to_lowercase()),
+            Some(file_name) => {
+                let file_name = file_name.trim_start_matches('*');
+                let file_name = file_name.strip_prefix("./").unwrap_or(file_name);
+                if file_name == asset_name {
+                    Some(checksum.to_lowercase())
+                } else {
+                    None
+                }
+            }
+        }
+    })
+}
+
+fn is_platform_asset(name: &str, arch: &str) -> bool {
+    const IGNORED_EXTENSIONS: &[&str] =
+        &[".sha256", ".sha512", ".asc", ".sig", ".txt", ".deb", ".rpm"];
+    let name = name.to_lowercase();
+    let os_names: &[&str] = match Platform::CURRENT {
+        Platform::Macos => &["darwin", "macos", "apple", "osx"],
+        Platform::Linux => &["linux"],
+    };
+    let arch_names: &[&str] = match arch {
+        "x86_64" => &["x86_64", "amd64", "x64"],
+        "aarch64" => &["aarch64", "arm64"],
+        other => &[other][..],
+    };
+    !IGNORED_EXTENSIONS.iter().any(|ext| name.ends_with(ext))
+        && os_names.iter().any(|os| name.contains(os))
+        && arch_names.iter().any(|arch| name.contains(arch))
+}
+
+// ------------------------------------------------------------------------------------------------
+// Unit Tests
+// ------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shared::PackageKind;
+    use pretty_assertions::assert_eq;
+
+    const RELEASE: &str = r##"{
+        "tag_name": "v1.2.3",
+        "assets": [
+            {"name": "tool-v1.2.3-x86_64-apple-darwin.tar.gz", "browser_download_url": "https://x.org/1"},
+            {"name": "tool-v1.2.3-x86_64-unknown-linux-musl.tar.gz", "browser_download_url": "https://x.org/2"},
+            {"name": "tool-v1.2.3-x86_64-unknown-linux-musl.tar.gz.sha256", "browser_download_url": "https://x.org/3"},
+            {"name": "tool-v1.2.3-aarch64-unknown-linux-gnu.tar.gz", "browser_download_url": "https://x.org/4"}
+        ]
+    }"##;
+
+    #[test]
+    fn test_parse_release() {
+        let release: Release = serde_json::from_str(RELEASE).unwrap();
+        assert_eq!(release.tag_name(), "v1.2.3");
+        assert_eq!(release.assets().count(), 4);
+    }
+
+    #[test]
+    fn test_select_asset_by_pattern() {
+        let release: Release = serde_json::from_str(RELEASE).unwrap();
+        let source = PackageGitHubRelease::new("me/tool").with_asset("aarch64-.*-linux");
+        let asset = source.select_asset(&release, &HashMap::default()).unwrap();
+        assert_eq!(asset.browser_download_url(), "https://x.org/4");
+
+        let source = PackageGitHubRelease::new("me/tool").with_asset("windows");
+        assert!(source.select_asset(&release, &HashMap::default()).is_err());
+    }
+
+    #[test]
+    fn test_invalid_binary() {
+        let package = Package::new(
+            Name::from_str("tool").unwrap(),
+            None,
+            PackageKind::default(),
+        );
+        let group = Name::from_str("tools").unwrap();
+        for binary in &["", ".."] {
+            let source = PackageGitHubRelease::new("me/tool").with_binary(binary);
+            let result = source.execute(
+                &InstallActionKind::Uninstall,
+                &group,
+                &group,
+                &package,
+                &HashMap::default(),
+            );
+            assert!(matches!(result, Err(Error::InvalidConfigValue(_, _))));
+        }
+    }
+
+    #[test]
+    fn test_checksum_asset() {
+        let release: Release = serde_json::from_str(RELEASE).unwrap();
+        let assets: Vec<&ReleaseAsset> = release.assets().collect();
+        let checksum = release.checksum_asset(assets[1]).unwrap();
+        assert_eq!(checksum.browser_download_url(), "https://x.org/3");
+        assert!(release.checksum_asset(assets[0]).is_none());
+
+        let release: Release = serde_json::from_str(
+            r##"{
+                "tag_name": "v1.2.3",
+                "assets": [
+                    {"name": "tool-v1.2.3-x86_64-apple-darwin.tar.gz", "browser_download_url": "https://x.org/1"},
+                    {"name": "SHA256SUMS", "browser_download_url": "https://x.org/2"}
+                ]
+            }"##,
+        )
+        .unwrap();
+        let assets: Vec<&ReleaseAsset> = release.assets().collect();
+        let checksum = release.checksum_asset(assets[0]).unwrap();
+        assert_eq!(checksum.browser_download_url(), "https://x.org/2");
+    }
+
+    #[test]
+    fn test_find_checksum() {
+        const SUM: &str = "9F86D081884C7D659A2FEAA0C55AD015A3BF4F1B2B0B822CD15D6C15B0F00A08";
+        assert_eq!(
+            find_checksum(&format!("{}\n", SUM), "tool.tar.gz"),
+            Some(SUM.to_lowercase())
+        );
+        let sums = format!(
+            "{}  tool-linux.tar.gz\n{} *./tool-darwin.tar.gz\n",
+            "0".repeat(64),
+            SUM
+        );
+        assert_eq!(
+            find_checksum(&sums, "tool-darwin.tar.gz"),
+            Some(SUM.to_lowercase())
+        );
+        assert_eq!(find_checksum(&sums, "tool-windows.zip"), None);
+        assert_eq!(
+            find_checksum("not a checksum tool.tar.gz", "tool.tar.gz"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_is_platform_asset() {
+        if Platform::CURRENT == Platform::Linux {
+            assert!(is_platform_asset(
+                "tool-v1.2.3-x86_64-unknown-linux-musl.tar.gz",
+                "x86_64"
+            ));
+            assert!(!is_platform_asset(
+                "tool-v1.2.3-x86_64-unknown-linux-musl.tar.gz.sha256",
+                "x86_64"
+            ));
+            assert!(!is_platform_asset(
+                "tool-v1.2.3-x86_64-apple-darwin.tar.gz",
+                "x86_64"
+            ));
+            assert!(is_platform_asset("tool-v1.2.3-arm64-linux.zip", "aarch64"));
+        }
+    }
+}
@@ -1,135 +1,641 @@
-use crate::error::Result;
-use crate::shared::{FileSystemResource, Name};
+/*!
+The log where installer actions are recorded, primarily the successful installation of packages
+within a package set, the commands executed, and the progress of each run.
+
+The log is accessed through the `InstallLog` trait, and there are two implementations, selected
+by the `install-log` setting in the tool configuration file:
+
+* `sqlite` - the default, a SQLite3 file, see [`PackageLog`](../sqlite_log/struct.PackageLog.html);
+  this requires the feature `sqlite-log`, which is enabled by default.
+* `json-lines` - a text file with one JSON object per line, see
+  [`JsonLinesLog`](../jsonl_log/struct.JsonLinesLog.html); this requires the feature `jsonl-log`,
+  and is intended for machines where the SQLite dependency is not available.
+
+Every entry records the machine it was logged on, see `local_machine_id`, so that the logs of
+several machines may be merged into one for reporting, see `InstallLog::merge_from`. Entries
+logged before machines were recorded have no machine, and are taken to be from this machine.
+*/
+
+use crate::error::{Error, Result};
+use crate::shared::command::{CommandOutput, CommandRecord};
+use crate::shared::{dirs, Config, FileSystemResource, InstallActionKind, Name, Scope};
 use crate::APP_NAME;
-use rusqlite::{params, Connection, Row};
-use std::convert::TryFrom;
-use std::path::PathBuf;
-use std::str::FromStr;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fmt::{Debug, Display, Formatter};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 // ------------------------------------------------------------------------------------------------
 // Public Types
 // ------------------------------------------------------------------------------------------------
 
 ///
-/// This is the log where installer actions are recorded, primarily the successful installation
-/// of packages within a package set.
+/// The operations on the install log used by actions; recording what the installers did, and
+/// querying that history.
+///
+pub trait InstallLog: Debug {
+    /// Return the identifier of this machine, recorded in each entry logged.
+    fn machine(&self) -> &str;
+
+    /// Record `machine` as the identifier of this machine in each entry logged from now on.
+    fn set_machine(&mut self, machine: &str);
+
+    /// Begin a transaction; all entries logged until `commit` is called are written together.
+    fn begin(&mut self) -> Result<()>;
+
+    /// Commit all entries logged since the call to `begin`.
+    fn commit(&mut self) -> Result<()>;
+
+    /// Begin a new run of `action`; progress is recorded against this run until `end_run` is
+    /// called.
+    fn begin_run(&mut self, action: &InstallActionKind) -> Result<()>;
+
+    /// Record the end of the current run, if any, and whether it succeeded.
+    fn end_run(&mut self, succeeded: bool) -> Result<()>;
+
+    /// Record that the package, or if `package_name` is `None` the whole package set, was
+    /// completed in the current run, if any.
+    fn log_run_progress(
+        &mut self,
+        package_set_group_name: &Name,
+        package_set_name: &Name,
+        package_name: Option<&Name>,
+    ) -> Result<()>;
+
+    /// Remove the progress recorded for the package set, and its packages, in the current run; this
+    /// is used when the changes made to a package set have been undone.
+    fn clear_run_progress(
+        &mut self,
+        package_set_group_name: &Name,
+        package_set_name: &Name,
+    ) -> Result<()>;
+
+    /// If the most recent previous run of `action` failed, return the package sets and packages
+    /// it completed; these are also copied into the current run, so that it in turn may be resumed.
+    /// If the previous run succeeded, or there is none, this returns `None`.
+    fn resume_run(&mut self, action: &InstallActionKind) -> Result<Option<RunCheckpoint>>;
+
+    /// Add this installed package to the log file. Currently this only logs successful
+    /// execution of the associated package installer.
+    fn log_installed_package(&mut self, package: &InstalledPackage) -> Result<()>;
+
+    /// Record that the action was successfully applied to the package set, along with the
+    /// package set's content hash at the time.
+    fn log_applied_package_set(
+        &mut self,
+        package_set_group_name: &Name,
+        package_set_name: &Name,
+        action: &InstallActionKind,
+        content_hash: &str,
+    ) -> Result<()>;
+
+    /// Record the execution of a command, see `take_command_records`.
+    fn log_command(&mut self, record: &CommandRecord) -> Result<()>;
+
+    /// Record that the action on the package set was interrupted, and so may be incomplete.
+    fn log_interrupted_package_set(
+        &mut self,
+        package_set_group_name: &Name,
+        package_set_name: &Name,
+        action: &InstallActionKind,
+    ) -> Result<()>;
+
+    /// Record that the prerequisite of the package set was added by the named installer.
+    fn log_added_prerequisite(
+        &mut self,
+        package_set_group_name: &Name,
+        package_set_name: &Name,
+        installer_name: &Name,
+        prerequisite: &str,
+    ) -> Result<()>;
+
+    /// Record that the package set was archived, removed from the repository to its archive,
+    /// along with the package set's content hash at the time.
+    fn log_archived_package_set(
+        &mut self,
+        package_set_group_name: &Name,
+        package_set_name: &Name,
+        content_hash: &str,
+    ) -> Result<()>;
+
+    /// Return the content hash of the package set when it was last installed, or updated, on
+    /// this machine. This is `None` if it has not been, or if the most recent action applied to
+    /// the package set was neither an install nor an update; for example an uninstall.
+    fn last_applied_hash(
+        &mut self,
+        package_set_group_name: &Name,
+        package_set_name: &Name,
+    ) -> Result<Option<String>>;
+
+    /// Return the group and package set names of all package sets that appear in the log for this
+    /// machine, either with installed packages or as applied package sets.
+    fn logged_package_sets(&mut self) -> Result<Vec<(Name, Name)>>;
+
+    /// Return every action applied to a package set, most recent first.
+    fn applied_package_sets(&mut self) -> Result<Vec<AppliedPackageSet>>;
+
+    /// Return up to `limit` number of rows from the installation history, most recent first.
+    fn installed_package_history(&mut self, limit: u32) -> Result<Vec<InstalledPackage>> {
+        self.installed_packages(&PackageLogQuery::default().with_limit(limit))
+    }
+
+    /// Return the rows from the installation history that match `query`.
+    fn installed_packages(&mut self, query: &PackageLogQuery) -> Result<Vec<InstalledPackage>>;
+
+    /// Return up to `limit` number of executed commands, most recent first.
+    fn command_history(&mut self, limit: u32) -> Result<Vec<CommandRecord>>;
+
+    /// Return up to `limit` number of packages, with their aggregated execution times, ordered
+    /// by the total time spent executing them. Only log entries that recorded a duration are
+    /// included.
+    fn package_durations(&mut self, limit: u32) -> Result<Vec<PackageDurations>>;
+
+    /// Return all the installed package, applied package set, and command entries in the log;
+    /// the progress of runs is specific to a machine and is not included.
+    fn entries(&mut self) -> Result<Vec<LogEntry>>;
+
+    /// Add the entries to the log as they are, keeping their dates and machines.
+    fn import_entries(&mut self, entries: &[LogEntry]) -> Result<()>;
+
+    /// Merge the entries from `other`, the log of another machine, into this log; entries from
+    /// `other` that do not record a machine are recorded as from `machine`, and entries already in
+    /// this log are skipped. Returns the number of entries merged.
+    fn merge_from(&mut self, other: &mut dyn InstallLog, machine: &str) -> Result<usize> {
+        let existing: HashSet<String> = self.entries()?.iter().map(LogEntry::key).collect();
+        let entries: Vec<LogEntry> = other
+            .entries()?
+            .into_iter()
+            .map(|entry| entry.with_default_machine(machine))
+            .filter(|entry| !existing.contains(&entry.key()))
+            .collect();
+        if !entries.is_empty() {
+            self.begin()?;
+            self.import_entries(&entries)?;
+            self.commit()?;
+        }
+        Ok(entries.len())
+    }
+}
+
 ///
-/// This file is a SQLite3 file, each log entry is a row in the table `installed`.
+/// A single entry in an install log, as exported from one log and imported into another when the
+/// logs of several machines are merged.
 ///
-#[derive(Debug)]
-pub struct PackageLog(Connection);
+#[derive(Clone, Debug)]
+pub enum LogEntry {
+    /// A package acted upon by an installer.
+    Installed(InstalledPackage),
+    /// An action applied to a package set.
+    Applied(AppliedPackageSet),
+    /// A command executed, and the machine it was executed on, if recorded.
+    Command(CommandRecord, Option<String>),
+}
 
 ///
-/// This represents a single log entry in `PackageLog`.
+/// The implementations of the install log, selected by the `install-log` setting in the tool
+/// configuration file.
 ///
-#[derive(Debug)]
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum InstallLogFormat {
+    /// A SQLite3 file, this requires the feature `sqlite-log`.
+    Sqlite,
+    /// A text file with one JSON object per line, this requires the feature `jsonl-log`.
+    JsonLines,
+}
+
+///
+/// The package sets, and packages, completed by a previous run that failed; these are skipped
+/// when the run is resumed.
+///
+#[derive(Clone, Debug, Default)]
+pub struct RunCheckpoint {
+    completed_package_sets: HashSet<(String, String)>,
+    completed_packages: HashSet<(String, String, String)>,
+}
+
+///
+/// This represents a single log entry in an `InstallLog`.
+///
+#[derive(Clone, Debug)]
 pub struct InstalledPackage {
-    date_time: Option<time::OffsetDateTime>,
-    package_set_group_name: Name,
-    package_set_name: Name,
-    package_name: Name,
-    installer_name: Name,
+    pub(crate) date_time: Option<time::OffsetDateTime>,
+    pub(crate) package_set_group_name: Name,
+    pub(crate) package_set_name: Name,
+    pub(crate) package_name: Name,
+    pub(crate) installer_name: Name,
+    pub(crate) started: Option<time::OffsetDateTime>,
+    pub(crate) duration: Option<Duration>,
+    pub(crate) output_bytes: Option<usize>,
+    pub(crate) output_lines: Option<usize>,
+    pub(crate) machine: Option<String>,
 }
 
 ///
-/// The file name of the installer log.
+/// This represents the aggregated execution times for a single package across all log entries
+/// in an `InstallLog`.
+///
+#[derive(Debug)]
+pub struct PackageDurations {
+    pub(crate) package_set_group_name: Name,
+    pub(crate) package_set_name: Name,
+    pub(crate) package_name: Name,
+    pub(crate) installer_name: Name,
+    pub(crate) count: u32,
+    pub(crate) total: Duration,
+    pub(crate) longest: Duration,
+}
+
+///
+/// This represents an action applied to a whole package set, an entry in the `applied` table in
+/// an `InstallLog`.
+///
+#[derive(Clone, Debug)]
+pub struct AppliedPackageSet {
+    pub(crate) date_time: Option<time::OffsetDateTime>,
+    pub(crate) package_set_group_name: Name,
+    pub(crate) package_set_name: Name,
+    pub(crate) action: String,
+    pub(crate) content_hash: String,
+    pub(crate) machine: Option<String>,
+}
+
+///
+/// A query over the rows of the `installed` table in `PackageLog`; by default all rows are
+/// returned, most recent first. Each filter that is set must match for a row to be returned.
+///
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PackageLogQuery {
+    pub(crate) package_set_group: Option<Name>,
+    pub(crate) package_set: Option<Name>,
+    pub(crate) package: Option<Name>,
+    pub(crate) installer: Option<Name>,
+    pub(crate) machine: Option<String>,
+    pub(crate) since: Option<time::OffsetDateTime>,
+    pub(crate) until: Option<time::OffsetDateTime>,
+    pub(crate) order: PackageLogOrder,
+    pub(crate) limit: u32,
+}
+
+///
+/// The order in which the results of a `PackageLogQuery` are returned.
+///
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum PackageLogOrder {
+    /// The most recent log entries first.
+    #[default]
+    NewestFirst,
+    /// The oldest log entries first.
+    OldestFirst,
+    /// By package set group, package set, and package name; then the most recent first.
+    ByPackage,
+}
+
+///
+/// The file name of the SQLite installer log.
 ///
 pub const LOG_FILE: &str = "install-log.sql";
 
+///
+/// The file name of the JSON-lines installer log.
+///
+pub const JSON_LINES_LOG_FILE: &str = "install-log.jsonl";
+
+// ------------------------------------------------------------------------------------------------
+// Public Functions
+// ------------------------------------------------------------------------------------------------
+
+///
+/// Format a duration for display, in the form `1h 2m 3.4s`; leading zero components are omitted.
+///
+pub fn duration_to_string(duration: Duration) -> String {
+    let total_secs = duration.as_secs();
+    let hours = total_secs / 3600;
+    let minutes = (total_secs % 3600) / 60;
+    let seconds = (total_secs % 60) as f64 + f64::from(duration.subsec_millis()) / 1000.0;
+    if hours > 0 {
+        format!("{}h {}m {:.1}s", hours, minutes, seconds)
+    } else if minutes > 0 {
+        format!("{}m {:.1}s", minutes, seconds)
+    } else {
+        format!("{:.1}s", seconds)
+    }
+}
+
+///
+/// Return the identifier recorded for this machine in the install log; either the `machine-id`
+/// setting in the tool configuration file, in `scope`, or, by default, see `default_machine_id`.
+///
+pub fn local_machine_id(scope: Scope) -> String {
+    Config::open(scope)
+        .ok()
+        .and_then(|config| config.machine_id().clone())
+        .unwrap_or_else(default_machine_id)
+}
+
+///
+/// Return the identifier recorded for this machine when no `machine-id` is configured; the
+/// machine's host name, if it can be read.
+///
+pub fn default_machine_id() -> String {
+    host_name().unwrap_or_else(|| UNKNOWN_MACHINE.to_string())
+}
+
+///
+/// Open the install log, in `scope`, in the format selected by the tool configuration file.
+///
+pub fn open_install_log(scope: Scope) -> Result<Box<dyn InstallLog>> {
+    InstallLogFormat::configured(scope).open(scope)
+}
+
 // ------------------------------------------------------------------------------------------------
 // Implementations
 // ------------------------------------------------------------------------------------------------
 
-impl FileSystemResource for PackageLog {
-    fn default_path() -> PathBuf {
-        xdirs::log_dir_for(APP_NAME).unwrap().join(LOG_FILE)
+impl Default for InstallLogFormat {
+    #[cfg(feature = "sqlite-log")]
+    fn default() -> Self {
+        Self::Sqlite
     }
 
-    fn open_from(log_file_path: PathBuf) -> Result<Self> {
-        let connection = if !log_file_path.is_file() {
-            debug!(
-                "PackageLog::open creating new log file: {:?}",
-                log_file_path
-            );
-            std::fs::create_dir_all(log_file_path.parent().unwrap()).unwrap();
-            let db = Connection::open(log_file_path).unwrap();
-            let _ = db.execute(
-                r##"CREATE TABLE installed (
-    date_time DATETIME NOT NULL,
-    package_set_group TEXT NOT NULL,
-    package_set TEXT NOT NULL,
-    package TEXT NOT NULL,
-    installer TEXT NOT NULL
-)"##,
-                params![],
-            )?;
-            db
-        } else {
-            debug!(
-                "PackageLog::open opening existing log file {:?}",
-                log_file_path
-            );
-            Connection::open(log_file_path)?
-        };
-        Ok(PackageLog(connection))
+    #[cfg(not(feature = "sqlite-log"))]
+    fn default() -> Self {
+        Self::JsonLines
     }
 }
 
-impl PackageLog {
-    /// Add this installed package to the log file. Currently this only logs successful
-    /// execution of the associated package installer.
-    pub fn log_installed_package(&mut self, package: &InstalledPackage) -> Result<()> {
-        trace!("Logging package installation success");
-        let date_time = time::OffsetDateTime::now_utc();
-        let _ = self.0.execute(
-            "INSERT INTO installed (date_time, package_set_group, package_set, package, installer) VALUES (?1, ?2, ?3, ?4, ?5)",
-            params![
-                date_time,
-                package.package_set_group_name.to_string(),
-                package.package_set_name.to_string(),
-                package.package_name.to_string(),
-                package.installer_name.to_string()],
-        )?;
-        Ok(())
-    }
-
-    /// Return up to `limit` number of rows from the installation history.
-    pub fn installed_package_history(&mut self, limit: u32) -> Result<Vec<InstalledPackage>> {
-        let mut stmt = self.0.prepare(&format!(
-            "SELECT * FROM installed ORDER BY date_time DESC{}",
-            if limit > 0 {
-                format!(" LIMIT {}", limit)
-            } else {
-                String::new()
+impl Display for InstallLogFormat {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Self::Sqlite => "sqlite",
+                Self::JsonLines => "json-lines",
             }
-        ))?;
-        let result_iter = stmt.query_map(params![], |row| InstalledPackage::try_from(row))?;
-        Ok(result_iter.map(|ip| ip.unwrap()).collect())
+        )
+    }
+}
+
+impl InstallLogFormat {
+    /// Return the format selected by the tool configuration file, in `scope`, or the default
+    /// format if the configuration file does not select one, or cannot be read.
+    pub fn configured(scope: Scope) -> Self {
+        Config::open(scope)
+            .ok()
+            .and_then(|config| *config.install_log())
+            .unwrap_or_default()
+    }
+
+    /// Return the default path of the install log in this format, in `scope`.
+    pub fn default_path(&self, scope: Scope) -> PathBuf {
+        let log_dir = dirs::log_dir_for(scope, APP_NAME).unwrap();
+        match self {
+            Self::Sqlite => log_dir.join(LOG_FILE),
+            Self::JsonLines => log_dir.join(JSON_LINES_LOG_FILE),
+        }
+    }
+
+    /// Return the format of the install log file at `path`, based on its extension; a file with
+    /// the extension `jsonl` is in the JSON-lines format, any other file is a SQLite3 file.
+    pub fn for_path(path: &Path) -> Self {
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("jsonl") => Self::JsonLines,
+            _ => Self::Sqlite,
+        }
+    }
+
+    /// Open the install log in this format, at its default path in `scope`, recording the
+    /// machine identifier configured in `scope`.
+    pub fn open(&self, scope: Scope) -> Result<Box<dyn InstallLog>> {
+        let mut install_log = self.open_from(self.default_path(scope))?;
+        install_log.set_machine(&local_machine_id(scope));
+        Ok(install_log)
+    }
+
+    /// Open the install log in this format, at `path`. This fails if the tool was built without
+    /// the feature this format requires.
+    pub fn open_from(&self, path: PathBuf) -> Result<Box<dyn InstallLog>> {
+        match self {
+            #[cfg(feature = "sqlite-log")]
+            Self::Sqlite => Ok(Box::new(crate::shared::sqlite_log::PackageLog::open_from(
+                path,
+            )?)),
+            #[cfg(feature = "jsonl-log")]
+            Self::JsonLines => Ok(Box::new(crate::shared::jsonl_log::JsonLinesLog::open_from(
+                path,
+            )?)),
+            #[allow(unreachable_patterns)]
+            _ => {
+                let _ = path;
+                Err(Error::UnsupportedInstallLogFormat(self.to_string()))
+            }
+        }
     }
 }
 
 // ------------------------------------------------------------------------------------------------
 
-impl<'stmt> TryFrom<&Row<'stmt>> for InstalledPackage {
-    type Error = rusqlite::Error;
+impl PackageLogQuery {
+    /// Only return entries for packages in the package set group `group`.
+    pub fn with_package_set_group(self, group: Name) -> Self {
+        Self {
+            package_set_group: Some(group),
+            ..self
+        }
+    }
+
+    /// Only return entries for packages in the package set `package_set`, in any group unless
+    /// `with_package_set_group` is also used.
+    pub fn with_package_set(self, package_set: Name) -> Self {
+        Self {
+            package_set: Some(package_set),
+            ..self
+        }
+    }
+
+    /// Only return entries for the package `package`.
+    pub fn with_package(self, package: Name) -> Self {
+        Self {
+            package: Some(package),
+            ..self
+        }
+    }
+
+    /// Only return entries for packages installed by the installer `installer`.
+    pub fn with_installer(self, installer: Name) -> Self {
+        Self {
+            installer: Some(installer),
+            ..self
+        }
+    }
 
-    fn try_from(row: &Row<'stmt>) -> rusqlite::Result<Self, Self::Error> {
-        fn get_name_from_row(row: &Row<'_>, idx: usize) -> rusqlite::Result<Name, rusqlite::Error> {
-            let value_string: String = row.get(idx)?;
-            let name: Name = Name::from_str(&value_string).unwrap();
-            Ok(name)
+    /// Only return entries logged on the machine `machine`, see `local_machine_id`; entries that
+    /// do not record a machine are taken to be from this machine.
+    pub fn with_machine(self, machine: &str) -> Self {
+        Self {
+            machine: Some(machine.to_string()),
+            ..self
+        }
+    }
+
+    /// Only return entries logged at, or after, `since`.
+    pub fn since(self, since: time::OffsetDateTime) -> Self {
+        Self {
+            since: Some(since),
+            ..self
+        }
+    }
+
+    /// Only return entries logged before `until`.
+    pub fn until(self, until: time::OffsetDateTime) -> Self {
+        Self {
+            until: Some(until),
+            ..self
         }
+    }
+
+    /// Return entries in the order `order`.
+    pub fn ordered_by(self, order: PackageLogOrder) -> Self {
+        Self { order, ..self }
+    }
+
+    /// Return at most `limit` entries, a limit of `0` returns all matching entries.
+    pub fn with_limit(self, limit: u32) -> Self {
+        Self { limit, ..self }
+    }
+
+    // Select the entries, from all `packages`, that match this query in order; entries that do not
+    // record a machine are from `local_machine`.
+    #[cfg(feature = "jsonl-log")]
+    pub(crate) fn select(
+        &self,
+        packages: impl Iterator<Item = InstalledPackage>,
+        local_machine: &str,
+    ) -> Vec<InstalledPackage> {
+        let mut selected: Vec<InstalledPackage> = packages
+            .filter(|installed| {
+                self.package_set_group
+                    .as_ref()
+                    .is_none_or(|name| name == installed.package_set_group_name())
+                    && self
+                        .package_set
+                        .as_ref()
+                        .is_none_or(|name| name == installed.package_set_name())
+                    && self
+                        .package
+                        .as_ref()
+                        .is_none_or(|name| name == installed.package_name())
+                    && self
+                        .installer
+                        .as_ref()
+                        .is_none_or(|name| name == installed.installer_name())
+                    && self.machine.as_ref().is_none_or(|machine| {
+                        machine
+                            == installed
+                                .machine()
+                                .map(String::as_str)
+                                .unwrap_or(local_machine)
+                    })
+                    && self
+                        .since
+                        .is_none_or(|since| installed.date_time >= Some(since))
+                    && self
+                        .until
+                        .is_none_or(|until| installed.date_time < Some(until))
+            })
+            .collect();
+        match self.order {
+            PackageLogOrder::NewestFirst => {
+                selected.sort_by_key(|installed| std::cmp::Reverse(installed.date_time))
+            }
+            PackageLogOrder::OldestFirst => selected.sort_by_key(|installed| installed.date_time),
+            PackageLogOrder::ByPackage => selected.sort_by(|a, b| {
+                (
+                    a.package_set_group_name.to_string(),
+                    a.package_set_name.to_string(),
+                    a.package_name.to_string(),
+                )
+                    .cmp(&(
+                        b.package_set_group_name.to_string(),
+                        b.package_set_name.to_string(),
+                        b.package_name.to_string(),
+                    ))
+                    .then_with(|| b.date_time.cmp(&a.date_time))
+            }),
+        }
+        if self.limit > 0 {
+            selected.truncate(self.limit as usize);
+        }
+        selected
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+
+impl RunCheckpoint {
+    // Record the package, or if `package_name` is `None` the whole package set, as completed.
+    #[cfg_attr(
+        not(any(feature = "sqlite-log", feature = "jsonl-log")),
+        allow(dead_code)
+    )]
+    pub(crate) fn add_completed(
+        &mut self,
+        package_set_group_name: String,
+        package_set_name: String,
+        package_name: Option<String>,
+    ) {
+        match package_name {
+            None => {
+                let _ = self
+                    .completed_package_sets
+                    .insert((package_set_group_name, package_set_name));
+            }
+            Some(package_name) => {
+                let _ = self.completed_packages.insert((
+                    package_set_group_name,
+                    package_set_name,
+                    package_name,
+                ));
+            }
+        }
+    }
 
-        Ok(InstalledPackage {
-            date_time: row.get(0)?,
-            package_set_group_name: get_name_from_row(row, 1)?,
-            package_set_name: get_name_from_row(row, 2)?,
-            package_name: get_name_from_row(row, 3)?,
-            installer_name: get_name_from_row(row, 4)?,
-        })
+    /// Return `true` if nothing was completed by the previous run.
+    pub fn is_empty(&self) -> bool {
+        self.completed_package_sets.is_empty() && self.completed_packages.is_empty()
+    }
+
+    /// Return `true` if the package set was completed by the previous run.
+    pub fn is_package_set_complete(
+        &self,
+        package_set_group_name: &Name,
+        package_set_name: &Name,
+    ) -> bool {
+        self.completed_package_sets.contains(&(
+            package_set_group_name.to_string(),
+            package_set_name.to_string(),
+        ))
+    }
+
+    /// Return `true` if the package was completed by the previous run.
+    pub fn is_package_complete(
+        &self,
+        package_set_group_name: &Name,
+        package_set_name: &Name,
+        package_name: &Name,
+    ) -> bool {
+        self.completed_packages.contains(&(
+            package_set_group_name.to_string(),
+            package_set_name.to_string(),
+            package_name.to_string(),
+        ))
     }
 }
 
+// ------------------------------------------------------------------------------------------------
+
 impl InstalledPackage {
     /// Create a new record for the install history log.
     pub fn new(
@@ -144,6 +650,39 @@ impl InstalledPackage {
             package_set_name,
             package_name,
             installer_name,
+            started: None,
+            duration: None,
+            output_bytes: None,
+            output_lines: None,
+            machine: None,
+        }
+    }
+
+    /// Add the execution details of the installer command to this record.
+    pub fn with_command_output(self, output: &CommandOutput) -> Self {
+        Self {
+            started: Some(*output.started()),
+            duration: Some(*output.duration()),
+            output_bytes: Some(output.output_bytes()),
+            output_lines: Some(output.output_lines()),
+            ..self
+        }
+    }
+
+    /// Set the date and time of the installation, this is kept when the record is imported into a
+    /// log, see `InstallLog::import_entries`, otherwise the log records the current time.
+    pub fn with_date_time(self, date_time: time::OffsetDateTime) -> Self {
+        Self {
+            date_time: Some(date_time),
+            ..self
+        }
+    }
+
+    /// Set the identifier of the machine the package was installed on.
+    pub fn with_machine(self, machine: &str) -> Self {
+        Self {
+            machine: Some(machine.to_string()),
+            ..self
         }
     }
 
@@ -176,4 +715,442 @@ impl InstalledPackage {
     pub fn installer_name(&self) -> &Name {
         &self.installer_name
     }
+
+    /// Return the date and time the installer command was started, if it was recorded.
+    pub fn started(&self) -> &Option<time::OffsetDateTime> {
+        &self.started
+    }
+
+    /// Return the time taken by the installer command, if it was recorded.
+    pub fn duration(&self) -> &Option<Duration> {
+        &self.duration
+    }
+
+    /// Return the time taken by the installer command, as a string, or an empty string if it
+    /// was not recorded.
+    pub fn duration_str(&self) -> String {
+        self.duration.map(duration_to_string).unwrap_or_default()
+    }
+
+    /// Return the number of bytes of output from the installer command, if it was recorded.
+    pub fn output_bytes(&self) -> &Option<usize> {
+        &self.output_bytes
+    }
+
+    /// Return the number of lines of output from the installer command, if it was recorded.
+    pub fn output_lines(&self) -> &Option<usize> {
+        &self.output_lines
+    }
+
+    /// Return the machine the package was acted upon, if it was recorded.
+    pub fn machine(&self) -> Option<&String> {
+        self.machine.as_ref()
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+
+impl PackageDurations {
+    /// Return the name of the package set group that contained the package set.
+    pub fn package_set_group_name(&self) -> &Name {
+        &self.package_set_group_name
+    }
+
+    /// Return the name of the package set that contained the package.
+    pub fn package_set_name(&self) -> &Name {
+        &self.package_set_name
+    }
+
+    /// Return the name of the package.
+    pub fn package_name(&self) -> &Name {
+        &self.package_name
+    }
+
+    /// Return the name of the installer that acted on the package.
+    pub fn installer_name(&self) -> &Name {
+        &self.installer_name
+    }
+
+    /// Return the number of times the package was acted upon.
+    pub fn count(&self) -> u32 {
+        self.count
+    }
+
+    /// Return the total time spent acting on the package.
+    pub fn total(&self) -> &Duration {
+        &self.total
+    }
+
+    /// Return the average time spent acting on the package.
+    pub fn average(&self) -> Duration {
+        if self.count == 0 {
+            Duration::default()
+        } else {
+            self.total / self.count
+        }
+    }
+
+    /// Return the longest time spent acting on the package.
+    pub fn longest(&self) -> &Duration {
+        &self.longest
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+
+impl AppliedPackageSet {
+    /// Return the date and time the action was applied.
+    pub fn date_time(&self) -> &Option<time::OffsetDateTime> {
+        &self.date_time
+    }
+
+    /// Return the date and time, as a string, the action was applied.
+    pub fn date_time_str(&self) -> String {
+        self.date_time.map(|d| d.to_string()).unwrap_or_default()
+    }
+
+    /// Return the name of the package set group that contained the package set.
+    pub fn package_set_group_name(&self) -> &Name {
+        &self.package_set_group_name
+    }
+
+    /// Return the name of the package set.
+    pub fn package_set_name(&self) -> &Name {
+        &self.package_set_name
+    }
+
+    /// Return the name of the action applied, such as `install` or `update`.
+    pub fn action(&self) -> &str {
+        &self.action
+    }
+
+    /// Return the content hash of the package set when the action was applied.
+    pub fn content_hash(&self) -> &str {
+        &self.content_hash
+    }
+
+    /// Return the machine the action was applied on, if it was recorded.
+    pub fn machine(&self) -> Option<&String> {
+        self.machine.as_ref()
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+
+impl LogEntry {
+    /// Return the machine this entry was logged on, if it was recorded.
+    pub fn machine(&self) -> Option<&String> {
+        match self {
+            Self::Installed(installed) => installed.machine(),
+            Self::Applied(applied) => applied.machine(),
+            Self::Command(_, machine) => machine.as_ref(),
+        }
+    }
+
+    // Record this entry as from `machine`, if it does not already record a machine.
+    fn with_default_machine(self, machine: &str) -> Self {
+        let machine = Some(
+            self.machine()
+                .cloned()
+                .unwrap_or_else(|| machine.to_string()),
+        );
+        match self {
+            Self::Installed(installed) => Self::Installed(InstalledPackage {
+                machine,
+                ..installed
+            }),
+            Self::Applied(applied) => Self::Applied(AppliedPackageSet { machine, ..applied }),
+            Self::Command(record, _) => Self::Command(record, machine),
+        }
+    }
+
+    // A key that identifies this entry, used to skip entries already in a log when merging.
+    fn key(&self) -> String {
+        let machine = self.machine().map(String::as_str).unwrap_or_default();
+        match self {
+            Self::Installed(installed) => format!(
+                "installed|{}|{:?}|{}/{}/{}|{}",
+                machine,
+                installed.date_time(),
+                installed.package_set_group_name(),
+                installed.package_set_name(),
+                installed.package_name(),
+                installed.installer_name()
+            ),
+            Self::Applied(applied) => format!(
+                "applied|{}|{:?}|{}/{}|{}",
+                machine,
+                applied.date_time(),
+                applied.package_set_group_name(),
+                applied.package_set_name(),
+                applied.action()
+            ),
+            Self::Command(record, _) => format!(
+                "command|{}|{:?}|{}",
+                machine,
+                record.started(),
+                record.command()
+            ),
+        }
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+// Private Functions
+// ------------------------------------------------------------------------------------------------
+
+#[cfg_attr(
+    not(any(feature = "sqlite-log", feature = "jsonl-log")),
+    allow(dead_code)
+)]
+pub(crate) const INTERRUPTED_ACTION: &str = "interrupted";
+
+#[cfg_attr(
+    not(any(feature = "sqlite-log", feature = "jsonl-log")),
+    allow(dead_code)
+)]
+pub(crate) const REQUIRES_ACTION: &str = "requires";
+
+#[cfg_attr(
+    not(any(feature = "sqlite-log", feature = "jsonl-log")),
+    allow(dead_code)
+)]
+pub(crate) const ARCHIVED_ACTION: &str = "archived";
+
+// Returns `true` if the action recorded for an applied package set leaves its packages installed,
+// so that its content hash is that of the package set as it is on this machine.
+pub(crate) fn is_applied_action(action: &str) -> bool {
+    action == InstallActionKind::Install.to_string()
+        || action == InstallActionKind::Update.to_string()
+}
+
+const UNKNOWN_MACHINE: &str = "unknown";
+
+#[allow(unsafe_code)]
+fn host_name() -> Option<String> {
+    let mut buffer = [0u8; 256];
+    let result =
+        unsafe { libc::gethostname(buffer.as_mut_ptr() as *mut libc::c_char, buffer.len()) };
+    if result == 0 {
+        let end = buffer.iter().position(|b| *b == 0).unwrap_or(buffer.len());
+        let name = String::from_utf8_lossy(&buffer[..end]).trim().to_string();
+        if !name.is_empty() {
+            return Some(name);
+        }
+    }
+    None
+}
+
+// ------------------------------------------------------------------------------------------------
+// Unit Tests
+// ------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use std::str::FromStr;
+
+    // Open a new, empty, log in each of the formats this build supports; each in a temporary
+    // directory that is removed when dropped.
+    fn open_test_logs(name: &str) -> Vec<(tempfile::TempDir, Box<dyn InstallLog>)> {
+        let mut formats: Vec<InstallLogFormat> = Default::default();
+        if cfg!(feature = "sqlite-log") {
+            formats.push(InstallLogFormat::Sqlite);
+        }
+        if cfg!(feature = "jsonl-log") {
+            formats.push(InstallLogFormat::JsonLines);
+        }
+        formats
+            .into_iter()
+            .map(|format| {
+                let work_dir = tempfile::tempdir().unwrap();
+                let log_db = format
+                    .open_from(work_dir.path().join(format!("{}.{}", name, format)))
+                    .unwrap();
+                (work_dir, log_db)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_duration_to_string() {
+        assert_eq!(duration_to_string(Duration::from_millis(0)), "0.0s");
+        assert_eq!(duration_to_string(Duration::from_millis(1_300)), "1.3s");
+        assert_eq!(duration_to_string(Duration::from_secs(62)), "1m 2.0s");
+        assert_eq!(duration_to_string(Duration::from_secs(3_723)), "1h 2m 3.0s");
+    }
+
+    #[test]
+    fn test_installed_packages_query() {
+        for (_work_dir, mut log_db) in open_test_logs("query") {
+            let name = |s: &str| Name::from_str(s).unwrap();
+            let started = time::OffsetDateTime::now_utc();
+            for (group, set, package, installer) in &[
+                ("tools", "rust", "ripgrep", "cargo"),
+                ("tools", "rust", "fd-find", "cargo"),
+                ("tools", "shell", "zsh", "homebrew"),
+                ("work", "rust", "cargo-audit", "cargo"),
+            ] {
+                log_db
+                    .log_installed_package(&InstalledPackage::new(
+                        name(group),
+                        name(set),
+                        name(package),
+                        name(installer),
+                    ))
+                    .unwrap();
+            }
+
+            assert_eq!(log_db.installed_package_history(0).unwrap().len(), 4);
+            assert_eq!(log_db.installed_package_history(2).unwrap().len(), 2);
+
+            let query = PackageLogQuery::default().with_installer(name("cargo"));
+            assert_eq!(log_db.installed_packages(&query).unwrap().len(), 3);
+
+            let query = PackageLogQuery::default()
+                .with_package_set_group(name("tools"))
+                .with_package_set(name("rust"))
+                .ordered_by(PackageLogOrder::ByPackage);
+            let packages: Vec<String> = log_db
+                .installed_packages(&query)
+                .unwrap()
+                .iter()
+                .map(|installed| installed.package_name().to_string())
+                .collect();
+            assert_eq!(packages, vec!["fd-find".to_string(), "ripgrep".to_string()]);
+
+            let query = PackageLogQuery::default()
+                .with_package(name("zsh"))
+                .since(started);
+            assert_eq!(log_db.installed_packages(&query).unwrap().len(), 1);
+            let query = PackageLogQuery::default().until(started);
+            assert!(log_db.installed_packages(&query).unwrap().is_empty());
+        }
+    }
+
+    #[test]
+    fn test_command_history() {
+        for (_work_dir, mut log_db) in open_test_logs("commands") {
+            let record = CommandRecord::new(
+                Some("group/set/package".to_string()),
+                "brew install ripgrep".to_string(),
+                time::OffsetDateTime::now_utc(),
+                Duration::from_millis(1_300),
+                Some(1),
+                "Error: No such formula".to_string(),
+            );
+            log_db.log_command(&record).unwrap();
+            let history = log_db.command_history(10).unwrap();
+            assert_eq!(history.len(), 1);
+            let logged = history.first().unwrap();
+            assert_eq!(logged.context(), record.context());
+            assert_eq!(logged.command(), record.command());
+            assert_eq!(logged.exit_code(), &Some(1));
+            assert_eq!(logged.duration(), record.duration());
+            assert_eq!(logged.stderr(), record.stderr());
+        }
+    }
+
+    #[test]
+    fn test_resume_run() {
+        for (_work_dir, mut log_db) in open_test_logs("runs") {
+            let group = Name::from_str("group").unwrap();
+            let set_one = Name::from_str("one").unwrap();
+            let set_two = Name::from_str("two").unwrap();
+            let package = Name::from_str("ripgrep").unwrap();
+            let action = InstallActionKind::Install;
+
+            log_db.begin_run(&action).unwrap();
+            assert!(log_db.resume_run(&action).unwrap().is_none());
+            log_db.log_run_progress(&group, &set_one, None).unwrap();
+            log_db
+                .log_run_progress(&group, &set_two, Some(&package))
+                .unwrap();
+            log_db.end_run(false).unwrap();
+
+            log_db.begin_run(&action).unwrap();
+            let checkpoint = log_db.resume_run(&action).unwrap().unwrap();
+            assert!(checkpoint.is_package_set_complete(&group, &set_one));
+            assert!(!checkpoint.is_package_set_complete(&group, &set_two));
+            assert!(checkpoint.is_package_complete(&group, &set_two, &package));
+            log_db.end_run(true).unwrap();
+
+            log_db.begin_run(&action).unwrap();
+            assert!(log_db.resume_run(&action).unwrap().is_none());
+            assert!(log_db
+                .resume_run(&InstallActionKind::Update)
+                .unwrap()
+                .is_none());
+            log_db.end_run(true).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_merge_logs() {
+        let others = open_test_logs("merge-other");
+        for ((_work_dir, mut log_db), (_other_work_dir, mut other_db)) in
+            open_test_logs("merge").into_iter().zip(others)
+        {
+            let name = |s: &str| Name::from_str(s).unwrap();
+            let local = log_db.machine().to_string();
+            log_db
+                .log_installed_package(&InstalledPackage::new(
+                    name("tools"),
+                    name("rust"),
+                    name("ripgrep"),
+                    name("cargo"),
+                ))
+                .unwrap();
+
+            // An entry logged before machines were recorded, and one from a named machine.
+            other_db
+                .import_entries(&[
+                    LogEntry::Installed(InstalledPackage {
+                        date_time: Some(time::OffsetDateTime::now_utc()),
+                        ..InstalledPackage::new(
+                            name("tools"),
+                            name("shell"),
+                            name("zsh"),
+                            name("homebrew"),
+                        )
+                    }),
+                    LogEntry::Applied(AppliedPackageSet {
+                        date_time: Some(time::OffsetDateTime::now_utc()),
+                        package_set_group_name: name("tools"),
+                        package_set_name: name("shell"),
+                        action: InstallActionKind::Install.to_string(),
+                        content_hash: "0123".to_string(),
+                        machine: Some("desktop".to_string()),
+                    }),
+                ])
+                .unwrap();
+
+            assert_eq!(log_db.merge_from(other_db.as_mut(), "laptop").unwrap(), 2);
+            assert_eq!(log_db.merge_from(other_db.as_mut(), "laptop").unwrap(), 0);
+
+            let history = log_db.installed_package_history(0).unwrap();
+            assert_eq!(history.len(), 2);
+            let local_history = log_db
+                .installed_packages(&PackageLogQuery::default().with_machine(&local))
+                .unwrap();
+            assert_eq!(local_history.len(), 1);
+            assert_eq!(local_history[0].machine(), Some(&local));
+            let laptop_history = log_db
+                .installed_packages(&PackageLogQuery::default().with_machine("laptop"))
+                .unwrap();
+            assert_eq!(laptop_history.len(), 1);
+            assert_eq!(laptop_history[0].package_name(), &name("zsh"));
+
+            // Entries from other machines are not part of this machine's state.
+            assert!(log_db
+                .last_applied_hash(&name("tools"), &name("shell"))
+                .unwrap()
+                .is_none());
+            assert_eq!(
+                log_db.logged_package_sets().unwrap(),
+                vec![(name("tools"), name("rust"))]
+            );
+        }
+    }
 }
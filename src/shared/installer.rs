@@ -1,17 +1,51 @@
-use crate::error::{ErrorKind, Result};
-use crate::shared::command::execute_shell_command;
+use crate::error::{Error, Result};
+use crate::reporter::{report_failure, report_group_end, report_group_start};
+use crate::shared::answers::PromptAnswers;
+use crate::shared::builders::{Builder, PackageBuilder, ShellCommandBuilder};
+use crate::shared::cargo::{is_cargo_installer, InstalledCrates};
+use crate::shared::command::{
+    execute_command_args, execute_command_args_output, execute_program, execute_shell_command,
+    execute_shell_command_output, find_executable, shell_var_replace, take_command_records,
+    user_shell, CommandOutput,
+};
+use crate::shared::dirs;
+use crate::shared::download::DOWNLOAD_INSTALLER;
 use crate::shared::env::{
-    add_action_vars, add_package_action_vars, add_package_set_action_vars, default_vars,
+    add_action_vars, add_bundle_action_vars, add_global_vars, add_package_action_vars,
+    add_package_set_action_vars, add_package_version_vars, add_prerequisite_vars, default_vars,
+    is_root, var_string_replace, vars_scope, Scope,
+};
+#[cfg(feature = "github-releases")]
+use crate::shared::github::GITHUB_INSTALLER;
+use crate::shared::install_log::{
+    is_applied_action, open_install_log, AppliedPackageSet, InstallLog, InstalledPackage,
+    PackageLogQuery, RunCheckpoint,
+};
+use crate::shared::interrupt::{check_interrupted, install_interrupt_handler, is_interrupted};
+#[cfg(feature = "github-releases")]
+use crate::shared::lock::LockFile;
+use crate::shared::migrate::{
+    check_format_version, registry_version, MigrationTarget, LIST_REGISTRY_FORMAT_VERSION,
+    REGISTRY_FORMAT_VERSION,
+};
+use crate::shared::packages::{
+    is_valid_prerequisite, Package, PackageBundle, PackageRepository, PackageSet, PackageSetGroup,
+};
+use crate::shared::selection::PackageSetSelection;
+use crate::shared::trust::{ScriptApprovals, ScriptTrust};
+use crate::shared::version::{parse_version, parse_versions, VersionStatus};
+use crate::shared::{
+    serialize_ordered_map, FileSystemResource, Name, PackageKind, Platform, Steps, StepsSummary,
 };
-use crate::shared::install_log::{InstalledPackage, PackageLog};
-use crate::shared::packages::{Package, PackageRepository, PackageSet, PackageSetGroup};
-use crate::shared::{FileSystemResource, Name, PackageKind, Platform};
 use crate::APP_NAME;
+use semver::Version;
 use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
 use std::collections::HashMap;
 use std::fmt::{Display, Formatter};
 use std::fs::read_to_string;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
 
 // ------------------------------------------------------------------------------------------------
 // Public Types
@@ -21,7 +55,7 @@ use std::path::PathBuf;
 /// An action that may be taken by an installer. These are set and passed through by a client such
 /// as the CLI to denote the action to take.
 ///
-#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Eq, Hash)]
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
 #[serde(deny_unknown_fields, rename_all = "kebab-case")]
 pub enum InstallActionKind {
     #[allow(missing_docs)]
@@ -34,6 +68,63 @@ pub enum InstallActionKind {
     LinkFiles,
 }
 
+///
+/// The user an installer must be run as; some installers, such as Homebrew, refuse to run as root
+/// while others, such as apt, require it.
+///
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum RunAs {
+    /// The installer may be run by any user, this is the default.
+    Any,
+    /// The installer must not be run as root.
+    User,
+    /// The installer must be run as root.
+    Root,
+}
+
+///
+/// A command run by an installer. This is either a script string, executed by the user's shell,
+/// or an argument vector, executed directly without a shell. In the latter form variables are
+/// replaced in each argument individually, so that values such as package names containing shell
+/// meta-characters are passed as a single argument and never interpreted by a shell.
+///
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+#[serde(untagged)]
+pub enum InstallerCommand {
+    /// A script string, executed by the user's shell.
+    Script(String),
+    /// An argument vector, the first element is the program to execute.
+    Exec(Vec<String>),
+}
+
+///
+/// A check that decides whether an installer is used on this machine; either a path that must
+/// exist, a command that must succeed, or a list of checks any, or all, of which must pass.
+///
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+#[serde(untagged)]
+pub enum IfExists {
+    /// A file or directory path that must exist.
+    Path(String),
+    /// A script string, executed by the user's shell, that must succeed; for example
+    /// `command -v brew`.
+    Command {
+        /// The script string to execute.
+        command: String,
+    },
+    /// A list of checks, at least one of which must pass.
+    Any {
+        /// The checks to evaluate.
+        any: Vec<IfExists>,
+    },
+    /// A list of checks, all of which must pass.
+    All {
+        /// The checks to evaluate.
+        all: Vec<IfExists>,
+    },
+}
+
 ///
 /// This holds the configuration regarding a single installer type, these can be platform-specific
 /// or not, and are defined to handle one kind of `PackageKind`. These instances are a part of the
@@ -47,11 +138,39 @@ pub struct Installer {
     platform: Option<Platform>,
     kind: PackageKind,
     #[serde(default, skip_serializing_if = "Option::is_none")]
-    if_exists: Option<String>,
-    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
-    commands: HashMap<InstallActionKind, String>,
+    if_exists: Option<IfExists>,
+    #[serde(
+        default,
+        skip_serializing_if = "HashMap::is_empty",
+        serialize_with = "serialize_ordered_map"
+    )]
+    commands: HashMap<InstallActionKind, InstallerCommand>,
+    #[serde(
+        default,
+        skip_serializing_if = "HashMap::is_empty",
+        serialize_with = "serialize_ordered_map"
+    )]
+    bundle_commands: HashMap<InstallActionKind, InstallerCommand>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    installed_version: Option<InstallerCommand>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    latest_version: Option<InstallerCommand>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    available_versions: Option<InstallerCommand>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    install_version: Option<InstallerCommand>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    update_self: Option<InstallerCommand>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    list_installed: Option<InstallerCommand>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    add_prerequisite: Option<InstallerCommand>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    run_as: Option<RunAs>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
-    update_self: Option<String>,
+    enabled: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    priority: Option<i32>,
 }
 
 ///
@@ -61,6 +180,57 @@ pub struct Installer {
 #[derive(Clone, Debug)]
 pub struct InstallerRegistry {
     installers: HashMap<(Platform, PackageKind), Installer>,
+    environment: HashMap<String, String>,
+    installed_crates: RefCell<Option<InstalledCrates>>,
+    checkpoint: RefCell<RunCheckpoint>,
+    last_summary: RefCell<ExecuteSummary>,
+}
+
+///
+/// Options that modify how the installer registry executes an action; by default every selected
+/// package set is acted upon, a selection that names something not in the repository is an error,
+/// and a failed package set is left as it was when the failure occurred.
+///
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ExecuteOptions {
+    changed_only: bool,
+    ignore_missing: bool,
+    resume: bool,
+    transactional: bool,
+    unlink_only: bool,
+}
+
+///
+/// The result of one of the checks made by `Installer::smoke_test`.
+///
+#[derive(Clone, Debug, PartialEq)]
+pub struct InstallerCheck {
+    check: String,
+    passed: bool,
+    detail: String,
+}
+
+///
+/// A summary of the work performed by the installer registry when executing an action.
+///
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ExecuteSummary {
+    package_sets: u32,
+    packages: u32,
+    succeeded: Vec<String>,
+    failed: Vec<String>,
+    warnings: Vec<String>,
+    steps: StepsSummary,
+}
+
+///
+/// A command, or script, that performing an action on a package set would run, with variables
+/// replaced; the source describes where in the package set it comes from.
+///
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct AuditedCommand {
+    source: String,
+    command: String,
 }
 
 ///
@@ -68,6 +238,117 @@ pub struct InstallerRegistry {
 ///
 pub const REGISTRY_FILE: &str = "installers.yml";
 
+///
+/// The package name used by `Installer::smoke_test` when running, or checking, commands that act
+/// on a package.
+///
+pub const SMOKE_TEST_PACKAGE: &str = "mcfg-smoke-test";
+
+// ------------------------------------------------------------------------------------------------
+// Private Types
+// ------------------------------------------------------------------------------------------------
+
+#[derive(Deserialize, Serialize, Debug)]
+struct RegistryFile {
+    version: u32,
+    installers: Vec<Installer>,
+}
+
+// The changes made while acting on a single package set, used to undo them if it fails.
+#[derive(Debug, Default)]
+struct PackageSetChanges {
+    packages: Vec<Name>,
+    links: Vec<PathBuf>,
+}
+
+// How a package is updated; to the latest version, to the highest available version that
+// satisfies its constraint, or not at all, with the reason.
+#[derive(Debug, PartialEq)]
+enum UpdateTo {
+    Latest,
+    Version(Version),
+    HeldBack(String),
+}
+
+// ------------------------------------------------------------------------------------------------
+// Public Functions
+// ------------------------------------------------------------------------------------------------
+
+///
+/// Return the registry content `registry_data` in its canonical form, as written by this tool;
+/// all installers are kept, regardless of platform.
+///
+pub fn format_registry(registry_data: &str) -> Result<String> {
+    Ok(serde_yaml::to_string(&parse_registry(registry_data)?)?)
+}
+
+///
+/// Return all the installers in the registry file at `registry_path`, including those that are
+/// discarded when the registry is opened as they do not match the current platform, or their
+/// `if_exists` check fails.
+///
+pub fn read_registry_installers(registry_path: &Path) -> Result<Vec<Installer>> {
+    Ok(parse_registry(&read_to_string(registry_path)?)?.installers)
+}
+
+///
+/// Enable, or disable, every installer named `name` in the registry file at `registry_path`,
+/// returning the number of installers changed. A disabled installer is kept in the registry file
+/// but discarded when the registry is opened. The registry file is rewritten in its canonical form,
+/// as `format_registry` would.
+///
+pub fn set_installer_enabled(registry_path: &Path, name: &Name, enabled: bool) -> Result<usize> {
+    info!(
+        "set_installer_enabled ({:?}, {}, {})",
+        registry_path, name, enabled
+    );
+    let mut registry_file = parse_registry(&read_to_string(registry_path)?)?;
+    let mut found = false;
+    let mut changed = 0;
+    for installer in registry_file
+        .installers
+        .iter_mut()
+        .filter(|installer| &installer.name == name)
+    {
+        found = true;
+        if installer.is_enabled() != enabled {
+            // An enabled installer omits the field, as it is the default.
+            installer.enabled = if enabled { None } else { Some(false) };
+            changed += 1;
+        }
+    }
+    if !found {
+        return Err(Error::NoInstaller(name.to_string()));
+    }
+    if changed > 0 {
+        std::fs::write(registry_path, serde_yaml::to_string(&registry_file)?)?;
+    }
+    Ok(changed)
+}
+
+///
+/// Return the variables used when performing `action` on `package_set`, or on `package` if
+/// provided, as they are passed to installer commands and package set scripts.
+///
+pub fn execution_vars(
+    environment: &HashMap<String, String>,
+    action: &InstallActionKind,
+    package_set: &PackageSet,
+    package: Option<&Package>,
+) -> HashMap<String, String> {
+    let variable_replacements = package_set_vars(environment, action, package_set);
+    match package {
+        None => variable_replacements,
+        Some(package) => {
+            let variable_replacements = add_package_action_vars(package, &variable_replacements);
+            match package.download() {
+                Some(download) => download.add_vars(&variable_replacements),
+                None => variable_replacements,
+            }
+        }
+    }
+}
+
 // ------------------------------------------------------------------------------------------------
 // Implementations
 // ------------------------------------------------------------------------------------------------
@@ -87,6 +368,174 @@ impl Display for InstallActionKind {
     }
 }
 
+impl FromStr for InstallActionKind {
+    type Err = Error;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "install" => Ok(InstallActionKind::Install),
+            "update" => Ok(InstallActionKind::Update),
+            "uninstall" => Ok(InstallActionKind::Uninstall),
+            "link" | "link-files" => Ok(InstallActionKind::LinkFiles),
+            _ => Err(Error::InvalidConfigValue(
+                "action".to_string(),
+                s.to_string(),
+            )),
+        }
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+
+impl Display for InstallerCommand {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InstallerCommand::Script(script_string) => write!(f, "{}", script_string),
+            InstallerCommand::Exec(args) => write!(f, "{}", shell_words::join(args)),
+        }
+    }
+}
+
+impl From<&str> for InstallerCommand {
+    fn from(script_string: &str) -> Self {
+        InstallerCommand::Script(script_string.to_string())
+    }
+}
+
+impl From<String> for InstallerCommand {
+    fn from(script_string: String) -> Self {
+        InstallerCommand::Script(script_string)
+    }
+}
+
+impl InstallerCommand {
+    /// Return this command with variables replaced, as it would be executed.
+    pub fn with_vars(&self, variable_replacements: &HashMap<String, String>) -> String {
+        match self {
+            InstallerCommand::Script(script_string) => {
+                shell_var_replace(script_string, variable_replacements)
+            }
+            InstallerCommand::Exec(args) => shell_words::join(
+                args.iter()
+                    .map(|arg| var_string_replace(arg, variable_replacements)),
+            ),
+        }
+    }
+
+    /// Return the program this command runs, with variables replaced; for a script this is the
+    /// first word that is not a variable assignment, if the script can be split into words.
+    pub fn program(&self, variable_replacements: &HashMap<String, String>) -> Option<String> {
+        match self {
+            InstallerCommand::Script(script_string) => {
+                shell_words::split(&shell_var_replace(script_string, variable_replacements))
+                    .ok()?
+                    .into_iter()
+                    .find(|word| !is_var_assignment(word))
+            }
+            InstallerCommand::Exec(args) => args
+                .first()
+                .map(|program| var_string_replace(program, variable_replacements)),
+        }
+    }
+
+    /// Execute this command, replacing any variables, returning a summary of the execution.
+    pub fn execute(
+        &self,
+        variable_replacements: &HashMap<String, String>,
+    ) -> Result<CommandOutput> {
+        match self {
+            InstallerCommand::Script(script_string) => {
+                execute_shell_command(script_string, variable_replacements)
+            }
+            InstallerCommand::Exec(args) => execute_command_args(args, variable_replacements),
+        }
+    }
+
+    /// Execute this command, replacing any variables, returning the text written to `stdout`.
+    pub fn execute_output(
+        &self,
+        variable_replacements: &HashMap<String, String>,
+    ) -> Result<String> {
+        match self {
+            InstallerCommand::Script(script_string) => {
+                execute_shell_command_output(script_string, variable_replacements)
+            }
+            InstallerCommand::Exec(args) => {
+                execute_command_args_output(args, variable_replacements)
+            }
+        }
+    }
+
+    /// Execute this command, as `execute` does, reporting each line of output prefixed with
+    /// `output_prefix` so that it remains attributable to the package it was run for.
+    pub fn execute_prefixed(
+        &self,
+        variable_replacements: &HashMap<String, String>,
+        output_prefix: &str,
+    ) -> Result<CommandOutput> {
+        debug!("InstallerCommand::execute_prefixed ({}, ...)", self);
+        let mut builder = match self {
+            InstallerCommand::Script(script_string) => {
+                ShellCommandBuilder::script(script_string, variable_replacements)
+            }
+            InstallerCommand::Exec(args) => {
+                ShellCommandBuilder::command_args(args, variable_replacements)?
+            }
+        };
+        builder.output_prefix(output_prefix).build().execute()
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+
+impl Display for IfExists {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let join = |checks: &Vec<IfExists>| {
+            checks
+                .iter()
+                .map(IfExists::to_string)
+                .collect::<Vec<String>>()
+                .join(", ")
+        };
+        match self {
+            IfExists::Path(path) => write!(f, "path {}", path),
+            IfExists::Command { command } => write!(f, "command '{}'", command),
+            IfExists::Any { any } => write!(f, "any of ({})", join(any)),
+            IfExists::All { all } => write!(f, "all of ({})", join(all)),
+        }
+    }
+}
+
+impl From<&str> for IfExists {
+    fn from(path: &str) -> Self {
+        IfExists::Path(path.to_string())
+    }
+}
+
+impl IfExists {
+    /// Return `true` if this check passes, else `false`. A command passes if it exits
+    /// successfully, with `variables` set, its output is discarded; an empty list of checks
+    /// always passes.
+    pub fn is_match(&self, variables: &HashMap<String, String>) -> bool {
+        match self {
+            IfExists::Path(path) => PathBuf::from(path).exists(),
+            IfExists::Command { command } => {
+                match execute_shell_command_output(command, variables) {
+                    Ok(_) => true,
+                    Err(e) => {
+                        debug!("IfExists::is_match: command {:?} failed: {}", command, e);
+                        false
+                    }
+                }
+            }
+            IfExists::Any { any } => {
+                any.is_empty() || any.iter().any(|check| check.is_match(variables))
+            }
+            IfExists::All { all } => all.iter().all(|check| check.is_match(variables)),
+        }
+    }
+}
+
 // ------------------------------------------------------------------------------------------------
 
 impl Installer {
@@ -100,11 +549,23 @@ impl Installer {
         Platform::CURRENT.is_match(&self.platform)
     }
 
-    /// Return `true` if the installer has a specified `if_exists` value, and if that path exists.
-    pub fn if_exists_match(&self) -> bool {
+    /// Return `true` if this installer is enabled, the default, else `false`. A disabled
+    /// installer is discarded when the registry is opened.
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.unwrap_or(true)
+    }
+
+    /// Return the check that must pass for this installer to be used, if any.
+    pub fn if_exists(&self) -> &Option<IfExists> {
+        &self.if_exists
+    }
+
+    /// Return `true` if the installer has no `if_exists` check, or if that check passes with
+    /// `variables` set.
+    pub fn if_exists_match(&self, variables: &HashMap<String, String>) -> bool {
         match &self.if_exists {
             None => true,
-            Some(path) => PathBuf::from(path).exists(),
+            Some(if_exists) => if_exists.is_match(variables),
         }
     }
 
@@ -118,51 +579,214 @@ impl Installer {
         self.kind.clone()
     }
 
+    /// Return the priority of this installer, when several application installers are available
+    /// for a package the one with the lowest priority is used. The default priority is `0`.
+    pub fn priority(&self) -> i32 {
+        self.priority.unwrap_or_default()
+    }
+
     /// Return the map of commands for this installer.
-    pub fn commands(&self) -> &HashMap<InstallActionKind, String> {
+    pub fn commands(&self) -> &HashMap<InstallActionKind, InstallerCommand> {
         &self.commands
     }
 
     /// Return the command for the specific action kind.
-    pub fn command_for(&self, kind: &InstallActionKind) -> Option<&String> {
+    pub fn command_for(&self, kind: &InstallActionKind) -> Option<&InstallerCommand> {
         self.commands.get(kind)
     }
 
+    /// Return the map of bundle commands for this installer.
+    pub fn bundle_commands(&self) -> &HashMap<InstallActionKind, InstallerCommand> {
+        &self.bundle_commands
+    }
+
+    /// Return the bundle command for the specific action kind.
+    pub fn bundle_command_for(&self, kind: &InstallActionKind) -> Option<&InstallerCommand> {
+        self.bundle_commands.get(kind)
+    }
+
+    /// Return the command that reports the installed version of a package, if any.
+    pub fn installed_version(&self) -> &Option<InstallerCommand> {
+        &self.installed_version
+    }
+
+    /// Return the command that reports the latest available version of a package, if any.
+    pub fn latest_version(&self) -> &Option<InstallerCommand> {
+        &self.latest_version
+    }
+
+    /// Return the command that lists the versions of a package available to install, if any.
+    pub fn available_versions(&self) -> &Option<InstallerCommand> {
+        &self.available_versions
+    }
+
+    /// Return the command that installs the version `package_version` of a package, if any.
+    pub fn install_version(&self) -> &Option<InstallerCommand> {
+        &self.install_version
+    }
+
     /// Return `true` if this installer supports updating itself.
     pub fn has_update_self(&self) -> bool {
         self.update_self.is_some()
     }
 
     /// Return the update self command for this installer.
-    pub fn update_self(&self) -> &Option<String> {
+    pub fn update_self(&self) -> &Option<InstallerCommand> {
         &self.update_self
     }
 
+    /// Return the command that lists the packages installed, by the user, with this installer.
+    pub fn list_installed(&self) -> &Option<InstallerCommand> {
+        &self.list_installed
+    }
+
+    /// Return the command that adds a package set prerequisite, such as a Homebrew tap or an apt
+    /// repository, with this installer.
+    pub fn add_prerequisite(&self) -> &Option<InstallerCommand> {
+        &self.add_prerequisite
+    }
+
+    /// Return the user this installer must be run as.
+    pub fn run_as(&self) -> RunAs {
+        self.run_as.unwrap_or(RunAs::Any)
+    }
+
+    /// Return an error if the current process is not running as the user required by `run_as`.
+    pub fn check_run_as(&self) -> Result<()> {
+        match self.run_as() {
+            RunAs::User if is_root() => Err(Error::InstallerRefusesRoot(self.name.to_string())),
+            RunAs::Root if !is_root() => Err(Error::InstallerRequiresRoot(self.name.to_string())),
+            _ => Ok(()),
+        }
+    }
+
+    /// Check that this installer is usable on this machine without installing anything. This
+    /// evaluates the `if_exists` check and `run_as` user, runs the `latest_version` command, if
+    /// any, and checks the install command; that the program it runs is on the `PATH` and, for a
+    /// script, that the user's shell can parse it. Commands that act on a package are given the
+    /// package name `SMOKE_TEST_PACKAGE`, and the variables in `environment`.
+    pub fn smoke_test(&self, environment: &HashMap<String, String>) -> Vec<InstallerCheck> {
+        info!("Installer::smoke_test {}", self.name);
+        let mut checks: Vec<InstallerCheck> = Default::default();
+
+        checks.push(match &self.if_exists {
+            None => InstallerCheck::passed("if-exists", "nothing to check"),
+            Some(if_exists) if if_exists.is_match(environment) => {
+                InstallerCheck::passed("if-exists", &format!("{} passed", if_exists))
+            }
+            Some(if_exists) => {
+                InstallerCheck::failed("if-exists", &format!("{} failed", if_exists))
+            }
+        });
+
+        checks.push(match self.check_run_as() {
+            Ok(_) => InstallerCheck::passed("run-as", &format!("{:?}", self.run_as())),
+            Err(e) => InstallerCheck::failed("run-as", &e.to_string()),
+        });
+
+        let package = PackageBuilder::named(Name::from_str(SMOKE_TEST_PACKAGE).unwrap()).build();
+        let variable_replacements = add_global_vars(&add_package_action_vars(
+            &package,
+            &add_action_vars(&InstallActionKind::Install, environment),
+        ));
+
+        if let Some(command) = &self.latest_version {
+            checks.push(match command.execute_output(&variable_replacements) {
+                Ok(output) => InstallerCheck::passed(
+                    "latest-version",
+                    &match parse_version(&output) {
+                        Some(version) => format!("reported version {}", version),
+                        None => "ran, but reported no version".to_string(),
+                    },
+                ),
+                Err(e) => InstallerCheck::failed("latest-version", &e.to_string()),
+            });
+        }
+
+        match self.commands.get(&InstallActionKind::Install) {
+            None => checks.push(InstallerCheck::failed("install", "no install command")),
+            Some(command) => {
+                let command_string = command.with_vars(&variable_replacements);
+                checks.push(match command.program(&variable_replacements) {
+                    None => InstallerCheck::failed(
+                        "install",
+                        &format!("no program found in '{}'", command_string),
+                    ),
+                    Some(program) => match find_executable(&program) {
+                        Some(path) => InstallerCheck::passed(
+                            "install",
+                            &format!("'{}', runs {}", command_string, path.display()),
+                        ),
+                        None => InstallerCheck::failed(
+                            "install",
+                            &format!("'{}', {} not found on PATH", command_string, program),
+                        ),
+                    },
+                });
+                if let InstallerCommand::Script(_) = command {
+                    // The `-n` flag has the shell read, but not execute, the script.
+                    checks.push(
+                        match execute_program(&user_shell(), &["-n", "-c", &command_string]) {
+                            Ok(_) => InstallerCheck::passed("install", "script parsed by shell"),
+                            Err(e) => InstallerCheck::failed("install", &e.to_string()),
+                        },
+                    );
+                }
+            }
+        }
+        checks
+    }
+
+    /// Run the `list_installed` command, if any, returning the names of the installed packages.
+    /// The first word of each line of output is taken as a package name; indented lines, and words
+    /// that are not valid names, are ignored.
+    pub fn list_installed_packages(
+        &self,
+        environment: &HashMap<String, String>,
+    ) -> Result<Vec<Name>> {
+        match &self.list_installed {
+            None => Ok(Default::default()),
+            Some(command) => {
+                let output = command.execute_output(environment)?;
+                Ok(output
+                    .lines()
+                    .filter(|line| !line.starts_with(char::is_whitespace))
+                    .filter_map(|line| line.split_whitespace().next())
+                    .filter_map(|word| Name::from_str(word.trim_end_matches(':')).ok())
+                    .collect())
+            }
+        }
+    }
+
     fn package_action(
         &self,
         action: &InstallActionKind,
         package: &Package,
         variable_replacements: &HashMap<String, String>,
-    ) -> Result<()> {
+        output_prefix: &str,
+    ) -> Result<Option<CommandOutput>> {
         if self.is_platform_match() && package.is_platform_match() {
             if self.kind() == *package.kind() {
-                let cmd = self.commands.get(&action);
-                if let Some(cmd_str) = cmd {
+                let cmd = self.commands.get(action);
+                if let Some(command) = cmd {
+                    self.check_run_as()?;
                     reportln!(
                         "* performing {} on {} package {}",
                         action,
                         &self.name,
                         package.name()
                     );
-                    execute_shell_command(cmd_str, variable_replacements)?;
+                    Ok(Some(
+                        command.execute_prefixed(variable_replacements, output_prefix)?,
+                    ))
                 } else {
                     info!("installer has no command for action {:?}", action);
+                    Ok(None)
                 }
-                Ok(())
             } else {
                 // One hopes we don't get here.
                 error!("Installer::install: the package isn't meant for this installer.");
-                Err(ErrorKind::WrongInstallerForKind(self.kind.clone()).into())
+                Err(Error::WrongInstallerForKind(self.kind.clone()))
             }
         } else {
             // It is not an error as a package set may include different packages per platform.
@@ -171,16 +795,144 @@ impl Installer {
                 package.name(),
                 Platform::CURRENT
             );
-            Ok(())
+            Ok(None)
+        }
+    }
+
+    fn package_version_action(
+        &self,
+        action: &InstallActionKind,
+        package: &Package,
+        version: &Version,
+        variable_replacements: &HashMap<String, String>,
+        output_prefix: &str,
+    ) -> Result<Option<CommandOutput>> {
+        match &self.install_version {
+            Some(command) => {
+                self.check_run_as()?;
+                reportln!(
+                    "* performing {} on {} package {} (version {})",
+                    action,
+                    &self.name,
+                    package.name(),
+                    version
+                );
+                let variable_replacements =
+                    add_package_version_vars(version, variable_replacements);
+                Ok(Some(
+                    command.execute_prefixed(&variable_replacements, output_prefix)?,
+                ))
+            }
+            None => {
+                info!("installer has no install version command");
+                Ok(None)
+            }
+        }
+    }
+
+    fn bundle_action(
+        &self,
+        action: &InstallActionKind,
+        bundle: &PackageBundle,
+        variable_replacements: &HashMap<String, String>,
+        output_prefix: &str,
+    ) -> Result<Option<CommandOutput>> {
+        if self.kind() == *bundle.kind() {
+            let cmd = self.bundle_commands.get(action);
+            if let Some(command) = cmd {
+                self.check_run_as()?;
+                reportln!(
+                    "* performing {} on {} bundle {}",
+                    action,
+                    &self.name,
+                    bundle.file()
+                );
+                Ok(Some(
+                    command.execute_prefixed(variable_replacements, output_prefix)?,
+                ))
+            } else {
+                info!("installer has no bundle command for action {:?}", action);
+                Ok(None)
+            }
+        } else {
+            error!("Installer::bundle_action: the bundle isn't meant for this installer.");
+            Err(Error::WrongInstallerForKind(self.kind.clone()))
+        }
+    }
+
+    fn prerequisite_action(
+        &self,
+        prerequisite: &str,
+        variable_replacements: &HashMap<String, String>,
+        output_prefix: &str,
+    ) -> Result<CommandOutput> {
+        match &self.add_prerequisite {
+            Some(_) if !is_valid_prerequisite(prerequisite) => {
+                Err(Error::InvalidPrerequisite(prerequisite.to_string()))
+            }
+            Some(command) => {
+                self.check_run_as()?;
+                reportln!("* adding {} prerequisite {}", &self.name, prerequisite);
+                command.execute_prefixed(variable_replacements, output_prefix)
+            }
+            None => Err(Error::NoPrerequisiteCommand(self.name.to_string())),
         }
     }
 }
 
 // ------------------------------------------------------------------------------------------------
 
-impl Into<Vec<Installer>> for InstallerRegistry {
-    fn into(self) -> Vec<Installer> {
-        let mut inner = self.installers;
+impl Display for InstallerCheck {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "[{}] {}: {}",
+            if self.passed { "ok" } else { "failed" },
+            self.check,
+            self.detail
+        )
+    }
+}
+
+impl InstallerCheck {
+    fn passed(check: &str, detail: &str) -> Self {
+        Self {
+            check: check.to_string(),
+            passed: true,
+            detail: detail.to_string(),
+        }
+    }
+
+    fn failed(check: &str, detail: &str) -> Self {
+        Self {
+            check: check.to_string(),
+            passed: false,
+            detail: detail.to_string(),
+        }
+    }
+
+    /// Return the name of the check; one of `if-exists`, `run-as`, `latest-version`, or
+    /// `install`.
+    pub fn check(&self) -> &String {
+        &self.check
+    }
+
+    /// Returns `true` if the check passed, else `false`.
+    pub fn is_passed(&self) -> bool {
+        self.passed
+    }
+
+    /// Return a description of the result of the check.
+    pub fn detail(&self) -> &String {
+        &self.detail
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+
+impl From<InstallerRegistry> for Vec<Installer> {
+    fn from(val: InstallerRegistry) -> Self {
+        let mut inner = val.installers;
         inner.drain().map(|(_, v)| v).collect()
     }
 }
@@ -189,6 +941,10 @@ impl From<Vec<Installer>> for InstallerRegistry {
     fn from(installers: Vec<Installer>) -> Self {
         let mut registry = Self {
             installers: Default::default(),
+            environment: default_vars(Scope::default()),
+            installed_crates: Default::default(),
+            checkpoint: Default::default(),
+            last_summary: Default::default(),
         };
         for installer in installers {
             let key = (
@@ -212,25 +968,29 @@ impl From<Vec<Installer>> for InstallerRegistry {
 }
 
 impl FileSystemResource for InstallerRegistry {
-    fn default_path() -> PathBuf {
-        xdirs::config_dir_for(APP_NAME).unwrap().join(REGISTRY_FILE)
+    fn default_path(scope: Scope) -> PathBuf {
+        dirs::config_dir_for(scope, APP_NAME)
+            .unwrap()
+            .join(REGISTRY_FILE)
     }
 
     fn open_from(registry_file: PathBuf) -> Result<Self> {
         info!("InstallerRegistry::read loading from {:?}", registry_file);
-        let registry_data = read_to_string(registry_file)?;
-        let installers: Vec<Installer> = serde_yaml::from_str(&registry_data)?;
+        let installers = parse_registry(&read_to_string(registry_file)?)?.installers;
         debug!(
             "InstallerRegistry::read: fetched {} installers from registry",
             installers.len()
         );
 
-        let (keep, discard): (Vec<Installer>, Vec<Installer>) = installers
-            .into_iter()
-            .partition(|i| i.is_platform_match() && i.if_exists_match());
+        // The checks are run before the registry's environment is set, with the default variables.
+        let variables = default_vars(Scope::default());
+        let (keep, discard): (Vec<Installer>, Vec<Installer>) =
+            installers.into_iter().partition(|i| {
+                i.is_enabled() && i.is_platform_match() && i.if_exists_match(&variables)
+            });
         for discarded in discard {
             info!(
-                "InstallerRegistry::read: discarding installer {}, not a platform match, or 'if_exist' check failed",
+                "InstallerRegistry::read: discarding installer {}, disabled, not a platform match, or 'if_exist' check failed",
                 discarded.name()
             )
         }
@@ -240,6 +1000,25 @@ impl FileSystemResource for InstallerRegistry {
 }
 
 impl InstallerRegistry {
+    /// Use `environment` as the variables for installer commands and package set scripts, rather
+    /// than the default variables.
+    pub fn with_environment(self, environment: HashMap<String, String>) -> Self {
+        Self {
+            environment,
+            ..self
+        }
+    }
+
+    /// Return the variables used by installer commands and package set scripts.
+    pub fn environment(&self) -> &HashMap<String, String> {
+        &self.environment
+    }
+
+    /// Return the scope of the variables used by installer commands and package set scripts.
+    pub fn scope(&self) -> Scope {
+        vars_scope(&self.environment)
+    }
+
     /// Return `true` if this registry contains no installer specifications, else `false`..
     pub fn is_empty(&self) -> bool {
         self.installers.is_empty()
@@ -250,168 +1029,949 @@ impl InstallerRegistry {
         self.installers.values()
     }
 
-    /// Return a matching installer for the platform/package kind pair.
+    /// Return the installer with the given name that matches the current platform, if any.
+    pub fn installer_named(&self, name: &str) -> Option<&Installer> {
+        self.installers()
+            .filter(|installer| installer.is_platform_match())
+            .find(|installer| installer.name() == name)
+    }
+
+    /// Return a matching installer for the platform/package kind pair. For an application kind
+    /// with no name this is the application installer, named or not, with the lowest priority and
+    /// then name.
     pub fn installer_for(&self, platform: Platform, kind: PackageKind) -> Option<&Installer> {
-        self.installers.get(&(platform, kind))
+        match kind {
+            PackageKind::Application(None) => self
+                .installers
+                .iter()
+                .filter(|((installer_platform, installer_kind), _)| {
+                    installer_platform == &platform
+                        && matches!(installer_kind, PackageKind::Application(_))
+                })
+                .map(|(_, installer)| installer)
+                .min_by(|lhs, rhs| (lhs.priority(), &lhs.name).cmp(&(rhs.priority(), &rhs.name))),
+            kind => self.installers.get(&(platform, kind)),
+        }
+    }
+
+    /// Return every command, and script, that performing `action` on `package_set` could run, in
+    /// the order they would be run; this does not check whether packages are already installed,
+    /// or held back, and so includes commands that may be skipped.
+    pub fn audit_package_set(
+        &self,
+        action: &InstallActionKind,
+        package_set: &PackageSet,
+    ) -> Vec<AuditedCommand> {
+        let mut commands: Vec<AuditedCommand> = Default::default();
+        let variable_replacements = package_set_vars(&self.environment, action, package_set);
+        let script = |source: &str, script_string: &str| {
+            AuditedCommand::new(
+                source,
+                &InstallerCommand::from(script_string).with_vars(&variable_replacements),
+            )
+        };
+
+        let (first_hook, last_hook) = if *action == InstallActionKind::Uninstall {
+            (
+                ("run-after", package_set.run_after()),
+                ("run-before", package_set.run_before()),
+            )
+        } else {
+            (
+                ("run-before", package_set.run_before()),
+                ("run-after", package_set.run_after()),
+            )
+        };
+
+        if let (hook_name, Some(cmd_str)) = first_hook {
+            commands.push(script(hook_name, cmd_str));
+        }
+
+        if adds_prerequisites(action) {
+            for (installer, prerequisite) in self.package_set_prerequisites(package_set) {
+                if let Some(command) = installer.add_prerequisite() {
+                    commands.push(AuditedCommand::new(
+                        &format!("requires {} [{}]", prerequisite, installer.name()),
+                        &command.with_vars(&add_prerequisite_vars(
+                            prerequisite,
+                            &variable_replacements,
+                        )),
+                    ));
+                }
+            }
+        }
+
+        if let Some(packages) = package_set.packages() {
+            for package in packages.filter(|package| package.is_platform_match()) {
+                let variable_replacements =
+                    add_package_action_vars(package, &variable_replacements);
+                if let Some(download) = package.download() {
+                    let variable_replacements = download.add_vars(&variable_replacements);
+                    commands.push(AuditedCommand::new(
+                        &format!("package {} [{}]", package.name(), DOWNLOAD_INSTALLER),
+                        &format!(
+                            "download {}",
+                            var_string_replace(download.url(), &variable_replacements)
+                        ),
+                    ));
+                    continue;
+                }
+                #[cfg(feature = "github-releases")]
+                if let Some(github) = package.github() {
+                    commands.push(AuditedCommand::new(
+                        &format!("package {} [{}]", package.name(), GITHUB_INSTALLER),
+                        &format!("download release asset from {}", github.repository()),
+                    ));
+                    continue;
+                }
+                if let Some((installer, command)) = self
+                    .installer_for(package.platform(), package.kind().clone())
+                    .and_then(|installer| {
+                        installer
+                            .commands()
+                            .get(action)
+                            .map(|command| (installer, command))
+                    })
+                {
+                    commands.push(AuditedCommand::new(
+                        &format!("package {} [{}]", package.name(), installer.name()),
+                        &command.with_vars(&variable_replacements),
+                    ));
+                }
+            }
+        }
+
+        if let Some(bundle) = package_set.bundle() {
+            if let Some((installer, command)) = self
+                .installer_for(package_set.platform(), bundle.kind().clone())
+                .and_then(|installer| {
+                    installer
+                        .bundle_commands()
+                        .get(action)
+                        .map(|command| (installer, command))
+                })
+            {
+                let variable_replacements = add_bundle_action_vars(
+                    &package_set.bundle_file_path().unwrap(),
+                    &variable_replacements,
+                );
+                commands.push(AuditedCommand::new(
+                    &format!("bundle {} [{}]", bundle.file(), installer.name()),
+                    &command.with_vars(&variable_replacements),
+                ));
+            }
+        }
+
+        if let Some(cmd_str) = package_set
+            .scripts()
+            .and_then(|scripts| scripts.get(action))
+        {
+            commands.push(script(&format!("scripts.{}", action), cmd_str));
+        }
+
+        if let (hook_name, Some(cmd_str)) = last_hook {
+            commands.push(script(hook_name, cmd_str));
+        }
+        commands
     }
 
     /// Update all installers, at least all those that support update-self.
     pub fn update_self(&self) -> Result<()> {
-        debug!("InstallerRegistry::update_self");
+        let _ = self.update_installers()?;
+        reportln!("Done.");
+        Ok(())
+    }
+
+    /// Update all installers that support update-self, returning the number of installers
+    /// updated. Unlike `update_self` this does not report completion.
+    pub fn update_installers(&self) -> Result<u32> {
+        debug!("InstallerRegistry::update_installers");
 
+        let mut updated = 0;
         for installer in self.installers() {
             if installer.is_platform_match() && installer.has_update_self() {
+                installer.check_run_as()?;
                 reportln!("Updating installer {}", installer.name);
-                let cmd_str = installer.update_self().as_ref().unwrap();
+                let command = installer.update_self().as_ref().unwrap();
                 let variable_replacements =
-                    add_action_vars(&InstallActionKind::Update, &default_vars());
-                execute_shell_command(cmd_str, &variable_replacements)?;
+                    add_action_vars(&InstallActionKind::Update, &self.environment);
+                let _ = command.execute(&variable_replacements)?;
+                updated += 1;
             }
         }
-        reportln!("Done.");
-        Ok(())
+        Ok(updated)
     }
 
-    /// Execute the `action`, against some package set (or all), in some package set group (or all)
-    /// in the provided repository.
+    /// Execute the `action`, against the selected package sets in the provided repository, as
+    /// modified by `options`.
     pub fn execute(
         &self,
         action: &InstallActionKind,
         repository: &PackageRepository,
-        package_set_group_name: &Option<Name>,
-        package_set_name: &Option<Name>,
-    ) -> Result<()> {
+        selection: &PackageSetSelection,
+        options: &ExecuteOptions,
+    ) -> Result<ExecuteSummary> {
+        let mut log_db = open_install_log(self.scope())?;
+        let result = self.execute_logged(action, repository, selection, options, log_db.as_mut());
+        self.last_summary().steps().report();
+        result
+    }
+
+    /// Execute the `action`, as `execute` does, recording installed packages in the provided log.
+    /// Each package set is reported as a step; unlike `execute` this does not report the summary
+    /// of steps, although it is included in the returned summary.
+    pub fn execute_logged(
+        &self,
+        action: &InstallActionKind,
+        repository: &PackageRepository,
+        selection: &PackageSetSelection,
+        options: &ExecuteOptions,
+        log_db: &mut dyn InstallLog,
+    ) -> Result<ExecuteSummary> {
         debug!(
-            "InstallerRegistry::execute (.., {}, {:?}, {:?})",
-            &action, &package_set_group_name, &package_set_name
+            "InstallerRegistry::execute_logged (.., {}, {:?}, {:?})",
+            &action, &selection, options
         );
-        let mut log_db = PackageLog::open()?;
-        if let Some(package_set_group_name) = package_set_group_name {
-            if let Some(package_set_group) = repository.group(package_set_group_name) {
-                self.execute_package_set_group(
-                    action,
+        let mut summary = ExecuteSummary::default();
+        match selection.check_matched(repository) {
+            Err(e) if options.is_ignore_missing() => {
+                ereportln!("Warning: {}", e);
+                summary.warnings.push(e.to_string());
+            }
+            result => result?,
+        }
+        install_interrupt_handler();
+        log_db.begin_run(action)?;
+        let checkpoint = if options.is_resume() {
+            match log_db.resume_run(action)? {
+                Some(checkpoint) => {
+                    reportln!("Resuming the previous {} run", action);
+                    checkpoint
+                }
+                None => {
+                    reportln!(
+                        "The previous {} run did not fail, nothing to resume",
+                        action
+                    );
+                    RunCheckpoint::default()
+                }
+            }
+        } else {
+            RunCheckpoint::default()
+        };
+        let _ = self.checkpoint.replace(checkpoint);
+        let steps = Steps::new(&action.to_string());
+        let result = repository.groups().try_for_each(|package_set_group| {
+            self.execute_package_set_group(
+                action,
+                package_set_group,
+                selection,
+                options,
+                log_db,
+                &steps,
+                &mut summary,
+            )
+        });
+        summary.steps = steps.summary();
+        let _ = self.last_summary.replace(summary.clone());
+        log_db.end_run(result.is_ok())?;
+        result.map(|_| summary)
+    }
+
+    /// Return the summary of the most recent execution, this is available whether or not the
+    /// execution succeeded, and so includes the package set that failed.
+    pub fn last_summary(&self) -> ExecuteSummary {
+        self.last_summary.borrow().clone()
+    }
+
+    /// Uninstall the packages of the package set that the install log records as installed on
+    /// this machine, since it was last uninstalled, and remove the links to its files; this is
+    /// used before the package set is removed from the repository. If the package set is still
+    /// applied its uninstall script, and hooks, are run in the same order as an uninstall. The
+    /// uninstall is recorded in the log, and the names of the packages uninstalled are returned.
+    pub fn retire_package_set(
+        &self,
+        package_set_group: &PackageSetGroup,
+        package_set: &PackageSet,
+        log_db: &mut dyn InstallLog,
+    ) -> Result<Vec<Name>> {
+        let group_name = package_set_group.name();
+        reportln!(
+            "Retiring package-set {} (in group {})",
+            package_set.name(),
+            group_name
+        );
+        let machine = log_db.machine().to_string();
+        let applied: Vec<AppliedPackageSet> = log_db
+            .applied_package_sets()?
+            .into_iter()
+            .filter(|applied| {
+                applied.package_set_group_name() == &group_name
+                    && applied.package_set_name() == package_set.name()
+                    && applied.machine().is_none_or(|found| found == &machine)
+            })
+            .collect();
+        let uninstalled_at = applied
+            .iter()
+            .find(|applied| applied.action() == InstallActionKind::Uninstall.to_string())
+            .and_then(|applied| *applied.date_time());
+        let installed: Vec<Name> = log_db
+            .installed_packages(
+                &PackageLogQuery::default()
+                    .with_package_set_group(group_name.clone())
+                    .with_package_set(package_set.name().clone())
+                    .with_machine(&machine),
+            )?
+            .into_iter()
+            .filter(|installed| match (uninstalled_at, installed.date_time()) {
+                (Some(uninstalled_at), Some(date_time)) => *date_time > uninstalled_at,
+                _ => true,
+            })
+            .map(|installed| installed.package_name().clone())
+            .collect();
+
+        // The scripts are only run if the package set is still applied, or its packages are still
+        // installed, on this machine.
+        let is_applied = applied
+            .first()
+            .is_some_and(|applied| is_applied_action(applied.action()))
+            || !installed.is_empty();
+        if is_applied
+            && ScriptTrust::configured(self.scope()) == ScriptTrust::Prompt
+            && (package_set.run_before().is_some()
+                || package_set.run_after().is_some()
+                || package_set.scripts().is_some())
+        {
+            ScriptApprovals::open(self.scope())?.check(&group_name, package_set)?;
+        }
+
+        let mut uninstalled: Vec<Name> = Default::default();
+        let variable_replacements = package_set_vars(
+            &self.environment,
+            &InstallActionKind::Uninstall,
+            package_set,
+        );
+        let output_prefix = format!("{}/{}", group_name, package_set.name());
+        if is_applied {
+            if let Some(cmd_str) = package_set.run_after() {
+                check_interrupted()?;
+                trace!("executing `run_after` script");
+                let _ = execute_script(cmd_str, &variable_replacements, &output_prefix)?;
+            }
+        }
+        if let Some(packages) = package_set.packages() {
+            for package in packages.filter(|package| installed.contains(package.name())) {
+                check_interrupted()?;
+                self.rollback_package(
                     package_set_group,
-                    package_set_name,
-                    &mut log_db,
+                    package_set,
+                    package,
+                    &variable_replacements,
+                    log_db,
                 )?;
-            } else {
-                warn!(
-                    "No package set group found named {:?}",
-                    package_set_group_name
-                )
+                uninstalled.push(package.name().clone());
             }
-        } else {
-            trace!("executing for all package groups in repository");
-            for package_set_group in repository.groups() {
-                self.execute_package_set_group(
+        }
+        if uninstalled.is_empty() && package_set.packages().is_some() {
+            reportln!("* no packages recorded as installed on this machine");
+        }
+        if is_applied {
+            if let Some(cmd_str) = package_set
+                .scripts()
+                .and_then(|scripts| scripts.get(&InstallActionKind::Uninstall))
+            {
+                check_interrupted()?;
+                trace!("executing {:?} script", InstallActionKind::Uninstall);
+                let _ = execute_script(cmd_str, &variable_replacements, &output_prefix)?;
+            }
+        }
+
+        let links: Vec<PathBuf> = package_set
+            .env_file_paths()
+            .iter()
+            .map(|original| env_file_link(package_set, original))
+            .chain(
+                package_set
+                    .link_file_paths()
+                    .into_iter()
+                    .map(|(link, _)| link),
+            )
+            .filter(|link| {
+                link.symlink_metadata()
+                    .map(|metadata| metadata.file_type().is_symlink())
+                    .unwrap_or_default()
+            })
+            .collect();
+        self.remove_links(&links, "the package set is retired");
+
+        if is_applied {
+            if let Some(cmd_str) = package_set.run_before() {
+                check_interrupted()?;
+                trace!("executing `run_before` script");
+                let _ = execute_script(cmd_str, &variable_replacements, &output_prefix)?;
+            }
+        }
+
+        for record in take_command_records() {
+            log_db.log_command(&record)?;
+        }
+        log_db.log_applied_package_set(
+            &group_name,
+            package_set.name(),
+            &InstallActionKind::Uninstall,
+            &package_set.content_hash()?,
+        )?;
+        Ok(uninstalled)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn execute_package_set_group(
+        &self,
+        action: &InstallActionKind,
+        package_set_group: &PackageSetGroup,
+        selection: &PackageSetSelection,
+        options: &ExecuteOptions,
+        log_db: &mut dyn InstallLog,
+        steps: &Steps,
+        summary: &mut ExecuteSummary,
+    ) -> Result<()> {
+        debug!(
+            "Installer::execute_package_set_group ({}, {:?}, {:?})",
+            action,
+            package_set_group.name(),
+            selection,
+        );
+        let group_name = package_set_group.name();
+        let package_sets: Vec<&PackageSet> = package_set_group
+            .package_sets()
+            .filter(|package_set| selection.is_selected(&group_name, package_set))
+            .collect();
+        for package_set in package_sets {
+            let qualified_name = format!("{}/{}", group_name, package_set.name());
+            let step_name = format!("{} package-set {}", action, qualified_name);
+            let content_hash = package_set.content_hash()?;
+            if options.is_changed_only()
+                && log_db.last_applied_hash(&package_set_group.name(), package_set.name())?
+                    == Some(content_hash.clone())
+            {
+                steps.skip(&step_name, "unchanged since last applied");
+                continue;
+            }
+            check_interrupted()?;
+            if self
+                .checkpoint
+                .borrow()
+                .is_package_set_complete(&group_name, package_set.name())
+            {
+                steps.skip(&step_name, "completed in the previous run");
+                continue;
+            }
+            steps.step(&step_name);
+            report_group_start(&format!("{} {}", action, qualified_name));
+            let result = self.execute_package_set(
+                action,
+                package_set_group,
+                package_set,
+                options,
+                log_db,
+                summary,
+            );
+            report_group_end();
+            // Commands are recorded whether or not the package set succeeded.
+            for record in take_command_records() {
+                log_db.log_command(&record)?;
+            }
+            if is_interrupted() {
+                log_db.log_interrupted_package_set(
+                    &package_set_group.name(),
+                    package_set.name(),
                     action,
-                    package_set_group,
-                    package_set_name,
-                    &mut log_db,
                 )?;
             }
+            if let Err(e) = &result {
+                steps.fail(e);
+                report_failure(
+                    &format!("{} failed on package-set {}", action, qualified_name),
+                    &e.to_string(),
+                );
+                summary.failed.push(qualified_name);
+                return result;
+            }
+            log_db.log_run_progress(&group_name, package_set.name(), None)?;
+            log_db.log_applied_package_set(
+                &package_set_group.name(),
+                package_set.name(),
+                action,
+                &content_hash,
+            )?;
+            summary.succeeded.push(qualified_name);
+        }
+        Ok(())
+    }
+
+    fn execute_package_set(
+        &self,
+        action: &InstallActionKind,
+        package_set_group: &PackageSetGroup,
+        package_set: &PackageSet,
+        options: &ExecuteOptions,
+        log_db: &mut dyn InstallLog,
+        summary: &mut ExecuteSummary,
+    ) -> Result<()> {
+        let mut changes = PackageSetChanges::default();
+        let result = self.execute_package_set_steps(
+            action,
+            package_set_group,
+            package_set,
+            options,
+            log_db,
+            summary,
+            &mut changes,
+        );
+        if result.is_err() {
+            if is_interrupted() {
+                // Remove the links created for this package set, so it is not left half-linked.
+                self.remove_links(&changes.links, "the package set was interrupted");
+            } else if options.is_transactional() && *action == InstallActionKind::Install {
+                self.rollback_package_set(package_set_group, package_set, &changes, log_db);
+            }
+        }
+        result
+    }
+
+    // Undo the changes made by a failed install of the package set; either by running its
+    // rollback script, or by uninstalling each package installed. Failures are reported, but do
+    // not stop the rollback. Each package uninstalled is recorded in the log and, if the rollback
+    // succeeded, so is the uninstall of the package set.
+    fn rollback_package_set(
+        &self,
+        package_set_group: &PackageSetGroup,
+        package_set: &PackageSet,
+        changes: &PackageSetChanges,
+        log_db: &mut dyn InstallLog,
+    ) {
+        reportln!(
+            "Rolling back install on package-set {} (in group {})",
+            package_set.name(),
+            package_set_group.name()
+        );
+        self.remove_links(&changes.links, "rolling back the package set");
+        let variable_replacements = package_set_vars(
+            &self.environment,
+            &InstallActionKind::Uninstall,
+            package_set,
+        );
+        let output_prefix = format!("{}/{}", package_set_group.name(), package_set.name());
+        let mut rolled_back = true;
+        if let Some(cmd_str) = package_set.rollback() {
+            trace!("executing `rollback` script");
+            if let Err(e) = execute_script(cmd_str, &variable_replacements, &output_prefix) {
+                ereportln!("* the rollback script failed: {}", e);
+                rolled_back = false;
+            }
+        } else {
+            for package_name in changes.packages.iter().rev() {
+                let package = package_set
+                    .packages()
+                    .and_then(|mut packages| packages.find(|p| p.name() == package_name));
+                if let Some(package) = package {
+                    if let Err(e) = self.rollback_package(
+                        package_set_group,
+                        package_set,
+                        package,
+                        &variable_replacements,
+                        log_db,
+                    ) {
+                        ereportln!("* could not uninstall package {}: {}", package_name, e);
+                        rolled_back = false;
+                    }
+                }
+            }
+        }
+        if rolled_back {
+            if let Err(e) = package_set.content_hash().and_then(|content_hash| {
+                log_db.log_applied_package_set(
+                    &package_set_group.name(),
+                    package_set.name(),
+                    &InstallActionKind::Uninstall,
+                    &content_hash,
+                )
+            }) {
+                warn!("could not log uninstall of rolled back package set: {}", e);
+            }
+        }
+        if let Err(e) = log_db.clear_run_progress(&package_set_group.name(), package_set.name()) {
+            warn!(
+                "could not clear run progress for rolled back package set: {}",
+                e
+            );
+        }
+    }
+
+    // Uninstall a single package, outside of the usual execution of an action, recording the
+    // uninstall in the log.
+    fn rollback_package(
+        &self,
+        package_set_group: &PackageSetGroup,
+        package_set: &PackageSet,
+        package: &Package,
+        variable_replacements: &HashMap<String, String>,
+        log_db: &mut dyn InstallLog,
+    ) -> Result<()> {
+        let action = InstallActionKind::Uninstall;
+        let variable_replacements = add_package_action_vars(package, variable_replacements);
+        let (installer_name, output) = if let Some(download) = package.download() {
+            (
+                Name::from_str(DOWNLOAD_INSTALLER)?,
+                download.execute(&action, package, &variable_replacements)?,
+            )
+        } else {
+            self.rollback_package_action(
+                package_set_group,
+                package_set,
+                package,
+                &variable_replacements,
+            )?
+        };
+        let uninstalled = InstalledPackage::new(
+            package_set_group.name(),
+            package_set.name().clone(),
+            package.name().clone(),
+            installer_name,
+        );
+        log_db.log_installed_package(&match output {
+            None => uninstalled,
+            Some(output) => uninstalled.with_command_output(&output),
+        })
+    }
+
+    #[cfg_attr(not(feature = "github-releases"), allow(unused_variables))]
+    fn rollback_package_action(
+        &self,
+        package_set_group: &PackageSetGroup,
+        package_set: &PackageSet,
+        package: &Package,
+        variable_replacements: &HashMap<String, String>,
+    ) -> Result<(Name, Option<CommandOutput>)> {
+        let action = InstallActionKind::Uninstall;
+        #[cfg(feature = "github-releases")]
+        if let Some(github) = package.github() {
+            return Ok((
+                Name::from_str(GITHUB_INSTALLER)?,
+                github.execute(
+                    &action,
+                    &package_set_group.name(),
+                    package_set.name(),
+                    package,
+                    variable_replacements,
+                )?,
+            ));
+        }
+        match self.installer_for(package.platform(), package.kind().clone()) {
+            None => Err(Error::NoInstallerForKind(package.kind().clone())),
+            Some(installer) => Ok((
+                installer.name().clone(),
+                installer.package_action(
+                    &action,
+                    package,
+                    variable_replacements,
+                    &format!(
+                        "{}/{}/{}",
+                        package_set_group.name(),
+                        package_set.name(),
+                        package.name()
+                    ),
+                )?,
+            )),
         }
-        reportln!("Done.");
-        Ok(())
     }
 
-    fn execute_package_set_group(
-        &self,
-        action: &InstallActionKind,
-        package_set_group: &PackageSetGroup,
-        package_set_name: &Option<Name>,
-        log_db: &mut PackageLog,
-    ) -> Result<()> {
-        debug!(
-            "Installer::execute_package_set_group ({}, {:?}, {:?})",
-            action,
-            package_set_group.name(),
-            package_set_name,
-        );
-        if let Some(package_set_name) = package_set_name {
-            if let Some(package_set) = package_set_group.package_set(package_set_name) {
-                self.execute_package_set(action, package_set_group, package_set, log_db)?;
-            } else {
-                warn!("No package set found named {:?}", package_set_name)
+    // Return the prerequisites of the package set, with the installer that adds each, in installer
+    // name order. Installers not available on this platform are skipped, as a package set may
+    // require, for example, both a Homebrew tap and an apt repository.
+    fn package_set_prerequisites<'a>(
+        &'a self,
+        package_set: &'a PackageSet,
+    ) -> Vec<(&'a Installer, &'a str)> {
+        let mut requires: Vec<(&Name, &Vec<String>)> = package_set.requires().iter().collect();
+        requires.sort();
+        let mut prerequisites: Vec<(&Installer, &str)> = Default::default();
+        for (installer_name, installer_prerequisites) in requires {
+            match self.installer_named(&installer_name.to_string()) {
+                None => info!(
+                    "InstallerRegistry::package_set_prerequisites: no installer {} for this platform",
+                    installer_name
+                ),
+                Some(installer) => prerequisites.extend(
+                    installer_prerequisites
+                        .iter()
+                        .map(|prerequisite| (installer, prerequisite.as_str())),
+                ),
             }
-        } else {
-            trace!("executing for all package sets in group");
-            for package_set in package_set_group.package_sets() {
-                self.execute_package_set(action, package_set_group, &package_set, log_db)?;
+        }
+        prerequisites
+    }
+
+    fn remove_links(&self, links: &[PathBuf], reason: &str) {
+        for link in links.iter().rev() {
+            reportln!("* removing link {:?}, {}", link, reason);
+            if let Err(e) = self.unlink_file(link) {
+                warn!("could not remove link {:?}: {}", link, e);
             }
         }
-        Ok(())
     }
 
-    fn execute_package_set(
+    #[allow(clippy::too_many_arguments)]
+    fn execute_package_set_steps(
         &self,
         action: &InstallActionKind,
         package_set_group: &PackageSetGroup,
         package_set: &PackageSet,
-        log_db: &mut PackageLog,
+        options: &ExecuteOptions,
+        log_db: &mut dyn InstallLog,
+        summary: &mut ExecuteSummary,
+        changes: &mut PackageSetChanges,
     ) -> Result<()> {
-        reportln!(
-            "Performing {} on package-set {} (in group {})",
-            action,
-            package_set.name(),
-            package_set_group.name()
-        );
+        if ScriptTrust::configured(self.scope()) == ScriptTrust::Prompt {
+            ScriptApprovals::open(self.scope())?.check(&package_set_group.name(), package_set)?;
+        }
+        if !package_set.prompt_vars().is_empty() {
+            PromptAnswers::open(self.scope())?.ask_for(package_set)?;
+        }
+        let mut variable_replacements = package_set_vars(&self.environment, action, package_set);
 
-        let mut variable_replacements =
-            add_package_set_action_vars(package_set, &add_action_vars(action, &default_vars()));
+        let output_prefix = format!("{}/{}", package_set_group.name(), package_set.name());
+
+        // On uninstall the hooks are reversed, so that tearing down mirrors the install order.
+        let (first_hook, last_hook) = if *action == InstallActionKind::Uninstall {
+            (
+                ("run_after", package_set.run_after()),
+                ("run_before", package_set.run_before()),
+            )
+        } else {
+            (
+                ("run_before", package_set.run_before()),
+                ("run_after", package_set.run_after()),
+            )
+        };
 
-        variable_replacements.extend(package_set.env_vars().clone());
+        if let (hook_name, Some(cmd_str)) = first_hook {
+            trace!("executing `{}` script", hook_name);
+            let _ = execute_script(cmd_str, &variable_replacements, &output_prefix)?;
+        }
 
-        if let Some(cmd_str) = package_set.run_before() {
-            trace!("executing `run_before` script");
-            execute_shell_command(cmd_str, &variable_replacements)?;
+        if adds_prerequisites(action) {
+            trace!("adding all package set prerequisites");
+            for (installer, prerequisite) in self.package_set_prerequisites(package_set) {
+                check_interrupted()?;
+                let _ = installer.prerequisite_action(
+                    prerequisite,
+                    &add_prerequisite_vars(prerequisite, &variable_replacements),
+                    &format!("{}/requires", output_prefix),
+                )?;
+                log_db.log_added_prerequisite(
+                    &package_set_group.name(),
+                    package_set.name(),
+                    installer.name(),
+                    prerequisite,
+                )?;
+            }
         }
 
         if let Some(packages) = package_set.packages() {
             trace!("executing all package actions");
             for package in packages {
+                check_interrupted()?;
+                if self.checkpoint.borrow().is_package_complete(
+                    &package_set_group.name(),
+                    package_set.name(),
+                    package.name(),
+                ) {
+                    reportln!(
+                        "* skipping package {}, completed in the previous run",
+                        package.name()
+                    );
+                    continue;
+                }
+                if let Some(download) = package.download() {
+                    if package.is_platform_match() {
+                        let variable_replacements =
+                            add_package_action_vars(package, &variable_replacements);
+                        let output = download.execute(action, package, &variable_replacements)?;
+                        let installed = InstalledPackage::new(
+                            package_set_group.name(),
+                            package_set.name().clone(),
+                            package.name().clone(),
+                            Name::from_str(DOWNLOAD_INSTALLER)?,
+                        );
+                        log_db.log_installed_package(&match output {
+                            None => installed,
+                            Some(output) => installed.with_command_output(&output),
+                        })?;
+                        summary.packages += 1;
+                        log_db.log_run_progress(
+                            &package_set_group.name(),
+                            package_set.name(),
+                            Some(package.name()),
+                        )?;
+                        changes.packages.push(package.name().clone());
+                    }
+                    continue;
+                }
+                #[cfg(feature = "github-releases")]
+                if let Some(github) = package.github() {
+                    if package.is_platform_match() {
+                        let variable_replacements =
+                            add_package_action_vars(package, &variable_replacements);
+                        let output = github.execute(
+                            action,
+                            &package_set_group.name(),
+                            package_set.name(),
+                            package,
+                            &variable_replacements,
+                        )?;
+                        let installed = InstalledPackage::new(
+                            package_set_group.name(),
+                            package_set.name().clone(),
+                            package.name().clone(),
+                            Name::from_str(GITHUB_INSTALLER)?,
+                        );
+                        log_db.log_installed_package(&match output {
+                            None => installed,
+                            Some(output) => installed.with_command_output(&output),
+                        })?;
+                        summary.packages += 1;
+                        log_db.log_run_progress(
+                            &package_set_group.name(),
+                            package_set.name(),
+                            Some(package.name()),
+                        )?;
+                        changes.packages.push(package.name().clone());
+                    }
+                    continue;
+                }
                 match self.installer_for(package.platform(), package.kind().clone()) {
-                    None => {
-                        return Err(ErrorKind::NoInstallerForKind(package.kind().clone()).into())
+                    None => return Err(Error::NoInstallerForKind(package.kind().clone())),
+                    Some(installer) if self.is_already_actioned(action, installer, package) => {
+                        reportln!(
+                            "* skipping {} on {} package {}, nothing to do",
+                            action,
+                            installer.name(),
+                            package.name()
+                        );
                     }
                     Some(installer) => {
                         let variable_replacements =
                             add_package_action_vars(package, &variable_replacements);
-                        installer.package_action(action, package, &variable_replacements)?;
-                        log_db.log_installed_package(&InstalledPackage::new(
+                        let output_prefix = format!("{}/{}", output_prefix, package.name());
+                        let output = match self.update_to(
+                            action,
+                            installer,
+                            &package_set_group.name(),
+                            package_set,
+                            package,
+                            &variable_replacements,
+                        ) {
+                            UpdateTo::Latest => installer.package_action(
+                                action,
+                                package,
+                                &variable_replacements,
+                                &output_prefix,
+                            )?,
+                            UpdateTo::Version(version) => installer.package_version_action(
+                                action,
+                                package,
+                                &version,
+                                &variable_replacements,
+                                &output_prefix,
+                            )?,
+                            UpdateTo::HeldBack(reason) => {
+                                reportln!(
+                                    "* skipping {} on {} package {}, {}",
+                                    action,
+                                    installer.name(),
+                                    package.name(),
+                                    reason
+                                );
+                                continue;
+                            }
+                        };
+                        let installed = InstalledPackage::new(
                             package_set_group.name(),
                             package_set.name().clone(),
                             package.name().clone(),
                             installer.name().clone(),
-                        ))?;
+                        );
+                        log_db.log_installed_package(&match output {
+                            None => installed,
+                            Some(output) => installed.with_command_output(&output),
+                        })?;
+                        summary.packages += 1;
+                        log_db.log_run_progress(
+                            &package_set_group.name(),
+                            package_set.name(),
+                            Some(package.name()),
+                        )?;
+                        changes.packages.push(package.name().clone());
                     }
                 }
             }
         }
 
+        if let Some(bundle) = package_set.bundle() {
+            trace!("executing bundle action");
+            match self.installer_for(package_set.platform(), bundle.kind().clone()) {
+                None => return Err(Error::NoInstallerForKind(bundle.kind().clone())),
+                Some(installer) => {
+                    let variable_replacements = add_bundle_action_vars(
+                        &package_set.bundle_file_path().unwrap(),
+                        &variable_replacements,
+                    );
+                    let output = installer.bundle_action(
+                        action,
+                        bundle,
+                        &variable_replacements,
+                        &format!("{}/{}", output_prefix, bundle.file()),
+                    )?;
+                    let installed = InstalledPackage::new(
+                        package_set_group.name(),
+                        package_set.name().clone(),
+                        bundle.name()?,
+                        installer.name().clone(),
+                    );
+                    log_db.log_installed_package(&match output {
+                        None => installed,
+                        Some(output) => installed.with_command_output(&output),
+                    })?;
+                    summary.packages += 1;
+                }
+            }
+        }
+
         if let Some(scripts) = package_set.scripts() {
             trace!("executing scripts? {:?}", scripts);
             if let Some(cmd_str) = scripts.get(action) {
                 trace!("executing {:?} script", action);
-                execute_shell_command(cmd_str, &variable_replacements)?;
+                let _ = execute_script(cmd_str, &variable_replacements, &output_prefix)?;
             }
         }
 
         trace!("executing all env-file actions");
-        if let Some(original) = package_set.env_file_path() {
-            let link = package_set
-                .path()
-                .parent()
-                .unwrap()
-                .join(package_set.name().as_path())
-                .join(original.file_name().unwrap());
+        for original in package_set.env_file_paths() {
+            let link = env_file_link(package_set, &original);
             match action {
                 InstallActionKind::Install => {
-                    self.link_file(&link, &original)?;
+                    check_interrupted()?;
+                    self.link_file(&link, &original, package_set.has_relative_links())?;
+                    changes.links.push(link);
                 }
-                InstallActionKind::Update => {
+                InstallActionKind::Update if options.is_unlink_only() => {
                     self.unlink_file(&link)?;
                 }
+                InstallActionKind::Update => {
+                    check_interrupted()?;
+                    self.relink_file(&link, &original, package_set.has_relative_links())?;
+                }
                 _ => {}
             };
         }
@@ -420,27 +1980,266 @@ impl InstallerRegistry {
         for (link, original) in package_set.link_file_paths() {
             match action {
                 InstallActionKind::Install => {
-                    self.link_file(&link, &original)?;
+                    check_interrupted()?;
+                    self.link_file(&link, &original, package_set.has_relative_links())?;
+                    changes.links.push(link);
                 }
-                InstallActionKind::Update => {
+                InstallActionKind::Update if options.is_unlink_only() => {
                     self.unlink_file(&link)?;
                 }
+                InstallActionKind::Update => {
+                    check_interrupted()?;
+                    self.relink_file(&link, &original, package_set.has_relative_links())?;
+                }
                 _ => {}
             };
         }
 
-        if let Some(cmd_str) = package_set.run_after() {
+        if let (hook_name, Some(cmd_str)) = last_hook {
+            check_interrupted()?;
             let _ = variable_replacements.remove("package_name");
-            trace!("executing `run_after` script");
-            execute_shell_command(cmd_str, &variable_replacements)?;
+            trace!("executing `{}` script", hook_name);
+            let _ = execute_script(cmd_str, &variable_replacements, &output_prefix)?;
         }
 
+        summary.package_sets += 1;
         Ok(())
     }
 
-    fn link_file(&self, link: &PathBuf, original: &PathBuf) -> Result<()> {
-        debug!("InstallerRegistry::link_file ({:?}, {:?})", link, original);
-        std::os::unix::fs::symlink(original, link)?;
+    /// Return the name of the installer used for the package, this may be one of the built-in
+    /// installers, or `None` if no installer is available.
+    pub fn installer_name_for(&self, package: &Package) -> Option<Name> {
+        if package.download().is_some() {
+            return Name::from_str(DOWNLOAD_INSTALLER).ok();
+        }
+        #[cfg(feature = "github-releases")]
+        if package.github().is_some() {
+            return Name::from_str(GITHUB_INSTALLER).ok();
+        }
+        self.installer_for(package.platform(), package.kind().clone())
+            .map(|installer| installer.name().clone())
+    }
+
+    /// Return the status of the installed version of the package, checked against any version
+    /// constraint on the package.
+    pub fn package_status(
+        &self,
+        group: &Name,
+        package_set: &PackageSet,
+        package: &Package,
+    ) -> VersionStatus {
+        self.installed_version(group, package_set, package)
+            .constrained_by(package.version())
+    }
+
+    /// Return the latest version of the package available from it's installer, if the installer
+    /// is able to report it.
+    pub fn available_version(
+        &self,
+        package_set: &PackageSet,
+        package: &Package,
+    ) -> Option<Version> {
+        match self.installer_for(package.platform(), package.kind().clone()) {
+            Some(installer) => {
+                let variable_replacements = package_vars(&self.environment, package_set, package);
+                self.latest_version_for(installer, &variable_replacements)
+            }
+            None => None,
+        }
+    }
+
+    #[cfg_attr(not(feature = "github-releases"), allow(unused_variables))]
+    fn installed_version(
+        &self,
+        group: &Name,
+        package_set: &PackageSet,
+        package: &Package,
+    ) -> VersionStatus {
+        if let Some(download) = package.download() {
+            let variable_replacements = package_vars(&self.environment, package_set, package);
+            return if download
+                .installed_path(package, &variable_replacements)
+                .is_file()
+            {
+                download
+                    .version()
+                    .as_ref()
+                    .and_then(|v| parse_version(v))
+                    .map(VersionStatus::Satisfied)
+                    .unwrap_or(VersionStatus::Unknown)
+            } else {
+                VersionStatus::NotInstalled
+            };
+        }
+        #[cfg(feature = "github-releases")]
+        if package.github().is_some() {
+            return match LockFile::open(self.scope()) {
+                Ok(lock_file) => lock_file
+                    .package(group, package_set.name(), package.name())
+                    .map(|locked| {
+                        parse_version(locked.version())
+                            .map(VersionStatus::Satisfied)
+                            .unwrap_or(VersionStatus::Unknown)
+                    })
+                    .unwrap_or(VersionStatus::NotInstalled),
+                Err(_) => VersionStatus::Unknown,
+            };
+        }
+        match self.installer_for(package.platform(), package.kind().clone()) {
+            None => VersionStatus::Unknown,
+            Some(installer) if is_cargo_installer(installer) => {
+                if !self.installed_crates_cache() {
+                    return VersionStatus::Unknown;
+                }
+                match self
+                    .installed_crates
+                    .borrow()
+                    .as_ref()
+                    .unwrap()
+                    .version(package.name())
+                {
+                    None => VersionStatus::NotInstalled,
+                    Some(version) => parse_version(version)
+                        .map(VersionStatus::Satisfied)
+                        .unwrap_or(VersionStatus::Unknown),
+                }
+            }
+            Some(installer) => match installer.installed_version() {
+                None => VersionStatus::Unknown,
+                Some(command) => {
+                    let variable_replacements =
+                        package_vars(&self.environment, package_set, package);
+                    match command.execute_output(&variable_replacements) {
+                        Ok(output) => parse_version(&output)
+                            .map(VersionStatus::Satisfied)
+                            .unwrap_or(VersionStatus::Unknown),
+                        Err(_) => VersionStatus::NotInstalled,
+                    }
+                }
+            },
+        }
+    }
+
+    fn latest_version_for(
+        &self,
+        installer: &Installer,
+        variable_replacements: &HashMap<String, String>,
+    ) -> Option<Version> {
+        installer.latest_version().as_ref().and_then(|command| {
+            command
+                .execute_output(variable_replacements)
+                .ok()
+                .and_then(|output| parse_version(&output))
+        })
+    }
+
+    // Determine how to update a package with a version constraint. If the latest version
+    // satisfies the constraint the package is updated as usual, otherwise it is updated to the
+    // highest available version that does, if the installer can list and install versions. A
+    // package already at that version, or one whose versions cannot be determined, is held back.
+    fn update_to(
+        &self,
+        action: &InstallActionKind,
+        installer: &Installer,
+        group: &Name,
+        package_set: &PackageSet,
+        package: &Package,
+        variable_replacements: &HashMap<String, String>,
+    ) -> UpdateTo {
+        let constraint = match (action, package.version()) {
+            (InstallActionKind::Update, Some(constraint)) => constraint,
+            _ => return UpdateTo::Latest,
+        };
+        let latest = self.latest_version_for(installer, variable_replacements);
+        if let Some(latest) = &latest {
+            if constraint.matches(latest) {
+                return UpdateTo::Latest;
+            }
+        }
+        let available = match (installer.available_versions(), installer.install_version()) {
+            (Some(command), Some(_)) => command
+                .execute_output(variable_replacements)
+                .map(|output| parse_versions(&output))
+                .unwrap_or_default(),
+            _ => Default::default(),
+        };
+        match constraint.highest_match(&available) {
+            Some(highest) => match self.installed_version(group, package_set, package) {
+                VersionStatus::Satisfied(installed) | VersionStatus::Violation(installed)
+                    if installed >= *highest =>
+                {
+                    UpdateTo::HeldBack(format!(
+                        "version {} is the highest that satisfies '{}'",
+                        installed, constraint
+                    ))
+                }
+                _ => UpdateTo::Version(highest.clone()),
+            },
+            None => UpdateTo::HeldBack(match (latest, installer.latest_version()) {
+                (Some(latest), _) => format!(
+                    "the latest version, {}, does not satisfy '{}'",
+                    latest, constraint
+                ),
+                (None, None) => format!(
+                    "the installer cannot report the latest version to check '{}'",
+                    constraint
+                ),
+                (None, Some(_)) => format!(
+                    "the latest version could not be determined to check '{}'",
+                    constraint
+                ),
+            }),
+        }
+    }
+
+    fn installed_crates_cache(&self) -> bool {
+        let mut installed_crates = self.installed_crates.borrow_mut();
+        if installed_crates.is_none() {
+            match InstalledCrates::fetch() {
+                Ok(fetched) => *installed_crates = Some(fetched),
+                Err(e) => {
+                    warn!("Could not determine installed crates, error: {}", e);
+                    return false;
+                }
+            }
+        }
+        true
+    }
+
+    fn is_already_actioned(
+        &self,
+        action: &InstallActionKind,
+        installer: &Installer,
+        package: &Package,
+    ) -> bool {
+        if !is_cargo_installer(installer) || !self.installed_crates_cache() {
+            return false;
+        }
+        let is_installed = self
+            .installed_crates
+            .borrow()
+            .as_ref()
+            .unwrap()
+            .is_installed(package.name());
+        match action {
+            InstallActionKind::Install => is_installed,
+            InstallActionKind::Uninstall => !is_installed,
+            _ => false,
+        }
+    }
+
+    // Create the link to `original`; if `relative` the link's target is expressed relative to the
+    // directory containing the link, so that it survives the tree being mounted elsewhere.
+    fn link_file(&self, link: &PathBuf, original: &PathBuf, relative: bool) -> Result<()> {
+        debug!(
+            "InstallerRegistry::link_file ({:?}, {:?}, {})",
+            link, original, relative
+        );
+        let target = match link.parent() {
+            Some(link_dir) if relative => dirs::relative_path(link_dir, original),
+            _ => original.clone(),
+        };
+        std::os::unix::fs::symlink(target, link)?;
         Ok(())
     }
 
@@ -449,6 +2248,237 @@ impl InstallerRegistry {
         std::fs::remove_file(link)?;
         Ok(())
     }
+
+    // Re-create the link so that it refers to `original`, any existing link is removed first; a
+    // missing link is not an error as it may have been removed by a previous update.
+    fn relink_file(&self, link: &PathBuf, original: &PathBuf, relative: bool) -> Result<()> {
+        debug!(
+            "InstallerRegistry::relink_file ({:?}, {:?}, {})",
+            link, original, relative
+        );
+        reportln!("* relinking {:?} to {:?}", link, original);
+        if link.symlink_metadata().is_ok() {
+            self.unlink_file(link)?;
+        }
+        self.link_file(link, original, relative)
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+
+impl ExecuteOptions {
+    /// Only act on package sets that have changed since they were last installed, or updated.
+    pub fn with_changed_only(self, changed_only: bool) -> Self {
+        Self {
+            changed_only,
+            ..self
+        }
+    }
+
+    /// If the selection names a group, or package set, not in the repository, report it and act on
+    /// the rest of the selection rather than failing.
+    pub fn with_ignore_missing(self, ignore_missing: bool) -> Self {
+        Self {
+            ignore_missing,
+            ..self
+        }
+    }
+
+    /// If the previous run of the action failed, skip the package sets and packages it completed.
+    pub fn with_resume(self, resume: bool) -> Self {
+        Self { resume, ..self }
+    }
+
+    /// If an install of a package set fails, undo the changes already made to it; see
+    /// `PackageSet::rollback`.
+    pub fn with_transactional(self, transactional: bool) -> Self {
+        Self {
+            transactional,
+            ..self
+        }
+    }
+
+    /// On update remove the links for env files and link files, but do not re-create them; this
+    /// was the behavior before links were re-created by update.
+    pub fn with_unlink_only(self, unlink_only: bool) -> Self {
+        Self {
+            unlink_only,
+            ..self
+        }
+    }
+
+    /// Return `true` if only changed package sets are acted on.
+    pub fn is_changed_only(&self) -> bool {
+        self.changed_only
+    }
+
+    /// Return `true` if names in the selection that are not in the repository are ignored.
+    pub fn is_ignore_missing(&self) -> bool {
+        self.ignore_missing
+    }
+
+    /// Return `true` if a failed previous run is resumed.
+    pub fn is_resume(&self) -> bool {
+        self.resume
+    }
+
+    /// Return `true` if failed package set installs are rolled back.
+    pub fn is_transactional(&self) -> bool {
+        self.transactional
+    }
+
+    /// Return `true` if update removes links without re-creating them.
+    pub fn is_unlink_only(&self) -> bool {
+        self.unlink_only
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+
+impl ExecuteSummary {
+    /// Return the number of package sets acted upon.
+    pub fn package_sets(&self) -> u32 {
+        self.package_sets
+    }
+
+    /// Return the number of packages acted upon.
+    pub fn packages(&self) -> u32 {
+        self.packages
+    }
+
+    /// Return the package sets acted upon successfully, as `group/package-set`.
+    pub fn succeeded(&self) -> &Vec<String> {
+        &self.succeeded
+    }
+
+    /// Return the package sets that failed, as `group/package-set`.
+    pub fn failed(&self) -> &Vec<String> {
+        &self.failed
+    }
+
+    /// Return the warnings reported while executing, such as groups or package sets that were
+    /// selected but not found.
+    pub fn warnings(&self) -> &Vec<String> {
+        &self.warnings
+    }
+
+    /// The summary of the steps reported, one for each package set.
+    pub fn steps(&self) -> &StepsSummary {
+        &self.steps
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+
+impl AuditedCommand {
+    fn new(source: &str, command: &str) -> Self {
+        Self {
+            source: source.to_string(),
+            command: command.to_string(),
+        }
+    }
+
+    /// Return where in the package set this command comes from; a script, such as `run-before`,
+    /// or a package, or bundle, and its installer.
+    pub fn source(&self) -> &String {
+        &self.source
+    }
+
+    /// Return the command, with variables replaced.
+    pub fn command(&self) -> &String {
+        &self.command
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+// Private Functions
+// ------------------------------------------------------------------------------------------------
+
+// Parse the content of a registry file; a registry that is simply a list of installers is read as
+// if it had been migrated to the current format, any other format must be the current one.
+fn parse_registry(registry_data: &str) -> Result<RegistryFile> {
+    match registry_version(registry_data)? {
+        LIST_REGISTRY_FORMAT_VERSION => Ok(RegistryFile {
+            version: REGISTRY_FORMAT_VERSION,
+            installers: serde_yaml::from_str(registry_data)?,
+        }),
+        version => {
+            check_format_version(MigrationTarget::Registry, version)?;
+            Ok(serde_yaml::from_str(registry_data)?)
+        }
+    }
+}
+
+// A shell word of the form `NAME=value`, which sets a variable for the program that follows.
+fn is_var_assignment(word: &str) -> bool {
+    match word.split_once('=') {
+        Some((name, _)) => {
+            !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+        }
+        None => false,
+    }
+}
+
+// The link created for one of the package set's environment files, in a directory named for the
+// package set next to the package set file.
+// Prerequisites are added before installing, or updating, packages; they are not removed on
+// uninstall as they may be shared with other package sets.
+fn adds_prerequisites(action: &InstallActionKind) -> bool {
+    matches!(
+        action,
+        InstallActionKind::Install | InstallActionKind::Update
+    )
+}
+
+fn env_file_link(package_set: &PackageSet, original: &Path) -> PathBuf {
+    package_set
+        .path()
+        .parent()
+        .unwrap()
+        .join(package_set.name().as_path())
+        .join(original.file_name().unwrap())
+}
+
+// Execute a package set script, reporting each line of output prefixed with `output_prefix`.
+fn execute_script(
+    script_string: &str,
+    variable_replacements: &HashMap<String, String>,
+    output_prefix: &str,
+) -> Result<CommandOutput> {
+    ShellCommandBuilder::script(script_string, variable_replacements)
+        .output_prefix(output_prefix)
+        .build()
+        .execute()
+}
+
+fn package_set_vars(
+    environment: &HashMap<String, String>,
+    action: &InstallActionKind,
+    package_set: &PackageSet,
+) -> HashMap<String, String> {
+    let mut variable_replacements = add_package_set_action_vars(
+        package_set,
+        &add_global_vars(&add_action_vars(action, environment)),
+    );
+    variable_replacements.extend(package_set.inherited_env_vars());
+    if !package_set.prompt_vars().is_empty() {
+        match PromptAnswers::open(vars_scope(environment)) {
+            Ok(answers) => variable_replacements.extend(answers.answers_for(package_set)),
+            Err(e) => ereportln!("* could not read answers to prompted variables: {}", e),
+        }
+    }
+    variable_replacements
+}
+
+fn package_vars(
+    environment: &HashMap<String, String>,
+    package_set: &PackageSet,
+    package: &Package,
+) -> HashMap<String, String> {
+    add_package_action_vars(
+        package,
+        &package_set_vars(environment, &InstallActionKind::Update, package_set),
+    )
 }
 
 // ------------------------------------------------------------------------------------------------
@@ -457,7 +2487,10 @@ impl InstallerRegistry {
 
 pub mod builders {
     use crate::shared::builders::Builder;
-    use crate::shared::{InstallActionKind, Installer, Name, PackageKind, Platform};
+    use crate::shared::{
+        IfExists, InstallActionKind, Installer, InstallerCommand, Name, PackageKind, Platform,
+        RunAs,
+    };
     use std::collections::HashMap;
     use std::path::PathBuf;
 
@@ -505,19 +2538,44 @@ pub mod builders {
                 kind: Default::default(),
                 if_exists: None,
                 commands: Default::default(),
+                bundle_commands: Default::default(),
+                installed_version: None,
+                latest_version: None,
+                available_versions: None,
+                install_version: None,
                 update_self: None,
+                list_installed: None,
+                add_prerequisite: None,
+                run_as: None,
+                enabled: None,
+                priority: None,
             })
         }
 
         /// Add a file or directory path that determines whether this installer is enabled.
         pub fn if_exists(&mut self, path: &str) -> &mut Self {
-            self.0.if_exists = Some(path.to_string());
+            self.0.if_exists = Some(path.into());
             self
         }
 
         /// Add a file or directory path that determines whether this installer is enabled.
         pub fn if_exists_path(&mut self, path: &PathBuf) -> &mut Self {
-            self.0.if_exists = Some(path.to_string_lossy().to_owned().to_string());
+            self.0.if_exists = Some(IfExists::Path(path.to_string_lossy().to_string()));
+            self
+        }
+
+        /// Add a command, that must succeed, that determines whether this installer is enabled.
+        pub fn if_exists_command(&mut self, command: &str) -> &mut Self {
+            self.0.if_exists = Some(IfExists::Command {
+                command: command.to_string(),
+            });
+            self
+        }
+
+        /// Add a check, or combination of checks, that determines whether this installer is
+        /// enabled.
+        pub fn if_exists_check(&mut self, if_exists: IfExists) -> &mut Self {
+            self.0.if_exists = Some(if_exists);
             self
         }
 
@@ -556,7 +2614,19 @@ pub mod builders {
 
         /// This is an installer for application packages.
         pub fn for_application_packages(&mut self) -> &mut Self {
-            self.of_kind(PackageKind::Application)
+            self.of_kind(PackageKind::Application(None))
+        }
+
+        /// This is an installer for application packages, distinguished from other application
+        /// installers by `name`.
+        pub fn for_named_application_packages(&mut self, name: &Name) -> &mut Self {
+            self.of_kind(PackageKind::Application(Some(name.clone())))
+        }
+
+        /// Set the priority of this installer, lower priorities are preferred.
+        pub fn priority(&mut self, priority: i32) -> &mut Self {
+            self.0.priority = Some(priority);
+            self
         }
 
         /// This is an installer for language packages.
@@ -571,13 +2641,25 @@ pub mod builders {
 
         /// Set the map of script strings, this is not additive.
         pub fn commands(&mut self, commands: HashMap<InstallActionKind, String>) -> &mut Self {
-            self.0.commands = commands;
+            self.0.commands = commands
+                .into_iter()
+                .map(|(kind, script_string)| (kind, script_string.into()))
+                .collect();
             self
         }
 
         /// Add a specific script string for the given command.
         pub fn add_command(&mut self, kind: InstallActionKind, script_string: &str) -> &mut Self {
-            let _ = self.0.commands.insert(kind, script_string.to_string());
+            let _ = self.0.commands.insert(kind, script_string.into());
+            self
+        }
+
+        /// Add a specific argument vector, executed without a shell, for the given command.
+        pub fn add_exec_command(&mut self, kind: InstallActionKind, args: &[&str]) -> &mut Self {
+            let _ = self.0.commands.insert(
+                kind,
+                InstallerCommand::Exec(args.iter().map(|arg| arg.to_string()).collect()),
+            );
             self
         }
 
@@ -601,9 +2683,67 @@ pub mod builders {
             self.add_command(InstallActionKind::LinkFiles, script_string)
         }
 
+        /// Add a specific script string for the given command, when acting on a bundle file.
+        pub fn add_bundle_command(
+            &mut self,
+            kind: InstallActionKind,
+            script_string: &str,
+        ) -> &mut Self {
+            let _ = self.0.bundle_commands.insert(kind, script_string.into());
+            self
+        }
+
+        /// Set the script string that reports the installed version of a package.
+        pub fn installed_version_command(&mut self, script_string: &str) -> &mut Self {
+            self.0.installed_version = Some(script_string.into());
+            self
+        }
+
+        /// Set the script string that reports the latest available version of a package.
+        pub fn latest_version_command(&mut self, script_string: &str) -> &mut Self {
+            self.0.latest_version = Some(script_string.into());
+            self
+        }
+
+        /// Set the script string that lists the versions of a package available to install.
+        pub fn available_versions_command(&mut self, script_string: &str) -> &mut Self {
+            self.0.available_versions = Some(script_string.into());
+            self
+        }
+
+        /// Set the script string that installs the version `package_version` of a package.
+        pub fn install_version_command(&mut self, script_string: &str) -> &mut Self {
+            self.0.install_version = Some(script_string.into());
+            self
+        }
+
         /// Add a specific script string for the update-self command.
         pub fn update_self_command(&mut self, script_string: &str) -> &mut Self {
-            self.0.update_self = Some(script_string.to_string());
+            self.0.update_self = Some(script_string.into());
+            self
+        }
+
+        /// Set the script string that lists the packages installed with this installer.
+        pub fn list_installed_command(&mut self, script_string: &str) -> &mut Self {
+            self.0.list_installed = Some(script_string.into());
+            self
+        }
+
+        /// Set the script string that adds a package set prerequisite with this installer.
+        pub fn add_prerequisite_command(&mut self, script_string: &str) -> &mut Self {
+            self.0.add_prerequisite = Some(script_string.into());
+            self
+        }
+
+        /// Set the user this installer must be run as.
+        pub fn run_as(&mut self, run_as: RunAs) -> &mut Self {
+            self.0.run_as = Some(run_as);
+            self
+        }
+
+        /// Disable this installer, it is kept in the registry file but not used.
+        pub fn disabled(&mut self) -> &mut Self {
+            self.0.enabled = Some(false);
             self
         }
     }
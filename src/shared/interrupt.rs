@@ -0,0 +1,115 @@
+/*!
+Handling of `SIGINT`, usually from Ctrl-C, while acting on package sets. Once the handler is
+installed an interrupt does not terminate the tool immediately, instead it is recorded and the
+command currently executing, along with its entire process group, is terminated. The tool can then
+remove partial changes, record the interruption in the install log, and exit with the code
+`INTERRUPTED_EXIT_CODE`. A second interrupt terminates the tool immediately.
+*/
+
+use crate::error::{Error, Result};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Once;
+
+// ------------------------------------------------------------------------------------------------
+// Public Types
+// ------------------------------------------------------------------------------------------------
+
+///
+/// The exit code used by the tool when it is interrupted, this follows the shell convention of
+/// `128` plus the signal number.
+///
+pub const INTERRUPTED_EXIT_CODE: i32 = 128 + libc::SIGINT;
+
+// ------------------------------------------------------------------------------------------------
+// Public Functions
+// ------------------------------------------------------------------------------------------------
+
+///
+/// Install the handler for `SIGINT`; this may be called any number of times, the handler is only
+/// installed once.
+///
+pub fn install_interrupt_handler() {
+    INSTALL_HANDLER.call_once(|| {
+        debug!("install_interrupt_handler");
+        let handler: extern "C" fn(libc::c_int) = on_interrupt;
+        set_signal_handler(libc::SIGINT, handler as libc::sighandler_t);
+        HANDLER_INSTALLED.store(true, Ordering::SeqCst);
+    });
+}
+
+///
+/// Returns `true` if the interrupt handler has been installed.
+///
+pub fn is_interrupt_handler_installed() -> bool {
+    HANDLER_INSTALLED.load(Ordering::SeqCst)
+}
+
+///
+/// Returns `true` if the tool has been interrupted since the handler was installed.
+///
+pub fn is_interrupted() -> bool {
+    INTERRUPTED.load(Ordering::SeqCst)
+}
+
+///
+/// Returns the error `Interrupted` if the tool has been interrupted, this is used between steps
+/// that do not execute a command.
+///
+pub fn check_interrupted() -> Result<()> {
+    if is_interrupted() {
+        Err(Error::Interrupted)
+    } else {
+        Ok(())
+    }
+}
+
+///
+/// Send `SIGTERM` to the process group with the id `process_group`, this is the same as the
+/// process id of the group's leader.
+///
+pub fn terminate_process_group(process_group: u32) {
+    send_signal_to_group(process_group, libc::SIGTERM);
+}
+
+///
+/// Send `SIGKILL` to the process group with the id `process_group`.
+///
+pub fn kill_process_group(process_group: u32) {
+    send_signal_to_group(process_group, libc::SIGKILL);
+}
+
+// ------------------------------------------------------------------------------------------------
+// Private Functions
+// ------------------------------------------------------------------------------------------------
+
+static INSTALL_HANDLER: Once = Once::new();
+
+static HANDLER_INSTALLED: AtomicBool = AtomicBool::new(false);
+
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+// Only async-signal-safe operations may be performed here.
+extern "C" fn on_interrupt(_: libc::c_int) {
+    if INTERRUPTED.swap(true, Ordering::SeqCst) {
+        #[allow(unsafe_code)]
+        unsafe {
+            libc::_exit(INTERRUPTED_EXIT_CODE);
+        }
+    }
+}
+
+#[allow(unsafe_code)]
+fn set_signal_handler(signal: libc::c_int, handler: libc::sighandler_t) {
+    let previous = unsafe { libc::signal(signal, handler) };
+    if previous == libc::SIG_ERR {
+        warn!("could not install handler for signal {}", signal);
+    }
+}
+
+#[allow(unsafe_code)]
+fn send_signal_to_group(process_group: u32, signal: libc::c_int) {
+    debug!("send_signal_to_group ({}, {})", process_group, signal);
+    if unsafe { libc::kill(-(process_group as libc::pid_t), signal) } != 0 {
+        debug!("could not signal process group {}", process_group);
+    }
+}
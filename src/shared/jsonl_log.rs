@@ -0,0 +1,822 @@
+/*!
+The JSON-lines implementation of the install log, this requires the feature `jsonl-log`. It has no
+dependency on SQLite and is intended for minimal environments, such as containers, where that
+dependency is not available.
+*/
+
+use crate::error::Result;
+use crate::shared::command::CommandRecord;
+use crate::shared::install_log::{
+    default_machine_id, is_applied_action, AppliedPackageSet, InstallLog, InstalledPackage,
+    LogEntry, PackageDurations, PackageLogQuery, RunCheckpoint, ARCHIVED_ACTION,
+    INTERRUPTED_ACTION, JSON_LINES_LOG_FILE, REQUIRES_ACTION,
+};
+use crate::shared::{dirs, FileSystemResource, InstallActionKind, Name, Scope};
+use crate::APP_NAME;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::{read_to_string, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::Duration;
+
+// ------------------------------------------------------------------------------------------------
+// Public Types
+// ------------------------------------------------------------------------------------------------
+
+///
+/// An install log kept in a text file where each line is a JSON object, a single record. Records
+/// are only ever appended to the file; so, for example, a run's end, or the clearing of a package
+/// set's progress, is a new record rather than a change to an existing one.
+///
+/// Each record has a `record` field naming its kind; `installed`, `applied`, `command`,
+/// `run-started`, `run-ended`, `run-progress`, or `run-progress-cleared`; with the remaining
+/// fields named as the columns of the corresponding SQLite tables. Dates are written in the
+/// form `2021-02-03T04:05:06.000000000+0000`.
+///
+#[derive(Debug)]
+pub struct JsonLinesLog {
+    path: PathBuf,
+    run_id: Option<u64>,
+    pending: Option<Vec<LogRecord>>,
+    machine: String,
+}
+
+// ------------------------------------------------------------------------------------------------
+// Implementations
+// ------------------------------------------------------------------------------------------------
+
+impl FileSystemResource for JsonLinesLog {
+    fn default_path(scope: Scope) -> PathBuf {
+        dirs::log_dir_for(scope, APP_NAME)
+            .unwrap()
+            .join(JSON_LINES_LOG_FILE)
+    }
+
+    fn open_from(log_file_path: PathBuf) -> Result<Self> {
+        if !log_file_path.is_file() {
+            debug!(
+                "JsonLinesLog::open creating new log file: {:?}",
+                log_file_path
+            );
+            std::fs::create_dir_all(log_file_path.parent().unwrap())?;
+            let _ = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&log_file_path)?;
+        }
+        Ok(Self {
+            path: log_file_path,
+            run_id: None,
+            pending: None,
+            machine: default_machine_id(),
+        })
+    }
+}
+
+impl InstallLog for JsonLinesLog {
+    fn machine(&self) -> &str {
+        &self.machine
+    }
+
+    fn set_machine(&mut self, machine: &str) {
+        self.machine = machine.to_string();
+    }
+
+    fn begin(&mut self) -> Result<()> {
+        trace!("JsonLinesLog::begin");
+        self.pending = Some(Default::default());
+        Ok(())
+    }
+
+    fn commit(&mut self) -> Result<()> {
+        trace!("JsonLinesLog::commit");
+        if let Some(records) = self.pending.take() {
+            self.write(&records)?;
+        }
+        Ok(())
+    }
+
+    fn begin_run(&mut self, action: &InstallActionKind) -> Result<()> {
+        trace!("JsonLinesLog::begin_run ({})", action);
+        let run_id = self
+            .read()?
+            .iter()
+            .filter_map(|record| match record {
+                LogRecord::RunStarted { run_id, .. } => Some(*run_id),
+                _ => None,
+            })
+            .max()
+            .unwrap_or_default()
+            + 1;
+        self.append(LogRecord::RunStarted {
+            run_id,
+            action: action.to_string(),
+            started: now(),
+        })?;
+        self.run_id = Some(run_id);
+        Ok(())
+    }
+
+    fn end_run(&mut self, succeeded: bool) -> Result<()> {
+        trace!("JsonLinesLog::end_run ({})", succeeded);
+        if let Some(run_id) = self.run_id.take() {
+            self.append(LogRecord::RunEnded {
+                run_id,
+                finished: now(),
+                succeeded,
+            })?;
+        }
+        Ok(())
+    }
+
+    fn log_run_progress(
+        &mut self,
+        package_set_group_name: &Name,
+        package_set_name: &Name,
+        package_name: Option<&Name>,
+    ) -> Result<()> {
+        if let Some(run_id) = self.run_id {
+            self.append(LogRecord::RunProgress {
+                run_id,
+                package_set_group: package_set_group_name.to_string(),
+                package_set: package_set_name.to_string(),
+                package: package_name.map(|name| name.to_string()),
+            })?;
+        }
+        Ok(())
+    }
+
+    fn clear_run_progress(
+        &mut self,
+        package_set_group_name: &Name,
+        package_set_name: &Name,
+    ) -> Result<()> {
+        if let Some(run_id) = self.run_id {
+            self.append(LogRecord::RunProgressCleared {
+                run_id,
+                package_set_group: package_set_group_name.to_string(),
+                package_set: package_set_name.to_string(),
+            })?;
+        }
+        Ok(())
+    }
+
+    fn resume_run(&mut self, action: &InstallActionKind) -> Result<Option<RunCheckpoint>> {
+        let records = self.read()?;
+        let action_name = action.to_string();
+        let previous_run_id = records
+            .iter()
+            .filter_map(|record| match record {
+                LogRecord::RunStarted { run_id, action, .. }
+                    if action == &action_name && Some(*run_id) != self.run_id =>
+                {
+                    Some(*run_id)
+                }
+                _ => None,
+            })
+            .max();
+        let previous_run_id = match previous_run_id {
+            None => return Ok(None),
+            Some(run_id) => run_id,
+        };
+        let succeeded = records.iter().any(|record| {
+            matches!(record, LogRecord::RunEnded { run_id, succeeded: true, .. } if *run_id == previous_run_id)
+        });
+        if succeeded {
+            return Ok(None);
+        }
+
+        debug!("JsonLinesLog::resume_run resuming run {}", previous_run_id);
+        // Progress is replayed in order, so that cleared package sets are not included.
+        let mut progress: Vec<(String, String, Option<String>)> = Default::default();
+        for record in records {
+            match record {
+                LogRecord::RunProgress {
+                    run_id,
+                    package_set_group,
+                    package_set,
+                    package,
+                } if run_id == previous_run_id => {
+                    progress.push((package_set_group, package_set, package))
+                }
+                LogRecord::RunProgressCleared {
+                    run_id,
+                    package_set_group,
+                    package_set,
+                } if run_id == previous_run_id => progress
+                    .retain(|(group, set, _)| group != &package_set_group || set != &package_set),
+                _ => {}
+            }
+        }
+
+        let mut checkpoint = RunCheckpoint::default();
+        let mut copied: Vec<LogRecord> = Default::default();
+        for (package_set_group, package_set, package) in progress {
+            if let Some(run_id) = self.run_id {
+                copied.push(LogRecord::RunProgress {
+                    run_id,
+                    package_set_group: package_set_group.clone(),
+                    package_set: package_set.clone(),
+                    package: package.clone(),
+                });
+            }
+            checkpoint.add_completed(package_set_group, package_set, package);
+        }
+        self.write(&copied)?;
+        Ok(Some(checkpoint))
+    }
+
+    fn log_installed_package(&mut self, package: &InstalledPackage) -> Result<()> {
+        trace!("Logging package installation success");
+        let record = installed_record(&InstalledPackage {
+            date_time: Some(time::OffsetDateTime::now_utc()),
+            machine: Some(self.machine.clone()),
+            ..package.clone()
+        });
+        self.append(record)
+    }
+
+    fn log_applied_package_set(
+        &mut self,
+        package_set_group_name: &Name,
+        package_set_name: &Name,
+        action: &InstallActionKind,
+        content_hash: &str,
+    ) -> Result<()> {
+        trace!("Logging package set {} applied", action);
+        self.append(LogRecord::Applied {
+            date_time: now(),
+            package_set_group: package_set_group_name.to_string(),
+            package_set: package_set_name.to_string(),
+            action: action.to_string(),
+            content_hash: content_hash.to_string(),
+            machine: Some(self.machine.clone()),
+        })
+    }
+
+    fn log_command(&mut self, record: &CommandRecord) -> Result<()> {
+        trace!("Logging command execution {:?}", record.command());
+        let record = command_record(record, Some(self.machine.clone()));
+        self.append(record)
+    }
+
+    fn log_interrupted_package_set(
+        &mut self,
+        package_set_group_name: &Name,
+        package_set_name: &Name,
+        action: &InstallActionKind,
+    ) -> Result<()> {
+        trace!("Logging package set {} interrupted", action);
+        self.append(LogRecord::Applied {
+            date_time: now(),
+            package_set_group: package_set_group_name.to_string(),
+            package_set: package_set_name.to_string(),
+            action: format!("{}-{}", INTERRUPTED_ACTION, action),
+            content_hash: String::new(),
+            machine: Some(self.machine.clone()),
+        })
+    }
+
+    fn log_added_prerequisite(
+        &mut self,
+        package_set_group_name: &Name,
+        package_set_name: &Name,
+        installer_name: &Name,
+        prerequisite: &str,
+    ) -> Result<()> {
+        trace!("Logging package set prerequisite added");
+        self.append(LogRecord::Applied {
+            date_time: now(),
+            package_set_group: package_set_group_name.to_string(),
+            package_set: package_set_name.to_string(),
+            action: format!("{} {} {}", REQUIRES_ACTION, installer_name, prerequisite),
+            content_hash: String::new(),
+            machine: Some(self.machine.clone()),
+        })
+    }
+
+    fn log_archived_package_set(
+        &mut self,
+        package_set_group_name: &Name,
+        package_set_name: &Name,
+        content_hash: &str,
+    ) -> Result<()> {
+        trace!("Logging package set archived");
+        self.append(LogRecord::Applied {
+            date_time: now(),
+            package_set_group: package_set_group_name.to_string(),
+            package_set: package_set_name.to_string(),
+            action: ARCHIVED_ACTION.to_string(),
+            content_hash: content_hash.to_string(),
+            machine: Some(self.machine.clone()),
+        })
+    }
+
+    fn last_applied_hash(
+        &mut self,
+        package_set_group_name: &Name,
+        package_set_name: &Name,
+    ) -> Result<Option<String>> {
+        let group_name = package_set_group_name.to_string();
+        let set_name = package_set_name.to_string();
+        Ok(self
+            .read()?
+            .into_iter()
+            .filter_map(|record| match record {
+                LogRecord::Applied {
+                    package_set_group,
+                    package_set,
+                    action,
+                    content_hash,
+                    machine,
+                    ..
+                } if package_set_group == group_name
+                    && package_set == set_name
+                    && self.is_local(&machine) =>
+                {
+                    Some((action, content_hash))
+                }
+                _ => None,
+            })
+            .next_back()
+            .filter(|(action, _)| is_applied_action(action))
+            .map(|(_, content_hash)| content_hash))
+    }
+
+    fn logged_package_sets(&mut self) -> Result<Vec<(Name, Name)>> {
+        let names: BTreeSet<(String, String)> = self
+            .read()?
+            .into_iter()
+            .filter_map(|record| match record {
+                LogRecord::Installed {
+                    package_set_group,
+                    package_set,
+                    machine,
+                    ..
+                }
+                | LogRecord::Applied {
+                    package_set_group,
+                    package_set,
+                    machine,
+                    ..
+                } if self.is_local(&machine) => Some((package_set_group, package_set)),
+                _ => None,
+            })
+            .collect();
+        let mut results: Vec<(Name, Name)> = Default::default();
+        for (group, package_set) in names {
+            results.push((Name::from_str(&group)?, Name::from_str(&package_set)?));
+        }
+        Ok(results)
+    }
+
+    fn applied_package_sets(&mut self) -> Result<Vec<AppliedPackageSet>> {
+        let mut applied: Vec<AppliedPackageSet> = self
+            .entries()?
+            .into_iter()
+            .filter_map(|entry| match entry {
+                LogEntry::Applied(applied) => Some(applied),
+                _ => None,
+            })
+            .collect();
+        // Records are appended, so the most recent is last.
+        applied.reverse();
+        Ok(applied)
+    }
+
+    fn installed_packages(&mut self, query: &PackageLogQuery) -> Result<Vec<InstalledPackage>> {
+        let packages = self.entries()?.into_iter().filter_map(|entry| match entry {
+            LogEntry::Installed(installed) => Some(installed),
+            _ => None,
+        });
+        Ok(query.select(packages, &self.machine))
+    }
+
+    fn command_history(&mut self, limit: u32) -> Result<Vec<CommandRecord>> {
+        let mut history: Vec<CommandRecord> = self
+            .entries()?
+            .into_iter()
+            .rev()
+            .filter_map(|entry| match entry {
+                LogEntry::Command(record, _) => Some(record),
+                _ => None,
+            })
+            .collect();
+        if limit > 0 {
+            history.truncate(limit as usize);
+        }
+        Ok(history)
+    }
+
+    fn package_durations(&mut self, limit: u32) -> Result<Vec<PackageDurations>> {
+        let mut totals: BTreeMap<DurationKey, (u32, u64, u64)> = Default::default();
+        for record in self.read()? {
+            if let LogRecord::Installed {
+                package_set_group,
+                package_set,
+                package,
+                installer,
+                duration_ms: Some(duration_ms),
+                ..
+            } = record
+            {
+                let (count, total, longest) = totals
+                    .entry((package_set_group, package_set, package, installer))
+                    .or_default();
+                *count += 1;
+                *total += duration_ms;
+                *longest = (*longest).max(duration_ms);
+            }
+        }
+        let mut durations: Vec<PackageDurations> = Default::default();
+        for ((group, package_set, package, installer), (count, total, longest)) in totals {
+            durations.push(PackageDurations {
+                package_set_group_name: Name::from_str(&group)?,
+                package_set_name: Name::from_str(&package_set)?,
+                package_name: Name::from_str(&package)?,
+                installer_name: Name::from_str(&installer)?,
+                count,
+                total: Duration::from_millis(total),
+                longest: Duration::from_millis(longest),
+            });
+        }
+        durations.sort_by(|a, b| b.total().cmp(a.total()));
+        if limit > 0 {
+            durations.truncate(limit as usize);
+        }
+        Ok(durations)
+    }
+
+    fn entries(&mut self) -> Result<Vec<LogEntry>> {
+        let mut entries: Vec<LogEntry> = Default::default();
+        for record in self.read()? {
+            if let Some(entry) = record_to_entry(record)? {
+                entries.push(entry);
+            }
+        }
+        Ok(entries)
+    }
+
+    fn import_entries(&mut self, entries: &[LogEntry]) -> Result<()> {
+        for entry in entries {
+            let record = match entry {
+                LogEntry::Installed(installed) => installed_record(installed),
+                LogEntry::Applied(applied) => applied_record(applied),
+                LogEntry::Command(record, machine) => command_record(record, machine.clone()),
+            };
+            self.append(record)?;
+        }
+        Ok(())
+    }
+}
+
+impl JsonLinesLog {
+    // Records that do not record a machine are from this machine.
+    fn is_local(&self, machine: &Option<String>) -> bool {
+        machine
+            .as_ref()
+            .is_none_or(|machine| machine == &self.machine)
+    }
+
+    // Append a record to the log, unless a transaction has begun in which case the record is
+    // only written on commit.
+    fn append(&mut self, record: LogRecord) -> Result<()> {
+        match &mut self.pending {
+            Some(pending) => {
+                pending.push(record);
+                Ok(())
+            }
+            None => self.write(&[record]),
+        }
+    }
+
+    fn write(&self, records: &[LogRecord]) -> Result<()> {
+        if records.is_empty() {
+            return Ok(());
+        }
+        let mut lines = String::new();
+        for record in records {
+            lines.push_str(&serde_json::to_string(record)?);
+            lines.push('\n');
+        }
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        file.write_all(lines.as_bytes())?;
+        Ok(())
+    }
+
+    // Read all records, those pending in a transaction are not included. A line that cannot be
+    // parsed is skipped, so that a truncated final line does not make the whole log unreadable.
+    fn read(&self) -> Result<Vec<LogRecord>> {
+        let content = if self.path.is_file() {
+            read_to_string(&self.path)?
+        } else {
+            String::new()
+        };
+        Ok(content
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .filter_map(|(index, line)| match serde_json::from_str(line) {
+                Ok(record) => Some(record),
+                Err(e) => {
+                    warn!(
+                        "JsonLinesLog::read skipping line {} of {:?}: {}",
+                        index + 1,
+                        self.path,
+                        e
+                    );
+                    None
+                }
+            })
+            .collect())
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+// Private Types
+// ------------------------------------------------------------------------------------------------
+
+// Package set group, package set, package, and installer names.
+type DurationKey = (String, String, String, String);
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(tag = "record", rename_all = "kebab-case")]
+enum LogRecord {
+    Installed {
+        date_time: String,
+        package_set_group: String,
+        package_set: String,
+        package: String,
+        installer: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        started: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        duration_ms: Option<u64>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        output_bytes: Option<usize>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        output_lines: Option<usize>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        machine: Option<String>,
+    },
+    Applied {
+        date_time: String,
+        package_set_group: String,
+        package_set: String,
+        action: String,
+        content_hash: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        machine: Option<String>,
+    },
+    Command {
+        started: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        context: Option<String>,
+        command: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        exit_code: Option<i32>,
+        duration_ms: u64,
+        stderr: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        machine: Option<String>,
+    },
+    RunStarted {
+        run_id: u64,
+        action: String,
+        started: String,
+    },
+    RunEnded {
+        run_id: u64,
+        finished: String,
+        succeeded: bool,
+    },
+    RunProgress {
+        run_id: u64,
+        package_set_group: String,
+        package_set: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        package: Option<String>,
+    },
+    RunProgressCleared {
+        run_id: u64,
+        package_set_group: String,
+        package_set: String,
+    },
+}
+
+// ------------------------------------------------------------------------------------------------
+// Private Functions
+// ------------------------------------------------------------------------------------------------
+
+const DATE_TIME_FORMAT: &str = "%Y-%m-%dT%H:%M:%S.%N%z";
+
+fn installed_record(installed: &InstalledPackage) -> LogRecord {
+    LogRecord::Installed {
+        date_time: installed
+            .date_time()
+            .map(format_date_time)
+            .unwrap_or_else(now),
+        package_set_group: installed.package_set_group_name().to_string(),
+        package_set: installed.package_set_name().to_string(),
+        package: installed.package_name().to_string(),
+        installer: installed.installer_name().to_string(),
+        started: installed.started().map(format_date_time),
+        duration_ms: installed.duration().map(|d| d.as_millis() as u64),
+        output_bytes: *installed.output_bytes(),
+        output_lines: *installed.output_lines(),
+        machine: installed.machine().cloned(),
+    }
+}
+
+fn applied_record(applied: &AppliedPackageSet) -> LogRecord {
+    LogRecord::Applied {
+        date_time: applied
+            .date_time()
+            .map(format_date_time)
+            .unwrap_or_else(now),
+        package_set_group: applied.package_set_group_name().to_string(),
+        package_set: applied.package_set_name().to_string(),
+        action: applied.action().to_string(),
+        content_hash: applied.content_hash().to_string(),
+        machine: applied.machine().cloned(),
+    }
+}
+
+fn command_record(record: &CommandRecord, machine: Option<String>) -> LogRecord {
+    LogRecord::Command {
+        started: format_date_time(*record.started()),
+        context: record.context().clone(),
+        command: record.command().to_string(),
+        exit_code: *record.exit_code(),
+        duration_ms: record.duration().as_millis() as u64,
+        stderr: record.stderr().to_string(),
+        machine,
+    }
+}
+
+// The entry for an installed, applied, or command record; run records are not entries.
+fn record_to_entry(record: LogRecord) -> Result<Option<LogEntry>> {
+    Ok(match record {
+        LogRecord::Installed {
+            date_time,
+            package_set_group,
+            package_set,
+            package,
+            installer,
+            started,
+            duration_ms,
+            output_bytes,
+            output_lines,
+            machine,
+        } => Some(LogEntry::Installed(InstalledPackage {
+            date_time: parse_date_time(&date_time),
+            package_set_group_name: Name::from_str(&package_set_group)?,
+            package_set_name: Name::from_str(&package_set)?,
+            package_name: Name::from_str(&package)?,
+            installer_name: Name::from_str(&installer)?,
+            started: started.as_deref().and_then(parse_date_time),
+            duration: duration_ms.map(Duration::from_millis),
+            output_bytes,
+            output_lines,
+            machine,
+        })),
+        LogRecord::Applied {
+            date_time,
+            package_set_group,
+            package_set,
+            action,
+            content_hash,
+            machine,
+        } => Some(LogEntry::Applied(AppliedPackageSet {
+            date_time: parse_date_time(&date_time),
+            package_set_group_name: Name::from_str(&package_set_group)?,
+            package_set_name: Name::from_str(&package_set)?,
+            action,
+            content_hash,
+            machine,
+        })),
+        LogRecord::Command {
+            started,
+            context,
+            command,
+            exit_code,
+            duration_ms,
+            stderr,
+            machine,
+        } => parse_date_time(&started).map(|started| {
+            LogEntry::Command(
+                CommandRecord::new(
+                    context,
+                    command,
+                    started,
+                    Duration::from_millis(duration_ms),
+                    exit_code,
+                    stderr,
+                ),
+                machine,
+            )
+        }),
+        _ => None,
+    })
+}
+
+fn now() -> String {
+    format_date_time(time::OffsetDateTime::now_utc())
+}
+
+fn format_date_time(date_time: time::OffsetDateTime) -> String {
+    date_time.format(DATE_TIME_FORMAT)
+}
+
+fn parse_date_time(date_time: &str) -> Option<time::OffsetDateTime> {
+    match time::OffsetDateTime::parse(date_time, DATE_TIME_FORMAT) {
+        Ok(date_time) => Some(date_time),
+        Err(e) => {
+            warn!("JsonLinesLog could not parse date {:?}: {}", date_time, e);
+            None
+        }
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+// Unit Tests
+// ------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_date_time_round_trip() {
+        let date_time = time::OffsetDateTime::now_utc();
+        assert_eq!(
+            parse_date_time(&format_date_time(date_time)),
+            Some(date_time)
+        );
+    }
+
+    #[test]
+    fn test_transaction_and_truncated_line() {
+        let work_dir = tempfile::tempdir().unwrap();
+        let log_file_path = work_dir.path().join("install-log.jsonl");
+        let mut log_db = JsonLinesLog::open_from(log_file_path.clone()).unwrap();
+        let group = Name::from_str("group").unwrap();
+        let package_set = Name::from_str("set").unwrap();
+
+        log_db.begin().unwrap();
+        log_db
+            .log_applied_package_set(&group, &package_set, &InstallActionKind::Install, "abc")
+            .unwrap();
+        assert_eq!(
+            log_db.last_applied_hash(&group, &package_set).unwrap(),
+            None
+        );
+        log_db.commit().unwrap();
+        assert_eq!(
+            log_db.last_applied_hash(&group, &package_set).unwrap(),
+            Some("abc".to_string())
+        );
+
+        let mut file = OpenOptions::new()
+            .append(true)
+            .open(&log_file_path)
+            .unwrap();
+        file.write_all(b"{\"record\":\"applied\",\"date_").unwrap();
+        assert_eq!(log_db.logged_package_sets().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_archived_package_set() {
+        let work_dir = tempfile::tempdir().unwrap();
+        let mut log_db =
+            JsonLinesLog::open_from(work_dir.path().join("install-log.jsonl")).unwrap();
+        let group = Name::from_str("group").unwrap();
+        let package_set = Name::from_str("set").unwrap();
+
+        log_db
+            .log_applied_package_set(&group, &package_set, &InstallActionKind::Install, "abc")
+            .unwrap();
+        assert_eq!(
+            log_db.last_applied_hash(&group, &package_set).unwrap(),
+            Some("abc".to_string())
+        );
+        log_db
+            .log_archived_package_set(&group, &package_set, "def")
+            .unwrap();
+        // An archived package set is no longer applied, so is always changed.
+        assert_eq!(
+            log_db.last_applied_hash(&group, &package_set).unwrap(),
+            None
+        );
+        let applied = log_db.applied_package_sets().unwrap();
+        assert_eq!(applied.len(), 2);
+        assert_eq!(applied[0].action(), ARCHIVED_ACTION);
+        assert_eq!(applied[0].content_hash(), "def");
+    }
+}
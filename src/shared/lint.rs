@@ -0,0 +1,542 @@
+/*!
+Static checks on the package repository, and installer registry, these find problems in package
+set files before they are found by an install action. This includes references to variables that
+are neither built-in nor defined by the package set, which would otherwise be replaced by their
+own name when executed.
+*/
+
+use crate::shared::dirs;
+use crate::shared::env::{
+    is_built_in_var, read_vars_file, var_references, BUILT_IN_VAR_PREFIX, VARS_FILE,
+};
+use crate::shared::packages::{
+    is_reserved_name, is_valid_prerequisite, Readable, GROUP_FILE, PACKAGE_SET_FILE,
+};
+use crate::shared::secrets::{SecretProvider, SECRET_VAR_PREFIX};
+use crate::shared::{
+    global_vars_paths, FileSystemResource, InstallerCommand, InstallerRegistry, Name, PackageSet,
+    PackageSetGroupConfig, Scope,
+};
+use crate::APP_NAME;
+use std::collections::HashSet;
+use std::ffi::OsStr;
+use std::fmt::{Display, Formatter};
+use std::fs::read_dir;
+use std::path::{Path, PathBuf};
+
+// ------------------------------------------------------------------------------------------------
+// Public Types
+// ------------------------------------------------------------------------------------------------
+
+///
+/// A single problem found in the repository, the path is the file, or directory, that contains
+/// the problem.
+///
+#[derive(Clone, Debug, PartialEq)]
+pub struct LintProblem {
+    path: PathBuf,
+    message: String,
+}
+
+// ------------------------------------------------------------------------------------------------
+// Public Functions
+// ------------------------------------------------------------------------------------------------
+
+///
+/// Check all the groups, and package sets, in the repository at `repository_path`, returning
+/// all problems found. The user-defined global variables for this machine are those in `scope`.
+///
+pub fn lint_repository(scope: Scope, repository_path: &Path) -> Vec<LintProblem> {
+    info!("lint_repository: checking {:?}", repository_path);
+    let mut vars_paths = vec![repository_path.join(".config").join(VARS_FILE)];
+    if let Some(config_dir) = dirs::config_dir_for(scope, APP_NAME) {
+        vars_paths.push(config_dir.join(VARS_FILE));
+    }
+    let (global_vars, mut problems) = lint_global_vars(&vars_paths);
+    match read_dir(repository_path) {
+        Ok(entries) => {
+            for group_path in entries
+                .filter_map(|entry| entry.ok())
+                .map(|entry| entry.path())
+            {
+                let dir_name = group_path.file_name().unwrap().to_string_lossy();
+                if group_path.is_dir() && !is_reserved_name(&dir_name) {
+                    if Name::is_valid(&dir_name) {
+                        problems.extend(lint_group(&group_path, &global_vars));
+                    } else {
+                        problems.push(LintProblem::new(
+                            &group_path,
+                            "group directory name is not a valid name",
+                        ));
+                    }
+                }
+            }
+        }
+        Err(e) => problems.push(LintProblem::new(repository_path, &e.to_string())),
+    }
+    problems.sort_by(|lhs, rhs| lhs.path.cmp(&rhs.path));
+    problems
+}
+
+///
+/// Check the package set file at `path`, with the user-defined global variables in `scope`,
+/// returning all problems found.
+///
+pub fn lint_package_set_file(scope: Scope, path: &Path) -> Vec<LintProblem> {
+    let (global_vars, _) = lint_global_vars(&global_vars_paths(scope));
+    lint_package_set(path, &global_vars).1
+}
+
+///
+/// Check the installer registry file at `registry_path`, returning all problems found.
+///
+pub fn lint_installer_registry(registry_path: &Path) -> Vec<LintProblem> {
+    info!("lint_installer_registry: checking {:?}", registry_path);
+    let registry = match InstallerRegistry::open_from(registry_path.to_path_buf()) {
+        Ok(registry) => registry,
+        Err(e) => return vec![LintProblem::new(registry_path, &e.to_string())],
+    };
+    let mut problems: Vec<LintProblem> = Default::default();
+    for installer in registry.installers() {
+        let mut commands: Vec<(String, &InstallerCommand)> = Default::default();
+        for (action, command) in installer.commands() {
+            commands.push((action.to_string(), command));
+        }
+        for (action, command) in installer.bundle_commands() {
+            commands.push((format!("bundle {}", action), command));
+        }
+        for (name, command) in &[
+            ("installed_version", installer.installed_version()),
+            ("latest_version", installer.latest_version()),
+            ("available_versions", installer.available_versions()),
+            ("install_version", installer.install_version()),
+            ("update_self", installer.update_self()),
+            ("list_installed", installer.list_installed()),
+            ("add_prerequisite", installer.add_prerequisite()),
+        ] {
+            if let Some(command) = command {
+                commands.push((name.to_string(), command));
+            }
+        }
+        for (name, command) in commands {
+            let strings = match command {
+                InstallerCommand::Script(script) => vec![script.as_str()],
+                InstallerCommand::Exec(args) => args.iter().map(String::as_str).collect(),
+            };
+            for string in strings {
+                problems.extend(lint_var_references(
+                    registry_path,
+                    &format!("installer {} command '{}'", installer.name(), name),
+                    string,
+                    &[],
+                ));
+            }
+        }
+    }
+    problems
+}
+
+// ------------------------------------------------------------------------------------------------
+// Implementations
+// ------------------------------------------------------------------------------------------------
+
+impl Display for LintProblem {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.path.display(), self.message)
+    }
+}
+
+impl LintProblem {
+    /// Construct a new problem for the file, or directory, at `path`.
+    pub fn new(path: &Path, message: &str) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            message: message.to_string(),
+        }
+    }
+
+    /// Return the path of the file, or directory, that contains the problem.
+    pub fn path(&self) -> &PathBuf {
+        &self.path
+    }
+
+    /// Return a description of the problem.
+    pub fn message(&self) -> &String {
+        &self.message
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+// Private Functions
+// ------------------------------------------------------------------------------------------------
+
+fn lint_global_vars(paths: &[PathBuf]) -> (Vec<String>, Vec<LintProblem>) {
+    let mut names: Vec<String> = Default::default();
+    let mut problems: Vec<LintProblem> = Default::default();
+    for path in paths {
+        match read_vars_file(path) {
+            Ok(vars) => {
+                problems.extend(lint_var_definitions(path, "variables", vars.keys()));
+                names.extend(vars.into_keys())
+            }
+            Err(e) => problems.push(LintProblem::new(path, &e.to_string())),
+        }
+    }
+    (names, problems)
+}
+
+fn lint_group(group_path: &Path, global_vars: &[String]) -> Vec<LintProblem> {
+    debug!("lint_group: checking {:?}", group_path);
+    let yaml_extension = OsStr::new("yml");
+    let mut problems: Vec<LintProblem> = Default::default();
+    let mut names: HashSet<Name> = Default::default();
+    let entries = match read_dir(group_path) {
+        Ok(entries) => entries.collect::<Vec<_>>(),
+        Err(e) => return vec![LintProblem::new(group_path, &e.to_string())],
+    };
+    if entries.is_empty() {
+        return vec![LintProblem::new(
+            group_path,
+            "group directory is empty, it may be a submodule that has not been updated",
+        )];
+    }
+    let mut defined_vars = global_vars.to_vec();
+    let group_file = group_path.join(GROUP_FILE);
+    match PackageSetGroupConfig::read(&group_file) {
+        Ok(config) => {
+            for (key, value) in config.env_vars() {
+                problems.extend(lint_var_references(
+                    &group_file,
+                    &format!("env-vars '{}'", key),
+                    value,
+                    &global_vars
+                        .iter()
+                        .map(String::as_str)
+                        .collect::<Vec<&str>>(),
+                ));
+            }
+            problems.extend(lint_var_definitions(
+                &group_file,
+                "env-vars",
+                config.env_vars().keys(),
+            ));
+            defined_vars.extend(config.env_vars().keys().cloned());
+        }
+        Err(e) => problems.push(LintProblem::new(&group_file, &e.to_string())),
+    }
+    for set_path in entries
+        .into_iter()
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+    {
+        if set_path.file_name() == Some(OsStr::new(GROUP_FILE)) {
+            continue;
+        }
+        let set_path = if set_path.is_file() && set_path.extension() == Some(yaml_extension) {
+            set_path
+        } else if set_path.is_dir() && set_path.join(PACKAGE_SET_FILE).is_file() {
+            set_path.join(PACKAGE_SET_FILE)
+        } else {
+            continue;
+        };
+        let (package_set, set_problems) = lint_package_set(&set_path, &defined_vars);
+        problems.extend(set_problems);
+        if let Some(package_set) = package_set {
+            if !names.insert(package_set.name().clone()) {
+                problems.push(LintProblem::new(
+                    &set_path,
+                    &format!(
+                        "package set name '{}' is used more than once in the group",
+                        package_set.name()
+                    ),
+                ));
+            }
+        }
+    }
+    problems
+}
+
+fn lint_package_set(path: &Path, global_vars: &[String]) -> (Option<PackageSet>, Vec<LintProblem>) {
+    debug!("lint_package_set: checking {:?}", path);
+    match PackageSet::read(&path.to_path_buf()) {
+        Ok(package_set) => {
+            let mut problems: Vec<LintProblem> = Default::default();
+            if let Some(bundle_path) = package_set.bundle_file_path() {
+                if !bundle_path.is_file() {
+                    problems.push(LintProblem::new(
+                        path,
+                        &format!("bundle file {:?} does not exist", bundle_path),
+                    ));
+                }
+            }
+            problems.extend(lint_package_set_vars(path, &package_set, global_vars));
+            (Some(package_set), problems)
+        }
+        Err(e) => (None, vec![LintProblem::new(path, &e.to_string())]),
+    }
+}
+
+fn lint_package_set_vars(
+    path: &Path,
+    package_set: &PackageSet,
+    global_vars: &[String],
+) -> Vec<LintProblem> {
+    let defined: Vec<&str> = package_set
+        .env_vars()
+        .keys()
+        .chain(package_set.prompt_vars().keys())
+        .chain(global_vars.iter())
+        .map(String::as_str)
+        .collect();
+    let mut strings: Vec<(String, &String)> = Default::default();
+    for (key, value) in package_set.env_vars() {
+        strings.push((format!("env-vars '{}'", key), value));
+    }
+    for (name, script) in &[
+        ("run-before", package_set.run_before()),
+        ("run-after", package_set.run_after()),
+        ("rollback", package_set.rollback()),
+    ] {
+        if let Some(script) = script {
+            strings.push((name.to_string(), script));
+        }
+    }
+    if let Some(scripts) = package_set.scripts() {
+        for (action, script) in scripts {
+            strings.push((format!("{} script", action), script));
+        }
+    }
+    for (source, target) in package_set.link_files() {
+        strings.push((format!("link-files '{}'", source), target));
+    }
+    for (shell, shell_files) in package_set.shell_files() {
+        for (source, target) in shell_files.link_files() {
+            strings.push((
+                format!("shell-files.{} link-files '{}'", shell, source),
+                target,
+            ));
+        }
+    }
+
+    let mut problems = lint_var_definitions(path, "env-vars", package_set.env_vars().keys());
+    for (installer_name, prerequisites) in package_set.requires() {
+        if !Name::is_valid(&installer_name.to_string()) {
+            problems.push(LintProblem::new(
+                path,
+                &format!(
+                    "requires installer '{}' is not a valid installer name",
+                    installer_name
+                ),
+            ));
+        }
+        for prerequisite in prerequisites {
+            if !is_valid_prerequisite(prerequisite) {
+                problems.push(LintProblem::new(
+                    path,
+                    &format!(
+                        "requires.{} prerequisite {:?} may not contain quotes or control characters",
+                        installer_name, prerequisite
+                    ),
+                ));
+            }
+        }
+    }
+    problems.extend(lint_var_definitions(
+        path,
+        "prompt-vars",
+        package_set.prompt_vars().keys(),
+    ));
+    for (location, string) in strings {
+        problems.extend(lint_var_references(path, &location, string, &defined));
+    }
+    problems
+}
+
+fn lint_var_definitions<'a>(
+    path: &Path,
+    location: &str,
+    names: impl Iterator<Item = &'a String>,
+) -> Vec<LintProblem> {
+    names
+        .filter(|name| name.starts_with(BUILT_IN_VAR_PREFIX))
+        .map(|name| {
+            LintProblem::new(
+                path,
+                &format!(
+                    "variable '{}' in {} uses the reserved namespace '{}', it will be ignored",
+                    name, location, BUILT_IN_VAR_PREFIX
+                ),
+            )
+        })
+        .collect()
+}
+
+fn lint_var_references(
+    path: &Path,
+    location: &str,
+    string: &str,
+    defined: &[&str],
+) -> Vec<LintProblem> {
+    var_references(string)
+        .into_iter()
+        .filter_map(|name| {
+            if let Some(reference) = name.strip_prefix(SECRET_VAR_PREFIX) {
+                SecretProvider::for_reference(reference).is_none().then(|| {
+                    format!(
+                        "unknown secret provider for '{}' in {}",
+                        reference, location
+                    )
+                })
+            } else if !is_built_in_var(&name) && !defined.contains(&name.as_str()) {
+                Some(format!(
+                    "unknown variable '{}' referenced in {}",
+                    name, location
+                ))
+            } else {
+                None
+            }
+        })
+        .map(|message| LintProblem::new(path, &message))
+        .collect()
+}
+
+// ------------------------------------------------------------------------------------------------
+// Unit Tests
+// ------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use std::fs;
+
+    #[test]
+    fn test_lint_repository() {
+        let work_dir = tempfile::tempdir().unwrap();
+        let repository_path = work_dir.path().to_path_buf();
+        let group_path = repository_path.join("tools");
+        fs::create_dir_all(&group_path).unwrap();
+        fs::create_dir_all(repository_path.join(".git")).unwrap();
+        fs::create_dir_all(repository_path.join("shared")).unwrap();
+        fs::write(group_path.join("good.yml"), "name: good\n").unwrap();
+        fs::write(group_path.join("again.yml"), "name: good\n").unwrap();
+        fs::write(group_path.join("bad.yml"), "name: not a name!\n").unwrap();
+        fs::write(
+            group_path.join("group.yml"),
+            "env-vars:\n  tools_dir: \"{{home}}/tools\"\n",
+        )
+        .unwrap();
+        fs::write(
+            group_path.join("scripted.yml"),
+            "name: scripted\nrun-after: \"ls {{tools_dir}}\"\n",
+        )
+        .unwrap();
+        fs::write(
+            group_path.join("bundled.yml"),
+            "name: bundled\nactions:\n  bundle:\n    file: Brewfile\n",
+        )
+        .unwrap();
+
+        let problems = lint_repository(Scope::User, &repository_path);
+
+        let files: Vec<String> = problems
+            .iter()
+            .map(|problem| {
+                problem
+                    .path()
+                    .file_name()
+                    .unwrap()
+                    .to_string_lossy()
+                    .to_string()
+            })
+            .collect();
+        assert_eq!(files.len(), 4);
+        assert!(files.contains(&"shared".to_string()));
+        assert!(files.contains(&"bad.yml".to_string()));
+        assert!(files.contains(&"bundled.yml".to_string()));
+        assert!(
+            files.contains(&"again.yml".to_string()) || files.contains(&"good.yml".to_string())
+        );
+    }
+
+    #[test]
+    fn test_lint_package_set_vars() {
+        let package_set: PackageSet = serde_yaml::from_str(
+            r##"
+            name: lux
+            env-vars:
+              lux_home: "{{home}}/.lux"
+            actions:
+              scripts:
+                install: "lux-install --home {{lux_home}} --set {{package_set_name}}"
+            run-after: "lux-config {{lux_hom}}"
+            "##,
+        )
+        .unwrap();
+
+        let problems = lint_package_set_vars(Path::new("lux.yml"), &package_set, &[]);
+        assert_eq!(problems.len(), 1);
+        assert_eq!(
+            problems.first().unwrap().message(),
+            "unknown variable 'lux_hom' referenced in run-after"
+        );
+
+        let problems =
+            lint_package_set_vars(Path::new("lux.yml"), &package_set, &["lux_hom".to_string()]);
+        assert!(problems.is_empty());
+
+        let package_set: PackageSet = serde_yaml::from_str(
+            r##"
+            name: lux
+            env-vars:
+              mcfg.home: "{{home}}/.lux"
+            run-after: "lux-config {{mcfg.home}} \\{{lux_hom}}"
+            "##,
+        )
+        .unwrap();
+
+        let problems = lint_package_set_vars(Path::new("lux.yml"), &package_set, &[]);
+        assert_eq!(problems.len(), 1);
+        assert_eq!(
+            problems.first().unwrap().message(),
+            "variable 'mcfg.home' in env-vars uses the reserved namespace 'mcfg.', it will be ignored"
+        );
+    }
+
+    #[test]
+    fn test_lint_package_set_requires() {
+        let package_set: PackageSet = serde_yaml::from_str(
+            r##"
+            name: fonts
+            requires:
+              homebrew:
+                - homebrew/cask-fonts
+              apt:
+                - "ppa:fonts/ppa'; rm -rf ~; echo '"
+            "##,
+        )
+        .unwrap();
+
+        let problems = lint_package_set_vars(Path::new("fonts.yml"), &package_set, &[]);
+        assert_eq!(problems.len(), 1);
+        assert_eq!(
+            problems.first().unwrap().message(),
+            "requires.apt prerequisite \"ppa:fonts/ppa'; rm -rf ~; echo '\" may not contain quotes or control characters"
+        );
+    }
+
+    #[test]
+    fn test_lint_global_vars() {
+        let work_dir = tempfile::tempdir().unwrap();
+        let vars_path = work_dir.path().join("vars.yml");
+        fs::write(&vars_path, "dotfiles_dir: \"{{home}}/dotfiles\"\n").unwrap();
+        let (names, problems) = lint_global_vars(std::slice::from_ref(&vars_path));
+        assert_eq!(names, vec!["dotfiles_dir".to_string()]);
+        assert!(problems.is_empty());
+
+        fs::write(&vars_path, "- not a map\n").unwrap();
+        let (names, problems) = lint_global_vars(std::slice::from_ref(&vars_path));
+        assert!(names.is_empty());
+        assert_eq!(problems.len(), 1);
+    }
+}
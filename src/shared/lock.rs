@@ -0,0 +1,157 @@
+use crate::error::Result;
+use crate::shared::{FileSystemResource, Name, PackageRepository, Scope};
+use crate::APP_NAME;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::read_to_string;
+use std::path::PathBuf;
+
+// ------------------------------------------------------------------------------------------------
+// Public Types
+// ------------------------------------------------------------------------------------------------
+
+///
+/// The lock file records the resolved version of packages whose installers pick a version at
+/// install time, for example the latest GitHub release. It lives in the package repository so
+/// that the same versions are installed on every machine that shares the repository.
+///
+#[derive(Deserialize, Serialize, Clone, Debug, Default, PartialEq)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct LockFile {
+    #[serde(skip)]
+    path: PathBuf,
+    packages: BTreeMap<String, LockedPackage>,
+}
+
+///
+/// The locked details of a single package.
+///
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct LockedPackage {
+    #[serde(deserialize_with = "Name::deserialize")]
+    installer: Name,
+    version: String,
+}
+
+///
+/// The lock file name.
+///
+pub const LOCK_FILE: &str = "mcfg.lock";
+
+// ------------------------------------------------------------------------------------------------
+// Implementations
+// ------------------------------------------------------------------------------------------------
+
+impl FileSystemResource for LockFile {
+    fn default_path(scope: Scope) -> PathBuf {
+        PackageRepository::default_path(scope).join(LOCK_FILE)
+    }
+
+    fn open_from(lock_file: PathBuf) -> Result<Self> {
+        if lock_file.is_file() {
+            info!("LockFile::open_from loading from {:?}", lock_file);
+            let lock_data = read_to_string(&lock_file)?;
+            let lock: Option<LockFile> = serde_yaml::from_str(&lock_data)?;
+            Ok(Self {
+                path: lock_file,
+                ..lock.unwrap_or_default()
+            })
+        } else {
+            debug!("LockFile::open_from no file {:?}, empty lock", lock_file);
+            Ok(Self {
+                path: lock_file,
+                ..Default::default()
+            })
+        }
+    }
+}
+
+impl LockFile {
+    /// Return the path this lock file was read from, and will be saved to.
+    pub fn path(&self) -> &PathBuf {
+        &self.path
+    }
+
+    /// Return `true` if there are no locked packages, else `false`.
+    pub fn is_empty(&self) -> bool {
+        self.packages.is_empty()
+    }
+
+    /// Return an iterator over all locked packages, keyed by `group/package-set/package`.
+    pub fn packages(&self) -> impl Iterator<Item = (&String, &LockedPackage)> {
+        self.packages.iter()
+    }
+
+    /// Return the locked details for the package, if any.
+    pub fn package(
+        &self,
+        group: &Name,
+        package_set: &Name,
+        package: &Name,
+    ) -> Option<&LockedPackage> {
+        self.packages.get(&lock_key(group, package_set, package))
+    }
+
+    /// Record the locked details for the package, replacing any previous details.
+    pub fn lock_package(
+        &mut self,
+        group: &Name,
+        package_set: &Name,
+        package: &Name,
+        locked: LockedPackage,
+    ) {
+        let _ = self
+            .packages
+            .insert(lock_key(group, package_set, package), locked);
+    }
+
+    /// Remove any locked details for the package.
+    pub fn unlock_package(&mut self, group: &Name, package_set: &Name, package: &Name) {
+        let _ = self.packages.remove(&lock_key(group, package_set, package));
+    }
+
+    /// Save the lock file back to the path it was read from.
+    pub fn save(&self) -> Result<()> {
+        debug!("LockFile::save to {:?}", &self.path);
+        let lock_data = serde_yaml::to_string(self)?;
+        std::fs::write(
+            &self.path,
+            format!(
+                "# This file is maintained by {}, do not edit.\n{}",
+                APP_NAME, lock_data
+            ),
+        )?;
+        Ok(())
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+
+impl LockedPackage {
+    /// Construct a new locked package.
+    pub fn new(installer: Name, version: &str) -> Self {
+        Self {
+            installer,
+            version: version.to_string(),
+        }
+    }
+
+    /// Return the name of the installer that resolved the version.
+    pub fn installer(&self) -> &Name {
+        &self.installer
+    }
+
+    /// Return the resolved version.
+    pub fn version(&self) -> &String {
+        &self.version
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+// Private Functions
+// ------------------------------------------------------------------------------------------------
+
+fn lock_key(group: &Name, package_set: &Name, package: &Name) -> String {
+    format!("{}/{}/{}", group, package_set, package)
+}
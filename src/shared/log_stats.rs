@@ -0,0 +1,381 @@
+/*!
+Statistics aggregated from the install log, answering questions the raw history does not; how much
+each installer is used, which package sets were updated most recently, which packages have never
+been updated, and on which days the most work was done.
+*/
+
+use crate::error::Result;
+use crate::shared::install_log::{
+    AppliedPackageSet, InstallLog, InstalledPackage, PackageDurations, PackageLogOrder,
+    PackageLogQuery,
+};
+use crate::shared::{InstallActionKind, Name};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::time::Duration;
+
+// ------------------------------------------------------------------------------------------------
+// Public Types
+// ------------------------------------------------------------------------------------------------
+
+///
+/// The statistics for the install log as a whole; each list is limited to the number of entries
+/// requested when the statistics were aggregated.
+///
+#[derive(Debug)]
+pub struct LogStatistics {
+    installers: Vec<InstallerStatistics>,
+    recently_updated: Vec<AppliedPackageSet>,
+    never_updated: Vec<InstalledPackage>,
+    busiest_days: Vec<DayStatistics>,
+    slowest_packages: Vec<PackageDurations>,
+}
+
+///
+/// The number of times an installer acted on packages, and the average time it took.
+///
+#[derive(Debug, PartialEq)]
+pub struct InstallerStatistics {
+    installer_name: Name,
+    count: u32,
+    packages: u32,
+    average: Option<Duration>,
+}
+
+///
+/// The number of packages acted upon on a single day.
+///
+#[derive(Debug, PartialEq)]
+pub struct DayStatistics {
+    date: time::Date,
+    count: u32,
+}
+
+// ------------------------------------------------------------------------------------------------
+// Implementations
+// ------------------------------------------------------------------------------------------------
+
+impl LogStatistics {
+    /// Aggregate the statistics for the entries in `log`, each list will have at most `limit`
+    /// entries; a `limit` of `0` means no limit.
+    pub fn from_log(log: &mut dyn InstallLog, limit: u32) -> Result<Self> {
+        let installed = log.installed_packages(
+            &PackageLogQuery::default().ordered_by(PackageLogOrder::OldestFirst),
+        )?;
+        let applied = log.applied_package_sets()?;
+        let slowest_packages = log.package_durations(limit)?;
+        Ok(Self::aggregate(installed, applied, slowest_packages, limit))
+    }
+
+    // `installed` must be oldest first, and `applied` most recent first.
+    pub(crate) fn aggregate(
+        installed: Vec<InstalledPackage>,
+        applied: Vec<AppliedPackageSet>,
+        slowest_packages: Vec<PackageDurations>,
+        limit: u32,
+    ) -> Self {
+        let limit = if limit == 0 {
+            usize::MAX
+        } else {
+            limit as usize
+        };
+        let update_action = InstallActionKind::Update.to_string();
+        let uninstall_action = InstallActionKind::Uninstall.to_string();
+
+        let mut installers: BTreeMap<Name, (u32, HashSet<PackageKey>, Vec<Duration>)> =
+            Default::default();
+        let mut days: BTreeMap<time::Date, u32> = Default::default();
+        let mut first_logged: Vec<&InstalledPackage> = Default::default();
+        let mut seen: HashSet<PackageKey> = Default::default();
+        for entry in &installed {
+            let (count, packages, durations) = installers
+                .entry(entry.installer_name().clone())
+                .or_default();
+            *count += 1;
+            let _ = packages.insert(package_key(entry));
+            if let Some(duration) = entry.duration() {
+                durations.push(*duration);
+            }
+            if let Some(date_time) = entry.date_time() {
+                *days.entry(date_time.date()).or_default() += 1;
+            }
+            if seen.insert(package_key(entry)) {
+                first_logged.push(entry);
+            }
+        }
+        let mut installers: Vec<InstallerStatistics> = installers
+            .into_iter()
+            .map(
+                |(installer_name, (count, packages, durations))| InstallerStatistics {
+                    installer_name,
+                    count,
+                    packages: packages.len() as u32,
+                    average: if durations.is_empty() {
+                        None
+                    } else {
+                        Some(durations.iter().sum::<Duration>() / durations.len() as u32)
+                    },
+                },
+            )
+            .collect();
+        installers.sort_by_key(|installer| std::cmp::Reverse(installer.count));
+        installers.truncate(limit);
+
+        // The most recent action, and the most recent update, applied to each package set.
+        let mut last_action: HashMap<PackageSetKey, &AppliedPackageSet> = Default::default();
+        let mut last_update: HashMap<PackageSetKey, &AppliedPackageSet> = Default::default();
+        let mut recently_updated: Vec<AppliedPackageSet> = Default::default();
+        for entry in &applied {
+            let key = package_set_key(entry.package_set_group_name(), entry.package_set_name());
+            let _ = last_action.entry(key.clone()).or_insert(entry);
+            if entry.action() == update_action && !last_update.contains_key(&key) {
+                let _ = last_update.insert(key, entry);
+                recently_updated.push(entry.clone());
+            }
+        }
+        recently_updated.truncate(limit);
+
+        // A package is never updated if no update was applied to its package set after the
+        // package was first logged, and the package set has not since been uninstalled.
+        let never_updated: Vec<InstalledPackage> = first_logged
+            .into_iter()
+            .filter(|entry| {
+                let key = package_set_key(entry.package_set_group_name(), entry.package_set_name());
+                let uninstalled = last_action
+                    .get(&key)
+                    .map(|applied| applied.action() == uninstall_action)
+                    .unwrap_or_default();
+                let updated = last_update
+                    .get(&key)
+                    .map(|applied| applied.date_time() > entry.date_time())
+                    .unwrap_or_default();
+                !uninstalled && !updated
+            })
+            .take(limit)
+            .cloned()
+            .collect();
+
+        let mut busiest_days: Vec<DayStatistics> = days
+            .into_iter()
+            .map(|(date, count)| DayStatistics { date, count })
+            .collect();
+        busiest_days.sort_by(|a, b| b.count.cmp(&a.count).then(b.date.cmp(&a.date)));
+        busiest_days.truncate(limit);
+
+        Self {
+            installers,
+            recently_updated,
+            never_updated,
+            busiest_days,
+            slowest_packages,
+        }
+    }
+
+    /// Return the number of packages acted upon by each installer, most used first.
+    pub fn installers(&self) -> &Vec<InstallerStatistics> {
+        &self.installers
+    }
+
+    /// Return the most recent update of each package set, most recent first.
+    pub fn recently_updated(&self) -> &Vec<AppliedPackageSet> {
+        &self.recently_updated
+    }
+
+    /// Return the first log entry of each package never updated since it was installed, oldest
+    /// first.
+    pub fn never_updated(&self) -> &Vec<InstalledPackage> {
+        &self.never_updated
+    }
+
+    /// Return the days on which the most packages were acted upon, busiest first.
+    pub fn busiest_days(&self) -> &Vec<DayStatistics> {
+        &self.busiest_days
+    }
+
+    /// Return the packages that took the most time to act upon, slowest first.
+    pub fn slowest_packages(&self) -> &Vec<PackageDurations> {
+        &self.slowest_packages
+    }
+
+    /// Returns `true` if there are no entries in the install log to aggregate, else `false`.
+    pub fn is_empty(&self) -> bool {
+        self.installers.is_empty() && self.recently_updated.is_empty()
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+
+impl InstallerStatistics {
+    /// Return the name of the installer.
+    pub fn installer_name(&self) -> &Name {
+        &self.installer_name
+    }
+
+    /// Return the number of times the installer acted on a package.
+    pub fn count(&self) -> u32 {
+        self.count
+    }
+
+    /// Return the number of distinct packages the installer acted on.
+    pub fn packages(&self) -> u32 {
+        self.packages
+    }
+
+    /// Return the average time the installer took, if any durations were recorded.
+    pub fn average(&self) -> &Option<Duration> {
+        &self.average
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+
+impl DayStatistics {
+    /// Return the day, in UTC.
+    pub fn date(&self) -> &time::Date {
+        &self.date
+    }
+
+    /// Return the number of packages acted upon on this day.
+    pub fn count(&self) -> u32 {
+        self.count
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+// Private Types
+// ------------------------------------------------------------------------------------------------
+
+// Package set group and package set names.
+type PackageSetKey = (Name, Name);
+
+// Package set group, package set, and package names.
+type PackageKey = (Name, Name, Name);
+
+// ------------------------------------------------------------------------------------------------
+// Private Functions
+// ------------------------------------------------------------------------------------------------
+
+fn package_key(entry: &InstalledPackage) -> PackageKey {
+    (
+        entry.package_set_group_name().clone(),
+        entry.package_set_name().clone(),
+        entry.package_name().clone(),
+    )
+}
+
+fn package_set_key(package_set_group_name: &Name, package_set_name: &Name) -> PackageSetKey {
+    (package_set_group_name.clone(), package_set_name.clone())
+}
+
+// ------------------------------------------------------------------------------------------------
+// Unit Tests
+// ------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use std::str::FromStr;
+
+    fn name(s: &str) -> Name {
+        Name::from_str(s).unwrap()
+    }
+
+    fn date_time(day: u8, hour: u8) -> Option<time::OffsetDateTime> {
+        Some(
+            time::Date::try_from_ymd(2021, 3, day)
+                .unwrap()
+                .try_with_hms(hour, 0, 0)
+                .unwrap()
+                .assume_utc(),
+        )
+    }
+
+    fn installed_entry(day: u8, set: &str, package: &str, installer: &str) -> InstalledPackage {
+        InstalledPackage {
+            date_time: date_time(day, 12),
+            duration: Some(Duration::from_secs(day as u64)),
+            ..InstalledPackage::new(name("tools"), name(set), name(package), name(installer))
+        }
+    }
+
+    fn applied_entry(day: u8, set: &str, action: &InstallActionKind) -> AppliedPackageSet {
+        AppliedPackageSet {
+            date_time: date_time(day, 13),
+            package_set_group_name: name("tools"),
+            package_set_name: name(set),
+            action: action.to_string(),
+            content_hash: String::new(),
+            machine: None,
+        }
+    }
+
+    #[test]
+    fn test_aggregate_statistics() {
+        let installed = vec![
+            installed_entry(1, "rust", "ripgrep", "cargo"),
+            installed_entry(1, "rust", "fd-find", "cargo"),
+            installed_entry(1, "shell", "zsh", "homebrew"),
+            installed_entry(2, "editors", "helix", "homebrew"),
+            installed_entry(3, "rust", "ripgrep", "cargo"),
+            installed_entry(3, "rust", "fd-find", "cargo"),
+        ];
+        let applied = vec![
+            applied_entry(3, "rust", &InstallActionKind::Update),
+            applied_entry(2, "editors", &InstallActionKind::Uninstall),
+            applied_entry(2, "editors", &InstallActionKind::Install),
+            applied_entry(1, "shell", &InstallActionKind::Install),
+            applied_entry(1, "rust", &InstallActionKind::Install),
+        ];
+        let statistics = LogStatistics::aggregate(installed, applied, Vec::default(), 0);
+
+        assert_eq!(
+            statistics.installers(),
+            &vec![
+                InstallerStatistics {
+                    installer_name: name("cargo"),
+                    count: 4,
+                    packages: 2,
+                    average: Some(Duration::from_secs(2)),
+                },
+                InstallerStatistics {
+                    installer_name: name("homebrew"),
+                    count: 2,
+                    packages: 2,
+                    average: Some(Duration::from_millis(1500)),
+                },
+            ]
+        );
+
+        let recently_updated: Vec<String> = statistics
+            .recently_updated()
+            .iter()
+            .map(|applied| applied.package_set_name().to_string())
+            .collect();
+        assert_eq!(recently_updated, vec!["rust".to_string()]);
+
+        // helix was uninstalled, and the rust package set was updated.
+        let never_updated: Vec<String> = statistics
+            .never_updated()
+            .iter()
+            .map(|installed| installed.package_name().to_string())
+            .collect();
+        assert_eq!(never_updated, vec!["zsh".to_string()]);
+
+        let busiest_days: Vec<(u8, u32)> = statistics
+            .busiest_days()
+            .iter()
+            .map(|day| (day.date().day(), day.count()))
+            .collect();
+        assert_eq!(busiest_days, vec![(1, 3), (3, 2), (2, 1)]);
+
+        let statistics = LogStatistics::aggregate(
+            vec![installed_entry(1, "rust", "ripgrep", "cargo")],
+            Vec::default(),
+            Vec::default(),
+            1,
+        );
+        assert_eq!(statistics.never_updated().len(), 1);
+        assert_eq!(statistics.busiest_days().len(), 1);
+        assert!(!statistics.is_empty());
+    }
+}
@@ -0,0 +1,180 @@
+/*!
+A simple renderer of Markdown text for the terminal, used to show the README files of groups and
+package sets. Only the common block elements, headings, lists, block quotes, rules, and fenced
+code, and the common inline elements, emphasis, code, and links, are recognized; anything else
+is printed as written.
+
+When rendering with ANSI styles headings and strong text are shown in bold, emphasis in italics,
+and code in a dimmed style; without styles the markup is simply removed, and headings underlined.
+*/
+
+use regex::{Captures, Regex};
+
+// ------------------------------------------------------------------------------------------------
+// Public Functions
+// ------------------------------------------------------------------------------------------------
+
+///
+/// Render the Markdown text for the terminal, with ANSI styles if `ansi` is `true`, returning the
+/// rendered lines.
+///
+pub fn render_markdown(markdown: &str, ansi: bool) -> Vec<String> {
+    let mut lines: Vec<String> = Default::default();
+    let mut in_code_block = false;
+    for line in markdown.lines() {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_code_block = !in_code_block;
+        } else if in_code_block {
+            lines.push(style(&format!("    {}", line), DIM, ansi));
+        } else if let Some(captures) = HEADING.captures(line) {
+            let level = captures.get(1).unwrap().as_str().len();
+            let text = render_inline(captures.get(2).unwrap().as_str(), false);
+            if ansi {
+                lines.push(style(
+                    &text,
+                    if level == 1 { BOLD_UNDERLINE } else { BOLD },
+                    ansi,
+                ));
+            } else {
+                let underline = if level == 1 { "=" } else { "-" };
+                lines.push(text.clone());
+                lines.push(underline.repeat(text.chars().count()));
+            }
+        } else if RULE.is_match(line) {
+            lines.push("─".repeat(RULE_WIDTH));
+        } else if let Some(captures) = LIST_ITEM.captures(line) {
+            lines.push(format!(
+                "{}  • {}",
+                captures.get(1).unwrap().as_str(),
+                render_inline(captures.get(2).unwrap().as_str(), ansi)
+            ));
+        } else if let Some(captures) = BLOCK_QUOTE.captures(line) {
+            lines.push(format!(
+                "  │ {}",
+                render_inline(captures.get(1).unwrap().as_str(), ansi)
+            ));
+        } else {
+            lines.push(render_inline(line.trim_end(), ansi));
+        }
+    }
+    lines
+}
+
+// ------------------------------------------------------------------------------------------------
+// Private Functions
+// ------------------------------------------------------------------------------------------------
+
+const BOLD: &str = "1";
+
+const BOLD_UNDERLINE: &str = "1;4";
+
+const ITALIC: &str = "3";
+
+const DIM: &str = "2";
+
+const RULE_WIDTH: usize = 40;
+
+lazy_static! {
+    static ref HEADING: Regex = Regex::new(r"^(#{1,6})\s+(.*?)\s*#*\s*$").unwrap();
+    static ref RULE: Regex = Regex::new(r"^\s*([-*_])(\s*[-*_]){2,}\s*$").unwrap();
+    static ref LIST_ITEM: Regex = Regex::new(r"^(\s*)[-*+]\s+(.*)$").unwrap();
+    static ref BLOCK_QUOTE: Regex = Regex::new(r"^\s*>\s?(.*)$").unwrap();
+    static ref CODE: Regex = Regex::new(r"`([^`]+)`").unwrap();
+    static ref STRONG: Regex = Regex::new(r"(\*\*|__)([^*_]+)(\*\*|__)").unwrap();
+    static ref EMPHASIS: Regex = Regex::new(r"(\*|\b_)([^*_]+)(\*|_\b)").unwrap();
+    static ref LINK: Regex = Regex::new(r"\[([^\]]+)\]\(([^)]+)\)").unwrap();
+}
+
+fn render_inline(text: &str, ansi: bool) -> String {
+    let text = CODE.replace_all(text, |captures: &Captures<'_>| {
+        style(captures.get(1).unwrap().as_str(), DIM, ansi)
+    });
+    let text = STRONG.replace_all(&text, |captures: &Captures<'_>| {
+        style(captures.get(2).unwrap().as_str(), BOLD, ansi)
+    });
+    let text = EMPHASIS.replace_all(&text, |captures: &Captures<'_>| {
+        style(captures.get(2).unwrap().as_str(), ITALIC, ansi)
+    });
+    LINK.replace_all(&text, |captures: &Captures<'_>| {
+        let label = captures.get(1).unwrap().as_str();
+        let url = captures.get(2).unwrap().as_str();
+        if label == url {
+            format!("<{}>", url)
+        } else {
+            format!("{} <{}>", label, url)
+        }
+    })
+    .to_string()
+}
+
+fn style(text: &str, codes: &str, ansi: bool) -> String {
+    if ansi {
+        format!("\x1b[{}m{}\x1b[0m", codes, text)
+    } else {
+        text.to_string()
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+// Unit Tests
+// ------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const README: &str = r#"# Rust tools
+
+The **rust** package set installs `rustup`, see [the book](https://rust-lang.github.io/rustup/).
+
+## Notes
+
+* run _after_ the shell is configured
+  - nested item
+> Restart the shell.
+
+---
+
+```bash
+rustup default stable
+```
+"#;
+
+    #[test]
+    fn test_render_plain() {
+        assert_eq!(
+            render_markdown(README, false),
+            vec![
+                "Rust tools",
+                "==========",
+                "",
+                "The rust package set installs rustup, see the book <https://rust-lang.github.io/rustup/>.",
+                "",
+                "Notes",
+                "-----",
+                "",
+                "  • run after the shell is configured",
+                "    • nested item",
+                "  │ Restart the shell.",
+                "",
+                "────────────────────────────────────────",
+                "",
+                "    rustup default stable",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_render_ansi() {
+        let lines = render_markdown("## Notes\n\nUse **this** and `that`.", true);
+        assert_eq!(
+            lines,
+            vec![
+                "\x1b[1mNotes\x1b[0m",
+                "",
+                "Use \x1b[1mthis\x1b[0m and \x1b[2mthat\x1b[0m.",
+            ]
+        );
+    }
+}
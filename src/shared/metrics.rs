@@ -0,0 +1,323 @@
+/*!
+Metrics for each install, update, uninstall, link-files, or sync run, so that scheduled runs can be
+monitored like any other job. Metrics may be written to a file in the Prometheus text exposition
+format, for the node exporter's textfile collector, and may be sent to a statsd server.
+
+The textfile holds the metrics for the most recent run of each action, so that a run of one action
+does not remove the metrics of another.
+*/
+
+use crate::error::Result;
+use crate::shared::config::MetricsConfig;
+use crate::shared::installer::ExecuteSummary;
+use crate::APP_NAME;
+use std::collections::BTreeMap;
+use std::fs::read_to_string;
+use std::net::UdpSocket;
+use std::path::Path;
+use std::time::Duration;
+
+// ------------------------------------------------------------------------------------------------
+// Public Types
+// ------------------------------------------------------------------------------------------------
+
+///
+/// The metrics recorded for a single run of an action.
+///
+#[derive(Clone, Debug, PartialEq)]
+pub struct RunMetrics {
+    action: String,
+    succeeded: bool,
+    timestamp: i64,
+    duration: Duration,
+    package_sets: u32,
+    packages: u32,
+    failures: u32,
+}
+
+// ------------------------------------------------------------------------------------------------
+// Public Functions
+// ------------------------------------------------------------------------------------------------
+
+///
+/// Write the metrics for a run to the textfile and statsd server in the configuration, if they
+/// are configured.
+///
+/// Any failure to write the metrics is logged, it is never returned as an error as it should not
+/// affect the outcome of the run itself.
+///
+pub fn record_metrics(config: &MetricsConfig, metrics: &RunMetrics) {
+    if let Some(textfile) = config.textfile() {
+        if let Err(e) = metrics.write_textfile(Path::new(textfile)) {
+            warn!(
+                "record_metrics: could not write textfile {:?}, error: {}",
+                textfile, e
+            );
+        }
+    }
+    if let Some(statsd) = config.statsd() {
+        if let Err(e) = metrics.send_statsd(statsd, config.statsd_prefix()) {
+            warn!(
+                "record_metrics: could not send to statsd {:?}, error: {}",
+                statsd, e
+            );
+        }
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+// Implementations
+// ------------------------------------------------------------------------------------------------
+
+impl RunMetrics {
+    /// Create the metrics for a run of the action `action_name`, from the installer registry's
+    /// summary and the run's result.
+    pub fn new<T>(
+        action_name: &str,
+        duration: Duration,
+        summary: &ExecuteSummary,
+        result: &Result<T>,
+    ) -> Self {
+        Self {
+            action: action_name.to_string(),
+            succeeded: result.is_ok(),
+            timestamp: time::OffsetDateTime::now_utc().unix_timestamp(),
+            duration,
+            package_sets: summary.package_sets(),
+            packages: summary.packages(),
+            failures: summary.failed().len() as u32,
+        }
+    }
+
+    /// Return the name of the action run.
+    pub fn action(&self) -> &String {
+        &self.action
+    }
+
+    /// Returns `true` if the run succeeded, else `false`.
+    pub fn succeeded(&self) -> bool {
+        self.succeeded
+    }
+
+    /// Return the time the run completed, in seconds since the Unix epoch.
+    pub fn timestamp(&self) -> i64 {
+        self.timestamp
+    }
+
+    /// Return the time the run took.
+    pub fn duration(&self) -> &Duration {
+        &self.duration
+    }
+
+    /// Return the number of package sets acted upon.
+    pub fn package_sets(&self) -> u32 {
+        self.package_sets
+    }
+
+    /// Return the number of packages acted upon.
+    pub fn packages(&self) -> u32 {
+        self.packages
+    }
+
+    /// Return the number of package sets that failed.
+    pub fn failures(&self) -> u32 {
+        self.failures
+    }
+
+    /// Write these metrics to the Prometheus textfile at `path`, retaining the metrics for other
+    /// actions already in the file. The file is replaced, rather than written in place, so that
+    /// the collector never reads a partial file.
+    pub fn write_textfile(&self, path: &Path) -> Result<()> {
+        debug!("RunMetrics::write_textfile ({:?})", path);
+        let existing = if path.is_file() {
+            read_to_string(path)?
+        } else {
+            String::new()
+        };
+        let textfile = self.to_textfile(&existing);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let temp_path = path.with_extension("prom.tmp");
+        std::fs::write(&temp_path, textfile)?;
+        std::fs::rename(&temp_path, path)?;
+        Ok(())
+    }
+
+    /// Return the Prometheus textfile content for these metrics, merged with the samples for
+    /// other actions in the `existing` textfile content.
+    pub fn to_textfile(&self, existing: &str) -> String {
+        let mut samples: BTreeMap<(String, String), String> = existing
+            .lines()
+            .filter_map(parse_sample)
+            .filter(|(_, action, _)| action != &self.action)
+            .map(|(metric, action, value)| ((metric, action), value))
+            .collect();
+        for (metric, value) in self.samples() {
+            let _ = samples.insert((metric.to_string(), self.action.clone()), value);
+        }
+
+        let mut textfile = String::new();
+        for (metric, help) in METRICS {
+            let name = metric_name(metric);
+            textfile.push_str(&format!("# HELP {} {}\n", name, help));
+            textfile.push_str(&format!("# TYPE {} gauge\n", name));
+            for ((_, action), value) in samples.iter().filter(|((m, _), _)| m == metric) {
+                textfile.push_str(&format!("{}{{action=\"{}\"}} {}\n", name, action, value));
+            }
+        }
+        textfile
+    }
+
+    /// Send these metrics to the statsd server at `address`, each metric name is prefixed with
+    /// `prefix` and the action name.
+    pub fn send_statsd(&self, address: &str, prefix: &str) -> Result<()> {
+        debug!("RunMetrics::send_statsd ({:?}, {:?})", address, prefix);
+        let socket = UdpSocket::bind("0.0.0.0:0")?;
+        socket.connect(address)?;
+        for line in self.to_statsd(prefix) {
+            let _ = socket.send(line.as_bytes())?;
+        }
+        Ok(())
+    }
+
+    /// Return the statsd lines for these metrics; timers for the duration, counters for runs,
+    /// packages, and failures, and gauges for the time and success of the run.
+    pub fn to_statsd(&self, prefix: &str) -> Vec<String> {
+        let prefix = format!("{}.{}", prefix, self.action);
+        vec![
+            format!("{}.runs:1|c", prefix),
+            format!("{}.duration:{}|ms", prefix, self.duration.as_millis()),
+            format!("{}.package_sets:{}|c", prefix, self.package_sets),
+            format!("{}.packages:{}|c", prefix, self.packages),
+            format!("{}.failures:{}|c", prefix, self.failures),
+            format!("{}.success:{}|g", prefix, self.succeeded as u8),
+            format!("{}.last_run:{}|g", prefix, self.timestamp),
+        ]
+    }
+
+    // Return the metric and value for each of the textfile samples for this run.
+    fn samples(&self) -> Vec<(&'static str, String)> {
+        vec![
+            ("success", (self.succeeded as u8).to_string()),
+            ("timestamp_seconds", self.timestamp.to_string()),
+            (
+                "duration_seconds",
+                format!("{:.3}", self.duration.as_secs_f64()),
+            ),
+            ("package_sets", self.package_sets.to_string()),
+            ("packages", self.packages.to_string()),
+            ("failures", self.failures.to_string()),
+        ]
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+// Private Types
+// ------------------------------------------------------------------------------------------------
+
+// The metrics written to the textfile, without the common prefix, and their help text.
+const METRICS: [(&str, &str); 6] = [
+    (
+        "success",
+        "Whether the last run of the action succeeded (1) or failed (0).",
+    ),
+    (
+        "timestamp_seconds",
+        "The time the last run of the action completed, in seconds since the Unix epoch.",
+    ),
+    (
+        "duration_seconds",
+        "The time the last run of the action took.",
+    ),
+    (
+        "package_sets",
+        "The number of package sets acted upon by the last run of the action.",
+    ),
+    (
+        "packages",
+        "The number of packages acted upon by the last run of the action.",
+    ),
+    (
+        "failures",
+        "The number of package sets that failed in the last run of the action.",
+    ),
+];
+
+// ------------------------------------------------------------------------------------------------
+// Private Functions
+// ------------------------------------------------------------------------------------------------
+
+fn metric_name(metric: &str) -> String {
+    format!("{}_last_run_{}", APP_NAME, metric)
+}
+
+// Parse a sample line written by `to_textfile`, returning the metric (without the common prefix),
+// action, and value; comments and lines for other metrics are ignored.
+fn parse_sample(line: &str) -> Option<(String, String, String)> {
+    let (name, rest) = line.split_once("{action=\"")?;
+    let (action, value) = rest.split_once("\"} ")?;
+    let metric = name.strip_prefix(&metric_name(""))?;
+    if METRICS.iter().any(|(known, _)| *known == metric) {
+        Some((
+            metric.to_string(),
+            action.to_string(),
+            value.trim().to_string(),
+        ))
+    } else {
+        None
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+// Unit Tests
+// ------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn run_metrics(action: &str, succeeded: bool) -> RunMetrics {
+        RunMetrics {
+            action: action.to_string(),
+            succeeded,
+            timestamp: 1_615_000_000,
+            duration: Duration::from_millis(75_250),
+            package_sets: 3,
+            packages: 12,
+            failures: if succeeded { 0 } else { 1 },
+        }
+    }
+
+    #[test]
+    fn test_textfile_merges_actions() {
+        let textfile = run_metrics("install", true).to_textfile("");
+        let textfile = run_metrics("update", false).to_textfile(&textfile);
+        let textfile = run_metrics("install", false).to_textfile(&textfile);
+        let samples: Vec<&str> = textfile
+            .lines()
+            .filter(|line| line.starts_with("mcfg_last_run_success") || line.contains("failures{"))
+            .collect();
+        assert_eq!(
+            samples,
+            vec![
+                "mcfg_last_run_success{action=\"install\"} 0",
+                "mcfg_last_run_success{action=\"update\"} 0",
+                "mcfg_last_run_failures{action=\"install\"} 1",
+                "mcfg_last_run_failures{action=\"update\"} 1",
+            ]
+        );
+        assert!(textfile.contains("# TYPE mcfg_last_run_duration_seconds gauge\n"));
+        assert!(textfile.contains("mcfg_last_run_duration_seconds{action=\"update\"} 75.250\n"));
+    }
+
+    #[test]
+    fn test_statsd_lines() {
+        let lines = run_metrics("sync", false).to_statsd("mcfg");
+        assert_eq!(lines[0], "mcfg.sync.runs:1|c");
+        assert_eq!(lines[1], "mcfg.sync.duration:75250|ms");
+        assert_eq!(lines[4], "mcfg.sync.failures:1|c");
+        assert_eq!(lines[5], "mcfg.sync.success:0|g");
+    }
+}
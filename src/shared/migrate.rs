@@ -0,0 +1,332 @@
+/*!
+Format versions for the package repository and installer registry, and the migrations that
+upgrade older formats, in place, to the current one.
+
+The repository records its format version in the file `.config/repository.yml`, a repository
+without this file is read as version `1`, which differs from earlier repositories only in having
+the file. The installer registry records its version in the `version` field of the registry file,
+a registry that is simply a list of installers is version `1`; such a registry is still read, as
+if it had been migrated, but is only rewritten in the current format by the **migrate** command.
+*/
+
+use crate::error::{Error, Result};
+use serde::{Deserialize, Serialize};
+use serde_yaml::Value;
+use std::fmt::{Display, Formatter};
+use std::fs;
+use std::path::Path;
+
+// ------------------------------------------------------------------------------------------------
+// Public Types
+// ------------------------------------------------------------------------------------------------
+
+///
+/// The format version of the package repository supported by this version of the tool.
+///
+pub const REPOSITORY_FORMAT_VERSION: u32 = 1;
+
+///
+/// The format version of the installer registry supported by this version of the tool.
+///
+pub const REGISTRY_FORMAT_VERSION: u32 = 2;
+
+///
+/// The format version of a package repository that does not record its format version.
+///
+pub const UNMARKED_REPOSITORY_FORMAT_VERSION: u32 = 1;
+
+///
+/// The format version of an installer registry that is simply a list of installers.
+///
+pub const LIST_REGISTRY_FORMAT_VERSION: u32 = 1;
+
+///
+/// The name of the file, in the repository's `.config` directory, that records the repository's
+/// format version.
+///
+pub const REPOSITORY_VERSION_FILE: &str = "repository.yml";
+
+///
+/// The resources that have a format version, and so may be migrated.
+///
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MigrationTarget {
+    /// The package repository.
+    Repository,
+    /// The installer registry file.
+    Registry,
+}
+
+///
+/// A single migration, this upgrades the target from one format version to the next.
+///
+#[derive(Clone, Debug)]
+pub struct Migration {
+    target: MigrationTarget,
+    from_version: u32,
+    description: &'static str,
+    migrate: fn(&Path) -> Result<()>,
+}
+
+// ------------------------------------------------------------------------------------------------
+// Public Functions
+// ------------------------------------------------------------------------------------------------
+
+///
+/// Return the format version of the repository at `repository_path`, a repository that does not
+/// record its version is `UNMARKED_REPOSITORY_FORMAT_VERSION`.
+///
+pub fn repository_version(repository_path: &Path) -> Result<u32> {
+    let version_path = repository_path
+        .join(".config")
+        .join(REPOSITORY_VERSION_FILE);
+    if version_path.is_file() {
+        let version_file: VersionFile = serde_yaml::from_str(&fs::read_to_string(version_path)?)?;
+        Ok(version_file.version)
+    } else {
+        Ok(UNMARKED_REPOSITORY_FORMAT_VERSION)
+    }
+}
+
+///
+/// Return the format version of the installer registry content `registry_data`.
+///
+pub fn registry_version(registry_data: &str) -> Result<u32> {
+    match serde_yaml::from_str(registry_data)? {
+        Value::Sequence(_) => Ok(LIST_REGISTRY_FORMAT_VERSION),
+        Value::Mapping(_) => {
+            let version_file: VersionFile = serde_yaml::from_str(registry_data)?;
+            Ok(version_file.version)
+        }
+        _ => Err(Error::InvalidConfigValue(
+            "version".to_string(),
+            "registry is neither a list nor a mapping".to_string(),
+        )),
+    }
+}
+
+///
+/// Return the format version of the `target` at `path`.
+///
+pub fn format_version(target: MigrationTarget, path: &Path) -> Result<u32> {
+    match target {
+        MigrationTarget::Repository => repository_version(path),
+        MigrationTarget::Registry => registry_version(&fs::read_to_string(path)?),
+    }
+}
+
+///
+/// Check that `version` is the format version supported by this version of the tool, returning
+/// an error that describes how to fix the problem if not.
+///
+pub fn check_format_version(target: MigrationTarget, version: u32) -> Result<()> {
+    let current = target.current_version();
+    if version < current {
+        Err(Error::MigrationRequired(
+            target.to_string(),
+            version,
+            current,
+        ))
+    } else if version > current {
+        Err(Error::UnsupportedFormatVersion(
+            target.to_string(),
+            version,
+            current,
+        ))
+    } else {
+        Ok(())
+    }
+}
+
+///
+/// Return the migrations, in order, required to upgrade `target` from `version` to the current
+/// format version.
+///
+pub fn pending_migrations(target: MigrationTarget, version: u32) -> Vec<&'static Migration> {
+    let mut pending: Vec<&'static Migration> = MIGRATIONS
+        .iter()
+        .filter(|migration| migration.target == target && migration.from_version >= version)
+        .collect();
+    pending.sort_by_key(|migration| migration.from_version);
+    pending
+}
+
+// ------------------------------------------------------------------------------------------------
+// Implementations
+// ------------------------------------------------------------------------------------------------
+
+impl Display for MigrationTarget {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                MigrationTarget::Repository => "package repository",
+                MigrationTarget::Registry => "installer registry",
+            }
+        )
+    }
+}
+
+impl MigrationTarget {
+    /// Return the format version of this target supported by this version of the tool.
+    pub fn current_version(&self) -> u32 {
+        match self {
+            MigrationTarget::Repository => REPOSITORY_FORMAT_VERSION,
+            MigrationTarget::Registry => REGISTRY_FORMAT_VERSION,
+        }
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+
+impl Display for Migration {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} version {} -> {}: {}",
+            self.target,
+            self.from_version,
+            self.to_version(),
+            self.description
+        )
+    }
+}
+
+impl Migration {
+    /// Return the resource upgraded by this migration.
+    pub fn target(&self) -> MigrationTarget {
+        self.target
+    }
+
+    /// Return the format version this migration upgrades from.
+    pub fn from_version(&self) -> u32 {
+        self.from_version
+    }
+
+    /// Return the format version this migration upgrades to.
+    pub fn to_version(&self) -> u32 {
+        self.from_version + 1
+    }
+
+    /// Return a description of the changes made by this migration.
+    pub fn description(&self) -> &'static str {
+        self.description
+    }
+
+    /// Apply this migration to the target at `path`, this will fail if the target is not at
+    /// the version this migration upgrades from.
+    pub fn apply(&self, path: &Path) -> Result<()> {
+        info!("Migration::apply {} to {:?}", self, path);
+        let version = format_version(self.target, path)?;
+        if version != self.from_version {
+            return Err(Error::UnsupportedFormatVersion(
+                self.target.to_string(),
+                version,
+                self.from_version,
+            ));
+        }
+        (self.migrate)(path)
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+// Private Types
+// ------------------------------------------------------------------------------------------------
+
+#[derive(Deserialize, Serialize, Debug)]
+struct VersionFile {
+    version: u32,
+}
+
+// The registry of all migrations, for each target there must be one migration from each
+// version to the next, ending at the current version. The repository has none, as the only
+// change so far, recording the format version, is not required to read it.
+const MIGRATIONS: &[Migration] = &[Migration {
+    target: MigrationTarget::Registry,
+    from_version: 1,
+    description: "move the list of installers into the 'installers' field, add 'version'",
+    migrate: registry_from_1,
+}];
+
+// ------------------------------------------------------------------------------------------------
+// Private Functions
+// ------------------------------------------------------------------------------------------------
+
+fn registry_from_1(registry_path: &Path) -> Result<()> {
+    // This is a textual change, indenting the existing list, so that any comments are kept.
+    let registry_data = fs::read_to_string(registry_path)?;
+    let mut migrated = String::from("---\nversion: 2\ninstallers:\n");
+    for line in registry_data
+        .lines()
+        .skip_while(|line| line.trim().is_empty() || line.trim() == "---")
+    {
+        if line.is_empty() {
+            migrated.push('\n');
+        } else {
+            migrated.push_str(&format!("  {}\n", line));
+        }
+    }
+    fs::write(registry_path, migrated)?;
+    Ok(())
+}
+
+// ------------------------------------------------------------------------------------------------
+// Unit Tests
+// ------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_migrations_are_complete() {
+        for target in &[MigrationTarget::Repository, MigrationTarget::Registry] {
+            let all = pending_migrations(*target, 0);
+            let mut version = all
+                .first()
+                .map(|migration| migration.from_version())
+                .unwrap_or_else(|| target.current_version());
+            for migration in all {
+                assert_eq!(migration.from_version(), version);
+                version = migration.to_version();
+            }
+            assert_eq!(version, target.current_version());
+        }
+    }
+
+    #[test]
+    fn test_unmarked_repository_is_current() {
+        let version = repository_version(Path::new("/no/such/mcfg/repository")).unwrap();
+        assert_eq!(version, UNMARKED_REPOSITORY_FORMAT_VERSION);
+        assert!(check_format_version(MigrationTarget::Repository, version).is_ok());
+        assert!(pending_migrations(MigrationTarget::Repository, version).is_empty());
+    }
+
+    #[test]
+    fn test_migrate_registry() {
+        let work_dir = tempfile::tempdir().unwrap();
+        let registry_path = work_dir.path().join("installers.yml");
+        fs::write(
+            &registry_path,
+            "---\n# standard\n- name: brew\n  kind: default\n  commands:\n    install: \"brew install\"\n",
+        )
+        .unwrap();
+        assert_eq!(
+            format_version(MigrationTarget::Registry, &registry_path).unwrap(),
+            1
+        );
+        for migration in pending_migrations(MigrationTarget::Registry, 1) {
+            migration.apply(&registry_path).unwrap();
+        }
+        let version = format_version(MigrationTarget::Registry, &registry_path).unwrap();
+        let migrated = fs::read_to_string(&registry_path).unwrap();
+
+        assert_eq!(version, REGISTRY_FORMAT_VERSION);
+        assert!(migrated.contains("  # standard\n  - name: brew\n"));
+        assert!(check_format_version(MigrationTarget::Registry, version).is_ok());
+        assert!(check_format_version(MigrationTarget::Registry, 1).is_err());
+        assert!(check_format_version(MigrationTarget::Registry, 3).is_err());
+    }
+}
@@ -3,17 +3,48 @@ Common modules used by the actions defined in the [`actions`](../actions/index.h
 
 * Models
   * **package sets** - the things you keep in your repository
+  * **version** - the versions of things installed, and constraints on them
   * **installers** - the things that install package sets
+  * **config** - the local machine settings for the tool itself
+  * **dirs** - the standard directories for configuration, data, and logs
+  * **selection** - the package sets an action is performed on
+  * **suggest** - the names suggested when a name is not found
+  * **lint** - static checks on the package repository
+  * **migrate** - format versions, and migrations between them, for the repository and registry
 * Logging
-  * **install log** - the place we record what the installers did
+  * **install log** - the place we record what the installers did, in SQLite (feature
+    `sqlite-log`, the default) or JSON-lines (feature `jsonl-log`) files
+  * **log stats** - statistics aggregated from the install log
+  * **lock** - the place we record versions resolved at install time
+  * **answers** - the place we record the user's answers to prompted variables
+  * **trust** - the place we record the user's approval of package set scripts
+  * **cache** - the place we keep parsed package sets, so they are not parsed on every command
 * Command Execution
+  * **cargo** - native support for the Rust `cargo` installer
+  * **download** - the built-in installer for downloaded files
+  * **github** - the built-in installer for GitHub release assets (feature `github-releases`)
+  * **git** - credentials, submodules, and commits for the package repository (feature `git`,
+    the default)
+  * **vcs** - the version control system used for the package repository; Git, a plain
+    directory, a downloaded archive, or an object store (feature `object-store`)
   * **shell command** - the way we execute installers
   * **editor** - when we need to edit things
-  * **env** - the environment variables we set for executing installers
+  * **prompt** - when we need to ask the user things
+  * **markdown** - when we need to show the user README files
+  * **env** - the environment variables we set for executing installers, and the user or system
+    scope
+  * **env script** - the shell scripts that set up the environment of installed package sets
+  * **secrets** - variables resolved from external secret providers
+* Notifications
+  * **notify** - how we tell the user a long-running action completed, and how we post run
+    summaries to webhooks (feature `webhooks`)
+  * **metrics** - the metrics we record for each run, for Prometheus or statsd
 */
 
-use crate::error::{ErrorKind, Result};
-use serde::{Deserialize, Deserializer, Serialize};
+use crate::error::{Error, Result};
+use serde::de::{MapAccess, Visitor};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::{BTreeMap, HashMap};
 use std::fmt::{Display, Formatter};
 use std::path::PathBuf;
 
@@ -44,18 +75,24 @@ pub enum Platform {
 ///
 /// This enumeration captures the set of support package types.
 ///
-#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Eq, Hash)]
-#[serde(rename_all = "kebab-case")]
+/// In package set and registry files a kind is written either as `application` or `default`, or
+/// as a single entry map; `language: <name>`, or `application: <name>`.
+///
+#[derive(Serialize, Clone, Debug, PartialEq, Eq, Hash)]
+#[serde(into = "PackageKindValue")]
+#[derive(Default)]
 pub enum PackageKind {
     /// Application packages are usually those with more significant user interfaces and require
     /// more complex installations. With homebrew these would be 'casks', they may also be
-    /// Snaps on Linux for example.
-    Application,
+    /// Snaps on Linux for example. As a platform may have several application installers, such
+    /// as flatpak and snap, each is distinguished by a name; a package with no name is installed
+    /// by the application installer with the lowest priority.
+    Application(Option<Name>),
     /// These packages are installed by the standard system package installer.
+    #[default]
     Default,
     /// These packages are installed by a language-, or environment-, specific tool. For example
     /// 'cargo' for Rust, or 'conda' for Python.
-    #[serde(deserialize_with = "Name::deserialize")]
     Language(Name),
 }
 
@@ -63,15 +100,15 @@ pub enum PackageKind {
 /// A Trait that is used by model elements that have file-system backed persistence.
 ///
 pub trait FileSystemResource {
-    /// The assumed default path for this resource.
-    fn default_path() -> PathBuf;
+    /// The assumed default path for this resource, in `scope`.
+    fn default_path(scope: Scope) -> PathBuf;
 
-    /// Open the resource from it's default location.
-    fn open() -> Result<Self>
+    /// Open the resource from it's default location, in `scope`.
+    fn open(scope: Scope) -> Result<Self>
     where
         Self: Sized,
     {
-        Self::open_from(Self::default_path())
+        Self::open_from(Self::default_path(scope))
     }
 
     /// Open the resource from the provided location.
@@ -79,17 +116,48 @@ pub trait FileSystemResource {
     where
         Self: Sized;
 
-    /// Returns `true` if the resource exist as a directory at it's default location, else `false`.
-    fn is_dir() -> bool {
-        Self::default_path().is_dir()
+    /// Returns `true` if the resource exist as a directory at it's default location, in `scope`,
+    /// else `false`.
+    fn is_dir(scope: Scope) -> bool {
+        Self::default_path(scope).is_dir()
     }
 
-    /// Returns `true` if the resource exist as a file at it's default location, else `false`.
-    fn is_file() -> bool {
-        Self::default_path().is_file()
+    /// Returns `true` if the resource exist as a file at it's default location, in `scope`, else
+    /// `false`.
+    fn is_file(scope: Scope) -> bool {
+        Self::default_path(scope).is_file()
     }
 }
 
+// ------------------------------------------------------------------------------------------------
+// Private Types
+// ------------------------------------------------------------------------------------------------
+
+// The serialized form of `PackageKind`; the unit and named forms of an application kind share the
+// name `application`, which cannot be expressed by a single enumeration.
+#[derive(Serialize)]
+#[serde(untagged)]
+enum PackageKindValue {
+    Simple(SimpleKind),
+    Named(NamedKind),
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "kebab-case")]
+enum SimpleKind {
+    Application,
+    Default,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "kebab-case")]
+enum NamedKind {
+    Application(Name),
+    Language(Name),
+}
+
+struct PackageKindVisitor;
+
 // ------------------------------------------------------------------------------------------------
 // Implementations
 // ------------------------------------------------------------------------------------------------
@@ -103,20 +171,20 @@ impl Display for Name {
 }
 
 impl FromStr for Name {
-    type Err = crate::error::Error;
+    type Err = Error;
 
     fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
         if Name::is_valid(s) {
             Ok(Self(s.to_string()))
         } else {
-            Err(ErrorKind::InvalidNameString(s.to_string()).into())
+            Err(Error::InvalidNameString(s.to_string()))
         }
     }
 }
 
-impl Into<String> for Name {
-    fn into(self) -> String {
-        self.0
+impl From<Name> for String {
+    fn from(val: Name) -> Self {
+        val.0
     }
 }
 
@@ -176,6 +244,18 @@ impl Display for Platform {
     }
 }
 
+impl FromStr for Platform {
+    type Err = Error;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "macos" => Ok(Self::Macos),
+            "linux" => Ok(Self::Linux),
+            _ => Err(Error::InvalidPlatformString(s.to_string())),
+        }
+    }
+}
+
 impl Platform {
     /// The platform you are running on.
     #[cfg(target_os = "macos")]
@@ -198,9 +278,89 @@ impl Platform {
 
 // ------------------------------------------------------------------------------------------------
 
-impl Default for PackageKind {
-    fn default() -> Self {
-        PackageKind::Default
+impl FromStr for PackageKind {
+    type Err = Error;
+
+    /// Parses `application`, `application:<name>`, `default`, or else the name of a language
+    /// installer.
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "application" => Ok(Self::Application(None)),
+            "default" => Ok(Self::Default),
+            _ => match s.strip_prefix("application:") {
+                Some(name) => Ok(Self::Application(Some(Name::from_str(name)?))),
+                None => Ok(Self::Language(Name::from_str(s)?)),
+            },
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for PackageKind {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_any(PackageKindVisitor)
+    }
+}
+
+impl<'de> Visitor<'de> for PackageKindVisitor {
+    type Value = PackageKind;
+
+    fn expecting(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "'application', 'default', or a map with a single 'application' or 'language' name"
+        )
+    }
+
+    fn visit_str<E>(self, value: &str) -> std::result::Result<Self::Value, E>
+    where
+        E: serde::de::Error,
+    {
+        match value {
+            "application" => Ok(PackageKind::Application(None)),
+            "default" => Ok(PackageKind::Default),
+            _ => Err(E::unknown_variant(value, &["application", "default"])),
+        }
+    }
+
+    fn visit_map<A>(self, mut map: A) -> std::result::Result<Self::Value, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        let kind = match map.next_key::<String>()? {
+            None => return Err(serde::de::Error::invalid_length(0, &self)),
+            Some(key) => {
+                let name = map.next_value::<String>()?;
+                let name = Name::from_str(&name).map_err(serde::de::Error::custom)?;
+                match key.as_str() {
+                    "application" => PackageKind::Application(Some(name)),
+                    "language" => PackageKind::Language(name),
+                    _ => {
+                        return Err(serde::de::Error::unknown_variant(
+                            &key,
+                            &["application", "language"],
+                        ))
+                    }
+                }
+            }
+        };
+        if map.next_key::<String>()?.is_some() {
+            return Err(serde::de::Error::invalid_length(2, &self));
+        }
+        Ok(kind)
+    }
+}
+
+impl From<PackageKind> for PackageKindValue {
+    fn from(kind: PackageKind) -> Self {
+        match kind {
+            PackageKind::Application(None) => Self::Simple(SimpleKind::Application),
+            PackageKind::Application(Some(name)) => Self::Named(NamedKind::Application(name)),
+            PackageKind::Default => Self::Simple(SimpleKind::Default),
+            PackageKind::Language(name) => Self::Named(NamedKind::Language(name)),
+        }
     }
 }
 
@@ -212,39 +372,201 @@ impl PackageKind {
     }
 }
 
+// ------------------------------------------------------------------------------------------------
+// Private Functions
+// ------------------------------------------------------------------------------------------------
+
+// Serialize a map with its keys in order, so that written files are stable.
+pub(crate) fn serialize_ordered_map<S, K, V>(
+    map: &HashMap<K, V>,
+    serializer: S,
+) -> std::result::Result<S::Ok, S::Error>
+where
+    S: Serializer,
+    K: Ord + Serialize,
+    V: Serialize,
+{
+    map.iter()
+        .collect::<BTreeMap<&K, &V>>()
+        .serialize(serializer)
+}
+
 // ------------------------------------------------------------------------------------------------
 // Modules
 // ------------------------------------------------------------------------------------------------
 
+#[doc(hidden)]
+pub mod answers;
+pub use answers::{PromptAnswers, ANSWERS_FILE};
+
+#[doc(hidden)]
+pub mod cache;
+pub use cache::{FileStamp, PackageSetCache, PACKAGE_SET_CACHE_FILE};
+
+#[doc(hidden)]
+pub mod cargo;
+pub use cargo::InstalledCrates;
+
 #[doc(hidden)]
 pub mod command;
 pub use command::{
-    edit_file, execute_interactive_shell, execute_shell_command, user_editor, user_shell,
+    command_environment, edit_file, execute_interactive_shell, execute_program,
+    execute_shell_command, execute_shell_command_output, find_executable, is_shell_present,
+    set_environment_policy, shell_escape, shell_var_replace, take_command_records, user_editor,
+    user_shell, CaptureMode, CommandOutput, CommandRecord, EnvironmentPolicy, ShellCommand,
+};
+
+#[doc(hidden)]
+pub mod config;
+pub use config::{
+    ArchiveConfig, Config, GitConfig, MetricsConfig, NotificationConfig, WebhookConfig, WebhookKind,
 };
 
 #[doc(hidden)]
 mod counter;
-pub use counter::StepCounter;
+pub use counter::{StepCounter, StepState, Steps, StepsSummary};
+
+#[doc(hidden)]
+pub mod dirs;
+
+#[doc(hidden)]
+pub mod download;
+pub use download::PackageDownload;
+
+#[doc(hidden)]
+pub mod env_script;
+pub use env_script::{
+    regenerate_env_scripts, write_env_scripts, EnvScript, ENV_SCRIPT_FILE, ENV_SCRIPT_FISH_FILE,
+};
 
 #[doc(hidden)]
 pub mod env;
 pub use env::{
-    add_action_vars, add_other_vars, add_package_action_vars, add_package_set_action_vars,
-    default_vars,
+    add_action_vars, add_global_vars, add_other_vars, add_package_action_vars,
+    add_package_set_action_vars, check_scope_privileges, default_vars, global_vars_paths,
+    is_built_in_var, is_root, read_vars_file, var_references, vars_scope, Scope, BUILT_IN_VARS,
+    BUILT_IN_VAR_PREFIX, ESCAPED_BRACES, VARS_FILE,
 };
 
+#[cfg(feature = "github-releases")]
+#[doc(hidden)]
+pub mod github;
+#[cfg(feature = "github-releases")]
+pub use github::PackageGitHubRelease;
+
+#[cfg(feature = "git")]
+#[doc(hidden)]
+pub mod git;
+#[cfg(feature = "git")]
+pub use git::{commit_all, commit_signature, move_path, remote_callbacks, update_submodules};
+
 #[doc(hidden)]
 pub mod install_log;
-pub use install_log::{InstalledPackage, PackageLog};
+pub use install_log::{
+    default_machine_id, local_machine_id, open_install_log, AppliedPackageSet, InstallLog,
+    InstallLogFormat, InstalledPackage, LogEntry, PackageDurations, PackageLogOrder,
+    PackageLogQuery, RunCheckpoint,
+};
+
+#[cfg(feature = "jsonl-log")]
+#[doc(hidden)]
+pub mod jsonl_log;
+#[cfg(feature = "jsonl-log")]
+pub use jsonl_log::JsonLinesLog;
 
 #[doc(hidden)]
 pub mod installer;
-pub use installer::{InstallActionKind, Installer, InstallerRegistry};
+pub use installer::{
+    execution_vars, read_registry_installers, set_installer_enabled, AuditedCommand,
+    ExecuteOptions, ExecuteSummary, IfExists, InstallActionKind, Installer, InstallerCheck,
+    InstallerCommand, InstallerRegistry, RunAs,
+};
+
+#[doc(hidden)]
+pub mod interrupt;
+pub use interrupt::{
+    check_interrupted, install_interrupt_handler, is_interrupted, INTERRUPTED_EXIT_CODE,
+};
+
+#[doc(hidden)]
+pub mod lint;
+pub use lint::{lint_installer_registry, lint_package_set_file, lint_repository, LintProblem};
+
+#[doc(hidden)]
+pub mod log_stats;
+pub use log_stats::{DayStatistics, InstallerStatistics, LogStatistics};
+
+#[doc(hidden)]
+pub mod lock;
+pub use lock::{LockFile, LockedPackage};
+
+#[doc(hidden)]
+pub mod markdown;
+pub use markdown::render_markdown;
+
+#[doc(hidden)]
+pub mod metrics;
+pub use metrics::{record_metrics, RunMetrics};
+
+#[doc(hidden)]
+pub mod migrate;
+pub use migrate::{Migration, MigrationTarget};
+
+#[doc(hidden)]
+pub mod notify;
+pub use notify::{notify, notify_completion};
+#[cfg(feature = "webhooks")]
+pub use notify::{notify_webhooks, post_webhook, RunSummary};
 
 #[doc(hidden)]
 pub mod packages;
-pub use packages::{Package, PackageRepository, PackageSet, PackageSetActions, PackageSetGroup};
+pub use packages::{
+    LazyPackageRepository, LazyPackageSetGroup, Package, PackageBundle, PackageRepository,
+    PackageSet, PackageSetActions, PackageSetGroup, PackageSetGroupConfig, ShellFiles,
+};
+
+#[doc(hidden)]
+pub mod prompt;
+pub use prompt::{prompt, prompt_optional, prompt_yes_no};
+
+#[doc(hidden)]
+pub mod secrets;
+pub use secrets::{is_secret_var, resolve_secret_var, SecretProvider};
+
+#[doc(hidden)]
+pub mod selection;
+pub use selection::{
+    is_ignore_case, is_same_name, set_ignore_case, NamePattern, PackageSetSelection,
+};
+
+#[doc(hidden)]
+pub mod schedule;
+pub use schedule::Schedule;
+
+#[doc(hidden)]
+pub mod suggest;
+pub use suggest::{did_you_mean, suggest_names};
+
+#[cfg(feature = "sqlite-log")]
+#[doc(hidden)]
+pub mod sqlite_log;
+#[cfg(feature = "sqlite-log")]
+pub use sqlite_log::{PackageLog, LOG_SCHEMA_VERSION};
+
+#[doc(hidden)]
+pub mod trust;
+pub use trust::{
+    package_set_scripts, script_hash, ScriptApprovals, ScriptTrust, SCRIPT_APPROVALS_FILE,
+};
+
+#[doc(hidden)]
+pub mod vcs;
+pub use vcs::{open_archive_vcs, open_repo_vcs, RepoVcs, RepoVcsKind};
+
+#[doc(hidden)]
+pub mod version;
 use std::str::FromStr;
+pub use version::{VersionConstraint, VersionStatus};
 
 ///
 /// Builder implementations to construct package and installer related struct types.
@@ -261,8 +583,9 @@ pub mod builders {
         fn build(&mut self) -> Self::Inner;
     }
 
+    pub use super::command::builders::ShellCommandBuilder;
     pub use super::installer::builders::InstallerBuilder;
     pub use super::packages::builders::{
-        PackageBuilder, PackageSetBuilder, PackageSetGroupBuilder,
+        PackageBuilder, PackageRepositoryBuilder, PackageSetBuilder, PackageSetGroupBuilder,
     };
 }
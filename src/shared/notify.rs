@@ -0,0 +1,378 @@
+/*!
+Notifications that a long-running action has completed; desktop notifications for the user, and,
+with the `webhooks` feature, a summary of the run posted to each configured webhook.
+*/
+
+#[cfg(feature = "webhooks")]
+use crate::error::Error;
+use crate::error::Result;
+#[cfg(any(feature = "webhooks", target_os = "linux", target_os = "macos"))]
+use crate::shared::builders::{Builder, ShellCommandBuilder};
+use crate::shared::command::execute_shell_command;
+#[cfg(target_os = "macos")]
+use crate::shared::command::find_executable;
+use crate::shared::config::NotificationConfig;
+#[cfg(feature = "webhooks")]
+use crate::shared::config::{WebhookConfig, WebhookKind};
+#[cfg(feature = "webhooks")]
+use crate::shared::download::curl_config_value;
+use crate::shared::install_log::duration_to_string;
+#[cfg(feature = "webhooks")]
+use crate::shared::installer::ExecuteSummary;
+use crate::APP_NAME;
+#[cfg(feature = "webhooks")]
+use serde::Serialize;
+use std::collections::HashMap;
+use std::time::Duration;
+
+// ------------------------------------------------------------------------------------------------
+// Public Types
+// ------------------------------------------------------------------------------------------------
+
+///
+/// The summary of a run, posted as JSON to generic webhooks.
+///
+#[cfg(feature = "webhooks")]
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct RunSummary {
+    machine: String,
+    action: String,
+    succeeded: bool,
+    duration_secs: u64,
+    packages: u32,
+    successes: Vec<String>,
+    failures: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+// ------------------------------------------------------------------------------------------------
+// Public Functions
+// ------------------------------------------------------------------------------------------------
+
+///
+/// Send a desktop notification that the action `action_name` has completed, successfully or not.
+/// The notification is only sent if the configuration allows it and the action took at least the
+/// configured minimum duration. The `variables` are those available to a configured notification
+/// command, see `notify`.
+///
+/// Any failure to send the notification is logged, it is never returned as an error as it should
+/// not affect the outcome of the action itself.
+///
+pub fn notify_completion<T>(
+    config: &NotificationConfig,
+    variables: &HashMap<String, String>,
+    action_name: &str,
+    duration: Duration,
+    result: &Result<T>,
+) {
+    if duration.as_secs() < config.min_duration_secs() {
+        debug!(
+            "notify_completion: action {} too quick to notify ({:?})",
+            action_name, duration
+        );
+        return;
+    }
+    let message = match result {
+        Ok(_) if config.on_success() => format!(
+            "{} completed in {}",
+            action_name,
+            duration_to_string(duration)
+        ),
+        Err(e) if config.on_failure() => format!(
+            "{} failed after {}: {}",
+            action_name,
+            duration_to_string(duration),
+            e
+        ),
+        _ => return,
+    };
+    if let Err(e) = notify(config, variables, APP_NAME, &message) {
+        warn!(
+            "notify_completion: could not send notification, error: {}",
+            e
+        );
+    }
+}
+
+///
+/// Send a desktop notification. If the configuration provides a command it is executed with
+/// `variables`, and the variables `notification_title` and `notification_message`, set, otherwise
+/// the platform default is used; `notify-send` on Linux and either `terminal-notifier` or
+/// `osascript` on macOS.
+///
+pub fn notify(
+    config: &NotificationConfig,
+    variables: &HashMap<String, String>,
+    title: &str,
+    message: &str,
+) -> Result<()> {
+    debug!("notify ({:?}, {:?})", title, message);
+    match config.command() {
+        Some(cmd_str) => {
+            let mut variable_replacements = variables.clone();
+            let _ =
+                variable_replacements.insert("notification_title".to_string(), title.to_string());
+            let _ = variable_replacements
+                .insert("notification_message".to_string(), message.to_string());
+            let _ = execute_shell_command(cmd_str, &variable_replacements)?;
+        }
+        None => platform_notify(title, message)?,
+    }
+    Ok(())
+}
+
+///
+/// Post the summary of a run to each of the configured webhooks that accepts it, depending on
+/// whether the run succeeded or failed.
+///
+/// Any failure to post to a webhook is logged, it is never returned as an error as it should not
+/// affect the outcome of the run itself.
+///
+#[cfg(feature = "webhooks")]
+pub fn notify_webhooks(webhooks: &[WebhookConfig], summary: &RunSummary) {
+    for webhook in webhooks.iter().filter(|webhook| {
+        if summary.succeeded {
+            webhook.on_success()
+        } else {
+            webhook.on_failure()
+        }
+    }) {
+        if let Err(e) = post_webhook(webhook, summary) {
+            warn!("notify_webhooks: could not post run summary, error: {}", e);
+        }
+    }
+}
+
+///
+/// Post the summary of a run to the webhook, with a body in the form the webhook's kind expects.
+///
+#[cfg(feature = "webhooks")]
+pub fn post_webhook(webhook: &WebhookConfig, summary: &RunSummary) -> Result<()> {
+    debug!("post_webhook ({:?}, {:?})", webhook.url(), webhook.kind());
+    let body = summary.to_body(webhook.kind())?;
+    // The URL, which may include a secret token, and the body are passed on stdin so that they
+    // are not visible in the process list, or logged.
+    let _ = ShellCommandBuilder::program("curl")
+        .args(&[
+            "--fail",
+            "--silent",
+            "--show-error",
+            "--max-time",
+            &WEBHOOK_TIMEOUT_SECS.to_string(),
+            "--header",
+            "Content-Type: application/json",
+            "--config",
+            "-",
+        ])
+        .input(&format!(
+            "url = {}\ndata-binary = {}\n",
+            curl_config_value(webhook.url()),
+            curl_config_value(&body)
+        ))
+        .apply_environment_policy(false)
+        .build()
+        .execute()
+        .map_err(|e| Error::WebhookFailed(webhook.url().clone(), e.to_string()))?;
+    Ok(())
+}
+
+// ------------------------------------------------------------------------------------------------
+// Implementations
+// ------------------------------------------------------------------------------------------------
+
+#[cfg(feature = "webhooks")]
+impl RunSummary {
+    /// Create the summary of a run of the action `action_name` on the machine `machine`, from the
+    /// installer registry's summary and the run's result.
+    pub fn new<T>(
+        machine: &str,
+        action_name: &str,
+        duration: Duration,
+        summary: &ExecuteSummary,
+        result: &Result<T>,
+    ) -> Self {
+        Self {
+            machine: machine.to_string(),
+            action: action_name.to_string(),
+            succeeded: result.is_ok(),
+            duration_secs: duration.as_secs(),
+            packages: summary.packages(),
+            successes: summary.succeeded().clone(),
+            failures: summary.failed().clone(),
+            error: result.as_ref().err().map(|e| e.to_string()),
+        }
+    }
+
+    /// Return the identifier of the machine the run was on.
+    pub fn machine(&self) -> &String {
+        &self.machine
+    }
+
+    /// Return the name of the action run.
+    pub fn action(&self) -> &String {
+        &self.action
+    }
+
+    /// Returns `true` if the run succeeded, else `false`.
+    pub fn succeeded(&self) -> bool {
+        self.succeeded
+    }
+
+    /// Return the package sets acted upon successfully, as `group/package-set`.
+    pub fn successes(&self) -> &Vec<String> {
+        &self.successes
+    }
+
+    /// Return the package sets that failed, as `group/package-set`.
+    pub fn failures(&self) -> &Vec<String> {
+        &self.failures
+    }
+
+    /// Return a single line describing the run, as used for chat messages.
+    pub fn message(&self) -> String {
+        let duration = duration_to_string(Duration::from_secs(self.duration_secs));
+        let mut message = if self.succeeded {
+            format!(
+                "{} {} on {} completed in {}; {} package sets, {} packages",
+                APP_NAME,
+                self.action,
+                self.machine,
+                duration,
+                self.successes.len(),
+                self.packages
+            )
+        } else {
+            format!(
+                "{} {} on {} failed after {}; {} package sets succeeded",
+                APP_NAME,
+                self.action,
+                self.machine,
+                duration,
+                self.successes.len()
+            )
+        };
+        if !self.failures.is_empty() {
+            message.push_str(&format!(", failed: {}", self.failures.join(", ")));
+        }
+        if let Some(error) = &self.error {
+            message.push_str(&format!(" ({})", error));
+        }
+        message
+    }
+
+    /// Return the request body for a webhook of the given kind.
+    pub fn to_body(&self, kind: WebhookKind) -> Result<String> {
+        Ok(match kind {
+            WebhookKind::Generic => serde_json::to_string(self)?,
+            WebhookKind::Slack => serde_json::to_string(&serde_json::json!({
+                "text": self.message()
+            }))?,
+        })
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+// Private Types
+// ------------------------------------------------------------------------------------------------
+
+// The longest time to wait for a webhook to respond.
+#[cfg(feature = "webhooks")]
+const WEBHOOK_TIMEOUT_SECS: u64 = 10;
+
+// ------------------------------------------------------------------------------------------------
+// Private Functions
+// ------------------------------------------------------------------------------------------------
+
+#[cfg(target_os = "linux")]
+fn platform_notify(title: &str, message: &str) -> Result<()> {
+    let _ = ShellCommandBuilder::program("notify-send")
+        .args(&[title, message])
+        .apply_environment_policy(false)
+        .build()
+        .execute()?;
+    Ok(())
+}
+
+#[cfg(target_os = "macos")]
+fn platform_notify(title: &str, message: &str) -> Result<()> {
+    let mut builder = if find_executable("terminal-notifier").is_some() {
+        let mut builder = ShellCommandBuilder::program("terminal-notifier");
+        let _ = builder.args(&["-title", title, "-message", message]);
+        builder
+    } else {
+        debug!("platform_notify: no terminal-notifier, using osascript");
+        let mut builder = ShellCommandBuilder::program("osascript");
+        let _ = builder.arg("-e").arg(&format!(
+            "display notification {:?} with title {:?}",
+            message, title
+        ));
+        builder
+    };
+    let _ = builder.apply_environment_policy(false).build().execute()?;
+    Ok(())
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+fn platform_notify(title: &str, message: &str) -> Result<()> {
+    info!(
+        "platform_notify: no desktop notifications on this platform, {}: {}",
+        title, message
+    );
+    Ok(())
+}
+
+// ------------------------------------------------------------------------------------------------
+// Unit Tests
+// ------------------------------------------------------------------------------------------------
+
+#[cfg(all(test, feature = "webhooks"))]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn failed_run() -> RunSummary {
+        RunSummary {
+            machine: "laptop".to_string(),
+            action: "update".to_string(),
+            succeeded: false,
+            duration_secs: 75,
+            packages: 3,
+            successes: vec!["tools/rust".to_string()],
+            failures: vec!["tools/shell".to_string()],
+            error: Some("boom".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_generic_webhook_body() {
+        let body: serde_json::Value =
+            serde_json::from_str(&failed_run().to_body(WebhookKind::Generic).unwrap()).unwrap();
+        assert_eq!(
+            body,
+            serde_json::json!({
+                "machine": "laptop",
+                "action": "update",
+                "succeeded": false,
+                "duration_secs": 75,
+                "packages": 3,
+                "successes": ["tools/rust"],
+                "failures": ["tools/shell"],
+                "error": "boom"
+            })
+        );
+    }
+
+    #[test]
+    fn test_slack_webhook_body() {
+        let body: serde_json::Value =
+            serde_json::from_str(&failed_run().to_body(WebhookKind::Slack).unwrap()).unwrap();
+        assert_eq!(
+            body,
+            serde_json::json!({
+                "text": "mcfg update on laptop failed after 1m 15.0s; 1 package sets succeeded, \
+                         failed: tools/shell (boom)"
+            })
+        );
+    }
+}
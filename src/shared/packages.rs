@@ -1,14 +1,26 @@
-use crate::error::Result;
-use crate::shared::{FileSystemResource, InstallActionKind, Name, PackageKind, Platform};
+use crate::error::{Error, Result};
+use crate::shared::cache::{FileStamp, PackageSetCache};
+use crate::shared::dirs;
+use crate::shared::migrate::{check_format_version, repository_version, MigrationTarget};
+use crate::shared::selection::{is_same_name, PackageSetSelection};
+use crate::shared::suggest::suggest_names;
+#[cfg(feature = "github-releases")]
+use crate::shared::PackageGitHubRelease;
+use crate::shared::{
+    is_shell_present, serialize_ordered_map, FileSystemResource, InstallActionKind, Name,
+    PackageDownload, PackageKind, Platform, Scope, VersionConstraint,
+};
 use crate::APP_NAME;
 use regex::Regex;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use std::collections::HashMap;
 use std::ffi::OsStr;
 use std::fs::read_dir;
 use std::io::Write;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
+use std::sync::{Arc, Mutex};
 
 // ------------------------------------------------------------------------------------------------
 // Public Types
@@ -27,11 +39,34 @@ pub struct Package {
     platform: Option<Platform>,
     #[serde(default, skip_serializing_if = "is_default")]
     kind: PackageKind,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    args: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    version: Option<VersionConstraint>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    download: Option<PackageDownload>,
+    #[cfg(feature = "github-releases")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    github: Option<PackageGitHubRelease>,
+}
+
+///
+/// A bundle is a file, within the package set directory, that describes a set of packages that an
+/// installer acts on as a single unit; for example a Homebrew `Brewfile`.
+///
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct PackageBundle {
+    file: String,
+    #[serde(default, skip_serializing_if = "is_default")]
+    kind: PackageKind,
 }
 
 ///
 /// The kinds of actions a package set can perform; either a list of packages to install, *or* a
-/// map of actions to script strings.
+/// map of actions to script strings, *or* a bundle file.
 ///
 #[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
 #[serde(deny_unknown_fields, untagged, rename_all = "kebab-case")]
@@ -41,9 +76,33 @@ pub enum PackageSetActions {
         packages: Vec<Package>,
     },
     Scripts {
-        #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+        #[serde(
+            default,
+            skip_serializing_if = "HashMap::is_empty",
+            serialize_with = "serialize_ordered_map"
+        )]
         scripts: HashMap<InstallActionKind, String>,
     },
+    Bundle {
+        bundle: PackageBundle,
+    },
+}
+
+///
+/// The files of a package set specific to a single shell; an env-file, and files to link. These
+/// are only used if the shell is present on the local machine.
+///
+#[derive(Deserialize, Serialize, Clone, Debug, Default, PartialEq)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct ShellFiles {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    env_file: Option<String>,
+    #[serde(
+        default,
+        skip_serializing_if = "HashMap::is_empty",
+        serialize_with = "serialize_ordered_map"
+    )]
+    link_files: HashMap<String, String>,
 }
 
 ///
@@ -58,23 +117,61 @@ pub struct PackageSet {
     #[serde(deserialize_with = "Name::deserialize")]
     name: Name,
     #[serde(default, skip_serializing_if = "Option::is_none")]
+    display_name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     description: Option<String>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     platform: Option<Platform>,
     #[serde(default, skip_serializing_if = "is_default")]
     optional: bool,
-    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    #[serde(default, skip_serializing_if = "is_default")]
+    priority: i32,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    tags: Vec<Name>,
+    #[serde(
+        default,
+        skip_serializing_if = "HashMap::is_empty",
+        serialize_with = "serialize_ordered_map"
+    )]
     env_vars: HashMap<String, String>,
+    #[serde(
+        default,
+        skip_serializing_if = "HashMap::is_empty",
+        serialize_with = "serialize_ordered_map"
+    )]
+    prompt_vars: HashMap<String, String>,
+    #[serde(
+        default,
+        skip_serializing_if = "HashMap::is_empty",
+        serialize_with = "serialize_ordered_map"
+    )]
+    requires: HashMap<Name, Vec<String>>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     run_before: Option<String>,
     #[serde(default, skip_serializing_if = "PackageSetActions::is_empty")]
     actions: PackageSetActions,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     env_file: Option<String>,
-    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    #[serde(
+        default,
+        skip_serializing_if = "HashMap::is_empty",
+        serialize_with = "serialize_ordered_map"
+    )]
     link_files: HashMap<String, String>,
+    #[serde(
+        default,
+        skip_serializing_if = "HashMap::is_empty",
+        serialize_with = "serialize_ordered_map"
+    )]
+    shell_files: HashMap<String, ShellFiles>,
+    #[serde(default, skip_serializing_if = "is_default")]
+    relative_links: bool,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     run_after: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    rollback: Option<String>,
+    #[serde(skip)]
+    group_config: Arc<PackageSetGroupConfig>,
 }
 
 ///
@@ -83,9 +180,32 @@ pub struct PackageSet {
 #[derive(Clone, Debug)]
 pub struct PackageSetGroup {
     path: PathBuf,
+    config: Arc<PackageSetGroupConfig>,
     package_sets: Vec<PackageSet>,
 }
 
+///
+/// The optional configuration shared by all package sets in a group, read from the file
+/// `group.yml` in the group's directory. Package sets inherit the platform, tags, and env-vars
+/// from their group; the package set's own values take precedence.
+///
+#[derive(Deserialize, Serialize, Clone, Debug, Default, PartialEq)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct PackageSetGroupConfig {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    description: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    platform: Option<Platform>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    tags: Vec<Name>,
+    #[serde(
+        default,
+        skip_serializing_if = "HashMap::is_empty",
+        serialize_with = "serialize_ordered_map"
+    )]
+    env_vars: HashMap<String, String>,
+}
+
 ///
 /// The package repository is a directory that contains package groups, which in turn contain
 /// package sets.
@@ -95,11 +215,56 @@ pub struct PackageRepository {
     package_set_groups: Vec<PackageSetGroup>,
 }
 
+///
+/// A package repository where only the directory structure has been read; groups, and their
+/// package sets, are parsed on demand. This allows a single package set, or those in a selection,
+/// to be loaded without parsing, or failing on, unrelated package sets.
+///
+#[derive(Clone, Debug)]
+pub struct LazyPackageRepository {
+    path: PathBuf,
+    package_set_groups: Vec<LazyPackageSetGroup>,
+    cache: Option<SharedPackageSetCache>,
+}
+
+///
+/// A package set group where only the paths of its package set files have been found, each
+/// package set is parsed on demand.
+///
+#[derive(Clone, Debug)]
+pub struct LazyPackageSetGroup {
+    path: PathBuf,
+    package_set_paths: Vec<PathBuf>,
+    cache: Option<SharedPackageSetCache>,
+}
+
 ///
 /// The name of the repository directory.
 ///
 pub const REPOSITORY_DIR: &str = "repository";
 
+///
+/// The name of the package set file, when a package set is a directory in a group.
+///
+pub const PACKAGE_SET_FILE: &str = "package-set.yml";
+
+///
+/// The name of the optional group configuration file, in a group's directory.
+///
+pub const GROUP_FILE: &str = "group.yml";
+
+///
+/// The name of the directory, in the repository, to which removed package sets are archived; it
+/// is not read as a group.
+///
+pub const ARCHIVE_DIR: &str = ".archive";
+
+///
+/// The name of the optional README file documenting a group, in the group's directory, or a
+/// package set, in the package set's directory.
+///
+pub const README_FILE: &str = "README.md";
+
 ///
 /// A trait implemented by things read from the file system.
 pub trait Readable {
@@ -118,6 +283,17 @@ pub trait Writeable<W: Write>: Serialize {
     }
 }
 
+// ------------------------------------------------------------------------------------------------
+// Private Types
+// ------------------------------------------------------------------------------------------------
+
+// The package set cache, shared by a repository and all of its groups.
+type SharedPackageSetCache = Arc<Mutex<PackageSetCache>>;
+
+// The most threads used to read group directories, and parse package set files, in parallel; on
+// a network file system most of the time is spent waiting on the file server, not the CPU.
+const MAX_READ_THREADS: usize = 8;
+
 // ------------------------------------------------------------------------------------------------
 // Implementations
 // ------------------------------------------------------------------------------------------------
@@ -141,12 +317,45 @@ impl PackageSetActions {
         match self {
             PackageSetActions::Packages { packages } => packages.is_empty(),
             PackageSetActions::Scripts { scripts } => scripts.is_empty(),
+            PackageSetActions::Bundle { .. } => false,
         }
     }
 }
 
 // ------------------------------------------------------------------------------------------------
 
+impl PackageBundle {
+    /// Construct a new bundle instance.
+    pub fn new(file: &str, kind: PackageKind) -> Self {
+        Self {
+            file: file.to_string(),
+            kind,
+        }
+    }
+
+    /// Return the name of the bundle file, relative to the package set directory.
+    pub fn file(&self) -> &String {
+        &self.file
+    }
+
+    /// Return the kind of installer required for this bundle.
+    pub fn kind(&self) -> &PackageKind {
+        &self.kind
+    }
+
+    /// Return a name for this bundle, used when logging, this is derived from the file name.
+    pub fn name(&self) -> Result<Name> {
+        Name::from_str(
+            &PathBuf::from(&self.file)
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy(),
+        )
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+
 impl<W: Write> Writeable<W> for Package {}
 
 impl Package {
@@ -156,6 +365,12 @@ impl Package {
             name,
             platform,
             kind,
+            id: None,
+            args: None,
+            version: None,
+            download: None,
+            #[cfg(feature = "github-releases")]
+            github: None,
         }
     }
 
@@ -164,6 +379,35 @@ impl Package {
         &self.name
     }
 
+    /// Return this package's installer-specific identifier, if one was provided. For example, the
+    /// Mac App Store identifies applications by a numeric ID rather than by name.
+    pub fn id(&self) -> &Option<String> {
+        &self.id
+    }
+
+    /// Return any additional arguments to pass to the installer for this package.
+    pub fn args(&self) -> &Option<String> {
+        &self.args
+    }
+
+    /// Return the version constraint for this package, if any.
+    pub fn version(&self) -> &Option<VersionConstraint> {
+        &self.version
+    }
+
+    /// Return the download description, if this package is installed by the built-in download
+    /// installer rather than by an installer from the registry.
+    pub fn download(&self) -> &Option<PackageDownload> {
+        &self.download
+    }
+
+    /// Return the GitHub release source, if this package is installed by the built-in GitHub
+    /// releases installer rather than by an installer from the registry.
+    #[cfg(feature = "github-releases")]
+    pub fn github(&self) -> &Option<PackageGitHubRelease> {
+        &self.github
+    }
+
     /// Return `true` if this package is intended for the current platform, else `false`.
     pub fn is_platform_match(&self) -> bool {
         Platform::CURRENT.is_match(&self.platform)
@@ -182,6 +426,40 @@ impl Package {
 
 // ------------------------------------------------------------------------------------------------
 
+impl ShellFiles {
+    /// Create shell files with the env-file `env_file`, and no files to link.
+    pub fn with_env_file(env_file: &str) -> Self {
+        Self {
+            env_file: Some(env_file.to_string()),
+            link_files: Default::default(),
+        }
+    }
+
+    /// Add a source and target to the map of link files.
+    pub fn add_link_file(self, repo_file_name: &str, local_fs_name: &str) -> Self {
+        let mut link_files = self.link_files;
+        let _ = link_files.insert(repo_file_name.to_string(), local_fs_name.to_string());
+        Self { link_files, ..self }
+    }
+
+    /// Return the name of an environment file to link, if one was provided.
+    pub fn env_file(&self) -> &Option<String> {
+        &self.env_file
+    }
+
+    /// Return the path to the environment file to link, if one was provided.
+    pub fn env_file_path(&self) -> Option<PathBuf> {
+        self.env_file.as_ref().map(PathBuf::from)
+    }
+
+    /// Return a map of file names to link.
+    pub fn link_files(&self) -> &HashMap<String, String> {
+        &self.link_files
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+
 impl Readable for PackageSet {
     fn read(path: &PathBuf) -> Result<Self> {
         debug!("PackageSet::read: reading package set file {:?}", path);
@@ -206,19 +484,30 @@ impl PackageSet {
         &self.path
     }
 
+    pub(crate) fn set_path(&mut self, path: &Path) {
+        self.path = path.to_path_buf();
+    }
+
+    /// Return the display name of this package set, if one has been provided. Unlike the package
+    /// set's name this is free text, and is only used when showing the package set to the user.
+    pub fn display_name(&self) -> &Option<String> {
+        &self.display_name
+    }
+
     /// Return the description of this package set, if one has been provided.
     pub fn description(&self) -> &Option<String> {
         &self.description
     }
 
-    /// Return `true` if this package is intended for the current platform, else `false`.
+    /// Return `true` if this package set is intended for the current platform, else `false`.
     pub fn is_platform_match(&self) -> bool {
-        Platform::CURRENT.is_match(&self.platform)
+        Platform::CURRENT.is_match(&self.inherited_platform())
     }
 
-    /// Return the platform this package is intended for, `None` implies all.
+    /// Return the platform this package set is intended for, if the package set does not specify
+    /// a platform this is inherited from its group; `None` implies all.
     pub fn platform(&self) -> Platform {
-        self.platform.as_ref().cloned().unwrap_or_default()
+        self.inherited_platform().unwrap_or_default()
     }
 
     /// Return `true` if this package set is optional, else `false`.
@@ -226,17 +515,64 @@ impl PackageSet {
         self.optional
     }
 
+    /// Return the priority of this package set, within its group package sets are executed in
+    /// priority order, lowest first, and then by name. The default priority is `0`.
+    pub fn priority(&self) -> i32 {
+        self.priority
+    }
+
+    /// Return the tags, used to select package sets across groups, for this package set; this
+    /// includes any tags inherited from its group.
+    pub fn tags(&self) -> impl Iterator<Item = &Name> {
+        self.tags.iter().chain(
+            self.group_config
+                .tags
+                .iter()
+                .filter(move |tag| !self.tags.contains(tag)),
+        )
+    }
+
+    /// Return `true` if this package set, or its group, has the tag `tag`, else `false`.
+    pub fn has_tag(&self, tag: &Name) -> bool {
+        self.tags.contains(tag) || self.group_config.tags.contains(tag)
+    }
+
     /// Return any environment variables the package set has declared for use in script strings.
     pub fn env_vars(&self) -> &HashMap<String, String> {
         &self.env_vars
     }
 
-    /// Return `true` if this package set has any actions, either package or script string.
+    /// Return the variables whose values are asked of the user, the first time they are needed
+    /// on this machine, mapped to the prompt shown.
+    pub fn prompt_vars(&self) -> &HashMap<String, String> {
+        &self.prompt_vars
+    }
+
+    /// Return the prerequisites of the package set, such as Homebrew taps or apt repositories,
+    /// mapped from the name of the installer that adds them.
+    pub fn requires(&self) -> &HashMap<Name, Vec<String>> {
+        &self.requires
+    }
+
+    /// Return the environment variables inherited from the package set's group, overridden by
+    /// those declared by the package set itself.
+    pub fn inherited_env_vars(&self) -> HashMap<String, String> {
+        let mut env_vars = self.group_config.env_vars.clone();
+        env_vars.extend(self.env_vars.clone());
+        env_vars
+    }
+
+    fn inherited_platform(&self) -> Option<Platform> {
+        self.platform
+            .as_ref()
+            .or_else(|| self.group_config.platform.as_ref())
+            .cloned()
+    }
+
+    /// Return `true` if this package set has any actions, either package, script string, or
+    /// bundle.
     pub fn has_actions(&self) -> bool {
-        !match &self.actions {
-            PackageSetActions::Packages { packages } => packages.is_empty(),
-            PackageSetActions::Scripts { scripts } => scripts.is_empty(),
-        }
+        !self.actions.is_empty()
     }
 
     /// Return the actions required by this package set.
@@ -249,7 +585,7 @@ impl PackageSet {
     pub fn packages(&self) -> Option<impl Iterator<Item = &Package>> {
         match &self.actions {
             PackageSetActions::Packages { packages } => Some(packages.iter()),
-            PackageSetActions::Scripts { .. } => None,
+            _ => None,
         }
     }
 
@@ -257,9 +593,69 @@ impl PackageSet {
     /// been provided instead.
     pub fn scripts(&self) -> Option<&HashMap<InstallActionKind, String>> {
         match &self.actions {
-            PackageSetActions::Packages { .. } => None,
             PackageSetActions::Scripts { scripts } => Some(scripts),
+            _ => None,
+        }
+    }
+
+    /// Return the bundle to process for this package set, or `None` if packages or script strings
+    /// have been provided instead.
+    pub fn bundle(&self) -> Option<&PackageBundle> {
+        match &self.actions {
+            PackageSetActions::Bundle { bundle } => Some(bundle),
+            _ => None,
+        }
+    }
+
+    /// Return a hash of the content of this package set, used to determine whether it has changed
+    /// since it was last applied. For a package set file this is the content of the file, for a
+    /// package set directory this is the content of all files in the directory. A package set
+    /// that was not read from a file, for example one built in memory, is hashed as YAML.
+    pub fn content_hash(&self) -> Result<String> {
+        let mut hasher = Sha256::new();
+        if self.path.file_name() == Some(OsStr::new(PACKAGE_SET_FILE)) {
+            let set_dir = self.path.parent().unwrap_or(&self.path);
+            let mut files = Vec::new();
+            collect_files(set_dir, &mut files)?;
+            files.sort();
+            for file in files {
+                hasher.update(
+                    file.strip_prefix(set_dir)
+                        .unwrap()
+                        .to_string_lossy()
+                        .as_bytes(),
+                );
+                hasher.update(std::fs::read(&file)?);
+            }
+        } else if self.path.is_file() {
+            hasher.update(std::fs::read(&self.path)?);
+        } else {
+            hasher.update(serde_yaml::to_string(self)?.as_bytes());
         }
+        Ok(format!("{:x}", hasher.finalize()))
+    }
+
+    /// Return the path to the README file documenting this package set, if one exists. For a
+    /// package set directory this is `README.md` in the directory, for a package set file it is
+    /// the file of the same name with the extension `md`.
+    pub fn readme_path(&self) -> Option<PathBuf> {
+        let readme_path = if self.path.file_name() == Some(OsStr::new(PACKAGE_SET_FILE)) {
+            self.path.with_file_name(README_FILE)
+        } else {
+            self.path.with_extension("md")
+        };
+        if readme_path.is_file() {
+            Some(readme_path)
+        } else {
+            None
+        }
+    }
+
+    /// Return the path to the bundle file, if a bundle was provided. The bundle file is relative
+    /// to the directory containing the package set file.
+    pub fn bundle_file_path(&self) -> Option<PathBuf> {
+        self.bundle()
+            .map(|bundle| self.path.parent().unwrap_or(&self.path).join(bundle.file()))
     }
 
     /// Return the name of an environment file to link, if one was provided.
@@ -277,14 +673,54 @@ impl PackageSet {
         &self.link_files
     }
 
-    /// Return a map of file path s to link.
+    /// Return a map of file path s to link, including those of each shell present on the local
+    /// machine.
     pub fn link_file_paths(&self) -> Vec<(PathBuf, PathBuf)> {
         self.link_files
             .iter()
+            .chain(
+                self.present_shell_files()
+                    .flat_map(|(_, shell_files)| shell_files.link_files().iter()),
+            )
             .map(|(src, tgt)| (self.path.join(src), PathBuf::from(tgt)))
             .collect()
     }
 
+    /// Return the paths to the environment files to link; the env-file, if one was provided, and
+    /// the env-file of each shell present on the local machine.
+    pub fn env_file_paths(&self) -> Vec<PathBuf> {
+        self.env_file_path()
+            .into_iter()
+            .chain(
+                self.present_shell_files()
+                    .filter_map(|(_, shell_files)| shell_files.env_file_path()),
+            )
+            .collect()
+    }
+
+    /// Return a map of shell names to the files specific to that shell.
+    pub fn shell_files(&self) -> &HashMap<String, ShellFiles> {
+        &self.shell_files
+    }
+
+    /// Return the files specific to each shell present on the local machine, ordered by shell
+    /// name.
+    pub fn present_shell_files(&self) -> impl Iterator<Item = (&String, &ShellFiles)> {
+        let mut shell_files: Vec<(&String, &ShellFiles)> = self
+            .shell_files
+            .iter()
+            .filter(|(shell, _)| is_shell_present(shell))
+            .collect();
+        shell_files.sort_by(|lhs, rhs| lhs.0.cmp(rhs.0));
+        shell_files.into_iter()
+    }
+
+    /// Return `true` if the links for the env file and link files are created relative to the
+    /// directory containing the link, else `false` and the links are absolute.
+    pub fn has_relative_links(&self) -> bool {
+        self.relative_links
+    }
+
     /// Return the script string to run before any other action, if one was provided.
     pub fn run_before(&self) -> &Option<String> {
         &self.run_before
@@ -294,6 +730,12 @@ impl PackageSet {
     pub fn run_after(&self) -> &Option<String> {
         &self.run_after
     }
+
+    /// Return the script string to run if a transactional install of this package set fails, if
+    /// one was provided.
+    pub fn rollback(&self) -> &Option<String> {
+        &self.rollback
+    }
 }
 
 // ------------------------------------------------------------------------------------------------
@@ -304,31 +746,7 @@ lazy_static! {
 
 impl Readable for PackageSetGroup {
     fn read(path: &PathBuf) -> Result<Self> {
-        debug!("PackageSetGroup::read: reading dir {:?}", path);
-        let mut group = PackageSetGroup {
-            path: path.clone(),
-            package_sets: Default::default(),
-        };
-        let yaml_extension = OsStr::new("yml");
-
-        for dir_entry in read_dir(path)? {
-            let set_path = dir_entry?.path();
-            // Option 1. Any file in this directory, "*.yml" that is package-set itself.
-            if set_path.is_file() && set_path.extension() == Some(yaml_extension) {
-                let _ = group.package_sets.push(PackageSet::read(&set_path)?);
-            }
-            // Option 2. A directory, which contains a file named "package-set.yml"
-            else if set_path.is_dir() {
-                let set_path = set_path.join("package-set.yml");
-                if set_path.is_file() {
-                    let _ = group.package_sets.push(PackageSet::read(&set_path)?);
-                }
-            } else {
-                debug!("PackageSetGroup::read: ignoring {:?}", set_path);
-            }
-        }
-        group.package_sets.sort_by_key(|ps| ps.name().clone());
-        Ok(group)
+        LazyPackageSetGroup::read(path)?.load()
     }
 }
 
@@ -336,7 +754,7 @@ impl PackageSetGroup {
     /// Return the name of this package set group, this is derived from the path of the group's
     /// directory.
     pub fn name(&self) -> Name {
-        Name::from_str(&*self.path.file_name().unwrap().to_string_lossy())
+        Name::from_str(&self.path.file_name().unwrap().to_string_lossy())
             .expect("Invalid name format!")
     }
 
@@ -360,6 +778,27 @@ impl PackageSetGroup {
         &self.path
     }
 
+    /// Return the description of this package set group, from its configuration file, if one
+    /// has been provided.
+    pub fn description(&self) -> &Option<String> {
+        &self.config.description
+    }
+
+    /// Return the configuration shared by all package sets in this group.
+    pub fn config(&self) -> &PackageSetGroupConfig {
+        &self.config
+    }
+
+    /// Return the path to the README file documenting this group, if one exists.
+    pub fn readme_path(&self) -> Option<PathBuf> {
+        let readme_path = self.path.join(README_FILE);
+        if readme_path.is_file() {
+            Some(readme_path)
+        } else {
+            None
+        }
+    }
+
     /// Return an iterator over all the package sets in this group.
     pub fn package_sets(&self) -> impl Iterator<Item = &PackageSet> {
         self.package_sets.iter()
@@ -372,65 +811,224 @@ impl PackageSetGroup {
 
     /// Return the package set named `name`, if one is present.
     pub fn package_set(&self, name: &Name) -> Option<&PackageSet> {
-        self.package_sets.iter().find(|ps| &ps.name == name)
+        self.package_sets
+            .iter()
+            .find(|ps| is_same_name(&ps.name, name))
+    }
+
+    /// Return the package set named `name`, or an error suggesting the names of similar package
+    /// sets.
+    pub fn package_set_or_suggest(&self, name: &Name) -> Result<&PackageSet> {
+        self.package_set(name).ok_or_else(|| Error::NoPackageSet {
+            group: self.name().to_string(),
+            name: name.to_string(),
+            suggestions: suggest_names(
+                &name.to_string(),
+                self.package_sets().map(|package_set| package_set.name()),
+            ),
+        })
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+
+impl PackageSetGroupConfig {
+    /// Read the group configuration file at `path`, a missing file is an empty configuration.
+    pub fn read(path: &Path) -> Result<Self> {
+        if path.is_file() {
+            debug!("PackageSetGroupConfig::read: reading group file {:?}", path);
+            Ok(serde_yaml::from_str(&std::fs::read_to_string(path)?)?)
+        } else {
+            Ok(Default::default())
+        }
+    }
+
+    /// Return the description of the group, if one has been provided.
+    pub fn description(&self) -> &Option<String> {
+        &self.description
+    }
+
+    /// Return the platform inherited by package sets that do not specify one, if provided.
+    pub fn platform(&self) -> &Option<Platform> {
+        &self.platform
+    }
+
+    /// Return the tags inherited by all package sets in the group.
+    pub fn tags(&self) -> impl Iterator<Item = &Name> {
+        self.tags.iter()
+    }
+
+    /// Return the environment variables inherited by all package sets in the group.
+    pub fn env_vars(&self) -> &HashMap<String, String> {
+        &self.env_vars
     }
 }
 
 // ------------------------------------------------------------------------------------------------
 
 lazy_static! {
-    static ref RESERVED_REPO_NAMES: Vec<&'static str> = vec![".git", ".config", ".local"];
+    static ref RESERVED_REPO_NAMES: Vec<&'static str> =
+        vec![".git", ".config", ".local", ARCHIVE_DIR];
 }
 
 impl FileSystemResource for PackageRepository {
-    fn default_path() -> PathBuf {
-        xdirs::config_dir_for(APP_NAME)
+    fn default_path(scope: Scope) -> PathBuf {
+        dirs::config_dir_for(scope, APP_NAME)
             .unwrap()
             .join(REPOSITORY_DIR)
     }
 
+    fn open(scope: Scope) -> Result<Self> {
+        LazyPackageRepository::open(scope)?.load()
+    }
+
     fn open_from(repository_path: PathBuf) -> Result<Self> {
         info!(
             "PackageRepository::actual_open: reading all package data from {:?}",
             &repository_path
         );
-        let mut package_set_groups: Vec<PackageSetGroup> = Default::default();
+        LazyPackageRepository::open_from(repository_path)?.load()
+    }
+}
+
+impl PackageRepository {
+    /// Open the repository at the default path, in `scope`, only reading the groups, and package
+    /// sets, that may be included in the selection. See `LazyPackageRepository::load_selected`.
+    pub fn open_selected(scope: Scope, selection: &PackageSetSelection) -> Result<Self> {
+        LazyPackageRepository::open(scope)?.load_selected(selection)
+    }
+
+    /// Return the path to the configuration directory included in the repository, in `scope`.
+    pub fn default_config_path(scope: Scope) -> PathBuf {
+        Self::default_path(scope).join(".config")
+    }
+
+    /// Return the path to the local content directory included in the repository, in `scope`.
+    pub fn default_local_path(scope: Scope) -> PathBuf {
+        Self::default_path(scope).join(".local")
+    }
+
+    /// Return the path to the repository root directory.
+    pub fn path(&self) -> &PathBuf {
+        &self.path
+    }
+
+    /// Return the path to the local content directory included in this repository.
+    pub fn local_path(&self) -> PathBuf {
+        self.path.join(".local")
+    }
+
+    /// Return `true` if the repository has no groups, else `false`.
+    pub fn is_empty(&self) -> bool {
+        self.package_set_groups.is_empty()
+    }
+
+    /// Return an iterator over all groups in this repository.
+    pub fn groups(&self) -> impl Iterator<Item = &PackageSetGroup> {
+        self.package_set_groups.iter()
+    }
+
+    /// Return `true` if this repository has a group named `name`, else `false`.
+    pub fn has_group(&self, name: &Name) -> bool {
+        self.group(name).is_some()
+    }
+
+    /// Return the group named `name`, if one is present.
+    pub fn group(&self, name: &Name) -> Option<&PackageSetGroup> {
+        self.package_set_groups
+            .iter()
+            .find(|psg| is_same_name(&psg.name(), name))
+    }
+
+    /// Return the group named `name`, or an error suggesting the names of similar groups.
+    pub fn group_or_suggest(&self, name: &Name) -> Result<&PackageSetGroup> {
+        self.group(name).ok_or_else(|| Error::NoPackageSetGroup {
+            name: name.to_string(),
+            suggestions: suggest_names(&name.to_string(), self.groups().map(|group| group.name())),
+        })
+    }
+
+    /// Return the single package set, `group/package_set`, returning an error if either the group
+    /// or the package set cannot be found.
+    pub fn package_set(&self, group: &Name, package_set: &Name) -> Result<&PackageSet> {
+        self.group_or_suggest(group)?
+            .package_set_or_suggest(package_set)
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+
+impl FileSystemResource for LazyPackageRepository {
+    fn default_path(scope: Scope) -> PathBuf {
+        PackageRepository::default_path(scope)
+    }
+
+    // Only the repository at the default path uses the package set cache.
+    fn open(scope: Scope) -> Result<Self> {
+        Ok(Self::open_from(Self::default_path(scope))?.with_cache(PackageSetCache::open(scope)?))
+    }
+
+    fn open_from(repository_path: PathBuf) -> Result<Self> {
+        info!(
+            "LazyPackageRepository::open_from: reading group directories from {:?}",
+            &repository_path
+        );
+        check_format_version(
+            MigrationTarget::Repository,
+            repository_version(&repository_path)?,
+        )?;
+        let mut group_paths: Vec<PathBuf> = Default::default();
         for dir_entry in read_dir(&repository_path)? {
             let group_path = dir_entry?.path();
             if group_path.is_dir() {
                 trace!(
-                    "PackageRepository::actual_open: found possible group dir {:?} -> {:?}",
+                    "LazyPackageRepository::open_from: found possible group dir {:?} -> {:?}",
                     &group_path,
                     group_path.file_name(),
                 );
                 let dir_name = group_path.file_name().unwrap().to_str().unwrap();
-                if RESERVED_REPO_NAMES.contains(&dir_name) {
+                if is_reserved_name(dir_name) {
                     debug!(
-                        "PackageRepository::actual_open: some files are always ignored ({:?}).",
+                        "LazyPackageRepository::open_from: some files are always ignored ({:?}).",
                         group_path
                     );
                 } else {
-                    package_set_groups.push(PackageSetGroup::read(&group_path)?);
+                    group_paths.push(group_path);
                 }
             }
         }
+        group_paths.sort();
+        let mut package_set_groups = parallel_map(&group_paths, |group_path| {
+            LazyPackageSetGroup::read(group_path)
+        })
+        .into_iter()
+        .collect::<Result<Vec<LazyPackageSetGroup>>>()?;
         package_set_groups.sort_by_key(|psg| psg.name());
-        Ok(PackageRepository {
+        Ok(Self {
             path: repository_path,
             package_set_groups,
+            cache: None,
         })
     }
 }
 
-impl PackageRepository {
-    /// Return the path to the configuration directory included in the repository.
-    pub fn default_config_path() -> PathBuf {
-        Self::default_path().join(".config")
-    }
-
-    /// Return the path to the local content directory included in the repository.
-    pub fn default_local_path() -> PathBuf {
-        Self::default_path().join(".local")
+impl LazyPackageRepository {
+    /// Return this repository, reading package sets through the cache rather than always parsing
+    /// the package set files.
+    pub fn with_cache(self, cache: PackageSetCache) -> Self {
+        let cache = Arc::new(Mutex::new(cache));
+        Self {
+            package_set_groups: self
+                .package_set_groups
+                .into_iter()
+                .map(|group| LazyPackageSetGroup {
+                    cache: Some(cache.clone()),
+                    ..group
+                })
+                .collect(),
+            cache: Some(cache),
+            ..self
+        }
     }
 
     /// Return the path to the repository root directory.
@@ -438,26 +1036,244 @@ impl PackageRepository {
         &self.path
     }
 
-    /// Return `true` if the repository has no groups, else `false`.
+    /// Return `true` if this repository has no groups, else `false`.
     pub fn is_empty(&self) -> bool {
         self.package_set_groups.is_empty()
     }
 
     /// Return an iterator over all groups in this repository.
-    pub fn groups(&self) -> impl Iterator<Item = &PackageSetGroup> {
+    pub fn groups(&self) -> impl Iterator<Item = &LazyPackageSetGroup> {
         self.package_set_groups.iter()
     }
 
-    /// Return `true` if this repository has a group named `name`, else `false`.
-    pub fn has_group(&self, name: &Name) -> bool {
-        self.group(name).is_some()
-    }
-
     /// Return the group named `name`, if one is present.
-    pub fn group(&self, name: &Name) -> Option<&PackageSetGroup> {
+    pub fn group(&self, name: &Name) -> Option<&LazyPackageSetGroup> {
         self.package_set_groups
             .iter()
-            .find(|psg| &psg.name() == name)
+            .find(|psg| is_same_name(&psg.name(), name))
+    }
+
+    /// Return the group named `name`, or an error suggesting the names of similar groups.
+    pub fn group_or_suggest(&self, name: &Name) -> Result<&LazyPackageSetGroup> {
+        self.group(name).ok_or_else(|| Error::NoPackageSetGroup {
+            name: name.to_string(),
+            suggestions: suggest_names(&name.to_string(), self.groups().map(|group| group.name())),
+        })
+    }
+
+    /// Load the single package set, `group/package_set`, returning an error if either the group
+    /// or the package set cannot be found.
+    pub fn package_set(&self, group: &Name, package_set: &Name) -> Result<PackageSet> {
+        let group = self.group_or_suggest(group)?;
+        let found = group.package_set(package_set)?;
+        self.save_cache();
+        found.ok_or_else(|| group.no_package_set(package_set))
+    }
+
+    /// Load every group, and package set, in this repository; failing if any cannot be parsed.
+    pub fn load(self) -> Result<PackageRepository> {
+        let groups: Vec<&LazyPackageSetGroup> = self.package_set_groups.iter().collect();
+        let package_set_groups = load_groups(&groups, |_, _| true)?;
+        self.save_cache();
+        Ok(PackageRepository {
+            path: self.path,
+            package_set_groups,
+        })
+    }
+
+    /// Load only the groups, and package sets, that may be included in the selection. A package
+    /// set file that cannot be parsed is only an error if the selection may include it, judged by
+    /// the name of its file, otherwise it is skipped with a warning.
+    pub fn load_selected(self, selection: &PackageSetSelection) -> Result<PackageRepository> {
+        if selection.is_all() {
+            return self.load();
+        }
+        let groups: Vec<&LazyPackageSetGroup> = self
+            .package_set_groups
+            .iter()
+            .filter(|group| selection.may_select_group(&group.name()))
+            .collect();
+        let package_set_groups = load_groups(&groups, |group, path| {
+            may_select_file(selection, &group.name(), path)
+        })?;
+        self.save_cache();
+        Ok(PackageRepository {
+            path: self.path,
+            package_set_groups,
+        })
+    }
+
+    // A cache that cannot be saved is only a missed optimization, so this is never an error.
+    fn save_cache(&self) {
+        if let Some(cache) = &self.cache {
+            if let Err(e) = cache.lock().unwrap().save() {
+                warn!(
+                    "LazyPackageRepository::save_cache: could not save cache, error: {}",
+                    e
+                );
+            }
+        }
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+
+impl LazyPackageSetGroup {
+    /// Find the package set files in the group directory at `path`, without parsing them.
+    pub fn read(path: &Path) -> Result<Self> {
+        debug!("LazyPackageSetGroup::read: reading dir {:?}", path);
+        let yaml_extension = OsStr::new("yml");
+        let mut package_set_paths: Vec<PathBuf> = Default::default();
+        for dir_entry in read_dir(path)? {
+            let set_path = dir_entry?.path();
+            // The group's own configuration.
+            if set_path.file_name() == Some(OsStr::new(GROUP_FILE)) {
+                continue;
+            }
+            // Option 1. Any file in this directory, "*.yml" that is package-set itself.
+            if set_path.is_file() && set_path.extension() == Some(yaml_extension) {
+                package_set_paths.push(set_path);
+            }
+            // Option 2. A directory, which contains a file named "package-set.yml"
+            else if set_path.is_dir() {
+                let set_path = set_path.join(PACKAGE_SET_FILE);
+                if set_path.is_file() {
+                    package_set_paths.push(set_path);
+                }
+            } else {
+                debug!("LazyPackageSetGroup::read: ignoring {:?}", set_path);
+            }
+        }
+        package_set_paths.sort();
+        Ok(Self {
+            path: path.to_path_buf(),
+            package_set_paths,
+            cache: None,
+        })
+    }
+
+    /// Return the name of this package set group, derived from the path of the group's directory.
+    pub fn name(&self) -> Name {
+        Name::from_str(&self.path.file_name().unwrap().to_string_lossy())
+            .expect("Invalid name format!")
+    }
+
+    /// Return the path to this package set group.
+    pub fn path(&self) -> &PathBuf {
+        &self.path
+    }
+
+    /// Return the paths of the package set files in this group, these have not been parsed.
+    pub fn package_set_paths(&self) -> impl Iterator<Item = &PathBuf> {
+        self.package_set_paths.iter()
+    }
+
+    /// Return the names of the package sets in this group, as given by the names of their files,
+    /// without parsing them.
+    pub fn package_set_file_names(&self) -> Vec<Name> {
+        self.package_set_paths
+            .iter()
+            .filter_map(|path| package_set_file_name(path))
+            .collect()
+    }
+
+    /// Return the error for a package set, `name`, not found in this group; suggesting the names
+    /// of similar package sets.
+    pub fn no_package_set(&self, name: &Name) -> Error {
+        Error::NoPackageSet {
+            group: self.name().to_string(),
+            name: name.to_string(),
+            suggestions: suggest_names(&name.to_string(), self.package_set_file_names()),
+        }
+    }
+
+    /// Read the configuration shared by all package sets in this group.
+    pub fn config(&self) -> Result<PackageSetGroupConfig> {
+        PackageSetGroupConfig::read(&self.path.join(GROUP_FILE))
+    }
+
+    /// Return an iterator that parses each package set in this group as it is reached; unlike a
+    /// loaded group these are in file name order, not priority order.
+    pub fn package_sets(&self) -> Result<impl Iterator<Item = Result<PackageSet>> + '_> {
+        let config = Arc::new(self.config()?);
+        Ok(self
+            .package_set_paths
+            .iter()
+            .map(move |path| self.read_package_set(path, &config)))
+    }
+
+    /// Load the package set named `name`, if one is present. The file named for the package set
+    /// is tried first, and only if that does not hold the package set are the remaining files
+    /// parsed; any that cannot be parsed are skipped with a warning.
+    pub fn package_set(&self, name: &Name) -> Result<Option<PackageSet>> {
+        let config = Arc::new(self.config()?);
+        let (named, others): (Vec<&PathBuf>, Vec<&PathBuf>) =
+            self.package_set_paths.iter().partition(|path| {
+                package_set_file_name(path)
+                    .map(|file_name| is_same_name(&file_name, name))
+                    .unwrap_or_default()
+            });
+        for path in named {
+            let package_set = self.read_package_set(path, &config)?;
+            if is_same_name(package_set.name(), name) {
+                return Ok(Some(package_set));
+            }
+        }
+        for path in others {
+            match self.read_package_set(path, &config) {
+                Ok(package_set) if is_same_name(package_set.name(), name) => {
+                    return Ok(Some(package_set))
+                }
+                Ok(_) => {}
+                Err(e) => warn!(
+                    "LazyPackageSetGroup::package_set: skipping {:?}, error: {}",
+                    path, e
+                ),
+            }
+        }
+        Ok(None)
+    }
+
+    /// Load this group, and all of its package sets; failing if any cannot be parsed.
+    pub fn load(&self) -> Result<PackageSetGroup> {
+        Ok(load_groups(&[self], |_, _| true)?.remove(0))
+    }
+
+    /// Load this group, and only the package sets that may be included in the selection. A
+    /// package set file that cannot be parsed is only an error if the selection may include it,
+    /// judged by the name of its file, otherwise it is skipped with a warning.
+    pub fn load_selected(&self, selection: &PackageSetSelection) -> Result<PackageSetGroup> {
+        let group_name = self.name();
+        Ok(load_groups(&[self], |_, path| {
+            may_select_file(selection, &group_name, path)
+        })?
+        .remove(0))
+    }
+
+    fn read_package_set(
+        &self,
+        path: &PathBuf,
+        config: &Arc<PackageSetGroupConfig>,
+    ) -> Result<PackageSet> {
+        // The cache is only locked to look up, and insert, entries so that other threads may
+        // parse package set files at the same time.
+        let mut package_set = match &self.cache {
+            Some(cache) => {
+                let stamp = FileStamp::of(path)?;
+                let cached = cache.lock().unwrap().get(path, &stamp);
+                match cached {
+                    Some(package_set) => package_set,
+                    None => {
+                        let package_set = PackageSet::read(path)?;
+                        cache.lock().unwrap().insert(path, stamp, &package_set);
+                        package_set
+                    }
+                }
+            }
+            None => PackageSet::read(path)?,
+        };
+        package_set.group_config = config.clone();
+        Ok(package_set)
     }
 }
 
@@ -465,16 +1281,143 @@ impl PackageRepository {
 // Private Functions
 // ------------------------------------------------------------------------------------------------
 
+// The name of a package set, as implied by its file name; either `<name>.yml` or
+// `<name>/package-set.yml`. The package set's own name is usually, but not always, the same.
+fn package_set_file_name(path: &Path) -> Option<Name> {
+    let stem = if path.file_name() == Some(OsStr::new(PACKAGE_SET_FILE)) {
+        path.parent()?.file_name()?
+    } else {
+        path.file_stem()?
+    };
+    Name::from_str(&stem.to_string_lossy()).ok()
+}
+
+// Load `groups`, parsing the package set files of all groups in parallel. A package set file
+// that cannot be parsed is an error if `is_required` returns `true` for its group and path,
+// otherwise it is skipped with a warning. The result is in the same order as `groups`, with the
+// package sets of each group in priority order, however many threads were used.
+fn load_groups<F>(groups: &[&LazyPackageSetGroup], is_required: F) -> Result<Vec<PackageSetGroup>>
+where
+    F: Fn(&LazyPackageSetGroup, &Path) -> bool,
+{
+    let configs = parallel_map(groups, |group| group.config().map(Arc::new))
+        .into_iter()
+        .collect::<Result<Vec<Arc<PackageSetGroupConfig>>>>()?;
+    let files: Vec<(usize, &PathBuf)> = groups
+        .iter()
+        .enumerate()
+        .flat_map(|(index, group)| {
+            group
+                .package_set_paths
+                .iter()
+                .map(move |path| (index, path))
+        })
+        .collect();
+    let results = parallel_map(&files, |(index, path)| {
+        groups[*index].read_package_set(path, &configs[*index])
+    });
+
+    let mut package_sets: Vec<Vec<PackageSet>> = groups.iter().map(|_| Vec::new()).collect();
+    for ((index, path), result) in files.into_iter().zip(results) {
+        match result {
+            Ok(package_set) => package_sets[index].push(package_set),
+            Err(e) if !is_required(groups[index], path) => {
+                warn!("load_groups: skipping unselected {:?}, error: {}", path, e)
+            }
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(groups
+        .iter()
+        .zip(configs)
+        .zip(package_sets)
+        .map(|((group, config), mut package_sets)| {
+            package_sets
+                .sort_by(|lhs, rhs| (lhs.priority, &lhs.name).cmp(&(rhs.priority, &rhs.name)));
+            PackageSetGroup {
+                path: group.path.clone(),
+                config,
+                package_sets,
+            }
+        })
+        .collect())
+}
+
+// Returns `true` if the package set in the file at `path` may be included in the selection,
+// judged by the name of its file; a file whose name is not a valid name may hold any package set.
+fn may_select_file(selection: &PackageSetSelection, group_name: &Name, path: &Path) -> bool {
+    match package_set_file_name(path) {
+        Some(name) => selection.may_select_package_set(group_name, &name),
+        None => true,
+    }
+}
+
+// Apply `f` to each of `items` using a small number of scoped threads, returning the results in
+// the same order as `items`.
+fn parallel_map<T, R, F>(items: &[T], f: F) -> Vec<R>
+where
+    T: Sync,
+    R: Send,
+    F: Fn(&T) -> R + Sync,
+{
+    let threads = std::thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(1)
+        .min(MAX_READ_THREADS)
+        .min(items.len());
+    if threads <= 1 {
+        return items.iter().map(f).collect();
+    }
+    let f = &f;
+    std::thread::scope(|scope| {
+        items
+            .chunks(items.len().div_ceil(threads))
+            .map(|chunk| scope.spawn(move || chunk.iter().map(f).collect::<Vec<R>>()))
+            .collect::<Vec<_>>()
+            .into_iter()
+            .flat_map(|handle| handle.join().unwrap())
+            .collect()
+    })
+}
+
+fn collect_files(dir: &Path, files: &mut Vec<PathBuf>) -> Result<()> {
+    for entry in read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_files(&path, files)?;
+        } else if path.is_file() {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+pub(crate) fn is_reserved_name(dir_name: &str) -> bool {
+    RESERVED_REPO_NAMES.contains(&dir_name)
+}
+
+// A prerequisite is quoted when it is added to an installer's add_prerequisite command, so it may
+// not contain a quote, or any control character, that could end the quoted string.
+pub(crate) fn is_valid_prerequisite(prerequisite: &str) -> bool {
+    !prerequisite.trim().is_empty()
+        && !prerequisite
+            .chars()
+            .any(|c| c == '\'' || c == '"' || c.is_control())
+}
+
 fn is_default<T: Default + PartialEq>(t: &T) -> bool {
     t == &T::default()
 }
 
 pub mod builders {
-    use crate::error::{ErrorKind, Result};
+    use crate::error::{Error, Result};
     use crate::shared::builders::Builder;
-    use crate::shared::packages::PackageSetActions;
+    use crate::shared::packages::{PackageBundle, PackageSetActions, ShellFiles};
+    #[cfg(feature = "github-releases")]
+    use crate::shared::PackageGitHubRelease;
     use crate::shared::{
-        InstallActionKind, Name, Package, PackageKind, PackageSet, PackageSetGroup, Platform,
+        InstallActionKind, Name, Package, PackageDownload, PackageKind, PackageRepository,
+        PackageSet, PackageSetGroup, Platform, VersionConstraint,
     };
     use std::collections::HashMap;
     use std::path::PathBuf;
@@ -504,6 +1447,14 @@ pub mod builders {
     #[derive(Clone, Debug)]
     pub struct PackageSetGroupBuilder(PackageSetGroup);
 
+    ///
+    /// Provides a fluent interface for programmatic creation of
+    /// [`PackageRepository`](../struct.packagerepository.html) instances, without reading them
+    /// from the file system.
+    ///
+    #[derive(Clone, Debug)]
+    pub struct PackageRepositoryBuilder(PackageRepository);
+
     // --------------------------------------------------------------------------------------------
     // Implementations
     // --------------------------------------------------------------------------------------------
@@ -535,9 +1486,48 @@ pub mod builders {
                 name,
                 platform: None,
                 kind: Default::default(),
+                id: None,
+                args: None,
+                version: None,
+                download: None,
+                #[cfg(feature = "github-releases")]
+                github: None,
             })
         }
 
+        /// Sets the download description, this package will be installed by the built-in download
+        /// installer.
+        pub fn with_download(&mut self, download: PackageDownload) -> &mut Self {
+            self.0.download = Some(download);
+            self
+        }
+
+        /// Sets the GitHub release source, this package will be installed by the built-in GitHub
+        /// releases installer.
+        #[cfg(feature = "github-releases")]
+        pub fn with_github_release(&mut self, github: PackageGitHubRelease) -> &mut Self {
+            self.0.github = Some(github);
+            self
+        }
+
+        /// Sets a version constraint for this package.
+        pub fn with_version(&mut self, version: VersionConstraint) -> &mut Self {
+            self.0.version = Some(version);
+            self
+        }
+
+        /// Sets additional arguments to pass to the installer for this package.
+        pub fn with_args(&mut self, args: &str) -> &mut Self {
+            self.0.args = Some(args.to_string());
+            self
+        }
+
+        /// Sets an installer-specific identifier for this package, used in place of the name.
+        pub fn with_id(&mut self, id: &str) -> &mut Self {
+            self.0.id = Some(id.to_string());
+            self
+        }
+
         /// Adds a platform constraint, this package is only installed on the provided platform.
         pub fn for_platform(&mut self, platform: Platform) -> &mut Self {
             self.0.platform = Some(platform);
@@ -573,7 +1563,13 @@ pub mod builders {
 
         /// This package uses the platform's application installer
         pub fn using_application_installer(&mut self) -> &mut Self {
-            self.of_kind(PackageKind::Application)
+            self.of_kind(PackageKind::Application(None))
+        }
+
+        /// This package uses the platform's application installer named `name`, such as flatpak
+        /// or snap.
+        pub fn using_named_application_installer(&mut self, name: &Name) -> &mut Self {
+            self.of_kind(PackageKind::Application(Some(name.clone())))
         }
 
         /// This package uses the specified language's installer
@@ -610,15 +1606,24 @@ pub mod builders {
             Self(PackageSet {
                 path: Default::default(),
                 name,
+                display_name: None,
                 description: None,
                 platform: None,
                 optional: false,
+                priority: 0,
+                tags: Default::default(),
                 env_vars: Default::default(),
+                prompt_vars: Default::default(),
+                requires: Default::default(),
                 run_before: None,
                 actions: Default::default(),
                 env_file: None,
                 link_files: Default::default(),
+                shell_files: Default::default(),
+                relative_links: false,
                 run_after: None,
+                rollback: None,
+                group_config: Default::default(),
             })
         }
 
@@ -628,6 +1633,12 @@ pub mod builders {
             self
         }
 
+        /// Add a display name, a human-friendly title, for this package set.
+        pub fn display_name(&mut self, display_name: &str) -> &mut Self {
+            self.0.display_name = Some(display_name.to_string());
+            self
+        }
+
         /// Add a description of this package set.
         pub fn description(&mut self, description: &str) -> &mut Self {
             self.0.description = Some(description.to_string());
@@ -668,6 +1679,18 @@ pub mod builders {
             self
         }
 
+        /// Set the priority of this package set, lower priorities are executed first.
+        pub fn priority(&mut self, priority: i32) -> &mut Self {
+            self.0.priority = priority;
+            self
+        }
+
+        /// Add a tag to this package set.
+        pub fn tag(&mut self, tag: Name) -> &mut Self {
+            self.0.tags.push(tag);
+            self
+        }
+
         /// Set the key/values to use as additional tool/environment variables.
         pub fn env_vars(&mut self, env_vars: HashMap<String, String>) -> &mut Self {
             self.0.env_vars = env_vars;
@@ -680,6 +1703,25 @@ pub mod builders {
             self
         }
 
+        /// Add a variable whose value is asked of the user, with the prompt `prompt`.
+        pub fn prompt_var(&mut self, key: &str, prompt: &str) -> &mut Self {
+            let _ = self
+                .0
+                .prompt_vars
+                .insert(key.to_string(), prompt.to_string());
+            self
+        }
+
+        /// Add a prerequisite, added by the named installer before any package is installed.
+        pub fn requires(&mut self, installer: &Name, prerequisite: &str) -> &mut Self {
+            self.0
+                .requires
+                .entry(installer.clone())
+                .or_default()
+                .push(prerequisite.to_string());
+            self
+        }
+
         /// Add a run-before script string.
         pub fn run_before(&mut self, script_string: &str) -> &mut Self {
             self.0.run_before = Some(script_string.to_string());
@@ -713,7 +1755,23 @@ pub mod builders {
                     packages.push(package);
                     Ok(self)
                 }
-                PackageSetActions::Scripts { .. } => Err(ErrorKind::InvalidBuilderState.into()),
+                _ => Err(Error::InvalidBuilderState),
+            }
+        }
+
+        /// Remove the named package from the list of packages.
+        pub fn remove_package_action(&mut self, name: &Name) -> Result<&mut Self> {
+            match &mut self.0.actions {
+                PackageSetActions::Packages { packages } => {
+                    match packages.iter().position(|package| package.name() == name) {
+                        Some(index) => {
+                            let _ = packages.remove(index);
+                            Ok(self)
+                        }
+                        None => Err(Error::NoPackage(self.0.name.to_string(), name.to_string())),
+                    }
+                }
+                _ => Err(Error::InvalidBuilderState),
             }
         }
 
@@ -744,11 +1802,11 @@ pub mod builders {
             script_string: &str,
         ) -> Result<&mut Self> {
             match &mut self.0.actions {
-                PackageSetActions::Packages { .. } => Err(ErrorKind::InvalidBuilderState.into()),
                 PackageSetActions::Scripts { scripts } => {
                     let _ = scripts.insert(kind, script_string.to_string());
                     Ok(self)
                 }
+                _ => Err(Error::InvalidBuilderState),
             }
         }
 
@@ -772,6 +1830,14 @@ pub mod builders {
             self.add_script_action(InstallActionKind::LinkFiles, script_string)
         }
 
+        /// This sets the internal actions to process a bundle file, of the given package kind, not
+        /// packages or script strings.
+        pub fn bundle_action(&mut self, file_name: &str, kind: PackageKind) -> &mut Self {
+            self.actions(PackageSetActions::Bundle {
+                bundle: PackageBundle::new(file_name, kind),
+            })
+        }
+
         /// Set the name of a file to be treated as an 'env-file'.
         pub fn env_file(&mut self, file_name: &str) -> &mut Self {
             self.0.env_file = Some(file_name.to_string());
@@ -793,11 +1859,35 @@ pub mod builders {
             self
         }
 
+        /// Set the files specific to the shell `shell`.
+        pub fn shell_files(&mut self, shell: &str, shell_files: ShellFiles) -> &mut Self {
+            let _ = self.0.shell_files.insert(shell.to_string(), shell_files);
+            self
+        }
+
+        /// Create links relative to the directory containing the link.
+        pub fn relative_links(&mut self) -> &mut Self {
+            self.0.relative_links = true;
+            self
+        }
+
+        /// Create links with absolute paths (the default).
+        pub fn absolute_links(&mut self) -> &mut Self {
+            self.0.relative_links = false;
+            self
+        }
+
         /// Add a run-after script string.
         pub fn run_after(&mut self, script_string: &str) -> &mut Self {
             self.0.run_after = Some(script_string.to_string());
             self
         }
+
+        /// Add a rollback script string.
+        pub fn rollback(&mut self, script_string: &str) -> &mut Self {
+            self.0.rollback = Some(script_string.to_string());
+            self
+        }
     }
     // --------------------------------------------------------------------------------------------
 
@@ -805,6 +1895,7 @@ pub mod builders {
         fn default() -> Self {
             Self(PackageSetGroup {
                 path: Default::default(),
+                config: Default::default(),
                 package_sets: Default::default(),
             })
         }
@@ -835,6 +1926,7 @@ pub mod builders {
         pub fn new_in(path: PathBuf) -> Self {
             Self(PackageSetGroup {
                 path,
+                config: Default::default(),
                 package_sets: vec![],
             })
         }
@@ -849,4 +1941,46 @@ pub mod builders {
             self.0.package_sets.push(package_set)
         }
     }
+
+    // --------------------------------------------------------------------------------------------
+
+    impl From<PackageRepository> for PackageRepositoryBuilder {
+        fn from(repository: PackageRepository) -> Self {
+            Self(repository)
+        }
+    }
+
+    impl From<PackageRepositoryBuilder> for PackageRepository {
+        fn from(builder: PackageRepositoryBuilder) -> Self {
+            builder.0
+        }
+    }
+
+    impl Builder for PackageRepositoryBuilder {
+        type Inner = PackageRepository;
+
+        fn build(&mut self) -> Self::Inner {
+            self.0.clone()
+        }
+    }
+
+    impl PackageRepositoryBuilder {
+        /// Create a new instance with the given root path.
+        pub fn new_in(path: PathBuf) -> Self {
+            Self(PackageRepository {
+                path,
+                package_set_groups: vec![],
+            })
+        }
+
+        /// Add all groups to the repository, this is not additive.
+        pub fn package_set_groups(&mut self, package_set_groups: &[PackageSetGroup]) {
+            self.0.package_set_groups = package_set_groups.to_vec()
+        }
+
+        /// Add a group to the repository, this is additive.
+        pub fn add_package_set_group(&mut self, package_set_group: PackageSetGroup) {
+            self.0.package_set_groups.push(package_set_group)
+        }
+    }
 }
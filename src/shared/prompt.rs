@@ -0,0 +1,57 @@
+/*!
+Simple line-based prompts, used by actions that ask the user for input on the terminal.
+*/
+
+use crate::error::Result;
+use std::io::{BufRead, Write};
+
+// ------------------------------------------------------------------------------------------------
+// Public Functions
+// ------------------------------------------------------------------------------------------------
+
+///
+/// Prompt for a line of input, returning the trimmed response; if the response is empty then the
+/// `default`, if provided, is returned.
+///
+pub fn prompt(question: &str, default: Option<&str>) -> Result<String> {
+    match default {
+        None => print!("{}: ", question),
+        Some(default) => print!("{} [{}]: ", question, default),
+    }
+    std::io::stdout().flush()?;
+    let mut response = String::new();
+    let _ = std::io::stdin().lock().read_line(&mut response)?;
+    let response = response.trim();
+    Ok(match (response.is_empty(), default) {
+        (true, Some(default)) => default.to_string(),
+        _ => response.to_string(),
+    })
+}
+
+///
+/// Prompt for a line of input, returning `None` if the response is empty.
+///
+pub fn prompt_optional(question: &str) -> Result<Option<String>> {
+    let response = prompt(question, None)?;
+    Ok(if response.is_empty() {
+        None
+    } else {
+        Some(response)
+    })
+}
+
+///
+/// Prompt for a yes, or no, response; returning `default` if the response is empty.
+///
+pub fn prompt_yes_no(question: &str, default: bool) -> Result<bool> {
+    let question = format!("{} ({})", question, if default { "Y/n" } else { "y/N" });
+    loop {
+        let response = prompt(&question, None)?;
+        match response.to_lowercase().as_str() {
+            "" => return Ok(default),
+            "y" | "yes" => return Ok(true),
+            "n" | "no" => return Ok(false),
+            _ => println!("Please answer 'y' or 'n'"),
+        }
+    }
+}
@@ -0,0 +1,389 @@
+use crate::error::{Error, Result};
+use crate::shared::{dirs, shell_escape, InstallActionKind, Platform, Scope};
+use crate::APP_NAME;
+use regex::Regex;
+use std::path::PathBuf;
+use std::time::Duration;
+
+// ------------------------------------------------------------------------------------------------
+// Public Types
+// ------------------------------------------------------------------------------------------------
+
+///
+/// A schedule describes a periodic, background, run of the tool to refresh the repository and
+/// then perform an install or update action. Schedules are implemented using the platform's own
+/// service manager; user-level `systemd` timers on Linux and `launchd` agents on macOS.
+///
+#[derive(Clone, Debug, PartialEq)]
+pub struct Schedule {
+    action: InstallActionKind,
+    every: Duration,
+    program: PathBuf,
+    scope: Scope,
+}
+
+///
+/// A file to be written to install a schedule with the platform's service manager.
+///
+#[derive(Clone, Debug, PartialEq)]
+pub struct ScheduleFile {
+    path: PathBuf,
+    content: String,
+}
+
+// ------------------------------------------------------------------------------------------------
+// Public Functions
+// ------------------------------------------------------------------------------------------------
+
+///
+/// Parse a duration string; this is a sequence of integer values each followed by one of the
+/// units `w` (weeks), `d` (days), `h` (hours), `m` (minutes), or `s` (seconds). For example,
+/// `"12h"`, `"1d"`, or `"1h30m"`.
+///
+pub fn parse_duration(s: &str) -> Result<Duration> {
+    let s = s.trim();
+    let invalid = || Error::InvalidConfigValue("duration".to_string(), s.to_string());
+    if s.is_empty() || !DURATION.is_match(s) {
+        return Err(invalid());
+    }
+    let mut seconds: u64 = 0;
+    for capture in DURATION_PART.captures_iter(s) {
+        let value: u64 = capture
+            .get(1)
+            .unwrap()
+            .as_str()
+            .parse()
+            .map_err(|_| invalid())?;
+        let multiplier: u64 = match capture.get(2).unwrap().as_str() {
+            "w" => 7 * 24 * 60 * 60,
+            "d" => 24 * 60 * 60,
+            "h" => 60 * 60,
+            "m" => 60,
+            _ => 1,
+        };
+        seconds = value
+            .checked_mul(multiplier)
+            .and_then(|part| seconds.checked_add(part))
+            .ok_or_else(invalid)?;
+    }
+    if seconds == 0 {
+        Err(invalid())
+    } else {
+        Ok(Duration::from_secs(seconds))
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+// Implementations
+// ------------------------------------------------------------------------------------------------
+
+lazy_static! {
+    static ref DURATION: Regex = Regex::new(r#"^([0-9]+[wdhms])+$"#).unwrap();
+    static ref DURATION_PART: Regex = Regex::new(r#"([0-9]+)([wdhms])"#).unwrap();
+}
+
+impl Schedule {
+    /// Create a new schedule to run `action` every `every` period, `program` is the path to the
+    /// tool executable itself. Only the install and update actions may be scheduled.
+    pub fn new(action: InstallActionKind, every: Duration, program: PathBuf) -> Result<Self> {
+        match action {
+            InstallActionKind::Install | InstallActionKind::Update => Ok(Self {
+                action,
+                every,
+                program,
+                scope: Default::default(),
+            }),
+            _ => Err(Error::InvalidConfigValue(
+                "action".to_string(),
+                action.to_string(),
+            )),
+        }
+    }
+
+    /// Keep the output of scheduled runs in the log directory for `scope`, rather than the user's.
+    pub fn with_scope(self, scope: Scope) -> Self {
+        Self { scope, ..self }
+    }
+
+    /// Return the action performed by this schedule.
+    pub fn action(&self) -> &InstallActionKind {
+        &self.action
+    }
+
+    /// Return the period between runs.
+    pub fn every(&self) -> &Duration {
+        &self.every
+    }
+
+    /// Return the name used to identify this schedule with the service manager.
+    pub fn label(&self) -> String {
+        format!("{}-{}", APP_NAME, self.action)
+    }
+
+    /// Return the path to the file that receives the output of scheduled runs.
+    pub fn output_path(&self) -> PathBuf {
+        dirs::log_dir_for(self.scope, APP_NAME)
+            .unwrap()
+            .join("schedule.log")
+    }
+
+    /// Return the script string run by the service manager; a refresh of the repository followed
+    /// by the scheduled action.
+    pub fn script_string(&self) -> String {
+        let program = shell_escape(&self.program.to_string_lossy());
+        format!("{} refresh && {} {}", program, program, self.action)
+    }
+
+    /// Return the file(s) that are required to install this schedule on the current platform.
+    pub fn files(&self) -> Vec<ScheduleFile> {
+        match Platform::CURRENT {
+            Platform::Linux => self.systemd_files(),
+            Platform::Macos => self.launchd_files(),
+        }
+    }
+
+    /// Return the command the user should run to enable the schedule once the files are written.
+    pub fn enable_command(&self) -> String {
+        match Platform::CURRENT {
+            Platform::Linux => format!(
+                "systemctl --user daemon-reload && systemctl --user enable --now {}.timer",
+                self.label()
+            ),
+            Platform::Macos => format!(
+                "launchctl load -w {}",
+                shell_escape(&self.launchd_path().to_string_lossy())
+            ),
+        }
+    }
+
+    /// Return the command the user should run to disable the schedule before the files are
+    /// removed.
+    pub fn disable_command(&self) -> String {
+        match Platform::CURRENT {
+            Platform::Linux => format!("systemctl --user disable --now {}.timer", self.label()),
+            Platform::Macos => format!(
+                "launchctl unload -w {}",
+                shell_escape(&self.launchd_path().to_string_lossy())
+            ),
+        }
+    }
+
+    fn systemd_files(&self) -> Vec<ScheduleFile> {
+        let unit_dir = dirs_next::config_dir().unwrap().join("systemd/user");
+        vec![
+            ScheduleFile {
+                path: unit_dir.join(format!("{}.service", self.label())),
+                content: format!(
+                    r##"[Unit]
+Description={app} scheduled {action}
+
+[Service]
+Type=oneshot
+ExecStart=/bin/sh -c "{script}"
+StandardOutput=append:{output}
+StandardError=append:{output}
+"##,
+                    app = APP_NAME,
+                    action = self.action,
+                    script = systemd_escape(&self.script_string()),
+                    output = self.output_path().to_string_lossy(),
+                ),
+            },
+            ScheduleFile {
+                path: unit_dir.join(format!("{}.timer", self.label())),
+                content: format!(
+                    r##"[Unit]
+Description={app} scheduled {action} timer
+
+[Timer]
+OnBootSec=15min
+OnUnitActiveSec={every}s
+Persistent=true
+
+[Install]
+WantedBy=timers.target
+"##,
+                    app = APP_NAME,
+                    action = self.action,
+                    every = self.every.as_secs(),
+                ),
+            },
+        ]
+    }
+
+    fn launchd_path(&self) -> PathBuf {
+        dirs_next::home_dir()
+            .unwrap()
+            .join("Library/LaunchAgents")
+            .join(format!("{}.plist", self.label()))
+    }
+
+    fn launchd_files(&self) -> Vec<ScheduleFile> {
+        vec![ScheduleFile {
+            path: self.launchd_path(),
+            content: format!(
+                r##"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+    <key>Label</key>
+    <string>{label}</string>
+    <key>ProgramArguments</key>
+    <array>
+        <string>/bin/sh</string>
+        <string>-c</string>
+        <string>{script}</string>
+    </array>
+    <key>StartInterval</key>
+    <integer>{every}</integer>
+    <key>StandardOutPath</key>
+    <string>{output}</string>
+    <key>StandardErrorPath</key>
+    <string>{output}</string>
+</dict>
+</plist>
+"##,
+                label = xml_escape(&self.label()),
+                script = xml_escape(&self.script_string()),
+                every = self.every.as_secs(),
+                output = xml_escape(&self.output_path().to_string_lossy()),
+            ),
+        }]
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+
+impl ScheduleFile {
+    /// Return the path this file should be written to.
+    pub fn path(&self) -> &PathBuf {
+        &self.path
+    }
+
+    /// Return the content of this file.
+    pub fn content(&self) -> &String {
+        &self.content
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+// Private Functions
+// ------------------------------------------------------------------------------------------------
+
+// Escape a value for a double-quoted argument in a systemd unit's command line; backslashes and
+// quotes are escaped, and `%` specifiers and `$` variable references are made literal.
+fn systemd_escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('%', "%%")
+        .replace('$', "$$")
+}
+
+// Escape a value for the text content of an XML element.
+fn xml_escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+// ------------------------------------------------------------------------------------------------
+// Unit Tests
+// ------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("90s").unwrap(), Duration::from_secs(90));
+        assert_eq!(parse_duration("30m").unwrap(), Duration::from_secs(30 * 60));
+        assert_eq!(
+            parse_duration("12h").unwrap(),
+            Duration::from_secs(12 * 3600)
+        );
+        assert_eq!(parse_duration("1d").unwrap(), Duration::from_secs(86400));
+        assert_eq!(
+            parse_duration("1w").unwrap(),
+            Duration::from_secs(7 * 86400)
+        );
+        assert_eq!(
+            parse_duration("1h30m").unwrap(),
+            Duration::from_secs(3600 + 1800)
+        );
+    }
+
+    #[test]
+    fn test_parse_bad_duration() {
+        assert!(parse_duration("").is_err());
+        assert!(parse_duration("12").is_err());
+        assert!(parse_duration("0h").is_err());
+        assert!(parse_duration("1 hour").is_err());
+        assert!(parse_duration("h1").is_err());
+        // Values that overflow, whether parsing, multiplying, or adding, are errors.
+        assert!(parse_duration("99999999999999999999999s").is_err());
+        assert!(parse_duration("99999999999999999w").is_err());
+        assert!(parse_duration("18446744073709551615s1s").is_err());
+    }
+
+    #[test]
+    fn test_schedule_only_install_or_update() {
+        let every = Duration::from_secs(60);
+        assert!(Schedule::new(InstallActionKind::Install, every, PathBuf::from("mcfg")).is_ok());
+        assert!(Schedule::new(InstallActionKind::Update, every, PathBuf::from("mcfg")).is_ok());
+        assert!(Schedule::new(InstallActionKind::Uninstall, every, PathBuf::from("mcfg")).is_err());
+        assert!(Schedule::new(InstallActionKind::LinkFiles, every, PathBuf::from("mcfg")).is_err());
+    }
+
+    #[test]
+    fn test_script_string_quotes_program() {
+        let program = PathBuf::from("/opt/my tools/it's/mcfg");
+        let schedule =
+            Schedule::new(InstallActionKind::Update, Duration::from_secs(60), program).unwrap();
+        assert_eq!(
+            schedule.script_string(),
+            r#"'/opt/my tools/it'\''s/mcfg' refresh && '/opt/my tools/it'\''s/mcfg' update"#
+        );
+        assert_eq!(
+            shell_words::split(&schedule.script_string()).unwrap(),
+            vec![
+                "/opt/my tools/it's/mcfg",
+                "refresh",
+                "&&",
+                "/opt/my tools/it's/mcfg",
+                "update"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_schedule_files_escape_program() {
+        let program = PathBuf::from("/opt/my tools/it's/mcfg");
+        let schedule =
+            Schedule::new(InstallActionKind::Update, Duration::from_secs(60), program).unwrap();
+
+        let service = &schedule.systemd_files()[0];
+        assert!(service.content().contains(
+            r#"ExecStart=/bin/sh -c "'/opt/my tools/it'\\''s/mcfg' refresh && '/opt/my tools/it'\\''s/mcfg' update""#
+        ));
+
+        let plist = &schedule.launchd_files()[0];
+        assert!(plist.content().contains(
+            "<string>&apos;/opt/my tools/it&apos;\\&apos;&apos;s/mcfg&apos; refresh &amp;&amp; \
+             &apos;/opt/my tools/it&apos;\\&apos;&apos;s/mcfg&apos; update</string>"
+        ));
+    }
+
+    #[test]
+    fn test_xml_escape() {
+        assert_eq!(
+            xml_escape(r#"a <b> & "c" 'd'"#),
+            "a &lt;b&gt; &amp; &quot;c&quot; &apos;d&apos;"
+        );
+    }
+}
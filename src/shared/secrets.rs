@@ -0,0 +1,166 @@
+/*!
+Variables that refer to secrets held by an external secret provider, rather than to a value in the
+variable map. A secret variable has the form `{{secret:<reference>}}`, where the form of the
+reference selects the provider:
+
+* `op://vault/item/field` - read with the 1Password CLI, `op read`.
+* `pass:path/to/secret` - the first line of the secret from `pass show`.
+* `keychain:service` or `keychain:service/account` - a generic password from the macOS keychain,
+  using `security find-generic-password`.
+
+Secrets are only resolved immediately before a command is spawned; they are never added to the
+variable map, or the command's environment, and the command as logged and recorded in the
+install log retains the secret variable, not its value. Each secret is resolved at most once by
+the tool.
+*/
+
+use crate::error::{Error, Result};
+use crate::shared::builders::{Builder, ShellCommandBuilder};
+use crate::shared::command::CaptureMode;
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+// ------------------------------------------------------------------------------------------------
+// Public Types
+// ------------------------------------------------------------------------------------------------
+
+///
+/// The prefix of a variable name that refers to a secret.
+///
+pub const SECRET_VAR_PREFIX: &str = "secret:";
+
+///
+/// The external providers from which secrets may be read.
+///
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SecretProvider {
+    /// The 1Password CLI, `op`.
+    OnePassword,
+    /// The standard Unix password manager, `pass`.
+    Pass,
+    /// The macOS keychain, using `security`.
+    Keychain,
+}
+
+// ------------------------------------------------------------------------------------------------
+// Public Functions
+// ------------------------------------------------------------------------------------------------
+
+///
+/// Returns `true` if the variable name `var_name` refers to a secret, else `false`.
+///
+pub fn is_secret_var(var_name: &str) -> bool {
+    var_name.starts_with(SECRET_VAR_PREFIX)
+}
+
+///
+/// Resolve the secret variable `var_name`, of the form `secret:<reference>`, returning the
+/// secret's value.
+///
+pub fn resolve_secret_var(var_name: &str) -> Result<String> {
+    let reference = var_name.strip_prefix(SECRET_VAR_PREFIX).unwrap_or(var_name);
+    if let Some(value) = RESOLVED_SECRETS.lock().unwrap().get(reference) {
+        return Ok(value.clone());
+    }
+    let provider = SecretProvider::for_reference(reference)
+        .ok_or_else(|| Error::UnknownSecretProvider(reference.to_string()))?;
+    debug!("resolve_secret_var ({:?}, {:?})", provider, reference);
+    let value = provider.read(reference)?;
+    let _ = RESOLVED_SECRETS
+        .lock()
+        .unwrap()
+        .insert(reference.to_string(), value.clone());
+    Ok(value)
+}
+
+// ------------------------------------------------------------------------------------------------
+// Implementations
+// ------------------------------------------------------------------------------------------------
+
+impl SecretProvider {
+    /// Return the provider for the secret `reference`, the variable name without the `secret:`
+    /// prefix, or `None` if the reference is not in a known form.
+    pub fn for_reference(reference: &str) -> Option<Self> {
+        if reference.starts_with("op://") {
+            Some(Self::OnePassword)
+        } else if reference.starts_with("pass:") {
+            Some(Self::Pass)
+        } else if reference.starts_with("keychain:") {
+            Some(Self::Keychain)
+        } else {
+            None
+        }
+    }
+
+    fn read(&self, reference: &str) -> Result<String> {
+        let mut builder = match self {
+            Self::OnePassword => {
+                let mut builder = ShellCommandBuilder::program("op");
+                let _ = builder.arg("read").arg(reference);
+                builder
+            }
+            Self::Pass => {
+                let mut builder = ShellCommandBuilder::program("pass");
+                let _ = builder.arg("show").arg(&reference["pass:".len()..]);
+                builder
+            }
+            Self::Keychain => {
+                let mut builder = ShellCommandBuilder::program("security");
+                let _ = builder.arg("find-generic-password").arg("-w");
+                match reference["keychain:".len()..].split_once('/') {
+                    Some((service, account)) => {
+                        let _ = builder.arg("-s").arg(service).arg("-a").arg(account);
+                    }
+                    None => {
+                        let _ = builder.arg("-s").arg(&reference["keychain:".len()..]);
+                    }
+                }
+                builder
+            }
+        };
+        // The provider may need to ask the user to unlock it, so only stdout is captured.
+        let output = builder
+            .capture(CaptureMode::Stdout)
+            .apply_environment_policy(false)
+            .build()
+            .execute_output()
+            .map_err(|e| Error::SecretNotResolved(reference.to_string(), e.to_string()))?;
+        // All providers write the secret as the first line of their output.
+        Ok(output.lines().next().unwrap_or_default().to_string())
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+// Private Functions
+// ------------------------------------------------------------------------------------------------
+
+lazy_static! {
+    static ref RESOLVED_SECRETS: Mutex<HashMap<String, String>> = Default::default();
+}
+
+// ------------------------------------------------------------------------------------------------
+// Unit Tests
+// ------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_secret_provider_for_reference() {
+        assert_eq!(
+            SecretProvider::for_reference("op://dev/github/token"),
+            Some(SecretProvider::OnePassword)
+        );
+        assert_eq!(
+            SecretProvider::for_reference("pass:dev/github"),
+            Some(SecretProvider::Pass)
+        );
+        assert_eq!(
+            SecretProvider::for_reference("keychain:github/me"),
+            Some(SecretProvider::Keychain)
+        );
+        assert_eq!(SecretProvider::for_reference("vault:dev/github"), None);
+        assert!(resolve_secret_var("secret:vault:dev/github").is_err());
+    }
+}
@@ -0,0 +1,595 @@
+/*!
+The selection of package sets, from the repository, that an action is to be performed on.
+
+A selection is made up of group names, and package set names; where a package set name may also
+be a path of the form `group/set`. With no names at all every package set is selected. Group,
+and plain package set, names act as filters, selecting the package sets in any of the named
+groups that have any of the given names. A path always selects exactly the named package set,
+regardless of the other names.
+
+A selection may also include tags, in which case only package sets with any of the tags are
+selected; tags, like group names, act as a filter on the other names.
+
+Names may also be skipped, a skipped group, or package set, is never selected; even if it is
+named in the selection itself.
+
+All names are actually patterns, a name may include the glob wildcards `*`, `?`, and `[...]`; so
+`dev-*` matches all names starting with `dev-`. A pattern that starts with `^` is a regular
+expression, and is matched against the whole name. Patterns are matched ignoring case if
+`set_ignore_case` has been called.
+
+A pattern that matches nothing in the repository is an error, reported by `check_matched` along
+with the names most like it.
+*/
+
+use crate::error::{Error, Result};
+use crate::shared::packages::LazyPackageRepository;
+use crate::shared::suggest::{did_you_mean, suggest_names};
+use crate::shared::{FileSystemResource, Name, PackageRepository, PackageSet};
+use regex::{Regex, RegexBuilder};
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+use std::sync::RwLock;
+
+// ------------------------------------------------------------------------------------------------
+// Public Types
+// ------------------------------------------------------------------------------------------------
+
+///
+/// A pattern matched against group, or package set, names. This is either a simple name, a glob
+/// pattern, or a regular expression.
+///
+#[derive(Clone, Debug)]
+pub struct NamePattern {
+    pattern: String,
+    regex: Regex,
+    regex_ignore_case: Regex,
+}
+
+///
+/// A selection of the package sets in the repository.
+///
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PackageSetSelection {
+    groups: Vec<NamePattern>,
+    package_sets: Vec<NamePattern>,
+    paths: Vec<(NamePattern, NamePattern)>,
+    tags: Vec<Name>,
+    skip_groups: Vec<NamePattern>,
+    skip_package_sets: Vec<NamePattern>,
+    skip_paths: Vec<(NamePattern, NamePattern)>,
+}
+
+// ------------------------------------------------------------------------------------------------
+// Public Functions
+// ------------------------------------------------------------------------------------------------
+
+///
+/// Set whether names, and name patterns, are matched ignoring case. This affects all patterns,
+/// including those already constructed.
+///
+pub fn set_ignore_case(ignore_case: bool) {
+    let mut inner = IGNORE_CASE.write().unwrap();
+    *inner = ignore_case;
+}
+
+///
+/// Returns whether names, and name patterns, are matched ignoring case.
+///
+pub fn is_ignore_case() -> bool {
+    *IGNORE_CASE.read().unwrap()
+}
+
+///
+/// Returns `true` if `name` is the same as `other`, ignoring case if `is_ignore_case`, else
+/// `false`.
+///
+pub fn is_same_name(name: &Name, other: &Name) -> bool {
+    name == other
+        || (is_ignore_case() && name.to_string().to_lowercase() == other.to_string().to_lowercase())
+}
+
+// ------------------------------------------------------------------------------------------------
+// Implementations
+// ------------------------------------------------------------------------------------------------
+
+impl Display for NamePattern {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.pattern)
+    }
+}
+
+impl FromStr for NamePattern {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let regex = if s.starts_with('^') {
+            format!("{}$", s.trim_end_matches('$'))
+        } else if s.is_empty()
+            || !s
+                .chars()
+                .all(|c| Name::is_valid(&c.to_string()) || GLOB_CHARS.contains(&c))
+        {
+            return Err(Error::InvalidNamePattern(s.to_string()));
+        } else {
+            glob_to_regex(s)
+        };
+        match (
+            Regex::new(&regex),
+            RegexBuilder::new(&regex).case_insensitive(true).build(),
+        ) {
+            (Ok(regex), Ok(regex_ignore_case)) => Ok(Self {
+                pattern: s.to_string(),
+                regex,
+                regex_ignore_case,
+            }),
+            _ => Err(Error::InvalidNamePattern(s.to_string())),
+        }
+    }
+}
+
+impl From<Name> for NamePattern {
+    fn from(name: Name) -> Self {
+        let regex = format!("^{}$", regex::escape(&name.to_string()));
+        Self {
+            regex: Regex::new(&regex).unwrap(),
+            regex_ignore_case: RegexBuilder::new(&regex)
+                .case_insensitive(true)
+                .build()
+                .unwrap(),
+            pattern: name.into(),
+        }
+    }
+}
+
+impl PartialEq for NamePattern {
+    fn eq(&self, other: &Self) -> bool {
+        self.pattern == other.pattern
+    }
+}
+
+impl NamePattern {
+    /// Return `true` if `name` matches this pattern, else `false`.
+    pub fn matches(&self, name: &Name) -> bool {
+        if is_ignore_case() {
+            self.regex_ignore_case.is_match(&name.to_string())
+        } else {
+            self.regex.is_match(&name.to_string())
+        }
+    }
+
+    /// Return `true` if this pattern is a simple name, rather than a glob or regular expression,
+    /// else `false`.
+    pub fn is_name(&self) -> bool {
+        Name::is_valid(&self.pattern)
+    }
+
+    // Return the text appended to an error for this pattern matching none of `candidates`.
+    fn did_you_mean<I, S>(&self, candidates: I) -> String
+    where
+        I: IntoIterator<Item = S>,
+        S: ToString,
+    {
+        if self.is_name() {
+            did_you_mean(&suggest_names(&self.pattern, candidates))
+        } else {
+            String::new()
+        }
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+
+impl PackageSetSelection {
+    /// Construct a selection of all package sets in the repository.
+    pub fn all() -> Self {
+        Default::default()
+    }
+
+    /// Construct a new selection from the group patterns and package set patterns, any package
+    /// set pattern of the form `group/set` is treated as a path to a single package set.
+    pub fn new(groups: Vec<NamePattern>, package_sets: Vec<NamePattern>) -> Self {
+        let mut selection = Self {
+            groups,
+            ..Default::default()
+        };
+        for package_set in package_sets {
+            match split_path(&package_set) {
+                Some(path) => selection.paths.push(path),
+                None => selection.package_sets.push(package_set),
+            }
+        }
+        selection
+    }
+
+    /// Return this selection, only including package sets with any of the tags.
+    pub fn with_tags(self, tags: Vec<Name>) -> Self {
+        Self { tags, ..self }
+    }
+
+    /// Return this selection, having skipped the named groups, and package sets. As with `new`
+    /// any package set pattern of the form `group/set` is treated as a path.
+    pub fn skipping(self, groups: Vec<NamePattern>, package_sets: Vec<NamePattern>) -> Self {
+        let mut selection = Self {
+            skip_groups: groups,
+            ..self
+        };
+        for package_set in package_sets {
+            match split_path(&package_set) {
+                Some(path) => selection.skip_paths.push(path),
+                None => selection.skip_package_sets.push(package_set),
+            }
+        }
+        selection
+    }
+
+    /// Return `true` if this selection includes every package set in the repository, else
+    /// `false`.
+    pub fn is_all(&self) -> bool {
+        self.groups.is_empty()
+            && self.package_sets.is_empty()
+            && self.paths.is_empty()
+            && self.tags.is_empty()
+            && self.skip_groups.is_empty()
+            && self.skip_package_sets.is_empty()
+            && self.skip_paths.is_empty()
+    }
+
+    /// Return `true` if the package set `package_set`, in the group named `group`, is included
+    /// in this selection, else `false`.
+    pub fn is_selected(&self, group: &Name, package_set: &PackageSet) -> bool {
+        let set_name = package_set.name();
+        let filtered =
+            !self.groups.is_empty() || !self.package_sets.is_empty() || !self.tags.is_empty();
+        if any_match(&self.skip_groups, group)
+            || any_match(&self.skip_package_sets, set_name)
+            || any_path_match(&self.skip_paths, group, set_name)
+        {
+            false
+        } else if any_path_match(&self.paths, group, set_name) {
+            true
+        } else if filtered {
+            (self.groups.is_empty() || any_match(&self.groups, group))
+                && (self.package_sets.is_empty() || any_match(&self.package_sets, set_name))
+                && (self.tags.is_empty() || self.tags.iter().any(|tag| package_set.has_tag(tag)))
+        } else {
+            self.paths.is_empty()
+        }
+    }
+
+    /// Return `true` if this selection may include package sets in the group named `group`, else
+    /// `false`; a group that cannot be selected need not be read from the repository.
+    pub fn may_select_group(&self, group: &Name) -> bool {
+        let filtered =
+            !self.groups.is_empty() || !self.package_sets.is_empty() || !self.tags.is_empty();
+        if any_match(&self.skip_groups, group) {
+            false
+        } else if self
+            .paths
+            .iter()
+            .any(|(path_group, _)| path_group.matches(group))
+        {
+            true
+        } else if filtered {
+            self.groups.is_empty() || any_match(&self.groups, group)
+        } else {
+            self.paths.is_empty()
+        }
+    }
+
+    /// Return `true` if this selection may include a package set named `package_set`, in the
+    /// group named `group`, else `false`. Unlike `is_selected` this does not require the package
+    /// set itself, so its tags are not checked.
+    pub fn may_select_package_set(&self, group: &Name, package_set: &Name) -> bool {
+        let filtered =
+            !self.groups.is_empty() || !self.package_sets.is_empty() || !self.tags.is_empty();
+        if any_match(&self.skip_groups, group)
+            || any_match(&self.skip_package_sets, package_set)
+            || any_path_match(&self.skip_paths, group, package_set)
+        {
+            false
+        } else if any_path_match(&self.paths, group, package_set) {
+            true
+        } else if filtered {
+            (self.groups.is_empty() || any_match(&self.groups, group))
+                && (self.package_sets.is_empty() || any_match(&self.package_sets, package_set))
+        } else {
+            self.paths.is_empty()
+        }
+    }
+
+    /// Return an error describing each pattern, in this selection, that does not match anything
+    /// in the repository. Skipped patterns are not checked, as skipping something that does not
+    /// exist is harmless.
+    pub fn check_matched(&self, repository: &PackageRepository) -> Result<()> {
+        let mut unmatched: Vec<String> = Default::default();
+        // Suggestions are only needed once something is unmatched, and the repository may only
+        // hold the package sets selected, so all the names in it are only found on demand.
+        let mut known: Option<RepositoryNames> = None;
+        for group in &self.groups {
+            if !repository
+                .groups()
+                .any(|found| group.matches(&found.name()))
+            {
+                let (groups, _) = known.get_or_insert_with(|| repository_names(repository));
+                unmatched.push(format!(
+                    "package set group '{}'{}",
+                    group,
+                    group.did_you_mean(groups.iter())
+                ));
+            }
+        }
+        for package_set in &self.package_sets {
+            if !repository
+                .groups()
+                .filter(|found| self.groups.is_empty() || any_match(&self.groups, &found.name()))
+                .flat_map(|found| found.package_sets())
+                .any(|found| package_set.matches(found.name()))
+            {
+                let (_, package_sets) = known.get_or_insert_with(|| repository_names(repository));
+                unmatched.push(format!(
+                    "package set '{}'{}",
+                    package_set,
+                    package_set.did_you_mean(
+                        package_sets
+                            .iter()
+                            .filter(|(group, _)| {
+                                self.groups.is_empty() || any_match(&self.groups, group)
+                            })
+                            .map(|(_, package_set)| package_set)
+                    )
+                ));
+            }
+        }
+        for (group, package_set) in &self.paths {
+            if !repository
+                .groups()
+                .filter(|found| group.matches(&found.name()))
+                .flat_map(|found| found.package_sets())
+                .any(|found| package_set.matches(found.name()))
+            {
+                let path = format!("{}/{}", group, package_set);
+                let suggestions = if group.is_name() && package_set.is_name() {
+                    let (_, package_sets) =
+                        known.get_or_insert_with(|| repository_names(repository));
+                    did_you_mean(&suggest_names(
+                        &path,
+                        package_sets
+                            .iter()
+                            .map(|(group, package_set)| format!("{}/{}", group, package_set)),
+                    ))
+                } else {
+                    String::new()
+                };
+                unmatched.push(format!("package set '{}'{}", path, suggestions));
+            }
+        }
+        for tag in &self.tags {
+            if !repository
+                .groups()
+                .flat_map(|found| found.package_sets())
+                .any(|found| found.has_tag(tag))
+            {
+                unmatched.push(format!("tag '{}'", tag));
+            }
+        }
+        if unmatched.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::NoMatchingPackageSets(unmatched.join(", ")))
+        }
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+// Private Functions
+// ------------------------------------------------------------------------------------------------
+
+const GLOB_CHARS: &[char] = &['*', '?', '[', ']', '!'];
+
+// The names of all groups, and all package sets as `(group, package_set)`, in a repository.
+type RepositoryNames = (Vec<Name>, Vec<(Name, Name)>);
+
+lazy_static! {
+    static ref IGNORE_CASE: RwLock<bool> = RwLock::new(false);
+}
+
+// Return the names of all groups, and all package sets as `(group, package_set)`, in the
+// repository; both those loaded into `repository` and those named by the package set files in the
+// repository directory, so that package sets not loaded are included.
+fn repository_names(repository: &PackageRepository) -> RepositoryNames {
+    let mut groups: Vec<Name> = repository.groups().map(|group| group.name()).collect();
+    let mut package_sets: Vec<(Name, Name)> = repository
+        .groups()
+        .flat_map(|group| {
+            group
+                .package_sets()
+                .map(move |package_set| (group.name(), package_set.name().clone()))
+        })
+        .collect();
+    if let Ok(lazy) = LazyPackageRepository::open_from(repository.path().clone()) {
+        for group in lazy.groups() {
+            groups.push(group.name());
+            for package_set in group.package_set_file_names() {
+                package_sets.push((group.name(), package_set));
+            }
+        }
+    }
+    groups.sort();
+    groups.dedup();
+    package_sets.sort();
+    package_sets.dedup();
+    (groups, package_sets)
+}
+
+fn glob_to_regex(glob: &str) -> String {
+    let mut regex = String::from("^");
+    for c in glob.chars() {
+        match c {
+            '*' => regex.push_str(".*"),
+            '?' => regex.push('.'),
+            '[' | ']' => regex.push(c),
+            '!' if regex.ends_with('[') => regex.push('^'),
+            _ => regex.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    regex.push('$');
+    regex
+}
+
+fn any_match(patterns: &[NamePattern], name: &Name) -> bool {
+    patterns.iter().any(|pattern| pattern.matches(name))
+}
+
+fn any_path_match(paths: &[(NamePattern, NamePattern)], group: &Name, package_set: &Name) -> bool {
+    paths
+        .iter()
+        .any(|(path_group, path_set)| path_group.matches(group) && path_set.matches(package_set))
+}
+
+fn split_path(pattern: &NamePattern) -> Option<(NamePattern, NamePattern)> {
+    if pattern.pattern.starts_with('^') {
+        return None;
+    }
+    let (group, package_set) = pattern.pattern.split_at(pattern.pattern.find('/')?);
+    Some((
+        NamePattern::from_str(group).ok()?,
+        NamePattern::from_str(&package_set[1..]).ok()?,
+    ))
+}
+
+// ------------------------------------------------------------------------------------------------
+// Unit Tests
+// ------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shared::builders::{Builder, PackageSetBuilder};
+
+    fn name(s: &str) -> Name {
+        Name::from_str(s).unwrap()
+    }
+
+    fn set(s: &str) -> PackageSet {
+        PackageSetBuilder::named(name(s)).build()
+    }
+
+    fn pattern(s: &str) -> NamePattern {
+        NamePattern::from_str(s).unwrap()
+    }
+
+    #[test]
+    fn test_patterns() {
+        assert!(pattern("rust").matches(&name("rust")));
+        assert!(!pattern("rust").matches(&name("rust-tools")));
+        assert!(pattern("dev-*").matches(&name("dev-tools")));
+        assert!(!pattern("dev-*").matches(&name("tools-dev")));
+        assert!(pattern("*python*").matches(&name("my-python3")));
+        assert!(pattern("python?").matches(&name("python3")));
+        assert!(pattern("python[23]").matches(&name("python2")));
+        assert!(!pattern("python[!23]").matches(&name("python2")));
+        assert!(pattern("a.b").matches(&name("a.b")));
+        assert!(!pattern("a.b").matches(&name("axb")));
+        assert!(pattern("^(rust|go)-.+").matches(&name("go-tools")));
+        assert!(!pattern("^(rust|go)-.+").matches(&name("python-tools")));
+        assert!(NamePattern::from_str("not a name").is_err());
+        assert!(NamePattern::from_str("^(unclosed").is_err());
+    }
+
+    #[test]
+    fn test_select_all() {
+        let selection = PackageSetSelection::all();
+        assert!(selection.is_all());
+        assert!(selection.is_selected(&name("tools"), &set("rust")));
+    }
+
+    #[test]
+    fn test_select_groups_and_sets() {
+        let selection = PackageSetSelection::new(
+            vec![pattern("tools"), pattern("apps")],
+            vec![pattern("rust")],
+        );
+        assert!(selection.is_selected(&name("tools"), &set("rust")));
+        assert!(selection.is_selected(&name("apps"), &set("rust")));
+        assert!(!selection.is_selected(&name("tools"), &set("python")));
+        assert!(!selection.is_selected(&name("other"), &set("rust")));
+
+        let selection = PackageSetSelection::new(vec![pattern("dev-*")], vec![]);
+        assert!(selection.is_selected(&name("dev-tools"), &set("rust")));
+        assert!(!selection.is_selected(&name("apps"), &set("rust")));
+    }
+
+    #[test]
+    fn test_select_paths() {
+        let selection = PackageSetSelection::new(
+            vec![pattern("apps")],
+            vec![pattern("tools/rust"), pattern("tools/python")],
+        );
+        assert!(selection.is_selected(&name("tools"), &set("rust")));
+        assert!(selection.is_selected(&name("tools"), &set("python")));
+        assert!(selection.is_selected(&name("apps"), &set("office")));
+        assert!(!selection.is_selected(&name("tools"), &set("go")));
+
+        let selection = PackageSetSelection::new(vec![], vec![pattern("tools/rust")]);
+        assert!(selection.is_selected(&name("tools"), &set("rust")));
+        assert!(!selection.is_selected(&name("apps"), &set("office")));
+
+        let selection = PackageSetSelection::new(vec![], vec![pattern("*/python*")]);
+        assert!(selection.is_selected(&name("tools"), &set("python3")));
+        assert!(!selection.is_selected(&name("tools"), &set("rust")));
+    }
+
+    #[test]
+    fn test_select_skipping() {
+        let selection = PackageSetSelection::all().skipping(
+            vec![pattern("apps")],
+            vec![pattern("python"), pattern("tools/go")],
+        );
+        assert!(!selection.is_all());
+        assert!(selection.is_selected(&name("tools"), &set("rust")));
+        assert!(!selection.is_selected(&name("apps"), &set("office")));
+        assert!(!selection.is_selected(&name("tools"), &set("python")));
+        assert!(!selection.is_selected(&name("tools"), &set("go")));
+        assert!(selection.is_selected(&name("fonts"), &set("go")));
+
+        let selection = PackageSetSelection::new(vec![], vec![pattern("apps/office")])
+            .skipping(vec![pattern("apps")], vec![]);
+        assert!(!selection.is_selected(&name("apps"), &set("office")));
+    }
+
+    #[test]
+    fn test_select_tags() {
+        let work = PackageSetBuilder::named(name("office"))
+            .tag(name("work"))
+            .build();
+        let selection = PackageSetSelection::all().with_tags(vec![name("work")]);
+        assert!(selection.is_selected(&name("apps"), &work));
+        assert!(!selection.is_selected(&name("apps"), &set("games")));
+
+        let selection =
+            PackageSetSelection::new(vec![pattern("tools")], vec![]).with_tags(vec![name("work")]);
+        assert!(!selection.is_selected(&name("apps"), &work));
+        assert!(selection.is_selected(&name("tools"), &work));
+    }
+
+    #[test]
+    fn test_may_select() {
+        let selection = PackageSetSelection::new(
+            vec![pattern("apps")],
+            vec![pattern("tools/rust"), pattern("python")],
+        )
+        .skipping(vec![], vec![pattern("apps/games")]);
+        assert!(selection.may_select_group(&name("apps")));
+        assert!(selection.may_select_group(&name("tools")));
+        assert!(!selection.may_select_group(&name("fonts")));
+        assert!(selection.may_select_package_set(&name("tools"), &name("rust")));
+        assert!(!selection.may_select_package_set(&name("tools"), &name("go")));
+        assert!(selection.may_select_package_set(&name("apps"), &name("python")));
+        assert!(!selection.may_select_package_set(&name("apps"), &name("games")));
+
+        let selection = PackageSetSelection::all().with_tags(vec![name("work")]);
+        assert!(selection.may_select_group(&name("fonts")));
+        assert!(selection.may_select_package_set(&name("fonts"), &name("fira")));
+    }
+}
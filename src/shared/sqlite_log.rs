@@ -0,0 +1,847 @@
+/*!
+The SQLite implementation of the install log, this is the default implementation and requires the
+feature `sqlite-log`.
+*/
+
+use crate::error::{Error, Result};
+use crate::shared::command::CommandRecord;
+use crate::shared::dirs;
+use crate::shared::install_log::{
+    default_machine_id, is_applied_action, AppliedPackageSet, InstallLog, InstalledPackage,
+    LogEntry, PackageDurations, PackageLogOrder, PackageLogQuery, RunCheckpoint, ARCHIVED_ACTION,
+    INTERRUPTED_ACTION, LOG_FILE, REQUIRES_ACTION,
+};
+use crate::shared::{FileSystemResource, InstallActionKind, Name, Scope};
+use crate::APP_NAME;
+use rusqlite::{params, Connection, Row, ToSql};
+use std::convert::TryFrom;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::Duration;
+
+// ------------------------------------------------------------------------------------------------
+// Public Types
+// ------------------------------------------------------------------------------------------------
+
+///
+/// This is the log where installer actions are recorded, primarily the successful installation
+/// of packages within a package set.
+///
+/// This file is a SQLite3 file, each log entry is a row in the table `installed`. The execution of
+/// every command run while acting on package sets, successful or not, is also recorded as a row in
+/// the table `commands`.
+///
+/// The schema of the log file is versioned, using the SQLite `user_version` pragma, and when an
+/// existing file is opened any migrations for later schema versions are applied in order; so the
+/// tables may gain columns and indexes without losing the existing history.
+///
+/// Each execution of an action on package sets is a run, a row in the table `runs`, and the package
+/// sets and packages completed during the run are rows in the table `run_progress`; a failed run
+/// may be resumed, see `resume_run`.
+///
+#[derive(Debug)]
+pub struct PackageLog {
+    connection: Connection,
+    run_id: Option<i64>,
+    machine: String,
+}
+
+///
+/// The schema version of the installer log supported by this version of the tool.
+///
+pub const LOG_SCHEMA_VERSION: u32 = 7;
+
+// ------------------------------------------------------------------------------------------------
+// Implementations
+// ------------------------------------------------------------------------------------------------
+
+impl FileSystemResource for PackageLog {
+    fn default_path(scope: Scope) -> PathBuf {
+        dirs::log_dir_for(scope, APP_NAME).unwrap().join(LOG_FILE)
+    }
+
+    fn open_from(log_file_path: PathBuf) -> Result<Self> {
+        if !log_file_path.is_file() {
+            debug!(
+                "PackageLog::open creating new log file: {:?}",
+                log_file_path
+            );
+            std::fs::create_dir_all(log_file_path.parent().unwrap())?;
+        } else {
+            debug!(
+                "PackageLog::open opening existing log file {:?}",
+                log_file_path
+            );
+        }
+        let mut connection = Connection::open(log_file_path)?;
+        migrate_schema(&mut connection)?;
+        Ok(PackageLog {
+            connection,
+            run_id: None,
+            machine: default_machine_id(),
+        })
+    }
+}
+impl PackageLog {
+    /// Return the schema version of the log file, this is `LOG_SCHEMA_VERSION` once opened.
+    pub fn schema_version(&self) -> Result<u32> {
+        schema_version(&self.connection)
+    }
+}
+
+impl InstallLog for PackageLog {
+    fn machine(&self) -> &str {
+        &self.machine
+    }
+
+    fn set_machine(&mut self, machine: &str) {
+        self.machine = machine.to_string();
+    }
+
+    fn begin(&mut self) -> Result<()> {
+        trace!("PackageLog::begin");
+        self.connection.execute_batch("BEGIN TRANSACTION")?;
+        Ok(())
+    }
+
+    fn commit(&mut self) -> Result<()> {
+        trace!("PackageLog::commit");
+        self.connection.execute_batch("COMMIT TRANSACTION")?;
+        Ok(())
+    }
+
+    fn begin_run(&mut self, action: &InstallActionKind) -> Result<()> {
+        trace!("PackageLog::begin_run ({})", action);
+        let _ = self.connection.execute(
+            "INSERT INTO runs (action, started) VALUES (?1, ?2)",
+            params![action.to_string(), time::OffsetDateTime::now_utc()],
+        )?;
+        self.run_id = Some(self.connection.last_insert_rowid());
+        Ok(())
+    }
+
+    fn end_run(&mut self, succeeded: bool) -> Result<()> {
+        trace!("PackageLog::end_run ({})", succeeded);
+        if let Some(run_id) = self.run_id.take() {
+            let _ = self.connection.execute(
+                "UPDATE runs SET finished = ?1, succeeded = ?2 WHERE run_id = ?3",
+                params![time::OffsetDateTime::now_utc(), succeeded, run_id],
+            )?;
+        }
+        Ok(())
+    }
+
+    fn log_run_progress(
+        &mut self,
+        package_set_group_name: &Name,
+        package_set_name: &Name,
+        package_name: Option<&Name>,
+    ) -> Result<()> {
+        if let Some(run_id) = self.run_id {
+            let _ = self.connection.execute(
+                "INSERT INTO run_progress (run_id, package_set_group, package_set, package) VALUES (?1, ?2, ?3, ?4)",
+                params![
+                    run_id,
+                    package_set_group_name.to_string(),
+                    package_set_name.to_string(),
+                    package_name.map(|name| name.to_string())],
+            )?;
+        }
+        Ok(())
+    }
+
+    fn clear_run_progress(
+        &mut self,
+        package_set_group_name: &Name,
+        package_set_name: &Name,
+    ) -> Result<()> {
+        if let Some(run_id) = self.run_id {
+            let _ = self.connection.execute(
+                "DELETE FROM run_progress WHERE run_id = ?1 AND package_set_group = ?2 AND package_set = ?3",
+                params![
+                    run_id,
+                    package_set_group_name.to_string(),
+                    package_set_name.to_string()],
+            )?;
+        }
+        Ok(())
+    }
+
+    fn resume_run(&mut self, action: &InstallActionKind) -> Result<Option<RunCheckpoint>> {
+        let previous: Option<(i64, Option<bool>)> = {
+            let mut stmt = self.connection.prepare(
+                r##"SELECT run_id, succeeded FROM runs
+WHERE action = ?1 AND run_id != ?2
+ORDER BY run_id DESC LIMIT 1"##,
+            )?;
+            let mut rows = stmt.query(params![action.to_string(), self.run_id.unwrap_or(-1)])?;
+            match rows.next()? {
+                Some(row) => Some((row.get(0)?, row.get(1)?)),
+                None => None,
+            }
+        };
+        match previous {
+            Some((previous_run_id, succeeded)) if succeeded != Some(true) => {
+                debug!("PackageLog::resume_run resuming run {}", previous_run_id);
+                let mut checkpoint = RunCheckpoint::default();
+                {
+                    let mut stmt = self.connection.prepare(
+                        "SELECT package_set_group, package_set, package FROM run_progress WHERE run_id = ?1",
+                    )?;
+                    let result_iter = stmt.query_map(params![previous_run_id], |row| {
+                        Ok((
+                            row.get::<_, String>(0)?,
+                            row.get::<_, String>(1)?,
+                            row.get::<_, Option<String>>(2)?,
+                        ))
+                    })?;
+                    for progress in result_iter {
+                        let (group, package_set, package) = progress?;
+                        checkpoint.add_completed(group, package_set, package);
+                    }
+                }
+                if let Some(run_id) = self.run_id {
+                    let _ = self.connection.execute(
+                        r##"INSERT INTO run_progress (run_id, package_set_group, package_set, package)
+SELECT ?1, package_set_group, package_set, package FROM run_progress WHERE run_id = ?2"##,
+                        params![run_id, previous_run_id],
+                    )?;
+                }
+                Ok(Some(checkpoint))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    fn log_installed_package(&mut self, package: &InstalledPackage) -> Result<()> {
+        trace!("Logging package installation success");
+        self.insert_installed(&InstalledPackage {
+            date_time: Some(time::OffsetDateTime::now_utc()),
+            machine: Some(self.machine.clone()),
+            ..package.clone()
+        })
+    }
+
+    fn log_applied_package_set(
+        &mut self,
+        package_set_group_name: &Name,
+        package_set_name: &Name,
+        action: &InstallActionKind,
+        content_hash: &str,
+    ) -> Result<()> {
+        trace!("Logging package set {} applied", action);
+        self.insert_applied(&AppliedPackageSet {
+            date_time: Some(time::OffsetDateTime::now_utc()),
+            package_set_group_name: package_set_group_name.clone(),
+            package_set_name: package_set_name.clone(),
+            action: action.to_string(),
+            content_hash: content_hash.to_string(),
+            machine: Some(self.machine.clone()),
+        })
+    }
+
+    fn log_command(&mut self, record: &CommandRecord) -> Result<()> {
+        trace!("Logging command execution {:?}", record.command());
+        self.insert_command(record, Some(&self.machine))
+    }
+
+    fn log_interrupted_package_set(
+        &mut self,
+        package_set_group_name: &Name,
+        package_set_name: &Name,
+        action: &InstallActionKind,
+    ) -> Result<()> {
+        trace!("Logging package set {} interrupted", action);
+        self.insert_applied(&AppliedPackageSet {
+            date_time: Some(time::OffsetDateTime::now_utc()),
+            package_set_group_name: package_set_group_name.clone(),
+            package_set_name: package_set_name.clone(),
+            action: format!("{}-{}", INTERRUPTED_ACTION, action),
+            content_hash: String::new(),
+            machine: Some(self.machine.clone()),
+        })
+    }
+
+    fn log_added_prerequisite(
+        &mut self,
+        package_set_group_name: &Name,
+        package_set_name: &Name,
+        installer_name: &Name,
+        prerequisite: &str,
+    ) -> Result<()> {
+        trace!("Logging package set prerequisite added");
+        self.insert_applied(&AppliedPackageSet {
+            date_time: Some(time::OffsetDateTime::now_utc()),
+            package_set_group_name: package_set_group_name.clone(),
+            package_set_name: package_set_name.clone(),
+            action: format!("{} {} {}", REQUIRES_ACTION, installer_name, prerequisite),
+            content_hash: String::new(),
+            machine: Some(self.machine.clone()),
+        })
+    }
+
+    fn log_archived_package_set(
+        &mut self,
+        package_set_group_name: &Name,
+        package_set_name: &Name,
+        content_hash: &str,
+    ) -> Result<()> {
+        trace!("Logging package set archived");
+        self.insert_applied(&AppliedPackageSet {
+            date_time: Some(time::OffsetDateTime::now_utc()),
+            package_set_group_name: package_set_group_name.clone(),
+            package_set_name: package_set_name.clone(),
+            action: ARCHIVED_ACTION.to_string(),
+            content_hash: content_hash.to_string(),
+            machine: Some(self.machine.clone()),
+        })
+    }
+
+    fn last_applied_hash(
+        &mut self,
+        package_set_group_name: &Name,
+        package_set_name: &Name,
+    ) -> Result<Option<String>> {
+        let mut stmt = self.connection.prepare(
+            r##"SELECT action, content_hash FROM applied
+WHERE package_set_group = ?1 AND package_set = ?2 AND IFNULL(machine, ?3) = ?3
+ORDER BY date_time DESC, rowid DESC LIMIT 1"##,
+        )?;
+        let mut rows = stmt.query(params![
+            package_set_group_name.to_string(),
+            package_set_name.to_string(),
+            self.machine
+        ])?;
+        Ok(match rows.next()? {
+            Some(row) if is_applied_action(&row.get::<_, String>(0)?) => Some(row.get(1)?),
+            _ => None,
+        })
+    }
+
+    fn logged_package_sets(&mut self) -> Result<Vec<(Name, Name)>> {
+        let mut stmt = self.connection.prepare(
+            r##"SELECT package_set_group, package_set FROM installed WHERE IFNULL(machine, ?1) = ?1
+UNION
+SELECT package_set_group, package_set FROM applied WHERE IFNULL(machine, ?1) = ?1
+ORDER BY package_set_group, package_set"##,
+        )?;
+        let result_iter = stmt.query_map(params![self.machine], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+        })?;
+        let mut results: Vec<(Name, Name)> = Default::default();
+        for names in result_iter {
+            let (group, package_set) = names?;
+            results.push((Name::from_str(&group)?, Name::from_str(&package_set)?));
+        }
+        Ok(results)
+    }
+
+    fn applied_package_sets(&mut self) -> Result<Vec<AppliedPackageSet>> {
+        let mut stmt = self.connection.prepare(
+            r##"SELECT date_time, package_set_group, package_set, action, content_hash, machine
+FROM applied
+ORDER BY date_time DESC"##,
+        )?;
+        let result_iter = stmt.query_map(params![], |row| AppliedPackageSet::try_from(row))?;
+        Ok(result_iter.collect::<rusqlite::Result<Vec<AppliedPackageSet>>>()?)
+    }
+
+    fn installed_packages(&mut self, query: &PackageLogQuery) -> Result<Vec<InstalledPackage>> {
+        let (sql, values) = query_to_sql(query, &self.machine);
+        trace!("PackageLog::installed_packages {:?}", sql);
+        let mut stmt = self.connection.prepare(&sql)?;
+        let result_iter = stmt.query_map(values, |row| InstalledPackage::try_from(row))?;
+        Ok(result_iter.collect::<rusqlite::Result<Vec<InstalledPackage>>>()?)
+    }
+
+    fn command_history(&mut self, limit: u32) -> Result<Vec<CommandRecord>> {
+        let mut stmt = self.connection.prepare(&format!(
+            r##"SELECT started, context, command, exit_code, duration_ms, stderr FROM commands
+ORDER BY started DESC{}"##,
+            limit_clause(limit)
+        ))?;
+        let result_iter = stmt.query_map(params![], |row| {
+            Ok(CommandRecord::new(
+                row.get(1)?,
+                row.get(2)?,
+                row.get(0)?,
+                Duration::from_millis(row.get::<_, i64>(4)? as u64),
+                row.get(3)?,
+                row.get(5)?,
+            ))
+        })?;
+        Ok(result_iter.collect::<rusqlite::Result<Vec<CommandRecord>>>()?)
+    }
+
+    fn package_durations(&mut self, limit: u32) -> Result<Vec<PackageDurations>> {
+        let mut stmt = self.connection.prepare(&format!(
+            r##"SELECT package_set_group, package_set, package, installer,
+       COUNT(*), SUM(duration_ms), MAX(duration_ms)
+FROM installed
+WHERE duration_ms IS NOT NULL
+GROUP BY package_set_group, package_set, package, installer
+ORDER BY SUM(duration_ms) DESC{}"##,
+            limit_clause(limit)
+        ))?;
+        let result_iter = stmt.query_map(params![], |row| PackageDurations::try_from(row))?;
+        Ok(result_iter.map(|pd| pd.unwrap()).collect())
+    }
+
+    fn entries(&mut self) -> Result<Vec<LogEntry>> {
+        let mut entries: Vec<LogEntry> = self
+            .installed_packages(
+                &PackageLogQuery::default().ordered_by(PackageLogOrder::OldestFirst),
+            )?
+            .into_iter()
+            .map(LogEntry::Installed)
+            .collect();
+        let mut applied = self.applied_package_sets()?;
+        applied.reverse();
+        entries.extend(applied.into_iter().map(LogEntry::Applied));
+        let mut stmt = self.connection.prepare(
+            r##"SELECT started, context, command, exit_code, duration_ms, stderr, machine FROM commands
+ORDER BY started"##,
+        )?;
+        let result_iter = stmt.query_map(params![], |row| {
+            Ok(LogEntry::Command(
+                CommandRecord::new(
+                    row.get(1)?,
+                    row.get(2)?,
+                    row.get(0)?,
+                    Duration::from_millis(row.get::<_, i64>(4)? as u64),
+                    row.get(3)?,
+                    row.get(5)?,
+                ),
+                row.get(6)?,
+            ))
+        })?;
+        for entry in result_iter {
+            entries.push(entry?);
+        }
+        Ok(entries)
+    }
+
+    fn import_entries(&mut self, entries: &[LogEntry]) -> Result<()> {
+        for entry in entries {
+            match entry {
+                LogEntry::Installed(installed) => self.insert_installed(installed)?,
+                LogEntry::Applied(applied) => self.insert_applied(applied)?,
+                LogEntry::Command(record, machine) => {
+                    self.insert_command(record, machine.as_deref())?
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl PackageLog {
+    fn insert_installed(&self, package: &InstalledPackage) -> Result<()> {
+        let _ = self.connection.execute(
+            "INSERT INTO installed (date_time, package_set_group, package_set, package, installer, started, duration_ms, output_bytes, output_lines, machine) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+            params![
+                package.date_time,
+                package.package_set_group_name.to_string(),
+                package.package_set_name.to_string(),
+                package.package_name.to_string(),
+                package.installer_name.to_string(),
+                package.started,
+                package.duration.map(|d| d.as_millis() as i64),
+                package.output_bytes.map(|v| v as i64),
+                package.output_lines.map(|v| v as i64),
+                package.machine],
+        )?;
+        Ok(())
+    }
+
+    fn insert_applied(&self, applied: &AppliedPackageSet) -> Result<()> {
+        let _ = self.connection.execute(
+            "INSERT INTO applied (date_time, package_set_group, package_set, action, content_hash, machine) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                applied.date_time,
+                applied.package_set_group_name.to_string(),
+                applied.package_set_name.to_string(),
+                applied.action,
+                applied.content_hash,
+                applied.machine],
+        )?;
+        Ok(())
+    }
+
+    fn insert_command(&self, record: &CommandRecord, machine: Option<&str>) -> Result<()> {
+        let _ = self.connection.execute(
+            "INSERT INTO commands (started, context, command, exit_code, duration_ms, stderr, machine) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                record.started(),
+                record.context(),
+                record.command(),
+                record.exit_code(),
+                record.duration().as_millis() as i64,
+                record.stderr(),
+                machine],
+        )?;
+        Ok(())
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+
+impl<'stmt> TryFrom<&Row<'stmt>> for InstalledPackage {
+    type Error = rusqlite::Error;
+
+    fn try_from(row: &Row<'stmt>) -> rusqlite::Result<Self, Self::Error> {
+        fn get_name_from_row(row: &Row<'_>, idx: usize) -> rusqlite::Result<Name, rusqlite::Error> {
+            let value_string: String = row.get(idx)?;
+            let name: Name = Name::from_str(&value_string).unwrap();
+            Ok(name)
+        }
+
+        Ok(InstalledPackage {
+            date_time: row.get(0)?,
+            package_set_group_name: get_name_from_row(row, 1)?,
+            package_set_name: get_name_from_row(row, 2)?,
+            package_name: get_name_from_row(row, 3)?,
+            installer_name: get_name_from_row(row, 4)?,
+            started: row.get(5)?,
+            duration: row
+                .get::<_, Option<i64>>(6)?
+                .map(|ms| Duration::from_millis(ms as u64)),
+            output_bytes: row.get::<_, Option<i64>>(7)?.map(|v| v as usize),
+            output_lines: row.get::<_, Option<i64>>(8)?.map(|v| v as usize),
+            machine: row.get(9)?,
+        })
+    }
+}
+
+impl<'stmt> TryFrom<&Row<'stmt>> for AppliedPackageSet {
+    type Error = rusqlite::Error;
+
+    fn try_from(row: &Row<'stmt>) -> rusqlite::Result<Self, Self::Error> {
+        fn get_name_from_row(row: &Row<'_>, idx: usize) -> rusqlite::Result<Name, rusqlite::Error> {
+            let value_string: String = row.get(idx)?;
+            let name: Name = Name::from_str(&value_string).unwrap();
+            Ok(name)
+        }
+
+        Ok(AppliedPackageSet {
+            date_time: row.get(0)?,
+            package_set_group_name: get_name_from_row(row, 1)?,
+            package_set_name: get_name_from_row(row, 2)?,
+            action: row.get(3)?,
+            content_hash: row.get(4)?,
+            machine: row.get(5)?,
+        })
+    }
+}
+
+impl<'stmt> TryFrom<&Row<'stmt>> for PackageDurations {
+    type Error = rusqlite::Error;
+
+    fn try_from(row: &Row<'stmt>) -> rusqlite::Result<Self, Self::Error> {
+        fn get_name_from_row(row: &Row<'_>, idx: usize) -> rusqlite::Result<Name, rusqlite::Error> {
+            let value_string: String = row.get(idx)?;
+            let name: Name = Name::from_str(&value_string).unwrap();
+            Ok(name)
+        }
+
+        Ok(PackageDurations {
+            package_set_group_name: get_name_from_row(row, 0)?,
+            package_set_name: get_name_from_row(row, 1)?,
+            package_name: get_name_from_row(row, 2)?,
+            installer_name: get_name_from_row(row, 3)?,
+            count: row.get(4)?,
+            total: Duration::from_millis(row.get::<_, i64>(5)? as u64),
+            longest: Duration::from_millis(row.get::<_, i64>(6)? as u64),
+        })
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+// Private Functions
+// ------------------------------------------------------------------------------------------------
+
+const INSTALLED_COLUMNS: &str = "date_time, package_set_group, package_set, package, installer, started, duration_ms, output_bytes, output_lines, machine";
+
+// A single schema migration, `migrate` upgrades the schema from version `to_version - 1`. Log
+// files created before the schema was versioned are at version `0`, whatever tables they have,
+// so these early migrations must not fail if their changes are already present.
+struct LogMigration {
+    to_version: u32,
+    description: &'static str,
+    migrate: fn(&Connection) -> Result<()>,
+}
+
+const LOG_MIGRATIONS: &[LogMigration] = &[
+    LogMigration {
+        to_version: 1,
+        description: "create the installed table",
+        migrate: |db| {
+            db.execute_batch(
+                r##"CREATE TABLE IF NOT EXISTS installed (
+    date_time DATETIME NOT NULL,
+    package_set_group TEXT NOT NULL,
+    package_set TEXT NOT NULL,
+    package TEXT NOT NULL,
+    installer TEXT NOT NULL
+)"##,
+            )?;
+            Ok(())
+        },
+    },
+    LogMigration {
+        to_version: 2,
+        description: "add timing and output columns to the installed table",
+        migrate: |db| {
+            add_missing_columns(
+                db,
+                "installed",
+                &[
+                    ("started", "DATETIME"),
+                    ("duration_ms", "INTEGER"),
+                    ("output_bytes", "INTEGER"),
+                    ("output_lines", "INTEGER"),
+                ],
+            )
+        },
+    },
+    LogMigration {
+        to_version: 3,
+        description: "create the applied table",
+        migrate: |db| {
+            db.execute_batch(
+                r##"CREATE TABLE IF NOT EXISTS applied (
+    date_time DATETIME NOT NULL,
+    package_set_group TEXT NOT NULL,
+    package_set TEXT NOT NULL,
+    action TEXT NOT NULL,
+    content_hash TEXT NOT NULL
+)"##,
+            )?;
+            Ok(())
+        },
+    },
+    LogMigration {
+        to_version: 4,
+        description: "create the commands table",
+        migrate: |db| {
+            db.execute_batch(
+                r##"CREATE TABLE IF NOT EXISTS commands (
+    started DATETIME NOT NULL,
+    context TEXT,
+    command TEXT NOT NULL,
+    exit_code INTEGER,
+    duration_ms INTEGER NOT NULL,
+    stderr TEXT NOT NULL
+)"##,
+            )?;
+            Ok(())
+        },
+    },
+    LogMigration {
+        to_version: 5,
+        description: "create the runs and run_progress tables",
+        migrate: |db| {
+            db.execute_batch(
+                r##"CREATE TABLE IF NOT EXISTS runs (
+    run_id INTEGER PRIMARY KEY AUTOINCREMENT,
+    action TEXT NOT NULL,
+    started DATETIME NOT NULL,
+    finished DATETIME,
+    succeeded INTEGER
+);
+CREATE TABLE IF NOT EXISTS run_progress (
+    run_id INTEGER NOT NULL,
+    package_set_group TEXT NOT NULL,
+    package_set TEXT NOT NULL,
+    package TEXT
+)"##,
+            )?;
+            Ok(())
+        },
+    },
+    LogMigration {
+        to_version: 6,
+        description: "index the installed and applied tables by package set",
+        migrate: |db| {
+            db.execute_batch(
+                r##"CREATE INDEX IF NOT EXISTS installed_package_set
+    ON installed (package_set_group, package_set);
+CREATE INDEX IF NOT EXISTS applied_package_set
+    ON applied (package_set_group, package_set)"##,
+            )?;
+            Ok(())
+        },
+    },
+    LogMigration {
+        to_version: 7,
+        description: "add the machine column to the installed, applied, and commands tables",
+        migrate: |db| {
+            for table in &["installed", "applied", "commands"] {
+                add_missing_columns(db, table, &[("machine", "TEXT")])?;
+            }
+            Ok(())
+        },
+    },
+];
+
+fn schema_version(db: &Connection) -> Result<u32> {
+    Ok(db.query_row("PRAGMA user_version", params![], |row| row.get(0))?)
+}
+
+fn migrate_schema(db: &mut Connection) -> Result<()> {
+    let version = schema_version(db)?;
+    if version > LOG_SCHEMA_VERSION {
+        return Err(Error::UnsupportedFormatVersion(
+            "install log".to_string(),
+            version,
+            LOG_SCHEMA_VERSION,
+        ));
+    }
+    for migration in LOG_MIGRATIONS
+        .iter()
+        .filter(|migration| migration.to_version > version)
+    {
+        debug!(
+            "PackageLog::open migrating schema to version {}, {}",
+            migration.to_version, migration.description
+        );
+        let transaction = db.transaction()?;
+        (migration.migrate)(&transaction)?;
+        transaction.pragma_update(None, "user_version", &migration.to_version)?;
+        transaction.commit()?;
+    }
+    Ok(())
+}
+
+fn add_missing_columns(db: &Connection, table: &str, columns: &[(&str, &str)]) -> Result<()> {
+    let existing: Vec<String> = {
+        let mut stmt = db.prepare(&format!("PRAGMA table_info({})", table))?;
+        let result_iter = stmt.query_map(params![], |row| row.get::<_, String>(1))?;
+        result_iter.collect::<rusqlite::Result<Vec<String>>>()?
+    };
+    for (column, column_type) in columns {
+        if !existing.iter().any(|c| c == column) {
+            let _ = db.execute(
+                &format!(
+                    "ALTER TABLE {} ADD COLUMN {} {}",
+                    table, column, column_type
+                ),
+                params![],
+            )?;
+        }
+    }
+    Ok(())
+}
+
+// The SQL statement for the query, and the values for its parameters; rows that do not record a
+// machine are from `local_machine`.
+fn query_to_sql(query: &PackageLogQuery, local_machine: &str) -> (String, Vec<Box<dyn ToSql>>) {
+    let mut conditions: Vec<&str> = Default::default();
+    let mut values: Vec<Box<dyn ToSql>> = Default::default();
+    for (condition, name) in &[
+        ("package_set_group = ?", &query.package_set_group),
+        ("package_set = ?", &query.package_set),
+        ("package = ?", &query.package),
+        ("installer = ?", &query.installer),
+    ] {
+        if let Some(name) = name {
+            conditions.push(condition);
+            values.push(Box::new(name.to_string()));
+        }
+    }
+    if let Some(machine) = &query.machine {
+        conditions.push("IFNULL(machine, ?) = ?");
+        values.push(Box::new(local_machine.to_string()));
+        values.push(Box::new(machine.clone()));
+    }
+    if let Some(since) = query.since {
+        conditions.push("date_time >= ?");
+        values.push(Box::new(since));
+    }
+    if let Some(until) = query.until {
+        conditions.push("date_time < ?");
+        values.push(Box::new(until));
+    }
+    let mut sql = format!("SELECT {} FROM installed", INSTALLED_COLUMNS);
+    if !conditions.is_empty() {
+        sql.push_str(" WHERE ");
+        sql.push_str(&conditions.join(" AND "));
+    }
+    sql.push_str(match query.order {
+        PackageLogOrder::NewestFirst => " ORDER BY date_time DESC",
+        PackageLogOrder::OldestFirst => " ORDER BY date_time ASC",
+        PackageLogOrder::ByPackage => {
+            " ORDER BY package_set_group, package_set, package, date_time DESC"
+        }
+    });
+    if query.limit > 0 {
+        sql.push_str(" LIMIT ?");
+        values.push(Box::new(query.limit));
+    }
+    (sql, values)
+}
+
+fn limit_clause(limit: u32) -> String {
+    if limit > 0 {
+        format!(" LIMIT {}", limit)
+    } else {
+        String::new()
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+// Unit Tests
+// ------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_migrate_unversioned_log() {
+        let work_dir = tempfile::tempdir().unwrap();
+        let log_file_path = work_dir.path().join("install-log.sql");
+        {
+            let db = Connection::open(&log_file_path).unwrap();
+            db.execute_batch(
+                r##"CREATE TABLE installed (
+    date_time DATETIME NOT NULL,
+    package_set_group TEXT NOT NULL,
+    package_set TEXT NOT NULL,
+    package TEXT NOT NULL,
+    installer TEXT NOT NULL,
+    started DATETIME
+)"##,
+            )
+            .unwrap();
+            let _ = db
+                .execute(
+                    "INSERT INTO installed VALUES (?1, 'group', 'set', 'ripgrep', 'cargo', NULL)",
+                    params![time::OffsetDateTime::now_utc()],
+                )
+                .unwrap();
+        }
+
+        let mut log_db = PackageLog::open_from(log_file_path.clone()).unwrap();
+        assert_eq!(log_db.schema_version().unwrap(), LOG_SCHEMA_VERSION);
+        let history = log_db.installed_package_history(0).unwrap();
+        assert_eq!(history.len(), 1);
+        assert_eq!(
+            history.first().unwrap().package_name().to_string(),
+            "ripgrep"
+        );
+        drop(log_db);
+
+        let log_db = PackageLog::open_from(log_file_path.clone()).unwrap();
+        assert_eq!(log_db.schema_version().unwrap(), LOG_SCHEMA_VERSION);
+        drop(log_db);
+
+        {
+            let db = Connection::open(&log_file_path).unwrap();
+            db.pragma_update(None, "user_version", &(LOG_SCHEMA_VERSION + 1))
+                .unwrap();
+        }
+        assert!(PackageLog::open_from(log_file_path).is_err());
+    }
+}
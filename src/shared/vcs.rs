@@ -0,0 +1,720 @@
+/*!
+The version control system used for the package repository, selected by the `vcs` setting in the
+tool configuration file. The repository is usually a Git repository, managed either with the
+built-in Git library or by running the `git` command, but it may also be a plain directory kept in
+step between machines by some other means, such as a file synchronization service, or a read-only
+copy of an archive downloaded over HTTPS, for environments where outbound Git is blocked, or a copy
+of a prefix in a cloud object store.
+*/
+
+use crate::error::{Error, Result};
+use crate::shared::builders::{Builder, ShellCommandBuilder};
+use crate::shared::dirs::{temp_dir_for, temp_dir_in};
+use crate::shared::download::{download_file, extract_archive};
+#[cfg(feature = "git")]
+use crate::shared::{commit_all, remote_callbacks, update_submodules, GitConfig};
+use crate::shared::{ArchiveConfig, Config, FileSystemResource, Scope};
+use crate::APP_NAME;
+#[cfg(feature = "git")]
+use git2::build::{CheckoutBuilder, RepoBuilder};
+#[cfg(feature = "git")]
+use git2::{ErrorClass, ErrorCode, FetchOptions, Repository};
+use serde::{Deserialize, Serialize};
+use std::fmt::{Debug, Display, Formatter};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// ------------------------------------------------------------------------------------------------
+// Public Types
+// ------------------------------------------------------------------------------------------------
+
+///
+/// The operations performed on the package repository by the init, and refresh, actions.
+///
+pub trait RepoVcs: Debug {
+    /// Returns `true` if the directory `path` is already a repository for this VCS, else `false`.
+    fn is_repository(&self, path: &Path) -> bool;
+
+    /// Create a new, empty, repository in the existing directory `path`.
+    fn init(&self, path: &Path) -> Result<()>;
+
+    /// Copy the remote repository at `url`, and any submodules, into the directory `path`.
+    fn clone_from(&self, url: &str, path: &Path) -> Result<()>;
+
+    /// Returns `true` if the repository at `path` has any commits, else `false`.
+    fn has_commits(&self, path: &Path) -> Result<bool>;
+
+    /// Commit all the files in the repository at `path` with `message`.
+    fn commit_all(&self, path: &Path, message: &str) -> Result<()>;
+
+    /// Returns `true` if the repository at `path` has an `origin` remote, else `false`.
+    fn has_origin(&self, path: &Path) -> Result<bool>;
+
+    /// Set the `origin` remote of the repository at `path` to `url`.
+    fn set_origin(&self, path: &Path, url: &str) -> Result<()>;
+
+    /// Update the repository at `path` from its remote, returning `true` if any changes were
+    /// applied.
+    fn refresh(&self, path: &Path) -> Result<bool>;
+}
+
+///
+/// The implementations of `RepoVcs`, selected by the `vcs` setting in the tool configuration
+/// file.
+///
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum RepoVcsKind {
+    /// Git, using the built-in Git library; this requires the feature `git`.
+    Git,
+    /// Git, running the `git` command, which must be on the `PATH`.
+    GitCommand,
+    /// A plain directory, with no version control; the repository cannot be cloned, and refresh
+    /// does nothing.
+    Directory,
+    /// A read-only copy of an archive, a tarball or zip file, downloaded and verified against its
+    /// SHA-256 checksum; refresh downloads the archive again only if the checksum has changed.
+    Archive,
+    /// A read-only copy of an S3, or GCS, bucket prefix, kept in step by running the `aws`, or
+    /// `gsutil`, command; this requires the feature `object-store`.
+    ObjectStore,
+}
+
+// ------------------------------------------------------------------------------------------------
+// Public Functions
+// ------------------------------------------------------------------------------------------------
+
+///
+/// Open the repository VCS selected by the tool configuration file, in `scope`.
+///
+pub fn open_repo_vcs(scope: Scope) -> Result<Box<dyn RepoVcs>> {
+    RepoVcsKind::configured(scope).open(scope)
+}
+
+///
+/// Open the archive VCS with the provided settings, rather than those in the tool configuration
+/// file.
+///
+pub fn open_archive_vcs(config: ArchiveConfig) -> Box<dyn RepoVcs> {
+    Box::new(ArchiveVcs { config })
+}
+
+// ------------------------------------------------------------------------------------------------
+// Private Types
+// ------------------------------------------------------------------------------------------------
+
+#[cfg(feature = "git")]
+#[derive(Debug)]
+struct LibraryGit {
+    config: GitConfig,
+}
+
+#[derive(Debug)]
+struct CommandGit {}
+
+#[derive(Debug)]
+struct Directory {}
+
+#[derive(Debug)]
+struct ArchiveVcs {
+    config: ArchiveConfig,
+}
+
+// Records the archive a repository was unpacked from, in the repository's local directory.
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+struct ArchiveSource {
+    url: String,
+    sha256: String,
+}
+
+const ARCHIVE_SOURCE_FILE: &str = "archive-source.yml";
+
+#[cfg(feature = "object-store")]
+#[derive(Debug)]
+struct ObjectStore {}
+
+// Records the bucket prefix a repository was copied from, in the repository's local directory.
+#[cfg(feature = "object-store")]
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+struct ObjectStoreSource {
+    url: String,
+}
+
+#[cfg(feature = "object-store")]
+const OBJECT_STORE_SOURCE_FILE: &str = "object-store-source.yml";
+
+const LOCAL_DIR: &str = ".local";
+
+// ------------------------------------------------------------------------------------------------
+// Implementations
+// ------------------------------------------------------------------------------------------------
+
+impl Default for RepoVcsKind {
+    #[cfg(feature = "git")]
+    fn default() -> Self {
+        Self::Git
+    }
+
+    #[cfg(not(feature = "git"))]
+    fn default() -> Self {
+        Self::GitCommand
+    }
+}
+
+impl Display for RepoVcsKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Self::Git => "git",
+                Self::GitCommand => "git-command",
+                Self::Directory => "directory",
+                Self::Archive => "archive",
+                Self::ObjectStore => "object-store",
+            }
+        )
+    }
+}
+
+impl RepoVcsKind {
+    /// Return the VCS selected by the tool configuration file, in `scope`, or the default VCS if
+    /// the configuration file does not select one, or cannot be read.
+    pub fn configured(scope: Scope) -> Self {
+        Config::open(scope)
+            .ok()
+            .and_then(|config| *config.vcs())
+            .unwrap_or_default()
+    }
+
+    /// Open this VCS, with the settings in the tool configuration file, in `scope`. This fails if
+    /// the tool was built without the feature this VCS requires.
+    pub fn open(&self, scope: Scope) -> Result<Box<dyn RepoVcs>> {
+        self.open_with(&Config::open(scope)?)
+    }
+
+    /// Open this VCS, as `open` does, with the settings in `config` rather than those in the tool
+    /// configuration file.
+    pub fn open_with(&self, config: &Config) -> Result<Box<dyn RepoVcs>> {
+        match self {
+            #[cfg(feature = "git")]
+            Self::Git => Ok(Box::new(LibraryGit {
+                config: config.git().clone().unwrap_or_default(),
+            })),
+            Self::GitCommand => Ok(Box::new(CommandGit {})),
+            Self::Directory => Ok(Box::new(Directory {})),
+            Self::Archive => Ok(open_archive_vcs(
+                config.archive().clone().unwrap_or_default(),
+            )),
+            #[cfg(feature = "object-store")]
+            Self::ObjectStore => Ok(Box::new(ObjectStore {})),
+            #[allow(unreachable_patterns)]
+            _ => Err(Error::UnsupportedRepoVcs(self.to_string())),
+        }
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+
+#[cfg(feature = "git")]
+impl RepoVcs for LibraryGit {
+    fn is_repository(&self, path: &Path) -> bool {
+        path.join(".git").is_dir()
+    }
+
+    fn init(&self, path: &Path) -> Result<()> {
+        let _ = Repository::init(path)?;
+        Ok(())
+    }
+
+    fn clone_from(&self, url: &str, path: &Path) -> Result<()> {
+        let mut fetch_options = FetchOptions::new();
+        let _ = fetch_options.remote_callbacks(remote_callbacks(&self.config));
+        let repository = RepoBuilder::new()
+            .fetch_options(fetch_options)
+            .clone(url, path)?;
+        if repository.submodules()?.is_empty() {
+            debug!("LibraryGit::clone_from no submodules in repository");
+        } else {
+            let _ = update_submodules(&repository, &self.config)?;
+        }
+        Ok(())
+    }
+
+    fn has_commits(&self, path: &Path) -> Result<bool> {
+        Ok(Repository::open(path)?.head().is_ok())
+    }
+
+    fn commit_all(&self, path: &Path, message: &str) -> Result<()> {
+        let _ = commit_all(&Repository::open(path)?, message)?;
+        Ok(())
+    }
+
+    fn has_origin(&self, path: &Path) -> Result<bool> {
+        Ok(Repository::open(path)?.find_remote("origin").is_ok())
+    }
+
+    fn set_origin(&self, path: &Path, url: &str) -> Result<()> {
+        let _ = Repository::open(path)?.remote("origin", url)?;
+        Ok(())
+    }
+
+    fn refresh(&self, path: &Path) -> Result<bool> {
+        match Repository::open(path) {
+            Err(e) => {
+                if e.code() == ErrorCode::NotFound && e.class() == ErrorClass::Repository {
+                    debug!("Local dir does not contain a Git repo, ignoring refresh");
+                    Ok(false)
+                } else {
+                    Err(e.into())
+                }
+            }
+            Ok(repo) => {
+                let head_ref = repo.head();
+                let head_ref = head_ref.unwrap();
+                let head_ref = head_ref.name().unwrap();
+                debug!("fetching remote reference {}", head_ref);
+
+                let mut fetch_options = FetchOptions::new();
+                let _ = fetch_options.remote_callbacks(remote_callbacks(&self.config));
+                repo.find_remote("origin")?
+                    .fetch(&[head_ref], Some(&mut fetch_options), None)?;
+                // TODO: stop if it is not remote
+
+                let fetch_head = repo.find_reference("FETCH_HEAD")?;
+                let fetch_commit = repo.reference_to_annotated_commit(&fetch_head)?;
+                let analysis = repo.merge_analysis(&[&fetch_commit])?;
+                let changed = if analysis.0.is_up_to_date() {
+                    debug!("No remote changes, repository untouched");
+                    false
+                } else if analysis.0.is_fast_forward() {
+                    debug!("fast-forwarding changes from remote");
+                    let mut reference = repo.find_reference(head_ref)?;
+                    // returns another reference, we can ignore it.
+                    let _ = reference.set_target(fetch_commit.id(), "Fast-Forward")?;
+                    repo.set_head(head_ref)?;
+                    repo.checkout_head(Some(CheckoutBuilder::default().force()))?;
+                    true
+                } else {
+                    panic!("Fast-Forward only");
+                };
+
+                let submodules = update_submodules(&repo, &self.config)?;
+                debug!("{} submodules changed", submodules);
+                Ok(changed || submodules > 0)
+            }
+        }
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+
+impl RepoVcs for CommandGit {
+    fn is_repository(&self, path: &Path) -> bool {
+        path.join(".git").is_dir()
+    }
+
+    fn init(&self, path: &Path) -> Result<()> {
+        let _ = run_git(path, &["init", "--quiet"])?;
+        Ok(())
+    }
+
+    fn clone_from(&self, url: &str, path: &Path) -> Result<()> {
+        let path = path.to_string_lossy();
+        let _ = run_git(
+            Path::new("."),
+            &["clone", "--quiet", "--recurse-submodules", url, &path],
+        )?;
+        Ok(())
+    }
+
+    fn has_commits(&self, path: &Path) -> Result<bool> {
+        Ok(run_git(path, &["rev-parse", "--verify", "--quiet", "HEAD"]).is_ok())
+    }
+
+    fn commit_all(&self, path: &Path, message: &str) -> Result<()> {
+        let _ = run_git(path, &["add", "--all"])?;
+        if run_git(path, &["config", "user.email"]).is_ok() {
+            let _ = run_git(path, &["commit", "--quiet", "--message", message])?;
+        } else {
+            // As with the built-in library, commit as the tool if no Git user is configured.
+            warn!("CommandGit::commit_all: no Git user configured, using default");
+            let user_name = format!("user.name={}", APP_NAME);
+            let user_email = format!("user.email={}@localhost", APP_NAME);
+            let _ = run_git(
+                path,
+                &[
+                    "-c",
+                    &user_name,
+                    "-c",
+                    &user_email,
+                    "commit",
+                    "--quiet",
+                    "--message",
+                    message,
+                ],
+            )?;
+        }
+        Ok(())
+    }
+
+    fn has_origin(&self, path: &Path) -> Result<bool> {
+        Ok(run_git(path, &["remote"])?
+            .lines()
+            .any(|remote| remote.trim() == "origin"))
+    }
+
+    fn set_origin(&self, path: &Path, url: &str) -> Result<()> {
+        let _ = run_git(path, &["remote", "add", "origin", url])?;
+        Ok(())
+    }
+
+    fn refresh(&self, path: &Path) -> Result<bool> {
+        if !self.is_repository(path) {
+            debug!("Local dir does not contain a Git repo, ignoring refresh");
+            return Ok(false);
+        }
+        let before = run_git(path, &["rev-parse", "HEAD"])?;
+        let _ = run_git(path, &["pull", "--quiet", "--ff-only"])?;
+        let after = run_git(path, &["rev-parse", "HEAD"])?;
+        let submodules = run_git(
+            path,
+            &["submodule", "update", "--init", "--recursive", "--quiet"],
+        )?;
+        Ok(before != after || !submodules.trim().is_empty())
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+
+impl RepoVcs for Directory {
+    fn is_repository(&self, path: &Path) -> bool {
+        path.is_dir()
+    }
+
+    fn init(&self, _: &Path) -> Result<()> {
+        Ok(())
+    }
+
+    fn clone_from(&self, _: &str, _: &Path) -> Result<()> {
+        Err(Error::UnsupportedRepoVcsOperation(
+            RepoVcsKind::Directory.to_string(),
+            "clone".to_string(),
+        ))
+    }
+
+    fn has_commits(&self, _: &Path) -> Result<bool> {
+        Ok(false)
+    }
+
+    fn commit_all(&self, _: &Path, _: &str) -> Result<()> {
+        Err(Error::UnsupportedRepoVcsOperation(
+            RepoVcsKind::Directory.to_string(),
+            "commit".to_string(),
+        ))
+    }
+
+    fn has_origin(&self, _: &Path) -> Result<bool> {
+        Ok(false)
+    }
+
+    fn set_origin(&self, _: &Path, _: &str) -> Result<()> {
+        Err(Error::UnsupportedRepoVcsOperation(
+            RepoVcsKind::Directory.to_string(),
+            "remotes".to_string(),
+        ))
+    }
+
+    fn refresh(&self, path: &Path) -> Result<bool> {
+        debug!(
+            "Repository {:?} is not under version control, ignoring refresh",
+            path
+        );
+        Ok(false)
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+
+impl RepoVcs for ArchiveVcs {
+    fn is_repository(&self, path: &Path) -> bool {
+        archive_source_path(path).is_file()
+    }
+
+    fn init(&self, _: &Path) -> Result<()> {
+        Ok(())
+    }
+
+    fn clone_from(&self, url: &str, path: &Path) -> Result<()> {
+        let sha256 = self.expected_sha256()?;
+        self.install(url, &sha256, path)
+    }
+
+    fn has_commits(&self, _: &Path) -> Result<bool> {
+        Ok(false)
+    }
+
+    fn commit_all(&self, _: &Path, _: &str) -> Result<()> {
+        Err(Error::UnsupportedRepoVcsOperation(
+            RepoVcsKind::Archive.to_string(),
+            "commit".to_string(),
+        ))
+    }
+
+    fn has_origin(&self, path: &Path) -> Result<bool> {
+        Ok(self.is_repository(path))
+    }
+
+    fn set_origin(&self, _: &Path, _: &str) -> Result<()> {
+        Err(Error::UnsupportedRepoVcsOperation(
+            RepoVcsKind::Archive.to_string(),
+            "remotes".to_string(),
+        ))
+    }
+
+    fn refresh(&self, path: &Path) -> Result<bool> {
+        let source_path = archive_source_path(path);
+        if !source_path.is_file() {
+            debug!("Local dir was not unpacked from an archive, ignoring refresh");
+            return Ok(false);
+        }
+        let source: ArchiveSource = serde_yaml::from_str(&fs::read_to_string(&source_path)?)?;
+        let sha256 = self.expected_sha256()?;
+        if sha256.eq_ignore_ascii_case(&source.sha256) {
+            debug!("Archive checksum unchanged, repository untouched");
+            Ok(false)
+        } else {
+            self.install(&source.url, &sha256, path)?;
+            Ok(true)
+        }
+    }
+}
+
+impl ArchiveVcs {
+    // The checksum is read from the configuration on each use, rather than recorded, so that a
+    // changed checksum, whether in the configuration or at the checksum URL, is a new archive.
+    fn expected_sha256(&self) -> Result<String> {
+        if let Some(sha256) = self.config.sha256() {
+            Ok(sha256.clone())
+        } else if let Some(sha256_url) = self.config.sha256_url() {
+            let work_dir = temp_dir_for(APP_NAME, "archive")?;
+            let result = download_file(sha256_url, None, work_dir.path())
+                .and_then(|sha256_path| Ok(fs::read_to_string(sha256_path)?));
+            let _ = work_dir.close();
+            match result?.split_whitespace().next() {
+                Some(sha256) => Ok(sha256.to_string()),
+                None => Err(Error::InvalidConfigValue(
+                    "sha256-url".to_string(),
+                    sha256_url.clone(),
+                )),
+            }
+        } else {
+            Err(Error::InvalidConfigValue(
+                "archive".to_string(),
+                "one of sha256 or sha256-url is required".to_string(),
+            ))
+        }
+    }
+
+    // Download, verify, and unpack the archive into a directory within the repository, so that
+    // the unpacked files can be moved, rather than copied, into place. Only once the archive has
+    // been verified and unpacked are the existing repository contents replaced.
+    fn install(&self, url: &str, sha256: &str, path: &Path) -> Result<()> {
+        let work_dir = temp_dir_for(APP_NAME, "archive")?;
+        fs::create_dir_all(path.join(LOCAL_DIR))?;
+        let unpack_dir = temp_dir_in(&path.join(LOCAL_DIR), APP_NAME, "archive")?;
+        let result = download_file(url, Some(sha256), work_dir.path())
+            .and_then(|archive_path| extract_archive(url, &archive_path, unpack_dir.path()))
+            .and_then(|_| replace_contents(&unpack_root(unpack_dir.path())?, path));
+        let _ = work_dir.close();
+        let _ = unpack_dir.close();
+        result?;
+
+        let source = ArchiveSource {
+            url: url.to_string(),
+            sha256: sha256.to_string(),
+        };
+        fs::write(archive_source_path(path), serde_yaml::to_string(&source)?)?;
+        Ok(())
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+
+#[cfg(feature = "object-store")]
+impl RepoVcs for ObjectStore {
+    fn is_repository(&self, path: &Path) -> bool {
+        object_store_source_path(path).is_file()
+    }
+
+    fn init(&self, _: &Path) -> Result<()> {
+        Ok(())
+    }
+
+    fn clone_from(&self, url: &str, path: &Path) -> Result<()> {
+        fs::create_dir_all(path.join(LOCAL_DIR))?;
+        let _ = sync_object_store(url, path)?;
+        let source = ObjectStoreSource {
+            url: url.to_string(),
+        };
+        fs::write(
+            object_store_source_path(path),
+            serde_yaml::to_string(&source)?,
+        )?;
+        Ok(())
+    }
+
+    fn has_commits(&self, _: &Path) -> Result<bool> {
+        Ok(false)
+    }
+
+    fn commit_all(&self, _: &Path, _: &str) -> Result<()> {
+        Err(Error::UnsupportedRepoVcsOperation(
+            RepoVcsKind::ObjectStore.to_string(),
+            "commit".to_string(),
+        ))
+    }
+
+    fn has_origin(&self, path: &Path) -> Result<bool> {
+        Ok(self.is_repository(path))
+    }
+
+    fn set_origin(&self, _: &Path, _: &str) -> Result<()> {
+        Err(Error::UnsupportedRepoVcsOperation(
+            RepoVcsKind::ObjectStore.to_string(),
+            "remotes".to_string(),
+        ))
+    }
+
+    fn refresh(&self, path: &Path) -> Result<bool> {
+        let source_path = object_store_source_path(path);
+        if !source_path.is_file() {
+            debug!("Local dir was not copied from an object store, ignoring refresh");
+            return Ok(false);
+        }
+        let source: ObjectStoreSource = serde_yaml::from_str(&fs::read_to_string(&source_path)?)?;
+        sync_object_store(&source.url, path)
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+// Private Functions
+// ------------------------------------------------------------------------------------------------
+
+fn archive_source_path(path: &Path) -> PathBuf {
+    path.join(LOCAL_DIR).join(ARCHIVE_SOURCE_FILE)
+}
+
+// Archives commonly hold a single top-level directory, named for the project and version; if so
+// that directory is the repository root.
+fn unpack_root(unpack_dir: &Path) -> Result<PathBuf> {
+    let entries: Vec<PathBuf> = fs::read_dir(unpack_dir)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<std::io::Result<_>>()?;
+    match entries.as_slice() {
+        [only] if only.is_dir() => Ok(only.clone()),
+        _ => Ok(unpack_dir.to_path_buf()),
+    }
+}
+
+// Replace everything in `path`, except the local directory, with the contents of `from_dir`.
+fn replace_contents(from_dir: &Path, path: &Path) -> Result<()> {
+    for entry in fs::read_dir(path)? {
+        let entry_path = entry?.path();
+        if entry_path.file_name() == Some(LOCAL_DIR.as_ref()) {
+            continue;
+        }
+        if fs::symlink_metadata(&entry_path)?.is_dir() {
+            fs::remove_dir_all(&entry_path)?;
+        } else {
+            fs::remove_file(&entry_path)?;
+        }
+    }
+    for entry in fs::read_dir(from_dir)? {
+        let from_path = entry?.path();
+        let file_name = from_path.file_name().unwrap();
+        if file_name == LOCAL_DIR {
+            continue;
+        }
+        fs::rename(&from_path, path.join(file_name))?;
+    }
+    Ok(())
+}
+
+// Run `git` in the directory `path`, returning its standard output.
+fn run_git(path: &Path, args: &[&str]) -> Result<String> {
+    let (stdout, _) = run_program("git", path, args)?;
+    Ok(stdout)
+}
+
+// Run `program` in the directory `path`, returning its standard output, and standard error, if it
+// succeeded. The tool's environment is not sanitized, as the program may need credentials from it.
+fn run_program(program: &str, path: &Path, args: &[&str]) -> Result<(String, String)> {
+    debug!("run_program: running {} {:?} in {:?}", program, args, path);
+    ShellCommandBuilder::program(program)
+        .args(args)
+        .current_dir(path)
+        .apply_environment_policy(false)
+        .build()
+        .execute_all_output()
+}
+
+#[cfg(feature = "object-store")]
+fn object_store_source_path(path: &Path) -> PathBuf {
+    path.join(LOCAL_DIR).join(OBJECT_STORE_SOURCE_FILE)
+}
+
+// Copy the objects under the bucket prefix `url` that have changed into `path`, and remove any
+// files no longer in the bucket, except the local directory. Both tools list each object they
+// copy or remove, `aws` on standard output and `gsutil` on standard error, and so any listed
+// object is a change.
+#[cfg(feature = "object-store")]
+fn sync_object_store(url: &str, path: &Path) -> Result<bool> {
+    let path_str = path.to_string_lossy();
+    let ((stdout, stderr), changes): ((String, String), &[&str]) = if url.starts_with("s3://") {
+        (
+            run_program(
+                "aws",
+                path,
+                &[
+                    "s3",
+                    "sync",
+                    "--delete",
+                    "--no-progress",
+                    "--exclude",
+                    ".local/*",
+                    url,
+                    &path_str,
+                ],
+            )?,
+            &["download:", "delete:"],
+        )
+    } else if url.starts_with("gs://") {
+        (
+            run_program(
+                "gsutil",
+                path,
+                &[
+                    "-m",
+                    "rsync",
+                    "-r",
+                    "-d",
+                    "-x",
+                    "^\\.local/",
+                    url,
+                    &path_str,
+                ],
+            )?,
+            &["Copying", "Removing"],
+        )
+    } else {
+        return Err(Error::InvalidConfigValue(
+            "repository-url".to_string(),
+            url.to_string(),
+        ));
+    };
+    Ok(stdout
+        .lines()
+        .chain(stderr.lines())
+        .any(|line| changes.iter().any(|change| line.trim().starts_with(change))))
+}
@@ -0,0 +1,260 @@
+/*!
+Version constraints on packages, and lenient parsing of the version strings reported by installers.
+*/
+
+use crate::error::Error;
+use regex::{Captures, Regex};
+use semver::{Version, VersionReq};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+// ------------------------------------------------------------------------------------------------
+// Public Types
+// ------------------------------------------------------------------------------------------------
+
+///
+/// A semantic version constraint on a package, such as `>=1.5, <2`.
+///
+#[derive(Clone, Debug, PartialEq)]
+pub struct VersionConstraint(VersionReq);
+
+///
+/// The result of comparing a package's installed version against its constraint.
+///
+#[derive(Clone, Debug, PartialEq)]
+pub enum VersionStatus {
+    /// The package is installed and the installed version satisfies the constraint, or there is
+    /// no constraint.
+    Satisfied(Version),
+    /// The installed version does not satisfy the constraint.
+    Violation(Version),
+    /// The package does not appear to be installed.
+    NotInstalled,
+    /// The installed version could not be determined.
+    Unknown,
+}
+
+// ------------------------------------------------------------------------------------------------
+// Public Functions
+// ------------------------------------------------------------------------------------------------
+
+///
+/// Parse a version from the output of an installer command; this finds the first version-like
+/// string, ignores any leading `v`, and fills in missing minor or patch components with zero.
+/// So, `"ripgrep 12.1"` is parsed as `12.1.0`.
+///
+pub fn parse_version(s: &str) -> Option<Version> {
+    VERSION
+        .captures(s)
+        .and_then(|captures| captures_version(&captures))
+}
+
+///
+/// Parse all the versions from the output of an installer command, such as a list of the
+/// versions of a package available to install; each version-like string is parsed as for
+/// `parse_version`.
+///
+pub fn parse_versions(s: &str) -> Vec<Version> {
+    VERSION
+        .captures_iter(s)
+        .filter_map(|captures| captures_version(&captures))
+        .collect()
+}
+
+// ------------------------------------------------------------------------------------------------
+// Implementations
+// ------------------------------------------------------------------------------------------------
+
+impl Display for VersionConstraint {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl FromStr for VersionConstraint {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        VersionReq::parse(s)
+            .map(Self)
+            .map_err(|_| Error::InvalidConfigValue("version".to_string(), s.to_string()))
+    }
+}
+
+impl Serialize for VersionConstraint {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for VersionConstraint {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let buf = String::deserialize(deserializer)?;
+        Self::from_str(&buf).map_err(serde::de::Error::custom)
+    }
+}
+
+impl VersionConstraint {
+    /// Return `true` if the version satisfies this constraint, else `false`.
+    pub fn matches(&self, version: &Version) -> bool {
+        self.0.matches(version)
+    }
+
+    /// Return the highest of `versions` that satisfies this constraint, if any do.
+    pub fn highest_match<'a>(
+        &self,
+        versions: impl IntoIterator<Item = &'a Version>,
+    ) -> Option<&'a Version> {
+        versions
+            .into_iter()
+            .filter(|version| self.matches(version))
+            .max()
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+
+impl Display for VersionStatus {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                VersionStatus::Satisfied(_) => "ok",
+                VersionStatus::Violation(_) => "violation",
+                VersionStatus::NotInstalled => "not installed",
+                VersionStatus::Unknown => "unknown",
+            }
+        )
+    }
+}
+
+impl VersionStatus {
+    /// Return a new status, where any known version is checked against the, optional,
+    /// constraint.
+    pub fn constrained_by(self, constraint: &Option<VersionConstraint>) -> Self {
+        match (self, constraint) {
+            (VersionStatus::Satisfied(version), Some(constraint))
+            | (VersionStatus::Violation(version), Some(constraint)) => {
+                if constraint.matches(&version) {
+                    VersionStatus::Satisfied(version)
+                } else {
+                    VersionStatus::Violation(version)
+                }
+            }
+            (status, _) => status,
+        }
+    }
+
+    /// Return the installed version, if known.
+    pub fn version(&self) -> Option<&Version> {
+        match self {
+            VersionStatus::Satisfied(version) | VersionStatus::Violation(version) => Some(version),
+            _ => None,
+        }
+    }
+
+    /// Return `true` if this is a constraint violation, else `false`.
+    pub fn is_violation(&self) -> bool {
+        matches!(self, VersionStatus::Violation(_))
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+// Private Functions
+// ------------------------------------------------------------------------------------------------
+
+lazy_static! {
+    static ref VERSION: Regex =
+        Regex::new(r"(\d+)(?:\.(\d+))?(?:\.(\d+))?(-[0-9A-Za-z.-]+)?(\+[0-9A-Za-z.-]+)?").unwrap();
+}
+
+fn captures_version(captures: &Captures<'_>) -> Option<Version> {
+    let version_string = format!(
+        "{}.{}.{}{}{}",
+        &captures[1],
+        captures.get(2).map(|m| m.as_str()).unwrap_or("0"),
+        captures.get(3).map(|m| m.as_str()).unwrap_or("0"),
+        captures.get(4).map(|m| m.as_str()).unwrap_or_default(),
+        captures.get(5).map(|m| m.as_str()).unwrap_or_default(),
+    );
+    Version::parse(&version_string).ok()
+}
+
+// ------------------------------------------------------------------------------------------------
+// Unit Tests
+// ------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_parse_version() {
+        assert_eq!(parse_version("1.2.3"), Some(Version::new(1, 2, 3)));
+        assert_eq!(parse_version("v1.2"), Some(Version::new(1, 2, 0)));
+        assert_eq!(
+            parse_version("ripgrep 12.1.1"),
+            Some(Version::new(12, 1, 1))
+        );
+        assert_eq!(
+            parse_version("ripgrep = \"13.0.0\"    # A search tool"),
+            Some(Version::new(13, 0, 0))
+        );
+        assert_eq!(
+            parse_version("tool 2.0.0-beta.1").unwrap().to_string(),
+            "2.0.0-beta.1"
+        );
+        assert_eq!(parse_version("no version here"), None);
+    }
+
+    #[test]
+    fn test_constraint() {
+        let constraint = VersionConstraint::from_str(">=1.5, <2").unwrap();
+        assert!(constraint.matches(&Version::new(1, 5, 0)));
+        assert!(constraint.matches(&Version::new(1, 9, 9)));
+        assert!(!constraint.matches(&Version::new(2, 0, 0)));
+        assert!(!constraint.matches(&Version::new(1, 4, 0)));
+        let constraint = Some(constraint);
+        assert!(VersionStatus::Satisfied(Version::new(2, 1, 0))
+            .constrained_by(&constraint)
+            .is_violation());
+        assert!(!VersionStatus::Satisfied(Version::new(1, 6, 0))
+            .constrained_by(&constraint)
+            .is_violation());
+        assert_eq!(
+            VersionStatus::Unknown.constrained_by(&constraint),
+            VersionStatus::Unknown
+        );
+        assert!(VersionConstraint::from_str("not a version").is_err());
+    }
+
+    #[test]
+    fn test_highest_match() {
+        let versions = parse_versions("tool (2.1.0, 1.9.2, 1.10, 1.4.0)");
+        assert_eq!(
+            versions,
+            vec![
+                Version::new(2, 1, 0),
+                Version::new(1, 9, 2),
+                Version::new(1, 10, 0),
+                Version::new(1, 4, 0),
+            ]
+        );
+        let constraint = VersionConstraint::from_str(">=1.5, <2").unwrap();
+        assert_eq!(
+            constraint.highest_match(&versions),
+            Some(&Version::new(1, 10, 0))
+        );
+        let constraint = VersionConstraint::from_str(">=3").unwrap();
+        assert_eq!(constraint.highest_match(&versions), None);
+    }
+}
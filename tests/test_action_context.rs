@@ -0,0 +1,107 @@
+use mcfg::actions::{ActionContext, InstallAction, ListAction};
+use mcfg::error::Error;
+use mcfg::reporter::{CiFormat, Reporter};
+use mcfg::shared::builders::{
+    Builder, InstallerBuilder, PackageBuilder, PackageRepositoryBuilder, PackageSetBuilder,
+    PackageSetGroupBuilder,
+};
+use mcfg::shared::{Config, InstallerRegistry, Name, PackageSetSelection, Platform};
+use std::collections::HashMap;
+use std::env::current_dir;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+fn test_context() -> ActionContext {
+    let root = current_dir().unwrap().join("tests/root");
+    ActionContext::new()
+        .with_repository_path(root.join("data/repository"))
+        .with_registry_path(root.join("config/installers.yml"))
+}
+
+#[test]
+fn test_context_reads_resources_once() {
+    let context = test_context();
+    let repository = context.repository().unwrap();
+    assert!(repository
+        .group(&Name::from_str("system").unwrap())
+        .is_some());
+    assert!(std::ptr::eq(repository, context.repository().unwrap()));
+
+    let registry = context.registry().unwrap();
+    assert!(std::ptr::eq(registry, context.registry().unwrap()));
+}
+
+#[test]
+fn test_context_with_provided_resources() {
+    let mut environment: HashMap<String, String> = Default::default();
+    let _ = environment.insert("name".to_string(), "value".to_string());
+    let context = ActionContext::new()
+        .with_registry_path(PathBuf::from("/no/such/installers.yml"))
+        .with_registry(InstallerRegistry::from(Vec::default()))
+        .with_environment(environment.clone())
+        .with_reporter(Reporter::new(false).with_ci_format(Some(CiFormat::GitHub)));
+
+    assert!(context.registry().unwrap().is_empty());
+    assert_eq!(context.environment(), &environment);
+    assert!(!context.reporter().is_interactive());
+    assert_eq!(context.reporter().ci_format(), Some(CiFormat::GitHub));
+}
+
+#[test]
+fn test_action_uses_context_repository() {
+    let context = test_context();
+    let action = ListAction::new_action(Some(Name::from_str("system").unwrap())).unwrap();
+    assert!(action.run(&context).is_ok());
+
+    let action = ListAction::new_action(Some(Name::from_str("sytem").unwrap())).unwrap();
+    match action.run(&context) {
+        Err(Error::NoPackageSetGroup { name, suggestions }) => {
+            assert_eq!(name, "sytem");
+            assert_eq!(suggestions, vec!["system".to_string()]);
+        }
+        result => panic!("expected no package set group, not {:?}", result),
+    }
+}
+
+#[test]
+fn test_install_action_uses_provided_resources() {
+    let work_dir = tempfile::tempdir().unwrap();
+    let actions_path = work_dir.path().join("actions.txt");
+    let repository_path = work_dir.path().join("repository");
+    let named = |name: &str| Name::from_str(name).unwrap();
+
+    let installer = InstallerBuilder::named(named("recording"))
+        .for_platform(Platform::default())
+        .for_default_packages()
+        .add_install_command(&format!(
+            "echo install {{{{package_name}}}} >> {}",
+            actions_path.display()
+        ))
+        .build();
+    let package_set = PackageSetBuilder::named(named("tools"))
+        .path(repository_path.join("dev/tools.yml"))
+        .package_actions(&[PackageBuilder::named(named("ripgrep")).build()])
+        .build();
+    let mut group = PackageSetGroupBuilder::new_in(repository_path.join("dev"));
+    group.add_package_set(package_set);
+    let mut repository = PackageRepositoryBuilder::new_in(repository_path.clone());
+    repository.add_package_set_group(group.build());
+
+    let context = ActionContext::new()
+        .with_config_path(PathBuf::from("/no/such/config.toml"))
+        .with_config(Config::default())
+        .with_install_log_path(work_dir.path().join("install-log"))
+        .with_registry_path(PathBuf::from("/no/such/installers.yml"))
+        .with_registry(InstallerRegistry::from(vec![installer]))
+        .with_repository(repository.build());
+
+    let action =
+        InstallAction::install_action(PackageSetSelection::all(), Default::default()).unwrap();
+    action.run(&context).unwrap();
+
+    assert_eq!(
+        std::fs::read_to_string(&actions_path).unwrap().trim(),
+        "install ripgrep"
+    );
+    assert!(!repository_path.join("dev").exists());
+}
@@ -0,0 +1,42 @@
+use mcfg::actions::{ActionContext, ActionReport, ListAction, ReportFormat};
+use pretty_assertions::assert_eq;
+use std::env::current_dir;
+use std::str::FromStr;
+use std::time::Duration;
+
+#[test]
+fn test_default_report() {
+    let context = ActionContext::new()
+        .with_repository_path(current_dir().unwrap().join("tests/root/data/repository"));
+    let action = ListAction::new_action(None).unwrap();
+    let report = action.run_with_report(&context).unwrap();
+    assert!(report.processed().is_empty());
+    assert!(report.warnings().is_empty());
+    assert!(report.is_success());
+}
+
+#[test]
+fn test_format_report() {
+    let mut report = ActionReport::new(Duration::from_millis(1500));
+    report.add_processed("dev/rust");
+    report.add_warning("no package set 'dev/go'");
+    report.add_error("package set dev/python failed");
+    assert!(!report.is_success());
+
+    assert_eq!(
+        report.format(ReportFormat::Text),
+        "Processed 1 item(s) in 1.50s, with 1 warning(s) and 1 error(s)\n  dev/rust\nWarning: no package set 'dev/go'\nError: package set dev/python failed"
+    );
+
+    let json: serde_json::Value = serde_json::from_str(&report.format(ReportFormat::Json)).unwrap();
+    assert_eq!(json["processed"][0], "dev/rust");
+    assert_eq!(json["duration_secs"], 1.5);
+    assert_eq!(json["succeeded"], false);
+}
+
+#[test]
+fn test_report_format_from_str() {
+    assert_eq!(ReportFormat::from_str("json").unwrap(), ReportFormat::Json);
+    assert_eq!(ReportFormat::from_str("text").unwrap(), ReportFormat::Text);
+    assert!(ReportFormat::from_str("yaml").is_err());
+}
@@ -0,0 +1,89 @@
+use mcfg::actions::{ActionContext, BackupAction};
+use mcfg::error::Error;
+use mcfg::shared::{Config, FileSystemResource, Scope};
+use pretty_assertions::assert_eq;
+use std::fs::{read_to_string, write};
+use std::path::Path;
+
+const PACKAGE_SET: &str = "name: tools\n";
+const REGISTRY: &str = "---\nversion: 2\ninstallers: []\n";
+const CONFIG: &str = "---\nmachine-id: backup-test\n";
+
+fn create_files(root: &Path) {
+    std::fs::create_dir_all(root.join("repository/dev")).unwrap();
+    write(root.join("repository/dev/tools.yml"), PACKAGE_SET).unwrap();
+    write(root.join("installers.yml"), REGISTRY).unwrap();
+    std::fs::create_dir_all(Config::default_path(Scope::User).parent().unwrap()).unwrap();
+    write(Config::default_path(Scope::User), CONFIG).unwrap();
+}
+
+fn remove_files(root: &Path) {
+    std::fs::remove_dir_all(root.join("repository")).unwrap();
+    std::fs::remove_file(root.join("installers.yml")).unwrap();
+    std::fs::remove_file(Config::default_path(Scope::User)).unwrap();
+}
+
+// This is the only test in this file, as it points the home directory, and so the configuration
+// file path, at a temporary directory for the whole process.
+#[test]
+fn test_backup_and_restore() {
+    let root = tempfile::tempdir().unwrap();
+    let root = root.path();
+    std::env::set_var("HOME", root.join("home"));
+    std::env::set_var("XDG_CONFIG_HOME", root.join("home/.config"));
+    std::env::set_var("XDG_DATA_HOME", root.join("home/.local/share"));
+    create_files(root);
+    let context = ActionContext::new()
+        .with_repository_path(root.join("repository"))
+        .with_registry_path(root.join("installers.yml"));
+    let archive = root.join("backup.tar.gz");
+
+    BackupAction::backup_action(archive.clone())
+        .unwrap()
+        .run(&context)
+        .unwrap();
+    assert!(archive.is_file());
+
+    // Restoring over an existing repository is refused.
+    match BackupAction::restore_action(archive.clone(), None)
+        .unwrap()
+        .run(&context)
+    {
+        Err(Error::RestoreTargetExists(_)) => {}
+        result => panic!("expected restore target exists, not {:?}", result),
+    }
+
+    remove_files(root);
+    BackupAction::restore_action(archive.clone(), None)
+        .unwrap()
+        .run(&context)
+        .unwrap();
+    assert_eq!(
+        read_to_string(root.join("repository/dev/tools.yml")).unwrap(),
+        PACKAGE_SET
+    );
+    assert_eq!(
+        read_to_string(root.join("installers.yml")).unwrap(),
+        REGISTRY
+    );
+    assert_eq!(
+        read_to_string(Config::default_path(Scope::User)).unwrap(),
+        CONFIG
+    );
+
+    // Restoring to a local directory leaves a link at the repository path.
+    remove_files(root);
+    let local_dir = root.join("local-repository");
+    BackupAction::restore_action(archive, Some(local_dir.to_string_lossy().into_owned()))
+        .unwrap()
+        .run(&context)
+        .unwrap();
+    assert_eq!(
+        std::fs::read_link(root.join("repository")).unwrap(),
+        local_dir
+    );
+    assert_eq!(
+        read_to_string(local_dir.join("dev/tools.yml")).unwrap(),
+        PACKAGE_SET
+    );
+}
@@ -0,0 +1,121 @@
+use mcfg::shared::{Config, FileSystemResource, RepoVcsKind, WebhookKind};
+use pretty_assertions::assert_eq;
+use std::env::current_dir;
+
+#[test]
+fn test_missing_config_file_is_default() {
+    let config = Config::open_from(
+        current_dir()
+            .unwrap()
+            .join("tests/root/config/no-such-file.yml"),
+    )
+    .unwrap();
+    assert_eq!(config, Config::default());
+    assert!(config.notifications().is_none());
+}
+
+#[test]
+fn test_parse_notifications() {
+    let config: Config = serde_yaml::from_str(
+        r##"
+notifications:
+  on-success: false
+  min-duration-secs: 120
+"##,
+    )
+    .unwrap();
+    let notifications = config.notifications().as_ref().unwrap();
+    assert_eq!(notifications.on_success(), false);
+    assert_eq!(notifications.on_failure(), true);
+    assert_eq!(notifications.min_duration_secs(), 120);
+    assert_eq!(notifications.command(), &None);
+}
+
+#[test]
+fn test_parse_git() {
+    let config: Config = serde_yaml::from_str(
+        r##"
+git:
+  ssh-agent: false
+  ssh-key: ~/.ssh/id_work
+"##,
+    )
+    .unwrap();
+    let git = config.git().as_ref().unwrap();
+    assert_eq!(git.ssh_agent(), false);
+    assert_eq!(git.ssh_key(), &Some("~/.ssh/id_work".to_string()));
+    assert_eq!(git.ssh_key_passphrase_var(), &None);
+    assert_eq!(git.credential_helper(), true);
+    assert_eq!(git.token_var(), "MCFG_GIT_TOKEN");
+}
+
+#[test]
+fn test_parse_webhooks() {
+    let config: Config = serde_yaml::from_str(
+        r##"
+webhooks:
+  - url: https://hooks.slack.com/services/T000/B000/XXXX
+    kind: slack
+    on-success: false
+  - url: https://example.com/mcfg
+"##,
+    )
+    .unwrap();
+    let webhooks = config.webhooks();
+    assert_eq!(webhooks.len(), 2);
+    assert_eq!(webhooks[0].kind(), WebhookKind::Slack);
+    assert_eq!(webhooks[0].on_success(), false);
+    assert_eq!(webhooks[0].on_failure(), true);
+    assert_eq!(webhooks[1].url(), "https://example.com/mcfg");
+    assert_eq!(webhooks[1].kind(), WebhookKind::Generic);
+    assert_eq!(webhooks[1].on_success(), true);
+}
+
+#[test]
+fn test_parse_metrics() {
+    let config: Config = serde_yaml::from_str(
+        r##"
+metrics:
+  textfile: /var/lib/node_exporter/textfile/mcfg.prom
+"##,
+    )
+    .unwrap();
+    let metrics = config.metrics().as_ref().unwrap();
+    assert_eq!(
+        metrics.textfile(),
+        &Some("/var/lib/node_exporter/textfile/mcfg.prom".to_string())
+    );
+    assert_eq!(metrics.statsd(), &None);
+    assert_eq!(metrics.statsd_prefix(), "mcfg");
+}
+
+#[test]
+fn test_parse_vcs() {
+    let config: Config = serde_yaml::from_str("vcs: git-command\n").unwrap();
+    assert_eq!(config.vcs(), &Some(RepoVcsKind::GitCommand));
+
+    let config: Config = serde_yaml::from_str("vcs: directory\n").unwrap();
+    assert_eq!(config.vcs(), &Some(RepoVcsKind::Directory));
+
+    let config: Config = serde_yaml::from_str("vcs: object-store\n").unwrap();
+    assert_eq!(config.vcs(), &Some(RepoVcsKind::ObjectStore));
+
+    assert!(serde_yaml::from_str::<Config>("vcs: svn\n").is_err());
+}
+
+#[test]
+fn test_parse_archive() {
+    let config: Config = serde_yaml::from_str(
+        "vcs: archive\narchive:\n  sha256-url: https://example.com/repository.tar.gz.sha256\n",
+    )
+    .unwrap();
+    assert_eq!(config.vcs(), &Some(RepoVcsKind::Archive));
+    let archive = config.archive().clone().unwrap();
+    assert_eq!(archive.sha256(), &None);
+    assert_eq!(
+        archive.sha256_url(),
+        &Some("https://example.com/repository.tar.gz.sha256".to_string())
+    );
+
+    assert!(serde_yaml::from_str::<Config>("archive:\n  md5: abc\n").is_err());
+}
@@ -0,0 +1,75 @@
+use mcfg::shared::dirs::{XDG_CONFIG_HOME, XDG_DATA_HOME, XDG_STATE_HOME};
+use mcfg::shared::{
+    dirs, Config, FileSystemResource, InstallLogFormat, InstallerRegistry, PackageRepository, Scope,
+};
+use pretty_assertions::assert_eq;
+use std::env;
+use std::path::PathBuf;
+
+// The environment is shared by all tests in this file, so all cases are in one test.
+#[test]
+fn test_xdg_overrides() {
+    env::set_var(XDG_CONFIG_HOME, "/tmp/mcfg-xdg/config");
+    env::set_var(XDG_DATA_HOME, "/tmp/mcfg-xdg/data");
+    env::set_var(XDG_STATE_HOME, "/tmp/mcfg-xdg/state");
+
+    assert_eq!(
+        Config::default_path(Scope::User),
+        PathBuf::from("/tmp/mcfg-xdg/config/mcfg/config.yml")
+    );
+    assert_eq!(
+        InstallerRegistry::default_path(Scope::User),
+        PathBuf::from("/tmp/mcfg-xdg/config/mcfg/installers.yml")
+    );
+    assert_eq!(
+        PackageRepository::default_path(Scope::User),
+        PathBuf::from("/tmp/mcfg-xdg/config/mcfg/repository")
+    );
+    assert_eq!(
+        InstallLogFormat::Sqlite.default_path(Scope::User),
+        PathBuf::from("/tmp/mcfg-xdg/state/mcfg/log/install-log.sql")
+    );
+    assert_eq!(
+        dirs::data_local_dir_for(Scope::User, "ripgrep"),
+        Some(PathBuf::from("/tmp/mcfg-xdg/data/ripgrep"))
+    );
+
+    // Relative paths are ignored, as required by the XDG specification.
+    env::set_var(XDG_CONFIG_HOME, "relative/config");
+    assert_ne!(
+        Config::default_path(Scope::User),
+        PathBuf::from("relative/config/mcfg/config.yml")
+    );
+    assert!(Config::default_path(Scope::User).is_absolute());
+
+    env::remove_var(XDG_CONFIG_HOME);
+    env::remove_var(XDG_DATA_HOME);
+    env::remove_var(XDG_STATE_HOME);
+}
+
+#[test]
+fn test_relative_path() {
+    assert_eq!(
+        dirs::relative_path(
+            &PathBuf::from("/home/me/.config"),
+            &PathBuf::from("/home/me/.config/mcfg/repository/env")
+        ),
+        PathBuf::from("mcfg/repository/env")
+    );
+    assert_eq!(
+        dirs::relative_path(
+            &PathBuf::from("/home/me/.local/bin"),
+            &PathBuf::from("/home/me/.config/mcfg/repository/tool")
+        ),
+        PathBuf::from("../../.config/mcfg/repository/tool")
+    );
+    assert_eq!(
+        dirs::relative_path(&PathBuf::from("/home/me"), &PathBuf::from("/home/me")),
+        PathBuf::from(".")
+    );
+    // Relative paths are returned unchanged.
+    assert_eq!(
+        dirs::relative_path(&PathBuf::from("/home/me"), &PathBuf::from("tool")),
+        PathBuf::from("tool")
+    );
+}
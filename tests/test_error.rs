@@ -0,0 +1,48 @@
+use mcfg::error::{Error, Result};
+use mcfg::shared::Name;
+use std::error::Error as StdError;
+use std::str::FromStr;
+
+#[test]
+fn test_match_error_variants() {
+    match Name::from_str("not a name") {
+        Err(Error::InvalidNameString(name)) => assert_eq!(name, "not a name"),
+        result => panic!("expected an invalid name, not {:?}", result),
+    }
+
+    let error = Name::from_str("").unwrap_err();
+    assert!(matches!(error, Error::InvalidNameString(_)));
+}
+
+#[test]
+fn test_error_source() {
+    fn read_missing() -> Result<String> {
+        Ok(std::fs::read_to_string("/no/such/mcfg/file")?)
+    }
+
+    let error = read_missing().unwrap_err();
+    assert!(matches!(error, Error::Io(_)));
+    let source = error.source().unwrap();
+    assert_eq!(source.to_string(), error.to_string());
+    assert!(source.downcast_ref::<std::io::Error>().is_some());
+
+    let error = Error::NoPackageSetGroup {
+        name: "tols".to_string(),
+        suggestions: Vec::default(),
+    };
+    assert!(error.source().is_none());
+    assert_eq!(
+        error.to_string(),
+        "No package set group 'tols' found in the repository"
+    );
+
+    let error = Error::NoPackageSet {
+        group: "dev".to_string(),
+        name: "tols".to_string(),
+        suggestions: vec!["tools".to_string(), "tool".to_string()],
+    };
+    assert_eq!(
+        error.to_string(),
+        "No package set 'tols' found in group 'dev'; did you mean 'tools', or 'tool'?"
+    );
+}
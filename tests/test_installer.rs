@@ -1,7 +1,11 @@
 use mcfg::shared::builders::Builder;
 use mcfg::shared::installer::builders::InstallerBuilder;
-use mcfg::shared::{Installer, Name, PackageKind, Platform};
+use mcfg::shared::{
+    default_vars, is_root, IfExists, InstallActionKind, Installer, InstallerCommand, Name,
+    PackageKind, Platform, RunAs, Scope,
+};
 use pretty_assertions::assert_eq;
+use std::collections::HashMap;
 use std::str::FromStr;
 
 #[test]
@@ -40,3 +44,217 @@ fn test_write() {
     let new_installers: Vec<Installer> = serde_yaml::from_str(&installers_str).unwrap();
     assert_eq!(installers, new_installers);
 }
+
+#[test]
+fn test_list_installed_packages() {
+    let installer = InstallerBuilder::named(Name::from_str("lister").unwrap())
+        .for_any_platform()
+        .list_installed_command("printf 'ripgrep v12.1.1:\\n    rg\\nfd-find\\n*** header\\n'")
+        .build();
+    let packages: Vec<String> = installer
+        .list_installed_packages(&default_vars(Scope::User))
+        .unwrap()
+        .into_iter()
+        .map(|name| name.to_string())
+        .collect();
+    assert_eq!(packages, vec!["ripgrep".to_string(), "fd-find".to_string()]);
+}
+
+#[test]
+fn test_installer_run_as() {
+    let installers_str = r##"
+        - name: homebrew
+          kind: default
+          run_as: user
+        - name: apt
+          kind: default
+          run_as: root
+        - name: cargo
+          kind:
+            language: rust
+        "##;
+    let installers: Vec<Installer> = serde_yaml::from_str(installers_str).unwrap();
+    assert_eq!(installers[0].run_as(), RunAs::User);
+    assert_eq!(installers[1].run_as(), RunAs::Root);
+    assert_eq!(installers[2].run_as(), RunAs::Any);
+
+    assert_eq!(installers[0].check_run_as().is_ok(), !is_root());
+    assert_eq!(installers[1].check_run_as().is_ok(), is_root());
+    assert!(installers[2].check_run_as().is_ok());
+
+    let installer = InstallerBuilder::named(Name::from_str("brew").unwrap())
+        .run_as(RunAs::User)
+        .build();
+    assert_eq!(installer.run_as(), RunAs::User);
+}
+
+#[test]
+fn test_installer_exec_commands() {
+    let installers_str = r##"
+        - name: apt
+          kind: default
+          commands:
+            install: "apt-get install {{package_name}}"
+            uninstall: [ apt-get, remove, "{{package_name}}" ]
+        "##;
+    let installers: Vec<Installer> = serde_yaml::from_str(installers_str).unwrap();
+    assert_eq!(
+        installers[0].command_for(&InstallActionKind::Install),
+        Some(&InstallerCommand::Script(
+            "apt-get install {{package_name}}".to_string()
+        ))
+    );
+    assert_eq!(
+        installers[0].command_for(&InstallActionKind::Uninstall),
+        Some(&InstallerCommand::Exec(vec![
+            "apt-get".to_string(),
+            "remove".to_string(),
+            "{{package_name}}".to_string()
+        ]))
+    );
+
+    let installers_str = serde_yaml::to_string(&installers).unwrap();
+    let new_installers: Vec<Installer> = serde_yaml::from_str(&installers_str).unwrap();
+    assert_eq!(installers, new_installers);
+}
+
+#[test]
+fn test_installer_exec_command_is_not_shell_interpreted() {
+    let mut variable_replacements: HashMap<String, String> = Default::default();
+    let _ = variable_replacements.insert(
+        "package_name".to_string(),
+        "ripgrep; echo \"$HOME\" `id`".to_string(),
+    );
+    let command = InstallerCommand::Exec(vec![
+        "printf".to_string(),
+        "%s".to_string(),
+        "{{package_name}}".to_string(),
+    ]);
+    assert_eq!(
+        command.execute_output(&variable_replacements).unwrap(),
+        "ripgrep; echo \"$HOME\" `id`"
+    );
+    assert_eq!(command.to_string(), "printf '%s' {{package_name}}");
+
+    assert!(InstallerCommand::Exec(Vec::default())
+        .execute(&variable_replacements)
+        .is_err());
+}
+
+#[test]
+fn test_installer_command_program() {
+    let mut variable_replacements: HashMap<String, String> = Default::default();
+    let _ = variable_replacements.insert("brew".to_string(), "/opt/homebrew/bin/brew".to_string());
+    let _ = variable_replacements.insert("package_name".to_string(), "ripgrep".to_string());
+    let command = InstallerCommand::Script(
+        "HOMEBREW_NO_AUTO_UPDATE=1 {{brew}} install '{{package_name}}'".to_string(),
+    );
+    assert_eq!(
+        command.program(&variable_replacements),
+        Some("/opt/homebrew/bin/brew".to_string())
+    );
+    assert_eq!(
+        command.with_vars(&variable_replacements),
+        "HOMEBREW_NO_AUTO_UPDATE=1 /opt/homebrew/bin/brew install 'ripgrep'"
+    );
+    let command = InstallerCommand::Exec(vec!["{{brew}}".to_string(), "update".to_string()]);
+    assert_eq!(
+        command.program(&variable_replacements),
+        Some("/opt/homebrew/bin/brew".to_string())
+    );
+}
+
+#[test]
+fn test_installer_smoke_test() {
+    let installer = InstallerBuilder::named(Name::from_str("smoky").unwrap())
+        .for_any_platform()
+        .for_default_packages()
+        .if_exists("/no/such/path/for/mcfg")
+        .add_install_command("true {{package_name}}")
+        .latest_version_command("echo 'smoky 1.2.3'")
+        .build();
+    let checks = installer.smoke_test(&default_vars(Scope::User));
+    assert_eq!(
+        checks
+            .iter()
+            .map(|check| (check.check().as_str(), check.is_passed()))
+            .collect::<Vec<(&str, bool)>>(),
+        vec![
+            ("if-exists", false),
+            ("run-as", true),
+            ("latest-version", true),
+            ("install", true),
+            ("install", true),
+        ]
+    );
+    assert_eq!(checks[2].detail(), "reported version 1.2.3");
+    assert!(checks[3]
+        .detail()
+        .starts_with("'true mcfg-smoke-test', runs "));
+
+    let installer = InstallerBuilder::named(Name::from_str("broken").unwrap())
+        .for_any_platform()
+        .for_default_packages()
+        .add_install_command("no-such-mcfg-program {{package_name}} |")
+        .build();
+    let failed: Vec<String> = installer
+        .smoke_test(&default_vars(Scope::User))
+        .iter()
+        .filter(|check| !check.is_passed())
+        .map(|check| check.to_string())
+        .collect();
+    assert_eq!(failed.len(), 2);
+    assert!(failed[0].starts_with("[failed] install: "));
+    assert!(failed[0].ends_with("no-such-mcfg-program not found on PATH"));
+}
+
+#[test]
+fn test_if_exists_checks() {
+    let installers_str = r##"
+        - name: path
+          kind: default
+          if_exists: /
+        - name: command
+          kind: default
+          if_exists:
+            command: "false"
+        - name: any
+          kind: default
+          if_exists:
+            any:
+              - /no/such/path/for/mcfg
+              - command: "true"
+        - name: all
+          kind: default
+          if_exists:
+            all:
+              - /
+              - command: "test -d /no/such/path/for/mcfg"
+        "##;
+    let installers: Vec<Installer> = serde_yaml::from_str(installers_str).unwrap();
+    assert_eq!(
+        installers
+            .iter()
+            .map(|installer| {
+                (
+                    installer.name().to_string(),
+                    installer.if_exists_match(&default_vars(Scope::User)),
+                )
+            })
+            .collect::<Vec<(String, bool)>>(),
+        vec![
+            ("path".to_string(), true),
+            ("command".to_string(), false),
+            ("any".to_string(), true),
+            ("all".to_string(), false),
+        ]
+    );
+    assert_eq!(
+        installers[0].if_exists(),
+        &Some(IfExists::Path("/".to_string()))
+    );
+    assert_eq!(
+        installers[2].if_exists().as_ref().unwrap().to_string(),
+        "any of (path /no/such/path/for/mcfg, command 'true')"
+    );
+}
@@ -1,7 +1,22 @@
-use mcfg::shared::installer::InstallerRegistry;
-use mcfg::shared::FileSystemResource;
+use mcfg::error::Error;
+use mcfg::shared::builders::{
+    Builder, InstallerBuilder, PackageBuilder, PackageSetBuilder, PackageSetGroupBuilder,
+};
+use mcfg::shared::installer::{
+    format_registry, read_registry_installers, set_installer_enabled, Installer, InstallerRegistry,
+};
+use mcfg::shared::migrate::{pending_migrations, registry_version};
+use mcfg::shared::{
+    ExecuteOptions, FileSystemResource, InstallActionKind, InstallLogFormat, InstalledPackage,
+    LogEntry, MigrationTarget, Name, PackageKind, PackageLogQuery, PackageRepository,
+    PackageSetSelection, Platform,
+};
 use pretty_assertions::assert_eq;
 use std::env::current_dir;
+use std::fs::read_to_string;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use time::{Duration, OffsetDateTime};
 
 #[test]
 fn test_parse_installer_file() {
@@ -18,3 +33,746 @@ fn test_parse_installer_file() {
     #[cfg(target_os = "linux")]
     assert_eq!(registry.installers().count(), 3);
 }
+
+#[test]
+fn test_format_installer_file() {
+    let registry_data = read_to_string(
+        current_dir()
+            .unwrap()
+            .join("tests/root/config/installers.yml"),
+    )
+    .unwrap();
+
+    let formatted = format_registry(&registry_data).unwrap();
+    assert!(formatted.contains("installers:"));
+    assert_eq!(format_registry(&formatted).unwrap(), formatted);
+    assert_eq!(
+        formatted.matches("- name:").count(),
+        registry_data.matches("- name:").count()
+    );
+}
+
+#[test]
+fn test_read_list_registry_file() {
+    // The test registry is a list of installers, as written before the registry had a version.
+    let registry_path = current_dir()
+        .unwrap()
+        .join("tests/root/config/installers.yml");
+    let registry_data = read_to_string(&registry_path).unwrap();
+    assert_eq!(registry_version(&registry_data).unwrap(), 1);
+    let work_dir = tempfile::tempdir().unwrap();
+    let migrated_path = work_dir.path().join("installers.yml");
+    std::fs::copy(&registry_path, &migrated_path).unwrap();
+    for migration in pending_migrations(MigrationTarget::Registry, 1) {
+        migration.apply(&migrated_path).unwrap();
+    }
+    assert_eq!(
+        read_registry_installers(&registry_path).unwrap(),
+        read_registry_installers(&migrated_path).unwrap()
+    );
+}
+
+#[test]
+fn test_read_all_registry_installers() {
+    let registry_path = current_dir()
+        .unwrap()
+        .join("tests/root/config/installers.yml");
+    let registry_data = read_to_string(&registry_path).unwrap();
+    assert_eq!(
+        read_registry_installers(&registry_path).unwrap().len(),
+        registry_data.matches("- name:").count()
+    );
+}
+
+#[test]
+fn test_disable_and_enable_installer() {
+    let work_dir = tempfile::tempdir().unwrap();
+    let registry_path = work_dir.path().join("installers.yml");
+    let _ = std::fs::copy(
+        current_dir()
+            .unwrap()
+            .join("tests/root/config/installers.yml"),
+        &registry_path,
+    )
+    .unwrap();
+    let count = || {
+        InstallerRegistry::open_from(registry_path.clone())
+            .unwrap()
+            .installers()
+            .count()
+    };
+    let before = count();
+    let conda = Name::from_str("conda").unwrap();
+
+    assert_eq!(
+        set_installer_enabled(&registry_path, &conda, false).unwrap(),
+        1
+    );
+    assert_eq!(count(), before - 1);
+    let installers = read_registry_installers(&registry_path).unwrap();
+    assert_eq!(installers.len(), 5);
+    assert!(!installers
+        .iter()
+        .find(|i| i.name() == &conda)
+        .unwrap()
+        .is_enabled());
+    assert_eq!(
+        set_installer_enabled(&registry_path, &conda, false).unwrap(),
+        0
+    );
+
+    assert_eq!(
+        set_installer_enabled(&registry_path, &conda, true).unwrap(),
+        1
+    );
+    assert_eq!(count(), before);
+    assert!(!read_to_string(&registry_path).unwrap().contains("enabled"));
+
+    assert!(
+        set_installer_enabled(&registry_path, &Name::from_str("nope").unwrap(), false).is_err()
+    );
+}
+
+#[test]
+fn test_application_installer_priority() {
+    let named = |name: &str| Name::from_str(name).unwrap();
+    let registry = InstallerRegistry::from(vec![
+        InstallerBuilder::named(named("snap"))
+            .for_platform(Platform::Linux)
+            .for_named_application_packages(&named("snap"))
+            .priority(10)
+            .build(),
+        InstallerBuilder::named(named("flatpak"))
+            .for_platform(Platform::Linux)
+            .for_named_application_packages(&named("flatpak"))
+            .build(),
+        InstallerBuilder::named(named("appimage"))
+            .for_platform(Platform::Linux)
+            .for_application_packages()
+            .priority(5)
+            .build(),
+    ]);
+    let installer_for = |kind: PackageKind| {
+        registry
+            .installer_for(Platform::Linux, kind)
+            .map(|installer| installer.name().to_string())
+    };
+    assert_eq!(
+        installer_for(PackageKind::Application(None)),
+        Some("flatpak".to_string())
+    );
+    assert_eq!(
+        installer_for(PackageKind::Application(Some(named("snap")))),
+        Some("snap".to_string())
+    );
+    assert_eq!(
+        installer_for(PackageKind::Application(Some(named("cask")))),
+        None
+    );
+    assert_eq!(installer_for(PackageKind::Default), None);
+}
+
+#[test]
+fn test_audit_package_set() {
+    let named = |name: &str| Name::from_str(name).unwrap();
+    let registry = InstallerBuilder::named(named("apt"))
+        .for_platform(Platform::default())
+        .for_default_packages()
+        .add_install_command("apt-get install {{package_name}}")
+        .add_uninstall_command("apt-get remove {{package_name}}")
+        .build();
+    let registry = InstallerRegistry::from(vec![registry]);
+    let package_set = PackageSetBuilder::named(named("tools"))
+        .path(PathBuf::from("/repository/dev/tools.yml"))
+        .run_before("echo before {{package_set_name}}")
+        .run_after("echo after")
+        .package_actions(&[PackageBuilder::named(named("ripgrep")).build()])
+        .build();
+
+    let audited = |action: InstallActionKind| {
+        registry
+            .audit_package_set(&action, &package_set)
+            .iter()
+            .map(|command| format!("{}: {}", command.source(), command.command()))
+            .collect::<Vec<String>>()
+    };
+    assert_eq!(
+        audited(InstallActionKind::Install),
+        vec![
+            "run-before: echo before tools",
+            "package ripgrep [apt]: apt-get install ripgrep",
+            "run-after: echo after",
+        ]
+    );
+    assert_eq!(
+        audited(InstallActionKind::Uninstall),
+        vec![
+            "run-after: echo after",
+            "package ripgrep [apt]: apt-get remove ripgrep",
+            "run-before: echo before tools",
+        ]
+    );
+    assert_eq!(
+        audited(InstallActionKind::Update),
+        vec!["run-before: echo before tools", "run-after: echo after"]
+    );
+}
+
+#[test]
+fn test_audit_package_set_prerequisites() {
+    let named = |name: &str| Name::from_str(name).unwrap();
+    let registry = InstallerBuilder::named(named("homebrew"))
+        .for_platform(Platform::default())
+        .for_default_packages()
+        .add_install_command("brew install {{package_name}}")
+        .add_uninstall_command("brew uninstall {{package_name}}")
+        .add_prerequisite_command("brew tap '{{prerequisite}}'")
+        .build();
+    let registry = InstallerRegistry::from(vec![registry]);
+    let package_set = PackageSetBuilder::named(named("fonts"))
+        .path(PathBuf::from("/repository/dev/fonts.yml"))
+        .requires(&named("homebrew"), "homebrew/cask-fonts")
+        .requires(&named("no-such-installer"), "ppa:fonts/ppa")
+        .package_actions(&[PackageBuilder::named(named("font-fira-code")).build()])
+        .build();
+    assert_eq!(
+        registry.installer_named("homebrew").unwrap().name(),
+        &named("homebrew")
+    );
+
+    let audited = |action: InstallActionKind| {
+        registry
+            .audit_package_set(&action, &package_set)
+            .iter()
+            .map(|command| format!("{}: {}", command.source(), command.command()))
+            .collect::<Vec<String>>()
+    };
+    // Installers not available on this platform are skipped.
+    assert_eq!(
+        audited(InstallActionKind::Install),
+        vec![
+            "requires homebrew/cask-fonts [homebrew]: brew tap 'homebrew/cask-fonts'",
+            "package font-fira-code [homebrew]: brew install font-fira-code",
+        ]
+    );
+    // Prerequisites are not removed on uninstall.
+    assert_eq!(
+        audited(InstallActionKind::Uninstall),
+        vec!["package font-fira-code [homebrew]: brew uninstall font-fira-code"]
+    );
+}
+
+#[test]
+fn test_audit_default_bundle_file_with_space() {
+    let registry = InstallerRegistry::from(
+        read_registry_installers(
+            &current_dir()
+                .unwrap()
+                .join("src/actions/default-installers.yml"),
+        )
+        .unwrap(),
+    );
+    let package_set = PackageSetBuilder::named(Name::from_str("homebrew-bundle").unwrap())
+        .path(PathBuf::from(
+            "/Users/me/Library/Application Support/mcfg/repository/macos/homebrew-bundle.yml",
+        ))
+        .for_platform(Platform::Macos)
+        .bundle_action("Brewfile", PackageKind::Default)
+        .build();
+
+    let audited: Vec<String> = registry
+        .audit_package_set(&InstallActionKind::Install, &package_set)
+        .iter()
+        .map(|command| command.command().to_string())
+        .collect();
+    assert_eq!(
+        audited,
+        vec!["brew bundle install --file '/Users/me/Library/Application Support/mcfg/repository/macos/Brewfile'"]
+    );
+    assert_eq!(
+        shell_words::split(&audited[0]).unwrap().last().unwrap(),
+        "/Users/me/Library/Application Support/mcfg/repository/macos/Brewfile"
+    );
+}
+
+#[test]
+fn test_install_package_set_prerequisites() {
+    let named = |name: &str| Name::from_str(name).unwrap();
+    let work_dir = tempfile::tempdir().unwrap();
+    let actions_path = work_dir.path().join("actions.txt");
+    let mut installer = InstallerBuilder::named(named("recording"));
+    let _ = installer
+        .for_platform(Platform::default())
+        .for_default_packages()
+        .add_install_command(&format!(
+            "echo install {{{{package_name}}}} >> {}",
+            actions_path.display()
+        ))
+        .add_prerequisite_command(&format!(
+            "echo requires '{{{{prerequisite}}}}' >> {}",
+            actions_path.display()
+        ));
+    let registry = InstallerRegistry::from(vec![installer.build()]);
+    let install = |prerequisite: &str| {
+        let repository = create_repository(
+            work_dir.path(),
+            &[(
+                "dev/fonts.yml",
+                &format!(
+                    "name: fonts\nrequires:\n  recording:\n    - \"{}\"\nactions:\n  packages:\n    - name: font-fira-code\n",
+                    prerequisite
+                ),
+            )],
+        );
+        let mut log_db = InstallLogFormat::default()
+            .open_from(work_dir.path().join("install-log"))
+            .unwrap();
+        registry
+            .execute_logged(
+                &InstallActionKind::Install,
+                &repository,
+                &PackageSetSelection::all(),
+                &ExecuteOptions::default(),
+                log_db.as_mut(),
+            )
+            .map(|_| log_db.applied_package_sets().unwrap())
+    };
+
+    // Each prerequisite added is logged, before the package set is.
+    let applied = install("ppa:fonts/ppa").unwrap();
+    assert_eq!(
+        applied
+            .iter()
+            .map(|applied| applied.action().to_string())
+            .collect::<Vec<String>>(),
+        vec![
+            InstallActionKind::Install.to_string(),
+            "requires recording ppa:fonts/ppa".to_string()
+        ]
+    );
+
+    // A prerequisite that would end the quoted string is not added.
+    assert!(matches!(
+        install("ppa:fonts/ppa'; echo 'oops"),
+        Err(Error::InvalidPrerequisite(_))
+    ));
+    assert_eq!(
+        read_to_string(&actions_path).unwrap(),
+        "requires ppa:fonts/ppa\ninstall font-fira-code\n"
+    );
+}
+
+#[test]
+fn test_retire_package_set() {
+    let named = |name: &str| Name::from_str(name).unwrap();
+    let work_dir = tempfile::tempdir().unwrap();
+    std::fs::create_dir_all(work_dir.path().join("dev")).unwrap();
+    let set_path = work_dir.path().join("dev/tools.yml");
+    std::fs::write(&set_path, "name: tools\n").unwrap();
+
+    let installer = InstallerBuilder::named(named("apt"))
+        .for_platform(Platform::default())
+        .for_default_packages()
+        .add_install_command("true {{package_name}}")
+        .add_uninstall_command("true {{package_name}}")
+        .build();
+    let registry = InstallerRegistry::from(vec![installer]);
+    let package_set = PackageSetBuilder::named(named("tools"))
+        .path(set_path)
+        .package_actions(&[
+            PackageBuilder::named(named("ripgrep")).build(),
+            PackageBuilder::named(named("fd")).build(),
+        ])
+        .build();
+    let mut group = PackageSetGroupBuilder::new_in(work_dir.path().join("dev"));
+    group.add_package_set(package_set.clone());
+    let group = group.build();
+
+    let mut log_db = InstallLogFormat::default()
+        .open_from(work_dir.path().join("install-log"))
+        .unwrap();
+    let machine = log_db.machine().to_string();
+    let installed_at = |package: &str, date_time: OffsetDateTime| {
+        LogEntry::Installed(
+            InstalledPackage::new(named("dev"), named("tools"), named(package), named("apt"))
+                .with_date_time(date_time)
+                .with_machine(&machine),
+        )
+    };
+    log_db
+        .import_entries(&[installed_at(
+            "ripgrep",
+            OffsetDateTime::now_utc() - Duration::hours(1),
+        )])
+        .unwrap();
+
+    let uninstalled = registry
+        .retire_package_set(&group, &package_set, log_db.as_mut())
+        .unwrap();
+    assert_eq!(uninstalled, vec![named("ripgrep")]);
+    assert_eq!(
+        log_db.applied_package_sets().unwrap()[0].action(),
+        InstallActionKind::Uninstall.to_string()
+    );
+
+    // Once retired, nothing is recorded as installed.
+    let uninstalled = registry
+        .retire_package_set(&group, &package_set, log_db.as_mut())
+        .unwrap();
+    assert!(uninstalled.is_empty());
+
+    // Unless installed again after it was retired.
+    log_db
+        .import_entries(&[installed_at(
+            "fd",
+            OffsetDateTime::now_utc() + Duration::hours(1),
+        )])
+        .unwrap();
+    let uninstalled = registry
+        .retire_package_set(&group, &package_set, log_db.as_mut())
+        .unwrap();
+    assert_eq!(uninstalled, vec![named("fd")]);
+}
+
+#[test]
+fn test_retire_script_package_set() {
+    let named = |name: &str| Name::from_str(name).unwrap();
+    let work_dir = tempfile::tempdir().unwrap();
+    let actions_path = work_dir.path().join("actions.txt");
+    let record = |action: &str| format!("\"echo {} >> {}\"", action, actions_path.display());
+    let repository = create_repository(
+        work_dir.path(),
+        &[(
+            "dev/tools.yml",
+            &format!(
+                "name: tools\nrun-before: {}\nactions:\n  scripts:\n    install: {}\n    uninstall: {}\nrun-after: {}\n",
+                record("before"),
+                record("install"),
+                record("uninstall"),
+                record("after")
+            ),
+        )],
+    );
+    let registry = InstallerRegistry::from(vec![]);
+    let mut log_db = InstallLogFormat::default()
+        .open_from(work_dir.path().join("install-log"))
+        .unwrap();
+    let _ = registry
+        .execute_logged(
+            &InstallActionKind::Install,
+            &repository,
+            &PackageSetSelection::all(),
+            &ExecuteOptions::default(),
+            log_db.as_mut(),
+        )
+        .unwrap();
+
+    let group = repository.group(&named("dev")).unwrap();
+    let package_set = group.package_set(&named("tools")).unwrap();
+    for _ in 0..2 {
+        assert!(registry
+            .retire_package_set(group, package_set, log_db.as_mut())
+            .unwrap()
+            .is_empty());
+    }
+    // The scripts are run in uninstall order, and only while the package set is applied.
+    assert_eq!(
+        read_to_string(&actions_path).unwrap(),
+        "before\ninstall\nafter\nafter\nuninstall\nbefore\n"
+    );
+}
+
+#[test]
+fn test_update_constrained_package() {
+    let named = |name: &str| Name::from_str(name).unwrap();
+    let work_dir = tempfile::tempdir().unwrap();
+    let repository = create_repository(
+        work_dir.path(),
+        &[(
+            "dev/tools.yml",
+            "name: tools\nactions:\n  packages:\n    - name: tool\n      version: \">=1, <2\"\n",
+        )],
+    );
+    let actions_path = work_dir.path().join("actions.txt");
+    let actions = || read_to_string(&actions_path).unwrap_or_default();
+
+    let update = |latest: &str, available_versions: Option<&str>| {
+        let mut installer = InstallerBuilder::named(named("versioned"));
+        let _ = installer
+            .for_platform(Platform::default())
+            .for_default_packages()
+            .add_update_command(&format!(
+                "echo update {{{{package_name}}}} >> {}",
+                actions_path.display()
+            ))
+            .latest_version_command(&format!("echo 'tool {}'", latest))
+            .install_version_command(&format!(
+                "echo install {{{{package_name}}}} {{{{package_version}}}} >> {}",
+                actions_path.display()
+            ));
+        if let Some(available_versions) = available_versions {
+            let _ = installer.available_versions_command(&format!("echo '{}'", available_versions));
+        }
+        let registry = InstallerRegistry::from(vec![installer.build()]);
+        let mut log_db = InstallLogFormat::default()
+            .open_from(work_dir.path().join("install-log"))
+            .unwrap();
+        let _ = registry
+            .execute_logged(
+                &InstallActionKind::Update,
+                &repository,
+                &PackageSetSelection::all(),
+                &ExecuteOptions::default(),
+                log_db.as_mut(),
+            )
+            .unwrap();
+    };
+
+    // The latest version satisfies the constraint, so the usual update command is run.
+    update("1.5.0", None);
+    assert_eq!(actions(), "update tool\n");
+
+    // The latest version does not satisfy the constraint, and the installer cannot list the
+    // available versions, so the package is held back.
+    update("2.1.0", None);
+    assert_eq!(actions(), "update tool\n");
+
+    // The highest available version that satisfies the constraint is installed instead.
+    update("2.1.0", Some("2.1.0 2.0.0 1.9.3 1.10.1 0.9.0"));
+    assert_eq!(actions(), "update tool\ninstall tool 1.10.1\n");
+}
+
+#[test]
+fn test_install_changed_after_uninstall() {
+    let work_dir = tempfile::tempdir().unwrap();
+    let repository = create_repository(
+        work_dir.path(),
+        &[(
+            "dev/tools.yml",
+            "name: tools\nactions:\n  packages:\n    - name: ripgrep\n",
+        )],
+    );
+    let actions_path = work_dir.path().join("actions.txt");
+    let registry = InstallerRegistry::from(vec![recording_installer(&actions_path)]);
+    let mut log_db = InstallLogFormat::default()
+        .open_from(work_dir.path().join("install-log"))
+        .unwrap();
+    let mut execute = |action: InstallActionKind, changed_only: bool| {
+        let summary = registry
+            .execute_logged(
+                &action,
+                &repository,
+                &PackageSetSelection::all(),
+                &ExecuteOptions::default().with_changed_only(changed_only),
+                log_db.as_mut(),
+            )
+            .unwrap();
+        summary.steps().skipped()
+    };
+
+    assert_eq!(execute(InstallActionKind::Install, true), 0);
+    assert_eq!(execute(InstallActionKind::Install, true), 1);
+    assert_eq!(execute(InstallActionKind::Uninstall, false), 0);
+    // The package set is unchanged, but has been uninstalled since it was last installed.
+    assert_eq!(execute(InstallActionKind::Install, true), 0);
+    assert_eq!(
+        read_to_string(&actions_path).unwrap(),
+        "install ripgrep\nuninstall ripgrep\ninstall ripgrep\n"
+    );
+}
+
+#[test]
+fn test_rollback_failed_package_set() {
+    let named = |name: &str| Name::from_str(name).unwrap();
+    let work_dir = tempfile::tempdir().unwrap();
+    let repository = create_repository(
+        work_dir.path(),
+        &[(
+            "dev/tools.yml",
+            "name: tools\nactions:\n  packages:\n    - name: ripgrep\n    - name: fd\n    - name: broken\n",
+        )],
+    );
+    let actions_path = work_dir.path().join("actions.txt");
+    let installer = InstallerBuilder::named(named("recording"))
+        .for_platform(Platform::default())
+        .for_default_packages()
+        .add_install_command(&format!(
+            "echo install {{{{package_name}}}} >> {} && test {{{{package_name}}}} != broken",
+            actions_path.display()
+        ))
+        .add_uninstall_command(&format!(
+            "echo uninstall {{{{package_name}}}} >> {}",
+            actions_path.display()
+        ))
+        .build();
+    let registry = InstallerRegistry::from(vec![installer]);
+    let mut log_db = InstallLogFormat::default()
+        .open_from(work_dir.path().join("install-log"))
+        .unwrap();
+
+    assert!(registry
+        .execute_logged(
+            &InstallActionKind::Install,
+            &repository,
+            &PackageSetSelection::all(),
+            &ExecuteOptions::default().with_transactional(true),
+            log_db.as_mut(),
+        )
+        .is_err());
+    assert_eq!(
+        read_to_string(&actions_path).unwrap(),
+        "install ripgrep\ninstall fd\ninstall broken\nuninstall fd\nuninstall ripgrep\n"
+    );
+
+    // Both the installs, and the uninstalls, of the rolled back packages are logged; as is the
+    // uninstall of the package set, so nothing remains to be retired.
+    let logged: Vec<String> = log_db
+        .installed_packages(&PackageLogQuery::default())
+        .unwrap()
+        .iter()
+        .map(|installed| installed.package_name().to_string())
+        .collect();
+    assert_eq!(logged.len(), 4);
+    assert!(!logged.contains(&"broken".to_string()));
+    let applied = log_db.applied_package_sets().unwrap();
+    assert_eq!(applied.len(), 1);
+    assert_eq!(
+        applied[0].action(),
+        InstallActionKind::Uninstall.to_string()
+    );
+    let group = repository.group(&named("dev")).unwrap();
+    let package_set = group.package_set(&named("tools")).unwrap();
+    assert!(registry
+        .retire_package_set(group, package_set, log_db.as_mut())
+        .unwrap()
+        .is_empty());
+}
+
+#[test]
+fn test_uninstall_hook_order() {
+    let work_dir = tempfile::tempdir().unwrap();
+    let actions_path = work_dir.path().join("actions.txt");
+    let repository = create_repository(
+        work_dir.path(),
+        &[(
+            "dev/tools.yml",
+            &format!(
+                "name: tools\nrun-before: \"echo before >> {0}\"\nactions:\n  packages:\n    - name: ripgrep\nrun-after: \"echo after >> {0}\"\n",
+                actions_path.display()
+            ),
+        )],
+    );
+    let registry = InstallerRegistry::from(vec![recording_installer(&actions_path)]);
+    let mut log_db = InstallLogFormat::default()
+        .open_from(work_dir.path().join("install-log"))
+        .unwrap();
+    for action in &[InstallActionKind::Install, InstallActionKind::Uninstall] {
+        let _ = registry
+            .execute_logged(
+                action,
+                &repository,
+                &PackageSetSelection::all(),
+                &ExecuteOptions::default(),
+                log_db.as_mut(),
+            )
+            .unwrap();
+    }
+
+    // Uninstall mirrors install, so the run-after script is run first and run-before last.
+    assert_eq!(
+        read_to_string(&actions_path).unwrap(),
+        "before\ninstall ripgrep\nafter\nafter\nuninstall ripgrep\nbefore\n"
+    );
+}
+
+#[test]
+fn test_update_relinks_files() {
+    let work_dir = tempfile::tempdir().unwrap();
+    let repository = create_repository(
+        work_dir.path(),
+        &[
+            (
+                "dev/tools/package-set.yml",
+                "name: tools\nenv-file: tools.env\nactions:\n  packages:\n    - name: ripgrep\n",
+            ),
+            ("dev/tools/tools.env", "export TOOLS=1\n"),
+        ],
+    );
+    let link_dir = work_dir.path().join("repository/dev/tools/tools");
+    std::fs::create_dir_all(&link_dir).unwrap();
+    let link = link_dir.join("tools.env");
+    let original = PathBuf::from("tools.env");
+    let registry = InstallerRegistry::from(vec![recording_installer(
+        &work_dir.path().join("actions.txt"),
+    )]);
+    let mut log_db = InstallLogFormat::default()
+        .open_from(work_dir.path().join("install-log"))
+        .unwrap();
+    let mut execute = |action: InstallActionKind, unlink_only: bool| {
+        let _ = registry
+            .execute_logged(
+                &action,
+                &repository,
+                &PackageSetSelection::all(),
+                &ExecuteOptions::default().with_unlink_only(unlink_only),
+                log_db.as_mut(),
+            )
+            .unwrap();
+    };
+
+    execute(InstallActionKind::Install, false);
+    assert_eq!(std::fs::read_link(&link).unwrap(), original);
+
+    // An update re-creates a link that was removed, or changed, since the install.
+    std::fs::remove_file(&link).unwrap();
+    execute(InstallActionKind::Update, false);
+    assert_eq!(std::fs::read_link(&link).unwrap(), original);
+    std::fs::remove_file(&link).unwrap();
+    std::os::unix::fs::symlink("elsewhere.env", &link).unwrap();
+    execute(InstallActionKind::Update, false);
+    assert_eq!(std::fs::read_link(&link).unwrap(), original);
+
+    // With unlink-only an update removes the link, and does not re-create it.
+    execute(InstallActionKind::Update, true);
+    assert!(link.symlink_metadata().is_err());
+    execute(InstallActionKind::Update, false);
+    assert_eq!(std::fs::read_link(&link).unwrap(), original);
+}
+
+// ------------------------------------------------------------------------------------------------
+
+// Create a package repository in `root`, containing the package set files listed as pairs of
+// path, relative to the repository, and content.
+fn create_repository(root: &Path, package_sets: &[(&str, &str)]) -> PackageRepository {
+    let repository_path = root.join("repository");
+    std::fs::create_dir_all(repository_path.join(".config")).unwrap();
+    std::fs::write(
+        repository_path.join(".config/repository.yml"),
+        "version: 1\n",
+    )
+    .unwrap();
+    for (path, content) in package_sets {
+        let path = repository_path.join(path);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, content).unwrap();
+    }
+    PackageRepository::open_from(repository_path).unwrap()
+}
+
+// An installer for default packages that records each action, and package, in `actions_path`.
+fn recording_installer(actions_path: &Path) -> Installer {
+    let record = |action: &str| {
+        format!(
+            "echo {} {{{{package_name}}}} >> {}",
+            action,
+            actions_path.display()
+        )
+    };
+    InstallerBuilder::named(Name::from_str("recording").unwrap())
+        .for_platform(Platform::default())
+        .for_default_packages()
+        .add_install_command(&record("install"))
+        .add_update_command(&record("update"))
+        .add_uninstall_command(&record("uninstall"))
+        .build()
+}
@@ -0,0 +1,59 @@
+use mcfg::shared::packages::{LazyPackageRepository, PackageRepository};
+use mcfg::shared::selection::{set_ignore_case, NamePattern, PackageSetSelection};
+use mcfg::shared::{FileSystemResource, Name};
+use std::env::current_dir;
+use std::str::FromStr;
+
+// Ignoring case is process-wide, so these tests are kept in their own test binary and run as a
+// single test so that they do not interfere with each other.
+#[test]
+fn test_name_lookup() {
+    let repository_path = current_dir().unwrap().join("tests/root/data/repository");
+    let repository = PackageRepository::open_from(repository_path.clone()).unwrap();
+    let lazy_repository = LazyPackageRepository::open_from(repository_path).unwrap();
+    let system = Name::from_str("system").unwrap();
+
+    // Unmatched names suggest the nearest names in the repository.
+    let selection = PackageSetSelection::new(
+        vec![NamePattern::from_str("sytem").unwrap()],
+        vec![NamePattern::from_str("system/fnts").unwrap()],
+    );
+    let error = selection
+        .check_matched(&repository)
+        .unwrap_err()
+        .to_string();
+    assert!(error.contains("package set group 'sytem'; did you mean 'system'?"));
+    assert!(error.contains("package set 'system/fnts'; did you mean 'system/fonts'?"));
+
+    let error = repository
+        .group_or_suggest(&Name::from_str("sys-tem").unwrap())
+        .unwrap_err()
+        .to_string();
+    assert_eq!(
+        error,
+        "No package set group 'sys-tem' found in the repository; did you mean 'system'?"
+    );
+    let error = lazy_repository
+        .package_set(&system, &Name::from_str("zhs").unwrap())
+        .unwrap_err()
+        .to_string();
+    assert_eq!(
+        error,
+        "No package set 'zhs' found in group 'system'; did you mean 'zsh'?"
+    );
+
+    // Patterns and names only match ignoring case once it is set.
+    let upper_case = Name::from_str("System").unwrap();
+    let pattern = NamePattern::from_str("Sys*").unwrap();
+    assert!(repository.group(&upper_case).is_none());
+    assert!(!pattern.matches(&system));
+
+    set_ignore_case(true);
+    assert!(repository.group(&upper_case).is_some());
+    assert!(pattern.matches(&system));
+    let gpg = lazy_repository
+        .package_set(&upper_case, &Name::from_str("GPG").unwrap())
+        .unwrap();
+    assert_eq!(gpg.name().to_string(), "gpg");
+    set_ignore_case(false);
+}
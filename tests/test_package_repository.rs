@@ -1,4 +1,5 @@
-use mcfg::shared::packages::PackageRepository;
+use mcfg::shared::packages::{LazyPackageRepository, PackageRepository};
+use mcfg::shared::selection::{NamePattern, PackageSetSelection};
 use mcfg::shared::{FileSystemResource, Name};
 use std::env::current_dir;
 use std::str::FromStr;
@@ -23,3 +24,168 @@ fn test_parse_repository() {
         .unwrap();
     assert_eq!(system_group.package_sets().count(), 5);
 }
+
+#[test]
+fn test_package_set_priority_order() {
+    let repository =
+        PackageRepository::open_from(current_dir().unwrap().join("tests/root/data/repository"))
+            .unwrap();
+    let system_group = repository
+        .group(&Name::from_str("system").unwrap())
+        .unwrap();
+    let names: Vec<String> = system_group
+        .package_sets()
+        .map(|package_set| package_set.name().to_string())
+        .collect();
+    assert_eq!(
+        names,
+        vec!["zsh", "fonts", "gnu-sed", "gpg", "macos-system-defaults"]
+    );
+}
+
+#[test]
+fn test_package_set_content_hash() {
+    let repository =
+        PackageRepository::open_from(current_dir().unwrap().join("tests/root/data/repository"))
+            .unwrap();
+    let system_group = repository
+        .group(&Name::from_str("system").unwrap())
+        .unwrap();
+    let gpg = system_group
+        .package_set(&Name::from_str("gpg").unwrap())
+        .unwrap();
+    let zsh = system_group
+        .package_set(&Name::from_str("zsh").unwrap())
+        .unwrap();
+    let gpg_hash = gpg.content_hash().unwrap();
+    assert_eq!(gpg_hash.len(), 64);
+    assert_eq!(gpg_hash, gpg.content_hash().unwrap());
+    assert_ne!(gpg_hash, zsh.content_hash().unwrap());
+}
+
+#[test]
+fn test_package_set_group_config() {
+    let repository =
+        PackageRepository::open_from(current_dir().unwrap().join("tests/root/data/repository"))
+            .unwrap();
+    let system_group = repository
+        .group(&Name::from_str("system").unwrap())
+        .unwrap();
+    assert_eq!(
+        system_group.description(),
+        &Some("Base system tools and configuration".to_string())
+    );
+
+    let gpg = system_group
+        .package_set(&Name::from_str("gpg").unwrap())
+        .unwrap();
+    assert!(gpg.has_tag(&Name::from_str("base").unwrap()));
+    assert_eq!(gpg.tags().count(), 1);
+
+    // The package set's own env-vars override those of the group.
+    let env_vars = gpg.inherited_env_vars();
+    assert_eq!(env_vars.get("gpg_home").unwrap(), "{{home}}/.gnupg");
+    assert_eq!(env_vars.get("system_fonts").unwrap(), "{{home}}/.fonts");
+    assert_eq!(gpg.env_vars().len(), 1);
+}
+
+#[test]
+fn test_lazy_repository_skips_unselected() {
+    let work_dir = tempfile::tempdir().unwrap();
+    let repository_path = work_dir.path().to_path_buf();
+    let tools = repository_path.join("tools");
+    std::fs::create_dir_all(tools.join("shell")).unwrap();
+    std::fs::create_dir_all(repository_path.join("apps")).unwrap();
+    std::fs::write(tools.join("rust.yml"), "name: rust\n").unwrap();
+    std::fs::write(tools.join("shell/package-set.yml"), "name: zsh\n").unwrap();
+    std::fs::write(tools.join("broken.yml"), "name: [broken\n").unwrap();
+    std::fs::write(repository_path.join("apps/office.yml"), "name: [broken\n").unwrap();
+
+    let repository = LazyPackageRepository::open_from(repository_path.clone()).unwrap();
+    assert_eq!(repository.groups().count(), 2);
+    let tools_name = Name::from_str("tools").unwrap();
+    assert_eq!(
+        repository
+            .group(&tools_name)
+            .unwrap()
+            .package_set_paths()
+            .count(),
+        3
+    );
+
+    // Targeted loading finds a package set whose file is named differently.
+    let zsh = repository
+        .package_set(&tools_name, &Name::from_str("zsh").unwrap())
+        .unwrap();
+    assert_eq!(zsh.name().to_string(), "zsh");
+    assert!(repository
+        .package_set(&tools_name, &Name::from_str("go").unwrap())
+        .is_err());
+
+    // Loading everything fails on the broken files, loading a selection does not.
+    assert!(repository.clone().load().is_err());
+    let selection =
+        PackageSetSelection::new(vec![], vec![NamePattern::from_str("tools/rust").unwrap()]);
+    let selected = repository.clone().load_selected(&selection).unwrap();
+    assert_eq!(selected.groups().count(), 1);
+    assert_eq!(
+        selected.group(&tools_name).unwrap().package_sets().count(),
+        2
+    );
+
+    let selection =
+        PackageSetSelection::new(vec![], vec![NamePattern::from_str("tools/broken").unwrap()]);
+    assert!(repository.load_selected(&selection).is_err());
+}
+
+#[test]
+fn test_parallel_loading_is_ordered() {
+    let work_dir = tempfile::tempdir().unwrap();
+    let repository_path = work_dir.path().to_path_buf();
+    for group in 0..12 {
+        let group_path = repository_path.join(format!("group-{:02}", group));
+        std::fs::create_dir_all(&group_path).unwrap();
+        for set in 0..10 {
+            // Priority runs in the opposite order to the file names.
+            std::fs::write(
+                group_path.join(format!("set-{:02}.yml", set)),
+                format!("name: set-{:02}\npriority: {}\n", set, 10 - set),
+            )
+            .unwrap();
+        }
+    }
+
+    let repository = PackageRepository::open_from(repository_path.clone()).unwrap();
+    let group_names: Vec<String> = repository.groups().map(|g| g.name().to_string()).collect();
+    let expected: Vec<String> = (0..12).map(|group| format!("group-{:02}", group)).collect();
+    assert_eq!(group_names, expected);
+    for group in repository.groups() {
+        let set_names: Vec<String> = group.package_sets().map(|s| s.name().to_string()).collect();
+        let expected: Vec<String> = (0..10).rev().map(|set| format!("set-{:02}", set)).collect();
+        assert_eq!(set_names, expected);
+    }
+}
+
+#[test]
+fn test_readme_paths() {
+    let repository_path = current_dir().unwrap().join("tests/root/data/repository");
+    let repository = PackageRepository::open_from(repository_path.clone()).unwrap();
+    let system_group = repository
+        .group(&Name::from_str("system").unwrap())
+        .unwrap();
+    assert_eq!(
+        system_group.readme_path(),
+        Some(repository_path.join("system/README.md"))
+    );
+    let gpg = system_group
+        .package_set(&Name::from_str("gpg").unwrap())
+        .unwrap();
+    assert_eq!(
+        gpg.readme_path(),
+        Some(repository_path.join("system/gpg.md"))
+    );
+    let zsh = system_group
+        .package_set(&Name::from_str("zsh").unwrap())
+        .unwrap();
+    assert_eq!(zsh.readme_path(), None);
+}
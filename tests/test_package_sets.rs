@@ -1,6 +1,6 @@
 use mcfg::shared::builders::Builder;
 use mcfg::shared::packages::builders::{PackageBuilder, PackageSetBuilder};
-use mcfg::shared::{Name, PackageSet};
+use mcfg::shared::{Name, Package, PackageKind, PackageSet};
 use pretty_assertions::assert_eq;
 use std::collections::HashMap;
 use std::path::PathBuf;
@@ -11,6 +11,7 @@ fn test_minimal_package_set() {
     let package_set = PackageSetBuilder::named(Name::from_str("example").unwrap()).build();
     assert_eq!(package_set.name(), &String::from("example"));
     assert_eq!(package_set.path(), &PathBuf::default());
+    assert_eq!(package_set.display_name(), &None);
     assert_eq!(package_set.description(), &None);
     assert_eq!(package_set.is_optional(), false);
     assert_eq!(package_set.run_before(), &None);
@@ -18,6 +19,8 @@ fn test_minimal_package_set() {
     assert_eq!(package_set.env_file(), &None);
     assert_eq!(package_set.link_files(), &HashMap::default());
     assert_eq!(package_set.run_after(), &None);
+    assert_eq!(package_set.rollback(), &None);
+    assert!(!package_set.has_relative_links());
 
     let package_set_str = serde_yaml::to_string(&package_set).unwrap();
     println!("{}", package_set_str);
@@ -190,3 +193,362 @@ fn test_parse_package_set_with_scripts() {
     assert_eq!(package_set.scripts().unwrap().len(), 2);
     assert_eq!(package_set.link_files().len(), 1)
 }
+
+#[test]
+fn test_parse_package_set_with_rollback() {
+    let config_str = r##"
+        name: lux
+        actions:
+          packages:
+            - name: lux
+        run-after: set-lux
+        rollback: rm -f "{{local-bin}}/set-lux"
+        "##;
+
+    let package_set: PackageSet = serde_yaml::from_str(config_str).unwrap();
+    assert_eq!(
+        package_set.rollback(),
+        &Some("rm -f \"{{local-bin}}/set-lux\"".to_string())
+    );
+
+    let package_set_str = serde_yaml::to_string(&package_set).unwrap();
+    assert!(package_set_str.contains("rollback:"));
+    let new_package_set: PackageSet = serde_yaml::from_str(&package_set_str).unwrap();
+    assert_eq!(package_set, new_package_set);
+}
+
+#[test]
+fn test_parse_package_set_with_display_name() {
+    let config_str = r##"
+        name: lux
+        display-name: Lux, the light toolkit
+        description: Tools for working with light
+        "##;
+
+    let package_set: PackageSet = serde_yaml::from_str(config_str).unwrap();
+    assert_eq!(package_set.name(), &String::from("lux"));
+    assert_eq!(
+        package_set.display_name(),
+        &Some("Lux, the light toolkit".to_string())
+    );
+
+    let package_set_str = serde_yaml::to_string(&package_set).unwrap();
+    assert!(package_set_str.contains("display-name:"));
+    let new_package_set: PackageSet = serde_yaml::from_str(&package_set_str).unwrap();
+    assert_eq!(package_set, new_package_set);
+
+    let built = PackageSetBuilder::named(Name::from_str("lux").unwrap())
+        .display_name("Lux, the light toolkit")
+        .description("Tools for working with light")
+        .build();
+    assert_eq!(built, package_set);
+}
+
+#[test]
+fn test_parse_package_set_with_relative_links() {
+    let config_str = r##"
+        name: lux
+        link-files:
+          lux.conf: "{{home}}/.lux.conf"
+        relative-links: true
+        "##;
+
+    let package_set: PackageSet = serde_yaml::from_str(config_str).unwrap();
+    assert!(package_set.has_relative_links());
+
+    let package_set_str = serde_yaml::to_string(&package_set).unwrap();
+    assert!(package_set_str.contains("relative-links: true"));
+    let new_package_set: PackageSet = serde_yaml::from_str(&package_set_str).unwrap();
+    assert_eq!(package_set, new_package_set);
+}
+
+#[test]
+fn test_parse_package_set_with_prompt_vars() {
+    let config_str = r##"
+        name: git
+        prompt-vars:
+          git_email: "Your git email"
+        run-after: "git config --global user.email {{git_email}}"
+        "##;
+
+    let package_set: PackageSet = serde_yaml::from_str(config_str).unwrap();
+    assert_eq!(package_set.prompt_vars().len(), 1);
+    assert_eq!(
+        package_set.prompt_vars().get("git_email").unwrap(),
+        "Your git email"
+    );
+
+    let package_set_str = serde_yaml::to_string(&package_set).unwrap();
+    assert!(package_set_str.contains("prompt-vars:"));
+    let new_package_set: PackageSet = serde_yaml::from_str(&package_set_str).unwrap();
+    assert_eq!(package_set, new_package_set);
+}
+
+#[test]
+fn test_parse_package_set_with_bundle() {
+    let config_str = r##"
+        name: brew
+        actions:
+          bundle:
+            file: Brewfile
+        "##;
+
+    let package_set: PackageSet = serde_yaml::from_str(config_str).unwrap();
+    println!("{:?}", package_set);
+    assert_eq!(package_set.name(), "brew");
+    assert!(package_set.has_actions());
+    assert!(package_set.packages().is_none());
+    assert!(package_set.scripts().is_none());
+    let bundle = package_set.bundle().unwrap();
+    assert_eq!(bundle.file(), "Brewfile");
+    assert_eq!(bundle.kind(), &PackageKind::Default);
+    assert_eq!(bundle.name().unwrap(), Name::from_str("Brewfile").unwrap());
+}
+
+#[test]
+fn test_package_set_with_bundle() {
+    let package_set = PackageSetBuilder::named(Name::from_str("brew").unwrap())
+        .bundle_action("Brewfile", PackageKind::Application(None))
+        .build();
+    assert_eq!(package_set.bundle().unwrap().file(), "Brewfile");
+    assert_eq!(
+        package_set.bundle().unwrap().kind(),
+        &PackageKind::Application(None)
+    );
+
+    let package_set_str = serde_yaml::to_string(&package_set).unwrap();
+    println!("{}", package_set_str);
+
+    let new_package_set = serde_yaml::from_str(&package_set_str).unwrap();
+    assert_eq!(package_set, new_package_set);
+}
+
+#[test]
+fn test_parse_package_set_with_package_id() {
+    let config_str = r##"
+        name: app-store
+        actions:
+          packages:
+            - name: xcode
+              id: "497799835"
+              kind:
+                language: mas
+        "##;
+
+    let package_set: PackageSet = serde_yaml::from_str(config_str).unwrap();
+    let package = package_set.packages().unwrap().next().unwrap();
+    assert_eq!(package.name(), "xcode");
+    assert_eq!(package.id(), &Some("497799835".to_string()));
+}
+
+#[test]
+fn test_parse_package_set_with_download() {
+    let config_str = r##"
+        name: ripgrep
+        actions:
+          packages:
+            - name: rg
+              download:
+                url: "https://github.com/BurntSushi/ripgrep/releases/download/{{download_version}}/ripgrep-{{download_version}}-x86_64-unknown-linux-musl.tar.gz"
+                sha256: "ee4e0751ab108b6da4f47c52da187d5177dc371f0f512a7caaec5434e711c091"
+                version: "12.1.1"
+                binary: "ripgrep-{{download_version}}-x86_64-unknown-linux-musl/rg"
+        "##;
+
+    let package_set: PackageSet = serde_yaml::from_str(config_str).unwrap();
+    let package = package_set.packages().unwrap().next().unwrap();
+    let download = package.download().as_ref().unwrap();
+    assert_eq!(download.version(), &Some("12.1.1".to_string()));
+    assert!(download.is_extract());
+    assert_eq!(
+        download.sha256(),
+        "ee4e0751ab108b6da4f47c52da187d5177dc371f0f512a7caaec5434e711c091"
+    );
+}
+
+#[cfg(feature = "github-releases")]
+#[test]
+fn test_parse_package_set_with_github_release() {
+    let config_str = r##"
+        name: ripgrep
+        actions:
+          packages:
+            - name: rg
+              github:
+                repository: BurntSushi/ripgrep
+                tag: "12.1.1"
+        "##;
+
+    let package_set: PackageSet = serde_yaml::from_str(config_str).unwrap();
+    let package = package_set.packages().unwrap().next().unwrap();
+    let github = package.github().as_ref().unwrap();
+    assert_eq!(github.repository(), "BurntSushi/ripgrep");
+    assert_eq!(github.tag(), &Some("12.1.1".to_string()));
+    assert!(github.asset().is_none());
+}
+
+#[test]
+fn test_parse_package_set_with_version_constraint() {
+    let config_str = r##"
+        name: node
+        actions:
+          packages:
+            - name: node
+              version: ">=14, <15"
+        "##;
+
+    let package_set: PackageSet = serde_yaml::from_str(config_str).unwrap();
+    let package = package_set.packages().unwrap().next().unwrap();
+    assert_eq!(package.version().as_ref().unwrap().to_string(), ">=14, <15");
+
+    let bad_config_str = r##"
+        name: node
+        actions:
+          packages:
+            - name: node
+              version: "not a version"
+        "##;
+    assert!(serde_yaml::from_str::<PackageSet>(bad_config_str).is_err());
+}
+
+#[test]
+fn test_package_set_remove_package() {
+    let mut builder = PackageSetBuilder::named(Name::from_str("example").unwrap());
+    let _ = builder
+        .with_package_actions()
+        .add_package_action(
+            PackageBuilder::named(Name::from_str("ripgrep").unwrap())
+                .of_kind(PackageKind::from_str("cargo").unwrap())
+                .build(),
+        )
+        .unwrap()
+        .add_package_action(PackageBuilder::named(Name::from_str("zsh").unwrap()).build())
+        .unwrap();
+
+    assert!(builder
+        .remove_package_action(&Name::from_str("missing").unwrap())
+        .is_err());
+    let package_set = builder
+        .remove_package_action(&Name::from_str("zsh").unwrap())
+        .unwrap()
+        .build();
+    let packages: Vec<&Package> = package_set.packages().unwrap().collect();
+    assert_eq!(packages.len(), 1);
+    assert_eq!(packages[0].name(), "ripgrep");
+    assert_eq!(
+        packages[0].kind(),
+        &PackageKind::Language(Name::from_str("cargo").unwrap())
+    );
+}
+
+#[test]
+fn test_package_kinds() {
+    let config_str = r##"
+        - name: gimp
+          kind: application
+        - name: org.gimp.GIMP
+          kind:
+            application: flatpak
+        - name: ripgrep
+          kind:
+            language: rust
+        - name: zsh
+        "##;
+    let packages: Vec<Package> = serde_yaml::from_str(config_str).unwrap();
+    let flatpak = Name::from_str("flatpak").unwrap();
+    assert_eq!(
+        packages
+            .iter()
+            .map(|package| package.kind().clone())
+            .collect::<Vec<PackageKind>>(),
+        vec![
+            PackageKind::Application(None),
+            PackageKind::Application(Some(flatpak.clone())),
+            PackageKind::Language(Name::from_str("rust").unwrap()),
+            PackageKind::Default,
+        ]
+    );
+    assert_eq!(
+        serde_yaml::from_str::<Vec<Package>>(&serde_yaml::to_string(&packages).unwrap()).unwrap(),
+        packages
+    );
+    assert_eq!(
+        PackageKind::from_str("application:flatpak").unwrap(),
+        PackageKind::Application(Some(flatpak))
+    );
+
+    assert!(serde_yaml::from_str::<PackageKind>("language").is_err());
+    assert!(serde_yaml::from_str::<PackageKind>("application: bad name").is_err());
+    assert!(serde_yaml::from_str::<PackageKind>("{application: snap, language: rust}").is_err());
+}
+
+#[test]
+fn test_parse_package_set_with_requires() {
+    let config_str = r##"
+        name: fonts
+        requires:
+          homebrew:
+            - homebrew/cask-fonts
+          apt:
+            - ppa:fonts/ppa
+        "##;
+
+    let package_set: PackageSet = serde_yaml::from_str(config_str).unwrap();
+    assert_eq!(package_set.requires().len(), 2);
+    assert_eq!(
+        package_set
+            .requires()
+            .get(&Name::from_str("homebrew").unwrap())
+            .unwrap(),
+        &vec!["homebrew/cask-fonts".to_string()]
+    );
+}
+
+#[test]
+fn test_parse_package_set_with_shell_files() {
+    let config_str = r##"
+        name: lux
+        env-file: sample.env
+        link-files:
+          set-lux: "{{local-bin}}/set-lux"
+        shell-files:
+          sh:
+            env-file: sample.sh
+            link-files:
+              lux.sh: "{{home}}/.lux.sh"
+          no-such-shell:
+            env-file: sample.nss
+            link-files:
+              lux.nss: "{{home}}/.lux.nss"
+        "##;
+
+    let package_set: PackageSet = serde_yaml::from_str(config_str).unwrap();
+    assert_eq!(package_set.shell_files().len(), 2);
+    assert_eq!(
+        package_set.shell_files().get("sh").unwrap().env_file(),
+        &Some("sample.sh".to_string())
+    );
+
+    // Only the files for shells present on this machine are used.
+    assert_eq!(
+        package_set.env_file_paths(),
+        vec![PathBuf::from("sample.env"), PathBuf::from("sample.sh")]
+    );
+    let mut links: Vec<PathBuf> = package_set
+        .link_file_paths()
+        .into_iter()
+        .map(|(_, target)| target)
+        .collect();
+    links.sort();
+    assert_eq!(
+        links,
+        vec![
+            PathBuf::from("{{home}}/.lux.sh"),
+            PathBuf::from("{{local-bin}}/set-lux")
+        ]
+    );
+
+    let package_set_str = serde_yaml::to_string(&package_set).unwrap();
+    let new_package_set: PackageSet = serde_yaml::from_str(&package_set_str).unwrap();
+    assert_eq!(package_set, new_package_set);
+}
@@ -0,0 +1,165 @@
+use mcfg::error::Error;
+use mcfg::shared::download::sha256_file;
+use mcfg::shared::{find_executable, open_archive_vcs, ArchiveConfig, RepoVcs, RepoVcsKind, Scope};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+fn init_and_commit(vcs: &dyn RepoVcs, dir: &Path) {
+    assert!(!vcs.is_repository(dir));
+    vcs.init(dir).unwrap();
+    assert!(vcs.is_repository(dir));
+    assert!(!vcs.has_commits(dir).unwrap());
+
+    fs::write(dir.join("example.yml"), "name: example\n").unwrap();
+    vcs.commit_all(dir, "Initial commit").unwrap();
+    assert!(vcs.has_commits(dir).unwrap());
+
+    assert!(!vcs.has_origin(dir).unwrap());
+    vcs.set_origin(dir, "https://example.com/repository.git")
+        .unwrap();
+    assert!(vcs.has_origin(dir).unwrap());
+}
+
+#[test]
+fn test_directory_vcs() {
+    let work_dir = tempfile::tempdir().unwrap();
+    let dir = work_dir.path().to_path_buf();
+    let vcs = RepoVcsKind::Directory.open(Scope::User).unwrap();
+    assert!(vcs.is_repository(&dir));
+    vcs.init(&dir).unwrap();
+    assert!(!dir.join(".git").exists());
+    assert!(!vcs.refresh(&dir).unwrap());
+    assert!(!vcs.has_commits(&dir).unwrap());
+    assert!(vcs
+        .clone_from("https://example.com/repository.git", &dir)
+        .is_err());
+    assert!(vcs.commit_all(&dir, "Initial commit").is_err());
+}
+
+#[test]
+fn test_git_command_vcs() {
+    if find_executable("git").is_none() {
+        return;
+    }
+    let work_dir = tempfile::tempdir().unwrap();
+    let dir = work_dir.path().to_path_buf();
+    init_and_commit(
+        RepoVcsKind::GitCommand.open(Scope::User).unwrap().as_ref(),
+        &dir,
+    );
+
+    // A repository created by one Git implementation is read by the other.
+    let library = RepoVcsKind::Git.open(Scope::User).unwrap();
+    assert!(library.has_commits(&dir).unwrap());
+    assert!(library.has_origin(&dir).unwrap());
+}
+
+#[test]
+fn test_git_library_vcs() {
+    let work_dir = tempfile::tempdir().unwrap();
+    let dir = work_dir.path().to_path_buf();
+    init_and_commit(RepoVcsKind::Git.open(Scope::User).unwrap().as_ref(), &dir);
+}
+
+// Create a tarball holding a single top-level directory, as most project archives do.
+fn make_archive(dir: &Path, content: &str) -> PathBuf {
+    let source_dir = dir.join("source/repository-1.0");
+    fs::create_dir_all(source_dir.join("system")).unwrap();
+    fs::write(source_dir.join("system/example.yml"), content).unwrap();
+    let archive_path = dir.join("repository.tar.gz");
+    let status = Command::new("tar")
+        .args(["-czf", &archive_path.to_string_lossy(), "-C"])
+        .arg(dir.join("source"))
+        .arg("repository-1.0")
+        .status()
+        .unwrap();
+    assert!(status.success());
+    archive_path
+}
+
+#[test]
+fn test_archive_vcs() {
+    if find_executable("curl").is_none() || find_executable("tar").is_none() {
+        return;
+    }
+    let work_dir = tempfile::tempdir().unwrap();
+    let dir = work_dir.path().to_path_buf();
+    let repository_dir = dir.join("repository");
+    let archive_path = make_archive(&dir, "name: example\n");
+    let url = format!("file://{}", archive_path.to_string_lossy());
+    let sha256 = sha256_file(&archive_path).unwrap();
+
+    let vcs = open_archive_vcs(ArchiveConfig::with_sha256(&sha256));
+    assert!(!vcs.is_repository(&repository_dir));
+    vcs.clone_from(&url, &repository_dir).unwrap();
+    assert!(vcs.is_repository(&repository_dir));
+    assert_eq!(
+        fs::read_to_string(repository_dir.join("system/example.yml")).unwrap(),
+        "name: example\n"
+    );
+    assert!(!vcs.refresh(&repository_dir).unwrap());
+    assert!(vcs.commit_all(&repository_dir, "Initial commit").is_err());
+
+    // A new archive, with a new checksum read from a URL, replaces the repository contents.
+    fs::write(repository_dir.join("local.yml"), "name: local\n").unwrap();
+    let archive_path = make_archive(&dir, "name: changed\n");
+    let sha256_path = dir.join("repository.tar.gz.sha256");
+    fs::write(
+        &sha256_path,
+        format!(
+            "{}  repository.tar.gz\n",
+            sha256_file(&archive_path).unwrap()
+        ),
+    )
+    .unwrap();
+    let vcs = open_archive_vcs(ArchiveConfig::with_sha256_url(&format!(
+        "file://{}",
+        sha256_path.to_string_lossy()
+    )));
+    assert!(vcs.refresh(&repository_dir).unwrap());
+    assert_eq!(
+        fs::read_to_string(repository_dir.join("system/example.yml")).unwrap(),
+        "name: changed\n"
+    );
+    assert!(!repository_dir.join("local.yml").exists());
+    assert!(!vcs.refresh(&repository_dir).unwrap());
+}
+
+#[test]
+fn test_archive_vcs_checksum_mismatch() {
+    if find_executable("curl").is_none() || find_executable("tar").is_none() {
+        return;
+    }
+    let work_dir = tempfile::tempdir().unwrap();
+    let dir = work_dir.path().to_path_buf();
+    let repository_dir = dir.join("repository");
+    let archive_path = make_archive(&dir, "name: example\n");
+    let url = format!("file://{}", archive_path.to_string_lossy());
+
+    let vcs = open_archive_vcs(ArchiveConfig::with_sha256(&"0".repeat(64)));
+    assert!(vcs.clone_from(&url, &repository_dir).is_err());
+    assert!(!vcs.is_repository(&repository_dir));
+    assert!(!repository_dir.join("system").exists());
+
+    let vcs = open_archive_vcs(ArchiveConfig::default());
+    assert!(vcs.clone_from(&url, &repository_dir).is_err());
+}
+
+#[test]
+fn test_object_store_vcs() {
+    let work_dir = tempfile::tempdir().unwrap();
+    let dir = work_dir.path().to_path_buf();
+    match RepoVcsKind::ObjectStore.open(Scope::User) {
+        Ok(vcs) => {
+            assert!(!vcs.is_repository(&dir));
+            assert!(!vcs.refresh(&dir).unwrap());
+            assert!(vcs
+                .clone_from("https://example.com/repository", &dir)
+                .is_err());
+            assert!(vcs.commit_all(&dir, "Initial commit").is_err());
+        }
+        Err(Error::UnsupportedRepoVcs(vcs)) => assert_eq!(vcs, "object-store"),
+        Err(e) => panic!("unexpected error {:?}", e),
+    }
+}
@@ -0,0 +1,84 @@
+use mcfg::actions::ActionContext;
+use mcfg::shared::{
+    check_scope_privileges, default_vars, dirs, is_root, vars_scope, Config, FileSystemResource,
+    InstallLogFormat, InstallerRegistry, PackageRepository, Scope,
+};
+use pretty_assertions::assert_eq;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+#[test]
+fn test_system_scope_paths() {
+    assert_eq!(
+        Config::default_path(Scope::System),
+        PathBuf::from("/etc/mcfg/config.yml")
+    );
+    assert_eq!(
+        InstallerRegistry::default_path(Scope::System),
+        PathBuf::from("/etc/mcfg/installers.yml")
+    );
+    assert_eq!(
+        PackageRepository::default_path(Scope::System),
+        PathBuf::from("/etc/mcfg/repository")
+    );
+    assert_eq!(
+        InstallLogFormat::Sqlite.default_path(Scope::System),
+        PathBuf::from("/var/lib/mcfg/log/install-log.sql")
+    );
+    assert_eq!(
+        dirs::data_local_dir_for(Scope::System, "ripgrep"),
+        Some(PathBuf::from("/var/lib/ripgrep"))
+    );
+    assert_ne!(
+        Config::default_path(Scope::User),
+        Config::default_path(Scope::System)
+    );
+}
+
+#[test]
+fn test_scope_vars() {
+    assert_eq!(Scope::default(), Scope::User);
+    assert_eq!(Scope::from_str("system").unwrap(), Scope::System);
+    assert!(Scope::from_str("machine").is_err());
+
+    let system_vars = default_vars(Scope::System);
+    assert_eq!(system_vars.get("install_scope").unwrap(), "system");
+    assert_eq!(
+        system_vars.get("repo_config_path").unwrap(),
+        "/etc/mcfg/repository/.config"
+    );
+    assert_eq!(vars_scope(&system_vars), Scope::System);
+
+    let user_vars = default_vars(Scope::User);
+    assert_eq!(user_vars.get("install_scope").unwrap(), "user");
+    assert_eq!(vars_scope(&user_vars), Scope::User);
+    assert_eq!(vars_scope(&Default::default()), Scope::User);
+}
+
+#[test]
+fn test_check_scope_privileges() {
+    assert_eq!(check_scope_privileges(Scope::System).is_ok(), is_root());
+    assert!(check_scope_privileges(Scope::User).is_ok());
+}
+
+#[test]
+fn test_action_context_scope() {
+    let context = ActionContext::for_scope(Scope::System);
+    assert_eq!(context.scope(), Scope::System);
+    assert_eq!(
+        context.config_path(),
+        &PathBuf::from("/etc/mcfg/config.yml")
+    );
+    assert_eq!(
+        context.repository_path(),
+        &PathBuf::from("/etc/mcfg/repository")
+    );
+    assert_eq!(
+        context.environment().get("install_scope").unwrap(),
+        "system"
+    );
+
+    let context = ActionContext::new();
+    assert_eq!(context.scope(), Scope::User);
+    assert_eq!(context.config_path(), &Config::default_path(Scope::User));
+}